@@ -23,7 +23,7 @@ async fn main() {
     log::info!(">>> TCP SERVER FOR CHANNEL EXAMPLE  <<<");
 
     let id = PrivateIdentity::new_from_name("link-example");
-    let mut transport = Transport::new(TransportConfig::new("server", &id, true));
+    let transport = Transport::new(TransportConfig::new("server", &id, true));
     log::trace!("transport instantiated");
 
     let dest = transport.add_destination(
@@ -66,27 +66,34 @@ async fn main() {
             }
         }
 
-        match out_link_events.try_recv() {
-            Ok(link_event) => {
-                match link_event.event {
+        // Several clients produce more events per second than the channels
+        // buffer, so handle all of them on every round
+        loop {
+            match out_link_events.try_recv() {
+                Ok(link_event) => match link_event.event {
                     LinkEvent::Activated => log::info!("link {} activated", link_event.id),
                     LinkEvent::Closed => log::info!("link {} closed", link_event.id),
                     LinkEvent::Data(payload) => log::error!("link {} data payload: {}", link_event.id,
                         std::str::from_utf8(payload.as_slice())
                             .map(str::to_string)
                             .unwrap_or_else(|_| format!("{:?}", payload.as_slice()))),
-                    LinkEvent::Proof(_) => {},
-                };
-                out_link_events.resubscribe();
-            },
-            Err(error) => {
-                if error != TryRecvError::Empty {
-                    log::info!("out_link_events channel error: {}", error);
-                }
+                    _ => {},
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(error) => log::info!("out_link_events channel error: {}", error),
             }
         }
 
-        if let Ok(link_event) = in_link_events.try_recv() {
+        loop {
+            let link_event = match in_link_events.try_recv() {
+                Ok(link_event) => link_event,
+                Err(TryRecvError::Empty) => break,
+                Err(error) => {
+                    log::info!("in_link_events channel error: {}", error);
+                    continue;
+                }
+            };
+
             let id = link_event.id;
             if let LinkEvent::Activated = link_event.event {
                 let maybe_link = transport.lock().await.find_in_link(&id).await;
@@ -107,7 +114,7 @@ async fn main() {
             }
         }
 
-        transport.lock().await.send_announce(&dest, None).await;
+        transport.lock().await.send_announce(&dest, None).await.unwrap();
 
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
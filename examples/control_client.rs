@@ -0,0 +1,46 @@
+//! Sends a message to a destination through a running rs-rnsd instance.
+//!
+//! Usage: control_client <destination hash> <message>
+
+use std::time::Duration;
+
+use reticulum::control::Client;
+use reticulum::destination::link::LinkStatus;
+use reticulum::hash::AddressHash;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let mut args = std::env::args().skip(1);
+    let (Some(destination), Some(message)) = (args.next(), args.next()) else {
+        eprintln!("usage: control_client <destination hash> <message>");
+        return Ok(());
+    };
+
+    let destination: AddressHash = destination.parse()?;
+
+    let mut client = Client::connect("127.0.0.1:37429").await?;
+
+    if !client.has_path(&destination).await? {
+        log::info!("requesting path to {}", destination);
+        client.request_path(&destination).await?;
+
+        while !client.has_path(&destination).await? {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    let link_id = client.link(&destination).await?;
+    log::info!("link {} to {}", link_id, destination);
+
+    while client.link_status(&destination).await? != Some(LinkStatus::Active) {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    for hash in client.send(&destination, message.as_bytes()).await? {
+        log::info!("sent packet {}", hash);
+    }
+
+    Ok(())
+}
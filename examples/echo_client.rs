@@ -0,0 +1,68 @@
+//! Sends requests to the `/echo` path of the first `echo_server` it hears
+//! of and prints the responses.
+
+use std::time::Duration;
+
+use reticulum::destination::link::LinkStatus;
+use reticulum::destination::DestinationName;
+use reticulum::iface::tcp_client::TcpClient;
+use reticulum::msgpack::{Reader, Writer};
+use reticulum::transport::{Transport, TransportConfig};
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let transport = Transport::new(TransportConfig::default());
+
+    transport
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(TcpClient::new("127.0.0.1:4242"), TcpClient::spawn);
+
+    let echo = DestinationName::new("example_utilities", "echo");
+    let mut announces = transport.recv_announces().await;
+    let destination = loop {
+        let announce = announces.recv().await.unwrap();
+        let desc = announce.destination.lock().await.desc;
+        if desc.name.as_name_hash_slice() == echo.as_name_hash_slice() {
+            break desc;
+        }
+    };
+
+    log::info!("linking to echo server {}", destination.address_hash);
+
+    let link = transport.link(destination).await;
+    while link.lock().await.status() != LinkStatus::Active {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let mut responses = transport.link_responses();
+
+    for number in 0.. {
+        let text = format!("echo {}", number);
+        let data = Writer::new().str(&text).finish();
+
+        let (packet, request_id) = link.lock().await.request_packet("/echo", &data).unwrap();
+        transport.send_packet(packet).await.unwrap();
+
+        let response = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Ok(received) = responses.recv().await {
+                    if received.response.request_id == request_id {
+                        return received.response.data;
+                    }
+                }
+            }
+        })
+        .await;
+
+        match response {
+            Ok(data) => log::info!("response: {:?}", Reader::new(&data).str()),
+            Err(_) => log::warn!("no response to request {}", request_id),
+        }
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
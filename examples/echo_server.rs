@@ -0,0 +1,47 @@
+//! Answers requests to `/echo` with their data. Run a TCP server on
+//! 127.0.0.1:4242 first, e.g. the `tcp_server` example, and `echo_client`
+//! to query it.
+
+use reticulum::destination::request::{IncomingRequest, RequestPolicy};
+use reticulum::destination::DestinationName;
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::tcp_client::TcpClient;
+use reticulum::transport::{Transport, TransportConfig};
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let identity = PrivateIdentity::new_from_name("echo-server");
+    let transport = Transport::new(TransportConfig::new("echo-server", &identity, false));
+
+    transport
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(TcpClient::new("127.0.0.1:4242"), TcpClient::spawn);
+
+    let destination = transport
+        .add_destination(identity, DestinationName::new("example_utilities", "echo"))
+        .await;
+
+    destination.lock().await.register_request_handler(
+        "/echo",
+        |request: &IncomingRequest| {
+            log::info!("echo request {} over link {}", request.request_id, request.link_id);
+            Some(request.data.to_vec())
+        },
+        RequestPolicy::All,
+    );
+
+    log::info!("echo server {}", destination.lock().await.desc.address_hash);
+
+    loop {
+        transport.send_announce(&destination, None).await.unwrap();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(std::time::Duration::from_secs(60)) => {}
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Fetches a file from the first `fileshare_server` it hears of and saves it
+//! in the current directory:
+//!
+//! ```text
+//! cargo run --example fileshare_client -- docs/notes.txt
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use reticulum::channel::Channel;
+use reticulum::destination::link::LinkStatus;
+use reticulum::destination::DestinationName;
+use reticulum::fileshare::{self, FileMessage};
+use reticulum::iface::tcp_client::TcpClient;
+use reticulum::transport::{Transport, TransportConfig};
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: fileshare_client <path>");
+        return;
+    };
+
+    let transport = Transport::new(TransportConfig::default());
+
+    transport
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(TcpClient::new("127.0.0.1:4242"), TcpClient::spawn);
+
+    let fileshare = DestinationName::new("example_utilities", "fileshare");
+    let mut announces = transport.recv_announces().await;
+    let destination = loop {
+        let announce = announces.recv().await.unwrap();
+        let desc = announce.destination.lock().await.desc;
+        if desc.name.as_name_hash_slice() == fileshare.as_name_hash_slice() {
+            break desc;
+        }
+    };
+
+    log::info!("linking to file server {}", destination.address_hash);
+
+    let link = transport.link(destination).await;
+    while link.lock().await.status() != LinkStatus::Active {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let transport = Arc::new(Mutex::new(transport));
+    let (channel, mut incoming) = Channel::<FileMessage>::new(link, &transport).await.unwrap();
+
+    let data = fileshare::fetch(&channel, &mut incoming, &path, |progress| {
+        log::info!(
+            "{}: {} of {} bytes in {} of {} chunks",
+            path,
+            progress.done_bytes,
+            progress.total_bytes,
+            progress.done_parts,
+            progress.total_parts
+        );
+    })
+    .await;
+
+    match data {
+        Ok(data) => {
+            let name = Path::new(&path).file_name().unwrap_or(path.as_ref());
+            std::fs::write(name, &data).unwrap();
+            log::info!("saved {} bytes to {}", data.len(), Path::new(name).display());
+        }
+        Err(err) => log::error!("can't fetch {}: {}", path, err),
+    }
+}
@@ -0,0 +1,105 @@
+//! Serves the files below a directory, the current one by default, to
+//! `fileshare_client`. Clients connect over TCP on port 4242.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use reticulum::channel::Channel;
+use reticulum::destination::link::LinkEvent;
+use reticulum::destination::DestinationName;
+use reticulum::fileshare::{FileMessage, FileServer};
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::tcp_server::TcpServer;
+use reticulum::transport::{Transport, TransportConfig};
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let root = std::env::args().nth(1).map_or_else(|| PathBuf::from("."), PathBuf::from);
+
+    let identity = PrivateIdentity::new_from_name("fileshare-server");
+    let transport = Transport::new(TransportConfig::new("fileshare-server", &identity, true));
+
+    transport.iface_manager().lock().await.spawn(
+        TcpServer::new("0.0.0.0:4242", transport.iface_manager()),
+        TcpServer::spawn,
+    );
+
+    let destination = transport
+        .add_destination(identity, DestinationName::new("example_utilities", "fileshare"))
+        .await;
+
+    log::info!(
+        "serving {} as {}",
+        root.display(),
+        destination.lock().await.desc.address_hash
+    );
+
+    let file_server = Arc::new(FileServer::new(root));
+
+    let mut served = file_server.subscribe();
+    tokio::spawn(async move {
+        while let Ok(file) = served.recv().await {
+            tokio::spawn(async move {
+                let mut progress = file.transfer.subscribe();
+                while progress.changed().await.is_ok() {
+                    let progress = progress.borrow_and_update().clone();
+                    log::info!(
+                        "{}: {:?} {:.0}% at {:.0} B/s",
+                        file.path,
+                        progress.state,
+                        progress.fraction() * 100.0,
+                        file.transfer.throughput()
+                    );
+
+                    if progress.state.is_finished() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    let mut in_link_events = transport.in_link_events();
+    let transport = Arc::new(Mutex::new(transport));
+    let mut announce = tokio::time::interval(Duration::from_secs(60));
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = announce.tick() => {
+                transport.lock().await.send_announce(&destination, None).await.unwrap();
+            }
+            Ok(event) = in_link_events.recv() => {
+                if !matches!(event.event, LinkEvent::Activated) {
+                    continue;
+                }
+
+                let Some(link) = transport.lock().await.find_in_link(&event.id).await else {
+                    continue;
+                };
+
+                let (channel, mut incoming) = match Channel::<FileMessage>::new(link, &transport).await {
+                    Ok(channel) => channel,
+                    Err(err) => {
+                        log::warn!("can't open channel on {}: {}", event.id, err);
+                        continue;
+                    }
+                };
+
+                log::info!("client linked over {}", event.id);
+
+                let file_server = file_server.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = file_server.serve(&channel, &mut incoming).await {
+                        log::warn!("stopped serving {}: {}", event.id, err);
+                    }
+                });
+            }
+        }
+    }
+}
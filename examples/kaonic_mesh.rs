@@ -12,7 +12,7 @@ async fn main() {
     log::info!(">> packet retransmitter <<");
 
     let transport = Arc::new(Mutex::new(TransportConfig::default()
-        .set_retransmit(true)
+        .set_transport_enabled(true)
         .set_broadcast(false)
         .build()));
 
@@ -14,7 +14,7 @@ async fn main() {
     let args: Vec<String> = env::args().collect();
 
     let transport = Arc::new(Mutex::new(TransportConfig::default()
-        .set_retransmit(true)
+        .set_transport_enabled(true)
         .set_broadcast(false)
         .build()));
 
@@ -9,7 +9,7 @@ use reticulum::transport::{Transport, TransportConfig};
 async fn main() {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace")).init();
 
-    let mut transport = Transport::new(TransportConfig::default());
+    let transport = Transport::new(TransportConfig::default());
 
     log::info!("start tcp app");
 
@@ -32,7 +32,8 @@ async fn main() {
 
     transport
         .send_packet(in_destination.lock().await.announce(OsRng, None).unwrap())
-        .await;
+        .await
+        .unwrap();
 
     tokio::spawn(async move {
         let recv = transport.recv_announces();
@@ -5,23 +5,25 @@ use tokio::io::AsyncBufReadExt;
 
 use rand_core::OsRng;
 
-use reticulum::destination::{DestinationName, SingleInputDestination};
+use reticulum::destination::{DestinationName, SingleInputDestination, SingleOutputDestination};
 use reticulum::destination::link::{LinkEvent, LinkStatus};
-use reticulum::hash::AddressHash;
 use reticulum::identity::PrivateIdentity;
 use reticulum::iface::tcp_client::TcpClient;
 use reticulum::iface::tcp_server::TcpServer;
-use reticulum::packet::{HeaderType, Packet, PacketDataBuffer, PropagationType};
+use reticulum::packet::{HeaderType, Packet, PacketBuilder, PACKET_MDU};
 use reticulum::transport::TransportConfig;
 
-fn create_data_packet(message: &String, destination: AddressHash) -> Packet {
-    let mut packet: Packet = Default::default();
-
-    packet.header.propagation_type = PropagationType::Transport;
-    packet.destination = destination;
-    packet.data = PacketDataBuffer::new_from_slice(message.as_bytes());
-
-    packet
+fn create_data_packet(message: &str, destination: &SingleOutputDestination) -> Packet {
+    let mut buffer = [0u8; PACKET_MDU];
+    let encrypted = destination
+        .encrypt(OsRng, message.as_bytes(), None, &mut buffer)
+        .expect("message fits into a packet");
+
+    PacketBuilder::new()
+        .data_to(destination.desc.address_hash)
+        .data(encrypted)
+        .build()
+        .expect("valid data packet")
 }
 
 #[tokio::main]
@@ -48,12 +50,12 @@ async fn main() {
         last_hop_id.clone(),
         last_hop_name,
     );
-    let last_hop_address = last_hop_destination.desc.address_hash;
+    let last_hop_output = SingleOutputDestination::new(*last_hop_id.as_identity(), last_hop_name);
 
     log::info!("Destination on last hop will be {}", last_hop_destination.desc);
 
-    let mut transport = TransportConfig::new("server", &identity, false)
-        .set_retransmit(true)
+    let transport = TransportConfig::new("server", &identity, false)
+        .set_transport_enabled(true)
         .build();
 
     let our_address = format!("0.0.0.0:{}", our_hop + 5101);
@@ -68,7 +70,7 @@ async fn main() {
         let client_addr = transport.iface_manager().lock().await.spawn(
             TcpClient::new(connect_to),
             TcpClient::spawn,
-        );
+        ).address();
 
         let destination = if our_hop == last_hop {
             transport.add_destination(
@@ -91,7 +93,7 @@ async fn main() {
 
         announce.transport = Some(transport_id);
         announce.header.header_type = HeaderType::Type2;
-        transport.send_direct(client_addr, announce).await;
+        transport.send_direct(client_addr, announce).await.unwrap();
     }
 
     if our_hop == last_hop {
@@ -106,10 +108,18 @@ async fn main() {
 
                 event = data_event.recv() => {
                     if let Ok(event) = event {
-                        if let Ok(text) = from_utf8(event.data.as_slice()) {
-                            log::info!("Message received: {}", text);
-                        } else {
+                        let Ok(text) = from_utf8(event.data.as_slice()) else {
                             log::info!("Broken message received (invalid utf8)");
+                            continue;
+                        };
+
+                        match event.link_id {
+                            Some(link_id) => {
+                                log::info!("Message over link received: {}", text);
+                                let reply = format!("received {}", text);
+                                let _ = transport.reply_on_link(&link_id, reply.as_bytes()).await;
+                            }
+                            None => log::info!("Message received: {}", text),
                         }
                     }
                 },
@@ -120,13 +130,6 @@ async fn main() {
                             LinkEvent::Activated => {
                                 log::info!("Inbound link {} established", event_data.id);
                             },
-                            LinkEvent::Data(payload) => {
-                                if let Ok(text) = from_utf8(payload.as_slice()) {
-                                    log::info!("Message over link received: {}", text);
-                                } else {
-                                    log::info!("Broken message over link (invalid utf8)");
-                                }
-                            },
                             LinkEvent::Closed => {
                                 log::info!("Link closed");
                             },
@@ -172,14 +175,14 @@ async fn main() {
                             log::info!("Sending message over link: {message}");
 
                             let packet = link.data_packet(message.as_bytes()).unwrap();
-                            transport.send_packet(packet).await;
+                            transport.send_packet(packet).await.unwrap();
                             continue;
                         }
                     }
 
                     log::info!("Sending message: {message}");
 
-                    let packet = create_data_packet(&message, last_hop_address);
+                    let packet = create_data_packet(&message, &last_hop_output);
                     transport.outbound(&packet).await;
                 }
             }
@@ -0,0 +1,244 @@
+//! Soak test: runs a simulated network with announce, link and data churn
+//! for hours of network time and checks that it stays healthy.
+//!
+//! The nodes are connected in a random topology and run on the clock of a
+//! [`SimNetwork`], so hours pass in minutes. Every ten minutes of network
+//! time the run checks that
+//!
+//! * no task panicked,
+//! * no link is stuck in its handshake,
+//! * the resident memory doesn't keep growing after a warm-up hour.
+//!
+//! It exits with an error once one of them fails.
+//!
+//! Call: cargo run --release --example soak [nodes] [hours] [seed]
+
+use std::env::args;
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use reticulum::destination::link::LinkStatus;
+use reticulum::destination::{DestinationDesc, DestinationName, SingleInputDestination};
+use reticulum::identity::PrivateIdentity;
+use reticulum::sim::SimNetwork;
+use reticulum::transport::TransportConfig;
+
+/// Network time between two rounds of churn.
+const STEP: Duration = Duration::from_secs(30);
+
+/// Network time between two checks of the invariants.
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Network time after which the memory use is taken as the baseline.
+const WARM_UP: Duration = Duration::from_secs(3600);
+
+/// Links which aren't active after this long are stuck.
+const STUCK_AFTER: Duration = Duration::from_secs(600);
+
+static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+/// Small deterministic generator, so a failing run can be repeated with its
+/// seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+struct App {
+    node: usize,
+    destination: Arc<Mutex<SingleInputDestination>>,
+    desc: DestinationDesc,
+}
+
+/// Resident memory of the process, where the OS tells it.
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+
+    Some(pages * 4096)
+}
+
+fn fail(message: String) -> ! {
+    log::error!("soak: {}", message);
+    exit(1);
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let mut args = args();
+    let nodes = args.nth(1).map_or(16, |s| s.parse::<usize>().unwrap_or(16)).max(2);
+    let hours = args.next().map_or(6, |s| s.parse::<u64>().unwrap_or(6));
+    let seed = args.next().map_or(1, |s| s.parse::<u64>().unwrap_or(1));
+
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("soak=info,reticulum=error"),
+    )
+    .init();
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        PANICS.fetch_add(1, Ordering::SeqCst);
+        default_hook(info);
+    }));
+
+    log::info!("soak: {} nodes for {} hours, seed {}", nodes, hours, seed);
+
+    let mut rng = Rng::new(seed);
+    let mut network = SimNetwork::new();
+
+    for index in 0..nodes {
+        let name = format!("soak{index}");
+        network.add_node(
+            TransportConfig::new(name.as_str(), &PrivateIdentity::new_from_name(&name), false)
+                .set_transport_enabled(true),
+        );
+    }
+
+    // A random tree keeps all nodes reachable, extra links add loops
+    for index in 1..nodes {
+        network.connect(index, rng.below(index)).await;
+    }
+    for _ in 0..nodes / 4 {
+        let (a, b) = (rng.below(nodes), rng.below(nodes));
+        if a != b {
+            network.connect(a, b).await;
+        }
+    }
+
+    let mut apps = Vec::new();
+    for node in 0..nodes {
+        for app in 0..1 + rng.below(2) {
+            let identity = PrivateIdentity::new_from_name(&format!("soak{node}-app{app}"));
+            let destination = network
+                .node_mut(node)
+                .add_destination(identity, DestinationName::new("soak", "app"))
+                .await;
+            let desc = destination.lock().await.desc;
+            network.node(node).send_announce(&destination, None).await.unwrap();
+            apps.push(App { node, destination, desc });
+        }
+    }
+
+    let end = Duration::from_secs(hours * 3600);
+    let mut elapsed = Duration::ZERO;
+    let mut next_check = CHECK_INTERVAL;
+    let mut baseline_memory = None;
+    let mut links_opened = 0u64;
+    let mut links_closed = 0u64;
+    let mut messages = 0u64;
+
+    while elapsed < end {
+        // Announces
+        for _ in 0..1 + nodes / 8 {
+            let app = &apps[rng.below(apps.len())];
+            network.node(app.node).send_announce(&app.destination, None).await.unwrap();
+        }
+
+        // New links to destinations with a known path
+        for _ in 0..1 + nodes / 8 {
+            let node = rng.below(nodes);
+            let app = &apps[rng.below(apps.len())];
+            if app.node != node && !network.node(node).paths(&app.desc.address_hash).await.is_empty() {
+                network.node(node).link(app.desc).await;
+                links_opened += 1;
+            }
+        }
+
+        // Data over active links, and some links closed again
+        for node in 0..nodes {
+            let transport = network.node(node);
+
+            if rng.chance(30) {
+                transport.send_to_all_out_links(&rng.next().to_be_bytes()).await;
+                messages += 1;
+            }
+
+            if rng.chance(5) {
+                let links = transport.active_links().await;
+                if !links.is_empty() {
+                    let link = &links[rng.below(links.len())];
+                    let _ = transport.link_close(link.id).await;
+                    links_closed += 1;
+                }
+            }
+        }
+
+        network.advance(STEP).await;
+        elapsed += STEP;
+
+        if elapsed < next_check {
+            continue;
+        }
+        next_check += CHECK_INTERVAL;
+
+        let panics = PANICS.load(Ordering::SeqCst);
+        if panics > 0 {
+            fail(format!("{} tasks panicked", panics));
+        }
+
+        let mut links = 0;
+        for (node, transport) in network.nodes().iter().enumerate() {
+            for link in transport.active_links().await {
+                links += 1;
+                let establishing = matches!(link.status, LinkStatus::Pending | LinkStatus::Handshake);
+                if establishing && link.age > STUCK_AFTER {
+                    fail(format!(
+                        "link {} of node {} is stuck in {:?} after {}s",
+                        link.id,
+                        node,
+                        link.status,
+                        link.age.as_secs()
+                    ));
+                }
+            }
+        }
+
+        let memory = resident_memory();
+        if elapsed >= WARM_UP {
+            if let (Some(memory), Some(baseline)) = (memory, *baseline_memory.get_or_insert(memory)) {
+                if memory > baseline + baseline / 2 {
+                    fail(format!(
+                        "resident memory grew from {} to {} KiB",
+                        baseline / 1024,
+                        memory / 1024
+                    ));
+                }
+            }
+        }
+
+        log::info!(
+            "soak: {}m, {} links open, {} opened, {} closed, {} messages, {} packets carried, {} KiB",
+            elapsed.as_secs() / 60,
+            links,
+            links_opened,
+            links_closed,
+            messages,
+            network.carried_packets(),
+            memory.map_or(0, |memory| memory / 1024),
+        );
+    }
+
+    log::info!("soak: passed");
+}
@@ -1,4 +1,3 @@
-use std::time::Duration;
 
 use rand_core::OsRng;
 use reticulum::destination::{DestinationName, SingleInputDestination};
@@ -14,7 +13,7 @@ async fn main() {
 
     let transport = Transport::new(TransportConfig::default());
 
-    let client_addr = transport
+    let client = transport
         .iface_manager()
         .lock()
         .await
@@ -24,11 +23,12 @@ async fn main() {
 
     let destination = SingleInputDestination::new(id, DestinationName::new("example", "app"));
 
-    tokio::time::sleep(Duration::from_secs(3)).await;
+    client.await_ready().await.expect("connected to 127.0.0.1:4242");
 
     transport
-        .send_direct(client_addr, destination.announce(OsRng, None).unwrap())
-        .await;
+        .send_direct(client.address(), destination.announce(OsRng, None).unwrap())
+        .await
+        .unwrap();
 
     let _ = tokio::signal::ctrl_c().await;
 
@@ -59,7 +59,7 @@ async fn main() {
             log::info!("link {}: {:?}", link.id(), link.status());
             if link.status() == LinkStatus::Active {
                 let packet = link.data_packet (b"foo").unwrap();
-                transport.send_packet(packet).await;
+                transport.send_packet(packet).await.unwrap();
             }
         }
         while let Ok(link_event) = out_link_events.try_recv() {
@@ -75,7 +75,7 @@ async fn main() {
         }
         transport
             .send_announce(&dest, None)
-            .await;
+            .await.unwrap();
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 
@@ -0,0 +1,50 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let source = crate_dir.join("../src/ffi.rs");
+    let example = crate_dir.join("examples/smoke.c");
+
+    println!("cargo:rerun-if-changed={}", source.display());
+    println!("cargo:rerun-if-changed={}", example.display());
+
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        header: Some("/* Generated from src/ffi.rs by the reticulum-capi build, don't edit. */".into()),
+        include_guard: Some("RETICULUM_H".into()),
+        cpp_compat: true,
+        documentation: true,
+        ..Default::default()
+    };
+
+    // The header in include/ is checked against this one by the smoke test,
+    // the build doesn't write to the source tree
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(source)
+        .generate()
+        .expect("C header")
+        .write_to_file(out_dir.join("reticulum.h"));
+
+    println!("cargo:rustc-check-cfg=cfg(smoke_example)");
+
+    // Cross builds only need the libraries, they aren't tested here
+    if env::var("TARGET") != env::var("HOST") {
+        return;
+    }
+
+    // The smoke test calls the example instead of its main function and
+    // links it on its own, so it doesn't end up in the libraries
+    cc::Build::new()
+        .file(&example)
+        .include(&out_dir)
+        .define("main", "reticulum_capi_smoke_main")
+        .flag_if_supported("-std=c11")
+        .cargo_metadata(false)
+        .compile("reticulum_capi_smoke");
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-cfg=smoke_example");
+}
@@ -0,0 +1,138 @@
+/*
+ * Two nodes joined by frame interfaces, the frames are carried by this
+ * program the way a firmware would carry them over its radio driver. The
+ * sensor announces a destination, the gateway links to it and sends data.
+ */
+
+#define _POSIX_C_SOURCE 199309L
+
+#include <stdatomic.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "reticulum.h"
+
+#define HASH_SIZE 16
+
+struct events {
+    atomic_int announced;
+    atomic_int link_active;
+    atomic_int data_received;
+    uint8_t destination[HASH_SIZE];
+};
+
+static void on_announce(void *user_data, const uint8_t *destination, const uint8_t *app_data,
+                        uintptr_t app_data_len) {
+    struct events *events = user_data;
+    (void)app_data;
+    (void)app_data_len;
+    if (memcmp(destination, events->destination, HASH_SIZE) == 0) {
+        atomic_store(&events->announced, 1);
+    }
+}
+
+static void on_link_activated(void *user_data, const uint8_t *link_id,
+                              const uint8_t *destination) {
+    struct events *events = user_data;
+    (void)link_id;
+    (void)destination;
+    atomic_store(&events->link_active, 1);
+}
+
+static void on_link_data(void *user_data, const uint8_t *link_id, const uint8_t *data,
+                         uintptr_t len) {
+    struct events *events = user_data;
+    (void)link_id;
+    if (len == 5 && memcmp(data, "hello", 5) == 0) {
+        atomic_store(&events->data_received, 1);
+    }
+}
+
+static void sleep_ms(long ms) {
+    struct timespec delay = { ms / 1000, (ms % 1000) * 1000000L };
+    nanosleep(&delay, NULL);
+}
+
+/* Moves all pending frames from one interface to the other. */
+static void carry(RnsInterface *from, RnsInterface *to) {
+    uint8_t frame[RNS_FRAME_MAX_SIZE];
+    int len;
+    while ((len = rns_interface_frame_out(from, frame, sizeof(frame))) > 0) {
+        rns_interface_frame_in(to, frame, (uintptr_t)len);
+    }
+}
+
+/* Carries frames both ways until `flag` is set, fails after 5 seconds. */
+static int wait_for(atomic_int *flag, RnsInterface *a, RnsInterface *b) {
+    for (int i = 0; i < 500; i++) {
+        carry(a, b);
+        carry(b, a);
+        if (atomic_load(flag)) {
+            return 1;
+        }
+        sleep_ms(10);
+    }
+    return 0;
+}
+
+#define CHECK(condition)                                              \
+    do {                                                              \
+        if (!(condition)) {                                           \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+int main(void) {
+    struct events sensor_events = { 0 };
+    struct events gateway_events = { 0 };
+
+    RnsIdentity *identity = rns_identity_new();
+    RnsNode *sensor = rns_node_new("sensor", identity);
+    RnsNode *gateway = rns_node_new("gateway", NULL);
+    CHECK(identity && sensor && gateway);
+
+    RnsInterface *sensor_iface = rns_interface_new(sensor);
+    RnsInterface *gateway_iface = rns_interface_new(gateway);
+    CHECK(sensor_iface && gateway_iface);
+
+    RnsCallbacks callbacks = { 0 };
+    callbacks.user_data = &sensor_events;
+    callbacks.on_link_data = on_link_data;
+    CHECK(rns_node_set_callbacks(sensor, callbacks) == RNS_OK);
+
+    callbacks.user_data = &gateway_events;
+    callbacks.on_announce = on_announce;
+    callbacks.on_link_activated = on_link_activated;
+    callbacks.on_link_data = NULL;
+    CHECK(rns_node_set_callbacks(gateway, callbacks) == RNS_OK);
+
+    RnsDestination *destination = rns_destination_new(sensor, identity, "smoke", "sensor");
+    CHECK(destination != NULL);
+    CHECK(rns_destination_hash(destination, gateway_events.destination) == RNS_OK);
+
+    const uint8_t app_data[] = "temperature";
+    CHECK(rns_destination_announce(sensor, destination, app_data, sizeof(app_data) - 1) == RNS_OK);
+    CHECK(wait_for(&gateway_events.announced, sensor_iface, gateway_iface));
+
+    uint8_t link_id[HASH_SIZE];
+    CHECK(rns_link_open(gateway, gateway_events.destination, link_id) == RNS_OK);
+    CHECK(wait_for(&gateway_events.link_active, sensor_iface, gateway_iface));
+
+    CHECK(rns_link_send(gateway, link_id, (const uint8_t *)"hello", 5) == RNS_OK);
+    CHECK(wait_for(&sensor_events.data_received, sensor_iface, gateway_iface));
+
+    CHECK(rns_interface_frame_in(gateway_iface, (const uint8_t *)"x", 1) == RNS_ERR_FRAME);
+    CHECK(rns_link_close(gateway, link_id) == RNS_OK);
+
+    rns_destination_free(destination);
+    rns_interface_free(gateway_iface);
+    rns_interface_free(sensor_iface);
+    rns_node_free(gateway);
+    rns_node_free(sensor);
+    rns_identity_free(identity);
+
+    printf("smoke: ok\n");
+    return 0;
+}
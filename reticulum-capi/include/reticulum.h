@@ -0,0 +1,305 @@
+/* Generated from src/ffi.rs by the reticulum-capi build, don't edit. */
+
+#ifndef RETICULUM_H
+#define RETICULUM_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define RNS_OK 0
+
+/**
+ * A null pointer, a string which isn't UTF-8 or an invalid identity.
+ */
+#define RNS_ERR_ARGUMENT -1
+
+/**
+ * The destination or link isn't known.
+ */
+#define RNS_ERR_NOT_FOUND -2
+
+/**
+ * The link is closed or the payload doesn't fit into a packet.
+ */
+#define RNS_ERR_LINK -3
+
+/**
+ * A received frame isn't a valid packet.
+ */
+#define RNS_ERR_FRAME -4
+
+/**
+ * The transport can't take more frames right now, try again later.
+ */
+#define RNS_ERR_BUSY -5
+
+/**
+ * Size of a buffer which fits every frame of an interface.
+ */
+#define RNS_FRAME_MAX_SIZE 2112
+
+/**
+ * A destination registered with a node.
+ */
+typedef struct RnsDestination RnsDestination;
+
+/**
+ * A private identity.
+ */
+typedef struct RnsIdentity RnsIdentity;
+
+/**
+ * An interface whose frames are carried by the app, e.g. over a radio or
+ * serial driver of the firmware.
+ */
+typedef struct RnsInterface RnsInterface;
+
+typedef struct RnsNode RnsNode;
+
+typedef void (*RnsAnnounceCallback)(void *user_data,
+                                    const uint8_t *destination,
+                                    const uint8_t *app_data,
+                                    uintptr_t app_data_len);
+
+typedef void (*RnsLinkCallback)(void *user_data, const uint8_t *link_id, const uint8_t *destination);
+
+typedef void (*RnsLinkDataCallback)(void *user_data,
+                                    const uint8_t *link_id,
+                                    const uint8_t *data,
+                                    uintptr_t len);
+
+/**
+ * Event callbacks of a node, any of them may be null. Pointers passed to
+ * the callbacks are only valid during the call.
+ */
+typedef struct RnsCallbacks {
+  void *user_data;
+  RnsAnnounceCallback on_announce;
+  RnsLinkCallback on_link_activated;
+  RnsLinkCallback on_link_closed;
+  RnsLinkDataCallback on_link_data;
+} RnsCallbacks;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates a new random identity.
+ */
+struct RnsIdentity *rns_identity_new(void);
+
+/**
+ * Restores an identity from its hex encoded private keys. Returns null if
+ * `hex` isn't a valid identity.
+ *
+ * # Safety
+ *
+ * `hex` must be null or point to a NUL terminated string.
+ */
+struct RnsIdentity *rns_identity_from_hex(const char *hex);
+
+/**
+ * Writes the hash of an identity to `out_hash`.
+ *
+ * # Safety
+ *
+ * `identity` must come from [`rns_identity_new`] and `out_hash` must have
+ * room for 16 bytes.
+ */
+int rns_identity_hash(const struct RnsIdentity *identity, uint8_t *out_hash);
+
+/**
+ * Frees an identity.
+ *
+ * # Safety
+ *
+ * `identity` must be null or come from [`rns_identity_new`] and not be used
+ * again.
+ */
+void rns_identity_free(struct RnsIdentity *identity);
+
+/**
+ * Creates a node named `name`. `identity` is the identity of the node, a
+ * new one is generated if it is null. Returns null if an argument is
+ * invalid or the runtime can't be started.
+ *
+ * # Safety
+ *
+ * `name` must point to a NUL terminated string, `identity` must be null or
+ * come from [`rns_identity_new`].
+ */
+struct RnsNode *rns_node_new(const char *name, const struct RnsIdentity *identity);
+
+/**
+ * Stops the node and frees it.
+ *
+ * # Safety
+ *
+ * `node` must be null or come from [`rns_node_new`] and not be used again.
+ */
+void rns_node_free(struct RnsNode *node);
+
+/**
+ * Connects the node to a TCP server at `addr`, e.g. `"10.0.0.1:4242"`.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `addr` must be a NUL terminated
+ * string.
+ */
+int rns_node_add_tcp_client(struct RnsNode *node, const char *addr);
+
+/**
+ * Accepts TCP clients on `addr`, e.g. `"0.0.0.0:4242"`.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `addr` must be a NUL terminated
+ * string.
+ */
+int rns_node_add_tcp_server(struct RnsNode *node, const char *addr);
+
+/**
+ * Adds an interface whose frames are exchanged with
+ * [`rns_interface_frame_in`] and [`rns_interface_frame_out`].
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`].
+ */
+struct RnsInterface *rns_interface_new(struct RnsNode *node);
+
+/**
+ * Passes a frame received by the app to the transport.
+ *
+ * # Safety
+ *
+ * `iface` must come from [`rns_interface_new`] and `frame` must point to
+ * `len` bytes.
+ */
+int rns_interface_frame_in(struct RnsInterface *iface, const uint8_t *frame, uintptr_t len);
+
+/**
+ * Takes the next frame the transport sends over the interface. Returns
+ * its length, or 0 if there is none. `buffer` should have room for
+ * [`RNS_FRAME_MAX_SIZE`] bytes.
+ *
+ * # Safety
+ *
+ * `iface` must come from [`rns_interface_new`] and `buffer` must have room
+ * for `capacity` bytes.
+ */
+int rns_interface_frame_out(struct RnsInterface *iface, uint8_t *buffer, uintptr_t capacity);
+
+/**
+ * Removes an interface from its node and frees it.
+ *
+ * # Safety
+ *
+ * `iface` must be null or come from [`rns_interface_new`] and not be used
+ * again.
+ */
+void rns_interface_free(struct RnsInterface *iface);
+
+/**
+ * Replaces the event callbacks of the node.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`]. The callbacks may be called until
+ * they are replaced or the node is freed.
+ */
+int rns_node_set_callbacks(struct RnsNode *node, struct RnsCallbacks callbacks);
+
+/**
+ * Adds a destination `app_name.aspects` to the node. It belongs to
+ * `identity`, or to the identity of the node if `identity` is null. Returns
+ * null if an argument is invalid.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `identity` must be null or come
+ * from [`rns_identity_new`], `app_name` and `aspects` must be NUL terminated
+ * strings.
+ */
+struct RnsDestination *rns_destination_new(struct RnsNode *node,
+                                           const struct RnsIdentity *identity,
+                                           const char *app_name,
+                                           const char *aspects);
+
+/**
+ * Writes the hash of a destination to `out_hash`.
+ *
+ * # Safety
+ *
+ * `destination` must come from [`rns_destination_new`] and `out_hash` must
+ * have room for 16 bytes.
+ */
+int rns_destination_hash(const struct RnsDestination *destination, uint8_t *out_hash);
+
+/**
+ * Announces a destination with optional `app_data`. Fails with
+ * [`RNS_ERR_ARGUMENT`] if the announce doesn't fit the MTU of the
+ * interfaces.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `destination` from
+ * [`rns_destination_new`] of the same node and `app_data` must point to
+ * `app_data_len` bytes.
+ */
+int rns_destination_announce(struct RnsNode *node,
+                             const struct RnsDestination *destination,
+                             const uint8_t *app_data,
+                             uintptr_t app_data_len);
+
+/**
+ * Frees a destination handle, the destination stays registered with the
+ * node.
+ *
+ * # Safety
+ *
+ * `destination` must be null or come from [`rns_destination_new`] and not
+ * be used again.
+ */
+void rns_destination_free(struct RnsDestination *destination);
+
+/**
+ * Opens a link to an announced destination and writes its id to
+ * `out_link_id`. The link can be used once `on_link_activated` is called.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `destination` must point to 16
+ * bytes and `out_link_id` must have room for 16 bytes.
+ */
+int rns_link_open(struct RnsNode *node, const uint8_t *destination, uint8_t *out_link_id);
+
+/**
+ * Sends `data` over a link, waiting while the link window is full.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`], `link_id` must point to 16 bytes
+ * and `data` to `len` bytes.
+ */
+int rns_link_send(struct RnsNode *node, const uint8_t *link_id, const uint8_t *data, uintptr_t len);
+
+/**
+ * Closes a link.
+ *
+ * # Safety
+ *
+ * `node` must come from [`rns_node_new`] and `link_id` must point to 16
+ * bytes.
+ */
+int rns_link_close(struct RnsNode *node, const uint8_t *link_id);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RETICULUM_H */
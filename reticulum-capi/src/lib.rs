@@ -0,0 +1,8 @@
+//! C API of reticulum-rs.
+//!
+//! The functions are implemented in [`reticulum::ffi`], this crate builds
+//! them into a static and a shared library, declared in
+//! `include/reticulum.h`. The build generates the header from them again
+//! and the tests check that it is up to date.
+
+pub use reticulum::ffi::*;
@@ -0,0 +1,34 @@
+//! Runs `examples/smoke.c`, which the build compiled against the generated
+//! header, and checks that `include/reticulum.h` is up to date.
+
+use std::ffi::c_int;
+use std::fs;
+use std::path::Path;
+
+// Links the library the example calls into
+use reticulum_capi as _;
+
+#[cfg(smoke_example)]
+#[link(name = "reticulum_capi_smoke", kind = "static")]
+extern "C" {
+    fn reticulum_capi_smoke_main() -> c_int;
+}
+
+#[cfg(smoke_example)]
+#[test]
+fn c_smoke_example() {
+    assert_eq!(unsafe { reticulum_capi_smoke_main() }, 0, "smoke example failed");
+}
+
+#[test]
+fn header_is_up_to_date() {
+    let generated = Path::new(env!("OUT_DIR")).join("reticulum.h");
+    let committed = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/reticulum.h");
+
+    assert!(
+        fs::read_to_string(&generated).unwrap() == fs::read_to_string(&committed).unwrap(),
+        "{} is out of date, copy {} over it",
+        committed.display(),
+        generated.display()
+    );
+}
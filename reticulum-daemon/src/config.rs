@@ -1,9 +1,22 @@
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
+use std::net::{IpAddr, Ipv6Addr};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
 use regex::Regex;
+use reticulum::diagnostics::DecodeDumps;
+use reticulum::hash::AddressHash;
+use reticulum::iface::InterfaceMode;
+use reticulum::transport::PATHFINDER_M;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use toml::Spanned;
+use toml::de::{DeTable, DeValue, ValueDeserializer};
+
+mod python;
 
 #[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Config {
@@ -13,6 +26,10 @@ pub struct Config {
     pub logging: LoggingConfig,
     #[serde(default)]
     pub interfaces: Vec<NamedInterface>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub propagation: Vec<PropagationRuleConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub static_paths: Vec<StaticPathConfig>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -29,12 +46,74 @@ pub struct ReticulumConfig {
     pub panic_on_interface_error: bool,
     #[serde(default)]
     pub instance_name: Option<String>,
+    /// Seconds between writes of the status file, 0 writes it on `SIGUSR1`
+    /// only.
+    #[serde(default = "default_status_interval")]
+    pub status_interval: u64,
+    /// Serve the `rnstransport.remote.management` destination.
+    #[serde(default)]
+    pub enable_remote_management: bool,
+    /// Identity hashes which may query the management destination.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remote_management_allowed: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoggingConfig {
     #[serde(default = "default_loglevel")]
     pub loglevel: log::LevelFilter,
+    /// Levels of single modules, overriding `loglevel`, e.g.
+    /// `"reticulum::transport" = "debug"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub modules: BTreeMap<String, log::LevelFilter>,
+    /// File to log to instead of stderr, relative to the config directory.
+    /// With `--service` the daemon logs to `logfile` if this isn't set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logfile: Option<PathBuf>,
+    /// Size in bytes after which the log file is rotated.
+    #[serde(default = "default_logfile_max_size")]
+    pub logfile_max_size: u64,
+    /// Seconds after which the log file is rotated whatever its size, 0
+    /// rotates by size only.
+    #[serde(default)]
+    pub logfile_rotate_interval: u64,
+    /// Rotated log files kept as `logfile.1`, `logfile.2` and so on.
+    #[serde(default = "default_logfile_backups")]
+    pub logfile_backups: u32,
+    /// What is logged about received bytes which couldn't be decoded, one
+    /// of `off`, `fields` and `hexdump`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_dumps: Option<String>,
+}
+
+/// Keeps broadcasts received on interfaces of group `from` off the
+/// interfaces of group `to`, see `groups` in [`InterfaceOptions`].
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PropagationRuleConfig {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub traffic: PropagationTraffic,
+}
+
+/// Path to a destination known in advance, used until one is learned from
+/// announces.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StaticPathConfig {
+    /// Hash of the destination.
+    pub destination: String,
+    /// Name of the interface the destination is reached over.
+    pub interface: String,
+    #[serde(default = "default_static_path_hops")]
+    pub hops: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PropagationTraffic {
+    #[default]
+    Announces,
+    All,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -54,12 +133,32 @@ pub enum InterfaceConfig {
         bind_host: String,
         #[serde(alias = "listen_port")]
         bind_port: u16,
+        #[serde(default)]
+        kiss_framing: bool,
+        #[serde(flatten)]
+        bind: BindOptions,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     TCPClientInterface {
         #[serde(default = "default_true", alias = "interface_enabled")]
         enabled: bool,
         target_host: String,
         target_port: u16,
+        #[serde(default)]
+        kiss_framing: bool,
+        /// Seconds without sending before an empty frame is sent, 0 turns
+        /// pings off.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ping_interval: Option<u64>,
+        /// Seconds without receiving before the connection is reopened.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rx_timeout: Option<u64>,
+        /// Seconds each address of the target gets to accept the connection.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connect_timeout: Option<u64>,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     UDPInterface {
         #[serde(default = "default_true", alias = "interface_enabled")]
@@ -68,10 +167,16 @@ pub enum InterfaceConfig {
         listen_port: u16,
         forward_ip: String,
         forward_port: u16,
+        #[serde(flatten)]
+        bind: BindOptions,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     AutoInterface {
         #[serde(default = "default_true")]
         enabled: bool,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     I2PInterface {
         #[serde(default = "default_true")]
@@ -79,6 +184,8 @@ pub enum InterfaceConfig {
         #[serde(default)]
         connectable: bool,
         peers: String,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     RNodeInterface {
         #[serde(default = "default_true", alias = "interface_enabled")]
@@ -91,6 +198,8 @@ pub enum InterfaceConfig {
         codingrate: u8,
         #[serde(default)]
         flow_control: bool,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     BLEInterface {
         #[serde(default = "default_true")]
@@ -99,6 +208,8 @@ pub enum InterfaceConfig {
         enable_peripheral: bool,
         #[serde(default)]
         enable_central: bool,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     KISSInterface {
         #[serde(default = "default_true")]
@@ -114,6 +225,8 @@ pub enum InterfaceConfig {
         slottime: u32,
         #[serde(default)]
         flow_control: bool,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     AX25KISSInterface {
         #[serde(default = "default_true")]
@@ -131,15 +244,93 @@ pub enum InterfaceConfig {
         slottime: u32,
         #[serde(default)]
         flow_control: bool,
+        #[serde(flatten)]
+        options: InterfaceOptions,
     },
     #[serde(other)]
     Unsupported,
 }
 
+/// Socket settings of interfaces which bind a port. Comes before
+/// [`InterfaceOptions`] in the interfaces, which would keep its keys as
+/// unknown ones otherwise.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct BindOptions {
+    /// More hosts to bind with the same port, e.g. "::" next to "0.0.0.0".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_bind_hosts: Vec<String>,
+    /// Accept IPv6 only on IPv6 hosts, or IPv4 as well. The system default,
+    /// usually both, if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reuse_address: bool,
+    /// Let other sockets bind the same port, Unix only.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reuse_port: bool,
+}
+
+/// Settings common to all interface types.
+///
+/// Keys which rs-rnsd doesn't know about are kept in `other` instead of being
+/// rejected, so configs written for Python Reticulum load unchanged.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct InterfaceOptions {
+    #[serde(default, alias = "networkname", alias = "network_name", skip_serializing_if = "Option::is_none")]
+    pub ifac_netname: Option<String>,
+    #[serde(default, alias = "passphrase", alias = "pass_phrase", skip_serializing_if = "Option::is_none")]
+    pub ifac_netkey: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u64>,
+    #[serde(default, alias = "interface_mode", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announce_cap: Option<f64>,
+    /// Groups which propagation rules refer to, e.g. "backbone" or "lora".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+    /// Seconds to wait after the first failed attempt to connect or bind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_delay: Option<f64>,
+    /// Upper bound in seconds of the growing wait between attempts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_max_delay: Option<f64>,
+    /// Share of the wait, between 0 and 1, which is randomly taken off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_jitter: Option<f64>,
+    /// Failed attempts in a row after which the interface gives up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_attempts: Option<u32>,
+    #[serde(flatten)]
+    pub other: BTreeMap<String, toml::Value>,
+}
+
+impl InterfaceConfig {
+    pub fn options(&self) -> Option<&InterfaceOptions> {
+        match self {
+            InterfaceConfig::TCPServerInterface { options, .. }
+            | InterfaceConfig::TCPClientInterface { options, .. }
+            | InterfaceConfig::UDPInterface { options, .. }
+            | InterfaceConfig::AutoInterface { options, .. }
+            | InterfaceConfig::I2PInterface { options, .. }
+            | InterfaceConfig::RNodeInterface { options, .. }
+            | InterfaceConfig::BLEInterface { options, .. }
+            | InterfaceConfig::KISSInterface { options, .. }
+            | InterfaceConfig::AX25KISSInterface { options, .. } => Some(options),
+            InterfaceConfig::Unsupported => None,
+        }
+    }
+}
+
 fn default_true() -> bool { true }
+fn is_false(value: &bool) -> bool { !*value }
 fn default_shared_port() -> u16 { 37428 }
 fn default_control_port() -> u16 { 37429 }
+fn default_status_interval() -> u64 { 60 }
 fn default_loglevel() -> log::LevelFilter { log::LevelFilter::Info }
+fn default_logfile_max_size() -> u64 { 5 * 1024 * 1024 }
+fn default_logfile_backups() -> u32 { 1 }
+fn default_static_path_hops() -> u8 { 1 }
 
 pub fn migrate_config(config_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
     if !config_file.exists() {
@@ -282,6 +473,13 @@ fn convert_config(content: &str) -> String {
             converted = quote_if_needed(&converted, "callsign");
             converted = quote_if_needed(&converted, "parity");
             converted = quote_if_needed(&converted, "loglevel");
+            converted = quote_if_needed(&converted, "mode");
+            converted = quote_if_needed(&converted, "ifac_netname");
+            converted = quote_if_needed(&converted, "ifac_netkey");
+            converted = quote_if_needed(&converted, "networkname");
+            converted = quote_if_needed(&converted, "network_name");
+            converted = quote_if_needed(&converted, "passphrase");
+            converted = quote_if_needed(&converted, "pass_phrase");
         }
         output.push_str(&converted);
         output.push('\n');
@@ -298,13 +496,24 @@ impl Default for ReticulumConfig {
             instance_control_port: 37429,
             panic_on_interface_error: false,
             instance_name: None,
+            status_interval: default_status_interval(),
+            enable_remote_management: false,
+            remote_management_allowed: Vec::new(),
         }
     }
 }
 
 impl Default for LoggingConfig {
     fn default() -> Self {
-        Self { loglevel: default_loglevel() }
+        Self {
+            loglevel: default_loglevel(),
+            modules: BTreeMap::new(),
+            logfile: None,
+            logfile_max_size: default_logfile_max_size(),
+            logfile_rotate_interval: 0,
+            logfile_backups: default_logfile_backups(),
+            decode_dumps: None,
+        }
     }
 }
 
@@ -331,39 +540,26 @@ impl Config {
             .join(".config/reticulum")
     }
 
-    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
         let config_basename = if path.join("config.toml").exists() {
             "config.toml"
         } else if path.join("config").exists() {
             "config"
         } else {
-            let err = format!("no config.toml or config file found in config path {}",
-                path.display());
-            return Err(err.into())
+            return Err(ConfigError::NotFound(path.to_path_buf()))
         };
         let config_file = path.join(config_basename);
-        let content = fs::read_to_string(&config_file)?;
-        let config: Self = match toml::from_str(&content) {
-            Ok(config) => config,
-            Err(err) => {
-                if config_basename == "config.toml" {
-                    eprintln!("{config_file:?} is not valid TOML");
-                    return Err(err.into())
-                } else {
-                    // attempt to convert
-                    eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                    eprintln!("Your config file appears to be in Python Reticulum format.");
-                    eprintln!("You can use the converter tool to migrate it to standard TOML:");
-                    eprintln!();
-                    eprintln!("  cargo run -p reticulum-daemon -- convert-config {}", config_file.display());
-                    eprintln!();
-                    eprintln!("This command will create a backup and convert your config to valid TOML.");
-                    eprintln!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-                    let converted = convert_config(&content);
-                    toml::from_str(&converted)?
-                }
-            }
+        let content = fs::read_to_string(&config_file)
+            .map_err(|err| ConfigError::Io(config_file.clone(), err))?;
+        // The legacy file name may hold TOML as well, e.g. after converting in
+        // place
+        let parsed = if config_basename == "config" && DeTable::parse(&content).is_err() {
+            log::info!("Reading {} in Python Reticulum format", config_file.display());
+            Self::parse_python(&content)
+        } else {
+            Self::parse(&content)
         };
+        let config = parsed.map_err(|problems| ConfigError::Invalid { file: config_file, problems })?;
         if config.reticulum.share_instance {
             log::warn!("share_instance is enabled but shared instances are not supported in reticulum-rs");
             log::warn!("Each Rust daemon process runs independently and is only limited by available ports");
@@ -403,10 +599,610 @@ impl Config {
                         enabled: true,
                         bind_host: "127.0.0.1".to_string(),
                         bind_port: 4242,
+                        kiss_framing: false,
+                        bind: BindOptions::default(),
+                        options: InterfaceOptions::default(),
                     },
                 },
             ],
+            propagation: Vec::new(),
+            static_paths: Vec::new(),
+        }
+    }
+}
+
+/// Part of a config a [`ConfigProblem`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigKey {
+    /// A key of the `[reticulum]` section.
+    Reticulum(&'static str),
+    /// A key of the `[logging]` section.
+    Logging(&'static str),
+    /// A key of the interface at the index, `None` for the whole interface.
+    Interface(usize, Option<&'static str>),
+    /// The propagation rule at the index.
+    Propagation(usize),
+    /// The static path at the index.
+    StaticPath(usize),
+}
+
+/// Something wrong with a config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigProblem {
+    pub key: Option<ConfigKey>,
+    /// Line of the config file, counting from 1, if the config was parsed
+    /// from text.
+    pub line: Option<usize>,
+    /// The text of that line.
+    pub context: Option<String>,
+    pub message: String,
+}
+
+impl ConfigProblem {
+    fn new(key: ConfigKey, message: String) -> Self {
+        Self { key: Some(key), line: None, context: None, message }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message)?,
+            None => write!(f, "{}", self.message)?,
+        }
+        if let Some(context) = &self.context {
+            write!(f, "\n    | {}", context)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config directory has neither a `config.toml` nor a `config` file.
+    NotFound(PathBuf),
+    Io(PathBuf, std::io::Error),
+    /// The config file can't be used, all problems found are listed.
+    Invalid { file: PathBuf, problems: Vec<ConfigProblem> },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::NotFound(path) => {
+                write!(f, "no config.toml or config file found in config path {}", path.display())
+            }
+            ConfigError::Io(file, err) => write!(f, "couldn't read {}: {}", file.display(), err),
+            ConfigError::Invalid { file, problems } => {
+                write!(f, "{} has {} problem(s):", file.display(), problems.len())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Config text being parsed, to put problems on their lines.
+struct Source<'i> {
+    content: &'i str,
+    problems: Vec<ConfigProblem>,
+}
+
+impl<'i> Source<'i> {
+    fn new(content: &'i str) -> Self {
+        Self { content, problems: Vec::new() }
+    }
+
+    /// Line of a byte offset in the content, counting from 1.
+    fn line_of(content: &str, offset: usize) -> usize {
+        content[..offset.min(content.len())].matches('\n').count() + 1
+    }
+
+    fn report(&mut self, span: Range<usize>, problem: ConfigProblem) {
+        self.report_line(Self::line_of(self.content, span.start), problem);
+    }
+
+    fn report_line(&mut self, line: usize, mut problem: ConfigProblem) {
+        problem.line = Some(line);
+        problem.context = self
+            .content
+            .lines()
+            .nth(line - 1)
+            .map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty());
+        self.problems.push(problem);
+    }
+
+    fn deserialize<T: DeserializeOwned>(&mut self, value: &Spanned<DeValue<'i>>, what: &str) -> Option<T> {
+        match T::deserialize(ValueDeserializer::from(value.clone())) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let message = format!("{}: {}", what, err.message());
+                self.report(err.span().unwrap_or(value.span()), ConfigProblem {
+                    key: None,
+                    line: None,
+                    context: None,
+                    message,
+                });
+                None
+            }
+        }
+    }
+
+    /// Validates the parsed config, `locate` finds the lines of the keys
+    /// problems are about.
+    fn finish(
+        mut self,
+        config: Config,
+        locate: impl Fn(ConfigKey) -> Option<usize>,
+    ) -> Result<Config, Vec<ConfigProblem>> {
+        for problem in config.validate() {
+            match problem.key.and_then(&locate) {
+                Some(line) => self.report_line(line, problem),
+                None => self.problems.push(problem),
+            }
+        }
+
+        if self.problems.is_empty() {
+            Ok(config)
+        } else {
+            self.problems.sort_by_key(|problem| problem.line);
+            Err(self.problems)
+        }
+    }
+}
+
+/// Value of `key` if `value` is a table which has it.
+fn table_entry<'a, 'i>(value: &'a Spanned<DeValue<'i>>, key: &str) -> Option<&'a Spanned<DeValue<'i>>> {
+    value
+        .get_ref()
+        .as_table()?
+        .iter()
+        .find(|(name, _)| name.get_ref() == key)
+        .map(|(_, value)| value)
+}
+
+/// Items of an array of tables, or the value itself if it isn't an array.
+fn array_items<'a, 'i>(value: &'a Spanned<DeValue<'i>>) -> Vec<&'a Spanned<DeValue<'i>>> {
+    match value.get_ref().as_array() {
+        Some(array) => array.iter().collect(),
+        None => vec![value],
+    }
+}
+
+/// Whether `host` is an IP address or a syntactically valid host name.
+fn is_valid_host(host: &str) -> bool {
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// A socket the daemon binds, to find conflicting binds.
+struct Bind<'a> {
+    protocol: &'static str,
+    host: &'a str,
+    port: u16,
+    /// An IPv6 bind which leaves IPv4 to others.
+    only_v6: bool,
+    reuse_port: bool,
+    owner: String,
+}
+
+impl Bind<'_> {
+    /// Whether this and `other` get in each other's way.
+    fn overlaps(&self, other: &Bind) -> bool {
+        fn normalize(host: &str) -> String {
+            let host = host
+                .strip_prefix('[')
+                .and_then(|host| host.strip_suffix(']'))
+                .unwrap_or(host);
+            let host = if host.parse::<Ipv6Addr>().is_ok() { host } else { host.trim_end_matches(':') };
+            match host.to_ascii_lowercase().as_str() {
+                "localhost" => "127.0.0.1".to_string(),
+                host => host.to_string(),
+            }
         }
+
+        if self.protocol != other.protocol
+            || self.port != other.port
+            || (self.reuse_port && other.reuse_port)
+        {
+            return false;
+        }
+
+        let (a, b) = (normalize(self.host), normalize(other.host));
+        match (a.parse::<IpAddr>(), b.parse::<IpAddr>()) {
+            // Dual-stack binds on the IPv6 wildcard take the IPv4 port too
+            (Ok(a_ip), Ok(b_ip)) if a_ip.is_ipv6() != b_ip.is_ipv6() => {
+                let (v6, only_v6) = if a_ip.is_ipv6() {
+                    (a_ip, self.only_v6)
+                } else {
+                    (b_ip, other.only_v6)
+                };
+                v6.is_unspecified() && !only_v6
+            }
+            _ => {
+                let unspecified = |host: &str| host.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified());
+                a == b || unspecified(&a) || unspecified(&b)
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Parses a TOML config and validates it, see [`Config::validate`].
+    ///
+    /// Unlike deserializing the whole file at once, this doesn't stop at the
+    /// first problem: every section, interface and propagation rule is
+    /// checked and all problems are returned with the lines they are on.
+    pub fn parse(content: &str) -> Result<Self, Vec<ConfigProblem>> {
+        let mut source = Source::new(content);
+
+        let (root, errors) = DeTable::parse_recoverable(content);
+        if !errors.is_empty() {
+            for err in errors {
+                let problem = ConfigProblem {
+                    key: None,
+                    line: None,
+                    context: None,
+                    message: format!("invalid TOML: {}", err.message()),
+                };
+                source.report(err.span().unwrap_or(0..0), problem);
+            }
+            source.problems.sort_by_key(|problem| problem.line);
+            return Err(source.problems);
+        }
+
+        let mut config = Config::default();
+        let mut reticulum = None;
+        let mut logging = None;
+        let mut interfaces = Vec::new();
+        let mut propagation = Vec::new();
+        let mut static_paths = Vec::new();
+
+        for (key, value) in root.get_ref().iter() {
+            match key.get_ref().as_ref() {
+                "reticulum" => {
+                    reticulum = Some(value);
+                    if let Some(section) = source.deserialize(value, "[reticulum]") {
+                        config.reticulum = section;
+                    }
+                }
+                "logging" => {
+                    logging = Some(value);
+                    if let Some(section) = source.deserialize(value, "[logging]") {
+                        config.logging = section;
+                    }
+                }
+                "interfaces" => {
+                    for (index, item) in array_items(value).into_iter().enumerate() {
+                        let what = match table_entry(item, "name").and_then(|name| name.get_ref().as_str()) {
+                            Some(name) => format!("interface '{}'", name),
+                            None => format!("interface {}", index + 1),
+                        };
+                        if let Some(interface) = source.deserialize(item, &what) {
+                            config.interfaces.push(interface);
+                            interfaces.push(item);
+                        }
+                    }
+                }
+                "propagation" => {
+                    for (index, item) in array_items(value).into_iter().enumerate() {
+                        let what = format!("propagation rule {}", index + 1);
+                        if let Some(rule) = source.deserialize(item, &what) {
+                            config.propagation.push(rule);
+                            propagation.push(item);
+                        }
+                    }
+                }
+                "static_paths" => {
+                    for (index, item) in array_items(value).into_iter().enumerate() {
+                        let what = format!("static path {}", index + 1);
+                        if let Some(path) = source.deserialize(item, &what) {
+                            config.static_paths.push(path);
+                            static_paths.push(item);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        source.finish(config, |key| {
+            let span = match key {
+                ConfigKey::Reticulum(key) => reticulum
+                    .map(|section| table_entry(section, key).unwrap_or(section).span()),
+                ConfigKey::Logging(key) => logging
+                    .map(|section| table_entry(section, key).unwrap_or(section).span()),
+                ConfigKey::Interface(index, key) => interfaces.get(index).map(|item| {
+                    key.and_then(|key| table_entry(item, key)).unwrap_or(item).span()
+                }),
+                ConfigKey::Propagation(index) => propagation.get(index).map(|item| item.span()),
+                ConfigKey::StaticPath(index) => static_paths.get(index).map(|item| item.span()),
+            };
+            span.map(|span| Source::line_of(content, span.start))
+        })
+    }
+
+    /// Parses a config in the format of Python Reticulum and validates it,
+    /// see [`python`].
+    pub fn parse_python(content: &str) -> Result<Self, Vec<ConfigProblem>> {
+        python::parse(content)
+    }
+
+    /// Checks port ranges, addresses, interface names, binds of enabled
+    /// interfaces and option combinations the daemon doesn't support.
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        let mut report = |key: ConfigKey, message: String| problems.push(ConfigProblem::new(key, message));
+
+        let mut binds = Vec::new();
+        let reticulum = &self.reticulum;
+        if reticulum.share_instance {
+            if reticulum.instance_control_port == 0 {
+                report(
+                    ConfigKey::Reticulum("instance_control_port"),
+                    "[reticulum]: instance_control_port 0 is out of range, use 1 to 65535".to_string(),
+                );
+            } else {
+                binds.push(Bind {
+                    protocol: "TCP",
+                    host: "127.0.0.1",
+                    port: reticulum.instance_control_port,
+                    only_v6: false,
+                    reuse_port: false,
+                    owner: "the instance control port".to_string(),
+                });
+            }
+            if reticulum.shared_instance_port == reticulum.instance_control_port {
+                report(
+                    ConfigKey::Reticulum("instance_control_port"),
+                    "[reticulum]: instance_control_port is the same as shared_instance_port".to_string(),
+                );
+            }
+        }
+
+        for hash in &reticulum.remote_management_allowed {
+            if let Err(err) = hash.parse::<AddressHash>() {
+                report(
+                    ConfigKey::Reticulum("remote_management_allowed"),
+                    format!(
+                        "[reticulum]: '{}' in remote_management_allowed is not an identity hash: {}",
+                        hash, err
+                    ),
+                );
+            }
+        }
+        if reticulum.enable_remote_management && reticulum.remote_management_allowed.is_empty() {
+            report(
+                ConfigKey::Reticulum("remote_management_allowed"),
+                "[reticulum]: enable_remote_management is set, but no identity is allowed".to_string(),
+            );
+        }
+
+        let logging = &self.logging;
+        if logging.logfile.as_ref().is_some_and(|path| path.as_os_str().is_empty()) {
+            report(ConfigKey::Logging("logfile"), "[logging]: logfile is empty".to_string());
+        }
+        if logging.logfile_max_size == 0 {
+            report(
+                ConfigKey::Logging("logfile_max_size"),
+                "[logging]: logfile_max_size must be at least 1 byte".to_string(),
+            );
+        }
+        if let Some(mode) = &logging.decode_dumps
+            && mode.parse::<DecodeDumps>().is_err()
+        {
+            report(
+                ConfigKey::Logging("decode_dumps"),
+                format!("[logging]: unknown decode_dumps '{}', use \"off\", \"fields\" or \"hexdump\"", mode),
+            );
+        }
+        for module in logging.modules.keys() {
+            let valid = module.split("::").all(|part| {
+                !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if !valid {
+                report(
+                    ConfigKey::Logging("modules"),
+                    format!("[logging]: '{}' is not a module path like \"reticulum::transport\"", module),
+                );
+            }
+        }
+
+        let mut names = HashMap::new();
+        for (index, iface) in self.interfaces.iter().enumerate() {
+            let name = iface.name.as_str();
+            let mut report_key = |key: &'static str, message: String| {
+                report(ConfigKey::Interface(index, Some(key)), format!("interface '{}': {}", name, message))
+            };
+
+            if name.trim().is_empty() {
+                report_key("name", "name is empty".to_string());
+            } else if let Some(first) = names.get(name) {
+                report_key("name", format!("name is already used by interface {}", first + 1));
+            } else {
+                names.insert(name, index);
+            }
+
+            let mut check_host = |key: &'static str, host: &str| {
+                if !is_valid_host(host) && !is_valid_host(host.trim_end_matches(':')) {
+                    report_key(key, format!("'{}' is not a valid IP address or host name", host));
+                }
+            };
+            let mut ports = Vec::new();
+            let (enabled, bind) = match &iface.config {
+                InterfaceConfig::TCPServerInterface { enabled, bind_host, bind_port, bind, .. } => {
+                    check_host("bind_host", bind_host);
+                    for host in &bind.extra_bind_hosts {
+                        check_host("extra_bind_hosts", host);
+                    }
+                    ports.push(("bind_port", *bind_port));
+                    (*enabled, Some(("TCP", bind_host.as_str(), *bind_port, bind)))
+                }
+                InterfaceConfig::TCPClientInterface { enabled, target_host, target_port, .. } => {
+                    check_host("target_host", target_host);
+                    ports.push(("target_port", *target_port));
+                    (*enabled, None)
+                }
+                InterfaceConfig::UDPInterface {
+                    enabled, listen_ip, listen_port, forward_ip, forward_port, bind, ..
+                } => {
+                    check_host("listen_ip", listen_ip);
+                    for host in &bind.extra_bind_hosts {
+                        check_host("extra_bind_hosts", host);
+                    }
+                    check_host("forward_ip", forward_ip);
+                    ports.push(("listen_port", *listen_port));
+                    ports.push(("forward_port", *forward_port));
+                    (*enabled, Some(("UDP", listen_ip.as_str(), *listen_port, bind)))
+                }
+                _ => (false, None),
+            };
+
+            for (key, port) in ports {
+                if port == 0 {
+                    report_key(key, format!("{} 0 is out of range, use 1 to 65535", key));
+                }
+            }
+
+            if let InterfaceConfig::TCPClientInterface { rx_timeout: Some(0), .. } = iface.config {
+                report_key("rx_timeout", "rx_timeout must be at least 1 second".to_string());
+            }
+            if let InterfaceConfig::TCPClientInterface { connect_timeout: Some(0), .. } = iface.config {
+                report_key("connect_timeout", "connect_timeout must be at least 1 second".to_string());
+            }
+
+            if let Some(options) = iface.config.options() {
+                if let Some(mode) = &options.mode
+                    && mode.parse::<InterfaceMode>().is_err()
+                {
+                    report_key("mode", format!("unknown mode '{}'", mode));
+                }
+                for (key, secs) in [
+                    ("reconnect_delay", options.reconnect_delay),
+                    ("reconnect_max_delay", options.reconnect_max_delay),
+                ] {
+                    if secs.is_some_and(|secs| !secs.is_finite() || secs < 0.0) {
+                        report_key(key, format!("{} must be a positive number of seconds", key));
+                    }
+                }
+                if let (Some(delay), Some(max_delay)) = (options.reconnect_delay, options.reconnect_max_delay)
+                    && delay > max_delay
+                {
+                    report_key("reconnect_delay", "reconnect_delay is longer than reconnect_max_delay".to_string());
+                }
+                if options.reconnect_jitter.is_some_and(|jitter| !(0.0..=1.0).contains(&jitter)) {
+                    report_key("reconnect_jitter", "reconnect_jitter must be between 0 and 1".to_string());
+                }
+                if options.reconnect_attempts == Some(0) {
+                    report_key("reconnect_attempts", "reconnect_attempts must be at least 1".to_string());
+                }
+            }
+
+            let Some((protocol, host, port, options)) = bind.filter(|(_, _, port, _)| enabled && *port != 0) else {
+                continue;
+            };
+            let key = if protocol == "TCP" { "bind_port" } else { "listen_port" };
+            let hosts = std::iter::once(host).chain(options.extra_bind_hosts.iter().map(String::as_str));
+            for host in hosts {
+                let bind = Bind {
+                    protocol,
+                    host,
+                    port,
+                    only_v6: options.ipv6_only.unwrap_or(false),
+                    reuse_port: options.reuse_port,
+                    owner: format!("interface '{}'", name),
+                };
+                if let Some(other) = binds.iter().find(|other| other.overlaps(&bind)) {
+                    report_key(key, format!("{} {}:{} is also bound by {}", protocol, host, port, other.owner));
+                }
+                binds.push(bind);
+            }
+        }
+
+        let groups: Vec<&String> = self
+            .interfaces
+            .iter()
+            .filter_map(|iface| iface.config.options())
+            .flat_map(|options| &options.groups)
+            .collect();
+        for (index, rule) in self.propagation.iter().enumerate() {
+            let key = ConfigKey::Propagation(index);
+            if !reticulum.enable_transport {
+                report(
+                    key,
+                    format!("propagation rule {}: rules only apply with enable_transport set", index + 1),
+                );
+            }
+            let mut rule_groups = vec![&rule.from];
+            if rule.to != rule.from {
+                rule_groups.push(&rule.to);
+            }
+            for group in rule_groups {
+                if !groups.contains(&group) {
+                    report(
+                        key,
+                        format!("propagation rule {}: no interface is in group '{}'", index + 1, group),
+                    );
+                }
+            }
+        }
+
+        for (index, path) in self.static_paths.iter().enumerate() {
+            let key = ConfigKey::StaticPath(index);
+            if let Err(err) = path.destination.parse::<AddressHash>() {
+                report(
+                    key,
+                    format!(
+                        "static path {}: '{}' is not a destination hash: {}",
+                        index + 1,
+                        path.destination,
+                        err
+                    ),
+                );
+            }
+            if !self.interfaces.iter().any(|iface| iface.name == path.interface) {
+                report(
+                    key,
+                    format!("static path {}: there is no interface '{}'", index + 1, path.interface),
+                );
+            }
+            if path.hops == 0 || path.hops as usize >= PATHFINDER_M {
+                report(
+                    key,
+                    format!("static path {}: hops must be 1 to {}", index + 1, PATHFINDER_M - 1),
+                );
+            }
+        }
+
+        problems
     }
 }
 
@@ -422,3 +1218,79 @@ pub fn python_log_filter(loglevel: u8) -> log::LevelFilter {
         _ => log::LevelFilter::Trace,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config with a TCP client interface named "client", `options` are
+    /// added to the interface.
+    fn config_with(options: &str) -> Config {
+        let content = format!(
+            "[[interfaces]]\n\
+             name = \"client\"\n\
+             type = \"TCPClientInterface\"\n\
+             target_host = \"127.0.0.1\"\n\
+             target_port = 4242\n\
+             {}\n",
+            options
+        );
+        toml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn accepts_valid_configs() {
+        assert!(Config::default_config().validate().is_empty());
+
+        let config = config_with(
+            "reconnect_delay = 1.0\n\
+             reconnect_max_delay = 60.0\n\
+             reconnect_jitter = 0.5\n\
+             reconnect_attempts = 3",
+        );
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_reconnect_options() {
+        let cases = [
+            ("reconnect_delay = -1.0", "reconnect_delay", "reconnect_delay must be a positive number of seconds"),
+            ("reconnect_delay = nan", "reconnect_delay", "reconnect_delay must be a positive number of seconds"),
+            (
+                "reconnect_max_delay = -5.0",
+                "reconnect_max_delay",
+                "reconnect_max_delay must be a positive number of seconds",
+            ),
+            (
+                "reconnect_delay = 10.0\nreconnect_max_delay = 5.0",
+                "reconnect_delay",
+                "reconnect_delay is longer than reconnect_max_delay",
+            ),
+            ("reconnect_jitter = 1.5", "reconnect_jitter", "reconnect_jitter must be between 0 and 1"),
+            ("reconnect_jitter = -0.1", "reconnect_jitter", "reconnect_jitter must be between 0 and 1"),
+            ("reconnect_attempts = 0", "reconnect_attempts", "reconnect_attempts must be at least 1"),
+        ];
+
+        for (options, key, message) in cases {
+            let problems = config_with(options).validate();
+            assert_eq!(problems.len(), 1, "{}: {:?}", options, problems);
+            assert_eq!(problems[0].key, Some(ConfigKey::Interface(0, Some(key))), "{}", options);
+            assert_eq!(problems[0].message, format!("interface 'client': {}", message));
+        }
+    }
+
+    #[test]
+    fn reports_duplicate_interface_names() {
+        let mut config = config_with("");
+        config.interfaces.extend(config_with("").interfaces);
+        config.interfaces.extend(config_with("").interfaces);
+
+        let problems = config.validate();
+        let reported: Vec<_> = problems.iter().map(|problem| problem.key).collect();
+        assert_eq!(
+            reported,
+            vec![Some(ConfigKey::Interface(1, Some("name"))), Some(ConfigKey::Interface(2, Some("name")))]
+        );
+        assert_eq!(problems[0].message, "interface 'client': name is already used by interface 1");
+    }
+}
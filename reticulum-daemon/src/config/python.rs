@@ -0,0 +1,527 @@
+//! Reads the config format of Python Reticulum, as written by `rnsd`.
+//!
+//! Python Reticulum uses configobj: `[section]` headers with `[[subsection]]`
+//! headers nested in them, indented by convention only, `key = value` lines
+//! with unquoted strings, `Yes`/`No` or `True`/`False` booleans and `None`
+//! for unset values. Values are typed by what they look like, except for keys
+//! which always hold strings, so e.g. a numeric passphrase stays a string.
+
+use serde::de::DeserializeOwned;
+
+use super::{Config, ConfigKey, ConfigProblem, Source, python_log_filter};
+
+/// Keys whose values are strings even if they look like numbers or booleans.
+const STRING_KEYS: &[&str] = &[
+    "name",
+    "type",
+    "bind_host",
+    "listen_ip",
+    "target_host",
+    "forward_ip",
+    "peers",
+    "port",
+    "callsign",
+    "parity",
+    "instance_name",
+    "logfile",
+    "mode",
+    "interface_mode",
+    "ifac_netname",
+    "networkname",
+    "network_name",
+    "ifac_netkey",
+    "passphrase",
+    "pass_phrase",
+    "from",
+    "to",
+    "traffic",
+];
+
+/// Keys whose values are comma separated lists.
+const LIST_KEYS: &[&str] = &["groups", "remote_management_allowed", "extra_bind_hosts"];
+
+/// A `[section]` or `[[subsection]]` and the values in it.
+#[derive(Default)]
+struct Section {
+    name: String,
+    /// Line of the header, 0 for the values before the first header.
+    line: usize,
+    values: Vec<(String, usize, toml::Value)>,
+    sections: Vec<Section>,
+}
+
+impl Section {
+    fn new(name: String, line: usize) -> Self {
+        Self { name, line, ..Default::default() }
+    }
+
+    fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Line of `key`, or of the header if the section doesn't have it.
+    fn line_of(&self, key: &str) -> usize {
+        self.values
+            .iter()
+            .find(|(name, _, _)| name == key)
+            .map_or(self.line, |(_, line, _)| *line)
+    }
+
+    /// Values of the section, subsections become nested tables.
+    fn to_table(&self) -> toml::Table {
+        let values = self
+            .values
+            .iter()
+            .map(|(key, _, value)| (key.clone(), value.clone()));
+        let sections = self
+            .sections
+            .iter()
+            .map(|section| (section.name.clone(), toml::Value::Table(section.to_table())));
+        values.chain(sections).collect()
+    }
+}
+
+/// Removes quotes around a key, section name or value. Returns the text and
+/// whether it was quoted.
+fn unquote(text: &str) -> (&str, bool) {
+    for quote in ['"', '\''] {
+        if let Some(inner) = text.strip_prefix(quote).and_then(|text| text.strip_suffix(quote)) {
+            return (inner, true);
+        }
+    }
+    (text, false)
+}
+
+/// Splits a value from a trailing comment. Quoted values, and quoted items
+/// of lists, may contain `#`.
+fn strip_comment(value: &str) -> Result<&str, String> {
+    let mut quote = None;
+    // Quotes only count at the start of the value or of a list item
+    let mut item_start = true;
+    let mut end = value.len();
+    for (index, c) in value.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '#' => {
+                end = index;
+                break;
+            }
+            None if item_start && (c == '"' || c == '\'') => quote = Some(c),
+            None => item_start = c == ',' || (item_start && c.is_whitespace()),
+        }
+    }
+    if quote.is_some() {
+        return Err("unterminated quoted value".to_string());
+    }
+
+    let value = value[..end].trim();
+    if let Some(open) = value.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let inner = &value[1..];
+        let rest = inner[inner.find(open).unwrap_or(inner.len() - 1) + 1..].trim();
+        if !rest.is_empty() && !rest.starts_with(',') {
+            return Err(format!("unexpected '{}' after quoted value", rest));
+        }
+    }
+
+    Ok(value)
+}
+
+/// Types a value the way Reticulum reads it. Returns `None` for unset values.
+fn typed_value(key: &str, value: &str, quoted: bool) -> Option<toml::Value> {
+    if !quoted && matches!(value.to_ascii_lowercase().as_str(), "none" | "nil" | "null") {
+        return None;
+    }
+
+    if quoted || STRING_KEYS.contains(&key) {
+        return Some(toml::Value::String(value.to_string()));
+    }
+
+    if LIST_KEYS.contains(&key) {
+        let items = value
+            .split(',')
+            .map(|item| unquote(item.trim()).0)
+            .filter(|item| !item.is_empty())
+            .map(|item| toml::Value::String(item.to_string()));
+        return Some(toml::Value::Array(items.collect()));
+    }
+
+    if key == "loglevel" {
+        if let Ok(level) = value.parse::<u8>() {
+            return Some(toml::Value::String(python_log_filter(level).to_string()));
+        }
+        return Some(toml::Value::String(value.to_string()));
+    }
+
+    let value = match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => toml::Value::Boolean(true),
+        "false" | "no" | "off" => toml::Value::Boolean(false),
+        _ => match (value.parse::<i64>(), value.parse::<f64>()) {
+            (Ok(int), _) => toml::Value::Integer(int),
+            (_, Ok(float)) => toml::Value::Float(float),
+            _ => toml::Value::String(value.to_string()),
+        },
+    };
+    Some(value)
+}
+
+/// Parses the file into its sections. Lines which can't be read are
+/// reported and skipped.
+fn parse_sections(source: &mut Source) -> Section {
+    let mut root = Section::default();
+    // Index path from the root to the section new values go to
+    let mut path: Vec<usize> = Vec::new();
+
+    let content = source.content;
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut report = |message: String| {
+            source.report_line(line_number, ConfigProblem { key: None, line: None, context: None, message })
+        };
+
+        if line.starts_with('[') {
+            let header = line.split('#').next().unwrap_or_default().trim();
+            let depth = header.chars().take_while(|c| *c == '[').count();
+            let closing = header.chars().rev().take_while(|c| *c == ']').count();
+            if closing != depth {
+                report("section header has unbalanced brackets".to_string());
+                continue;
+            }
+            if depth > path.len() + 1 {
+                report(format!("section nested {} levels deep without a parent section", depth));
+                continue;
+            }
+
+            let name = unquote(header[depth..header.len() - depth].trim()).0;
+            path.truncate(depth - 1);
+            let mut parent = &mut root;
+            for &index in &path {
+                parent = &mut parent.sections[index];
+            }
+            if parent.sections.iter().any(|section| section.name == name) {
+                report(format!("section '{}' is defined twice", name));
+            }
+            parent.sections.push(Section::new(name.to_string(), line_number));
+            path.push(parent.sections.len() - 1);
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            report("expected 'key = value' or a section header".to_string());
+            continue;
+        };
+        let key = unquote(key.trim()).0;
+        let value = value.trim();
+        if value.starts_with("'''") || value.starts_with("\"\"\"") {
+            report("multi-line values are not supported".to_string());
+            continue;
+        }
+        let value = match strip_comment(value) {
+            Ok(value) => value,
+            Err(message) => {
+                report(message);
+                continue;
+            }
+        };
+
+        let (value, quoted) = unquote(value);
+        let mut section = &mut root;
+        for &index in &path {
+            section = &mut section.sections[index];
+        }
+        if section.values.iter().any(|(name, _, _)| name == key) {
+            report(format!("'{}' is set twice", key));
+            continue;
+        }
+        if let Some(value) = typed_value(key, value, quoted) {
+            section.values.push((key.to_string(), line_number, value));
+        }
+    }
+
+    root
+}
+
+impl Source<'_> {
+    fn deserialize_table<T: DeserializeOwned>(&mut self, table: toml::Table, line: usize, what: &str) -> Option<T> {
+        match toml::Value::Table(table).try_into() {
+            Ok(value) => Some(value),
+            Err(err) => {
+                let message = format!("{}: {}", what, err.message());
+                self.report_line(line, ConfigProblem { key: None, line: None, context: None, message });
+                None
+            }
+        }
+    }
+}
+
+pub(super) fn parse(content: &str) -> Result<Config, Vec<ConfigProblem>> {
+    let mut source = Source::new(content);
+    let root = parse_sections(&mut source);
+
+    let mut config = Config::default();
+
+    let reticulum = root.section("reticulum");
+    if let Some(section) = reticulum
+        && let Some(reticulum) = source.deserialize_table(section.to_table(), section.line, "[reticulum]")
+    {
+        config.reticulum = reticulum;
+    }
+
+    let logging = root.section("logging");
+    if let Some(section) = logging
+        && let Some(logging) = source.deserialize_table(section.to_table(), section.line, "[logging]")
+    {
+        config.logging = logging;
+    }
+
+    // Interfaces are named by their subsection
+    let mut interfaces = Vec::new();
+    for section in root.section("interfaces").map_or(&[][..], |section| &section.sections) {
+        let mut table = section.to_table();
+        table.insert("name".to_string(), toml::Value::String(section.name.clone()));
+
+        let what = format!("interface '{}'", section.name);
+        if let Some(interface) = source.deserialize_table(table, section.line, &what) {
+            config.interfaces.push(interface);
+            interfaces.push(section);
+        }
+    }
+
+    let mut propagation = Vec::new();
+    let rules = root.section("propagation").map_or(&[][..], |section| &section.sections);
+    for (index, section) in rules.iter().enumerate() {
+        let what = format!("propagation rule {}", index + 1);
+        if let Some(rule) = source.deserialize_table(section.to_table(), section.line, &what) {
+            config.propagation.push(rule);
+            propagation.push(section);
+        }
+    }
+
+    let mut static_paths = Vec::new();
+    let paths = root.section("static_paths").map_or(&[][..], |section| &section.sections);
+    for (index, section) in paths.iter().enumerate() {
+        let what = format!("static path {}", index + 1);
+        if let Some(path) = source.deserialize_table(section.to_table(), section.line, &what) {
+            config.static_paths.push(path);
+            static_paths.push(section);
+        }
+    }
+
+    source.finish(config, |key| match key {
+        ConfigKey::Reticulum(key) => reticulum.map(|section| section.line_of(key)),
+        ConfigKey::Logging(key) => logging.map(|section| section.line_of(key)),
+        ConfigKey::Interface(index, key) => interfaces
+            .get(index)
+            .map(|section| key.map_or(section.line, |key| section.line_of(key))),
+        ConfigKey::Propagation(index) => propagation.get(index).map(|section| section.line),
+        ConfigKey::StaticPath(index) => static_paths.get(index).map(|section| section.line),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::InterfaceConfig;
+
+    /// Parses `content` into sections and returns the lines with problems.
+    fn sections(content: &str) -> (Section, Vec<(usize, String)>) {
+        let mut source = Source::new(content);
+        let root = parse_sections(&mut source);
+        let problems = source
+            .problems
+            .into_iter()
+            .map(|problem| (problem.line.unwrap(), problem.message))
+            .collect();
+        (root, problems)
+    }
+
+    fn value<'s>(section: &'s Section, key: &str) -> Option<&'s toml::Value> {
+        section
+            .values
+            .iter()
+            .find(|(name, _, _)| name == key)
+            .map(|(_, _, value)| value)
+    }
+
+    #[test]
+    fn parses_rnsd_config() {
+        let config = parse(include_str!("../../../tests/rns-py-configs/udp/config")).unwrap();
+
+        assert!(config.reticulum.enable_transport);
+        assert!(config.reticulum.share_instance);
+        assert_eq!(config.reticulum.instance_name.as_deref(), Some("default"));
+        assert_eq!(config.logging.loglevel, log::LevelFilter::Info);
+
+        assert_eq!(config.interfaces.len(), 2);
+        assert_eq!(config.interfaces[0].name, "Default Interface");
+        assert!(matches!(config.interfaces[0].config, InterfaceConfig::AutoInterface { enabled: false, .. }));
+
+        assert_eq!(config.interfaces[1].name, "UDP Interface");
+        match &config.interfaces[1].config {
+            InterfaceConfig::UDPInterface { enabled, listen_ip, listen_port, forward_ip, forward_port, .. } => {
+                assert!(*enabled);
+                assert_eq!((listen_ip.as_str(), *listen_port), ("0.0.0.0", 4243));
+                assert_eq!((forward_ip.as_str(), *forward_port), ("127.0.0.1", 4242));
+            }
+            other => panic!("unexpected interface {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nests_subsections() {
+        let (root, problems) = sections(
+            "top = 1\n\
+             [interfaces]\n\
+             [[First]]\n\
+             port = 1\n\
+             [[[Inner]]]\n\
+             depth = 3\n\
+             [[Second]]\n\
+             port = 2\n\
+             [logging]\n\
+             loglevel = 7\n",
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        assert_eq!(value(&root, "top"), Some(&toml::Value::Integer(1)));
+        let interfaces = root.section("interfaces").unwrap();
+        assert_eq!(interfaces.line, 2);
+        assert!(interfaces.values.is_empty());
+
+        let first = interfaces.section("First").unwrap();
+        assert_eq!(value(first, "port"), Some(&toml::Value::String("1".to_string())));
+        assert_eq!(value(first.section("Inner").unwrap(), "depth"), Some(&toml::Value::Integer(3)));
+
+        // A shallower header closes the deeper sections
+        let second = interfaces.section("Second").unwrap();
+        assert_eq!(second.line, 7);
+        assert_eq!(value(second, "port"), Some(&toml::Value::String("2".to_string())));
+        assert!(second.section("Inner").is_none());
+        assert!(root.section("logging").is_some());
+    }
+
+    #[test]
+    fn strips_comments_and_quotes() {
+        let (root, problems) = sections(
+            "# comment\n\
+             [reticulum] # trailing comment\n\
+             \x20 # indented comment\n\
+             count = 5 # five\n\
+             hash = \"a # b\"\n\
+             single = 'quoted'\n\
+             owner = Bob's node # not a quote\n\
+             number = \"42\"\n\
+             \"quoted key\" = value\n\
+             unset = None\n\
+             unset_quoted = 'None'\n\
+             remote_management_allowed = 'abc', \"def\",\n",
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        let section = root.section("reticulum").unwrap();
+        assert_eq!(value(section, "count"), Some(&toml::Value::Integer(5)));
+        assert_eq!(value(section, "hash"), Some(&toml::Value::String("a # b".to_string())));
+        assert_eq!(value(section, "single"), Some(&toml::Value::String("quoted".to_string())));
+        assert_eq!(value(section, "owner"), Some(&toml::Value::String("Bob's node".to_string())));
+        assert_eq!(value(section, "number"), Some(&toml::Value::String("42".to_string())));
+        assert_eq!(value(section, "quoted key"), Some(&toml::Value::String("value".to_string())));
+        assert_eq!(value(section, "unset"), None);
+        assert_eq!(value(section, "unset_quoted"), Some(&toml::Value::String("None".to_string())));
+
+        let allowed = toml::Value::Array(vec![
+            toml::Value::String("abc".to_string()),
+            toml::Value::String("def".to_string()),
+        ]);
+        assert_eq!(value(section, "remote_management_allowed"), Some(&allowed));
+    }
+
+    #[test]
+    fn reads_python_booleans() {
+        let (root, problems) = sections(
+            "[reticulum]\n\
+             a = yes\n\
+             b = No\n\
+             c = True\n\
+             d = false\n\
+             e = on\n\
+             f = OFF\n\
+             g = 'yes'\n\
+             passphrase = yes\n",
+        );
+        assert!(problems.is_empty(), "{:?}", problems);
+
+        let section = root.section("reticulum").unwrap();
+        for (key, expected) in [("a", true), ("b", false), ("c", true), ("d", false), ("e", true), ("f", false)] {
+            assert_eq!(value(section, key), Some(&toml::Value::Boolean(expected)), "{}", key);
+        }
+        // Quoted values and string keys stay strings
+        assert_eq!(value(section, "g"), Some(&toml::Value::String("yes".to_string())));
+        assert_eq!(value(section, "passphrase"), Some(&toml::Value::String("yes".to_string())));
+
+        let config = parse("[reticulum]\nenable_transport = Yes\nshare_instance = False\n").unwrap();
+        assert!(config.reticulum.enable_transport);
+        assert!(!config.reticulum.share_instance);
+    }
+
+    #[test]
+    fn reports_duplicates() {
+        let (root, problems) = sections(
+            "[reticulum]\n\
+             enable_transport = yes\n\
+             enable_transport = no\n\
+             [interfaces]\n\
+             [[Twice]]\n\
+             [[Twice]]\n",
+        );
+        assert_eq!(
+            problems,
+            vec![
+                (3, "'enable_transport' is set twice".to_string()),
+                (6, "section 'Twice' is defined twice".to_string()),
+            ]
+        );
+
+        // The first value wins
+        let section = root.section("reticulum").unwrap();
+        assert_eq!(value(section, "enable_transport"), Some(&toml::Value::Boolean(true)));
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let (root, problems) = sections(
+            "[reticulum\n\
+             [[orphan]]]\n\
+             [[[deep]]]\n\
+             just words\n\
+             open = \"unterminated\n\
+             after = \"quoted\" tail\n\
+             multi = '''first\n\
+             fine = 1\n",
+        );
+        assert_eq!(
+            problems,
+            vec![
+                (1, "section header has unbalanced brackets".to_string()),
+                (2, "section header has unbalanced brackets".to_string()),
+                (3, "section nested 3 levels deep without a parent section".to_string()),
+                (4, "expected 'key = value' or a section header".to_string()),
+                (5, "unterminated quoted value".to_string()),
+                (6, "unexpected 'tail' after quoted value".to_string()),
+                (7, "multi-line values are not supported".to_string()),
+            ]
+        );
+
+        // Parsing goes on after bad lines
+        assert_eq!(value(&root, "fine"), Some(&toml::Value::Integer(1)));
+
+        let problems = parse("[reticulum]\nshare_instance = maybe\n").unwrap_err();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(1));
+    }
+}
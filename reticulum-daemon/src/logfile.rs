@@ -0,0 +1,115 @@
+//! Log file of the daemon.
+//!
+//! The daemon logs to the file set as `logfile` in the `[logging]` section,
+//! or like rnsd to `logfile` in the config directory when running with
+//! `--service`. The file is rotated once it grows past a size or gets older
+//! than an interval: it is moved to `logfile.1`, older rotations move one
+//! number up and the oldest one is dropped.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+pub const LOG_FILE: &str = "logfile";
+
+/// When log files are rotated and how many are kept.
+#[derive(Debug, Clone, Copy)]
+pub struct Rotation {
+    /// Size in bytes after which the file is rotated.
+    pub max_size: u64,
+    /// Age after which the file is rotated whatever its size.
+    pub interval: Option<Duration>,
+    /// Rotated files which are kept.
+    pub backups: u32,
+}
+
+pub struct RotatingLog {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    /// When the current file was started.
+    started: SystemTime,
+}
+
+impl RotatingLog {
+    /// Opens the log file at `path`, appending to what is already there.
+    /// Missing directories are created.
+    pub fn open(path: PathBuf, rotation: Rotation) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let started = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or_else(|_| SystemTime::now());
+
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size: metadata.len(),
+            started,
+        })
+    }
+
+    /// Path of the rotated file with the given number.
+    fn backup_path(&self, number: u32) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{}", number));
+        PathBuf::from(path)
+    }
+
+    fn needs_rotation(&self, len: usize) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        let too_old = self.rotation.interval.is_some_and(|interval| {
+            self.started.elapsed().is_ok_and(|age| age >= interval)
+        });
+
+        too_old || self.size + len as u64 > self.rotation.max_size
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.rotation.backups == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for number in (1..self.rotation.backups).rev() {
+                let from = self.backup_path(number);
+                if from.exists() {
+                    fs::rename(from, self.backup_path(number + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.backup_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        self.started = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Write for RotatingLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.needs_rotation(buf.len()) {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
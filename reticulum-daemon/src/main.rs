@@ -1,27 +1,64 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
 use rand_core::OsRng;
+use reticulum::control;
+use reticulum::destination::DestinationName;
+use reticulum::diagnostics;
+use reticulum::hash::AddressHash;
 use reticulum::identity::PrivateIdentity;
-use reticulum::iface::tcp_client::TcpClient;
+use reticulum::iface::backoff::BackoffConfig;
+use reticulum::iface::tcp_client::{TcpClient, TcpKeepalive};
 use reticulum::iface::tcp_server::TcpServer;
+use reticulum::iface::propagation::{PropagationRule, PropagationScope};
+use reticulum::iface::socket::{join_host_port, SocketOptions};
 use reticulum::iface::udp::UdpInterface;
-use reticulum::transport::TransportConfig;
+use reticulum::iface::{InterfaceEvent, InterfaceManager, InterfaceMode, InterfaceState};
+use reticulum::management;
+use reticulum::transport::{Transport, TransportConfig, TransportEvent};
+use tokio::net::TcpListener;
 use tokio::signal;
+use tokio::sync::{broadcast, Mutex};
+use tokio_util::sync::CancellationToken;
 
 mod config;
-use self::config::{Config, InterfaceConfig};
+mod logfile;
+mod status;
+use self::config::{
+    BindOptions, Config, InterfaceConfig, InterfaceOptions, LoggingConfig, NamedInterface, PropagationRuleConfig,
+    PropagationTraffic, ReticulumConfig, StaticPathConfig,
+};
 
 /// Reticulum-rs daemon
 #[derive(Parser)]
 #[clap(version)]
-#[clap(args_conflicts_with_subcommands=true)]
 pub struct Command {
     /// Reticulum config directory
-    #[arg(short, long)]
+    #[arg(short, long = "config", alias = "config-dir", global = true)]
     pub config_dir: Option<PathBuf>,
+    /// Check the config for problems, print them and exit
+    #[arg(long)]
+    pub check_config: bool,
+    /// Print an example config explaining all options and exit
+    #[arg(long)]
+    pub exampleconfig: bool,
+    /// Log more than the config says, can be given multiple times
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Log less than the config says, can be given multiple times
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub quiet: u8,
+    /// Run as a service and log to a file, "logfile" in the config directory
+    /// unless the config sets another
+    #[arg(short, long)]
+    pub service: bool,
     #[command(subcommand)]
-    pub convert_config: Option<Subcommand>,
+    pub subcommand: Option<Subcommand>,
 }
 
 #[derive(clap::Subcommand)]
@@ -30,37 +67,256 @@ pub enum Subcommand {
     ConvertConfig {
         /// Path to the Python Reticulum config file
         config_file: PathBuf
+    },
+    /// Bring up the configured interfaces, announce a destination and print
+    /// what is sent to it
+    Announce {
+        /// Full name of the destination, e.g. "example_utilities.echo"
+        #[arg(long)]
+        dest: String,
+        /// App data to include in the announce
+        #[arg(long)]
+        app_data: Option<String>,
+        /// Announce again every given number of seconds
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+    /// Bring up the configured interfaces and print received announces
+    Listen {
+        /// Only print announces of destinations with this full name, e.g.
+        /// "lxmf.delivery"
+        #[arg(long)]
+        aspect: Option<String>,
+    },
+    /// Print the hash of a destination like `rnid -H` does
+    DestinationHash {
+        /// Full name of the destination, e.g. "lxmf.delivery"
+        name: String,
+        /// Identity file of the destination, plain destinations have none
+        #[arg(short, long)]
+        identity: Option<PathBuf>,
+    },
+}
+
+/// Example config printed by `--exampleconfig`.
+const EXAMPLE_CONFIG: &str = include_str!("example_config.toml");
+
+/// Level of the config raised by `verbose` and lowered by `quiet` steps.
+fn log_level(level: log::LevelFilter, verbose: u8, quiet: u8) -> log::LevelFilter {
+    let index = (level as usize + verbose as usize).saturating_sub(quiet as usize);
+    log::LevelFilter::iter()
+        .nth(index)
+        .unwrap_or(log::LevelFilter::max())
+}
+
+/// Logs to stderr, or to `log_file` if set. `RUST_LOG` overrides `level` and
+/// the module levels of the config.
+fn init_logging(
+    config: &LoggingConfig,
+    level: log::LevelFilter,
+    log_file: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut filters = vec![format!("{:?}", level)];
+    filters.extend(config.modules.iter().map(|(module, level)| format!("{}={:?}", module, level)));
+
+    let mut builder = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or(filters.join(","))
+    );
+    if let Some(path) = log_file {
+        let rotation = logfile::Rotation {
+            max_size: config.logfile_max_size,
+            interval: (config.logfile_rotate_interval > 0)
+                .then(|| Duration::from_secs(config.logfile_rotate_interval)),
+            backups: config.logfile_backups,
+        };
+        let log = logfile::RotatingLog::open(path.clone(), rotation)
+            .map_err(|err| format!("couldn't open log file {}: {}", path.display(), err))?;
+        builder
+            .target(env_logger::Target::Pipe(Box::new(log)))
+            .write_style(env_logger::WriteStyle::Never);
     }
+    builder.init();
+
+    if let Some(mode) = &config.decode_dumps {
+        diagnostics::set_decode_dumps(mode.parse().unwrap_or_default());
+    }
+
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cmd = Command::parse();
-    if let Some(subcommand) = cmd.convert_config {
-        match subcommand {
-            Subcommand::ConvertConfig { config_file } => return config::migrate_config(&config_file)
+/// Prints all problems of the config in the given or default config
+/// directory. Exits with status 1 if there are any.
+fn check_config(config_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = config_dir.map(Path::to_path_buf).or_else(Config::find_existing) else {
+        let paths: Vec<_> = Config::search_paths().iter().map(|path| path.display().to_string()).collect();
+        return Err(format!("no config found in {}", paths.join(", ")).into());
+    };
+
+    match Config::from_file(&path) {
+        Ok(_) => {
+            println!("Configuration in {} is valid", path.display());
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
         }
     }
+}
 
-    let (config, config_path) = Config::load(cmd.config_dir.as_deref())?;
-    env_logger::Builder::from_env(
-        env_logger::Env::default().default_filter_or(format!("{:?}", config.logging.loglevel))
-    ).init();
+/// Reports interface options which are parsed but not applied yet.
+fn check_options(name: &str, options: &InterfaceOptions) {
+    if options.ifac_netname.is_some() || options.ifac_netkey.is_some() {
+        log::warn!("Interface '{}': interface access codes are not supported, traffic will not be authenticated", name);
+    }
+    if options.bitrate.is_some() {
+        log::info!("Interface '{}': option 'bitrate' is ignored", name);
+    }
+    if options.announce_cap.is_some() {
+        log::info!("Interface '{}': option 'announce_cap' is ignored", name);
+    }
+    for key in options.other.keys() {
+        log::debug!("Interface '{}': unknown option '{}' is ignored", name, key);
+    }
+}
 
-    log::info!("Configuration loaded from: {}", config_path.display());
-    log::info!("Reticulum daemon starting");
+/// Reconnect policy of an interface, defaults where options are missing.
+fn backoff_config(options: &InterfaceOptions) -> BackoffConfig {
+    let mut backoff = BackoffConfig::default();
+    if let Some(secs) = options.reconnect_delay {
+        backoff.initial = Duration::from_secs_f64(secs.max(0.0));
+    }
+    if let Some(secs) = options.reconnect_max_delay {
+        backoff.max = Duration::from_secs_f64(secs.max(0.0));
+    }
+    if let Some(jitter) = options.reconnect_jitter {
+        backoff.jitter = jitter;
+    }
+    backoff.set_max_attempts(options.reconnect_attempts)
+}
+
+fn socket_options(bind: &BindOptions) -> SocketOptions {
+    SocketOptions {
+        reuse_address: bind.reuse_address,
+        reuse_port: bind.reuse_port,
+        only_v6: bind.ipv6_only,
+    }
+}
+
+/// Logs interfaces which give up reconnecting or stall. Returns the name of the
+/// first one if `panic_on_interface_error` is set, never returns otherwise.
+async fn watch_interfaces(
+    mut events: broadcast::Receiver<InterfaceEvent>,
+    iface_manager: Arc<Mutex<InterfaceManager>>,
+    panic_on_error: bool,
+) -> String {
+    loop {
+        let (address, state) = match events.recv().await {
+            Ok(InterfaceEvent::State(address, state)) => (address, state),
+            Ok(InterfaceEvent::Stalled(address)) => {
+                let name = iface_manager.lock().await.display_name(&address);
+                log::warn!("Interface '{}' doesn't take packets, they are dropped for now", name);
+                continue;
+            }
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => std::future::pending().await,
+        };
 
-    let identity = PrivateIdentity::new_from_rand(OsRng);
-    let transport = TransportConfig::new(
-            "rns-daemon",
-            &identity,
-            config.reticulum.enable_transport)
-        .set_retransmit(config.reticulum.enable_transport)
+        let name = iface_manager.lock().await.display_name(&address);
+
+        match state {
+            InterfaceState::Reconnecting { attempt, delay } => log::debug!(
+                "Interface '{}': attempt {} failed, retrying in {:.1}s",
+                name,
+                attempt,
+                delay.as_secs_f64()
+            ),
+            InterfaceState::Failed => {
+                log::error!("Interface '{}' failed and was stopped", name);
+                if panic_on_error {
+                    return name;
+                }
+            }
+            InterfaceState::Connected | InterfaceState::Disconnected => {}
+        }
+    }
+}
+
+/// File the identity of the transport is kept in, relative to the config
+/// directory. Python Reticulum keeps it in the same place.
+const IDENTITY_FILE: &str = "storage/transport_identity";
+
+/// Loads the identity of the transport from `path`, or creates and saves a
+/// new one if there is none yet.
+fn load_identity(path: &Path) -> io::Result<PrivateIdentity> {
+    match PrivateIdentity::from_file(path) {
+        Ok(identity) => Ok(identity),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let identity = PrivateIdentity::new_from_rand(OsRng);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            identity.to_file(path)?;
+            log::info!("Created transport identity {}", identity.address_hash());
+            Ok(identity)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Prints the hash of destination `name` of the identity in file
+/// `identity`, in brackets as Python Reticulum prints hashes.
+fn print_destination_hash(name: &str, identity: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    if name.is_empty() || name.split('.').any(str::is_empty) {
+        return Err(format!("'{}' is not a destination name like \"app.aspect\"", name).into());
+    }
+
+    let identity = match identity {
+        Some(path) => Some(
+            PrivateIdentity::from_file(path)
+                .map_err(|err| format!("couldn't read identity {}: {}", path.display(), err))?,
+        ),
+        None => None,
+    };
+
+    let identity = identity.as_ref().map(PrivateIdentity::as_identity);
+    println!("{}", DestinationName::hash_from_name_and_identity(name, identity));
+
+    Ok(())
+}
+
+/// How long the daemon waits at startup for its TCP servers to listen.
+const SERVER_BIND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Creates the transport and spawns the enabled interfaces under their
+/// names from the config.
+async fn start_transport(
+    config: &ReticulumConfig,
+    identity: &PrivateIdentity,
+    interfaces: Vec<NamedInterface>,
+    propagation: &[PropagationRuleConfig],
+    static_paths: &[StaticPathConfig],
+) -> Transport {
+    let transport = TransportConfig::new("rns-daemon", identity, false)
+        .set_transport_enabled(config.enable_transport)
         .build();
 
     let iface_manager = transport.iface_manager();
+    let mut servers = Vec::new();
+    let mut addresses = HashMap::new();
+
+    for rule in propagation {
+        let (scope, traffic) = match rule.traffic {
+            PropagationTraffic::Announces => (PropagationScope::Announces, "announces"),
+            PropagationTraffic::All => (PropagationScope::All, "broadcasts"),
+        };
+        log::info!("Keeping {} from group '{}' off group '{}'", traffic, rule.from, rule.to);
+        iface_manager.lock().await.add_propagation_rule(
+            PropagationRule::new(rule.from.as_str(), rule.to.as_str(), scope)
+        );
+    }
 
-    for iface in config.interfaces {
+    for iface in interfaces {
         let enabled = match &iface.config {
             InterfaceConfig::TCPServerInterface { enabled, .. } => *enabled,
             InterfaceConfig::TCPClientInterface { enabled, .. } => *enabled,
@@ -78,61 +334,418 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             continue;
         }
 
-        match iface.config {
-            InterfaceConfig::TCPServerInterface { bind_host, bind_port, .. } => {
-                let addr = format!("{}:{}", bind_host.trim_end_matches(':'), bind_port);
+        if let Some(options) = iface.config.options() {
+            check_options(&iface.name, options);
+        }
+
+        let backoff = iface.config.options().map(backoff_config).unwrap_or_default();
+        let groups = iface.config.options().map(|options| options.groups.clone()).unwrap_or_default();
+
+        let mode = iface.config.options()
+            .and_then(|options| options.mode.as_deref())
+            .map(|mode| mode.parse::<InterfaceMode>().unwrap_or_else(|_| {
+                log::warn!("Interface '{}': unknown mode '{}', using full mode", iface.name, mode);
+                InterfaceMode::Full
+            }))
+            .unwrap_or_default();
+
+        let handle = match iface.config {
+            InterfaceConfig::TCPServerInterface { bind_host, bind_port, kiss_framing, bind, .. } => {
+                let addr = join_host_port(&bind_host, bind_port);
                 log::info!("Enabling interface '{}': TCP Server on {}", iface.name, addr);
-                iface_manager.lock().await.spawn(
-                    TcpServer::new(addr, iface_manager.clone()),
+                let mut server = TcpServer::new(addr, iface_manager.clone())
+                    .set_kiss_framing(kiss_framing)
+                    .set_backoff(backoff)
+                    .set_socket_options(socket_options(&bind));
+                for host in &bind.extra_bind_hosts {
+                    let addr = join_host_port(host, bind_port);
+                    log::info!("Interface '{}': TCP Server on {} as well", iface.name, addr);
+                    server = server.add_bind_addr(addr);
+                }
+                let handle = iface_manager.lock().await.spawn_named(
+                    iface.name.clone(),
+                    server,
+                    mode,
                     TcpServer::spawn,
                 );
+                servers.push((iface.name.clone(), handle.clone()));
+                Some(handle)
             }
-            InterfaceConfig::TCPClientInterface { target_host, target_port, .. } => {
-                let addr = format!("{}:{}", target_host.trim_end_matches(':'), target_port);
+            InterfaceConfig::TCPClientInterface {
+                target_host, target_port, kiss_framing, ping_interval, rx_timeout, connect_timeout, ..
+            } => {
+                let addr = join_host_port(&target_host, target_port);
                 log::info!("Enabling interface '{}': TCP Client to {}", iface.name, addr);
-                iface_manager.lock().await.spawn(
-                    TcpClient::new(addr),
+                let mut keepalive = TcpKeepalive::default();
+                if let Some(secs) = ping_interval {
+                    keepalive.ping_interval = (secs > 0).then(|| Duration::from_secs(secs));
+                }
+                keepalive.rx_timeout = rx_timeout.map(Duration::from_secs);
+                let mut client = TcpClient::new(addr)
+                    .set_kiss_framing(kiss_framing)
+                    .set_keepalive(keepalive)
+                    .set_backoff(backoff);
+                if let Some(secs) = connect_timeout {
+                    client = client.set_connect_timeout(Duration::from_secs(secs));
+                }
+                let handle = iface_manager.lock().await.spawn_named(
+                    iface.name.clone(),
+                    client,
+                    mode,
                     TcpClient::spawn,
                 );
+                Some(handle)
             }
-            InterfaceConfig::UDPInterface { listen_ip, listen_port, forward_ip, forward_port, .. } => {
-                let bind_addr = format!("{}:{}", listen_ip, listen_port);
-                let forward_addr = format!("{}:{}", forward_ip, forward_port);
+            InterfaceConfig::UDPInterface { listen_ip, listen_port, forward_ip, forward_port, bind, .. } => {
+                let bind_addr = join_host_port(&listen_ip, listen_port);
+                let forward_addr = join_host_port(&forward_ip, forward_port);
                 log::info!("Enabling interface '{}': UDP {}→{}", iface.name, bind_addr, forward_addr);
-                iface_manager.lock().await.spawn(
-                    UdpInterface::new(bind_addr, Some(forward_addr), false),
+                let mut udp = UdpInterface::new(bind_addr, Some(forward_addr), false)
+                    .set_backoff(backoff)
+                    .set_socket_options(socket_options(&bind));
+                for host in &bind.extra_bind_hosts {
+                    udp = udp.add_bind_addr(join_host_port(host, listen_port));
+                }
+                let handle = iface_manager.lock().await.spawn_named(
+                    iface.name.clone(),
+                    udp,
+                    mode,
                     UdpInterface::spawn,
                 );
+                Some(handle)
             }
             InterfaceConfig::AutoInterface { .. } => {
                 log::warn!("Interface '{}' type 'AutoInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::I2PInterface { .. } => {
                 log::warn!("Interface '{}' type 'I2PInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::RNodeInterface { .. } => {
                 log::warn!("Interface '{}' type 'RNodeInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::BLEInterface { .. } => {
                 log::warn!("Interface '{}' type 'BLEInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::KISSInterface { .. } => {
                 log::warn!("Interface '{}' type 'KISSInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::AX25KISSInterface { .. } => {
                 log::warn!("Interface '{}' type 'AX25KISSInterface' is not yet supported", iface.name);
+                None
             }
             InterfaceConfig::Unsupported => {
                 log::warn!("Interface '{}' uses an unsupported type", iface.name);
+                None
             }
+        };
+
+        if let Some(handle) = &handle {
+            addresses.insert(iface.name.clone(), handle.address());
+        }
+        if let Some(handle) = handle.filter(|_| !groups.is_empty()) {
+            iface_manager.lock().await.set_groups(&handle.address(), groups);
+        }
+    }
+
+    for path in static_paths {
+        let Ok(destination) = path.destination.parse::<AddressHash>() else {
+            continue;
+        };
+        match addresses.get(&path.interface) {
+            Some(iface) => transport.add_static_path(destination, *iface, path.hops).await,
+            None => log::warn!(
+                "Interface '{}' isn't running, no static path to {}",
+                path.interface,
+                destination
+            ),
         }
     }
 
+    // Local programs started along with the daemon connect to its servers
+    for (name, server) in servers {
+        if !matches!(tokio::time::timeout(SERVER_BIND_TIMEOUT, server.await_ready()).await, Ok(Ok(()))) {
+            log::warn!("Interface '{}' is not listening yet", name);
+        }
+    }
+
+    transport
+}
+
+/// Prints app data as text if it is printable, in hex otherwise.
+fn format_app_data(app_data: &[u8]) -> String {
+    match std::str::from_utf8(app_data) {
+        Ok(text) if !text.chars().any(char::is_control) => format!("{:?}", text),
+        _ => app_data.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+async fn announce(
+    transport: Transport,
+    dest: &str,
+    app_data: Option<String>,
+    interval: Option<u64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let destination = transport
+        .add_destination(PrivateIdentity::new_from_rand(OsRng), DestinationName::new_from_full_name(dest))
+        .await;
+    let address = destination.lock().await.desc.address_hash;
+    let mut events = transport.events_for_destination(address);
+
+    let app_data = app_data.as_deref().map(str::as_bytes);
+    println!("announcing {} as {}", dest, address);
+    transport.send_announce(&destination, app_data).await?;
+
+    // An interval of zero would announce in a busy loop
+    let period = interval.map(|secs| Duration::from_secs(secs.max(1)));
+    let mut reannounce = period.map(|period| {
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    });
+
+    loop {
+        tokio::select! {
+            _ = signal::ctrl_c() => break,
+            _ = async { reannounce.as_mut().unwrap().tick().await }, if reannounce.is_some() => {
+                match transport.send_announce(&destination, app_data).await {
+                    Ok(()) => println!("announced {} again", address),
+                    Err(err) => eprintln!("couldn't announce {}: {}", address, err),
+                }
+            }
+            event = events.recv() => match event {
+                Ok(TransportEvent::DataReceived(data)) => println!(
+                    "data from iface {}: {}",
+                    data.iface,
+                    format_app_data(data.data.as_slice())
+                ),
+                Ok(TransportEvent::LinkActivated { id, .. }) => println!("link {} activated", id),
+                Ok(TransportEvent::LinkClosed { id, .. }) => println!("link {} closed", id),
+                Ok(TransportEvent::LinkReestablished { old, new, .. }) => {
+                    println!("link {} reestablished as {}", old, new)
+                }
+                Ok(_) => {}
+                Err(err) => log::warn!("missed events: {}", err),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+async fn listen(transport: Transport, aspect: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let name_hash = aspect
+        .as_deref()
+        .map(|aspect| DestinationName::new_from_full_name(aspect).as_name_hash_slice().to_vec());
+    let mut events = transport.events_filtered(TransportEvent::is_announce);
+
+    println!("listening for announces");
+
+    loop {
+        let event = tokio::select! {
+            _ = signal::ctrl_c() => break,
+            event = events.recv() => event,
+        };
+
+        let announce = match event {
+            Ok(TransportEvent::AnnounceReceived(announce)) => announce,
+            Ok(_) => continue,
+            Err(err) => {
+                log::warn!("missed events: {}", err);
+                continue;
+            }
+        };
+
+        let destination = announce.destination.lock().await;
+        if name_hash.as_deref().is_some_and(|hash| hash != destination.desc.name.as_name_hash_slice()) {
+            continue;
+        }
+
+        println!(
+            "announce from {} app data {}",
+            destination.desc.address_hash,
+            format_app_data(announce.app_data.as_slice())
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cmd = Command::parse();
+    if let Some(Subcommand::ConvertConfig { config_file }) = &cmd.subcommand {
+        return config::migrate_config(config_file);
+    }
+    if let Some(Subcommand::DestinationHash { name, identity }) = &cmd.subcommand {
+        if let Err(err) = print_destination_hash(name, identity.as_deref()) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if cmd.exampleconfig {
+        print!("{}", EXAMPLE_CONFIG);
+        return Ok(());
+    }
+    if cmd.check_config {
+        return check_config(cmd.config_dir.as_deref());
+    }
+
+    let (config, config_path) = match Config::load(cmd.config_dir.as_deref()) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let log_file = match &config.logging.logfile {
+        Some(path) => Some(config_path.join(path)),
+        None => cmd.service.then(|| config_path.join(logfile::LOG_FILE)),
+    };
+    if let Some(path) = &log_file {
+        println!("Logging to {}", path.display());
+    }
+    init_logging(
+        &config.logging,
+        log_level(config.logging.loglevel, cmd.verbose, cmd.quiet),
+        log_file,
+    )?;
+
+    log::info!("Configuration loaded from: {}", config_path.display());
+
+    // The management destination is derived from the identity, so it has to
+    // stay the same across restarts
+    let identity = if config.reticulum.enable_remote_management {
+        let path = config_path.join(IDENTITY_FILE);
+        match load_identity(&path) {
+            Ok(identity) => identity,
+            Err(err) => {
+                eprintln!("Couldn't load the transport identity from {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        PrivateIdentity::new_from_rand(OsRng)
+    };
+
+    match cmd.subcommand {
+        Some(Subcommand::Announce { dest, app_data, interval }) => {
+            let transport = start_transport(
+                &config.reticulum,
+                &identity,
+                config.interfaces,
+                &config.propagation,
+                &config.static_paths,
+            )
+            .await;
+            return announce(transport, &dest, app_data, interval).await;
+        }
+        Some(Subcommand::Listen { aspect }) => {
+            let transport = start_transport(
+                &config.reticulum,
+                &identity,
+                config.interfaces,
+                &config.propagation,
+                &config.static_paths,
+            )
+            .await;
+            return listen(transport, aspect).await;
+        }
+        _ => {}
+    }
+
+    log::info!("Reticulum daemon starting");
+
+    let transport = start_transport(
+        &config.reticulum,
+        &identity,
+        config.interfaces,
+        &config.propagation,
+        &config.static_paths,
+    )
+    .await;
+    let transport = Arc::new(transport);
+    let iface_events = transport.iface_manager().lock().await.events();
+    let watch_task = watch_interfaces(
+        iface_events,
+        transport.iface_manager(),
+        config.reticulum.panic_on_interface_error,
+    );
+
+    let control_cancel = CancellationToken::new();
+    let control_task = if config.reticulum.share_instance {
+        let port = config.reticulum.instance_control_port;
+        match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => {
+                log::info!("Control port listening on 127.0.0.1:{}", port);
+                Some(tokio::spawn(
+                    control::Server::new(transport.clone()).run(listener, control_cancel.clone())
+                ))
+            }
+            Err(err) => {
+                log::warn!("Couldn't open control port 127.0.0.1:{}: {}", port, err);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let management_cancel = CancellationToken::new();
+    let management_task = if config.reticulum.enable_remote_management {
+        let allowed = config
+            .reticulum
+            .remote_management_allowed
+            .iter()
+            .filter_map(|hash| hash.parse::<AddressHash>().ok())
+            .collect();
+        let server = management::Server::new(transport.clone(), identity.clone(), allowed);
+        Some(tokio::spawn(server.run(management_cancel.clone())))
+    } else {
+        None
+    };
+
+    let status_cancel = CancellationToken::new();
+    let status_path = config_path.join(status::STATUS_FILE);
+    log::info!("Writing status to {}", status_path.display());
+    let status_task = tokio::spawn(status::run(
+        transport.clone(),
+        status_path,
+        config.reticulum.status_interval,
+        status_cancel.clone(),
+    ));
+
     log::info!("Reticulum instance running, interfaces initialized");
 
-    signal::ctrl_c().await?;
+    let failed = tokio::select! {
+        result = signal::ctrl_c() => {
+            result?;
+            log::info!("Shutdown signal received, cleaning up");
+            None
+        }
+        name = watch_task => {
+            log::error!("Shutting down, panic_on_interface_error is set");
+            Some(name)
+        }
+    };
 
-    log::info!("Shutdown signal received, cleaning up");
+    control_cancel.cancel();
+    if let Some(control_task) = control_task {
+        let _ = control_task.await;
+    }
+    management_cancel.cancel();
+    if let Some(management_task) = management_task {
+        let _ = management_task.await;
+    }
+    status_cancel.cancel();
+    let _ = status_task.await;
     drop(transport);
-    Ok(())
+
+    match failed {
+        Some(name) => Err(format!("interface '{}' failed", name).into()),
+        None => Ok(()),
+    }
 }
@@ -0,0 +1,348 @@
+//! JSON status file of a running daemon.
+//!
+//! A snapshot of the interfaces, paths, links, traffic, announce counts,
+//! rejected link proofs, replayed link packets, packet processing times and
+//! table sizes is written to `status.json` in the config directory
+//! every `status_interval` seconds and whenever the daemon receives
+//! `SIGUSR1`, so a node can be inspected without the control port.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reticulum::destination::link::LinkStatus;
+use reticulum::transport::{LinkDirection, ProcessingStats, TableStats, Transport};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+pub const STATUS_FILE: &str = "status.json";
+
+#[derive(Serialize)]
+pub struct Status {
+    /// Unix time of the snapshot in seconds.
+    pub updated: u64,
+    pub pid: u32,
+    pub interfaces: Vec<InterfaceInfo>,
+    pub paths: Vec<PathInfo>,
+    pub links: Vec<LinkInfo>,
+    pub traffic: Vec<TrafficInfo>,
+    pub announces: AnnounceInfo,
+    pub rejected_proofs: RejectedProofInfo,
+    /// Packets to local destinations by the key which decrypted them.
+    pub decryption: DecryptionInfo,
+    /// Replayed packets dropped by all links, closed ones included.
+    pub link_replays: u64,
+    pub processing: ProcessingInfo,
+    pub memory: MemoryInfo,
+}
+
+#[derive(Serialize)]
+pub struct InterfaceInfo {
+    /// Name from the config, clients of a server are named after it.
+    pub name: Option<String>,
+    pub address: String,
+    pub mode: String,
+    pub bitrate: Option<u64>,
+    pub echoes: u64,
+    /// The interface doesn't take packets, they are dropped.
+    pub stalled: bool,
+}
+
+#[derive(Serialize)]
+pub struct PathInfo {
+    pub destination: String,
+    pub hops: u8,
+    pub via: String,
+    pub interface: String,
+    /// Seconds since the announce establishing the path.
+    pub age: u64,
+}
+
+#[derive(Serialize)]
+pub struct LinkInfo {
+    pub id: String,
+    pub destination: String,
+    pub direction: &'static str,
+    pub status: &'static str,
+    pub rtt_ms: u128,
+    pub age: u64,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub replays: u64,
+}
+
+/// Data packets exchanged with a destination.
+#[derive(Serialize)]
+pub struct TrafficInfo {
+    pub destination: String,
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    pub packets_received: u64,
+    pub bytes_received: u64,
+    /// Seconds since the last packet.
+    pub idle: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct AnnounceInfo {
+    pub received: u64,
+    pub dropped: u64,
+    pub sent: u64,
+    pub retransmitted: u64,
+}
+
+#[derive(Serialize)]
+pub struct DecryptionInfo {
+    pub current_ratchet: u64,
+    pub previous_ratchet: u64,
+    pub identity: u64,
+    pub undecrypted: u64,
+}
+
+#[derive(Serialize)]
+pub struct RejectedProofInfo {
+    pub malformed: u64,
+    pub invalid_signature: u64,
+    pub unknown_link: u64,
+    pub wrong_interface: u64,
+}
+
+/// Received packets and how long they waited for and took to process, in
+/// microseconds.
+#[derive(Serialize)]
+pub struct ProcessingInfo {
+    pub packets: u64,
+    pub budget_yields: u64,
+    pub mean_lock_wait_us: u64,
+    pub max_lock_wait_us: u64,
+    pub mean_processing_us: u64,
+    pub max_processing_us: u64,
+}
+
+impl From<ProcessingStats> for ProcessingInfo {
+    fn from(stats: ProcessingStats) -> Self {
+        Self {
+            packets: stats.packets,
+            budget_yields: stats.budget_yields,
+            mean_lock_wait_us: stats.mean_lock_wait().as_micros() as u64,
+            max_lock_wait_us: stats.lock_wait.max.as_micros() as u64,
+            mean_processing_us: stats.mean_processing().as_micros() as u64,
+            max_processing_us: stats.processing.max.as_micros() as u64,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TableInfo {
+    pub entries: usize,
+    /// Approximate memory of the entries.
+    pub bytes: usize,
+}
+
+impl From<TableStats> for TableInfo {
+    fn from(stats: TableStats) -> Self {
+        Self { entries: stats.entries, bytes: stats.bytes }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MemoryInfo {
+    pub paths: TableInfo,
+    pub announces: TableInfo,
+    pub links: TableInfo,
+    pub relayed_links: TableInfo,
+    pub packet_cache: TableInfo,
+    pub destinations: TableInfo,
+    pub verified_announces: TableInfo,
+    pub traffic: TableInfo,
+    pub total_bytes: usize,
+}
+
+fn link_status_name(status: LinkStatus) -> &'static str {
+    match status {
+        LinkStatus::Pending => "pending",
+        LinkStatus::Handshake => "handshake",
+        LinkStatus::Active => "active",
+        LinkStatus::Stale => "stale",
+        LinkStatus::Closed => "closed",
+    }
+}
+
+impl Status {
+    pub async fn collect(transport: &Transport) -> Self {
+        let interfaces = transport
+            .iface_manager()
+            .lock()
+            .await
+            .interfaces()
+            .into_iter()
+            .map(|iface| InterfaceInfo {
+                name: iface.name,
+                address: iface.address.to_hex_string(),
+                mode: format!("{:?}", iface.mode),
+                bitrate: iface.bitrate,
+                echoes: iface.echoes,
+                stalled: iface.stalled,
+            })
+            .collect();
+
+        let paths = transport
+            .all_paths()
+            .await
+            .into_iter()
+            .map(|(destination, path)| PathInfo {
+                destination: destination.to_hex_string(),
+                hops: path.hops,
+                via: path.received_from.to_hex_string(),
+                interface: path.iface.to_hex_string(),
+                age: path.announced.elapsed().as_secs(),
+            })
+            .collect();
+
+        let links = transport
+            .active_links()
+            .await
+            .into_iter()
+            .map(|link| LinkInfo {
+                id: link.id.to_hex_string(),
+                destination: link.destination.to_hex_string(),
+                direction: match link.direction {
+                    LinkDirection::Outbound => "outbound",
+                    LinkDirection::Inbound => "inbound",
+                },
+                status: link_status_name(link.status),
+                rtt_ms: link.rtt.as_millis(),
+                age: link.age.as_secs(),
+                rx_bytes: link.rx_bytes,
+                tx_bytes: link.tx_bytes,
+                replays: link.replays,
+            })
+            .collect();
+
+        let traffic = transport
+            .all_traffic_stats()
+            .await
+            .into_iter()
+            .map(|(destination, stats)| TrafficInfo {
+                destination: destination.to_hex_string(),
+                packets_sent: stats.packets_sent,
+                bytes_sent: stats.bytes_sent,
+                packets_received: stats.packets_received,
+                bytes_received: stats.bytes_received,
+                idle: stats.last_activity.map(|instant| instant.elapsed().as_secs()),
+            })
+            .collect();
+
+        let counts = transport.announce_counts().await;
+        let rejected = transport.rejected_proofs().await;
+        let decryption = transport.decryption_counts().await;
+        let memory = transport.memory_stats().await;
+
+        Self {
+            updated: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+            pid: std::process::id(),
+            interfaces,
+            paths,
+            links,
+            traffic,
+            announces: AnnounceInfo {
+                received: counts.received,
+                dropped: counts.dropped,
+                sent: counts.sent,
+                retransmitted: counts.retransmitted,
+            },
+            rejected_proofs: RejectedProofInfo {
+                malformed: rejected.malformed,
+                invalid_signature: rejected.invalid_signature,
+                unknown_link: rejected.unknown_link,
+                wrong_interface: rejected.wrong_interface,
+            },
+            decryption: DecryptionInfo {
+                current_ratchet: decryption.current_ratchet,
+                previous_ratchet: decryption.previous_ratchet,
+                identity: decryption.identity,
+                undecrypted: decryption.undecrypted,
+            },
+            link_replays: transport.link_replays().await,
+            processing: transport.processing_stats().into(),
+            memory: MemoryInfo {
+                paths: memory.paths.into(),
+                announces: memory.announces.into(),
+                links: memory.links.into(),
+                relayed_links: memory.relayed_links.into(),
+                packet_cache: memory.packet_cache.into(),
+                destinations: memory.destinations.into(),
+                verified_announces: memory.verified_announces.into(),
+                traffic: memory.traffic.into(),
+                total_bytes: memory.total_bytes(),
+            },
+        }
+    }
+
+    /// Replaces the file at `path`, readers never see a partial snapshot.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let partial = path.with_extension("json.tmp");
+        fs::write(&partial, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(partial, path)
+    }
+}
+
+#[cfg(unix)]
+type DumpSignal = tokio::signal::unix::Signal;
+#[cfg(not(unix))]
+type DumpSignal = ();
+
+#[cfg(unix)]
+fn dump_signal() -> Option<DumpSignal> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    signal(SignalKind::user_defined1())
+        .map_err(|err| log::warn!("Couldn't listen for SIGUSR1: {}", err))
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn dump_signal() -> Option<DumpSignal> {
+    None
+}
+
+/// Resolves when a snapshot is requested with `SIGUSR1`.
+async fn dump_requested(signal: &mut Option<DumpSignal>) {
+    match signal {
+        #[cfg(unix)]
+        Some(signal) => {
+            signal.recv().await;
+        }
+        _ => std::future::pending().await,
+    }
+}
+
+/// Writes the status file to `path` every `interval` seconds, or only on
+/// `SIGUSR1` if `interval` is 0, until cancelled.
+pub async fn run(
+    transport: Arc<Transport>,
+    path: PathBuf,
+    interval: u64,
+    cancel: CancellationToken,
+) {
+    let mut ticks = (interval > 0).then(|| tokio::time::interval(Duration::from_secs(interval)));
+    let mut dump = dump_signal();
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = async { ticks.as_mut().unwrap().tick().await }, if ticks.is_some() => {}
+            _ = dump_requested(&mut dump) => log::info!("Status dump requested"),
+        }
+
+        let status = Status::collect(&transport).await;
+        if let Err(err) = status.write(&path) {
+            log::warn!("Couldn't write status file {}: {}", path.display(), err);
+        }
+    }
+}
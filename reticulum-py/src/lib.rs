@@ -0,0 +1,397 @@
+//! Python bindings of reticulum-rs.
+//!
+//! The `reticulum_rs` module runs the Rust transport behind a small Python
+//! API, so apps written against the reference stack can be tested with it:
+//!
+//! ```python
+//! import reticulum_rs
+//!
+//! transport = reticulum_rs.Transport("node")
+//! transport.add_tcp_client("127.0.0.1:4242")
+//! destination = transport.add_destination("app", "aspect")
+//! transport.announce(destination, b"hello")
+//!
+//! destination_hash, app_data = transport.recv_announce(timeout=10)
+//! link = transport.link(destination_hash)
+//! ```
+//!
+//! Every transport owns the runtime driving it. Calls which wait for the
+//! network release the GIL, so other Python threads keep running.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use pyo3::exceptions::{PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand_core::OsRng;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::{Mutex, RwLock};
+
+use reticulum::destination::link::{Link, LinkEvent, LinkEventData, LinkId, LinkStatus};
+use reticulum::destination::{DestinationName, SingleInputDestination};
+use reticulum::hash::{AddressHash, ADDRESS_HASH_SIZE};
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::tcp_client::TcpClient;
+use reticulum::iface::tcp_server::TcpServer;
+use reticulum::runtime;
+use reticulum::transport::{AnnounceEvent, Transport, TransportConfig};
+
+fn address(hash: &[u8]) -> PyResult<AddressHash> {
+    let hash: [u8; ADDRESS_HASH_SIZE] = hash
+        .try_into()
+        .map_err(|_| PyValueError::new_err("hashes are 16 bytes long"))?;
+
+    Ok(AddressHash::new(hash))
+}
+
+/// Waits for the next message of `receiver`, skipping lagged ones. `None`
+/// if the timeout elapses or the transport is gone.
+async fn recv<T: Clone>(receiver: &mut Receiver<T>, timeout: Option<f64>) -> Option<T> {
+    let next = async {
+        loop {
+            match receiver.recv().await {
+                Ok(message) => return Some(message),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    };
+
+    match timeout {
+        Some(timeout) => runtime::timeout(Duration::from_secs_f64(timeout), next)
+            .await
+            .ok()
+            .flatten(),
+        None => next.await,
+    }
+}
+
+/// A private identity.
+#[pyclass(frozen, name = "Identity")]
+pub struct PyIdentity(PrivateIdentity);
+
+#[pymethods]
+impl PyIdentity {
+    /// Creates a new random identity.
+    #[new]
+    fn new() -> Self {
+        Self(PrivateIdentity::new_from_rand(OsRng))
+    }
+
+    /// Restores an identity from its hex encoded private keys.
+    #[staticmethod]
+    fn from_hex(hex: &str) -> PyResult<Self> {
+        PrivateIdentity::new_from_hex_string(hex)
+            .map(Self)
+            .map_err(|_| PyValueError::new_err("invalid identity"))
+    }
+
+    fn to_hex(&self) -> String {
+        self.0.to_hex_string()
+    }
+
+    #[getter]
+    fn hash<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.0.address_hash().as_slice())
+    }
+}
+
+/// A destination registered with a transport.
+#[pyclass(frozen, name = "Destination")]
+pub struct PyDestination {
+    destination: Arc<Mutex<SingleInputDestination>>,
+    address: AddressHash,
+}
+
+#[pymethods]
+impl PyDestination {
+    #[getter]
+    fn hash<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.address.as_slice())
+    }
+}
+
+/// A link opened by or to a transport.
+#[pyclass(frozen, name = "Link")]
+pub struct PyLink {
+    link: Arc<Mutex<Link>>,
+    id: LinkId,
+    transport: Py<PyTransport>,
+}
+
+#[pymethods]
+impl PyLink {
+    #[getter]
+    fn id<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, self.id.as_slice())
+    }
+
+    /// One of `"pending"`, `"handshake"`, `"active"`, `"stale"` or `"closed"`.
+    #[getter]
+    fn status(&self, py: Python<'_>) -> &'static str {
+        let transport = self.transport.get();
+        let status = py.allow_threads(|| transport.runtime.block_on(async { self.link.lock().await.status() }));
+
+        match status {
+            LinkStatus::Pending => "pending",
+            LinkStatus::Handshake => "handshake",
+            LinkStatus::Active => "active",
+            LinkStatus::Stale => "stale",
+            LinkStatus::Closed => "closed",
+        }
+    }
+
+    /// Sends `data` over the link, waiting while the link window is full.
+    fn send(&self, py: Python<'_>, data: &[u8]) -> PyResult<()> {
+        let transport = self.transport.get();
+
+        py.allow_threads(|| {
+            transport
+                .runtime
+                .block_on(async { transport.transport.read().await.send_to_link(&self.link, data).await })
+        })
+        .map(|_| ())
+        .map_err(|err| PyRuntimeError::new_err(format!("couldn't send over link: {:?}", err)))
+    }
+
+    fn close(&self, py: Python<'_>) -> PyResult<()> {
+        let transport = self.transport.get();
+
+        py.allow_threads(|| {
+            transport.runtime.block_on(async {
+                transport.out_links.lock().await.remove(&self.id);
+                transport.transport.read().await.link_close(self.id).await
+            })
+        })
+        .map_err(|err| PyRuntimeError::new_err(format!("couldn't close link: {:?}", err)))
+    }
+}
+
+/// Kind, link and data of a link event as returned to Python.
+type PyLinkEvent<'py> = (&'static str, PyLink, Option<Bound<'py, PyBytes>>);
+
+struct LinkEvents {
+    in_links: Receiver<LinkEventData>,
+    out_links: Receiver<LinkEventData>,
+}
+
+/// A transport with its own runtime. Announces and link events are queued
+/// from its creation on until they are received.
+#[pyclass(frozen, name = "Transport")]
+pub struct PyTransport {
+    // Only adding destinations needs the transport mutably
+    transport: RwLock<Transport>,
+    identity: PrivateIdentity,
+    announces: Mutex<Receiver<AnnounceEvent>>,
+    link_events: Mutex<LinkEvents>,
+    out_links: Mutex<HashMap<LinkId, Arc<Mutex<Link>>>>,
+    // Dropped last, after the transport stopped its tasks
+    runtime: Runtime,
+}
+
+#[pymethods]
+impl PyTransport {
+    /// Creates a transport named `name`. A new identity is generated if
+    /// `identity` isn't given.
+    #[new]
+    #[pyo3(signature = (name, identity = None))]
+    fn new(name: &str, identity: Option<&PyIdentity>) -> PyResult<Self> {
+        let identity = match identity {
+            Some(identity) => identity.0.clone(),
+            None => PrivateIdentity::new_from_rand(OsRng),
+        };
+
+        let runtime = Runtime::new().map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+
+        let (transport, announces) = runtime.block_on(async {
+            let transport = Transport::new(TransportConfig::new(name, &identity, false));
+            let announces = transport.recv_announces().await;
+            (transport, announces)
+        });
+
+        let link_events = LinkEvents {
+            in_links: transport.in_link_events(),
+            out_links: transport.out_link_events(),
+        };
+
+        Ok(Self {
+            transport: RwLock::new(transport),
+            identity,
+            announces: Mutex::new(announces),
+            link_events: Mutex::new(link_events),
+            out_links: Mutex::new(HashMap::new()),
+            runtime,
+        })
+    }
+
+    /// Connects to a TCP server at `addr`, e.g. `"10.0.0.1:4242"`.
+    fn add_tcp_client(&self, addr: &str) {
+        self.runtime.block_on(async {
+            self.transport
+                .read()
+                .await
+                .iface_manager()
+                .lock()
+                .await
+                .spawn(TcpClient::new(addr), TcpClient::spawn);
+        });
+    }
+
+    /// Accepts TCP clients on `addr`, e.g. `"0.0.0.0:4242"`.
+    fn add_tcp_server(&self, addr: &str) {
+        self.runtime.block_on(async {
+            let iface_manager = self.transport.read().await.iface_manager();
+            iface_manager
+                .lock()
+                .await
+                .spawn(TcpServer::new(addr, iface_manager.clone()), TcpServer::spawn);
+        });
+    }
+
+    /// Adds a destination `app_name.aspects`. It belongs to `identity`, or
+    /// to the identity of the transport if it isn't given.
+    #[pyo3(signature = (app_name, aspects, identity = None))]
+    fn add_destination(&self, app_name: &str, aspects: &str, identity: Option<&PyIdentity>) -> PyDestination {
+        let identity = match identity {
+            Some(identity) => identity.0.clone(),
+            None => self.identity.clone(),
+        };
+
+        self.runtime.block_on(async {
+            let destination = self
+                .transport
+                .write()
+                .await
+                .add_destination(identity, DestinationName::new(app_name, aspects))
+                .await;
+            let address = destination.lock().await.desc.address_hash;
+            PyDestination { destination, address }
+        })
+    }
+
+    #[pyo3(signature = (destination, app_data = None))]
+    fn announce(&self, destination: &PyDestination, app_data: Option<&[u8]>) -> PyResult<()> {
+        self.runtime
+            .block_on(async {
+                self.transport
+                    .read()
+                    .await
+                    .send_announce(&destination.destination, app_data)
+                    .await
+            })
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Waits for the next announce and returns the destination hash and the
+    /// app data of it, or `None` if `timeout` seconds elapsed.
+    #[pyo3(signature = (timeout = None))]
+    fn recv_announce<'py>(
+        &self,
+        py: Python<'py>,
+        timeout: Option<f64>,
+    ) -> Option<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+        let announce = py.allow_threads(|| {
+            self.runtime.block_on(async {
+                let announce = recv(&mut *self.announces.lock().await, timeout).await?;
+                let destination = announce.destination.lock().await.desc.address_hash;
+                Some((destination, announce.app_data.as_slice().to_vec()))
+            })
+        });
+
+        announce.map(|(destination, app_data)| {
+            (PyBytes::new(py, destination.as_slice()), PyBytes::new(py, &app_data))
+        })
+    }
+
+    /// Opens a link to an announced destination. It can be used once its
+    /// `"activated"` event is received.
+    fn link(slf: &Bound<'_, Self>, destination: &[u8]) -> PyResult<PyLink> {
+        let this = slf.get();
+        let destination = address(destination)?;
+
+        let link = this.runtime.block_on(async {
+            let transport = this.transport.read().await;
+            let destination = transport.get_out_destination(&destination).await?;
+            let desc = destination.lock().await.desc;
+
+            let link = transport.link(desc).await;
+            let id = *link.lock().await.id();
+            this.out_links.lock().await.insert(id, link.clone());
+
+            Some((link, id))
+        });
+
+        let (link, id) = link.ok_or_else(|| PyKeyError::new_err("unknown destination"))?;
+
+        Ok(PyLink { link, id, transport: slf.clone().unbind() })
+    }
+
+    /// Waits for the next event of a link and returns its kind, the link and
+    /// the received data, or `None` if `timeout` seconds elapsed. The kind is
+    /// `"activated"`, `"closed"` or `"data"`, data is only set for `"data"`.
+    #[pyo3(signature = (timeout = None))]
+    fn recv_link_event<'py>(
+        slf: &Bound<'py, Self>,
+        timeout: Option<f64>,
+    ) -> Option<PyLinkEvent<'py>> {
+        let py = slf.py();
+        let this = slf.get();
+
+        let event = py.allow_threads(|| {
+            this.runtime.block_on(async {
+                loop {
+                    let mut link_events = this.link_events.lock().await;
+                    let LinkEvents { in_links, out_links } = &mut *link_events;
+
+                    let event = tokio::select! {
+                        event = recv(in_links, timeout) => event,
+                        event = recv(out_links, timeout) => event,
+                    }?;
+                    drop(link_events);
+
+                    let (kind, data) = match event.event {
+                        LinkEvent::Activated => ("activated", None),
+                        LinkEvent::Closed => ("closed", None),
+                        LinkEvent::Data(payload) => ("data", Some(payload.as_slice().to_vec())),
+                        LinkEvent::IdentityReceived(identity) => {
+                            ("identified", Some(identity.address_hash.as_slice().to_vec()))
+                        }
+                        LinkEvent::RequestReceived(request) => ("request", Some(request.data)),
+                        LinkEvent::Proof(_)
+                        | LinkEvent::ResourceAdvertised(_)
+                        | LinkEvent::KeepAlive
+                        | LinkEvent::Reestablished { .. } => continue,
+                    };
+
+                    let link = match this.out_links.lock().await.get(&event.id) {
+                        Some(link) => Some(link.clone()),
+                        None => this.transport.read().await.find_in_link(&event.id).await,
+                    };
+
+                    // Events of links which are gone already are skipped
+                    if let Some(link) = link {
+                        return Some((kind, link, event.id, data));
+                    }
+                }
+            })
+        });
+
+        event.map(|(kind, link, id, data)| {
+            let link = PyLink { link, id, transport: slf.clone().unbind() };
+            (kind, link, data.map(|data| PyBytes::new(py, &data)))
+        })
+    }
+}
+
+#[pymodule]
+pub fn reticulum_rs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyIdentity>()?;
+    module.add_class::<PyTransport>()?;
+    module.add_class::<PyDestination>()?;
+    module.add_class::<PyLink>()?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Runs a Python app against the module: two transports connected over TCP,
+//! one announces and the other links to it.
+
+use pyo3::prelude::*;
+use reticulum_rs::reticulum_rs;
+
+const SCRIPT: &std::ffi::CStr = cr#"
+import socket
+import time
+
+import reticulum_rs
+
+with socket.socket() as sock:
+    sock.bind(("127.0.0.1", 0))
+    addr = "127.0.0.1:%d" % sock.getsockname()[1]
+
+identity = reticulum_rs.Identity()
+assert reticulum_rs.Identity.from_hex(identity.to_hex()).hash == identity.hash
+
+server = reticulum_rs.Transport("server", identity)
+client = reticulum_rs.Transport("client")
+server.add_tcp_server(addr)
+time.sleep(0.2)
+client.add_tcp_client(addr)
+time.sleep(0.5)
+
+destination = server.add_destination("app", "python")
+server.announce(destination, b"hello")
+
+while True:
+    announce = client.recv_announce(timeout=5)
+    assert announce is not None, "no announce"
+    if announce[0] == destination.hash:
+        break
+assert announce[1] == b"hello"
+
+def wait_for(transport, kind):
+    while True:
+        event = transport.recv_link_event(timeout=5)
+        assert event is not None, "no %s event" % kind
+        if event[0] == kind:
+            return event
+
+link = client.link(destination.hash)
+_, activated, _ = wait_for(client, "activated")
+assert activated.id == link.id
+assert link.status == "active"
+
+link.send(b"ping")
+_, in_link, data = wait_for(server, "data")
+assert data == b"ping"
+
+in_link.send(b"pong")
+_, _, data = wait_for(client, "data")
+assert data == b"pong"
+
+link.close()
+
+try:
+    client.link(bytes(16))
+    assert False, "linked to unknown destination"
+except KeyError:
+    pass
+"#;
+
+#[test]
+fn python_app() {
+    pyo3::append_to_inittab!(reticulum_rs);
+
+    Python::with_gil(|py| {
+        if let Err(err) = py.run(SCRIPT, None, None) {
+            err.display(py);
+            panic!("python app failed");
+        }
+    });
+}
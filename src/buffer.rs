@@ -1,3 +1,10 @@
+//! Fixed size buffers for encoding and decoding packets.
+//!
+//! None of them allocate, so packets can be decoded into a
+//! [`StaticBuffer`] on the stack, e.g. in the receive interrupt of a radio
+//! driver. Writes which don't fit return [`RnsError::OutOfMemory`] instead
+//! of panicking, the `safe_*` variants write as much as fits.
+
 use core::cmp::min;
 use core::fmt;
 
@@ -10,6 +17,8 @@ pub struct StaticBuffer<const N: usize> {
 }
 
 impl<const N: usize> StaticBuffer<N> {
+    pub const CAPACITY: usize = N;
+
     pub const fn new() -> Self {
         Self {
             buffer: [0u8; N],
@@ -25,6 +34,15 @@ impl<const N: usize> StaticBuffer<N> {
         buffer
     }
 
+    /// Copies `data` into a new buffer, failing if it doesn't fit.
+    pub fn try_from_slice(data: &[u8]) -> Result<Self, RnsError> {
+        let mut buffer = Self::new();
+
+        buffer.write(data)?;
+
+        Ok(buffer)
+    }
+
     pub fn reset(&mut self) {
         self.len = 0;
     }
@@ -41,6 +59,15 @@ impl<const N: usize> StaticBuffer<N> {
         self.len == 0
     }
 
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Bytes which can still be written.
+    pub fn remaining(&self) -> usize {
+        N - self.len
+    }
+
     pub fn chain_write(&mut self, data: &[u8]) -> Result<&mut Self, RnsError> {
         self.write(data)?;
         Ok(self)
@@ -101,11 +128,27 @@ impl<const N: usize> StaticBuffer<N> {
         &mut self.buffer[..self.len]
     }
 
+    /// Sets the length to `len` and returns the buffer to be filled.
+    ///
+    /// # Panics
+    ///
+    /// If `len` exceeds the capacity. Use [`StaticBuffer::try_acquire_buf`]
+    /// for lengths taken from received data.
     pub fn accuire_buf(&mut self, len: usize) -> &mut [u8] {
         self.len = len;
         &mut self.buffer[..self.len]
     }
 
+    /// Like [`StaticBuffer::accuire_buf`], but fails if `len` exceeds the
+    /// capacity instead of panicking.
+    pub fn try_acquire_buf(&mut self, len: usize) -> Result<&mut [u8], RnsError> {
+        if len > N {
+            return Err(RnsError::OutOfMemory);
+        }
+
+        Ok(self.accuire_buf(len))
+    }
+
     pub fn accuire_buf_max(&mut self) -> &mut [u8] {
         self.len = self.buffer.len();
         &mut self.buffer[..self.len]
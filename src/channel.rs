@@ -11,23 +11,36 @@
 //! [Channel] also guarantees delivery of the messages in the order in which
 //! they were sent.
 //!
+//! Messages which don't fit into a single packet are split into fragments,
+//! each sent as a message of the system type [FRAGMENT_MESSAGE_TYPE], and
+//! reassembled by the receiving [Channel] before they are unpacked. The
+//! reference implementation has no such message type, so only messages up
+//! to [CHANNEL_MDU] bytes can be exchanged with it.
+//!
+//! [Channel::send_transfer] reports the delivery of the fragments of a
+//! message through a [TransferHandle], which can also pause and cancel it.
+//! The fragments of a cancelled message which weren't sent yet are replaced
+//! by empty fragments telling the receiver to drop the message, so the
+//! sequence of the channel has no gap.
+//!
 //! This module defines the [Message] trait and the [Channel] struct.
 
-use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
 use alloc::sync::{Arc, Weak};
+use core::fmt;
+use core::time::Duration;
 
-use tokio::sync::{broadcast, Mutex, MutexGuard, mpsc};
-use tokio::time::{Duration, Instant, sleep};
+use tokio::sync::{broadcast, Mutex, MutexGuard, mpsc, Notify};
 use tokio_util::sync::CancellationToken;
 
 use crate::destination::link::{
-    LinkEvent, LinkEventData, LinkId, LinkPayload, LinkStatus
+    LinkError, LinkEvent, LinkEventData, LinkId, LinkPayload, LinkStatus
 };
 use crate::error::RnsError;
 use crate::hash::Hash;
-use crate::packet::{
-    PacketContext, PACKET_MDU
-};
+use crate::packet::PacketContext;
+use crate::runtime::{self, sleep, Instant};
+use crate::transfer::{Transfer, TransferCommand, TransferHandle, TransferState};
 
 #[cfg(not(test))]
 use crate::{destination::link::Link, packet::Packet, transport::Transport};
@@ -35,8 +48,36 @@ use crate::{destination::link::Link, packet::Packet, transport::Transport};
 #[cfg(test)]
 use mock::{Link, Packet, Transport};
 
+/// Largest plain text of a link packet, as the reference implementation
+/// computes it for the default MTU of 500 bytes: what is left after the
+/// headers and the token overhead, rounded down to whole AES blocks minus
+/// one byte of padding.
+const LINK_MDU: usize = 431;
+
+/// Maximal payload size of a message sent through a `Channel` in a single
+/// packet.
+pub const CHANNEL_MDU: usize = LINK_MDU - 6;
+
+/// Message type of the fragments of messages larger than [CHANNEL_MDU],
+/// taken from the range reserved for system messages.
+pub const FRAGMENT_MESSAGE_TYPE: u16 = 0xff80;
+
+/// Maximal number of fragments a message may be split into.
+pub const MAX_FRAGMENTS: usize = 64;
+
+/// Size of the fragment header: flags and the type of the fragmented message.
+const FRAGMENT_HEADER_SIZE: usize = 3;
+
+/// Fragment flag set on all but the last fragment of a message.
+const FRAGMENT_MORE: u8 = 0x01;
+
+/// Fragment flag of the fragments replacing those of a cancelled message.
+const FRAGMENT_ABORT: u8 = 0x02;
+
+const FRAGMENT_MDU: usize = CHANNEL_MDU - FRAGMENT_HEADER_SIZE;
+
 /// Maximal payload size of a message sent through a `Channel`.
-pub const CHANNEL_MDU: usize = PACKET_MDU - 6;
+pub const MAX_MESSAGE_SIZE: usize = MAX_FRAGMENTS * FRAGMENT_MDU;
 
 /// Message model for `Channel`.
 ///
@@ -59,38 +100,24 @@ pub trait Message: Clone + Send + Sized + Sync + 'static {
     fn message_type(&self) -> u16;
 }
 
-async fn outlet_send(
+async fn outlet_packet(
     link: &Arc<Mutex<Link>>,
     raw: &[u8],
-    transport: &Arc<Mutex<Transport>>
-) -> (Packet, bool) {
-    let mut packet;
-    let active;
-
-    {
-        let link = link.lock().await;
-        packet = link.data_packet(raw).unwrap();
-        active = link.status() == LinkStatus::Active;
-    }
-
+) -> Result<Packet, LinkError> {
+    let mut packet = link.lock().await.data_packet(raw)?;
     packet.context = PacketContext::Channel;
 
-    if active {
-        transport.lock().await.send_packet(packet).await;
-    }
-
-    (packet, active)
+    Ok(packet)
 }
 
-async fn outlet_resend(
+async fn outlet_send(
     link: &Arc<Mutex<Link>>,
     packet: Packet,
     transport: Weak<Mutex<Transport>>,
 ) -> bool {
     if link.lock().await.status() == LinkStatus::Active {
         if let Some(transport) = transport.upgrade() {
-            transport.lock().await.send_packet(packet).await;
-            return true;
+            return transport.lock().await.send_packet(packet).await.is_ok();
         }
     }
 
@@ -98,10 +125,10 @@ async fn outlet_resend(
 }
 
 
-async fn outlet_is_usable(link: &Arc<Mutex<Link>>) -> bool {
-    link.lock().await.status() == LinkStatus::Active
-    // This diverges from the reference implementation. The value is
-    // hardcoded to true in the reference implementation, citing
+async fn outlet_status(link: &Arc<Mutex<Link>>) -> LinkStatus {
+    link.lock().await.status()
+    // The link has to be active to send. This diverges from the reference
+    // implementation, which hardcodes the outlet to be usable, citing
     // "issues looking at Link.status".
 }
 
@@ -123,18 +150,121 @@ pub enum MessageStatus {
     Delivered
 }
 
-struct Envelope<M: Message> {
-    message: M,
-    sequence: u16,
+/// Failure of a [Channel] operation, with the state that caused it.
+#[derive(Debug, PartialEq)]
+pub enum ChannelError {
+    /// The underlying link refused the operation.
+    Link(LinkError),
+    /// The channel was torn down after a message could not be delivered.
+    Closed { link_id: LinkId },
+    /// The transport the channel sends through no longer exists.
+    TransportDropped { link_id: LinkId },
+    /// The link is not active (yet).
+    LinkNotReady { link_id: LinkId, status: LinkStatus },
+    /// Too many messages are awaiting delivery.
+    WindowFull { link_id: LinkId, outstanding: usize, window: usize },
+    /// The message is too large even when split into fragments.
+    MessageTooBig { link_id: LinkId, size: usize, max: usize },
+    /// A received envelope is shorter than its header.
+    MalformedEnvelope { link_id: LinkId, len: usize },
+    /// [`Message::unpack`] rejected a received message.
+    Unpack {
+        link_id: LinkId,
+        message_type: u16,
+        sequence: u16,
+        source: RnsError,
+    },
+}
+
+impl ChannelError {
+    /// Returns whether the operation may succeed if retried later, which is
+    /// the case while the link comes up or the window is full.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ChannelError::LinkNotReady { .. } | ChannelError::WindowFull { .. }
+        )
+    }
 }
 
-impl<M: Message> Envelope<M> {
-    fn new(message: M, sequence: u16) -> Self {
-        Self { message, sequence }
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::Link(error) => write!(f, "channel link error: {}", error),
+            ChannelError::Closed { link_id } => {
+                write!(f, "channel({}) is closed", link_id)
+            }
+            ChannelError::TransportDropped { link_id } => {
+                write!(f, "channel({}) transport was dropped", link_id)
+            }
+            ChannelError::LinkNotReady { link_id, status } => {
+                write!(f, "channel({}) link is not ready (status {:?})", link_id, status)
+            }
+            ChannelError::WindowFull { link_id, outstanding, window } => write!(
+                f,
+                "channel({}) window is full ({} of {} messages outstanding)",
+                link_id, outstanding, window
+            ),
+            ChannelError::MessageTooBig { link_id, size, max } => write!(
+                f,
+                "channel({}) message of {}B exceeds the maximum of {}B",
+                link_id, size, max
+            ),
+            ChannelError::MalformedEnvelope { link_id, len } => write!(
+                f,
+                "channel({}) received malformed envelope of {}B",
+                link_id, len
+            ),
+            ChannelError::Unpack { link_id, message_type, sequence, source } => write!(
+                f,
+                "channel({}) could not unpack message {} of type {:#06x}: {}",
+                link_id, sequence, message_type, source
+            ),
+        }
     }
+}
+
+impl std::error::Error for ChannelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChannelError::Link(error) => Some(error),
+            ChannelError::Unpack { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<LinkError> for ChannelError {
+    fn from(error: LinkError) -> Self {
+        ChannelError::Link(error)
+    }
+}
+
+impl From<ChannelError> for RnsError {
+    fn from(error: ChannelError) -> Self {
+        match error {
+            ChannelError::Link(error) => error.into(),
+            ChannelError::Closed { .. }
+            | ChannelError::TransportDropped { .. }
+            | ChannelError::LinkNotReady { .. }
+            | ChannelError::WindowFull { .. } => RnsError::ChannelLinkNotReady,
+            ChannelError::MessageTooBig { .. } => RnsError::ChannelMessageTooBig,
+            ChannelError::MalformedEnvelope { .. } => RnsError::ChannelError,
+            ChannelError::Unpack { source, .. } => source,
+        }
+    }
+}
+
+struct Envelope {
+    message_type: u16,
+    sequence: u16,
+    payload: Vec<u8>,
+}
 
-    fn unpack(raw: &[u8]) -> Result<Self, RnsError> {
-        let (message_type, sequence, size) = deenvelope_raw(raw)?;
+impl Envelope {
+    fn unpack(raw: &[u8], link_id: LinkId) -> Result<Self, ChannelError> {
+        let (message_type, sequence, size) = deenvelope_raw(raw)
+            .ok_or(ChannelError::MalformedEnvelope { link_id, len: raw.len() })?;
 
         if raw.len() as u16 != size + 6 {
             log::trace!(
@@ -142,12 +272,21 @@ impl<M: Message> Envelope<M> {
             );
         }
 
-        let message = M::unpack(&raw[6..], message_type)?;
-
-        Ok(Self::new(message, sequence))
+        Ok(Self { message_type, sequence, payload: raw[6..].to_vec() })
     }
 }
 
+fn unpack_message<M: Message>(
+    link_id: LinkId,
+    message_type: u16,
+    sequence: u16,
+    payload: &[u8],
+) -> Result<M, ChannelError> {
+    M::unpack(payload, message_type).map_err(|source| {
+        ChannelError::Unpack { link_id, message_type, sequence, source }
+    })
+}
+
 fn envelope_raw(
     data: &[u8],
     message_type: u16,
@@ -175,24 +314,118 @@ fn envelope_raw(
 }
 
 
-fn deenvelope_raw(data: &[u8]) -> Result<(u16, u16, u16), RnsError>
+fn deenvelope_raw(data: &[u8]) -> Option<(u16, u16, u16)>
 {
     if data.len() < 6 {
-        return Err(RnsError::ChannelError);
+        return None;
     }
 
     let message_type = u16::from_be_bytes([data[0], data[1]]);
     let sequence = u16::from_be_bytes([data[2], data[3]]);
     let size = u16::from_be_bytes([data[4], data[5]]);
 
-    Ok((message_type, sequence, size))
+    Some((message_type, sequence, size))
+}
+
+/// Splits a message into fragment payloads, or returns `None` if it needs
+/// more than [MAX_FRAGMENTS] of them.
+fn fragment_raw(packed: &[u8], message_type: u16) -> Option<Vec<Vec<u8>>> {
+    let chunks = packed.chunks(FRAGMENT_MDU);
+    let count = chunks.len();
+
+    if count > MAX_FRAGMENTS {
+        return None;
+    }
+
+    let fragments = chunks
+        .enumerate()
+        .map(|(index, chunk)| {
+            let flags = if index + 1 < count { FRAGMENT_MORE } else { 0 };
+
+            let mut fragment = Vec::with_capacity(FRAGMENT_HEADER_SIZE + chunk.len());
+            fragment.push(flags);
+            fragment.extend_from_slice(&message_type.to_be_bytes());
+            fragment.extend_from_slice(chunk);
+            fragment
+        })
+        .collect();
+
+    Some(fragments)
+}
+
+/// Bytes of the message an envelope payload carries.
+fn message_bytes(message_type: u16, payload: &[u8]) -> usize {
+    if message_type == FRAGMENT_MESSAGE_TYPE {
+        payload.len() - FRAGMENT_HEADER_SIZE
+    } else {
+        payload.len()
+    }
+}
+
+/// Fragment telling the receiver to drop the fragments of a message of type
+/// `message_type` it received so far.
+fn abort_fragment(message_type: u16) -> Vec<u8> {
+    let mut fragment = Vec::with_capacity(FRAGMENT_HEADER_SIZE);
+    fragment.push(FRAGMENT_ABORT);
+    fragment.extend_from_slice(&message_type.to_be_bytes());
+    fragment
+}
+
+/// Reassembles the fragments of one message as they arrive in sequence.
+#[derive(Default)]
+struct Assembler {
+    message_type: u16,
+    fragments: usize,
+    payload: Vec<u8>,
 }
 
-fn message_raw<M: Message>(message: &M, sequence: Option<u16>) -> Vec<u8> {
-    let packed = message.pack();
-    let message_type = message.message_type();
+impl Assembler {
+    /// Adds a fragment and returns the type and payload of the message once
+    /// its last fragment was added.
+    fn add(&mut self, fragment: &[u8]) -> Option<(u16, Vec<u8>)> {
+        if fragment.len() < FRAGMENT_HEADER_SIZE {
+            return None;
+        }
+
+        let flags = fragment[0];
+        let message_type = u16::from_be_bytes([fragment[1], fragment[2]]);
 
-    envelope_raw(packed.as_ref(), message_type, sequence)
+        if flags & FRAGMENT_ABORT != 0 {
+            self.reset();
+            return None;
+        }
+
+        if self.fragments > 0 && message_type != self.message_type {
+            self.reset();
+        }
+
+        if self.fragments == MAX_FRAGMENTS {
+            self.reset();
+            return None;
+        }
+
+        self.message_type = message_type;
+        self.fragments += 1;
+        self.payload.extend_from_slice(&fragment[FRAGMENT_HEADER_SIZE..]);
+
+        if flags & FRAGMENT_MORE != 0 {
+            return None;
+        }
+
+        let payload = core::mem::take(&mut self.payload);
+        self.reset();
+
+        Some((message_type, payload))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fragments == 0
+    }
+
+    fn reset(&mut self) {
+        self.fragments = 0;
+        self.payload.clear();
+    }
 }
 
 fn packet_timeout_time(
@@ -210,22 +443,22 @@ fn packet_timeout_time(
 }
 
 
-static WINDOW: u16 = 2;
+pub(crate) static WINDOW: u16 = 2;
 
-static WINDOW_MIN: u16 = 2;
-static WINDOW_MIN_LIMIT_MEDIUM: u16  = 5;
-static WINDOW_MIN_LIMIT_FAST: u16 = 16;
+pub(crate) static WINDOW_MIN: u16 = 2;
+pub(crate) static WINDOW_MIN_LIMIT_MEDIUM: u16  = 5;
+pub(crate) static WINDOW_MIN_LIMIT_FAST: u16 = 16;
 
-static WINDOW_MAX_SLOW: u16 = 5;
-static WINDOW_MAX_MEDIUM: u16 = 12;
-static WINDOW_MAX_FAST: u16 = 48;
+pub(crate) static WINDOW_MAX_SLOW: u16 = 5;
+pub(crate) static WINDOW_MAX_MEDIUM: u16 = 12;
+pub(crate) static WINDOW_MAX_FAST: u16 = 48;
 static WINDOW_MAX: u16 = WINDOW_MAX_FAST;
 
-static FAST_RATE_THRESHOLD: u16 = 10;
+pub(crate) static FAST_RATE_THRESHOLD: u16 = 10;
 
-static RTT_FAST: f32 = 0.18;
-static RTT_MEDIUM: f32 = 0.75;
-static RTT_SLOW: f32 = 1.45;
+pub(crate) static RTT_FAST: f32 = 0.18;
+pub(crate) static RTT_MEDIUM: f32 = 0.75;
+pub(crate) static RTT_SLOW: f32 = 1.45;
 
 struct ChannelParams {
     pub max_tries: u16,
@@ -292,7 +525,7 @@ fn watch_message_try(
     mut delivery: broadcast::Receiver<bool>,
     cancel: CancellationToken,
 ) {
-    tokio::spawn(async move {
+    runtime::spawn(async move {
         let started = Instant::now();
         let timeout = started + interval;
 
@@ -325,14 +558,34 @@ fn watch_message_try(
     });
 }
 
+/// A fragment of a message sent with [Channel::send_transfer].
+struct TransferPart {
+    transfer: Transfer,
+    message_type: u16,
+    bytes: usize,
+}
+
+struct PendingPacket {
+    packet: Packet,
+    raw: Vec<u8>,
+    sequence: u16,
+    part: Option<TransferPart>,
+    /// Delivery notices of a message sent before the channel was rebound.
+    delivered: Option<broadcast::Sender<bool>>,
+}
+
 struct SentMessage {
     pub packet: Packet,
+    pub raw: Vec<u8>,
+    pub sequence: u16,
     pub delivered: broadcast::Sender<bool>,
     pub tries: u16,
+    pub part: Option<TransferPart>,
 }
 
 struct Inbound<M: Message> {
-    on_hold: BTreeMap<u16, M>,
+    on_hold: BTreeMap<u16, Envelope>,
+    assembler: Assembler,
     incoming: broadcast::Sender<M>,
     sequence: u16,
     link_id: LinkId,
@@ -343,7 +596,10 @@ impl<M: Message> Inbound<M> {
     fn new(link_id: LinkId) -> Self {
         Self {
             on_hold: BTreeMap::new(),
-            incoming: broadcast::Sender::new(16),
+            assembler: Assembler::default(),
+            // Room for a full window of messages, which the peer may send
+            // before the application reads any of them
+            incoming: broadcast::Sender::new(WINDOW_MAX as usize),
             sequence: 0u16,
             link_id,
         }
@@ -356,10 +612,10 @@ impl<M: Message> Inbound<M> {
     pub async fn receive(&mut self, raw: &[u8]) {
         log::trace!("channel({}) received {}B", self.link_id, raw.len());
 
-        let envelope = match Envelope::<M>::unpack(raw) {
+        let envelope = match Envelope::unpack(raw, self.link_id) {
             Ok(env) => env,
-            Err(_) => {
-                log::error!("channel({}): error unpacking message", self.link_id);
+            Err(err) => {
+                log::error!("{}", err);
                 return;
             }
         };
@@ -375,23 +631,51 @@ impl<M: Message> Inbound<M> {
             }
         }
 
-        let replaced = self.on_hold.insert(sequence, envelope.message);
+        let replaced = self.on_hold.insert(sequence, envelope);
 
         if replaced.is_some() {
             log::trace!("channel({}): duplicate message received", self.link_id);
         }
 
-        while let Some(message) = self.on_hold.remove(&self.sequence) {
-            let result = self.incoming.send(message);
+        while let Some(envelope) = self.on_hold.remove(&self.sequence) {
+            self.deliver(envelope);
+            self.sequence = self.sequence.wrapping_add(1);
+        }
+    }
+
+    fn deliver(&mut self, envelope: Envelope) {
+        let Envelope { message_type, sequence, payload } = envelope;
 
-            if result.is_err() {
+        let (message_type, payload) = if message_type == FRAGMENT_MESSAGE_TYPE {
+            match self.assembler.add(&payload) {
+                Some(assembled) => assembled,
+                None => return,
+            }
+        } else {
+            if !self.assembler.is_empty() {
                 log::warn!(
-                    "channel({}): received a message that will not be processed (no subscribers)",
-                    self.link_id,
+                    "channel({}): dropping incomplete fragmented message",
+                    self.link_id
                 );
+                self.assembler.reset();
             }
 
-            self.sequence = self.sequence.wrapping_add(1);
+            (message_type, payload)
+        };
+
+        let message = match unpack_message(self.link_id, message_type, sequence, &payload) {
+            Ok(message) => message,
+            Err(err) => {
+                log::error!("{}", err);
+                return;
+            }
+        };
+
+        if self.incoming.send(message).is_err() {
+            log::warn!(
+                "channel({}): received a message that will not be processed (no subscribers)",
+                self.link_id,
+            );
         }
     }
 }
@@ -402,11 +686,16 @@ struct Outbound {
     outlet: Arc<Mutex<Link>>,
     link_id: LinkId,
     sent_messages: BTreeMap<Hash, SentMessage>,
+    pending: VecDeque<PendingPacket>,
     delivered: BTreeSet<Hash>,
     next_sequence: u16,
     params: Arc<Mutex<ChannelParams>>,
     timeouts_tx: mpsc::Sender<Hash>,
     cancel: CancellationToken,
+    /// Transfers which aren't finished.
+    transfers: Vec<Transfer>,
+    /// Notified when a transfer is paused, resumed or cancelled.
+    transfers_wake: Arc<Notify>,
 }
 
 
@@ -425,11 +714,14 @@ impl Outbound {
             outlet,
             link_id,
             sent_messages: BTreeMap::new(),
+            pending: VecDeque::new(),
             delivered: BTreeSet::new(),
             next_sequence: 0,
             params,
             timeouts_tx,
             cancel: CancellationToken::new(),
+            transfers: Vec::new(),
+            transfers_wake: Arc::new(Notify::new()),
         }
     }
 
@@ -437,23 +729,38 @@ impl Outbound {
         self.cancel.clone()
     }
 
+    fn transfers_wake(&self) -> Arc<Notify> {
+        self.transfers_wake.clone()
+    }
+
     pub (crate) fn link_id(&self) -> LinkId {
         self.link_id
     }
 
-    async fn is_ready_to_send(&self) -> bool {
+    async fn check_ready(&self) -> Result<(), ChannelError> {
+        let link_id = self.link_id;
+
         if self.cancel.is_cancelled() {
-            return false;
+            return Err(ChannelError::Closed { link_id });
         }
 
-        if !outlet_is_usable(&self.outlet).await {
-            return false
+        let status = outlet_status(&self.outlet).await;
+        if status != LinkStatus::Active {
+            return Err(ChannelError::LinkNotReady { link_id, status });
         }
 
-        let outstanding = self.sent_messages.len();
+        let outstanding = self.sent_messages.len() + self.pending.len();
         let window = self.params.lock().await.window as usize;
 
-        outstanding < window
+        if outstanding >= window {
+            return Err(ChannelError::WindowFull { link_id, outstanding, window });
+        }
+
+        Ok(())
+    }
+
+    async fn is_ready_to_send(&self) -> bool {
+        self.check_ready().await.is_ok()
     }
 
     async fn handle_proof(&mut self, packet_hash: Hash) {
@@ -474,6 +781,11 @@ impl Outbound {
 
         self.delivered.insert(packet_hash);
 
+        if let Some(part) = &sent_message.part {
+            part.transfer.part_done(part.bytes);
+            self.transfers.retain(|transfer| !transfer.state().is_finished());
+        }
+
         let result = sent_message.delivered.send(true);
 
         if let Err(e) = result {
@@ -484,6 +796,8 @@ impl Outbound {
                 e
             );
         }
+
+        self.flush().await;
     }
 
     async fn handle_timeout(&mut self, packet_hash: Hash) {
@@ -510,7 +824,7 @@ impl Outbound {
             return;
         }
 
-        let sent = outlet_resend(
+        let sent = outlet_send(
             &self.outlet,
             packet,
             self.transport.clone()
@@ -553,63 +867,251 @@ impl Outbound {
     async fn teardown(&mut self) {
         log::info!("channel({}): message timed out, tearing down channel", self.link_id);
         outlet_timed_out(&self.outlet).await;
+
+        for transfer in self.transfers.drain(..) {
+            transfer.set_state(TransferState::Failed);
+        }
     }
 
-    pub async fn send<M: Message>(&mut self, message: &M) -> Result<Hash, RnsError> {
-        let transport = match self.transport.upgrade() {
-            Some(t) => t,
-            None => {
-                return Err(RnsError::ChannelLinkNotReady);
+    /// Packs `message` and returns the payloads of its envelopes with their
+    /// message types.
+    async fn fragments<M: Message>(
+        &self,
+        message: &M,
+    ) -> Result<Vec<(u16, Vec<u8>)>, ChannelError> {
+        if self.transport.upgrade().is_none() {
+            return Err(ChannelError::TransportDropped { link_id: self.link_id });
+        }
+
+        self.check_ready().await?;
+
+        let packed = message.pack();
+        let message_type = message.message_type();
+
+        if packed.len() <= CHANNEL_MDU {
+            return Ok(vec![(message_type, packed)]);
+        }
+
+        let fragments = fragment_raw(&packed, message_type).ok_or(
+            ChannelError::MessageTooBig {
+                link_id: self.link_id,
+                size: packed.len(),
+                max: MAX_MESSAGE_SIZE,
             }
-        };
+        )?;
+
+        Ok(fragments.into_iter().map(|f| (FRAGMENT_MESSAGE_TYPE, f)).collect())
+    }
 
-        if !self.is_ready_to_send().await {
-            return Err(RnsError::ChannelLinkNotReady);
+    /// Queues the envelopes of a message and returns the hash of the last
+    /// one.
+    async fn enqueue(
+        &mut self,
+        fragments: Vec<(u16, Vec<u8>)>,
+        transfer: Option<(&Transfer, u16)>,
+    ) -> Result<Hash, ChannelError> {
+        let mut packets = Vec::with_capacity(fragments.len());
+        for (message_type, payload) in fragments {
+            let sequence = self.next_sequence;
+            let raw = envelope_raw(&payload, message_type, Some(sequence));
+            let packet = outlet_packet(&self.outlet, &raw).await?;
+
+            let part = transfer.map(|(transfer, original_type)| TransferPart {
+                transfer: transfer.clone(),
+                message_type: original_type,
+                bytes: message_bytes(message_type, &payload),
+            });
+
+            packets.push(PendingPacket { packet, raw, sequence, part, delivered: None });
+
+            self.next_sequence = self.next_sequence.wrapping_add(1);
         }
 
-        let sequence = self.next_sequence;
+        let packet_hash = packets.last().map(|p| p.packet.hash()).unwrap();
+
+        self.pending.extend(packets);
+        self.flush().await;
+
+        Ok(packet_hash)
+    }
+
+    pub async fn send<M: Message>(&mut self, message: &M) -> Result<Hash, ChannelError> {
+        let fragments = self.fragments(message).await?;
+
+        self.enqueue(fragments, None).await
+    }
 
-        self.next_sequence = self.next_sequence.wrapping_add(1);
+    pub async fn send_transfer<M: Message>(
+        &mut self,
+        message: &M,
+    ) -> Result<TransferHandle, ChannelError> {
+        let fragments = self.fragments(message).await?;
+
+        let parts = fragments.len();
+        let bytes = fragments
+            .iter()
+            .map(|(message_type, payload)| message_bytes(*message_type, payload))
+            .sum();
+        let (transfer, handle) = Transfer::new(bytes, parts, self.transfers_wake());
+
+        self.transfers.push(transfer.clone());
+        self.enqueue(fragments, Some((&transfer, message.message_type()))).await?;
+
+        Ok(handle)
+    }
+
+    /// Applies what the application asked of the transfers. The pending
+    /// fragments of cancelled transfers are replaced by abort fragments.
+    async fn apply_transfer_commands(&mut self) {
+        for pending in self.pending.iter_mut() {
+            let Some(part) = &pending.part else {
+                continue;
+            };
+
+            if part.transfer.command() != TransferCommand::Cancel {
+                continue;
+            }
+
+            let abort = abort_fragment(part.message_type);
+            let raw = envelope_raw(&abort, FRAGMENT_MESSAGE_TYPE, Some(pending.sequence));
+            match outlet_packet(&self.outlet, &raw).await {
+                Ok(packet) => {
+                    pending.packet = packet;
+                    pending.raw = raw;
+                }
+                Err(err) => log::warn!(
+                    "channel({}): can't abort cancelled message: {}",
+                    self.link_id,
+                    err
+                ),
+            }
 
-        let packet_hash;
-        {
-            let raw = message_raw(message, Some(sequence));
+            pending.part = None;
+        }
 
-            if raw.len() > PACKET_MDU {
-                return Err(RnsError::ChannelMessageTooBig);
+        for transfer in &self.transfers {
+            match transfer.command() {
+                TransferCommand::Run if transfer.state() == TransferState::Paused => {
+                    transfer.set_state(TransferState::Active)
+                }
+                TransferCommand::Pause => transfer.set_state(TransferState::Paused),
+                TransferCommand::Cancel => transfer.set_state(TransferState::Cancelled),
+                TransferCommand::Run => {}
             }
+        }
 
-            let (packet, sent) = outlet_send(&self.outlet, &raw, &transport).await;
-            packet_hash = packet.hash();
+        self.transfers.retain(|transfer| !transfer.state().is_finished());
+    }
 
-            let (delivery_tx, delivery_rx) = broadcast::channel(1);
+    /// Sends pending packets while the window has room for them and the
+    /// transfer of the next one isn't paused.
+    async fn flush(&mut self) {
+        self.apply_transfer_commands().await;
 
-            if sent {
-                let sent_message = SentMessage {
-                    packet,
-                    delivered: delivery_tx,
-                    tries: 1,
-                };
+        loop {
+            let window = self.params.lock().await.window as usize;
+            if self.sent_messages.len() >= window {
+                return;
+            }
+
+            let Some(pending) = self.pending.front() else {
+                return;
+            };
 
-                self.sent_messages.insert(packet_hash, sent_message);
+            if pending
+                .part
+                .as_ref()
+                .is_some_and(|part| part.transfer.command() == TransferCommand::Pause)
+            {
+                return;
             }
 
-            let tries = if sent { 1 } else { 0 };
-            let rtt = *self.outlet.lock().await.rtt();
+            let pending = self.pending.pop_front().unwrap();
+            self.transmit(pending).await;
+        }
+    }
 
-            let ring_len = self.sent_messages.len();
-            let timeout = packet_timeout_time(rtt, ring_len, tries);
+    async fn transmit(&mut self, pending: PendingPacket) {
+        let PendingPacket { packet, raw, sequence, part, delivered } = pending;
+        let packet_hash = packet.hash();
 
-            watch_message_try(
-                self.timeouts_tx.clone(),
-                packet_hash,
-                timeout,
-                delivery_rx,
-                self.cancel.clone(),
-            );
+        let sent = outlet_send(&self.outlet, packet, self.transport.clone()).await;
+
+        let delivery_tx = delivered.unwrap_or_else(|| broadcast::channel(1).0);
+        let delivery_rx = delivery_tx.subscribe();
+
+        if sent {
+            let sent_message = SentMessage {
+                packet,
+                raw,
+                sequence,
+                delivered: delivery_tx,
+                tries: 1,
+                part,
+            };
+
+            self.sent_messages.insert(packet_hash, sent_message);
+        } else if let Some(part) = part {
+            part.transfer.set_state(TransferState::Failed);
         }
 
-        Ok(packet_hash)
+        let tries = if sent { 1 } else { 0 };
+        let rtt = *self.outlet.lock().await.rtt();
+
+        let ring_len = self.sent_messages.len();
+        let timeout = packet_timeout_time(rtt, ring_len, tries);
+
+        watch_message_try(
+            self.timeouts_tx.clone(),
+            packet_hash,
+            timeout,
+            delivery_rx,
+            self.cancel.clone(),
+        );
+    }
+
+    /// Moves the channel over to `outlet`. The messages awaiting delivery
+    /// are packed for the new link and queued in front of the pending ones,
+    /// in the order of their sequence numbers.
+    async fn rebind(
+        &mut self,
+        outlet: Arc<Mutex<Link>>,
+        transport: &Arc<Mutex<Transport>>,
+        timeouts_tx: mpsc::Sender<Hash>,
+    ) -> Result<(), ChannelError> {
+        // Stops the timeout watchers and the tasks serving the old link
+        self.cancel.cancel();
+        self.cancel = CancellationToken::new();
+
+        self.transport = Arc::downgrade(transport);
+        self.outlet = outlet;
+        self.link_id = *self.outlet.lock().await.id();
+        self.timeouts_tx = timeouts_tx;
+
+        let next_sequence = self.next_sequence;
+        let mut unacked: Vec<SentMessage> = core::mem::take(&mut self.sent_messages)
+            .into_values()
+            .collect();
+        unacked.sort_by_key(|sent| sent.sequence.wrapping_sub(next_sequence));
+
+        let resent = unacked.into_iter().map(|sent| PendingPacket {
+            packet: sent.packet,
+            raw: sent.raw,
+            sequence: sent.sequence,
+            part: sent.part,
+            delivered: Some(sent.delivered),
+        });
+        let mut pending: VecDeque<PendingPacket> = resent.collect();
+        pending.append(&mut self.pending);
+
+        for pending in pending.iter_mut() {
+            pending.packet = outlet_packet(&self.outlet, &pending.raw).await?;
+        }
+
+        self.pending = pending;
+        self.flush().await;
+
+        Ok(())
     }
 
     pub async fn watch_delivery(
@@ -632,6 +1134,8 @@ impl Outbound {
             None => {
                 if self.delivered.contains(packet_hash) {
                     MessageStatus::Delivered
+                } else if self.pending.iter().any(|p| p.packet.hash() == *packet_hash) {
+                    MessageStatus::Waiting
                 } else {
                     MessageStatus::Unknown
                 }
@@ -648,21 +1152,26 @@ async fn spawn_watch_outbound(
 ) {
     let cancel = outbound.lock().await.cancel();
     let our_link_id = outbound.lock().await.link_id();
+    let transfers_wake = outbound.lock().await.transfers_wake();
 
-    tokio::spawn(async move {
+    runtime::spawn(async move {
         loop {
             tokio::select! {
                 event_data = out_link_events.recv() => {
+                    // The events of all links of the transport arrive here, so
+                    // busy neighbours can make this receiver lag. Missed
+                    // proofs are made up for by retransmissions.
                     let event_data = match event_data {
                         Ok(ev) => ev,
-                        Err(err) => {
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
                             log::warn!(
-                                "channel({}): error {} watching outlink events, may miss delivery proofs",
+                                "channel({}): missed {} link events, may miss delivery proofs",
                                 our_link_id,
-                                err
+                                missed
                             );
-                            return;
+                            continue;
                         }
+                        Err(broadcast::error::RecvError::Closed) => return,
                     };
 
                     if event_data.id == our_link_id {
@@ -678,6 +1187,10 @@ async fn spawn_watch_outbound(
                     }
                 },
 
+                _ = transfers_wake.notified() => {
+                    outbound.lock().await.flush().await;
+                },
+
                 _ = cancel.cancelled() => {
                     break;
                 }
@@ -688,27 +1201,29 @@ async fn spawn_watch_outbound(
 
 
 async fn spawn_receiver<M: Message>(
+    inbound: Arc<Mutex<Inbound<M>>>,
     mut rx: broadcast::Receiver<LinkPayload>,
-    our_link_id: LinkId,
     cancel: CancellationToken,
-) -> broadcast::Sender<M> {
-    let mut inbound = Inbound::new(our_link_id);
-    let incoming = inbound.get_incoming();
+) {
+    let our_link_id = inbound.lock().await.link_id;
 
-    tokio::spawn(async move {
+    runtime::spawn(async move {
         loop {
             tokio::select!{
                 received = rx.recv() => {
                     match received {
-                        Ok(payload) => inbound.receive(payload.as_slice()).await,
-                        Err(err) => {
+                        Ok(payload) => {
+                            inbound.lock().await.receive(payload.as_slice()).await
+                        }
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
                             log::error!(
-                                "channel({}): error {} getting inbound message from link",
+                                "channel({}): missed {} inbound messages from link",
                                 our_link_id,
-                                err
+                                missed
                             );
                             continue;
                         }
+                        Err(broadcast::error::RecvError::Closed) => break,
                     }
                 },
 
@@ -718,8 +1233,6 @@ async fn spawn_receiver<M: Message>(
             }
         }
     });
-
-    incoming
 }
 
 
@@ -734,6 +1247,7 @@ async fn spawn_receiver<M: Message>(
 pub struct Channel<M: Message> {
     pub link: Arc<Mutex<Link>>,
     outbound: Arc<Mutex<Outbound>>,
+    inbound: Arc<Mutex<Inbound<M>>>,
     incoming: broadcast::Sender<M>,
 }
 
@@ -748,7 +1262,7 @@ impl<M: Message> Channel<M> {
     pub async fn new(
         link: Arc<Mutex<Link>>,
         transport: &Arc<Mutex<Transport>>
-    ) -> Result<(Self, broadcast::Receiver<M>), RnsError> {
+    ) -> Result<(Self, broadcast::Receiver<M>), ChannelError> {
         let (me_tx, me_rx) = mpsc::channel(16);
 
         let outbound = Outbound::new(
@@ -772,22 +1286,81 @@ impl<M: Message> Channel<M> {
 
         let rx = link.lock().await.bind_to_channel()?;
 
-        let incoming = spawn_receiver(rx, link_id, cancel).await;
+        let inbound = Arc::new(Mutex::new(Inbound::new(link_id)));
+        let incoming = inbound.lock().await.get_incoming();
         let incoming_rx = incoming.subscribe();
 
-        let channel = Self { link, outbound, incoming };
+        spawn_receiver(Arc::clone(&inbound), rx, cancel).await;
+
+        let channel = Self { link, outbound, inbound, incoming };
 
         Ok((channel, incoming_rx))
     }
 
+    /// Move the channel over to `link`, a new link to the same destination,
+    /// e. g. one reported by [`LinkEvent::Reestablished`].
+    ///
+    /// The sequence numbers continue where they left off on both sides, so
+    /// the peer has to rebind its channel as well. Messages which were not
+    /// delivered yet are resent over the new link. Their hashes change as
+    /// they are encrypted for the new link, but the receivers returned by
+    /// [`Channel::watch_message_delivery`] still report their delivery.
+    ///
+    /// Fails if `link` is not active or already wraps a channel.
+    pub async fn rebind(
+        &mut self,
+        link: Arc<Mutex<Link>>,
+        transport: &Arc<Mutex<Transport>>,
+    ) -> Result<(), ChannelError> {
+        let link_id = *link.lock().await.id();
+
+        let status = outlet_status(&link).await;
+        if status != LinkStatus::Active {
+            return Err(ChannelError::LinkNotReady { link_id, status });
+        }
+
+        let rx = link.lock().await.bind_to_channel()?;
+        let link_events = transport.lock().await.events_for_link(link_id).await;
+
+        let (me_tx, me_rx) = mpsc::channel(16);
+        let cancel = {
+            let mut outbound = self.outbound.lock().await;
+            outbound.rebind(Arc::clone(&link), transport, me_tx).await?;
+            outbound.cancel()
+        };
+
+        spawn_watch_outbound(Arc::clone(&self.outbound), link_events, me_rx).await;
+
+        self.inbound.lock().await.link_id = link_id;
+        spawn_receiver(Arc::clone(&self.inbound), rx, cancel).await;
+
+        self.link = link;
+
+        Ok(())
+    }
+
     /// Send a message over the channel.
     ///
-    /// Fails if the channel is not ready to send. If successful, it returns
-    /// the `Hash` with which the message can be identified.
-    pub async fn send(&self, message: &M) -> Result<Hash, RnsError> {
+    /// Fails if the channel is not ready to send, see
+    /// [`ChannelError::is_transient`] for the failures worth a retry. If
+    /// successful, it returns the `Hash` with which the message can be
+    /// identified. For a message split into fragments this is the hash of
+    /// the last fragment; the fragments which don't fit into the window are
+    /// sent as earlier ones are delivered.
+    pub async fn send(&self, message: &M) -> Result<Hash, ChannelError> {
         self.outbound.lock().await.send(message).await
     }
 
+    /// Send a message like [`Channel::send`], returning a handle which
+    /// reports the delivery of its fragments and can pause or cancel sending
+    /// them.
+    ///
+    /// Pausing holds back the messages sent after this one as well, as the
+    /// channel delivers messages in order.
+    pub async fn send_transfer(&self, message: &M) -> Result<TransferHandle, ChannelError> {
+        self.outbound.lock().await.send_transfer(message).await
+    }
+
     /// Get notified when a specific message's delivery is confirmed.
     pub async fn watch_message_delivery(
         &self,
@@ -827,11 +1400,11 @@ mod mock {
     use tokio::time::Duration;
 
     use crate::destination::link::{
-        LinkEvent, LinkEventData, LinkId, LinkPayload, LinkStatus
+        LinkError, LinkEvent, LinkEventData, LinkId, LinkPayload, LinkStatus
     };
-    use crate::error::RnsError;
     use crate::hash::{AddressHash, Hash};
     use crate::packet::{PacketContext, PacketDataBuffer};
+    use crate::transport::SendError;
 
     #[derive(Clone, Copy)]
     pub struct Packet {
@@ -895,7 +1468,11 @@ mod mock {
             self.status
         }
 
-        pub fn data_packet(&self, raw: &[u8]) -> Result<Packet, RnsError> {
+        pub fn data_packet(&self, raw: &[u8]) -> Result<Packet, LinkError> {
+            if self.status != LinkStatus::Active && self.status != LinkStatus::Stale {
+                return Err(LinkError::NotActive { link_id: self.id, status: self.status });
+            }
+
             Ok(Packet::new(raw, self.id))
         }
 
@@ -905,9 +1482,9 @@ mod mock {
 
         pub fn bind_to_channel(
             &mut self
-        ) -> Result<broadcast::Receiver<LinkPayload>, RnsError> {
+        ) -> Result<broadcast::Receiver<LinkPayload>, LinkError> {
             if self.bound {
-                return Err(RnsError::ChannelError);
+                return Err(LinkError::ChannelBound { link_id: self.id });
             }
 
             self.bound = true;
@@ -937,8 +1514,9 @@ mod mock {
             self.out_tx.subscribe()
         }
 
-        pub async fn send_packet(&self, packet: Packet) {
+        pub async fn send_packet(&self, packet: Packet) -> Result<(), SendError> {
             self.packets.lock().await.push(packet);
+            Ok(())
         }
 
         // helper method
@@ -984,7 +1562,8 @@ mod tests {
     #[derive(Clone, Debug, PartialEq)]
     enum TestMessage {
         Long(u64),
-        Short(u32)
+        Short(u32),
+        Bulk(Vec<u8>),
     }
 
     impl Message for TestMessage {
@@ -997,6 +1576,8 @@ mod tests {
                 if let Ok(a) = <[u8; 4]>::try_from(packed) {
                     return Ok(Self::Short(u32::from_le_bytes(a)));
                 }
+            } else if message_type == 3 {
+                return Ok(Self::Bulk(packed.to_vec()));
             }
 
             Err(RnsError::ChannelUnknownMessageType)
@@ -1005,7 +1586,8 @@ mod tests {
         fn pack(&self) -> Vec<u8> {
             match self {
                 Self::Long(x) => x.to_le_bytes().to_vec(),
-                Self::Short(x) => x.to_le_bytes().to_vec()
+                Self::Short(x) => x.to_le_bytes().to_vec(),
+                Self::Bulk(x) => x.clone(),
             }
         }
 
@@ -1013,6 +1595,7 @@ mod tests {
             match self {
                 Self::Long(_) => 1,
                 Self::Short(_) => 2,
+                Self::Bulk(_) => 3,
             }
         }
     }
@@ -1112,8 +1695,13 @@ mod tests {
 
         assert!(!channel_a.is_ready().await);
 
+        let link_id = *fixture.link_a.lock().await.id();
+
         let result = channel_a.send(&TestMessage::Short(0)).await;
-        assert_eq!(result, Err(RnsError::ChannelLinkNotReady));
+        assert_eq!(
+            result,
+            Err(ChannelError::LinkNotReady { link_id, status: LinkStatus::Pending })
+        );
 
         fixture.link_a.lock().await.status = LinkStatus::Active;
 
@@ -1130,7 +1718,174 @@ mod tests {
         assert!(!channel_a.is_ready().await);
 
         let result = channel_a.send(&TestMessage::Short(3)).await;
-        assert_eq!(result, Err(RnsError::ChannelLinkNotReady));
+        assert_eq!(
+            result,
+            Err(ChannelError::WindowFull { link_id, outstanding: 2, window: 2 })
+        );
+        assert!(result.unwrap_err().is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_fragmented_message() {
+        let fixture = Fixture::new();
+
+        let (channel_a, _) = Channel::<TestMessage>::new(
+            fixture.link_a.clone(),
+            &fixture.transport_a
+        ).await.unwrap();
+
+        let (_channel_b, mut incoming_b) = Channel::<TestMessage>::new(
+            fixture.link_b.clone(),
+            &fixture.transport_b
+        ).await.unwrap();
+
+        let bulk: Vec<u8> = (0..3 * CHANNEL_MDU).map(|i| i as u8).collect();
+        let last_hash = channel_a.send(&TestMessage::Bulk(bulk.clone())).await.unwrap();
+
+        // Only a window full of fragments is sent right away
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 2);
+        assert_eq!(channel_a.message_status(&last_hash).await, MessageStatus::Waiting);
+        assert!(!channel_a.is_ready().await);
+
+        for packet in &packets {
+            fixture.transport_a.lock().await.out_tx.send(packet.prove()).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 4);
+        assert_eq!(channel_a.message_status(&last_hash).await, MessageStatus::Sent(1));
+
+        // Fragments arriving out of order are reassembled in sequence
+        for packet in packets.iter().rev() {
+            fixture.link_b.lock().await.tx.send(packet.payload()).unwrap();
+        }
+
+        let incoming = incoming_b.recv().await.expect("expected incoming message");
+        assert_eq!(incoming, TestMessage::Bulk(bulk));
+
+        let too_big = TestMessage::Bulk(vec![0u8; MAX_MESSAGE_SIZE + 1]);
+        let (channel_c, _) = Channel::<TestMessage>::new(
+            Arc::new(Mutex::new(Link::new(LinkStatus::Active))),
+            &fixture.transport_b
+        ).await.unwrap();
+        assert!(matches!(
+            channel_c.send(&too_big).await,
+            Err(ChannelError::MessageTooBig { max: MAX_MESSAGE_SIZE, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let fixture = Fixture::new();
+
+        let (channel_a, _) = Channel::<TestMessage>::new(
+            fixture.link_a.clone(),
+            &fixture.transport_a
+        ).await.unwrap();
+
+        let (_channel_b, mut incoming_b) = Channel::<TestMessage>::new(
+            fixture.link_b.clone(),
+            &fixture.transport_b
+        ).await.unwrap();
+
+        let bulk: Vec<u8> = (0..12 * FRAGMENT_MDU).map(|i| i as u8).collect();
+        let transfer = channel_a.send_transfer(&TestMessage::Bulk(bulk)).await.unwrap();
+
+        let progress = transfer.progress();
+        assert_eq!(progress.total_parts, 12);
+        assert_eq!(progress.total_bytes, 12 * FRAGMENT_MDU);
+        assert_eq!(progress.state, TransferState::Active);
+
+        // A paused transfer sends no further fragments
+        transfer.pause();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(transfer.progress().state, TransferState::Paused);
+
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 2);
+        for packet in &packets {
+            fixture.transport_a.lock().await.out_tx.send(packet.prove()).unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(fixture.transport_a.lock().await.packets().await.len(), 2);
+        assert_eq!(transfer.progress().done_parts, 2);
+        assert_eq!(transfer.progress().done_bytes, 2 * FRAGMENT_MDU);
+        assert!(transfer.throughput() > 0.0);
+
+        transfer.resume();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(transfer.progress().state, TransferState::Active);
+        let sent = fixture.transport_a.lock().await.packets().await.len();
+        assert!(sent > 2 && sent < 12);
+
+        // The fragments left are replaced by abort fragments
+        transfer.cancel();
+        assert_eq!(transfer.finished().await, TransferState::Cancelled);
+
+        let mut proven = 2;
+        loop {
+            let packets = fixture.transport_a.lock().await.packets().await;
+            if proven == packets.len() {
+                break;
+            }
+            for packet in &packets[proven..] {
+                fixture.transport_a.lock().await.out_tx.send(packet.prove()).unwrap();
+            }
+            proven = packets.len();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 12);
+        assert!(packets[sent].data.len() < packets[sent - 1].data.len());
+
+        // The receiver drops the cancelled message and carries on
+        channel_a.send(&TestMessage::Short(7)).await.unwrap();
+        let packets = fixture.transport_a.lock().await.packets().await;
+        for packet in &packets {
+            fixture.link_b.lock().await.tx.send(packet.payload()).unwrap();
+        }
+
+        let incoming = incoming_b.recv().await.expect("expected incoming message");
+        assert_eq!(incoming, TestMessage::Short(7));
+    }
+
+    #[tokio::test]
+    async fn test_channel_errors() {
+        let fixture = Fixture::new();
+        let link_id = *fixture.link_a.lock().await.id();
+
+        let (_channel_a, _) = Channel::<TestMessage>::new(
+            fixture.link_a.clone(),
+            &fixture.transport_a
+        ).await.unwrap();
+
+        let result = Channel::<TestMessage>::new(
+            fixture.link_a.clone(),
+            &fixture.transport_a
+        ).await;
+        let error = result.err().expect("link is already bound");
+        assert_eq!(error, ChannelError::Link(LinkError::ChannelBound { link_id }));
+        assert!(std::error::Error::source(&error).is_some());
+        assert_eq!(RnsError::from(error), RnsError::ChannelError);
+
+        let error = Envelope::unpack(&[0x00, 0x01], link_id).err().unwrap();
+        assert_eq!(error, ChannelError::MalformedEnvelope { link_id, len: 2 });
+
+        let error = unpack_message::<TestMessage>(link_id, 7, 3, &[0x01]).unwrap_err();
+        assert_eq!(
+            error,
+            ChannelError::Unpack {
+                link_id,
+                message_type: 7,
+                sequence: 3,
+                source: RnsError::ChannelUnknownMessageType,
+            }
+        );
+        assert!(error.to_string().contains("type 0x0007"));
     }
 
     #[tokio::test]
@@ -1213,4 +1968,65 @@ mod tests {
 
         assert!(incoming_b.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_rebind() {
+        let fixture = Fixture::new();
+
+        let (mut channel_a, _) = Channel::<TestMessage>::new(
+            fixture.link_a.clone(),
+            &fixture.transport_a
+        ).await.unwrap();
+
+        let (mut channel_b, mut incoming_b) = Channel::<TestMessage>::new(
+            fixture.link_b.clone(),
+            &fixture.transport_b
+        ).await.unwrap();
+
+        channel_a.send(&TestMessage::Short(1)).await.unwrap();
+        let second = channel_a.send(&TestMessage::Short(2)).await.unwrap();
+        let mut delivered = channel_a.watch_message_delivery(second).await.unwrap();
+
+        // Only the first message arrives before the link dies
+        let packets = fixture.transport_a.lock().await.packets().await;
+        fixture.link_b.lock().await.tx.send(packets[0].payload()).unwrap();
+        assert_eq!(incoming_b.recv().await.unwrap(), TestMessage::Short(1));
+
+        fixture.link_a.lock().await.close();
+        fixture.link_b.lock().await.close();
+
+        let pending = Arc::new(Mutex::new(Link::new(LinkStatus::Pending)));
+        assert!(matches!(
+            channel_a.rebind(pending, &fixture.transport_a).await,
+            Err(ChannelError::LinkNotReady { status: LinkStatus::Pending, .. })
+        ));
+
+        let link_c = Arc::new(Mutex::new(Link::new(LinkStatus::Active)));
+        let link_d = Arc::new(Mutex::new(Link::new(LinkStatus::Active)));
+        channel_a.rebind(link_c.clone(), &fixture.transport_a).await.unwrap();
+        channel_b.rebind(link_d.clone(), &fixture.transport_b).await.unwrap();
+
+        // Both unacknowledged messages are resent over the new link
+        let link_c_id = *link_c.lock().await.id();
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 4);
+        assert!(packets[2..].iter().all(|packet| packet.id == link_c_id));
+
+        // The receiver drops the duplicate and continues in sequence
+        for packet in &packets[2..] {
+            link_d.lock().await.tx.send(packet.payload()).unwrap();
+            fixture.transport_a.lock().await.out_tx.send(packet.prove()).unwrap();
+        }
+        assert_eq!(incoming_b.recv().await.unwrap(), TestMessage::Short(2));
+        assert!(delivered.recv().await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        channel_a.send(&TestMessage::Short(3)).await.unwrap();
+        let packets = fixture.transport_a.lock().await.packets().await;
+        assert_eq!(packets.len(), 5);
+
+        link_d.lock().await.tx.send(packets[4].payload()).unwrap();
+        assert_eq!(incoming_b.recv().await.unwrap(), TestMessage::Short(3));
+    }
 }
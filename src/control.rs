@@ -0,0 +1,479 @@
+//! Control port RPC of a running instance.
+//!
+//! A daemon serves its [`Transport`] with a [`Server`] on the instance
+//! control port, other processes on the same machine use a [`Client`] to
+//! query paths, set up links and send payloads through it.
+//!
+//! Every message is a msgpack array prefixed with its length as a 32 bit big
+//! endian integer. A request is `[method, args...]`, the response is
+//! `[true, result...]` or `[false, error message]`. Requests on a
+//! connection are answered in order.
+//!
+//! The protocol is specific to this implementation, Python programs can't
+//! talk to it. There is no authentication, so the server should only listen
+//! on the loopback interface.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio_util::sync::CancellationToken;
+
+use crate::destination::link::{LinkId, LinkStatus};
+use crate::hash::{AddressHash, Hash, ADDRESS_HASH_SIZE, HASH_SIZE};
+use crate::iface::{InterfaceMode, InterfaceSummary};
+use crate::msgpack::{Reader, Writer};
+use crate::runtime::Instant;
+use crate::transport::{TrafficStats, Transport};
+
+/// Largest request or response.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+const METHOD_HAS_PATH: &str = "has_path";
+const METHOD_REQUEST_PATH: &str = "request_path";
+const METHOD_LINK: &str = "link";
+const METHOD_LINK_STATUS: &str = "link_status";
+const METHOD_SEND: &str = "send";
+const METHOD_CLOSE_LINK: &str = "close_link";
+const METHOD_TRAFFIC_STATS: &str = "traffic_stats";
+const METHOD_INTERFACES: &str = "interfaces";
+
+#[derive(Debug)]
+pub enum ControlError {
+    /// The connection to the control port failed.
+    Connection(io::Error),
+    /// A message couldn't be decoded.
+    Protocol,
+    /// The instance couldn't carry out the request.
+    Remote(String),
+}
+
+impl fmt::Display for ControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlError::Connection(err) => write!(f, "control connection failed: {}", err),
+            ControlError::Protocol => write!(f, "malformed control message"),
+            ControlError::Remote(message) => write!(f, "control request failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ControlError {}
+
+impl From<io::Error> for ControlError {
+    fn from(err: io::Error) -> Self {
+        ControlError::Connection(err)
+    }
+}
+
+async fn read_message<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Vec<u8>> {
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "control message too large"));
+    }
+
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message).await?;
+
+    Ok(message)
+}
+
+async fn write_message<S: AsyncWrite + Unpin>(stream: &mut S, message: &[u8]) -> io::Result<()> {
+    stream.write_u32(message.len() as u32).await?;
+    stream.write_all(message).await?;
+    stream.flush().await
+}
+
+fn read_address(reader: &mut Reader) -> Option<AddressHash> {
+    let bytes: [u8; ADDRESS_HASH_SIZE] = reader.bin().ok()?.try_into().ok()?;
+    Some(AddressHash::new(bytes))
+}
+
+fn read_hash(reader: &mut Reader) -> Option<Hash> {
+    let bytes: [u8; HASH_SIZE] = reader.bin().ok()?.try_into().ok()?;
+    Some(Hash::new(bytes))
+}
+
+fn link_status_from_u8(value: u8) -> Option<LinkStatus> {
+    match value {
+        0x00 => Some(LinkStatus::Pending),
+        0x01 => Some(LinkStatus::Handshake),
+        0x02 => Some(LinkStatus::Active),
+        0x03 => Some(LinkStatus::Stale),
+        0x04 => Some(LinkStatus::Closed),
+        _ => None,
+    }
+}
+
+/// Serves a transport on the control port.
+pub struct Server {
+    transport: Arc<Transport>,
+}
+
+impl Server {
+    pub fn new(transport: Arc<Transport>) -> Self {
+        Self { transport }
+    }
+
+    /// Accepts clients on `listener` until `cancel` is triggered.
+    pub async fn run(self, listener: TcpListener, cancel: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                result = listener.accept() => match result {
+                    Ok((stream, addr)) => {
+                        log::debug!("control: client {} connected", addr);
+
+                        let transport = self.transport.clone();
+                        let cancel = cancel.clone();
+                        tokio::spawn(async move {
+                            tokio::select! {
+                                _ = cancel.cancelled() => {}
+                                _ = Self::serve_client(&transport, stream) => {}
+                            }
+                            log::debug!("control: client {} disconnected", addr);
+                        });
+                    }
+                    Err(err) => log::warn!("control: couldn't accept client: {}", err),
+                }
+            }
+        }
+    }
+
+    async fn serve_client(transport: &Transport, mut stream: TcpStream) {
+        while let Ok(request) = read_message(&mut stream).await {
+            let response = match Self::handle_request(transport, &request).await {
+                Ok(mut response) => response.finish(),
+                Err(message) => Writer::new().array(2).bool(false).str(&message).finish(),
+            };
+
+            if write_message(&mut stream, &response).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn handle_request(transport: &Transport, request: &[u8]) -> Result<Writer, String> {
+        let mut reader = Reader::new(request);
+        let invalid = || String::from("invalid request");
+
+        reader.array().map_err(|_| invalid())?;
+        let method = reader.str().map_err(|_| invalid())?;
+
+        let mut response = Writer::new();
+
+        // The only request which isn't about a destination
+        if method == METHOD_INTERFACES {
+            let interfaces = transport.iface_manager().lock().await.interfaces();
+
+            response.array(2).bool(true).array(interfaces.len() as u32);
+            for iface in interfaces {
+                response.array(6).bin(iface.address.as_slice());
+                match &iface.name {
+                    Some(name) => response.str(name),
+                    None => response.nil(),
+                };
+                response
+                    .str(&format!("{:?}", iface.mode))
+                    .opt_uint(iface.bitrate)
+                    .uint(iface.echoes)
+                    .bool(iface.stalled);
+            }
+
+            return Ok(response);
+        }
+
+        let destination = read_address(&mut reader).ok_or_else(invalid)?;
+
+        match method {
+            METHOD_HAS_PATH => {
+                let known = transport.knows_destination(&destination).await;
+                response.array(2).bool(true).bool(known);
+            }
+            METHOD_REQUEST_PATH => {
+                transport.request_path(&destination, None, None).await;
+                response.array(1).bool(true);
+            }
+            METHOD_LINK => {
+                let desc = match transport.get_out_destination(&destination).await {
+                    Some(out_destination) => out_destination.lock().await.desc,
+                    None => return Err(format!("unknown destination {}", destination)),
+                };
+
+                let link = transport.link(desc).await;
+                let link_id = *link.lock().await.id();
+                response.array(2).bool(true).bin(link_id.as_slice());
+            }
+            METHOD_LINK_STATUS => {
+                response.array(2).bool(true);
+                match transport.find_out_link(&destination).await {
+                    Some(link) => response.uint(link.lock().await.status() as u64),
+                    None => response.nil(),
+                };
+            }
+            METHOD_SEND => {
+                let data = reader.bin().map_err(|_| invalid())?;
+                let hashes = transport.send_to_out_links(&destination, data).await;
+
+                response.array(2).bool(true).array(hashes.len() as u32);
+                for hash in hashes {
+                    response.bin(hash.as_slice());
+                }
+            }
+            METHOD_CLOSE_LINK => {
+                if let Some(link) = transport.find_out_link(&destination).await {
+                    let link_id = *link.lock().await.id();
+                    transport
+                        .link_close(link_id)
+                        .await
+                        .map_err(|err| format!("couldn't close link: {:?}", err))?;
+                }
+                response.array(1).bool(true);
+            }
+            METHOD_TRAFFIC_STATS => {
+                response.array(2).bool(true);
+                match transport.traffic_stats(&destination).await {
+                    Some(stats) => response
+                        .array(5)
+                        .uint(stats.packets_sent)
+                        .uint(stats.bytes_sent)
+                        .uint(stats.packets_received)
+                        .uint(stats.bytes_received)
+                        .opt_uint(
+                            stats
+                                .last_activity
+                                .map(|instant| instant.elapsed().as_millis() as u64),
+                        ),
+                    None => response.nil(),
+                };
+            }
+            _ => return Err(format!("unknown method {}", method)),
+        }
+
+        Ok(response)
+    }
+}
+
+/// Connection to the control port of a running instance.
+pub struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, ControlError> {
+        Ok(Self {
+            stream: TcpStream::connect(addr).await?,
+        })
+    }
+
+    async fn call(&mut self, request: &[u8]) -> Result<Vec<u8>, ControlError> {
+        write_message(&mut self.stream, request).await?;
+        let response = read_message(&mut self.stream).await?;
+
+        let mut reader = Reader::new(&response);
+        reader.array().map_err(|_| ControlError::Protocol)?;
+        if reader.bool().map_err(|_| ControlError::Protocol)? {
+            Ok(reader.remaining().to_vec())
+        } else {
+            let message = reader.str().map_err(|_| ControlError::Protocol)?;
+            Err(ControlError::Remote(message.into()))
+        }
+    }
+
+    async fn call_for(
+        &mut self,
+        method: &str,
+        destination: &AddressHash,
+    ) -> Result<Vec<u8>, ControlError> {
+        let request = Writer::new()
+            .array(2)
+            .str(method)
+            .bin(destination.as_slice())
+            .finish();
+
+        self.call(&request).await
+    }
+
+    /// Returns whether the instance knows a path to `destination`.
+    pub async fn has_path(&mut self, destination: &AddressHash) -> Result<bool, ControlError> {
+        let result = self.call_for(METHOD_HAS_PATH, destination).await?;
+        Reader::new(&result).bool().map_err(|_| ControlError::Protocol)
+    }
+
+    /// Asks the instance to request a path to `destination`.
+    pub async fn request_path(&mut self, destination: &AddressHash) -> Result<(), ControlError> {
+        self.call_for(METHOD_REQUEST_PATH, destination).await?;
+        Ok(())
+    }
+
+    /// Makes the instance set up a link to an announced destination, or
+    /// reuse its existing one.
+    pub async fn link(&mut self, destination: &AddressHash) -> Result<LinkId, ControlError> {
+        let result = self.call_for(METHOD_LINK, destination).await?;
+        read_address(&mut Reader::new(&result)).ok_or(ControlError::Protocol)
+    }
+
+    /// Status of the link of the instance to `destination`.
+    pub async fn link_status(
+        &mut self,
+        destination: &AddressHash,
+    ) -> Result<Option<LinkStatus>, ControlError> {
+        let result = self.call_for(METHOD_LINK_STATUS, destination).await?;
+
+        let mut reader = Reader::new(&result);
+        if reader.nil() {
+            return Ok(None);
+        }
+
+        let status = reader.uint::<u8>().map_err(|_| ControlError::Protocol)?;
+        link_status_from_u8(status).map(Some).ok_or(ControlError::Protocol)
+    }
+
+    /// Sends `data` over the active links to `destination` and returns the
+    /// hashes of the sent packets, which is empty if no link is active.
+    pub async fn send(
+        &mut self,
+        destination: &AddressHash,
+        data: &[u8],
+    ) -> Result<Vec<Hash>, ControlError> {
+        let request = Writer::new()
+            .array(3)
+            .str(METHOD_SEND)
+            .bin(destination.as_slice())
+            .bin(data)
+            .finish();
+
+        let result = self.call(&request).await?;
+
+        let mut reader = Reader::new(&result);
+        let len = reader.array().map_err(|_| ControlError::Protocol)?;
+        (0..len)
+            .map(|_| read_hash(&mut reader).ok_or(ControlError::Protocol))
+            .collect()
+    }
+
+    /// Data packets the instance exchanged with `destination`, `None` if
+    /// there were none.
+    pub async fn traffic_stats(
+        &mut self,
+        destination: &AddressHash,
+    ) -> Result<Option<TrafficStats>, ControlError> {
+        let result = self.call_for(METHOD_TRAFFIC_STATS, destination).await?;
+
+        let mut reader = Reader::new(&result);
+        if reader.nil() {
+            return Ok(None);
+        }
+
+        let protocol = |_| ControlError::Protocol;
+        reader.array().map_err(protocol)?;
+        let packets_sent = reader.uint().map_err(protocol)?;
+        let bytes_sent = reader.uint().map_err(protocol)?;
+        let packets_received = reader.uint().map_err(protocol)?;
+        let bytes_received = reader.uint().map_err(protocol)?;
+        let last_activity = if reader.nil() {
+            None
+        } else {
+            let elapsed = Duration::from_millis(reader.uint().map_err(protocol)?);
+            Instant::now().checked_sub(elapsed)
+        };
+
+        Ok(Some(TrafficStats {
+            packets_sent,
+            bytes_sent,
+            packets_received,
+            bytes_received,
+            last_activity,
+        }))
+    }
+
+    /// The running interfaces of the instance.
+    pub async fn interfaces(&mut self) -> Result<Vec<InterfaceSummary>, ControlError> {
+        let request = Writer::new().array(1).str(METHOD_INTERFACES).finish();
+        let result = self.call(&request).await?;
+
+        let protocol = |_| ControlError::Protocol;
+        let mut reader = Reader::new(&result);
+        let len = reader.array().map_err(protocol)?;
+
+        let mut interfaces = Vec::new();
+        for _ in 0..len {
+            reader.array().map_err(protocol)?;
+            let address = read_address(&mut reader).ok_or(ControlError::Protocol)?;
+            let name = if reader.nil() {
+                None
+            } else {
+                Some(reader.str().map_err(protocol)?.into())
+            };
+            let mode = reader.str().map_err(protocol)?.parse::<InterfaceMode>().map_err(protocol)?;
+            let bitrate = if reader.nil() { None } else { Some(reader.uint().map_err(protocol)?) };
+            let echoes = reader.uint().map_err(protocol)?;
+            let stalled = reader.bool().map_err(protocol)?;
+
+            interfaces.push(InterfaceSummary { address, name, mode, bitrate, echoes, stalled });
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Closes the link of the instance to `destination`.
+    pub async fn close_link(&mut self, destination: &AddressHash) -> Result<(), ControlError> {
+        self.call_for(METHOD_CLOSE_LINK, destination).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use crate::transport::TransportConfig;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn query_instance_over_control_port() {
+        let transport = Arc::new(Transport::new(TransportConfig::default()));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let cancel = CancellationToken::new();
+        tokio::spawn(Server::new(transport.clone()).run(listener, cancel.clone()));
+
+        let mut client = Client::connect(addr).await.unwrap();
+
+        let unknown = AddressHash::new_from_rand(OsRng);
+        assert!(!client.has_path(&unknown).await.unwrap());
+        client.request_path(&unknown).await.unwrap();
+        assert_eq!(client.link_status(&unknown).await.unwrap(), None);
+        assert!(client.send(&unknown, b"data").await.unwrap().is_empty());
+        assert!(matches!(client.link(&unknown).await, Err(ControlError::Remote(_))));
+        assert_eq!(client.traffic_stats(&unknown).await.unwrap(), None);
+
+        // The connection keeps working after failed requests
+        assert!(!client.has_path(&unknown).await.unwrap());
+
+        let channel = {
+            let iface_manager = transport.iface_manager();
+            let mut iface_manager = iface_manager.lock().await;
+            let channel = iface_manager.new_channel_with_mode(1, InterfaceMode::Gateway);
+            iface_manager.set_name(channel.address(), "Default Interface");
+            channel
+        };
+        let address = *channel.address();
+
+        let interfaces = client.interfaces().await.unwrap();
+        assert_eq!(interfaces.len(), 1);
+        assert_eq!(interfaces[0].address, address);
+        assert_eq!(interfaces[0].name.as_deref(), Some("Default Interface"));
+        assert_eq!(interfaces[0].mode, InterfaceMode::Gateway);
+
+        cancel.cancel();
+    }
+}
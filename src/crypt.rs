@@ -1 +1,2 @@
 pub mod fernet;
+pub mod rng;
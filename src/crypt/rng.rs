@@ -0,0 +1,125 @@
+//! Random number generator shared by the parts of a transport.
+//!
+//! Key generation and encryption take `R: CryptoRngCore + Copy`, which the
+//! zero-sized `OsRng` satisfies. A [`SharedRng`] wraps any other generator,
+//! e.g. a seeded one for deterministic tests or the entropy source of an
+//! embedded board, and `&SharedRng` can be passed wherever `OsRng` is.
+
+use alloc::sync::Arc;
+use std::sync::Mutex;
+
+use rand_core::{CryptoRng, CryptoRngCore, OsRng, RngCore};
+
+#[derive(Clone)]
+pub struct SharedRng(Arc<Mutex<dyn CryptoRngCore + Send>>);
+
+impl SharedRng {
+    pub fn new<R: CryptoRngCore + Send + 'static>(rng: R) -> Self {
+        Self(Arc::new(Mutex::new(rng)))
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut dyn CryptoRngCore) -> T) -> T {
+        // A panic while generating doesn't leave the generator unusable
+        let mut rng = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        f(&mut *rng)
+    }
+}
+
+impl Default for SharedRng {
+    /// The operating system's generator.
+    fn default() -> Self {
+        Self::new(OsRng)
+    }
+}
+
+impl core::fmt::Debug for SharedRng {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SharedRng")
+    }
+}
+
+impl RngCore for &SharedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.with(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.with(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.with(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.with(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+impl CryptoRng for &SharedRng {}
+
+impl RngCore for SharedRng {
+    fn next_u32(&mut self) -> u32 {
+        (&*self).next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        (&*self).next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        (&*self).fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        (&*self).try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for SharedRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts up, predictable on purpose.
+    struct CountingRng(u8);
+
+    impl RngCore for CountingRng {
+        fn next_u32(&mut self) -> u32 {
+            rand_core::impls::next_u32_via_fill(self)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            rand_core::impls::next_u64_via_fill(self)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for byte in dest {
+                *byte = self.0;
+                self.0 = self.0.wrapping_add(1);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for CountingRng {}
+
+    #[test]
+    fn copies_share_the_generator() {
+        let rng = SharedRng::new(CountingRng(0));
+        let copy = rng.clone();
+
+        let mut first = [0u8; 2];
+        let mut second = [0u8; 2];
+        (&rng).fill_bytes(&mut first);
+        (&copy).fill_bytes(&mut second);
+
+        assert_eq!(first, [0, 1]);
+        assert_eq!(second, [2, 3]);
+    }
+}
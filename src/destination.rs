@@ -1,23 +1,45 @@
+pub mod app_data;
 pub mod link;
+pub mod link_compression;
 pub mod link_map;
+pub mod link_replay;
+pub mod link_sequence;
+pub mod link_window;
+pub mod ratchets;
+pub mod request;
 
 use ed25519_dalek::{Signature, SigningKey, VerifyingKey, SIGNATURE_LENGTH};
 use rand_core::CryptoRngCore;
-use x25519_dalek::PublicKey;
+use x25519_dalek::{EphemeralSecret, PublicKey};
 
-use core::{fmt, marker::PhantomData};
+use core::{fmt, future::Future, marker::PhantomData, pin::Pin, time::Duration};
+use std::io;
+use std::path::PathBuf;
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 
 use crate::{
+    buffer::StaticBuffer,
+    crypt::fernet::{Fernet, PlainText},
     error::RnsError,
-    hash::{AddressHash, Hash},
-    identity::{EmptyIdentity, HashIdentity, Identity, PrivateIdentity, PUBLIC_KEY_LENGTH},
+    hash::{AddressHash, Hash, ADDRESS_HASH_SIZE},
+    identity::{
+        DecryptIdentity, DerivedKey, EmptyIdentity, DERIVED_KEY_LENGTH, HashIdentity, Identity, PrivateIdentity,
+        PUBLIC_KEY_LENGTH,
+    },
     packet::{
-        self, DestinationType, Header, HeaderType, IfacFlag, Packet, PacketContext,
-        PacketDataBuffer, PacketType, PropagationType,
+        self, ContextFlag, DestinationType, Header, HeaderType, IfacFlag, Packet, PacketContext,
+        PacketDataBuffer, PacketType, PropagationType, PACKET_MDU,
     },
+    runtime,
 };
 use sha2::Digest;
 
+use ratchets::{Ratchet, RatchetRetention, Ratchets};
+use request::{IncomingRequest, RegisteredHandler, RequestHandlers, RequestPolicy};
+
 //***************************************************************************//
 
 pub trait Direction {}
@@ -58,12 +80,28 @@ impl Type for Group {
 
 pub const NAME_HASH_LENGTH: usize = 10;
 pub const RAND_HASH_LENGTH: usize = 10;
+/// Length of the ratchet key carried by announces with the context flag set.
+pub const RATCHET_LENGTH: usize = PUBLIC_KEY_LENGTH;
 pub const MIN_ANNOUNCE_DATA_LENGTH: usize =
     PUBLIC_KEY_LENGTH * 2 + NAME_HASH_LENGTH + RAND_HASH_LENGTH + SIGNATURE_LENGTH;
 
+/// Flags, hops, destination and context of an announce on the wire.
+const ANNOUNCE_HEADER_LENGTH: usize = 2 + ADDRESS_HASH_SIZE + 1;
+
+/// Most app data an announce carries when it has to fit into `mtu` bytes
+/// on the wire. Reticulum has no announces spanning several packets.
+pub fn max_announce_app_data(mtu: usize) -> usize {
+    mtu.min(ANNOUNCE_HEADER_LENGTH + PACKET_MDU)
+        .saturating_sub(ANNOUNCE_HEADER_LENGTH + MIN_ANNOUNCE_DATA_LENGTH)
+}
+
+/// Longest full name which is kept in [`DestinationName`] next to its hash.
+pub const NAME_MAX_LENGTH: usize = 128;
+
 #[derive(Copy, Clone)]
 pub struct DestinationName {
     pub hash: Hash,
+    full_name: StaticBuffer<NAME_MAX_LENGTH>,
 }
 
 impl DestinationName {
@@ -77,7 +115,32 @@ impl DestinationName {
                 .into(),
         );
 
-        Self { hash }
+        let mut full_name = StaticBuffer::new();
+        if app_name.len() + 1 + aspects.len() <= NAME_MAX_LENGTH {
+            full_name
+                .chain_safe_write(app_name.as_bytes())
+                .chain_safe_write(b".")
+                .chain_safe_write(aspects.as_bytes());
+        }
+
+        Self { hash, full_name }
+    }
+
+    /// Creates a name from its dotted form, e.g. `"lxmf.delivery"`.
+    pub fn new_from_full_name(full_name: &str) -> Self {
+        match full_name.split_once('.') {
+            Some((app_name, aspects)) => Self::new(app_name, aspects),
+            None => {
+                let mut name = Self {
+                    hash: Hash::new_from_slice(full_name.as_bytes()),
+                    full_name: StaticBuffer::new(),
+                };
+                if full_name.len() <= NAME_MAX_LENGTH {
+                    name.full_name.chain_safe_write(full_name.as_bytes());
+                }
+                name
+            }
+        }
     }
 
     pub fn new_from_hash_slice(hash_slice: &[u8]) -> Self {
@@ -86,12 +149,47 @@ impl DestinationName {
 
         Self {
             hash: Hash::new(hash),
+            full_name: StaticBuffer::new(),
         }
     }
 
     pub fn as_name_hash_slice(&self) -> &[u8] {
         &self.hash.as_slice()[..NAME_HASH_LENGTH]
     }
+
+    /// Returns the dotted name, e.g. `"lxmf.delivery"`.
+    ///
+    /// Names only known by their hash, such as those of announced
+    /// destinations, and names longer than [`NAME_MAX_LENGTH`] return `None`.
+    pub fn full_name(&self) -> Option<&str> {
+        if self.full_name.is_empty() {
+            return None;
+        }
+
+        core::str::from_utf8(self.full_name.as_slice()).ok()
+    }
+
+    /// Splits the full name into the app name and its aspects.
+    pub fn expand_aspects(&self) -> Option<(&str, Vec<&str>)> {
+        let mut parts = self.full_name()?.split('.');
+        let app_name = parts.next()?;
+
+        Some((app_name, parts.collect()))
+    }
+
+    /// Computes the address of a destination without creating it, the
+    /// same as `rnid -H` of Python Reticulum does for an identity file read
+    /// with [`PrivateIdentity::from_file`](crate::identity::PrivateIdentity::from_file).
+    ///
+    /// `identity` is `None` for plain destinations.
+    pub fn hash_from_name_and_identity(full_name: &str, identity: Option<&Identity>) -> AddressHash {
+        let name = Self::new_from_full_name(full_name);
+
+        match identity {
+            Some(identity) => create_address_hash(identity, &name),
+            None => create_address_hash(&EmptyIdentity, &name),
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -113,13 +211,50 @@ pub type DestinationAnnounce = Packet;
 
 impl DestinationAnnounce {
     pub fn validate(packet: &Packet) -> Result<(SingleOutputDestination, &[u8]), RnsError> {
+        Self::decode(packet, true)
+    }
+
+    /// Decodes an announce without checking its signature again.
+    ///
+    /// Only use this for announces which have already passed [`Self::validate`].
+    pub(crate) fn decode_verified(
+        packet: &Packet,
+    ) -> Result<(SingleOutputDestination, &[u8]), RnsError> {
+        Self::decode(packet, false)
+    }
+
+    /// Returns the ratchet key of an announce, if it carries one.
+    pub fn ratchet(packet: &Packet) -> Option<[u8; RATCHET_LENGTH]> {
+        if packet.header.packet_type != PacketType::Announce
+            || packet.header.context_flag != ContextFlag::Set
+        {
+            return None;
+        }
+
+        let offset = PUBLIC_KEY_LENGTH * 2 + NAME_HASH_LENGTH + RAND_HASH_LENGTH;
+        packet
+            .data
+            .as_slice()
+            .get(offset..offset + RATCHET_LENGTH)
+            .and_then(|ratchet| ratchet.try_into().ok())
+    }
+
+    fn decode(
+        packet: &Packet,
+        verify: bool,
+    ) -> Result<(SingleOutputDestination, &[u8]), RnsError> {
         if packet.header.packet_type != PacketType::Announce {
             return Err(RnsError::PacketError);
         }
 
         let announce_data = packet.data.as_slice();
 
-        if announce_data.len() < MIN_ANNOUNCE_DATA_LENGTH {
+        let ratchet_length = match packet.header.context_flag {
+            ContextFlag::Set => RATCHET_LENGTH,
+            ContextFlag::Unset => 0,
+        };
+
+        if announce_data.len() < MIN_ANNOUNCE_DATA_LENGTH + ratchet_length {
             return Err(RnsError::OutOfMemory);
         }
 
@@ -146,39 +281,148 @@ impl DestinationAnnounce {
         offset += NAME_HASH_LENGTH;
         let rand_hash = &announce_data[offset..(offset + RAND_HASH_LENGTH)];
         offset += RAND_HASH_LENGTH;
+        let ratchet = &announce_data[offset..(offset + ratchet_length)];
+        offset += ratchet_length;
         let signature = &announce_data[offset..(offset + SIGNATURE_LENGTH)];
         offset += SIGNATURE_LENGTH;
         let app_data = &announce_data[offset..];
 
-        let destination = &packet.destination;
+        if verify {
+            Self::verify_signature(
+                &packet.destination,
+                &identity,
+                name_hash,
+                rand_hash,
+                ratchet,
+                signature,
+                app_data,
+            )?;
+        }
+
+        Ok((
+            SingleOutputDestination::new(identity, DestinationName::new_from_hash_slice(name_hash)),
+            app_data,
+        ))
+    }
 
+    fn verify_signature(
+        destination: &AddressHash,
+        identity: &Identity,
+        name_hash: &[u8],
+        rand_hash: &[u8],
+        ratchet: &[u8],
+        signature: &[u8],
+        app_data: &[u8],
+    ) -> Result<(), RnsError> {
         // Keeping signed data on stack is only option for now.
         // Verification function doesn't support prehashed message.
         let signed_data = PacketDataBuffer::new()
             .chain_write(destination.as_slice())?
-            .chain_write(public_key.as_bytes())?
-            .chain_write(verifying_key.as_bytes())?
+            .chain_write(identity.public_key.as_bytes())?
+            .chain_write(identity.verifying_key.as_bytes())?
             .chain_write(name_hash)?
             .chain_write(rand_hash)?
+            .chain_write(ratchet)?
             .chain_write(app_data)?
             .finalize();
 
         let signature = Signature::from_slice(signature).map_err(|_| RnsError::CryptoError)?;
 
-        identity.verify(signed_data.as_slice(), &signature)?;
+        identity.verify(signed_data.as_slice(), &signature)
+    }
+}
 
-        Ok((
-            SingleOutputDestination::new(identity, DestinationName::new_from_hash_slice(name_hash)),
-            app_data,
-        ))
+/// Which identities may keep links to a destination open.
+///
+/// Unless all are allowed, the initiator of a link has to identify itself.
+/// The link is torn down when the identity isn't allowed, and requests over
+/// links which haven't identified yet are dropped.
+#[derive(Clone, Default)]
+pub enum LinkAccess {
+    #[default]
+    All,
+    Allowed(Vec<AddressHash>),
+    Callback(Arc<dyn Fn(&Identity) -> bool + Send + Sync>),
+}
+
+impl LinkAccess {
+    pub fn is_restricted(&self) -> bool {
+        !matches!(self, Self::All)
+    }
+
+    pub fn allows(&self, identity: &Identity) -> bool {
+        match self {
+            Self::All => true,
+            Self::Allowed(allowed) => allowed.contains(&identity.address_hash),
+            Self::Callback(callback) => callback(identity),
+        }
+    }
+}
+
+impl fmt::Debug for LinkAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "All"),
+            Self::Allowed(allowed) => f.debug_tuple("Allowed").field(allowed).finish(),
+            Self::Callback(_) => write!(f, "Callback"),
+        }
     }
 }
 
+/// What a destination does with link requests beyond its [`LinkLimits`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LinkOverflow {
+    /// The request isn't answered, the initiator gives up after a timeout.
+    #[default]
+    Ignore,
+    /// The link is proven and closed right away, so the initiator learns
+    /// about it without waiting.
+    Close,
+}
+
+/// Limits on the inbound links of a destination, protecting small nodes
+/// from floods of link requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LinkLimits {
+    /// Most inbound links which may be open at the same time.
+    pub max_links: Option<usize>,
+    /// Most link requests accepted within the duration.
+    pub request_rate: Option<(u32, Duration)>,
+    pub overflow: LinkOverflow,
+}
+
+/// An inbound link request, as the [`LinkAcceptor`] of a destination sees it
+/// before the link is proven. The initiator hasn't identified yet, the link
+/// id is all there is to tell requests apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LinkRequestInfo {
+    pub link_id: AddressHash,
+    pub destination: AddressHash,
+    /// Interface the request came in on.
+    pub iface: AddressHash,
+    pub hops: u8,
+    /// Inbound links of the destination which are open already.
+    pub active_links: usize,
+}
+
+/// Future of a [`LinkAcceptor`], `true` accepts the link.
+pub type LinkAcceptance = Pin<Box<dyn Future<Output = bool> + Send>>;
+
+/// Decides whether an inbound link request is proven, see
+/// [`SingleInputDestination::set_link_acceptor`].
+pub type LinkAcceptor = Arc<dyn Fn(LinkRequestInfo) -> LinkAcceptance + Send + Sync>;
+
 pub struct Destination<I: HashIdentity, D: Direction, T: Type> {
     pub direction: PhantomData<D>,
     pub r#type: PhantomData<T>,
     pub identity: I,
     pub desc: DestinationDesc,
+    app_data: Option<Vec<u8>>,
+    link_access: LinkAccess,
+    link_limits: LinkLimits,
+    link_acceptor: Option<LinkAcceptor>,
+    request_handlers: RequestHandlers,
+    ratchets: Option<Ratchets>,
 }
 
 impl<I: HashIdentity, D: Direction, T: Type> Destination<I, D, T> {
@@ -219,6 +463,75 @@ pub enum DestinationHandleStatus {
     LinkProof,
 }
 
+/// Key a packet to a destination was decrypted with, see
+/// [`SingleInputDestination::decrypt`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecryptionKey {
+    /// The ratchet announced last.
+    CurrentRatchet,
+    /// An older ratchet, by the number of rotations since it was current.
+    PreviousRatchet(usize),
+    /// The key of the identity, used by senders which know no ratchet.
+    Identity,
+}
+
+/// Keys of a [`SingleInputDestination`] to decrypt packets with, taken out
+/// of the destination so trying them doesn't keep it locked.
+#[derive(Clone)]
+pub struct DecryptionKeys {
+    identity: PrivateIdentity,
+    ratchets: Vec<Ratchet>,
+}
+
+impl DecryptionKeys {
+    /// Decrypts like [`SingleInputDestination::decrypt`].
+    pub fn decrypt<'b, R: CryptoRngCore + Copy>(
+        &self,
+        rng: R,
+        data: &[u8],
+        out_buf: &'b mut [u8],
+    ) -> Result<(&'b [u8], DecryptionKey), RnsError> {
+        decrypt_with(&self.identity, self.ratchets.iter(), rng, data, out_buf)
+    }
+}
+
+fn decrypt_with<'a, 'b, R: CryptoRngCore + Copy>(
+    identity: &PrivateIdentity,
+    ratchets: impl Iterator<Item = &'a Ratchet>,
+    rng: R,
+    data: &[u8],
+    out_buf: &'b mut [u8],
+) -> Result<(&'b [u8], DecryptionKey), RnsError> {
+    if data.len() <= PUBLIC_KEY_LENGTH {
+        return Err(RnsError::InvalidArgument);
+    }
+
+    let (ephemeral_key, token) = data.split_at(PUBLIC_KEY_LENGTH);
+    let ephemeral_key = PublicKey::from(<[u8; PUBLIC_KEY_LENGTH]>::try_from(ephemeral_key).unwrap());
+    let salt = Some(identity.address_hash().as_slice());
+
+    let ratchets = ratchets.enumerate().map(|(index, ratchet)| {
+        let key = match index {
+            0 => DecryptionKey::CurrentRatchet,
+            rotations => DecryptionKey::PreviousRatchet(rotations),
+        };
+        (DerivedKey::new_from_private_key(ratchet.secret(), &ephemeral_key, salt), key)
+    });
+    let identity_key = core::iter::once_with(|| {
+        (identity.derive_key(&ephemeral_key, salt), DecryptionKey::Identity)
+    });
+
+    let (len, key) = ratchets
+        .chain(identity_key)
+        .find_map(|(derived_key, key)| {
+            let plain_text = identity.decrypt(rng, token, &derived_key, out_buf).ok()?;
+            Some((plain_text.len(), key))
+        })
+        .ok_or(RnsError::CryptoError)?;
+
+    Ok((&out_buf[..len], key))
+}
+
 impl Destination<PrivateIdentity, Input, Single> {
     pub fn new(identity: PrivateIdentity, name: DestinationName) -> Self {
         let address_hash = create_address_hash(&identity, &name);
@@ -233,29 +546,208 @@ impl Destination<PrivateIdentity, Input, Single> {
                 name,
                 address_hash,
             },
+            app_data: None,
+            link_access: LinkAccess::All,
+            link_limits: LinkLimits::default(),
+            link_acceptor: None,
+            request_handlers: RequestHandlers::default(),
+            ratchets: None,
+        }
+    }
+
+    /// Sets the app data which announces and path responses carry when no
+    /// app data is passed explicitly. Announces are built from it when they
+    /// are sent, so changes apply to the next announce. With ratchets
+    /// enabled it has to leave room for a ratchet.
+    pub fn set_default_app_data(&mut self, app_data: Option<&[u8]>) -> Result<(), RnsError> {
+        let max = Self::max_default_app_data(self.ratchets.is_some());
+        if app_data.is_some_and(|data| data.len() > max) {
+            return Err(RnsError::InvalidArgument);
+        }
+
+        self.app_data = app_data.map(Vec::from);
+
+        Ok(())
+    }
+
+    /// Longest default app data which fits an announce, leaving room for a
+    /// ratchet if `ratchets` is set.
+    fn max_default_app_data(ratchets: bool) -> usize {
+        let max = max_announce_app_data(usize::MAX);
+        if ratchets {
+            max - RATCHET_LENGTH
+        } else {
+            max
+        }
+    }
+
+    pub fn default_app_data(&self) -> Option<&[u8]> {
+        self.app_data.as_deref()
+    }
+
+    /// Restricts which identities may use links to this destination. Applies
+    /// to links which identify after the change.
+    pub fn set_link_access(&mut self, access: LinkAccess) {
+        self.link_access = access;
+    }
+
+    pub fn link_access(&self) -> &LinkAccess {
+        &self.link_access
+    }
+
+    pub fn set_link_limits(&mut self, limits: LinkLimits) {
+        self.link_limits = limits;
+    }
+
+    pub fn link_limits(&self) -> &LinkLimits {
+        &self.link_limits
+    }
+
+    /// Asks `acceptor` about every link request within the [`LinkLimits`]
+    /// before the link is proven. Rejected requests are handled like those
+    /// over the limits, see [`LinkOverflow`].
+    ///
+    /// The transport waits for the answer before it handles further
+    /// packets, so it should come quickly.
+    pub fn set_link_acceptor<F, R>(&mut self, acceptor: F)
+    where
+        F: Fn(LinkRequestInfo) -> R + Send + Sync + 'static,
+        R: Future<Output = bool> + Send + 'static,
+    {
+        self.link_acceptor = Some(Arc::new(move |request| Box::pin(acceptor(request))));
+    }
+
+    pub fn remove_link_acceptor(&mut self) {
+        self.link_acceptor = None;
+    }
+
+    /// Announces carry ratchets from now on, which are kept in the file at
+    /// `path`. The ratchets stored there are loaded, so messages encrypted
+    /// to them stay decryptable after a restart. Fails if the default app
+    /// data leaves no room for a ratchet.
+    pub fn enable_ratchets(
+        &mut self,
+        path: impl Into<PathBuf>,
+        retention: RatchetRetention,
+    ) -> io::Result<()> {
+        let max = Self::max_default_app_data(true);
+        if self.app_data.as_ref().is_some_and(|data| data.len() > max) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "default app data leaves no room for a ratchet",
+            ));
+        }
+
+        self.ratchets = Some(Ratchets::load(path, &self.identity, retention)?);
+
+        Ok(())
+    }
+
+    pub fn disable_ratchets(&mut self) {
+        self.ratchets = None;
+    }
+
+    pub fn ratchets(&self) -> Option<&Ratchets> {
+        self.ratchets.as_ref()
+    }
+
+    /// Decrypts `data` sent to this destination into `out_buf`. The current
+    /// ratchet is tried first, then the older ones, as senders may have
+    /// cached an older announce, and the identity key last.
+    pub fn decrypt<'b, R: CryptoRngCore + Copy>(
+        &self,
+        rng: R,
+        data: &[u8],
+        out_buf: &'b mut [u8],
+    ) -> Result<(&'b [u8], DecryptionKey), RnsError> {
+        decrypt_with(&self.identity, self.ratchets.iter().flat_map(Ratchets::iter), rng, data, out_buf)
+    }
+
+    /// Copies the keys to decrypt packets with, for decrypting them while
+    /// the destination is used otherwise.
+    pub fn decryption_keys(&self) -> DecryptionKeys {
+        DecryptionKeys {
+            identity: self.identity.clone(),
+            ratchets: self.ratchets.iter().flat_map(Ratchets::iter).cloned().collect(),
         }
     }
 
+    /// Generates a new ratchet if the current one is due for rotation, see
+    /// [`Ratchets::rotate`]. Returns whether the ratchets changed.
+    pub fn rotate_ratchets<R: CryptoRngCore>(&mut self, rng: R) -> io::Result<bool> {
+        let Some(ratchets) = &mut self.ratchets else {
+            return Ok(false);
+        };
+
+        let now = runtime::UNIX_EPOCH.elapsed().unwrap_or_default();
+        ratchets.rotate(rng, &self.identity, now)
+    }
+
+    pub fn link_acceptor(&self) -> Option<&LinkAcceptor> {
+        self.link_acceptor.as_ref()
+    }
+
+    /// Answers requests to `path` from peers `policy` allows with `handler`,
+    /// replacing the handler registered for it before. Requests to paths
+    /// without a handler are passed on to [`Transport::link_requests`].
+    ///
+    /// Handlers run while the transport handles the request packet, so they
+    /// should return quickly.
+    ///
+    /// [`Transport::link_requests`]: crate::transport::Transport::link_requests
+    pub fn register_request_handler<F>(&mut self, path: &str, handler: F, policy: RequestPolicy)
+    where
+        F: Fn(&IncomingRequest) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.request_handlers.register(path, Arc::new(handler), policy);
+    }
+
+    /// Returns whether a handler was registered for `path`.
+    pub fn deregister_request_handler(&mut self, path: &str) -> bool {
+        self.request_handlers.deregister(path)
+    }
+
+    /// Paths with a registered request handler.
+    pub fn request_paths(&self) -> impl Iterator<Item = &str> {
+        self.request_handlers.paths()
+    }
+
+    pub(crate) fn request_handler(&self, path_hash: &AddressHash) -> Option<&RegisteredHandler> {
+        self.request_handlers.get(path_hash)
+    }
+
+    /// Creates an announce with `app_data`, or the default app data if it is
+    /// `None`. It carries the current ratchet if ratchets are enabled.
     pub fn announce<R: CryptoRngCore + Copy>(
         &self,
         rng: R,
         app_data: Option<&[u8]>,
     ) -> Result<Packet, RnsError> {
+        let app_data = app_data.or(self.default_app_data());
+
         let mut packet_data = PacketDataBuffer::new();
 
         let rand_hash = Hash::new_from_rand(rng);
-        let timestamp = std::time::UNIX_EPOCH.elapsed().unwrap().as_secs().to_be_bytes();
+        let timestamp = runtime::UNIX_EPOCH.elapsed().unwrap().as_secs().to_be_bytes();
         let rand_hash = [&rand_hash.as_slice()[..RAND_HASH_LENGTH / 2], &timestamp[3..]].concat();
 
         let pub_key = self.identity.as_identity().public_key_bytes();
         let verifying_key = self.identity.as_identity().verifying_key_bytes();
 
+        let ratchet = self
+            .ratchets
+            .as_ref()
+            .and_then(Ratchets::current)
+            .map(|ratchet| ratchet.public_key().to_bytes());
+        let ratchet = ratchet.as_ref().map_or(&[][..], |ratchet| ratchet.as_slice());
+
         packet_data
             .chain_safe_write(self.desc.address_hash.as_slice())
             .chain_safe_write(pub_key)
             .chain_safe_write(verifying_key)
             .chain_safe_write(self.desc.name.as_name_hash_slice())
-            .chain_safe_write(&rand_hash);
+            .chain_safe_write(&rand_hash)
+            .chain_safe_write(ratchet);
 
         if let Some(data) = app_data {
             packet_data.write(data)?;
@@ -270,6 +762,7 @@ impl Destination<PrivateIdentity, Input, Single> {
             .chain_safe_write(verifying_key)
             .chain_safe_write(self.desc.name.as_name_hash_slice())
             .chain_safe_write(&rand_hash)
+            .chain_safe_write(ratchet)
             .chain_safe_write(&signature.to_bytes());
 
         if let Some(data) = app_data {
@@ -280,6 +773,7 @@ impl Destination<PrivateIdentity, Input, Single> {
             header: Header {
                 ifac_flag: IfacFlag::Open,
                 header_type: HeaderType::Type1,
+                context_flag: if ratchet.is_empty() { ContextFlag::Unset } else { ContextFlag::Set },
                 propagation_type: PropagationType::Broadcast,
                 destination_type: DestinationType::Single,
                 packet_type: PacketType::Announce,
@@ -320,9 +814,61 @@ impl Destination<PrivateIdentity, Input, Single> {
     pub fn sign_key(&self) -> &SigningKey {
         self.identity.sign_key()
     }
+
+    /// Creates an explicit proof of delivery for the packet with `hash`.
+    pub fn proof(&self, hash: &Hash) -> Packet {
+        let signature = self.identity.sign(hash.as_slice());
+
+        let mut packet_data = PacketDataBuffer::new();
+        packet_data.safe_write(hash.as_slice());
+        packet_data.safe_write(&signature.to_bytes()[..]);
+
+        Packet {
+            header: Header {
+                packet_type: PacketType::Proof,
+                ..Default::default()
+            },
+            ifac: None,
+            destination: AddressHash::new_from_hash(hash),
+            transport: None,
+            context: PacketContext::None,
+            data: packet_data,
+        }
+    }
 }
 
 impl Destination<Identity, Output, Single> {
+    /// Encrypts `text` for this destination into `out_buf`, to `ratchet` if
+    /// the destination announced one and to its identity key otherwise.
+    pub fn encrypt<'b, R: CryptoRngCore + Copy>(
+        &self,
+        rng: R,
+        text: &[u8],
+        ratchet: Option<&[u8; RATCHET_LENGTH]>,
+        out_buf: &'b mut [u8],
+    ) -> Result<&'b [u8], RnsError> {
+        if out_buf.len() < PUBLIC_KEY_LENGTH {
+            return Err(RnsError::InvalidArgument);
+        }
+
+        let target = ratchet.map_or(self.identity.public_key, |ratchet| PublicKey::from(*ratchet));
+
+        let ephemeral_key = EphemeralSecret::random_from_rng(rng);
+        out_buf[..PUBLIC_KEY_LENGTH].copy_from_slice(PublicKey::from(&ephemeral_key).as_bytes());
+
+        let derived_key = DerivedKey::new(
+            &ephemeral_key.diffie_hellman(&target),
+            Some(self.identity.address_hash.as_slice()),
+        );
+        let (enc_key, sign_key) = derived_key.as_bytes().split_at(DERIVED_KEY_LENGTH / 2);
+
+        let token_len = Fernet::new_from_slices(enc_key, sign_key, rng)
+            .encrypt(PlainText::from(text), &mut out_buf[PUBLIC_KEY_LENGTH..])?
+            .len();
+
+        Ok(&out_buf[..PUBLIC_KEY_LENGTH + token_len])
+    }
+
     pub fn new(identity: Identity, name: DestinationName) -> Self {
         let address_hash = create_address_hash(&identity, &name);
         Self {
@@ -334,6 +880,12 @@ impl Destination<Identity, Output, Single> {
                 name,
                 address_hash,
             },
+            app_data: None,
+            link_access: LinkAccess::All,
+            link_limits: LinkLimits::default(),
+            link_acceptor: None,
+            request_handlers: RequestHandlers::default(),
+            ratchets: None,
         }
     }
 }
@@ -350,6 +902,12 @@ impl<D: Direction> Destination<EmptyIdentity, D, Plain> {
                 name,
                 address_hash,
             },
+            app_data: None,
+            link_access: LinkAccess::All,
+            link_limits: LinkLimits::default(),
+            link_acceptor: None,
+            request_handlers: RequestHandlers::default(),
+            ratchets: None,
         }
     }
 }
@@ -375,12 +933,17 @@ mod tests {
 
     use crate::buffer::OutputBuffer;
     use crate::hash::Hash;
-    use crate::identity::PrivateIdentity;
+    use crate::identity::{PrivateIdentity, PUBLIC_KEY_LENGTH};
+    use crate::packet::{ContextFlag, PacketDataBuffer};
     use crate::serde::Serialize;
 
     use super::DestinationAnnounce;
     use super::DestinationName;
+    use super::LinkAccess;
     use super::SingleInputDestination;
+    use super::max_announce_app_data;
+    use super::ratchets::RatchetRetention;
+    use super::{NAME_HASH_LENGTH, RAND_HASH_LENGTH, RATCHET_LENGTH};
 
     #[test]
     fn create_announce() {
@@ -396,6 +959,133 @@ mod tests {
         println!("Announce packet {}", announce_packet);
     }
 
+    #[test]
+    fn announce_default_app_data() {
+        let mut destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "in"),
+        );
+
+        destination.set_default_app_data(Some(b"status")).unwrap();
+
+        let announce = destination.announce(OsRng, None).unwrap();
+        assert_eq!(DestinationAnnounce::validate(&announce).unwrap().1, b"status");
+
+        let announce = destination.announce(OsRng, Some(b"explicit")).unwrap();
+        assert_eq!(DestinationAnnounce::validate(&announce).unwrap().1, b"explicit");
+
+        assert!(destination.set_default_app_data(Some(&[0u8; 2048])).is_err());
+        assert_eq!(destination.default_app_data(), Some(&b"status"[..]));
+    }
+
+    #[test]
+    fn default_app_data_leaves_room_for_ratchet() {
+        let mut destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "in"),
+        );
+        let path = std::env::temp_dir()
+            .join(format!("reticulum-ratchets-{}", destination.desc.address_hash.to_hex()));
+
+        // Without ratchets the whole announce is available
+        let max = max_announce_app_data(usize::MAX);
+        destination.set_default_app_data(Some(&vec![0u8; max])).unwrap();
+        assert!(destination.enable_ratchets(&path, RatchetRetention::default()).is_err());
+        assert!(destination.ratchets().is_none());
+
+        destination.set_default_app_data(Some(&vec![0u8; max - RATCHET_LENGTH])).unwrap();
+        destination.enable_ratchets(&path, RatchetRetention::default()).unwrap();
+        assert!(destination.set_default_app_data(Some(&vec![0u8; max])).is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn announce_app_data_fits_mtu() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "in"),
+        );
+
+        let max = max_announce_app_data(500);
+        let announce = destination.announce(OsRng, Some(&vec![0u8; max])).unwrap();
+        assert_eq!(announce.wire_len(), 500);
+
+        assert_eq!(max_announce_app_data(100), 0);
+
+        let max = max_announce_app_data(usize::MAX);
+        assert!(destination.announce(OsRng, Some(&vec![0u8; max])).is_ok());
+        assert!(destination.announce(OsRng, Some(&vec![0u8; max + 1])).is_err());
+    }
+
+    #[test]
+    fn announce_with_ratchet() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "in"),
+        );
+
+        let mut announce = destination.announce(OsRng, Some(b"data")).unwrap();
+        assert_eq!(DestinationAnnounce::ratchet(&announce), None);
+
+        // Insert a ratchet after the random hash and sign the announce again
+        let data = announce.data.as_slice().to_vec();
+        let keys_end = PUBLIC_KEY_LENGTH * 2 + NAME_HASH_LENGTH + RAND_HASH_LENGTH;
+        let ratchet = [0x42u8; RATCHET_LENGTH];
+
+        let signed = [
+            destination.desc.address_hash.as_slice(),
+            &data[..keys_end],
+            &ratchet,
+            b"data",
+        ]
+        .concat();
+        let signature = destination.identity.sign(&signed).to_bytes();
+
+        let mut announce_data = PacketDataBuffer::new();
+        announce_data
+            .chain_safe_write(&data[..keys_end])
+            .chain_safe_write(&ratchet)
+            .chain_safe_write(&signature)
+            .chain_safe_write(b"data");
+        announce.data = announce_data;
+
+        // Without the context flag the ratchet is taken for the signature
+        assert!(DestinationAnnounce::validate(&announce).is_err());
+
+        announce.header.context_flag = ContextFlag::Set;
+        assert_eq!(DestinationAnnounce::validate(&announce).unwrap().1, b"data");
+        assert_eq!(DestinationAnnounce::ratchet(&announce), Some(ratchet));
+    }
+
+    #[test]
+    fn announce_carries_current_ratchet() {
+        let mut destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "in"),
+        );
+        let path = std::env::temp_dir()
+            .join(format!("reticulum-ratchets-{}", destination.desc.address_hash.to_hex()));
+        let _ = std::fs::remove_file(&path);
+
+        destination.enable_ratchets(&path, RatchetRetention::default()).unwrap();
+        assert!(destination.rotate_ratchets(OsRng).unwrap());
+        assert!(!destination.rotate_ratchets(OsRng).unwrap());
+
+        let current = destination.ratchets().unwrap().current().unwrap().public_key();
+        let announce = destination.announce(OsRng, Some(b"data")).unwrap();
+        assert_eq!(announce.header.context_flag, ContextFlag::Set);
+        assert_eq!(DestinationAnnounce::validate(&announce).unwrap().1, b"data");
+        assert_eq!(DestinationAnnounce::ratchet(&announce), Some(current.to_bytes()));
+
+        // A restart picks up the announced ratchet
+        destination.disable_ratchets();
+        destination.enable_ratchets(&path, RatchetRetention::default()).unwrap();
+        assert_eq!(destination.ratchets().unwrap().current().unwrap().public_key(), current);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     fn create_path_request_hash() {
         let name = DestinationName::new("rnstransport", "path.request");
@@ -460,4 +1150,45 @@ mod tests {
 
         DestinationAnnounce::validate(&announce).expect("valid announce");
     }
+
+    #[test]
+    fn hash_from_name_and_identity() {
+        let identity = PrivateIdentity::new_from_rand(OsRng);
+        let destination =
+            SingleInputDestination::new(identity.clone(), DestinationName::new("lxmf", "delivery"));
+
+        assert_eq!(
+            DestinationName::hash_from_name_and_identity("lxmf.delivery", Some(identity.as_identity())),
+            destination.desc.address_hash
+        );
+
+        let name = DestinationName::new_from_full_name("app.aspect.one");
+        assert_eq!(name.hash, DestinationName::new("app", "aspect.one").hash);
+        assert_eq!(name.full_name(), Some("app.aspect.one"));
+        assert_eq!(name.expand_aspects(), Some(("app", vec!["aspect", "one"])));
+
+        let unnamed = DestinationName::new_from_hash_slice(name.as_name_hash_slice());
+        assert_eq!(unnamed.full_name(), None);
+    }
+
+    #[test]
+    fn link_access() {
+        let allowed = PrivateIdentity::new_from_name("allowed");
+        let other = PrivateIdentity::new_from_name("other");
+
+        assert!(LinkAccess::All.allows(other.as_identity()));
+        assert!(!LinkAccess::All.is_restricted());
+
+        let access = LinkAccess::Allowed(vec![*allowed.address_hash()]);
+        assert!(access.is_restricted());
+        assert!(access.allows(allowed.as_identity()));
+        assert!(!access.allows(other.as_identity()));
+
+        let allowed_hash = *allowed.address_hash();
+        let access = LinkAccess::Callback(alloc::sync::Arc::new(move |identity| {
+            identity.address_hash == allowed_hash
+        }));
+        assert!(access.allows(allowed.as_identity()));
+        assert!(!access.allows(other.as_identity()));
+    }
 }
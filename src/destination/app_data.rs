@@ -0,0 +1,116 @@
+//! Announce app data formats of common Reticulum applications.
+//!
+//! LXMF delivery destinations announce a msgpack list with the display name
+//! and the stamp cost, older LXMF versions and NomadNet nodes announce the
+//! plain UTF-8 name.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::msgpack::{Reader, Writer};
+
+/// App data announced by LXMF delivery destinations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LxmfAppData {
+    pub display_name: Option<String>,
+    pub stamp_cost: Option<u8>,
+}
+
+impl LxmfAppData {
+    pub fn new(display_name: Option<&str>, stamp_cost: Option<u8>) -> Self {
+        Self {
+            display_name: display_name.map(Into::into),
+            stamp_cost,
+        }
+    }
+
+    /// Encodes as `[display_name, stamp_cost]`, the same as LXMF.
+    pub fn encode(&self) -> Vec<u8> {
+        Writer::new()
+            .array(2)
+            .opt_bin(self.display_name.as_ref().map(|name| name.as_bytes()))
+            .opt_uint(self.stamp_cost.map(u64::from))
+            .finish()
+    }
+
+    /// Decodes both the msgpack format and the original plain name format.
+    pub fn decode(app_data: &[u8]) -> Option<Self> {
+        let first = *app_data.first()?;
+
+        let is_list = (0x90..=0x9f).contains(&first) || first == 0xdc;
+        if !is_list {
+            return Some(Self {
+                display_name: Some(String::from(core::str::from_utf8(app_data).ok()?)),
+                stamp_cost: None,
+            });
+        }
+
+        let mut reader = Reader::new(app_data);
+        let len = reader.array().ok()?;
+        if len < 1 {
+            return None;
+        }
+
+        let display_name = if reader.nil() {
+            None
+        } else {
+            core::str::from_utf8(reader.bin_or_str().ok()?)
+                .ok()
+                .map(String::from)
+        };
+
+        let stamp_cost = if len >= 2 && !reader.nil() {
+            reader.uint::<u8>().ok()
+        } else {
+            None
+        };
+
+        Some(Self {
+            display_name,
+            stamp_cost,
+        })
+    }
+}
+
+/// Returns the node name announced by a NomadNet node.
+pub fn nomadnet_node_name(app_data: &[u8]) -> Option<&str> {
+    if app_data.is_empty() {
+        return None;
+    }
+
+    core::str::from_utf8(app_data).ok()
+}
+
+/// Returns a human readable name from announce app data of LXMF peers or
+/// NomadNet nodes.
+pub fn display_name(app_data: &[u8]) -> Option<String> {
+    LxmfAppData::decode(app_data)?.display_name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lxmf_app_data_matches_python() {
+        // msgpack.packb([b"Alice", 8])
+        let encoded = [0x92, 0xc4, 0x05, b'A', b'l', b'i', b'c', b'e', 0x08];
+
+        let app_data = LxmfAppData::new(Some("Alice"), Some(8));
+        assert_eq!(app_data.encode(), encoded);
+        assert_eq!(LxmfAppData::decode(&encoded), Some(app_data));
+
+        // msgpack.packb([None, None])
+        assert_eq!(LxmfAppData::decode(&[0x92, 0xc0, 0xc0]), Some(LxmfAppData::default()));
+
+        // Names packed as str by other implementations
+        assert_eq!(display_name(&[0x91, 0xa3, b'B', b'o', b'b']).as_deref(), Some("Bob"));
+
+        // Original format and NomadNet nodes
+        assert_eq!(display_name(b"Node").as_deref(), Some("Node"));
+        assert_eq!(nomadnet_node_name(b"Node"), Some("Node"));
+
+        assert_eq!(display_name(&[]), None);
+        assert_eq!(display_name(&[0x92, 0xc4, 0x05, b'A']), None);
+    }
+}
@@ -1,23 +1,31 @@
 use std::{
     cmp::min,
-    time::{Duration, Instant},
+    fmt,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    sync::Arc,
+    time::Duration,
 };
 
 use ed25519_dalek::{Signature, SigningKey, Verifier, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
-use rand_core::OsRng;
-use sha2::Digest;
 use x25519_dalek::StaticSecret;
 
 use crate::{
-    buffer::OutputBuffer,
+    crypt::rng::SharedRng,
     error::RnsError,
     hash::{AddressHash, Hash, ADDRESS_HASH_SIZE, HASH_SIZE},
     identity::{DecryptIdentity, DerivedKey, EncryptIdentity, Identity, PrivateIdentity},
+    msgpack::{Reader, Writer},
     packet::{
         DestinationType, Header, Packet, PacketContext, PacketDataBuffer, PacketType, PACKET_MDU,
     },
+    runtime::{Clock, Instant, RuntimeClock, SystemTime, UNIX_EPOCH},
 };
 
+use super::link_compression::{self, LinkCompression, ALGORITHM_ZLIB};
+use super::link_replay::ReplayGuard;
+use super::link_sequence::{self, LinkSequence, SequenceTracker};
+use super::link_window::LinkWindow;
+use super::request::{path_hash, LinkRequest, LinkResponse, RequestId};
 use super::DestinationDesc;
 
 const LINK_MTU_SIZE: usize = 3;
@@ -39,10 +47,105 @@ impl LinkStatus {
 
 pub type LinkId = AddressHash;
 
+/// Failure of an operation on a [`Link`].
+#[derive(Debug, PartialEq)]
+pub enum LinkError {
+    /// Data can only be sent while the link is active or stale.
+    NotActive { link_id: LinkId, status: LinkStatus },
+    /// The link is already wrapped in a channel.
+    ChannelBound { link_id: LinkId },
+    /// The payload couldn't be encrypted with the link key.
+    Encryption { link_id: LinkId, source: RnsError },
+    /// No link with the id is known.
+    Unknown { link_id: LinkId },
+}
+
+impl LinkError {
+    pub fn link_id(&self) -> &LinkId {
+        match self {
+            LinkError::NotActive { link_id, .. } => link_id,
+            LinkError::ChannelBound { link_id } => link_id,
+            LinkError::Encryption { link_id, .. } => link_id,
+            LinkError::Unknown { link_id } => link_id,
+        }
+    }
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::NotActive { link_id, status } => {
+                write!(f, "link({}) is not active (status {:?})", link_id, status)
+            }
+            LinkError::ChannelBound { link_id } => {
+                write!(f, "link({}) is already bound to a channel", link_id)
+            }
+            LinkError::Encryption { link_id, source } => {
+                write!(f, "link({}) failed to encrypt payload: {}", link_id, source)
+            }
+            LinkError::Unknown { link_id } => write!(f, "link({}) is not known", link_id),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LinkError::Encryption { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<LinkError> for RnsError {
+    fn from(error: LinkError) -> Self {
+        match error {
+            LinkError::NotActive { .. } | LinkError::Unknown { .. } => RnsError::LinkClosed,
+            LinkError::ChannelBound { .. } => RnsError::ChannelError,
+            LinkError::Encryption { source, .. } => source,
+        }
+    }
+}
+
+/// Why a link request proof was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofError {
+    /// The proof is addressed to another link than the one it was checked
+    /// against.
+    WrongLink { expected: LinkId, actual: LinkId },
+    /// Proofs hold a signature and a key, optionally followed by the link
+    /// MTU, anything else is malformed.
+    InvalidLength(usize),
+    /// The proof isn't signed by the destination of the link.
+    InvalidSignature,
+    /// Nobody waits for a proof of this link.
+    UnknownLink(LinkId),
+    /// A relayed proof came in on another interface than the one the link
+    /// request was sent on.
+    WrongInterface,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::WrongLink { expected, actual } => {
+                write!(f, "proof is for link {} instead of {}", actual, expected)
+            }
+            ProofError::InvalidLength(len) => write!(f, "proof has invalid length {}", len),
+            ProofError::InvalidSignature => write!(f, "proof signature is not valid"),
+            ProofError::UnknownLink(link_id) => write!(f, "proof is for unknown link {}", link_id),
+            ProofError::WrongInterface => write!(f, "proof came in on an unexpected interface"),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
 #[derive(Clone, Debug)]
 pub struct LinkPayload {
     buffer: [u8; PACKET_MDU],
     len: usize,
+    sequence: Option<LinkSequence>,
 }
 
 impl LinkPayload {
@@ -50,6 +153,7 @@ impl LinkPayload {
         Self {
             buffer: [0u8; PACKET_MDU],
             len: 0,
+            sequence: None,
         }
     }
 
@@ -60,7 +164,12 @@ impl LinkPayload {
 
         buffer[..len].copy_from_slice(&data[..len]);
 
-        Self { buffer, len }
+        Self { buffer, len, sequence: None }
+    }
+
+    fn with_sequence(mut self, sequence: Option<LinkSequence>) -> Self {
+        self.sequence = sequence;
+        self
     }
 
     pub fn len(&self) -> usize {
@@ -74,6 +183,12 @@ impl LinkPayload {
     pub fn as_slice(&self) -> &[u8] {
         &self.buffer[..self.len]
     }
+
+    /// Where the payload is among the data of the link, `None` unless the
+    /// peer sequences it, see [`link_sequence`].
+    pub fn sequence(&self) -> Option<LinkSequence> {
+        self.sequence
+    }
 }
 
 impl Default for LinkPayload {
@@ -93,34 +208,58 @@ impl From<&Packet> for LinkId {
 
         let hashable_data = &data[..data.len() - data_diff];
 
-        AddressHash::new_from_hash(&Hash::new(
-            Hash::generator()
-                .chain_update([packet.header.to_meta() & 0b00001111])
-                .chain_update(packet.destination.as_slice())
-                .chain_update([packet.context as u8])
-                .chain_update(hashable_data)
-                .finalize()
-                .into(),
-        ))
+        AddressHash::new_from_hash(&packet.hash_with_data(hashable_data))
     }
 }
 
 // TODO: consider boxing MessageReceived because Packet is >2000 bytes
-#[expect(clippy::large_enum_variant)]
 pub enum LinkHandleResult {
     None,
     Activated,
     KeepAlive,
     MessageReceived(Option<Packet>),
+    /// Data arrived over the link, with the proof to send if the link proves
+    /// messages.
+    DataReceived(Option<Packet>, Box<LinkPayload>),
+    /// The link waits for a proof and this one is not valid.
+    ProofRejected(ProofError),
+    /// The packet repeats one already received. Its payload is not delivered
+    /// again, but the proof is sent again in case the first one got lost.
+    Replayed(Option<Packet>),
+    /// The initiator of an inbound link identified itself.
+    Identified(Identity),
+    /// The peer of an inbound link sent a request.
+    Request(Box<LinkRequest>),
+    /// The peer of an outbound link answered a request.
+    Response(Box<LinkResponse>),
+    /// Both peers compress data now, with the answer to send to the
+    /// initiator if this is an inbound link.
+    CompressionAgreed(Option<Packet>),
 }
 
 #[derive(Clone, Debug)]
 pub enum LinkEvent {
     Activated,
+    /// The transport replaced the outbound link `old`, which died, with the
+    /// active link `new` to the same destination, see
+    /// [`LinkPolicy::reestablish`](crate::transport::LinkPolicy::reestablish).
+    Reestablished { old: LinkId, new: LinkId },
     // LinkPayload >2000 bytes so we box it
     Data(Box<LinkPayload>),
     Proof(Hash),
     Closed,
+    /// The initiator of an inbound link identified itself, and the
+    /// destination allows links of the identity.
+    IdentityReceived(Box<Identity>),
+    /// The peer of an inbound link sent a request. It is also delivered
+    /// through [`Transport::link_requests`](crate::transport::Transport::link_requests).
+    RequestReceived(Box<LinkRequest>),
+    /// The peer advertised a resource. Resources aren't implemented yet, so
+    /// the payload is the msgpack encoded advertisement and the resource is
+    /// never transferred.
+    ResourceAdvertised(Box<LinkPayload>),
+    /// The peer sent a keep-alive or answered one.
+    KeepAlive,
 }
 
 #[derive(Clone, Debug)]
@@ -135,32 +274,78 @@ pub struct Link {
     destination: DestinationDesc,
     priv_identity: PrivateIdentity,
     peer_identity: Identity,
+    /// Identity the initiator of an inbound link identified with.
+    remote_identity: Option<Identity>,
     derived_key: DerivedKey,
     status: LinkStatus,
+    /// Whether the peer closed the link.
+    closed_by_peer: bool,
     request_time: Instant,
     rtt: Duration,
     event_tx: tokio::sync::broadcast::Sender<LinkEventData>,
     proves_messages: bool,
     channel_tx: Option<tokio::sync::broadcast::Sender<LinkPayload>>,
+    window: LinkWindow,
+    window_notify: Arc<tokio::sync::Notify>,
+    replay_guard: ReplayGuard,
+    compression: Option<LinkCompression>,
+    /// Whether the peer agreed to compress.
+    compresses: bool,
+    sequencing: bool,
+    // Numbers are taken like the transmitted bytes are counted
+    tx_sequence: AtomicU32,
+    rx_sequence: SequenceTracker,
+    keep_alive_sent: Option<Instant>,
+    created: Instant,
+    rx_bytes: u64,
+    // Data packets are created through a shared reference
+    tx_bytes: AtomicU64,
+    rng: SharedRng,
+    clock: Arc<dyn Clock>,
 }
 
 impl Link {
     pub fn new(
         destination: DestinationDesc,
         event_tx: tokio::sync::broadcast::Sender<LinkEventData>,
+    ) -> Self {
+        Self::new_with_rng(destination, event_tx, SharedRng::default())
+    }
+
+    /// Like [`Link::new`], but keys and IVs are taken from `rng`.
+    pub fn new_with_rng(
+        destination: DestinationDesc,
+        event_tx: tokio::sync::broadcast::Sender<LinkEventData>,
+        rng: SharedRng,
     ) -> Self {
         Self {
             id: AddressHash::new_empty(),
             destination,
-            priv_identity: PrivateIdentity::new_from_rand(OsRng),
+            priv_identity: PrivateIdentity::new_from_rand(&rng),
             peer_identity: Identity::default(),
+            remote_identity: None,
             derived_key: DerivedKey::new_empty(),
             status: LinkStatus::Pending,
+            closed_by_peer: false,
             request_time: Instant::now(),
             rtt: Duration::from_secs(0),
             event_tx,
             proves_messages: false,
             channel_tx: None,
+            window: LinkWindow::new(Duration::from_secs(0)),
+            window_notify: Arc::new(tokio::sync::Notify::new()),
+            replay_guard: ReplayGuard::new(),
+            compression: None,
+            compresses: false,
+            sequencing: false,
+            tx_sequence: AtomicU32::new(0),
+            rx_sequence: SequenceTracker::default(),
+            keep_alive_sent: None,
+            created: Instant::now(),
+            rx_bytes: 0,
+            tx_bytes: AtomicU64::new(0),
+            rng,
+            clock: Arc::new(RuntimeClock),
         }
     }
 
@@ -168,14 +353,43 @@ impl Link {
         self.proves_messages = setting;
     }
 
+    /// Compresses data sent over the link with `compression` if the peer
+    /// agrees, see [`link_compression`]. Takes effect when the link is
+    /// established.
+    pub fn set_compression(&mut self, compression: Option<LinkCompression>) {
+        self.compression = compression;
+    }
+
+    /// Whether both peers agreed to compress data.
+    pub fn compresses(&self) -> bool {
+        self.compresses
+    }
+
+    /// Numbers the data sent over the link, see [`link_sequence`]. Only for
+    /// peers running this implementation.
+    pub fn set_sequencing(&mut self, sequencing: bool) {
+        self.sequencing = sequencing;
+    }
+
+    pub fn sequencing(&self) -> bool {
+        self.sequencing
+    }
+
+    /// Offers the peer of an active link to compress data, `None` if this
+    /// link doesn't compress.
+    pub fn compression_offer(&self) -> Option<Packet> {
+        self.compression?;
+        self.encrypted_packet(&[ALGORITHM_ZLIB], PacketContext::LinkCompression).ok()
+    }
+
     #[allow(unused)]  // This method is mocked out in the unit tests, so clippy
                       // will complain about it being unused in the test build.
     pub(crate) fn bind_to_channel(
         &mut self
-    ) -> Result<tokio::sync::broadcast::Receiver<LinkPayload>, RnsError> {
+    ) -> Result<tokio::sync::broadcast::Receiver<LinkPayload>, LinkError> {
         if self.channel_tx.is_some() {
             log::error!("link({}) cannot be bound to another channel", self.id());
-            return Err(RnsError::ChannelError);
+            return Err(LinkError::ChannelBound { link_id: self.id });
         }
 
         let (tx, rx) = tokio::sync::broadcast::channel(16);
@@ -192,6 +406,17 @@ impl Link {
         signing_key: SigningKey,
         destination: DestinationDesc,
         event_tx: tokio::sync::broadcast::Sender<LinkEventData>,
+    ) -> Result<Self, RnsError> {
+        Self::new_from_request_with_rng(packet, signing_key, destination, event_tx, SharedRng::default())
+    }
+
+    /// Like [`Link::new_from_request`], but keys and IVs are taken from `rng`.
+    pub fn new_from_request_with_rng(
+        packet: &Packet,
+        signing_key: SigningKey,
+        destination: DestinationDesc,
+        event_tx: tokio::sync::broadcast::Sender<LinkEventData>,
+        rng: SharedRng,
     ) -> Result<Self, RnsError> {
         if packet.data.len() < PUBLIC_KEY_LENGTH * 2 {
             return Err(RnsError::InvalidArgument);
@@ -208,15 +433,31 @@ impl Link {
         let mut link = Self {
             id: link_id,
             destination,
-            priv_identity: PrivateIdentity::new(StaticSecret::random_from_rng(OsRng), signing_key),
+            priv_identity: PrivateIdentity::new(StaticSecret::random_from_rng(&rng), signing_key),
             peer_identity,
+            remote_identity: None,
             derived_key: DerivedKey::new_empty(),
             status: LinkStatus::Pending,
+            closed_by_peer: false,
             request_time: Instant::now(),
             rtt: Duration::from_secs(0),
             event_tx,
             proves_messages: false,
             channel_tx: None,
+            window: LinkWindow::new(Duration::from_secs(0)),
+            window_notify: Arc::new(tokio::sync::Notify::new()),
+            replay_guard: ReplayGuard::new(),
+            compression: None,
+            compresses: false,
+            sequencing: false,
+            tx_sequence: AtomicU32::new(0),
+            rx_sequence: SequenceTracker::default(),
+            keep_alive_sent: None,
+            created: Instant::now(),
+            rx_bytes: 0,
+            tx_bytes: AtomicU64::new(0),
+            rng,
+            clock: Arc::new(RuntimeClock),
         };
 
         link.handshake(peer_identity);
@@ -249,8 +490,21 @@ impl Link {
         packet
     }
 
+    /// Takes the time from `clock` instead of the runtime.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        self.request_time = now;
+        self.created = now;
+        self.clock = clock;
+        self
+    }
+
     pub fn touch(&mut self) {
-        self.request_time = Instant::now();
+        self.request_time = self.clock.now();
+    }
+
+    fn since(&self, instant: Instant) -> Duration {
+        self.clock.now().saturating_duration_since(instant)
     }
 
     pub fn prove(&mut self) -> Packet {
@@ -295,38 +549,81 @@ impl Link {
             PacketContext::None => {
                 let mut buffer = [0u8; PACKET_MDU];
                 if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
-                    log::trace!("link({}): data {}B", self.id, plain_text.len());
-                    self.touch();
-                    self.post_event(LinkEvent::Data(Box::new(LinkPayload::new_from_slice(plain_text))));
-
-                    let proof = if self.proves_messages {
-                        Some(self.message_proof(packet.hash()))
-                    } else {
-                        None
-                    };
-
-                    return LinkHandleResult::MessageReceived(proof);
+                    return self.receive_data(packet, plain_text, None);
                 } else {
                     log::error!("link({}): can't decrypt packet", self.id);
                 }
             },
+            PacketContext::Sequenced => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(sequenced) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    match link_sequence::decode(sequenced) {
+                        Some((number, plain_text)) => return self.receive_data(packet, plain_text, Some(number)),
+                        None => log::warn!("link({}): sequenced packet without a number", self.id),
+                    }
+                } else {
+                    log::error!("link({}): can't decrypt sequenced packet", self.id);
+                }
+            }
+            // Compressed data may overtake the answer to our offer, so it is
+            // accepted once we offered, only sending waits for the agreement
+            PacketContext::Compressed if self.compression.is_some() => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(compressed) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    match link_compression::decompress(compressed) {
+                        Some(plain_text) => return self.receive_data(packet, &plain_text, None),
+                        None => log::warn!("link({}): can't decompress packet", self.id),
+                    }
+                } else {
+                    log::error!("link({}): can't decrypt compressed packet", self.id);
+                }
+            }
+            PacketContext::LinkCompression => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed compression offer", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    if self.compression.is_none() || !plain_text.contains(&ALGORITHM_ZLIB) {
+                        log::debug!("link({}): not compressing as the peer offered", self.id);
+                        return LinkHandleResult::None;
+                    }
+
+                    log::debug!("link({}): compressing data", self.id);
+                    self.touch();
+                    self.compresses = true;
+
+                    let answer = if out_link { None } else { self.compression_offer() };
+                    return LinkHandleResult::CompressionAgreed(answer);
+                } else {
+                    log::error!("link({}): can't decrypt compression offer", self.id);
+                }
+            }
             PacketContext::KeepAlive => {
                 if !packet.data.is_empty() && packet.data.as_slice()[0] == 0xFF {
                     self.touch();
                     log::trace!("link({}): keep-alive request", self.id);
+                    self.post_event(LinkEvent::KeepAlive);
                     return LinkHandleResult::KeepAlive;
                 }
                 if !packet.data.is_empty() && packet.data.as_slice()[0] == 0xFE {
                     log::trace!("link({}): keep-alive response", self.id);
                     self.touch();
+                    self.post_event(LinkEvent::KeepAlive);
                     return LinkHandleResult::None;
                 }
             },
             PacketContext::LinkRTT if !out_link => {
                 let mut buffer = [0u8; PACKET_MDU];
                 if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
-                    if let Ok(rtt) = rmp::decode::read_f64(&mut &plain_text[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed rtt packet", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    if let Ok(rtt) = Reader::new(plain_text).f64() {
                         self.rtt = Duration::from_secs_f64(rtt);
+                        self.window = LinkWindow::new(self.rtt);
                     } else {
                         log::error!("link({}): failed to decode rtt", self.id);
                     }
@@ -337,6 +634,10 @@ impl Link {
             PacketContext::LinkClose => {
                 let mut buffer = [0u8; PACKET_MDU];
                 if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed link close packet", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
                     match plain_text[..].try_into() {
                         Err(err) => {
                             log::error!("link({}): invalid decode link close payload: {err}",
@@ -345,6 +646,7 @@ impl Link {
                         Ok(dest_bytes) => {
                             let link_id = LinkId::new(dest_bytes);
                             if self.id == link_id {
+                                self.closed_by_peer = true;
                                 self.close();
                             }
                         }
@@ -353,20 +655,105 @@ impl Link {
                     log::error!("link({}): can't decrypt link close packet", self.id);
                 }
             },
+            PacketContext::LinkIdentify if !out_link => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed identify packet", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    match validate_identify(&self.id, plain_text) {
+                        Ok(identity) => {
+                            log::debug!(
+                                "link({}): peer identified as {}",
+                                self.id,
+                                identity.address_hash
+                            );
+                            self.touch();
+                            self.remote_identity = Some(identity);
+                            return LinkHandleResult::Identified(identity);
+                        }
+                        Err(err) => {
+                            log::warn!("link({}): invalid identify packet: {:?}", self.id, err)
+                        }
+                    }
+                } else {
+                    log::error!("link({}): can't decrypt identify packet", self.id);
+                }
+            }
+            PacketContext::Request if !out_link => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed request", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    let request_id = RequestId::new_from_hash(&packet.hash());
+                    match LinkRequest::decode(request_id, plain_text) {
+                        Ok(request) => {
+                            log::trace!("link({}): request {}", self.id, request_id);
+                            self.touch();
+                            return LinkHandleResult::Request(Box::new(request));
+                        }
+                        Err(_) => log::warn!("link({}): malformed request", self.id),
+                    }
+                } else {
+                    log::error!("link({}): can't decrypt request", self.id);
+                }
+            }
+            PacketContext::Response if out_link => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed response", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    match LinkResponse::decode(plain_text) {
+                        Ok(response) => {
+                            log::trace!("link({}): response to {}", self.id, response.request_id);
+                            self.touch();
+                            return LinkHandleResult::Response(Box::new(response));
+                        }
+                        Err(_) => log::warn!("link({}): malformed response", self.id),
+                    }
+                } else {
+                    log::error!("link({}): can't decrypt response", self.id);
+                }
+            }
+            PacketContext::ResourceAdvertisement => {
+                let mut buffer = [0u8; PACKET_MDU];
+                if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer[..]) {
+                    if !self.replay_guard.accept(packet.data.as_slice()) {
+                        log::warn!("link({}): dropped replayed resource advertisement", self.id);
+                        return LinkHandleResult::Replayed(None);
+                    }
+                    log::debug!("link({}): resource advertised, resources aren't supported", self.id);
+                    self.touch();
+                    let payload = LinkPayload::new_from_slice(plain_text);
+                    self.post_event(LinkEvent::ResourceAdvertised(Box::new(payload)));
+                } else {
+                    log::error!("link({}): can't decrypt resource advertisement", self.id);
+                }
+            }
             PacketContext::Channel => {
                 if let Some(ref channel_tx) = self.channel_tx {
                     let mut buffer = [0u8; PACKET_MDU];
                     if let Ok(plain_text) = self.decrypt(packet.data.as_slice(), &mut buffer) {
+                        let proof = Some(self.message_proof(packet.hash()));
+
+                        if !self.replay_guard.accept(packet.data.as_slice()) {
+                            log::debug!("link({}): dropped repeated channel packet", self.id);
+                            return LinkHandleResult::Replayed(proof);
+                        }
+
                         log::trace!("link({}): data over channel {}B", self.id, plain_text.len());
-                        self.request_time = Instant::now();
+                        self.request_time = self.clock.now();
 
                         channel_tx.send(LinkPayload::new_from_slice(plain_text)).ok();
 
                         let payload = LinkPayload::new_from_slice(plain_text);
                         self.post_event(LinkEvent::Data(Box::new(payload)));
 
-                        let proof = Some(self.message_proof(packet.hash()));
-
                         return LinkHandleResult::MessageReceived(proof);
                     } else {
                         log::error!("link({}): can't decrypt channel packet", self.id);
@@ -381,11 +768,38 @@ impl Link {
         LinkHandleResult::None
     }
 
+    fn receive_data(&mut self, packet: &Packet, plain_text: &[u8], number: Option<u32>) -> LinkHandleResult {
+        let proof = if self.proves_messages {
+            Some(self.message_proof(packet.hash()))
+        } else {
+            None
+        };
+
+        if !self.replay_guard.accept(packet.data.as_slice()) {
+            log::warn!("link({}): dropped replayed data packet", self.id);
+            return LinkHandleResult::Replayed(proof);
+        }
+
+        let sequence = number.map(|number| self.rx_sequence.receive(number));
+        if let Some(sequence) = sequence.filter(LinkSequence::has_gap) {
+            log::debug!("link({}): {} packets missing before {}", self.id, sequence.missed, sequence.number);
+        }
+
+        log::trace!("link({}): data {}B", self.id, plain_text.len());
+        self.touch();
+        let payload = Box::new(LinkPayload::new_from_slice(plain_text).with_sequence(sequence));
+        self.post_event(LinkEvent::Data(payload.clone()));
+
+        LinkHandleResult::DataReceived(proof, payload)
+    }
+
     pub fn handle_packet(&mut self, packet: &Packet, out_link: bool) -> LinkHandleResult {
         if packet.destination != self.id {
             return LinkHandleResult::None;
         }
 
+        self.rx_bytes += packet.data.len() as u64;
+
         match packet.header.packet_type {
             PacketType::Data => self.handle_data_packet(packet, out_link),
             PacketType::Proof => self.handle_proof_packet(packet),
@@ -397,22 +811,26 @@ impl Link {
         if self.status == LinkStatus::Pending
             && packet.context == PacketContext::LinkRequestProof
         {
-            if let Ok(identity) = validate_proof_packet(&self.destination, &self.id, packet)
-            {
-                log::debug!("link({}): has been proved", self.id);
+            match validate_link_proof(&self.destination.identity, &self.id, packet) {
+                Ok(identity) => {
+                    log::debug!("link({}): has been proved", self.id);
 
-                self.handshake(identity);
+                    self.handshake(identity);
 
-                self.status = LinkStatus::Active;
-                self.rtt = self.request_time.elapsed();
+                    self.status = LinkStatus::Active;
+                    self.rtt = self.since(self.request_time);
+                    self.window = LinkWindow::new(self.rtt);
 
-                log::debug!("link({}): activated", self.id);
+                    log::debug!("link({}): activated", self.id);
 
-                self.post_event(LinkEvent::Activated);
+                    self.post_event(LinkEvent::Activated);
 
-                return LinkHandleResult::Activated;
-            } else {
-                log::warn!("link({}): proof is not valid", self.id);
+                    return LinkHandleResult::Activated;
+                }
+                Err(err) => {
+                    log::warn!("link({}): {}", self.id, err);
+                    return LinkHandleResult::ProofRejected(err);
+                }
             }
         }
 
@@ -421,6 +839,9 @@ impl Link {
                 &self.peer_identity,
                 packet.data.as_slice()
             ) {
+                if self.window.delivered(&hash, self.rtt) {
+                    self.window_notify.notify_waiters();
+                }
                 self.post_event(LinkEvent::Proof(hash));
             }
         }
@@ -428,20 +849,81 @@ impl Link {
         LinkHandleResult::None
     }
 
-    pub fn data_packet(&self, data: &[u8]) -> Result<Packet, RnsError> {
+    pub fn data_packet(&self, data: &[u8]) -> Result<Packet, LinkError> {
+        if self.sequencing {
+            let number = self.tx_sequence.fetch_add(1, Ordering::Relaxed);
+            return self.encrypted_packet(&link_sequence::encode(number, data), PacketContext::Sequenced);
+        }
+
+        let compressed = self
+            .compression
+            .filter(|_| self.compresses)
+            .and_then(|compression| compression.compress(data));
+
+        match compressed {
+            Some(compressed) => self.encrypted_packet(&compressed, PacketContext::Compressed),
+            None => self.encrypted_packet(data, PacketContext::None),
+        }
+    }
+
+    /// Proves to the peer of an outbound link that it is used by `identity`.
+    pub fn identify_packet(&self, identity: &PrivateIdentity) -> Result<Packet, LinkError> {
+        let identity_key = identity_key(identity.as_identity());
+
+        let mut sign_data = [0u8; ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2];
+        sign_data[..ADDRESS_HASH_SIZE].copy_from_slice(self.id.as_slice());
+        sign_data[ADDRESS_HASH_SIZE..].copy_from_slice(&identity_key);
+        let signature = identity.sign(&sign_data);
+
+        let mut proof = [0u8; IDENTIFY_LEN];
+        proof[..PUBLIC_KEY_LENGTH * 2].copy_from_slice(&identity_key);
+        proof[PUBLIC_KEY_LENGTH * 2..].copy_from_slice(&signature.to_bytes());
+
+        self.encrypted_packet(&proof, PacketContext::LinkIdentify)
+    }
+
+    /// Requests `path` from the peer of an outbound link. `data` is a msgpack
+    /// encoded value, e.g. nil. The response refers to the returned id.
+    pub fn request_packet(&self, path: &str, data: &[u8]) -> Result<(Packet, RequestId), LinkError> {
+        let sent_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+
+        let request = LinkRequest::encode(sent_at, &path_hash(path), data);
+        let packet = self.encrypted_packet(&request, PacketContext::Request)?;
+
+        Ok((packet, RequestId::new_from_hash(&packet.hash())))
+    }
+
+    /// Answers the request `request_id` of the peer of an inbound link with
+    /// the msgpack encoded `data`.
+    pub fn response_packet(&self, request_id: &RequestId, data: &[u8]) -> Result<Packet, LinkError> {
+        self.encrypted_packet(&LinkResponse::encode(request_id, data), PacketContext::Response)
+    }
+
+    fn encrypted_packet(&self, data: &[u8], context: PacketContext) -> Result<Packet, LinkError> {
         if self.status != LinkStatus::Active && self.status != LinkStatus::Stale {
             log::warn!("link: can't create data packet for closed link");
-            return Err(RnsError::LinkClosed)
+            return Err(LinkError::NotActive { link_id: self.id, status: self.status });
         }
 
+        self.seal(data, context)
+    }
+
+    /// Encrypts `data` into a packet, which needs the handshake to be done.
+    fn seal(&self, data: &[u8], context: PacketContext) -> Result<Packet, LinkError> {
         let mut packet_data = PacketDataBuffer::new();
 
         let cipher_text_len = {
-            let cipher_text = self.encrypt(data, packet_data.accuire_buf_max())?;
+            let cipher_text = self
+                .encrypt(data, packet_data.accuire_buf_max())
+                .map_err(|source| LinkError::Encryption { link_id: self.id, source })?;
             cipher_text.len()
         };
 
         packet_data.resize(cipher_text_len);
+        self.tx_bytes.fetch_add(cipher_text_len as u64, Ordering::Relaxed);
 
         Ok(Packet {
             header: Header {
@@ -452,11 +934,22 @@ impl Link {
             ifac: None,
             destination: self.id,
             transport: None,
-            context: PacketContext::None,
+            context,
             data: packet_data,
         })
     }
 
+    /// Whether `interval` passed since the last keep-alive, or since the
+    /// link was created before the first. Takes the keep-alive as sent if so.
+    pub(crate) fn keep_alive_due(&mut self, interval: Duration) -> bool {
+        if self.since(self.keep_alive_sent.unwrap_or(self.created)) < interval {
+            return false;
+        }
+
+        self.keep_alive_sent = Some(self.clock.now());
+        true
+    }
+
     pub fn keep_alive_packet(&self, data: u8) -> Packet {
         log::trace!("link({}): create keep alive {}", self.id, data);
 
@@ -502,22 +995,27 @@ impl Link {
 
     pub fn encrypt<'a>(&self, text: &[u8], out_buf: &'a mut [u8]) -> Result<&'a [u8], RnsError> {
         self.priv_identity
-            .encrypt(OsRng, text, &self.derived_key, out_buf)
+            .encrypt(&self.rng, text, &self.derived_key, out_buf)
     }
 
     pub fn decrypt<'a>(&self, text: &[u8], out_buf: &'a mut [u8]) -> Result<&'a [u8], RnsError> {
         self.priv_identity
-            .decrypt(OsRng, text, &self.derived_key, out_buf)
+            .decrypt(&self.rng, text, &self.derived_key, out_buf)
     }
 
     pub fn destination(&self) -> &DestinationDesc {
         &self.destination
     }
 
+    /// Identity the peer of an inbound link identified with, see
+    /// [`Link::identify_packet`].
+    pub fn remote_identity(&self) -> Option<&Identity> {
+        self.remote_identity.as_ref()
+    }
+
     pub fn create_rtt(&self) -> Packet {
         let rtt = self.rtt.as_secs_f64();
-        let mut buf = Vec::with_capacity(9);
-        rmp::encode::write_f64(&mut buf, rtt).unwrap();
+        let buf = Writer::new().f64(rtt).finish();
 
         let mut packet_data = PacketDataBuffer::new();
 
@@ -556,7 +1054,7 @@ impl Link {
             .derive_key(&self.peer_identity.public_key, Some(self.id.as_slice()));
     }
 
-    fn post_event(&self, event: LinkEvent) {
+    pub(crate) fn post_event(&self, event: LinkEvent) {
         let _ = self.event_tx.send(LinkEventData {
             id: self.id,
             address_hash: self.destination.address_hash,
@@ -566,9 +1064,7 @@ impl Link {
 
     pub(crate) fn teardown(&mut self) -> Result<Option<Packet>, RnsError> {
         let packet = if self.status != LinkStatus::Pending && self.status != LinkStatus::Closed {
-            let mut packet = self.data_packet(self.id.as_slice())?;
-            packet.context = PacketContext::LinkClose;
-            Some(packet)
+            Some(self.seal(self.id.as_slice(), PacketContext::LinkClose)?)
         } else {
             None
         };
@@ -579,6 +1075,7 @@ impl Link {
     pub(crate) fn close(&mut self) {
         self.status = LinkStatus::Closed;
         self.post_event(LinkEvent::Closed);
+        self.window_notify.notify_waiters();
         log::warn!("link: close {}", self.id);
     }
 
@@ -592,20 +1089,25 @@ impl Link {
         log::warn!(
             "link({}): restart after {}s",
             self.id,
-            self.request_time.elapsed().as_secs()
+            self.since(self.request_time).as_secs()
         );
 
         self.status = LinkStatus::Pending;
     }
 
     pub fn elapsed(&self) -> Duration {
-        self.request_time.elapsed()
+        self.since(self.request_time)
     }
 
     pub fn status(&self) -> LinkStatus {
         self.status
     }
 
+    /// Whether the link was closed by the peer rather than this side.
+    pub fn closed_by_peer(&self) -> bool {
+        self.closed_by_peer
+    }
+
     pub fn id(&self) -> &LinkId {
         &self.id
     }
@@ -613,54 +1115,121 @@ impl Link {
     pub fn rtt(&self) -> &Duration {
         &self.rtt
     }
+
+    /// Time since the link was created.
+    pub fn age(&self) -> Duration {
+        self.since(self.created)
+    }
+
+    /// Bytes of packet data received over the link.
+    pub fn rx_bytes(&self) -> u64 {
+        self.rx_bytes
+    }
+
+    /// Replayed packets dropped by the link.
+    pub fn replays(&self) -> u64 {
+        self.replay_guard.replays()
+    }
+
+    /// Bytes of data packets created for the link.
+    pub fn tx_bytes(&self) -> u64 {
+        self.tx_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn window(&self) -> &LinkWindow {
+        &self.window
+    }
+
+    pub(crate) fn window_mut(&mut self) -> &mut LinkWindow {
+        &mut self.window
+    }
+
+    /// Notified whenever a proof makes room in the window.
+    pub(crate) fn window_notify(&self) -> Arc<tokio::sync::Notify> {
+        self.window_notify.clone()
+    }
 }
 
-fn validate_proof_packet(
-    destination: &DestinationDesc,
-    id: &LinkId,
-    packet: &Packet,
-) -> Result<Identity, RnsError> {
-    const MIN_PROOF_LEN: usize = SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH;
-    const MTU_PROOF_LEN: usize = SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH + LINK_MTU_SIZE;
-    const SIGN_DATA_LEN: usize = ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2 + LINK_MTU_SIZE;
+const PROOF_LEN: usize = SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH;
+const IDENTIFY_LEN: usize = PUBLIC_KEY_LENGTH * 2 + SIGNATURE_LENGTH;
+
+/// Encryption key followed by the signing key, as identities are exchanged.
+fn identity_key(identity: &Identity) -> [u8; PUBLIC_KEY_LENGTH * 2] {
+    let mut key = [0u8; PUBLIC_KEY_LENGTH * 2];
+    key[..PUBLIC_KEY_LENGTH].copy_from_slice(identity.public_key_bytes());
+    key[PUBLIC_KEY_LENGTH..].copy_from_slice(identity.verifying_key_bytes());
+    key
+}
 
-    if packet.data.len() < MIN_PROOF_LEN {
+/// Checks the decrypted payload of an identify packet and returns the
+/// identity it proves.
+fn validate_identify(link_id: &LinkId, payload: &[u8]) -> Result<Identity, RnsError> {
+    if payload.len() != IDENTIFY_LEN {
         return Err(RnsError::PacketError);
     }
 
-    let mut proof_data = [0u8; SIGN_DATA_LEN];
+    let (key, signature) = payload.split_at(PUBLIC_KEY_LENGTH * 2);
+    let identity = Identity::new_from_slices(&key[..PUBLIC_KEY_LENGTH], &key[PUBLIC_KEY_LENGTH..]);
+    let signature = Signature::from_slice(signature).map_err(|_| RnsError::IncorrectSignature)?;
 
-    let verifying_key = destination.identity.verifying_key.as_bytes();
-    let sign_data_len = {
-        let mut output = OutputBuffer::new(&mut proof_data[..]);
+    let mut sign_data = [0u8; ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2];
+    sign_data[..ADDRESS_HASH_SIZE].copy_from_slice(link_id.as_slice());
+    sign_data[ADDRESS_HASH_SIZE..].copy_from_slice(key);
+    identity.verify(&sign_data, &signature)?;
 
-        output.write(id.as_slice())?;
-        output.write(
-            &packet.data.as_slice()[SIGNATURE_LENGTH..SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH],
-        )?;
-        output.write(verifying_key)?;
+    Ok(identity)
+}
+const MTU_PROOF_LEN: usize = SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH + LINK_MTU_SIZE;
+
+/// Checks that a link request proof has the length of a signature and a key,
+/// optionally followed by the link MTU.
+pub fn check_link_proof_len(packet: &Packet) -> Result<(), ProofError> {
+    match packet.data.len() {
+        PROOF_LEN | MTU_PROOF_LEN => Ok(()),
+        len => Err(ProofError::InvalidLength(len)),
+    }
+}
 
-        if packet.data.len() >= MTU_PROOF_LEN {
-            let mtu_bytes = &packet.data.as_slice()[SIGNATURE_LENGTH + PUBLIC_KEY_LENGTH..];
-            output.write(mtu_bytes)?;
-        }
+/// Checks that `packet` proves the link `link_id` to a destination with the
+/// given identity. Returns the identity of the link's peer, whose key was
+/// sent in the proof.
+pub fn validate_link_proof(
+    identity: &Identity,
+    link_id: &LinkId,
+    packet: &Packet,
+) -> Result<Identity, ProofError> {
+    const SIGN_DATA_LEN: usize = ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2 + LINK_MTU_SIZE;
 
-        output.offset()
-    };
+    if packet.destination != *link_id {
+        return Err(ProofError::WrongLink { expected: *link_id, actual: packet.destination });
+    }
 
-    let identity = Identity::new_from_slices(
-        &proof_data[ADDRESS_HASH_SIZE..ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH],
-        verifying_key,
-    );
+    check_link_proof_len(packet)?;
 
-    let signature = Signature::from_slice(&packet.data.as_slice()[..SIGNATURE_LENGTH])
-        .map_err(|_| RnsError::CryptoError)?;
+    let data = packet.data.as_slice();
+    let (signature, public_key) = data.split_at(SIGNATURE_LENGTH);
+    let verifying_key = identity.verifying_key.as_bytes();
 
-    identity
-        .verify(&proof_data[..sign_data_len], &signature)
-        .map_err(|_| RnsError::IncorrectSignature)?;
+    // Link id, peer key, destination key and the optional MTU bytes
+    let mut sign_data = [0u8; SIGN_DATA_LEN];
+    let sign_data_len = ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH + data.len() - SIGNATURE_LENGTH;
+    sign_data[..ADDRESS_HASH_SIZE].copy_from_slice(link_id.as_slice());
+    sign_data[ADDRESS_HASH_SIZE..ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH]
+        .copy_from_slice(&public_key[..PUBLIC_KEY_LENGTH]);
+    sign_data[ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH..ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2]
+        .copy_from_slice(verifying_key);
+    sign_data[ADDRESS_HASH_SIZE + PUBLIC_KEY_LENGTH * 2..sign_data_len]
+        .copy_from_slice(&public_key[PUBLIC_KEY_LENGTH..]);
 
-    Ok(identity)
+    let peer_identity = Identity::new_from_slices(&public_key[..PUBLIC_KEY_LENGTH], verifying_key);
+
+    let signature = Signature::from_slice(signature).map_err(|_| ProofError::InvalidSignature)?;
+
+    peer_identity
+        .verify(&sign_data[..sign_data_len], &signature)
+        .map_err(|_| ProofError::InvalidSignature)?;
+
+    Ok(peer_identity)
 }
 
 fn validate_message_proof(
@@ -0,0 +1,87 @@
+//! Compression of the data sent over a link.
+//!
+//! Python links don't compress single packets, so this is an extension
+//! both peers have to agree on. Once an outbound link is active it offers
+//! compression with a [`PacketContext::LinkCompression`] packet, and a peer
+//! which compresses as well answers with one. Python peers ignore the offer
+//! and the link keeps sending plain data.
+//!
+//! After that, data of at least the threshold is zlib compressed and sent as
+//! [`PacketContext::Compressed`], unless that doesn't make it smaller.
+//!
+//! [`PacketContext::LinkCompression`]: crate::packet::PacketContext::LinkCompression
+//! [`PacketContext::Compressed`]: crate::packet::PacketContext::Compressed
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+
+use crate::packet::PACKET_MDU;
+
+/// Smallest payload worth compressing by default, the zlib header and
+/// checksum alone take 6 bytes.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 64;
+
+/// Algorithm byte of the offer, the only one there is so far.
+pub(crate) const ALGORITHM_ZLIB: u8 = 0x01;
+
+/// Compression level, link payloads are small so the best one costs little.
+const LEVEL: u8 = 9;
+
+/// Compression settings of a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkCompression {
+    /// Payloads shorter than this are sent as they are.
+    pub threshold: usize,
+}
+
+impl Default for LinkCompression {
+    fn default() -> Self {
+        Self { threshold: DEFAULT_COMPRESSION_THRESHOLD }
+    }
+}
+
+impl LinkCompression {
+    /// Compresses `data` if it is long enough and gets shorter.
+    pub(crate) fn compress(&self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() < self.threshold {
+            return None;
+        }
+
+        let compressed = compress_to_vec_zlib(data, LEVEL);
+        (compressed.len() < data.len()).then_some(compressed)
+    }
+}
+
+/// Decompresses the payload of a [`PacketContext::Compressed`] packet, which
+/// doesn't expand beyond what fits into a packet.
+///
+/// [`PacketContext::Compressed`]: crate::packet::PacketContext::Compressed
+pub(crate) fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    decompress_to_vec_zlib_with_limit(data, PACKET_MDU).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compresses_long_repetitive_data_only() {
+        let compression = LinkCompression::default();
+
+        let text = b"temperature=21.5 humidity=40 temperature=21.6 humidity=41 temperature=21.5";
+        let compressed = compression.compress(text).expect("compressed");
+        assert!(compressed.len() < text.len());
+        assert_eq!(decompress(&compressed).unwrap(), text);
+
+        assert!(compression.compress(b"short").is_none());
+        let random: Vec<u8> = (0..128u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        assert!(compression.compress(&random).is_none());
+    }
+
+    #[test]
+    fn rejects_bombs_and_garbage() {
+        let bomb = compress_to_vec_zlib(&[0u8; PACKET_MDU * 4], LEVEL);
+        assert!(decompress(&bomb).is_none());
+        assert!(decompress(b"not zlib").is_none());
+    }
+}
@@ -0,0 +1,91 @@
+//! Replay protection for encrypted link payloads.
+//!
+//! Every token sent over a link starts with a random IV, and the HMAC of the
+//! token covers it, so a peer can't produce two valid tokens with the same
+//! IV except by sending the same token twice. A link remembers the IVs of
+//! the tokens it decrypted recently and treats a token with a known IV as a
+//! replay: its payload isn't delivered again.
+
+use std::collections::{HashSet, VecDeque};
+
+/// Length of the IV at the start of a token.
+pub const TOKEN_IV_LENGTH: usize = 16;
+
+/// Tokens remembered per link. Replays of older tokens are not detected.
+const REMEMBERED_TOKENS: usize = 1024;
+
+#[derive(Default)]
+pub struct ReplayGuard {
+    seen: HashSet<[u8; TOKEN_IV_LENGTH]>,
+    order: VecDeque<[u8; TOKEN_IV_LENGTH]>,
+    replays: u64,
+}
+
+impl ReplayGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the token, which must have been decrypted successfully.
+    /// Returns false if it was seen before.
+    pub fn accept(&mut self, token: &[u8]) -> bool {
+        let Some(iv) = token.get(..TOKEN_IV_LENGTH) else {
+            return true;
+        };
+        let iv: [u8; TOKEN_IV_LENGTH] = iv.try_into().expect("IV length");
+
+        if !self.seen.insert(iv) {
+            self.replays += 1;
+            return false;
+        }
+
+        self.order.push_back(iv);
+        if self.order.len() > REMEMBERED_TOKENS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        true
+    }
+
+    /// Replayed tokens rejected so far.
+    pub fn replays(&self) -> u64 {
+        self.replays
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(iv: u8) -> [u8; 48] {
+        let mut token = [0u8; 48];
+        token[..TOKEN_IV_LENGTH].fill(iv);
+        token
+    }
+
+    #[test]
+    fn rejects_repeated_tokens() {
+        let mut guard = ReplayGuard::new();
+
+        assert!(guard.accept(&token(1)));
+        assert!(guard.accept(&token(2)));
+        assert!(!guard.accept(&token(1)));
+        assert_eq!(guard.replays(), 1);
+    }
+
+    #[test]
+    fn forgets_oldest_tokens() {
+        let mut guard = ReplayGuard::new();
+
+        for index in 0..=REMEMBERED_TOKENS {
+            let mut token = [0u8; TOKEN_IV_LENGTH];
+            token[..8].copy_from_slice(&(index as u64).to_le_bytes());
+            assert!(guard.accept(&token));
+        }
+
+        assert_eq!(guard.seen.len(), REMEMBERED_TOKENS);
+        assert!(guard.accept(&[0u8; TOKEN_IV_LENGTH]));
+    }
+}
@@ -0,0 +1,117 @@
+//! Numbering of the data sent over a link.
+//!
+//! Links don't tell the receiver of plain data whether anything was lost on
+//! the way. A link which sequences its data numbers every data packet and
+//! sends it as [`PacketContext::Sequenced`], and the receiver passes the
+//! number and what it concludes from it on with the payload, see
+//! [`LinkPayload::sequence`]. Applications built on [`LinkEvent::Data`]
+//! rather than a channel can then ask for what they missed.
+//!
+//! Every link understands sequenced packets, only sending them is optional.
+//! Python peers drop them, so sequencing is only for peers running this
+//! implementation. Sequenced data isn't compressed.
+//!
+//! [`PacketContext::Sequenced`]: crate::packet::PacketContext::Sequenced
+//! [`LinkPayload::sequence`]: super::link::LinkPayload::sequence
+//! [`LinkEvent::Data`]: super::link::LinkEvent::Data
+
+/// Length of the number in front of the data.
+pub const SEQUENCE_LEN: usize = 4;
+
+/// Where a sequenced payload is in the data received over a link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkSequence {
+    /// Number of the packet, counting up from zero per link and direction.
+    pub number: u32,
+    /// Packets skipped between the latest one before and this one, which
+    /// were lost or are still underway.
+    pub missed: u32,
+    /// Whether the packet comes after one with a higher number, which
+    /// reported it as missed.
+    pub late: bool,
+}
+
+impl LinkSequence {
+    /// Whether packets before this one are missing.
+    pub fn has_gap(&self) -> bool {
+        self.missed > 0
+    }
+}
+
+/// Numbers of the packets a link received.
+#[derive(Debug, Default)]
+pub(crate) struct SequenceTracker {
+    /// Number expected next, `None` before the first packet.
+    next: Option<u32>,
+}
+
+impl SequenceTracker {
+    /// Places packet `number`, which must not be a replay. Numbers wrap, the
+    /// ones up to half the range ahead of the expected one count as later.
+    pub(crate) fn receive(&mut self, number: u32) -> LinkSequence {
+        let ahead = number.wrapping_sub(self.next.unwrap_or(0));
+
+        if self.next.is_some() && ahead > u32::MAX / 2 {
+            return LinkSequence { number, missed: 0, late: true };
+        }
+
+        self.next = Some(number.wrapping_add(1));
+        LinkSequence { number, missed: ahead, late: false }
+    }
+}
+
+/// Puts `number` in front of `data`.
+pub(crate) fn encode(number: u32, data: &[u8]) -> Vec<u8> {
+    let mut sequenced = Vec::with_capacity(SEQUENCE_LEN + data.len());
+    sequenced.extend_from_slice(&number.to_be_bytes());
+    sequenced.extend_from_slice(data);
+    sequenced
+}
+
+/// Splits the payload of a sequenced packet into the number and the data.
+pub(crate) fn decode(sequenced: &[u8]) -> Option<(u32, &[u8])> {
+    let (number, data) = sequenced.split_first_chunk::<SEQUENCE_LEN>()?;
+    Some((u32::from_be_bytes(*number), data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_gaps_and_late_packets() {
+        let mut tracker = SequenceTracker::default();
+
+        assert_eq!(tracker.receive(0), LinkSequence { number: 0, missed: 0, late: false });
+        assert_eq!(tracker.receive(1), LinkSequence { number: 1, missed: 0, late: false });
+
+        let sequence = tracker.receive(4);
+        assert!(sequence.has_gap());
+        assert_eq!(sequence.missed, 2);
+
+        assert_eq!(tracker.receive(2), LinkSequence { number: 2, missed: 0, late: true });
+        assert_eq!(tracker.receive(5), LinkSequence { number: 5, missed: 0, late: false });
+
+        // The first packet may be lost too
+        let mut tracker = SequenceTracker::default();
+        assert_eq!(tracker.receive(3).missed, 3);
+    }
+
+    #[test]
+    fn numbers_wrap() {
+        let mut tracker = SequenceTracker::default();
+        tracker.receive(u32::MAX - 1);
+
+        assert!(!tracker.receive(u32::MAX).has_gap());
+        assert!(!tracker.receive(0).has_gap());
+        assert!(tracker.receive(u32::MAX).late);
+    }
+
+    #[test]
+    fn encodes_the_number_in_front() {
+        let sequenced = encode(258, b"data");
+        assert_eq!(&sequenced[..SEQUENCE_LEN], &[0, 0, 1, 2]);
+        assert_eq!(decode(&sequenced), Some((258, &b"data"[..])));
+        assert_eq!(decode(&[1, 2]), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! Flow control for data sent over a link.
+//!
+//! Packets sent with [`Transport::send_to_link`] stay outstanding until the
+//! peer proves them or their proof times out, and no more than the current
+//! window may be outstanding at once. The window follows the same rules as
+//! the one of a [`Channel`]: every proof grows it by one, and after enough
+//! consecutive rounds at a fast or medium RTT its upper limit is raised.
+//! A timeout shrinks it again, so a slow path isn't overrun by bulk senders.
+//!
+//! [`Transport::send_to_link`]: crate::transport::Transport::send_to_link
+//! [`Channel`]: crate::channel::Channel
+
+use std::collections::BTreeMap;
+use core::time::Duration;
+
+use crate::channel::{
+    FAST_RATE_THRESHOLD, RTT_FAST, RTT_MEDIUM, RTT_SLOW, WINDOW, WINDOW_MAX_FAST,
+    WINDOW_MAX_MEDIUM, WINDOW_MAX_SLOW, WINDOW_MIN, WINDOW_MIN_LIMIT_FAST,
+    WINDOW_MIN_LIMIT_MEDIUM,
+};
+use crate::hash::Hash;
+use crate::runtime::Instant;
+
+/// Multiple of the link RTT after which an unproved packet is given up on,
+/// like the traffic timeout factor of Python links.
+const TRAFFIC_TIMEOUT_FACTOR: u32 = 6;
+
+/// Lower bound of the proof timeout, the RTT of a fresh link may be zero.
+const TRAFFIC_TIMEOUT_MIN: Duration = Duration::from_secs(1);
+
+pub struct LinkWindow {
+    window: u16,
+    window_max: u16,
+    window_min: u16,
+    fast_rate_rounds: u16,
+    medium_rate_rounds: u16,
+    outstanding: BTreeMap<Hash, Instant>,
+}
+
+impl LinkWindow {
+    /// Creates a window for a link with the given `rtt`.
+    pub fn new(rtt: Duration) -> Self {
+        let slow = rtt.as_secs_f32() > RTT_SLOW;
+
+        Self {
+            window: if slow { 1 } else { WINDOW },
+            window_max: if slow { 1 } else { WINDOW_MAX_SLOW },
+            window_min: if slow { 1 } else { WINDOW_MIN },
+            fast_rate_rounds: 0,
+            medium_rate_rounds: 0,
+            outstanding: BTreeMap::new(),
+        }
+    }
+
+    /// Returns whether another packet may be sent.
+    pub fn is_open(&self) -> bool {
+        self.outstanding.len() < self.window as usize
+    }
+
+    pub fn window(&self) -> usize {
+        self.window as usize
+    }
+
+    /// Number of sent packets which are neither proved nor timed out.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.len()
+    }
+
+    pub(crate) fn sent(&mut self, hash: Hash, now: Instant) {
+        self.outstanding.insert(hash, now);
+    }
+
+    /// Handles the proof of `hash` and returns whether it was outstanding.
+    pub(crate) fn delivered(&mut self, hash: &Hash, rtt: Duration) -> bool {
+        if self.outstanding.remove(hash).is_none() {
+            return false;
+        }
+
+        if self.window < self.window_max {
+            self.window += 1;
+        }
+
+        let rtt = rtt.as_secs_f32();
+        if rtt == 0.0 {
+            return true;
+        }
+
+        if rtt > RTT_FAST {
+            self.fast_rate_rounds = 0;
+            if rtt > RTT_MEDIUM {
+                self.medium_rate_rounds = 0;
+            } else {
+                self.medium_rate_rounds += 1;
+                if self.window_max < WINDOW_MAX_MEDIUM
+                    && self.medium_rate_rounds == FAST_RATE_THRESHOLD
+                {
+                    self.window_max = WINDOW_MAX_MEDIUM;
+                    self.window_min = WINDOW_MIN_LIMIT_MEDIUM;
+                }
+            }
+        } else {
+            self.fast_rate_rounds += 1;
+            if self.window_max < WINDOW_MAX_FAST && self.fast_rate_rounds == FAST_RATE_THRESHOLD {
+                self.window_max = WINDOW_MAX_FAST;
+                self.window_min = WINDOW_MIN_LIMIT_FAST;
+            }
+        }
+
+        true
+    }
+
+    /// Drops packets sent before `now - timeout` and shrinks the window once
+    /// for every one of them. Returns the number of dropped packets.
+    pub(crate) fn expire(&mut self, now: Instant, timeout: Duration) -> usize {
+        let before = self.outstanding.len();
+        self.outstanding
+            .retain(|_, sent| now.duration_since(*sent) < timeout);
+
+        let expired = before - self.outstanding.len();
+        for _ in 0..expired {
+            if self.window > self.window_min {
+                self.window -= 1;
+            }
+        }
+
+        expired
+    }
+}
+
+/// Time to wait for the proof of a packet sent over a link with `rtt`.
+pub fn proof_timeout(rtt: Duration) -> Duration {
+    (rtt * TRAFFIC_TIMEOUT_FACTOR).max(TRAFFIC_TIMEOUT_MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(value: u8) -> Hash {
+        Hash::new([value; 32])
+    }
+
+    #[test]
+    fn window_grows_with_proofs_and_shrinks_on_timeout() {
+        let start = Instant::now();
+        let rtt = Duration::from_millis(50);
+        let mut window = LinkWindow::new(rtt);
+
+        assert_eq!(window.window(), WINDOW as usize);
+        window.sent(hash(0), start);
+        window.sent(hash(1), start);
+        assert!(!window.is_open());
+
+        assert!(window.delivered(&hash(0), rtt));
+        assert!(!window.delivered(&hash(0), rtt));
+        assert_eq!(window.window(), WINDOW as usize + 1);
+        assert!(window.is_open());
+
+        // Fast proofs raise the limit beyond the slow maximum
+        for value in 2..40 {
+            window.sent(hash(value), start);
+            window.delivered(&hash(value), rtt);
+        }
+        assert!(window.window() > WINDOW_MAX_SLOW as usize);
+
+        let grown = window.window();
+        window.sent(hash(100), start);
+        window.sent(hash(101), start);
+        assert_eq!(window.expire(start, proof_timeout(rtt)), 0);
+        assert_eq!(window.expire(start + Duration::from_secs(2), proof_timeout(rtt)), 3);
+        assert_eq!(window.outstanding(), 0);
+        assert_eq!(window.window(), grown - 3);
+    }
+
+    #[test]
+    fn slow_links_send_one_packet_at_a_time() {
+        let mut window = LinkWindow::new(Duration::from_secs(2));
+        window.sent(hash(0), Instant::now());
+        assert!(!window.is_open());
+        window.delivered(&hash(0), Duration::from_secs(2));
+        assert_eq!(window.window(), 1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Ratchets of a destination.
+//!
+//! A ratchet is an X25519 key which announces carry next to the identity
+//! key. Senders encrypt to the latest ratchet they know of, so rotating
+//! ratchets limits what a leaked key can decrypt. The keys are kept for a
+//! while after they were rotated out, as peers may still use older
+//! announces, and are written to a file so they survive restarts.
+//!
+//! The file has the layout of Python Reticulum, so either implementation
+//! can load the ratchets of the other: a msgpack map of the packed list of
+//! keys, newest first, and its signature by the identity of the destination.
+
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use ed25519_dalek::Signature;
+use rand_core::CryptoRngCore;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use super::RATCHET_LENGTH;
+use crate::identity::PrivateIdentity;
+use crate::msgpack::{Reader, Writer};
+
+/// Time after which a new ratchet is generated, as in Python Reticulum.
+pub const RATCHET_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// Ratchets kept by default, as in Python Reticulum.
+pub const RATCHET_COUNT: usize = 512;
+
+/// How often ratchets are rotated and how long they are kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RatchetRetention {
+    /// Least time between two ratchets.
+    pub interval: Duration,
+    /// Most ratchets kept, including the current one.
+    pub count: usize,
+    /// Ratchets older than this are dropped. The current ratchet is kept
+    /// regardless.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for RatchetRetention {
+    fn default() -> Self {
+        Self {
+            interval: RATCHET_INTERVAL,
+            count: RATCHET_COUNT,
+            max_age: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Ratchet {
+    secret: StaticSecret,
+    /// Time since the UNIX epoch the ratchet was generated at.
+    created: Duration,
+}
+
+impl Ratchet {
+    fn new<R: CryptoRngCore>(rng: R, created: Duration) -> Self {
+        Self { secret: StaticSecret::random_from_rng(rng), created }
+    }
+
+    pub fn secret(&self) -> &StaticSecret {
+        &self.secret
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(&self.secret)
+    }
+
+    pub fn created(&self) -> Duration {
+        self.created
+    }
+}
+
+/// The ratchets of a destination, newest first.
+pub struct Ratchets {
+    ratchets: Vec<Ratchet>,
+    retention: RatchetRetention,
+    path: Option<PathBuf>,
+}
+
+impl Ratchets {
+    /// Ratchets which are only kept in memory.
+    pub fn new(retention: RatchetRetention) -> Self {
+        Self { ratchets: Vec::new(), retention, path: None }
+    }
+
+    /// Ratchets which are kept in the file at `path`, starting with the
+    /// ones stored there. The file has to be signed by `identity`, a
+    /// missing file is created on the first rotation.
+    ///
+    /// Like in Python Reticulum, the file doesn't record when the ratchets
+    /// were created, so they count as created when it was last written.
+    pub fn load(
+        path: impl Into<PathBuf>,
+        identity: &PrivateIdentity,
+        retention: RatchetRetention,
+    ) -> io::Result<Self> {
+        let path = path.into();
+
+        let ratchets = match fs::read(&path) {
+            Ok(data) => decode_ratchets(&data, identity, modified(&path)).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "malformed or forged ratchet file")
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        log::debug!("ratchets: loaded {} from {}", ratchets.len(), path.display());
+
+        Ok(Self { ratchets, retention, path: Some(path) })
+    }
+
+    pub fn retention(&self) -> &RatchetRetention {
+        &self.retention
+    }
+
+    /// Applies to the next rotation.
+    pub fn set_retention(&mut self, retention: RatchetRetention) {
+        self.retention = retention;
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The ratchet announced last.
+    pub fn current(&self) -> Option<&Ratchet> {
+        self.ratchets.first()
+    }
+
+    /// The kept ratchets, newest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Ratchet> {
+        self.ratchets.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ratchets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ratchets.is_empty()
+    }
+
+    /// Generates a new ratchet if the current one is older than the
+    /// interval and drops the ratchets beyond the retention. Returns
+    /// whether the ratchets changed.
+    ///
+    /// The ratchets are saved before they change, if saving fails they are
+    /// left as they are. Announcing a ratchet which is lost on a restart
+    /// would leave messages encrypted to it undecryptable.
+    pub fn rotate<R: CryptoRngCore>(
+        &mut self,
+        rng: R,
+        identity: &PrivateIdentity,
+        now: Duration,
+    ) -> io::Result<bool> {
+        let mut ratchets = self.ratchets.clone();
+
+        let due = ratchets
+            .first()
+            .is_none_or(|current| now.saturating_sub(current.created) >= self.retention.interval);
+        if due {
+            ratchets.insert(0, Ratchet::new(rng, now));
+        }
+
+        self.retain(&mut ratchets, now);
+
+        if !due && ratchets.len() == self.ratchets.len() {
+            return Ok(false);
+        }
+
+        if let Some(path) = &self.path {
+            write_ratchets(path, &ratchets, identity)?;
+        }
+
+        self.ratchets = ratchets;
+
+        Ok(true)
+    }
+
+    fn retain(&self, ratchets: &mut Vec<Ratchet>, now: Duration) {
+        ratchets.truncate(self.retention.count.max(1));
+
+        if let Some(max_age) = self.retention.max_age {
+            let mut index = 0;
+            ratchets.retain(|ratchet| {
+                index += 1;
+                index == 1 || now.saturating_sub(ratchet.created) <= max_age
+            });
+        }
+    }
+}
+
+/// Writes `ratchets` signed by `identity` to the file at `path`, replacing
+/// it atomically.
+fn write_ratchets(path: &Path, ratchets: &[Ratchet], identity: &PrivateIdentity) -> io::Result<()> {
+    let mut keys = Writer::new();
+    keys.array(ratchets.len() as u32);
+    for ratchet in ratchets {
+        keys.bin(ratchet.secret.as_bytes());
+    }
+    let keys = keys.finish();
+
+    let data = Writer::new()
+        .map(2)
+        .str("signature")
+        .bin(&identity.sign(&keys).to_bytes())
+        .str("ratchets")
+        .bin(&keys)
+        .finish();
+
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, data)?;
+    fs::rename(&temp_path, path)
+}
+
+fn decode_ratchets(data: &[u8], identity: &PrivateIdentity, created: Duration) -> Option<Vec<Ratchet>> {
+    let mut reader = Reader::new(data);
+    let (mut signature, mut keys) = (None, None);
+    for _ in 0..reader.map().ok()? {
+        match reader.bin_or_str().ok()? {
+            b"signature" => signature = Some(reader.bin().ok()?),
+            b"ratchets" => keys = Some(reader.bin().ok()?),
+            _ => return None,
+        }
+    }
+
+    let signature = Signature::from_slice(signature?).ok()?;
+    let keys = keys?;
+    identity.verify(keys, &signature).ok()?;
+
+    let mut reader = Reader::new(keys);
+    let count = reader.array().ok()?;
+    let mut ratchets = Vec::with_capacity(count.min(RATCHET_COUNT as u32) as usize);
+    for _ in 0..count {
+        let key: [u8; RATCHET_LENGTH] = reader.bin().ok()?.try_into().ok()?;
+        ratchets.push(Ratchet { secret: StaticSecret::from(key), created });
+    }
+
+    Some(ratchets)
+}
+
+/// Time since the UNIX epoch the file at `path` was last written at.
+fn modified(path: &Path) -> Duration {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn rotate_at(ratchets: &mut Ratchets, identity: &PrivateIdentity, now: Duration) -> bool {
+        ratchets.rotate(OsRng, identity, now).unwrap()
+    }
+
+    #[test]
+    fn rotates_after_the_interval() {
+        let identity = PrivateIdentity::new_from_rand(OsRng);
+        let mut ratchets = Ratchets::new(RatchetRetention::default());
+
+        assert!(rotate_at(&mut ratchets, &identity, HOUR));
+        let first = ratchets.current().unwrap().public_key();
+
+        assert!(!rotate_at(&mut ratchets, &identity, HOUR + RATCHET_INTERVAL / 2));
+        assert_eq!(ratchets.current().unwrap().public_key(), first);
+
+        assert!(rotate_at(&mut ratchets, &identity, HOUR + RATCHET_INTERVAL));
+        assert_ne!(ratchets.current().unwrap().public_key(), first);
+        assert_eq!(ratchets.len(), 2);
+    }
+
+    #[test]
+    fn keeps_ratchets_within_the_retention() {
+        let identity = PrivateIdentity::new_from_rand(OsRng);
+        let mut ratchets = Ratchets::new(RatchetRetention {
+            interval: HOUR,
+            count: 3,
+            max_age: Some(HOUR * 10),
+        });
+
+        for hour in 0..5 {
+            rotate_at(&mut ratchets, &identity, HOUR * hour);
+        }
+        assert_eq!(ratchets.len(), 3);
+        assert_eq!(ratchets.current().unwrap().created(), HOUR * 4);
+
+        // Old ratchets expire, the current one stays
+        ratchets.set_retention(RatchetRetention {
+            interval: HOUR * 100,
+            count: 3,
+            max_age: Some(HOUR),
+        });
+        assert!(rotate_at(&mut ratchets, &identity, HOUR * 20));
+        assert_eq!(ratchets.len(), 1);
+        assert_eq!(ratchets.current().unwrap().created(), HOUR * 4);
+    }
+
+    #[test]
+    fn persist_ratchets() {
+        let identity = PrivateIdentity::new_from_rand(OsRng);
+        let path = std::env::temp_dir().join(format!("reticulum-ratchets-{}", identity.address_hash().to_hex()));
+        let _ = fs::remove_file(&path);
+
+        let mut ratchets = Ratchets::load(&path, &identity, RatchetRetention::default()).unwrap();
+        assert!(ratchets.is_empty());
+
+        rotate_at(&mut ratchets, &identity, HOUR);
+        rotate_at(&mut ratchets, &identity, HOUR * 2);
+
+        let restored = Ratchets::load(&path, &identity, RatchetRetention::default()).unwrap();
+        assert_eq!(restored.len(), 2);
+        for (restored, ratchet) in restored.iter().zip(ratchets.iter()) {
+            assert_eq!(restored.public_key(), ratchet.public_key());
+            assert_eq!(restored.created(), modified(&path));
+        }
+
+        // Only the identity which wrote the file is trusted
+        let other = PrivateIdentity::new_from_rand(OsRng);
+        assert!(Ratchets::load(&path, &other, RatchetRetention::default()).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ratchet_file_layout_like_python() {
+        let identity = PrivateIdentity::new_from_rand(OsRng);
+        let path = std::env::temp_dir().join(format!("reticulum-ratchets-py-{}", identity.address_hash().to_hex()));
+        let _ = fs::remove_file(&path);
+
+        let mut ratchets = Ratchets::load(&path, &identity, RatchetRetention::default()).unwrap();
+        rotate_at(&mut ratchets, &identity, HOUR);
+        rotate_at(&mut ratchets, &identity, HOUR * 2);
+
+        // {"signature": sign(packb(keys)), "ratchets": packb(keys)}
+        let data = fs::read(&path).unwrap();
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.map().unwrap(), 2);
+        assert_eq!(reader.str().unwrap(), "signature");
+        let signature = Signature::from_slice(reader.bin().unwrap()).unwrap();
+        assert_eq!(reader.str().unwrap(), "ratchets");
+        let keys = reader.bin().unwrap();
+        assert!(reader.is_empty());
+        identity.verify(keys, &signature).unwrap();
+
+        let mut reader = Reader::new(keys);
+        assert_eq!(reader.array().unwrap(), 2);
+        for ratchet in ratchets.iter() {
+            assert_eq!(reader.bin().unwrap(), ratchet.secret().as_bytes());
+        }
+
+        // Python writes the same map, the keys only as bin
+        let mut keys = Writer::new();
+        keys.array(1).bin(&[7u8; RATCHET_LENGTH]);
+        let keys = keys.finish();
+        let python = Writer::new()
+            .map(2)
+            .str("signature")
+            .bin(&identity.sign(&keys).to_bytes())
+            .str("ratchets")
+            .bin(&keys)
+            .finish();
+        let loaded = decode_ratchets(&python, &identity, HOUR).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].secret().as_bytes(), &[7u8; RATCHET_LENGTH]);
+        assert_eq!(loaded[0].created(), HOUR);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
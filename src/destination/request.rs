@@ -0,0 +1,226 @@
+//! Requests and responses over links.
+//!
+//! A request is the msgpack array `[sent_at, path_hash, data]` in a data
+//! packet with the `Request` context. The truncated hash of that packet
+//! identifies the request, and the response is `[request_id, data]` in a
+//! packet with the `Response` context. `data` is any msgpack value. This is
+//! what Python's `Link.request()` sends for requests and responses which fit
+//! into one packet.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use std::collections::HashMap;
+
+use crate::error::RnsError;
+use crate::hash::{AddressHash, ADDRESS_HASH_SIZE};
+use crate::identity::Identity;
+use crate::msgpack::{Reader, Writer};
+
+/// Truncated hash of the request packet.
+pub type RequestId = AddressHash;
+
+/// Hash by which requests name their path, the same as Python's
+/// `Identity.truncated_hash(path.encode("utf-8"))`.
+pub fn path_hash(path: &str) -> AddressHash {
+    AddressHash::new_from_slice(path.as_bytes())
+}
+
+fn read_address(reader: &mut Reader) -> Result<AddressHash, RnsError> {
+    let bytes: [u8; ADDRESS_HASH_SIZE] =
+        reader.bin()?.try_into().map_err(|_| RnsError::PacketError)?;
+    Ok(AddressHash::new(bytes))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkRequest {
+    pub request_id: RequestId,
+    pub path_hash: AddressHash,
+    /// Unix time in seconds at which the peer sent the request.
+    pub sent_at: f64,
+    /// msgpack encoded request data.
+    pub data: Vec<u8>,
+}
+
+impl LinkRequest {
+    pub fn encode(sent_at: f64, path_hash: &AddressHash, data: &[u8]) -> Vec<u8> {
+        Writer::new()
+            .array(3)
+            .f64(sent_at)
+            .bin(path_hash.as_slice())
+            .raw(data)
+            .finish()
+    }
+
+    pub fn decode(request_id: RequestId, data: &[u8]) -> Result<Self, RnsError> {
+        let mut reader = Reader::new(data);
+
+        if reader.array()? != 3 {
+            return Err(RnsError::PacketError);
+        }
+
+        let sent_at = reader.f64()?;
+        let path_hash = read_address(&mut reader)?;
+
+        Ok(Self {
+            request_id,
+            path_hash,
+            sent_at,
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkResponse {
+    pub request_id: RequestId,
+    /// msgpack encoded response data.
+    pub data: Vec<u8>,
+}
+
+impl LinkResponse {
+    pub fn encode(request_id: &RequestId, data: &[u8]) -> Vec<u8> {
+        Writer::new()
+            .array(2)
+            .bin(request_id.as_slice())
+            .raw(data)
+            .finish()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, RnsError> {
+        let mut reader = Reader::new(data);
+
+        if reader.array()? != 2 {
+            return Err(RnsError::PacketError);
+        }
+
+        let request_id = read_address(&mut reader)?;
+
+        Ok(Self {
+            request_id,
+            data: reader.remaining().to_vec(),
+        })
+    }
+}
+
+/// Who may send requests to a path.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum RequestPolicy {
+    /// Anyone, also peers which didn't identify.
+    #[default]
+    All,
+    /// Peers which identified with one of the identity hashes.
+    Allowed(Vec<AddressHash>),
+    /// Nobody, the path is disabled.
+    Nobody,
+}
+
+impl RequestPolicy {
+    pub fn allows(&self, identity: Option<&Identity>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Allowed(allowed) => identity.is_some_and(|identity| allowed.contains(&identity.address_hash)),
+            Self::Nobody => false,
+        }
+    }
+}
+
+/// A request as its handler sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct IncomingRequest<'a> {
+    pub path: &'a str,
+    /// msgpack encoded request data.
+    pub data: &'a [u8],
+    pub request_id: RequestId,
+    pub link_id: AddressHash,
+    /// Identity the peer identified with on the link, `None` if it didn't.
+    pub remote_identity: Option<&'a Identity>,
+    /// Unix time in seconds at which the peer sent the request.
+    pub requested_at: f64,
+}
+
+/// Answers the requests to a path with msgpack encoded data, or not at all
+/// with `None`.
+pub type RequestHandler = Arc<dyn Fn(&IncomingRequest) -> Option<Vec<u8>> + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct RegisteredHandler {
+    pub path: String,
+    pub handler: RequestHandler,
+    pub policy: RequestPolicy,
+}
+
+/// Request handlers of a destination by the hash of their path.
+#[derive(Clone, Default)]
+pub(crate) struct RequestHandlers {
+    handlers: HashMap<AddressHash, RegisteredHandler>,
+}
+
+impl RequestHandlers {
+    pub fn register(&mut self, path: &str, handler: RequestHandler, policy: RequestPolicy) {
+        let registered = RegisteredHandler { path: path.into(), handler, policy };
+        self.handlers.insert(path_hash(path), registered);
+    }
+
+    pub fn deregister(&mut self, path: &str) -> bool {
+        self.handlers.remove(&path_hash(path)).is_some()
+    }
+
+    pub fn get(&self, path_hash: &AddressHash) -> Option<&RegisteredHandler> {
+        self.handlers.get(path_hash)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.handlers.values().map(|registered| registered.path.as_str())
+    }
+}
+
+impl fmt::Debug for RequestHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.paths()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_hash_matches_python() {
+        // RNS.Identity.truncated_hash("/status".encode("utf-8")).hex()
+        assert_eq!(path_hash("/status").to_hex_string(), "ae4267a01f1269fbbf4824d26cf3bb22");
+    }
+
+    #[test]
+    fn request_policies() {
+        let identity = crate::identity::PrivateIdentity::new_from_name("requester");
+        let identity = identity.as_identity();
+
+        assert!(RequestPolicy::All.allows(None));
+        assert!(!RequestPolicy::Nobody.allows(Some(identity)));
+
+        let allowed = RequestPolicy::Allowed(vec![identity.address_hash]);
+        assert!(allowed.allows(Some(identity)));
+        assert!(!allowed.allows(None));
+        assert!(!RequestPolicy::Allowed(Vec::new()).allows(Some(identity)));
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = Writer::new().array(1).bool(true).finish();
+        let request_id = AddressHash::new([0x11; ADDRESS_HASH_SIZE]);
+
+        let encoded = LinkRequest::encode(1700000000.5, &path_hash("/status"), &data);
+        let request = LinkRequest::decode(request_id, &encoded).unwrap();
+        assert_eq!(request.path_hash, path_hash("/status"));
+        assert_eq!(request.sent_at, 1700000000.5);
+        assert_eq!(request.data, data);
+
+        let response = LinkResponse::decode(&LinkResponse::encode(&request_id, &data)).unwrap();
+        assert_eq!(response, LinkResponse { request_id, data: data.clone() });
+
+        assert!(LinkRequest::decode(request_id, &data).is_err());
+        assert!(LinkResponse::decode(&[]).is_err());
+    }
+}
@@ -0,0 +1,301 @@
+//! Detail on received bytes which couldn't be decoded.
+//!
+//! Interfaces warn when a frame or a packet can't be decoded. With
+//! [`set_decode_dumps`] they also log what the bytes looked like: the header
+//! bits and flags, where each field lies, and the field and offset at which
+//! decoding stopped. [`DecodeDumps::Hexdump`] adds the bytes themselves.
+//!
+//! Dumps are rate limited like per-packet trace lines, see
+//! [`TraceCategory::DecodeDump`], so a peer sending garbage doesn't flood
+//! the log.
+
+use core::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::error::RnsError;
+use crate::hash::ADDRESS_HASH_SIZE;
+use crate::packet::{Header, HeaderType, IfacFlag, PACKET_MDU};
+use crate::trace::{self, TraceCategory};
+
+/// Bytes shown by a hexdump, the rest is only counted.
+const HEXDUMP_LIMIT: usize = 256;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeDumps {
+    /// Only the warning is logged.
+    #[default]
+    Off,
+    /// The warning is followed by the decoded fields.
+    Fields,
+    /// The warning is followed by the decoded fields and a hexdump.
+    Hexdump,
+}
+
+impl core::str::FromStr for DecodeDumps {
+    type Err = RnsError;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_ascii_lowercase().as_str() {
+            "off" => Ok(DecodeDumps::Off),
+            "fields" => Ok(DecodeDumps::Fields),
+            "hexdump" => Ok(DecodeDumps::Hexdump),
+            _ => Err(RnsError::InvalidArgument),
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(DecodeDumps::Off as u8);
+
+/// Sets what is logged about bytes which couldn't be decoded.
+pub fn set_decode_dumps(mode: DecodeDumps) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn decode_dumps() -> DecodeDumps {
+    match MODE.load(Ordering::Relaxed) {
+        1 => DecodeDumps::Fields,
+        2 => DecodeDumps::Hexdump,
+        _ => DecodeDumps::Off,
+    }
+}
+
+/// Field of the packet wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireField {
+    Header,
+    Hops,
+    Transport,
+    Destination,
+    Context,
+    Data,
+}
+
+/// Where and why decoding a packet stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeFailure {
+    pub field: WireField,
+    /// Offset of the field in the packet.
+    pub offset: usize,
+    /// Bytes the field needs, at most for the data.
+    pub expected: usize,
+    /// Bytes there are from the offset on.
+    pub available: usize,
+}
+
+impl fmt::Display for DecodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.field == WireField::Data {
+            write!(
+                f,
+                "data at offset {} is {} bytes, more than the MDU of {}",
+                self.offset, self.available, self.expected
+            )
+        } else {
+            write!(
+                f,
+                "{:?} cut short at offset {}, {} bytes needed, {} left",
+                self.field, self.offset, self.expected, self.available
+            )
+        }
+    }
+}
+
+/// Dump of bytes which were meant to be a packet, see the [module](self).
+#[derive(Debug, Clone, Copy)]
+pub struct PacketDump<'a> {
+    bytes: &'a [u8],
+    hexdump: bool,
+}
+
+impl<'a> PacketDump<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, hexdump: false }
+    }
+
+    /// Adds a hexdump of the bytes.
+    pub fn with_hexdump(mut self) -> Self {
+        self.hexdump = true;
+        self
+    }
+
+    /// The field at which [`Packet::deserialize`] stops, `None` if the bytes
+    /// decode.
+    ///
+    /// [`Packet::deserialize`]: crate::packet::Packet::deserialize
+    pub fn failure(&self) -> Option<DecodeFailure> {
+        let len = self.bytes.len();
+        let header_type = self.bytes.first().map(|meta| Header::from_meta(*meta).header_type);
+
+        let mut fields = vec![(WireField::Header, 1), (WireField::Hops, 1)];
+        if header_type == Some(HeaderType::Type2) {
+            fields.push((WireField::Transport, ADDRESS_HASH_SIZE));
+        }
+        fields.push((WireField::Destination, ADDRESS_HASH_SIZE));
+        fields.push((WireField::Context, 1));
+
+        let mut offset = 0;
+        for (field, expected) in fields {
+            if offset + expected > len {
+                return Some(DecodeFailure { field, offset, expected, available: len - offset });
+            }
+            offset += expected;
+        }
+
+        (len - offset > PACKET_MDU).then_some(DecodeFailure {
+            field: WireField::Data,
+            offset,
+            expected: PACKET_MDU,
+            available: len - offset,
+        })
+    }
+}
+
+impl fmt::Display for PacketDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", self.bytes.len())?;
+
+        if let Some(&meta) = self.bytes.first() {
+            let header = Header::from_meta(meta);
+            write!(
+                f,
+                ", meta {:#010b} (ifac {:?}, header {:?}, context flag {:?}, propagation {:?}, destination {:?}, packet {:?})",
+                meta,
+                header.ifac_flag,
+                header.header_type,
+                header.context_flag,
+                header.propagation_type,
+                header.destination_type,
+                header.packet_type
+            )?;
+
+            if let Some(hops) = self.bytes.get(1) {
+                write!(f, ", hops {}", hops)?;
+            }
+            if header.ifac_flag == IfacFlag::Authenticated {
+                write!(f, ", IFAC set but not removed")?;
+            }
+        }
+
+        match self.failure() {
+            Some(failure) => write!(f, ": {}", failure)?,
+            None => write!(f, ": fields decode")?,
+        }
+
+        if self.hexdump {
+            write_hexdump(f, self.bytes)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_hexdump(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for (line, chunk) in bytes[..bytes.len().min(HEXDUMP_LIMIT)].chunks(16).enumerate() {
+        write!(f, "\n  {:04x}:", line * 16)?;
+        for byte in chunk {
+            write!(f, " {:02x}", byte)?;
+        }
+    }
+
+    if bytes.len() > HEXDUMP_LIMIT {
+        write!(f, "\n  ... {} more bytes", bytes.len() - HEXDUMP_LIMIT)?;
+    }
+
+    Ok(())
+}
+
+fn dump_allowed() -> Option<bool> {
+    let mode = decode_dumps();
+    (mode != DecodeDumps::Off && trace::allow(TraceCategory::DecodeDump)).then_some(mode == DecodeDumps::Hexdump)
+}
+
+/// Warns that `bytes` received by `source` aren't a packet, with a dump if
+/// enabled.
+pub(crate) fn packet_undecodable(source: impl fmt::Display, bytes: &[u8]) {
+    log::warn!("{}: couldn't decode packet", source);
+
+    if let Some(hexdump) = dump_allowed() {
+        let dump = PacketDump::new(bytes);
+        let dump = if hexdump { dump.with_hexdump() } else { dump };
+        log::warn!("{}: undecodable packet: {}", source, dump);
+    }
+}
+
+/// Warns that a codec of `source` dropped frames for `reason`, with the
+/// received `chunk` which completed them if dumps are enabled. The frames
+/// themselves are gone by then.
+pub(crate) fn frame_undecodable(source: impl fmt::Display, reason: &str, chunk: &[u8]) {
+    log::warn!("{}: couldn't decode frame", source);
+
+    if let Some(hexdump) = dump_allowed() {
+        log::warn!("{}: dropped {} frame in a chunk of {} bytes", source, reason, chunk.len());
+        if hexdump {
+            log::warn!("{}: chunk:{}", source, Hexdump(chunk));
+        }
+    }
+}
+
+struct Hexdump<'a>(&'a [u8]);
+
+impl fmt::Display for Hexdump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hexdump(f, self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::InputBuffer;
+    use crate::hash::AddressHash;
+    use crate::packet::{Packet, PacketDataBuffer};
+
+    use super::*;
+
+    #[test]
+    fn finds_the_field_decoding_stops_at() {
+        let packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"data"),
+            destination: AddressHash::new([0x11; ADDRESS_HASH_SIZE]),
+            ..Default::default()
+        };
+        let wire = packet.to_wire_vec().unwrap();
+
+        assert_eq!(PacketDump::new(&wire).failure(), None);
+        assert!(Packet::deserialize(&mut InputBuffer::new(&wire)).is_ok());
+
+        let failure = PacketDump::new(&wire[..10]).failure().unwrap();
+        assert_eq!(failure, DecodeFailure { field: WireField::Destination, offset: 2, expected: 16, available: 8 });
+        assert!(Packet::deserialize(&mut InputBuffer::new(&wire[..10])).is_err());
+
+        // Type 2 headers carry a transport id first
+        let mut type2 = wire.clone();
+        type2[0] |= 0b0100_0000;
+        let failure = PacketDump::new(&type2).failure().unwrap();
+        assert_eq!(failure, DecodeFailure { field: WireField::Destination, offset: 18, expected: 16, available: 5 });
+
+        let mut oversized = wire[..19].to_vec();
+        oversized.resize(19 + PACKET_MDU + 1, 0);
+        let failure = PacketDump::new(&oversized).failure().unwrap();
+        assert_eq!((failure.field, failure.offset, failure.available), (WireField::Data, 19, PACKET_MDU + 1));
+        assert!(Packet::deserialize(&mut InputBuffer::new(&oversized)).is_err());
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("Hexdump".parse::<DecodeDumps>(), Ok(DecodeDumps::Hexdump));
+        assert_eq!("fields".parse::<DecodeDumps>(), Ok(DecodeDumps::Fields));
+        assert!("verbose".parse::<DecodeDumps>().is_err());
+    }
+
+    #[test]
+    fn dumps_header_bits_and_bytes() {
+        let dump = PacketDump::new(&[0b0101_0001, 3, 0xaa]).with_hexdump().to_string();
+
+        assert!(dump.starts_with("3 bytes, meta 0b01010001 (ifac Open, header Type2"), "{}", dump);
+        assert!(dump.contains("hops 3"), "{}", dump);
+        assert!(dump.contains("Transport cut short at offset 2, 16 bytes needed, 1 left"), "{}", dump);
+        assert!(dump.ends_with("\n  0000: 51 03 aa"), "{}", dump);
+
+        assert_eq!(PacketDump::new(&[]).to_string(), "0 bytes: Header cut short at offset 0, 1 bytes needed, 0 left");
+    }
+}
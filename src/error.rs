@@ -12,4 +12,29 @@ pub enum RnsError {
     ChannelLinkNotReady,
     ChannelMessageTooBig,
     ChannelUnknownMessageType,
+    PacketTooLarge,
 }
+
+impl core::fmt::Display for RnsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let description = match self {
+            RnsError::OutOfMemory => "out of memory",
+            RnsError::InvalidArgument => "invalid argument",
+            RnsError::IncorrectSignature => "incorrect signature",
+            RnsError::IncorrectHash => "incorrect hash",
+            RnsError::CryptoError => "cryptographic operation failed",
+            RnsError::PacketError => "malformed packet",
+            RnsError::ConnectionError => "connection error",
+            RnsError::LinkClosed => "link is closed",
+            RnsError::ChannelError => "channel error",
+            RnsError::ChannelLinkNotReady => "channel link is not ready",
+            RnsError::ChannelMessageTooBig => "channel message is too big",
+            RnsError::ChannelUnknownMessageType => "unknown channel message type",
+            RnsError::PacketTooLarge => "packet is larger than the MTU",
+        };
+
+        f.write_str(description)
+    }
+}
+
+impl std::error::Error for RnsError {}
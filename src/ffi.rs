@@ -0,0 +1,854 @@
+//! C ABI for embedding a node in mobile apps, firmware and daemons.
+//!
+//! A node is created with [`rns_node_new`] and owns its transport and the
+//! runtime driving it, so the functions can be called from any thread of
+//! the app. Functions return [`RNS_OK`] or a negative error code.
+//! Destination hashes and link ids are passed as 16 byte arrays. Events are
+//! delivered through the [`RnsCallbacks`] set with [`rns_node_set_callbacks`];
+//! the callbacks run on a runtime thread and must not block.
+//!
+//! Besides the TCP interfaces, a node can have interfaces whose frames are
+//! carried by the app itself, see [`rns_interface_new`].
+//!
+//! The `reticulum-capi` crate builds these functions into a static and a
+//! shared library and generates `reticulum-capi/include/reticulum.h` from
+//! this module.
+
+use core::ffi::{c_char, c_int, c_void, CStr};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use rand_core::OsRng;
+use tokio::runtime::Runtime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::destination::link::{Link, LinkEvent, LinkEventData, LinkId};
+use crate::destination::{DestinationName, SingleInputDestination};
+use crate::hash::{AddressHash, ADDRESS_HASH_SIZE};
+use crate::identity::PrivateIdentity;
+use crate::iface::tcp_client::TcpClient;
+use crate::iface::tcp_server::TcpServer;
+use crate::iface::{InterfaceRxSender, InterfaceTxReceiver, RxMessage};
+use crate::packet::{Packet, PACKET_MAX_SIZE};
+use crate::transport::{Transport, TransportConfig, TransportEvent};
+
+pub const RNS_OK: c_int = 0;
+/// A null pointer, a string which isn't UTF-8 or an invalid identity.
+pub const RNS_ERR_ARGUMENT: c_int = -1;
+/// The destination or link isn't known.
+pub const RNS_ERR_NOT_FOUND: c_int = -2;
+/// The link is closed or the payload doesn't fit into a packet.
+pub const RNS_ERR_LINK: c_int = -3;
+/// A received frame isn't a valid packet.
+pub const RNS_ERR_FRAME: c_int = -4;
+/// The transport can't take more frames right now, try again later.
+pub const RNS_ERR_BUSY: c_int = -5;
+
+/// Size of a buffer which fits every frame of an interface.
+pub const RNS_FRAME_MAX_SIZE: usize = 2112;
+
+const _: () = assert!(PACKET_MAX_SIZE <= RNS_FRAME_MAX_SIZE);
+
+/// Frames waiting to be taken by the app before the oldest are dropped.
+const FRAME_QUEUE_SIZE: usize = 32;
+
+// The callback types are nullable, so they are declared with their `Option`
+pub type RnsAnnounceCallback = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        destination: *const u8,
+        app_data: *const u8,
+        app_data_len: usize,
+    ),
+>;
+
+pub type RnsLinkCallback =
+    Option<unsafe extern "C" fn(user_data: *mut c_void, link_id: *const u8, destination: *const u8)>;
+
+pub type RnsLinkDataCallback = Option<
+    unsafe extern "C" fn(user_data: *mut c_void, link_id: *const u8, data: *const u8, len: usize),
+>;
+
+/// Event callbacks of a node, any of them may be null. Pointers passed to
+/// the callbacks are only valid during the call.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RnsCallbacks {
+    pub user_data: *mut c_void,
+    pub on_announce: RnsAnnounceCallback,
+    pub on_link_activated: RnsLinkCallback,
+    pub on_link_closed: RnsLinkCallback,
+    pub on_link_data: RnsLinkDataCallback,
+}
+
+// The app is responsible for `user_data` being usable from the runtime threads
+unsafe impl Send for RnsCallbacks {}
+
+/// A private identity.
+pub struct RnsIdentity(PrivateIdentity);
+
+/// A destination registered with a node.
+pub struct RnsDestination {
+    destination: Arc<Mutex<SingleInputDestination>>,
+    address: AddressHash,
+}
+
+/// An interface whose frames are carried by the app, e.g. over a radio or
+/// serial driver of the firmware.
+pub struct RnsInterface {
+    address: AddressHash,
+    rx_channel: InterfaceRxSender,
+    tx_channel: InterfaceTxReceiver,
+    stop: CancellationToken,
+}
+
+pub struct RnsNode {
+    transport: Transport,
+    identity: PrivateIdentity,
+    out_links: Mutex<HashMap<LinkId, Arc<Mutex<Link>>>>,
+    callbacks: Option<CancellationToken>,
+    // Dropped last, after the transport stopped its tasks
+    runtime: Runtime,
+}
+
+unsafe fn string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(ptr).to_str().ok().map(String::from)
+}
+
+unsafe fn address(ptr: *const u8) -> Option<AddressHash> {
+    if ptr.is_null() {
+        return None;
+    }
+
+    let mut hash = [0u8; ADDRESS_HASH_SIZE];
+    hash.copy_from_slice(core::slice::from_raw_parts(ptr, ADDRESS_HASH_SIZE));
+    Some(AddressHash::new(hash))
+}
+
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(core::slice::from_raw_parts(ptr, len)),
+    }
+}
+
+unsafe fn write_address(out: *mut u8, address: &AddressHash) {
+    core::ptr::copy_nonoverlapping(address.as_slice().as_ptr(), out, ADDRESS_HASH_SIZE);
+}
+
+/// Creates a new random identity.
+#[no_mangle]
+pub extern "C" fn rns_identity_new() -> *mut RnsIdentity {
+    Box::into_raw(Box::new(RnsIdentity(PrivateIdentity::new_from_rand(OsRng))))
+}
+
+/// Restores an identity from its hex encoded private keys. Returns null if
+/// `hex` isn't a valid identity.
+///
+/// # Safety
+///
+/// `hex` must be null or point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rns_identity_from_hex(hex: *const c_char) -> *mut RnsIdentity {
+    match string(hex).map(|hex| PrivateIdentity::new_from_hex_string(&hex)) {
+        Some(Ok(identity)) => Box::into_raw(Box::new(RnsIdentity(identity))),
+        _ => core::ptr::null_mut(),
+    }
+}
+
+/// Writes the hash of an identity to `out_hash`.
+///
+/// # Safety
+///
+/// `identity` must come from [`rns_identity_new`] and `out_hash` must have
+/// room for 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_identity_hash(identity: *const RnsIdentity, out_hash: *mut u8) -> c_int {
+    let Some(identity) = identity.as_ref() else {
+        return RNS_ERR_ARGUMENT;
+    };
+    if out_hash.is_null() {
+        return RNS_ERR_ARGUMENT;
+    }
+
+    write_address(out_hash, identity.0.address_hash());
+
+    RNS_OK
+}
+
+/// Frees an identity.
+///
+/// # Safety
+///
+/// `identity` must be null or come from [`rns_identity_new`] and not be used
+/// again.
+#[no_mangle]
+pub unsafe extern "C" fn rns_identity_free(identity: *mut RnsIdentity) {
+    if !identity.is_null() {
+        drop(Box::from_raw(identity));
+    }
+}
+
+/// Creates a node named `name`. `identity` is the identity of the node, a
+/// new one is generated if it is null. Returns null if an argument is
+/// invalid or the runtime can't be started.
+///
+/// # Safety
+///
+/// `name` must point to a NUL terminated string, `identity` must be null or
+/// come from [`rns_identity_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rns_node_new(name: *const c_char, identity: *const RnsIdentity) -> *mut RnsNode {
+    let Some(name) = string(name) else {
+        return core::ptr::null_mut();
+    };
+
+    let identity = match identity.as_ref() {
+        Some(identity) => identity.0.clone(),
+        None => PrivateIdentity::new_from_rand(OsRng),
+    };
+
+    let Ok(runtime) = Runtime::new() else {
+        return core::ptr::null_mut();
+    };
+
+    let transport = runtime.block_on(async {
+        Transport::new(TransportConfig::new(name, &identity, false))
+    });
+
+    Box::into_raw(Box::new(RnsNode {
+        transport,
+        identity,
+        out_links: Mutex::new(HashMap::new()),
+        callbacks: None,
+        runtime,
+    }))
+}
+
+/// Stops the node and frees it.
+///
+/// # Safety
+///
+/// `node` must be null or come from [`rns_node_new`] and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn rns_node_free(node: *mut RnsNode) {
+    if node.is_null() {
+        return;
+    }
+
+    let node = Box::from_raw(node);
+    if let Some(callbacks) = &node.callbacks {
+        callbacks.cancel();
+    }
+    drop(node);
+}
+
+/// Connects the node to a TCP server at `addr`, e.g. `"10.0.0.1:4242"`.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `addr` must be a NUL terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn rns_node_add_tcp_client(node: *mut RnsNode, addr: *const c_char) -> c_int {
+    let (Some(node), Some(addr)) = (node.as_ref(), string(addr)) else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    node.runtime.block_on(async {
+        node.transport
+            .iface_manager()
+            .lock()
+            .await
+            .spawn(TcpClient::new(addr), TcpClient::spawn);
+    });
+
+    RNS_OK
+}
+
+/// Accepts TCP clients on `addr`, e.g. `"0.0.0.0:4242"`.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `addr` must be a NUL terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn rns_node_add_tcp_server(node: *mut RnsNode, addr: *const c_char) -> c_int {
+    let (Some(node), Some(addr)) = (node.as_ref(), string(addr)) else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    node.runtime.block_on(async {
+        let iface_manager = node.transport.iface_manager();
+        iface_manager
+            .lock()
+            .await
+            .spawn(TcpServer::new(addr, iface_manager.clone()), TcpServer::spawn);
+    });
+
+    RNS_OK
+}
+
+/// Adds an interface whose frames are exchanged with
+/// [`rns_interface_frame_in`] and [`rns_interface_frame_out`].
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`].
+#[no_mangle]
+pub unsafe extern "C" fn rns_interface_new(node: *mut RnsNode) -> *mut RnsInterface {
+    let Some(node) = node.as_ref() else {
+        return core::ptr::null_mut();
+    };
+
+    let channel = node.runtime.block_on(async {
+        node.transport
+            .iface_manager()
+            .lock()
+            .await
+            .new_channel(FRAME_QUEUE_SIZE)
+    });
+
+    let address = *channel.address();
+    let stop = channel.stop.clone();
+    let (rx_channel, tx_channel) = channel.split();
+
+    Box::into_raw(Box::new(RnsInterface {
+        address,
+        rx_channel,
+        tx_channel,
+        stop,
+    }))
+}
+
+/// Passes a frame received by the app to the transport.
+///
+/// # Safety
+///
+/// `iface` must come from [`rns_interface_new`] and `frame` must point to
+/// `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_interface_frame_in(iface: *mut RnsInterface, frame: *const u8, len: usize) -> c_int {
+    let (Some(iface), Some(frame)) = (iface.as_ref(), bytes(frame, len)) else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    let Ok(packet) = Packet::from_wire(frame) else {
+        return RNS_ERR_FRAME;
+    };
+
+    match iface.rx_channel.try_send(RxMessage { address: iface.address, packet }) {
+        Ok(()) => RNS_OK,
+        Err(_) => RNS_ERR_BUSY,
+    }
+}
+
+/// Takes the next frame the transport sends over the interface. Returns
+/// its length, or 0 if there is none. `buffer` should have room for
+/// [`RNS_FRAME_MAX_SIZE`] bytes.
+///
+/// # Safety
+///
+/// `iface` must come from [`rns_interface_new`] and `buffer` must have room
+/// for `capacity` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_interface_frame_out(
+    iface: *mut RnsInterface,
+    buffer: *mut u8,
+    capacity: usize,
+) -> c_int {
+    let Some(iface) = iface.as_mut() else {
+        return RNS_ERR_ARGUMENT;
+    };
+    if buffer.is_null() {
+        return RNS_ERR_ARGUMENT;
+    }
+
+    let Ok(message) = iface.tx_channel.try_recv() else {
+        return 0;
+    };
+
+    let buffer = core::slice::from_raw_parts_mut(buffer, capacity);
+    match message.packet.to_wire(buffer) {
+        Ok(len) => len as c_int,
+        Err(_) => RNS_ERR_ARGUMENT,
+    }
+}
+
+/// Removes an interface from its node and frees it.
+///
+/// # Safety
+///
+/// `iface` must be null or come from [`rns_interface_new`] and not be used
+/// again.
+#[no_mangle]
+pub unsafe extern "C" fn rns_interface_free(iface: *mut RnsInterface) {
+    if iface.is_null() {
+        return;
+    }
+
+    let iface = Box::from_raw(iface);
+    iface.stop.cancel();
+}
+
+/// Replaces the event callbacks of the node.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`]. The callbacks may be called until
+/// they are replaced or the node is freed.
+#[no_mangle]
+pub unsafe extern "C" fn rns_node_set_callbacks(node: *mut RnsNode, callbacks: RnsCallbacks) -> c_int {
+    let Some(node) = node.as_mut() else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    if let Some(previous) = node.callbacks.take() {
+        previous.cancel();
+    }
+
+    let cancel = CancellationToken::new();
+    node.callbacks = Some(cancel.clone());
+
+    let events = node.transport.events();
+    let in_link_events = node.transport.in_link_events();
+    let out_link_events = node.transport.out_link_events();
+
+    node.runtime
+        .spawn(deliver_events(callbacks, events, in_link_events, out_link_events, cancel));
+
+    RNS_OK
+}
+
+/// Adds a destination `app_name.aspects` to the node. It belongs to
+/// `identity`, or to the identity of the node if `identity` is null. Returns
+/// null if an argument is invalid.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `identity` must be null or come
+/// from [`rns_identity_new`], `app_name` and `aspects` must be NUL terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn rns_destination_new(
+    node: *mut RnsNode,
+    identity: *const RnsIdentity,
+    app_name: *const c_char,
+    aspects: *const c_char,
+) -> *mut RnsDestination {
+    let (Some(node), Some(app_name), Some(aspects)) = (node.as_mut(), string(app_name), string(aspects)) else {
+        return core::ptr::null_mut();
+    };
+
+    let identity = match identity.as_ref() {
+        Some(identity) => identity.0.clone(),
+        None => node.identity.clone(),
+    };
+    let name = DestinationName::new(&app_name, &aspects);
+
+    let transport = &mut node.transport;
+    let destination = node.runtime.block_on(async {
+        let destination = transport.add_destination(identity, name).await;
+        let address = destination.lock().await.desc.address_hash;
+        RnsDestination { destination, address }
+    });
+
+    Box::into_raw(Box::new(destination))
+}
+
+/// Writes the hash of a destination to `out_hash`.
+///
+/// # Safety
+///
+/// `destination` must come from [`rns_destination_new`] and `out_hash` must
+/// have room for 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_destination_hash(destination: *const RnsDestination, out_hash: *mut u8) -> c_int {
+    let Some(destination) = destination.as_ref() else {
+        return RNS_ERR_ARGUMENT;
+    };
+    if out_hash.is_null() {
+        return RNS_ERR_ARGUMENT;
+    }
+
+    write_address(out_hash, &destination.address);
+
+    RNS_OK
+}
+
+/// Announces a destination with optional `app_data`. Fails with
+/// [`RNS_ERR_ARGUMENT`] if the announce doesn't fit the MTU of the
+/// interfaces.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `destination` from
+/// [`rns_destination_new`] of the same node and `app_data` must point to
+/// `app_data_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_destination_announce(
+    node: *mut RnsNode,
+    destination: *const RnsDestination,
+    app_data: *const u8,
+    app_data_len: usize,
+) -> c_int {
+    let (Some(node), Some(destination), Some(app_data)) =
+        (node.as_ref(), destination.as_ref(), bytes(app_data, app_data_len))
+    else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    let app_data = (!app_data.is_empty()).then_some(app_data);
+    match node
+        .runtime
+        .block_on(node.transport.send_announce(&destination.destination, app_data))
+    {
+        Ok(()) => RNS_OK,
+        Err(_) => RNS_ERR_ARGUMENT,
+    }
+}
+
+/// Frees a destination handle, the destination stays registered with the
+/// node.
+///
+/// # Safety
+///
+/// `destination` must be null or come from [`rns_destination_new`] and not
+/// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn rns_destination_free(destination: *mut RnsDestination) {
+    if !destination.is_null() {
+        drop(Box::from_raw(destination));
+    }
+}
+
+/// Opens a link to an announced destination and writes its id to
+/// `out_link_id`. The link can be used once `on_link_activated` is called.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `destination` must point to 16
+/// bytes and `out_link_id` must have room for 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_link_open(
+    node: *mut RnsNode,
+    destination: *const u8,
+    out_link_id: *mut u8,
+) -> c_int {
+    let (Some(node), Some(destination)) = (node.as_ref(), address(destination)) else {
+        return RNS_ERR_ARGUMENT;
+    };
+    if out_link_id.is_null() {
+        return RNS_ERR_ARGUMENT;
+    }
+
+    let link_id = node.runtime.block_on(async {
+        let destination = node.transport.get_out_destination(&destination).await?;
+        let desc = destination.lock().await.desc;
+
+        let link = node.transport.link(desc).await;
+        let link_id = *link.lock().await.id();
+        node.out_links.lock().await.insert(link_id, link);
+
+        Some(link_id)
+    });
+
+    match link_id {
+        Some(link_id) => {
+            write_address(out_link_id, &link_id);
+            RNS_OK
+        }
+        None => RNS_ERR_NOT_FOUND,
+    }
+}
+
+/// Sends `data` over a link, waiting while the link window is full.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`], `link_id` must point to 16 bytes
+/// and `data` to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_link_send(
+    node: *mut RnsNode,
+    link_id: *const u8,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let (Some(node), Some(link_id), Some(data)) = (node.as_ref(), address(link_id), bytes(data, len))
+    else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    node.runtime.block_on(async {
+        let Some(link) = node.find_link(&link_id).await else {
+            return RNS_ERR_NOT_FOUND;
+        };
+
+        match node.transport.send_to_link(&link, data).await {
+            Ok(_) => RNS_OK,
+            Err(_) => RNS_ERR_LINK,
+        }
+    })
+}
+
+/// Closes a link.
+///
+/// # Safety
+///
+/// `node` must come from [`rns_node_new`] and `link_id` must point to 16
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rns_link_close(node: *mut RnsNode, link_id: *const u8) -> c_int {
+    let (Some(node), Some(link_id)) = (node.as_ref(), address(link_id)) else {
+        return RNS_ERR_ARGUMENT;
+    };
+
+    node.runtime.block_on(async {
+        if node.find_link(&link_id).await.is_none() {
+            return RNS_ERR_NOT_FOUND;
+        }
+
+        node.out_links.lock().await.remove(&link_id);
+
+        match node.transport.link_close(link_id).await {
+            Ok(()) => RNS_OK,
+            Err(_) => RNS_ERR_LINK,
+        }
+    })
+}
+
+impl RnsNode {
+    async fn find_link(&self, link_id: &LinkId) -> Option<Arc<Mutex<Link>>> {
+        if let Some(link) = self.out_links.lock().await.get(link_id) {
+            return Some(link.clone());
+        }
+
+        self.transport.find_in_link(link_id).await
+    }
+}
+
+async fn deliver_events(
+    callbacks: RnsCallbacks,
+    mut events: tokio::sync::broadcast::Receiver<TransportEvent>,
+    mut in_link_events: tokio::sync::broadcast::Receiver<LinkEventData>,
+    mut out_link_events: tokio::sync::broadcast::Receiver<LinkEventData>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let link_event = tokio::select! {
+            _ = cancel.cancelled() => break,
+            event = events.recv() => {
+                match event {
+                    Ok(TransportEvent::AnnounceReceived(announce)) => {
+                        let destination = announce.destination.lock().await.desc.address_hash;
+                        let app_data = announce.app_data.as_slice();
+                        if let Some(on_announce) = callbacks.on_announce {
+                            unsafe {
+                                on_announce(
+                                    callbacks.user_data,
+                                    destination.as_slice().as_ptr(),
+                                    app_data.as_ptr(),
+                                    app_data.len(),
+                                )
+                            };
+                        }
+                        continue;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            event = in_link_events.recv() => event,
+            event = out_link_events.recv() => event,
+        };
+
+        let event = match link_event {
+            Ok(event) => event,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+
+        let link_id = event.id.as_slice().as_ptr();
+        let destination = event.address_hash.as_slice().as_ptr();
+
+        unsafe {
+            match event.event {
+                LinkEvent::Activated => {
+                    if let Some(on_link_activated) = callbacks.on_link_activated {
+                        on_link_activated(callbacks.user_data, link_id, destination);
+                    }
+                }
+                LinkEvent::Closed => {
+                    if let Some(on_link_closed) = callbacks.on_link_closed {
+                        on_link_closed(callbacks.user_data, link_id, destination);
+                    }
+                }
+                LinkEvent::Data(payload) => {
+                    if let Some(on_link_data) = callbacks.on_link_data {
+                        let data = payload.as_slice();
+                        on_link_data(callbacks.user_data, link_id, data.as_ptr(), data.len());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Announce([u8; ADDRESS_HASH_SIZE]),
+        LinkActivated([u8; ADDRESS_HASH_SIZE]),
+        LinkData(Vec<u8>),
+    }
+
+    unsafe fn hash(ptr: *const u8) -> [u8; ADDRESS_HASH_SIZE] {
+        core::slice::from_raw_parts(ptr, ADDRESS_HASH_SIZE).try_into().unwrap()
+    }
+
+    unsafe extern "C" fn on_announce(
+        user_data: *mut c_void,
+        destination: *const u8,
+        _: *const u8,
+        _: usize,
+    ) {
+        let events = &*(user_data as *const mpsc::Sender<Event>);
+        let _ = events.send(Event::Announce(hash(destination)));
+    }
+
+    unsafe extern "C" fn on_link_activated(user_data: *mut c_void, link_id: *const u8, _: *const u8) {
+        let events = &*(user_data as *const mpsc::Sender<Event>);
+        let _ = events.send(Event::LinkActivated(hash(link_id)));
+    }
+
+    unsafe extern "C" fn on_link_data(
+        user_data: *mut c_void,
+        _: *const u8,
+        data: *const u8,
+        len: usize,
+    ) {
+        let events = &*(user_data as *const mpsc::Sender<Event>);
+        let _ = events.send(Event::LinkData(core::slice::from_raw_parts(data, len).to_vec()));
+    }
+
+    fn callbacks(events: &mpsc::Sender<Event>) -> RnsCallbacks {
+        RnsCallbacks {
+            user_data: events as *const _ as *mut c_void,
+            on_announce: Some(on_announce),
+            on_link_activated: Some(on_link_activated),
+            on_link_closed: None,
+            on_link_data: Some(on_link_data),
+        }
+    }
+
+    /// Waits for the first event matching `predicate`.
+    fn wait(events: &mpsc::Receiver<Event>, predicate: impl Fn(&Event) -> bool) -> Event {
+        loop {
+            let event = events.recv_timeout(Duration::from_secs(5)).expect("event");
+            if predicate(&event) {
+                return event;
+            }
+        }
+    }
+
+    #[test]
+    fn link_between_nodes() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let addr = CString::new(addr).unwrap();
+
+        unsafe {
+            let identity = rns_identity_new();
+            let server = rns_node_new(c"server".as_ptr(), identity);
+            let client = rns_node_new(c"client".as_ptr(), core::ptr::null());
+            assert!(!server.is_null() && !client.is_null());
+            assert!(rns_identity_from_hex(c"not hex".as_ptr()).is_null());
+
+            let (server_tx, server_events) = mpsc::channel();
+            let (client_tx, client_events) = mpsc::channel();
+            assert_eq!(rns_node_set_callbacks(server, callbacks(&server_tx)), RNS_OK);
+            assert_eq!(rns_node_set_callbacks(client, callbacks(&client_tx)), RNS_OK);
+
+            assert_eq!(rns_node_add_tcp_server(server, addr.as_ptr()), RNS_OK);
+            std::thread::sleep(Duration::from_millis(200));
+            assert_eq!(rns_node_add_tcp_client(client, addr.as_ptr()), RNS_OK);
+            std::thread::sleep(Duration::from_millis(500));
+
+            let handle = rns_destination_new(server, identity, c"app".as_ptr(), c"ffi".as_ptr());
+            let mut destination = [0u8; ADDRESS_HASH_SIZE];
+            assert_eq!(rns_destination_hash(handle, destination.as_mut_ptr()), RNS_OK);
+            assert_eq!(rns_destination_announce(server, handle, core::ptr::null(), 0), RNS_OK);
+            wait(&client_events, |event| *event == Event::Announce(destination));
+
+            let mut link_id = [0u8; ADDRESS_HASH_SIZE];
+            assert_eq!(rns_link_open(client, destination.as_ptr(), link_id.as_mut_ptr()), RNS_OK);
+            wait(&client_events, |event| *event == Event::LinkActivated(link_id));
+
+            let data = b"hello from ffi";
+            assert_eq!(rns_link_send(client, link_id.as_ptr(), data.as_ptr(), data.len()), RNS_OK);
+            wait(&server_events, |event| *event == Event::LinkData(data.to_vec()));
+
+            let unknown = [0x55u8; ADDRESS_HASH_SIZE];
+            assert_eq!(rns_link_send(client, unknown.as_ptr(), data.as_ptr(), data.len()), RNS_ERR_NOT_FOUND);
+            assert_eq!(rns_link_close(client, link_id.as_ptr()), RNS_OK);
+
+            rns_destination_free(handle);
+            rns_identity_free(identity);
+            rns_node_free(client);
+            rns_node_free(server);
+        }
+    }
+
+    #[test]
+    fn frames_of_app_interface() {
+        unsafe {
+            let node = rns_node_new(c"firmware".as_ptr(), core::ptr::null());
+            let iface = rns_interface_new(node);
+
+            let destination = rns_destination_new(node, core::ptr::null(), c"app".as_ptr(), c"frames".as_ptr());
+            assert_eq!(rns_destination_announce(node, destination, b"data".as_ptr(), 4), RNS_OK);
+
+            // The announce goes out over the interface ...
+            let mut frame = [0u8; RNS_FRAME_MAX_SIZE];
+            let mut len = 0;
+            for _ in 0..50 {
+                len = rns_interface_frame_out(iface, frame.as_mut_ptr(), frame.len());
+                if len != 0 {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            assert!(len > 0);
+
+            let packet = Packet::from_wire(&frame[..len as usize]).unwrap();
+            assert_eq!(packet.destination, (*destination).address);
+
+            // ... and frames from the app reach the transport
+            assert_eq!(rns_interface_frame_in(iface, frame.as_ptr(), 3), RNS_ERR_FRAME);
+            assert_eq!(rns_interface_frame_in(iface, frame.as_ptr(), len as usize), RNS_OK);
+
+            rns_interface_free(iface);
+            rns_destination_free(destination);
+            rns_node_free(node);
+        }
+    }
+}
@@ -0,0 +1,539 @@
+//! Sharing files over a [`Channel`].
+//!
+//! A [`FileServer`] serves the files below a directory to the peers of
+//! channels, which [`fetch`] them by their path relative to that directory.
+//! A peer sends a [`FileMessage::Request`] and the server answers with a
+//! [`FileMessage::Offer`] carrying the size of the file, followed by its
+//! chunks, or with [`FileMessage::NotFound`].
+//!
+//! Chunks are up to [`CHUNK_SIZE`] bytes and zlib compressed when that makes
+//! them smaller. The channel splits them into fragments and confirms their
+//! delivery, so the server reports the progress of every file through a
+//! [`TransferHandle`], which can also pause or cancel it.
+//!
+//! This stands in for the resources of the reference implementation, which
+//! aren't implemented yet: only peers using these helpers understand each
+//! other, Python programs can't fetch files this way.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use miniz_oxide::deflate::compress_to_vec_zlib;
+use miniz_oxide::inflate::decompress_to_vec_zlib_with_limit;
+use tokio::fs::{self, File};
+use tokio::io::AsyncReadExt;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Notify;
+
+use crate::channel::{Channel, ChannelError, Message, MAX_MESSAGE_SIZE};
+use crate::destination::link::LinkStatus;
+use crate::error::RnsError;
+use crate::runtime;
+use crate::transfer::{
+    Transfer, TransferCommand, TransferHandle, TransferProgress, TransferState,
+};
+
+/// Largest chunk of a file, so a chunk message still fits into the channel
+/// next to its compression flag.
+pub const CHUNK_SIZE: usize = MAX_MESSAGE_SIZE - 1;
+
+const REQUEST_MESSAGE_TYPE: u16 = 0xfe01;
+const OFFER_MESSAGE_TYPE: u16 = 0xfe02;
+const NOT_FOUND_MESSAGE_TYPE: u16 = 0xfe03;
+const CHUNK_MESSAGE_TYPE: u16 = 0xfe04;
+const CANCELLED_MESSAGE_TYPE: u16 = 0xfe05;
+
+const CHUNK_COMPRESSED: u8 = 0x01;
+
+/// Compression level of chunks, a trade between their size and the time
+/// it takes to compress them.
+const LEVEL: u8 = 6;
+
+/// Interval at which waiting for messages checks whether the link closed.
+const LINK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delay before sending again while the channel isn't ready.
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Messages of a file sharing channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileMessage {
+    /// Asks for the file at a path.
+    Request(String),
+    /// The file at a path follows in chunks of this many bytes in total.
+    Offer { path: String, size: u64 },
+    /// There is no file at a path.
+    NotFound(String),
+    /// The next part of the offered file.
+    Chunk { compressed: bool, data: Vec<u8> },
+    /// The server cancelled the offered file.
+    Cancelled,
+}
+
+impl Message for FileMessage {
+    fn unpack(packed: &[u8], message_type: u16) -> Result<Self, RnsError> {
+        let text = |data: &[u8]| {
+            String::from_utf8(data.to_vec()).map_err(|_| RnsError::InvalidArgument)
+        };
+
+        match message_type {
+            REQUEST_MESSAGE_TYPE => Ok(FileMessage::Request(text(packed)?)),
+            OFFER_MESSAGE_TYPE => {
+                if packed.len() < 8 {
+                    return Err(RnsError::InvalidArgument);
+                }
+
+                let (size, path) = packed.split_at(8);
+                Ok(FileMessage::Offer {
+                    path: text(path)?,
+                    size: u64::from_be_bytes(size.try_into().unwrap()),
+                })
+            }
+            NOT_FOUND_MESSAGE_TYPE => Ok(FileMessage::NotFound(text(packed)?)),
+            CHUNK_MESSAGE_TYPE => match packed.split_first() {
+                Some((flags, data)) => Ok(FileMessage::Chunk {
+                    compressed: flags & CHUNK_COMPRESSED != 0,
+                    data: data.to_vec(),
+                }),
+                None => Err(RnsError::InvalidArgument),
+            },
+            CANCELLED_MESSAGE_TYPE => Ok(FileMessage::Cancelled),
+            _ => Err(RnsError::ChannelUnknownMessageType),
+        }
+    }
+
+    fn pack(&self) -> Vec<u8> {
+        match self {
+            FileMessage::Request(path) | FileMessage::NotFound(path) => path.as_bytes().to_vec(),
+            FileMessage::Offer { path, size } => {
+                let mut packed = size.to_be_bytes().to_vec();
+                packed.extend_from_slice(path.as_bytes());
+                packed
+            }
+            FileMessage::Chunk { compressed, data } => {
+                let mut packed = Vec::with_capacity(data.len() + 1);
+                packed.push(if *compressed { CHUNK_COMPRESSED } else { 0 });
+                packed.extend_from_slice(data);
+                packed
+            }
+            FileMessage::Cancelled => Vec::new(),
+        }
+    }
+
+    fn message_type(&self) -> u16 {
+        match self {
+            FileMessage::Request(_) => REQUEST_MESSAGE_TYPE,
+            FileMessage::Offer { .. } => OFFER_MESSAGE_TYPE,
+            FileMessage::NotFound(_) => NOT_FOUND_MESSAGE_TYPE,
+            FileMessage::Chunk { .. } => CHUNK_MESSAGE_TYPE,
+            FileMessage::Cancelled => CANCELLED_MESSAGE_TYPE,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FileShareError {
+    Channel(ChannelError),
+    /// The link of the channel was closed.
+    Closed,
+    NotFound { path: String },
+    /// The server cancelled the file.
+    Cancelled { path: String },
+    /// The peer sent a message which doesn't fit the transfer.
+    Malformed { path: String },
+    /// Messages were dropped as they weren't read in time.
+    Lagged { skipped: u64 },
+    Io(io::Error),
+}
+
+impl fmt::Display for FileShareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileShareError::Channel(error) => write!(f, "fileshare channel error: {}", error),
+            FileShareError::Closed => f.write_str("fileshare link is closed"),
+            FileShareError::NotFound { path } => write!(f, "file {} not found", path),
+            FileShareError::Cancelled { path } => write!(f, "file {} was cancelled", path),
+            FileShareError::Malformed { path } => write!(f, "file {} arrived malformed", path),
+            FileShareError::Lagged { skipped } => {
+                write!(f, "fileshare skipped {} messages", skipped)
+            }
+            FileShareError::Io(error) => write!(f, "fileshare io error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for FileShareError {}
+
+impl From<ChannelError> for FileShareError {
+    fn from(error: ChannelError) -> Self {
+        match error {
+            ChannelError::Closed { .. } | ChannelError::TransportDropped { .. } => {
+                FileShareError::Closed
+            }
+            error => FileShareError::Channel(error),
+        }
+    }
+}
+
+impl From<io::Error> for FileShareError {
+    fn from(error: io::Error) -> Self {
+        FileShareError::Io(error)
+    }
+}
+
+/// A file the server started to send.
+#[derive(Clone)]
+pub struct ServedFile {
+    pub path: String,
+    pub transfer: TransferHandle,
+}
+
+/// Serves the files below a directory.
+pub struct FileServer {
+    root: PathBuf,
+    served: broadcast::Sender<ServedFile>,
+}
+
+impl FileServer {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            served: broadcast::Sender::new(16),
+        }
+    }
+
+    /// Receiver of the files as the server starts to send them.
+    pub fn subscribe(&self) -> broadcast::Receiver<ServedFile> {
+        self.served.subscribe()
+    }
+
+    /// Answers the requests received over `channel` one after another,
+    /// until its link closes.
+    pub async fn serve(
+        &self,
+        channel: &Channel<FileMessage>,
+        incoming: &mut broadcast::Receiver<FileMessage>,
+    ) -> Result<(), FileShareError> {
+        loop {
+            let path = match next_message(channel, incoming).await {
+                Ok(FileMessage::Request(path)) => path,
+                Ok(message) => {
+                    log::debug!("fileshare: ignoring {:?} while waiting for requests", message);
+                    continue;
+                }
+                Err(FileShareError::Lagged { skipped }) => {
+                    log::warn!("fileshare: missed {} requests", skipped);
+                    continue;
+                }
+                Err(FileShareError::Closed) => return Ok(()),
+                Err(err) => return Err(err),
+            };
+
+            self.send_file(channel, path).await?;
+        }
+    }
+
+    async fn send_file(
+        &self,
+        channel: &Channel<FileMessage>,
+        path: String,
+    ) -> Result<(), FileShareError> {
+        let (mut file, size) = match self.open(&path).await {
+            Ok(opened) => opened,
+            Err(err) => {
+                log::info!("fileshare: can't serve {}: {}", path, err);
+                send(channel, &FileMessage::NotFound(path)).await?;
+                return Ok(());
+            }
+        };
+
+        log::info!("fileshare: sending {} ({} bytes)", path, size);
+
+        let wake = Arc::new(Notify::new());
+        let (transfer, handle) = Transfer::new(size as usize, (size as usize).div_ceil(CHUNK_SIZE), wake.clone());
+        let _ = self.served.send(ServedFile { path: path.clone(), transfer: handle });
+
+        let offer = FileMessage::Offer { path: path.clone(), size };
+        if let Err(err) = send(channel, &offer).await {
+            transfer.set_state(TransferState::Failed);
+            return Err(err);
+        }
+
+        // Only one chunk at a time is held in memory
+        let mut remaining = size;
+        while remaining > 0 {
+            // Pausing and cancelling take effect between chunks
+            loop {
+                let notified = wake.notified();
+                match transfer.command() {
+                    TransferCommand::Run => break,
+                    TransferCommand::Pause => {
+                        transfer.set_state(TransferState::Paused);
+                        notified.await;
+                    }
+                    TransferCommand::Cancel => {
+                        log::info!("fileshare: cancelled {}", path);
+                        transfer.set_state(TransferState::Cancelled);
+                        return send(channel, &FileMessage::Cancelled).await.map(|_| ());
+                    }
+                }
+            }
+            transfer.set_state(TransferState::Active);
+
+            let mut chunk = vec![0u8; remaining.min(CHUNK_SIZE as u64) as usize];
+            if let Err(err) = file.read_exact(&mut chunk).await {
+                // E.g. the file was truncated, the peer mustn't wait for the rest
+                log::warn!("fileshare: can't read {}: {}", path, err);
+                transfer.set_state(TransferState::Failed);
+                return send(channel, &FileMessage::Cancelled).await.map(|_| ());
+            }
+            remaining -= chunk.len() as u64;
+            let len = chunk.len();
+
+            let message = runtime::run_blocking(move || compress(chunk))
+                .await
+                .ok_or(FileShareError::Malformed { path: path.clone() })?;
+
+            let delivered = match send(channel, &message).await {
+                Ok(sent) => sent.finished().await,
+                Err(err) => {
+                    transfer.set_state(TransferState::Failed);
+                    return Err(err);
+                }
+            };
+
+            if delivered != TransferState::Complete {
+                transfer.set_state(TransferState::Failed);
+                return Err(FileShareError::Closed);
+            }
+
+            transfer.part_done(len);
+        }
+
+        // Empty files have no chunks to complete them
+        transfer.set_state(TransferState::Complete);
+
+        Ok(())
+    }
+
+    /// Opens the file at `path` below the root and returns its size. Paths
+    /// leaving the root are refused, through symbolic links as well.
+    async fn open(&self, path: &str) -> io::Result<(File, u64)> {
+        let relative = Path::new(path.trim_start_matches('/'));
+        let inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !inside {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "path leaves the root"));
+        }
+
+        let root = fs::canonicalize(&self.root).await?;
+        let target = fs::canonicalize(root.join(relative)).await?;
+        if !target.starts_with(&root) {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "path leaves the root"));
+        }
+
+        let file = File::open(&target).await?;
+        let metadata = file.metadata().await?;
+        if !metadata.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file"));
+        }
+
+        Ok((file, metadata.len()))
+    }
+}
+
+/// Fetches the file at `path` from the [`FileServer`] at the other end of
+/// `channel`. `progress` is called whenever a chunk arrived.
+pub async fn fetch(
+    channel: &Channel<FileMessage>,
+    incoming: &mut broadcast::Receiver<FileMessage>,
+    path: &str,
+    mut progress: impl FnMut(&TransferProgress),
+) -> Result<Vec<u8>, FileShareError> {
+    send(channel, &FileMessage::Request(path.into())).await?;
+
+    let size = loop {
+        match next_message(channel, incoming).await? {
+            FileMessage::Offer { path: offered, size } if offered == path => break size as usize,
+            FileMessage::NotFound(missing) if missing == path => {
+                return Err(FileShareError::NotFound { path: path.into() });
+            }
+            message => log::debug!("fileshare: ignoring {:?} while waiting for {}", message, path),
+        }
+    };
+
+    let malformed = || FileShareError::Malformed { path: path.into() };
+
+    let mut state = TransferProgress {
+        state: TransferState::Active,
+        total_bytes: size,
+        done_bytes: 0,
+        total_parts: size.div_ceil(CHUNK_SIZE),
+        done_parts: 0,
+    };
+    progress(&state);
+
+    // The size is the peer's word, so memory is taken as chunks arrive
+    let mut data = Vec::with_capacity(size.min(CHUNK_SIZE));
+    while data.len() < size {
+        match next_message(channel, incoming).await? {
+            FileMessage::Chunk { compressed, data: chunk } => {
+                let chunk = match compressed {
+                    true => decompress_to_vec_zlib_with_limit(&chunk, CHUNK_SIZE)
+                        .map_err(|_| malformed())?,
+                    false => chunk,
+                };
+                if chunk.is_empty() || data.len() + chunk.len() > size {
+                    return Err(malformed());
+                }
+
+                data.extend_from_slice(&chunk);
+                state.done_bytes = data.len();
+                state.done_parts += 1;
+            }
+            FileMessage::Cancelled => {
+                state.state = TransferState::Cancelled;
+                progress(&state);
+                return Err(FileShareError::Cancelled { path: path.into() });
+            }
+            _ => return Err(malformed()),
+        }
+
+        if data.len() < size {
+            progress(&state);
+        }
+    }
+
+    state.state = TransferState::Complete;
+    progress(&state);
+
+    Ok(data)
+}
+
+/// The chunk message of `chunk`, compressed if that makes it smaller.
+fn compress(chunk: Vec<u8>) -> FileMessage {
+    let compressed = compress_to_vec_zlib(&chunk, LEVEL);
+    if compressed.len() < chunk.len() {
+        FileMessage::Chunk { compressed: true, data: compressed }
+    } else {
+        FileMessage::Chunk { compressed: false, data: chunk }
+    }
+}
+
+/// Sends `message`, waiting while the channel isn't ready.
+async fn send(
+    channel: &Channel<FileMessage>,
+    message: &FileMessage,
+) -> Result<TransferHandle, FileShareError> {
+    loop {
+        match channel.send_transfer(message).await {
+            Ok(handle) => return Ok(handle),
+            Err(ChannelError::LinkNotReady { status: LinkStatus::Closed, .. }) => {
+                return Err(FileShareError::Closed);
+            }
+            Err(err) if err.is_transient() => runtime::sleep(RETRY_INTERVAL).await,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Waits for the next message of `channel`, failing once its link closed.
+async fn next_message(
+    channel: &Channel<FileMessage>,
+    incoming: &mut broadcast::Receiver<FileMessage>,
+) -> Result<FileMessage, FileShareError> {
+    loop {
+        tokio::select! {
+            message = incoming.recv() => {
+                return match message {
+                    Ok(message) => Ok(message),
+                    Err(RecvError::Lagged(skipped)) => Err(FileShareError::Lagged { skipped }),
+                    Err(RecvError::Closed) => Err(FileShareError::Closed),
+                };
+            }
+            _ = runtime::sleep(LINK_CHECK_INTERVAL) => {
+                if channel.link.lock().await.status() == LinkStatus::Closed {
+                    return Err(FileShareError::Closed);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            FileMessage::Request("docs/notes.txt".into()),
+            FileMessage::Offer { path: "docs/notes.txt".into(), size: 1 << 40 },
+            FileMessage::NotFound("missing".into()),
+            FileMessage::Chunk { compressed: true, data: vec![1, 2, 3] },
+            FileMessage::Chunk { compressed: false, data: vec![] },
+            FileMessage::Cancelled,
+        ];
+
+        for message in messages {
+            let unpacked = FileMessage::unpack(&message.pack(), message.message_type()).unwrap();
+            assert_eq!(unpacked, message);
+        }
+
+        assert!(FileMessage::unpack(&[0; 4], OFFER_MESSAGE_TYPE).is_err());
+        assert!(FileMessage::unpack(&[], CHUNK_MESSAGE_TYPE).is_err());
+        assert!(FileMessage::unpack(&[0xff], REQUEST_MESSAGE_TYPE).is_err());
+    }
+
+    #[test]
+    fn chunks_are_compressed_when_smaller() {
+        let text = b"all work and no play makes jack a dull boy. ".repeat(100);
+        match compress(text.clone()) {
+            FileMessage::Chunk { compressed: true, data } => {
+                assert!(data.len() < text.len());
+                assert_eq!(decompress_to_vec_zlib_with_limit(&data, CHUNK_SIZE).unwrap(), text);
+            }
+            message => panic!("expected a compressed chunk, got {:?}", message),
+        }
+
+        let random: Vec<u8> = (0..256u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        assert_eq!(compress(random.clone()), FileMessage::Chunk { compressed: false, data: random });
+    }
+
+    #[tokio::test]
+    async fn paths_stay_below_the_root() {
+        let dir = std::env::temp_dir().join(format!("fileshare-root-{}", std::process::id()));
+        let root = dir.join("root");
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs").join("notes.txt"), b"notes").unwrap();
+        std::fs::write(dir.join("secret"), b"secret").unwrap();
+
+        let server = FileServer::new(&root);
+
+        assert_eq!(server.open("docs/notes.txt").await.unwrap().1, 5);
+        assert_eq!(server.open("/docs/notes.txt").await.unwrap().1, 5);
+        for path in ["../secret", "docs/../../secret", "./docs/notes.txt", "docs", "missing"] {
+            assert!(server.open(path).await.is_err(), "{}", path);
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("secret"), root.join("link")).unwrap();
+            std::os::unix::fs::symlink(&dir, root.join("parent")).unwrap();
+            std::os::unix::fs::symlink(root.join("docs"), root.join("inside")).unwrap();
+
+            let err = server.open("link").await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert!(server.open("parent/secret").await.is_err());
+            assert_eq!(server.open("inside/notes.txt").await.unwrap().1, 5);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
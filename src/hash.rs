@@ -1,6 +1,7 @@
 use alloc::fmt::Write;
 use core::cmp;
 use core::fmt;
+use core::str::FromStr;
 
 use crypto_common::typenum::Unsigned;
 use crypto_common::OutputSizeUser;
@@ -91,18 +92,12 @@ impl AddressHash {
         Self::new_from_hash(&Hash::new_from_rand(rng))
     }
 
+    /// Reads the hash from the first 32 hex digits of `hex_string`, see
+    /// [`str::parse`] for one which takes nothing else.
     pub fn new_from_hex_string(hex_string: &str) -> Result<Self, RnsError> {
-        if hex_string.len() < ADDRESS_HASH_SIZE * 2 {
-            return Err(RnsError::IncorrectHash);
-        }
-
-        let mut bytes = [0u8; ADDRESS_HASH_SIZE];
-
-        for i in 0..ADDRESS_HASH_SIZE {
-            bytes[i] = u8::from_str_radix(&hex_string[i * 2..(i * 2) + 2], 16).unwrap();
-        }
+        let digits = hex_string.get(..ADDRESS_HASH_SIZE * 2).ok_or(RnsError::IncorrectHash)?;
 
-        Ok(Self(bytes))
+        Ok(Self(parse_hex(digits)?))
     }
 
     pub const fn new_empty() -> Self {
@@ -125,7 +120,8 @@ impl AddressHash {
         self.0.len() == 0
     }
 
-    pub fn to_hex_string(&self) -> String {
+    /// Lowercase hex digits without the brackets of [`fmt::Display`].
+    pub fn to_hex(&self) -> String {
         let mut hex_string = String::with_capacity(ADDRESS_HASH_SIZE * 2);
 
         for byte in self.0 {
@@ -134,6 +130,75 @@ impl AddressHash {
 
         hex_string
     }
+
+    /// Same as [`AddressHash::to_hex`].
+    pub fn to_hex_string(&self) -> String {
+        self.to_hex()
+    }
+}
+
+/// Why a string is not an [`AddressHash`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashParseError {
+    /// The string has this many characters instead of 32 hex digits.
+    Length(usize),
+    /// The character at this position is no hex digit.
+    InvalidDigit { position: usize, character: char },
+}
+
+impl fmt::Display for HashParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashParseError::Length(len) => {
+                write!(f, "hash has {} characters instead of {} hex digits", len, ADDRESS_HASH_SIZE * 2)
+            }
+            HashParseError::InvalidDigit { position, character } => {
+                write!(f, "{:?} at position {} is no hex digit", character, position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashParseError {}
+
+impl From<HashParseError> for RnsError {
+    fn from(_: HashParseError) -> Self {
+        RnsError::IncorrectHash
+    }
+}
+
+fn parse_hex(digits: &str) -> Result<[u8; ADDRESS_HASH_SIZE], HashParseError> {
+    let len = digits.chars().count();
+    if len != ADDRESS_HASH_SIZE * 2 {
+        return Err(HashParseError::Length(len));
+    }
+
+    let mut bytes = [0u8; ADDRESS_HASH_SIZE];
+    for (position, character) in digits.chars().enumerate() {
+        let digit = character
+            .to_digit(16)
+            .ok_or(HashParseError::InvalidDigit { position, character })?;
+        bytes[position / 2] = (bytes[position / 2] << 4) | digit as u8;
+    }
+
+    Ok(bytes)
+}
+
+/// Parses 32 hex digits, in either case and optionally in the brackets of
+/// [`fmt::Display`] as Python Reticulum prints them, e.g.
+/// `"<5b2ac5c3e6b8ef4bbc1a2f0e3e1f0d2c>"`.
+impl FromStr for AddressHash {
+    type Err = HashParseError;
+
+    fn from_str(hash: &str) -> Result<Self, Self::Err> {
+        let hash = hash.trim();
+        let digits = hash
+            .strip_prefix('<')
+            .and_then(|hash| hash.strip_suffix('>'))
+            .unwrap_or(hash);
+
+        parse_hex(digits).map(Self)
+    }
 }
 
 impl From<Hash> for AddressHash {
@@ -142,13 +207,14 @@ impl From<Hash> for AddressHash {
     }
 }
 
+/// Hex digits in angle brackets, like `RNS.prettyhexrep` in Python.
 impl fmt::Display for AddressHash {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "/")?;
+        write!(f, "<")?;
         for data in self.0.iter() {
             write!(f, "{:0>2x}", data)?;
         }
-        write!(f, "/")?;
+        write!(f, ">")?;
 
         Ok(())
     }
@@ -169,7 +235,7 @@ mod tests {
 
     use rand_core::OsRng;
 
-    use crate::hash::AddressHash;
+    use crate::hash::{AddressHash, HashParseError};
 
     #[test]
     fn address_hex_string() {
@@ -185,4 +251,28 @@ mod tests {
             original_address_hash.as_slice()
         );
     }
+
+    #[test]
+    fn address_display_round_trips() {
+        let address = AddressHash::new([0xab; 16]);
+
+        assert_eq!(address.to_string(), format!("<{}>", "ab".repeat(16)));
+        assert_eq!(address.to_hex(), "ab".repeat(16));
+        assert_eq!(address.to_string().parse::<AddressHash>(), Ok(address));
+        assert_eq!(" ABABABABABABABABABABABABABABABAB ".parse::<AddressHash>(), Ok(address));
+    }
+
+    #[test]
+    fn address_parse_errors() {
+        assert_eq!("abcd".parse::<AddressHash>(), Err(HashParseError::Length(4)));
+        assert_eq!(
+            format!("<{}>", "ab".repeat(17)).parse::<AddressHash>(),
+            Err(HashParseError::Length(34))
+        );
+        assert_eq!(
+            format!("{}g", "a".repeat(31)).parse::<AddressHash>(),
+            Err(HashParseError::InvalidDigit { position: 31, character: 'g' })
+        );
+        assert!(AddressHash::new_from_hex_string(&"é".repeat(16)).is_err());
+    }
 }
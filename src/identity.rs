@@ -1,4 +1,10 @@
+pub mod manager;
+
 use alloc::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
 use hkdf::Hkdf;
 use rand_core::CryptoRngCore;
 
@@ -13,6 +19,7 @@ use crate::{
 };
 
 pub const PUBLIC_KEY_LENGTH: usize = ed25519_dalek::PUBLIC_KEY_LENGTH;
+pub const PRIVATE_KEY_LENGTH: usize = PUBLIC_KEY_LENGTH * 2;
 
 #[cfg(feature = "fernet-aes128")]
 pub const DERIVED_KEY_LENGTH: usize = 256 / 8;
@@ -51,6 +58,12 @@ pub struct Identity {
     pub address_hash: AddressHash,
 }
 
+impl core::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Identity({})", self.address_hash)
+    }
+}
+
 impl Identity {
     pub fn new(public_key: PublicKey, verifying_key: VerifyingKey) -> Self {
         let hash = Hash::new(
@@ -290,6 +303,43 @@ impl PrivateIdentity {
         ))
     }
 
+    /// Creates an identity from the 64 byte private key format which Python
+    /// stores in identity files: the X25519 key followed by the Ed25519 seed.
+    pub fn new_from_private_key_bytes(bytes: &[u8]) -> Result<Self, RnsError> {
+        if bytes.len() != PRIVATE_KEY_LENGTH {
+            return Err(RnsError::InvalidArgument);
+        }
+
+        let mut private_key_bytes = [0u8; PUBLIC_KEY_LENGTH];
+        let mut sign_key_bytes = [0u8; PUBLIC_KEY_LENGTH];
+        private_key_bytes.copy_from_slice(&bytes[..PUBLIC_KEY_LENGTH]);
+        sign_key_bytes.copy_from_slice(&bytes[PUBLIC_KEY_LENGTH..]);
+
+        Ok(Self::new(
+            StaticSecret::from(private_key_bytes),
+            SigningKey::from_bytes(&sign_key_bytes),
+        ))
+    }
+
+    /// Private keys in the format of Python identity files.
+    pub fn to_private_key_bytes(&self) -> [u8; PRIVATE_KEY_LENGTH] {
+        let mut bytes = [0u8; PRIVATE_KEY_LENGTH];
+        bytes[..PUBLIC_KEY_LENGTH].copy_from_slice(self.private_key.as_bytes());
+        bytes[PUBLIC_KEY_LENGTH..].copy_from_slice(self.sign_key.as_bytes());
+        bytes
+    }
+
+    /// Reads a Python identity file, such as those written by `rnid -g`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new_from_private_key_bytes(&fs::read(path)?)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid identity private key"))
+    }
+
+    /// Writes the identity to a Python identity file.
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_private_key_bytes())
+    }
+
     pub fn sign_key(&self) -> &SigningKey {
         &self.sign_key
     }
@@ -0,0 +1,244 @@
+//! Named local identities.
+//!
+//! An [`IdentityManager`] keeps several [`PrivateIdentity`]s under names
+//! chosen by the application, e.g. one per persona, and optionally persists
+//! them in a directory. Every identity is stored in its own file using the
+//! Python identity file format, so the files can be used with `rnid -i` and
+//! Python applications as they are.
+//!
+//! Destinations are created for a stored identity the usual way:
+//!
+//! ```
+//! # use rand_core::OsRng;
+//! # use reticulum::destination::DestinationName;
+//! # use reticulum::identity::manager::IdentityManager;
+//! # use reticulum::transport::{Transport, TransportConfig};
+//! # #[tokio::main]
+//! # async fn main() {
+//! # let transport = Transport::new(TransportConfig::default());
+//! let mut identities = IdentityManager::new();
+//! identities.create("work", OsRng).unwrap();
+//!
+//! let identity = identities.get("work").unwrap().clone();
+//! let destination = transport
+//!     .add_destination(identity, DestinationName::new("lxmf", "delivery"))
+//!     .await;
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rand_core::CryptoRngCore;
+
+use super::PrivateIdentity;
+
+pub struct IdentityManager {
+    storage_path: Option<PathBuf>,
+    identities: BTreeMap<String, PrivateIdentity>,
+}
+
+impl IdentityManager {
+    /// Creates a manager which keeps identities in memory only.
+    pub fn new() -> Self {
+        Self {
+            storage_path: None,
+            identities: BTreeMap::new(),
+        }
+    }
+
+    /// Opens the identity directory at `path`, creating it if needed, and
+    /// loads all identities stored in it.
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+
+        let mut identities = BTreeMap::new();
+        for entry in fs::read_dir(&path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+
+            if !is_valid_name(&name) {
+                continue;
+            }
+
+            match PrivateIdentity::from_file(entry.path()) {
+                Ok(identity) => {
+                    identities.insert(name, identity);
+                }
+                Err(err) => {
+                    log::warn!("identity: couldn't load {}: {}", entry.path().display(), err);
+                }
+            }
+        }
+
+        Ok(Self {
+            storage_path: Some(path),
+            identities,
+        })
+    }
+
+    /// Generates a new identity and stores it as `name`.
+    pub fn create<R: CryptoRngCore>(&mut self, name: &str, rng: R) -> io::Result<&PrivateIdentity> {
+        if self.identities.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("identity {} already exists", name),
+            ));
+        }
+
+        self.insert(name, PrivateIdentity::new_from_rand(rng))?;
+
+        Ok(&self.identities[name])
+    }
+
+    /// Stores `identity` as `name`, replacing an identity with the same name.
+    pub fn insert(&mut self, name: &str, identity: PrivateIdentity) -> io::Result<()> {
+        if !is_valid_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid identity name {:?}", name),
+            ));
+        }
+
+        if let Some(path) = &self.storage_path {
+            identity.to_file(path.join(name))?;
+        }
+
+        self.identities.insert(name.into(), identity);
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PrivateIdentity> {
+        self.identities.get(name)
+    }
+
+    /// Removes the identity `name` and deletes its file.
+    pub fn remove(&mut self, name: &str) -> io::Result<Option<PrivateIdentity>> {
+        let identity = self.identities.remove(name);
+
+        if let (Some(path), Some(_)) = (&self.storage_path, &identity) {
+            fs::remove_file(path.join(name))?;
+        }
+
+        Ok(identity)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.identities.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PrivateIdentity)> {
+        self.identities
+            .iter()
+            .map(|(name, identity)| (name.as_str(), identity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.identities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.identities.is_empty()
+    }
+
+    /// Imports a Python identity file as `name`.
+    pub fn import_file<P: AsRef<Path>>(&mut self, name: &str, path: P) -> io::Result<()> {
+        let identity = PrivateIdentity::from_file(path)?;
+        self.insert(name, identity)
+    }
+
+    /// Writes the identity `name` to a Python identity file.
+    pub fn export_file<P: AsRef<Path>>(&self, name: &str, path: P) -> io::Result<()> {
+        self.get_or_not_found(name)?.to_file(path)
+    }
+
+    /// Imports a private key in hex, as printed by `rnid -x`.
+    pub fn import_hex(&mut self, name: &str, hex_string: &str) -> io::Result<()> {
+        let hex_string = hex_string.trim();
+        if hex_string.len() != super::PRIVATE_KEY_LENGTH * 2
+            || !hex_string.bytes().all(|byte| byte.is_ascii_hexdigit())
+        {
+            return Err(invalid_key());
+        }
+
+        let identity = PrivateIdentity::new_from_hex_string(hex_string).map_err(|_| invalid_key())?;
+        self.insert(name, identity)
+    }
+
+    /// Exports the private key of `name` in hex, as printed by `rnid -x`.
+    pub fn export_hex(&self, name: &str) -> io::Result<String> {
+        Ok(self.get_or_not_found(name)?.to_hex_string())
+    }
+
+    fn get_or_not_found(&self, name: &str) -> io::Result<&PrivateIdentity> {
+        self.get(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown identity {}", name))
+        })
+    }
+}
+
+impl Default for IdentityManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(|c: char| c == '/' || c == '\\' || c.is_control())
+}
+
+fn invalid_key() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid identity private key")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use super::*;
+
+    #[test]
+    fn persist_and_exchange_identities() {
+        let path = std::env::temp_dir().join(format!("rns-identities-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+
+        let mut identities = IdentityManager::open(&path).unwrap();
+        let alice = *identities.create("alice", OsRng).unwrap().address_hash();
+        identities.insert("bob", PrivateIdentity::new_from_name("bob")).unwrap();
+
+        assert!(identities.create("alice", OsRng).is_err());
+        assert!(identities.insert("../eve", PrivateIdentity::new_from_name("eve")).is_err());
+
+        // Files are raw 64 byte private keys like Python identity files
+        assert_eq!(fs::read(path.join("bob")).unwrap().len(), 64);
+
+        let hex = identities.export_hex("bob").unwrap();
+        identities.remove("bob").unwrap();
+
+        let mut identities = IdentityManager::open(&path).unwrap();
+        assert_eq!(identities.names().collect::<Vec<_>>(), ["alice"]);
+        assert_eq!(identities.get("alice").unwrap().address_hash(), &alice);
+
+        identities.import_hex("carol", &hex).unwrap();
+        assert_eq!(
+            identities.get("carol").unwrap().address_hash(),
+            PrivateIdentity::new_from_name("bob").address_hash()
+        );
+        assert!(identities.import_hex("dave", "00").is_err());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
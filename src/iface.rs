@@ -1,20 +1,53 @@
-pub mod hdlc;
+pub mod backoff;
+pub mod codec;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod framed_device;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod kaonic;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod local_client;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod local_server;
+pub mod propagation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod socket;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod tcp_client;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod tcp_server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod udp;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod stream;
+
+pub use codec::hdlc;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
+use tokio::sync::broadcast;
 use tokio::sync::mpsc;
-use tokio::task;
+use tokio::sync::watch;
 use tokio_util::sync::CancellationToken;
 
+use crate::error::RnsError;
 use crate::hash::AddressHash;
 use crate::hash::Hash;
 use crate::packet::Packet;
+use crate::packet::PacketType;
+use crate::runtime;
+use crate::runtime::Instant;
+
+use propagation::PropagationRule;
 
 pub type InterfaceTxSender = mpsc::Sender<TxMessage>;
 pub type InterfaceTxReceiver = mpsc::Receiver<TxMessage>;
@@ -40,8 +73,165 @@ pub struct RxMessage {
     pub packet: Packet,       // Received packet
 }
 
+/// Role of an interface in the network, as in Python Reticulum.
+///
+/// The mode decides whether announces are rebroadcast on an interface and
+/// how path requests received on it are answered.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InterfaceMode {
+    #[default]
+    Full,
+    PointToPoint,
+    /// Serves end devices, announces are never broadcast on it.
+    AccessPoint,
+    /// Connects devices which move between network segments.
+    Roaming,
+    /// Connects to a different network segment.
+    Boundary,
+    /// Serves clients which are allowed to discover paths through this node.
+    Gateway,
+}
+
+impl InterfaceMode {
+    /// Returns `true` if an announce may be broadcast on an interface in this
+    /// mode. `from` is the mode of the interface the announce was received
+    /// on, or `None` for announces of local destinations.
+    pub fn rebroadcasts_announce_from(&self, from: Option<InterfaceMode>) -> bool {
+        !matches!(
+            (self, from),
+            (InterfaceMode::AccessPoint, _)
+                | (InterfaceMode::Roaming, Some(InterfaceMode::Roaming | InterfaceMode::Boundary))
+                | (InterfaceMode::Boundary, Some(InterfaceMode::Roaming))
+        )
+    }
+
+    /// Returns `true` if path requests for unknown destinations received on
+    /// an interface in this mode are forwarded, which Python only does for
+    /// the modes in `Interface.DISCOVER_PATHS_FOR`.
+    pub fn discovers_paths(&self) -> bool {
+        matches!(
+            self,
+            InterfaceMode::AccessPoint | InterfaceMode::Gateway | InterfaceMode::Roaming
+        )
+    }
+}
+
+impl core::str::FromStr for InterfaceMode {
+    type Err = RnsError;
+
+    /// Parses the mode names and abbreviations accepted by Python Reticulum.
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_ascii_lowercase().as_str() {
+            "full" => Ok(InterfaceMode::Full),
+            "pointtopoint" | "point_to_point" | "ptp" => Ok(InterfaceMode::PointToPoint),
+            "accesspoint" | "access_point" | "ap" => Ok(InterfaceMode::AccessPoint),
+            "roaming" => Ok(InterfaceMode::Roaming),
+            "boundary" => Ok(InterfaceMode::Boundary),
+            "gateway" | "gw" => Ok(InterfaceMode::Gateway),
+            _ => Err(RnsError::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceEvent {
+    /// An interface was registered with the manager.
+    Up(AddressHash),
+    /// An interface stopped, its channel is closed.
+    Down(AddressHash),
+    /// The connection state of an interface changed.
+    State(AddressHash, InterfaceState),
+    /// An interface didn't take packets for longer than the tx stall
+    /// timeout, see [`InterfaceManager::set_tx_stall_timeout`]. Packets for
+    /// it are dropped until it takes them again.
+    Stalled(AddressHash),
+}
+
+/// Connection state of an interface which connects to a peer or binds a
+/// socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceState {
+    /// The connection is open or the socket bound.
+    Connected,
+    /// The connection was lost.
+    Disconnected,
+    /// The last attempt failed, the next one follows after `delay`.
+    Reconnecting { attempt: u32, delay: Duration },
+    /// The interface gave up and stopped.
+    Failed,
+}
+
+/// Reports state changes of one interface as [`InterfaceEvent::State`].
+#[derive(Clone)]
+pub struct InterfaceReporter {
+    address: AddressHash,
+    events_tx: broadcast::Sender<InterfaceEvent>,
+    state_tx: Arc<watch::Sender<Option<InterfaceState>>>,
+}
+
+impl InterfaceReporter {
+    pub fn report(&self, state: InterfaceState) {
+        self.state_tx.send_replace(Some(state));
+        let _ = self.events_tx.send(InterfaceEvent::State(self.address, state));
+    }
+}
+
+/// Handle of a spawned interface, see [`InterfaceManager::spawn`].
+#[derive(Clone)]
+pub struct InterfaceHandle {
+    address: AddressHash,
+    state: watch::Receiver<Option<InterfaceState>>,
+    cancel: CancellationToken,
+    stop: CancellationToken,
+}
+
+impl InterfaceHandle {
+    pub fn address(&self) -> AddressHash {
+        self.address
+    }
+
+    /// The last state the interface reported, `None` before its first
+    /// attempt to connect or bind finished.
+    pub fn status(&self) -> Option<InterfaceState> {
+        *self.state.borrow()
+    }
+
+    /// Whether the interface stopped, after giving up or a shutdown.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_cancelled()
+    }
+
+    /// Waits until the interface connected or bound its socket. Fails if it
+    /// gives up or stops first.
+    ///
+    /// Interfaces which never report their state, e.g. ones driving a
+    /// channel of their own, are never ready.
+    pub async fn await_ready(&self) -> Result<(), RnsError> {
+        let mut state = self.state.clone();
+
+        tokio::select! {
+            result = state.wait_for(|state| {
+                matches!(state, Some(InterfaceState::Connected | InterfaceState::Failed))
+            }) => match result.map(|state| *state) {
+                Ok(Some(InterfaceState::Connected)) => Ok(()),
+                _ => Err(RnsError::ConnectionError),
+            },
+            _ = self.stop.cancelled() => Err(RnsError::ConnectionError),
+        }
+    }
+
+    /// Stops the interface and closes its channel.
+    pub fn shutdown(&self) {
+        self.cancel.cancel();
+        self.stop.cancel();
+    }
+}
+
 pub struct InterfaceChannel {
     pub address: AddressHash,
+    pub mode: InterfaceMode,
     pub rx_channel: InterfaceRxSender,
     pub tx_channel: InterfaceTxReceiver,
     pub stop: CancellationToken,
@@ -64,6 +254,7 @@ impl InterfaceChannel {
     ) -> Self {
         Self {
             address,
+            mode: InterfaceMode::default(),
             rx_channel,
             tx_channel,
             stop,
@@ -83,16 +274,191 @@ pub trait Interface {
     fn mtu() -> usize;
 }
 
+/// Sent packets are remembered this long to recognize them if the interface
+/// delivers them back to us, e.g. UDP broadcasts looped back by the host or a
+/// mis-wired serial line.
+const ECHO_WINDOW: Duration = Duration::from_secs(5);
+const ECHO_HISTORY_SIZE: usize = 64;
+
+/// Received packets are remembered this long, broadcasts of the same packet
+/// skip the interfaces which delivered it to us. In densely meshed
+/// topologies every neighbour would otherwise get back what it just sent.
+const ORIGIN_WINDOW: Duration = Duration::from_secs(30);
+const ORIGIN_HISTORY_SIZE: usize = 256;
+
+/// How often the watchdog of an interface checks it.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time after which an interface whose tx queue stays full is flagged as
+/// stalled, see [`InterfaceManager::set_tx_stall_timeout`].
+pub const DEFAULT_TX_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Watches an interface until it stops. Interfaces whose task dropped its
+/// end of the tx queue are stopped, ones whose queue stays full for
+/// `stall_timeout` are flagged as stalled.
+async fn watchdog(
+    address: AddressHash,
+    stop: CancellationToken,
+    tx_send: mpsc::WeakSender<TxMessage>,
+    stalled: Arc<AtomicBool>,
+    stall_timeout: Duration,
+    events_tx: broadcast::Sender<InterfaceEvent>,
+) {
+    let mut full_since = None;
+
+    loop {
+        tokio::select! {
+            _ = stop.cancelled() => break,
+            _ = runtime::sleep(WATCHDOG_INTERVAL) => {}
+        }
+
+        // The manager dropped the interface, it is gone once stopped
+        let Some(tx_send) = tx_send.upgrade() else {
+            stop.cancelled().await;
+            break;
+        };
+
+        if tx_send.is_closed() {
+            log::warn!("iface: {} stopped without closing its channel", address);
+            stop.cancel();
+            break;
+        }
+
+        if tx_send.capacity() > 0 {
+            full_since = None;
+            if stalled.swap(false, Ordering::Relaxed) {
+                log::info!("iface: {} takes packets again", address);
+            }
+            continue;
+        }
+
+        let since = *full_since.get_or_insert_with(Instant::now);
+        if since.elapsed() >= stall_timeout && !stalled.swap(true, Ordering::Relaxed) {
+            log::warn!("iface: {} didn't take packets for {:?}", address, stall_timeout);
+            let _ = events_tx.send(InterfaceEvent::Stalled(address));
+        }
+    }
+
+    let _ = events_tx.send(InterfaceEvent::Down(address));
+}
+
+#[derive(Default)]
+struct TxHistory {
+    /// Hash and hop count of recently sent packets. Packets relayed back by
+    /// other nodes have more hops, only our own frames match exactly.
+    sent: VecDeque<(Hash, u8, Instant)>,
+    echoes: u64,
+}
+
 struct LocalInterface {
     address: AddressHash,
+    name: Option<String>,
+    mode: InterfaceMode,
+    groups: Vec<String>,
+    bitrate: Option<u64>,
     tx_send: InterfaceTxSender,
     stop: CancellationToken,
+    /// Set by the watchdog while the tx queue doesn't drain.
+    stalled: Arc<AtomicBool>,
+    tx_history: Mutex<TxHistory>,
+    /// Hashes of recently received packets.
+    rx_history: Mutex<VecDeque<(Hash, Instant)>>,
+    /// Whether it was created with a reporter for its state.
+    reports_state: bool,
+    /// Largest packet it sends, unknown for bare channels.
+    mtu: Option<usize>,
+}
+
+impl LocalInterface {
+    fn summary(&self) -> InterfaceSummary {
+        InterfaceSummary {
+            address: self.address,
+            name: self.name.clone(),
+            mode: self.mode,
+            bitrate: self.bitrate,
+            echoes: self.tx_history.lock().unwrap().echoes,
+            stalled: self.stalled.load(Ordering::Relaxed),
+        }
+    }
+
+    fn remember_received(&self, packet: &Packet) {
+        let mut history = self.rx_history.lock().unwrap();
+        if history.len() == ORIGIN_HISTORY_SIZE {
+            history.pop_front();
+        }
+        history.push_back((packet.hash(), Instant::now()));
+    }
+
+    fn has_delivered(&self, hash: &Hash) -> bool {
+        let mut history = self.rx_history.lock().unwrap();
+        let now = Instant::now();
+
+        while history
+            .front()
+            .is_some_and(|(_, received)| now.duration_since(*received) > ORIGIN_WINDOW)
+        {
+            history.pop_front();
+        }
+
+        history.iter().any(|(received, _)| received == hash)
+    }
+
+    fn remember_sent(&self, packet: &Packet) {
+        let mut history = self.tx_history.lock().unwrap();
+        if history.sent.len() == ECHO_HISTORY_SIZE {
+            history.sent.pop_front();
+        }
+        history.sent.push_back((packet.hash(), packet.header.hops, Instant::now()));
+    }
+
+    fn is_echo(&self, packet: &Packet) -> bool {
+        let mut history = self.tx_history.lock().unwrap();
+        let now = Instant::now();
+
+        while history
+            .sent
+            .front()
+            .is_some_and(|(_, _, sent)| now.duration_since(*sent) > ECHO_WINDOW)
+        {
+            history.sent.pop_front();
+        }
+
+        let hash = packet.hash();
+        let hops = packet.header.hops;
+        let Some(index) = history
+            .sent
+            .iter()
+            .position(|(sent_hash, sent_hops, _)| *sent_hash == hash && *sent_hops == hops)
+        else {
+            return false;
+        };
+
+        history.sent.remove(index);
+        history.echoes += 1;
+
+        true
+    }
+}
+
+/// Snapshot of an interface, see [`InterfaceManager::interfaces`].
+#[derive(Debug, Clone)]
+pub struct InterfaceSummary {
+    pub address: AddressHash,
+    pub name: Option<String>,
+    pub mode: InterfaceMode,
+    pub bitrate: Option<u64>,
+    /// Our own packets the interface delivered back.
+    pub echoes: u64,
+    /// Whether the interface stopped taking packets, see
+    /// [`InterfaceEvent::Stalled`].
+    pub stalled: bool,
 }
 
 pub struct InterfaceContext<T: Interface> {
     pub inner: Arc<Mutex<T>>,
     pub channel: InterfaceChannel,
     pub cancel: CancellationToken,
+    pub reporter: InterfaceReporter,
 }
 
 pub struct InterfaceManager {
@@ -101,12 +467,16 @@ pub struct InterfaceManager {
     rx_send: InterfaceRxSender,
     cancel: CancellationToken,
     ifaces: Vec<LocalInterface>,
+    rules: Vec<PropagationRule>,
+    events_tx: broadcast::Sender<InterfaceEvent>,
+    tx_stall_timeout: Duration,
 }
 
 impl InterfaceManager {
     pub fn new(rx_cap: usize) -> Self {
         let (rx_send, rx_recv) = InterfaceChannel::make_rx_channel(rx_cap);
         let rx_recv = Arc::new(tokio::sync::Mutex::new(rx_recv));
+        let (events_tx, _) = broadcast::channel(16);
 
         Self {
             counter: 0,
@@ -114,10 +484,17 @@ impl InterfaceManager {
             rx_send,
             cancel: CancellationToken::new(),
             ifaces: Vec::new(),
+            rules: Vec::new(),
+            events_tx,
+            tx_stall_timeout: DEFAULT_TX_STALL_TIMEOUT,
         }
     }
 
     pub fn new_channel(&mut self, tx_cap: usize) -> InterfaceChannel {
+        self.new_channel_with_mode(tx_cap, InterfaceMode::default())
+    }
+
+    pub fn new_channel_with_mode(&mut self, tx_cap: usize, mode: InterfaceMode) -> InterfaceChannel {
         self.counter += 1;
 
         let counter_bytes = self.counter.to_le_bytes();
@@ -128,56 +505,341 @@ impl InterfaceManager {
         log::debug!("iface: create channel {}", address);
 
         let stop = CancellationToken::new();
+        let stalled = Arc::new(AtomicBool::new(false));
+
+        runtime::spawn(watchdog(
+            address,
+            stop.clone(),
+            tx_send.downgrade(),
+            stalled.clone(),
+            self.tx_stall_timeout,
+            self.events_tx.clone(),
+        ));
 
         self.ifaces.push(LocalInterface {
             address,
+            name: None,
+            mode,
+            groups: Vec::new(),
+            bitrate: None,
             tx_send,
             stop: stop.clone(),
+            stalled,
+            tx_history: Mutex::new(TxHistory::default()),
+            rx_history: Mutex::new(VecDeque::new()),
+            reports_state: false,
+            mtu: None,
         });
 
+        let _ = self.events_tx.send(InterfaceEvent::Up(address));
+
         InterfaceChannel {
             rx_channel: self.rx_send.clone(),
             tx_channel: tx_recv,
             address,
+            mode,
             stop,
         }
     }
 
     pub fn new_context<T: Interface>(&mut self, inner: T) -> InterfaceContext<T> {
-        let channel = self.new_channel(1);
+        self.new_context_with_mode(inner, InterfaceMode::default())
+    }
+
+    pub fn new_context_with_mode<T: Interface>(
+        &mut self,
+        inner: T,
+        mode: InterfaceMode,
+    ) -> InterfaceContext<T> {
+        let channel = self.new_channel_with_mode(1, mode);
+        if let Some(iface) = self.ifaces.iter_mut().find(|iface| iface.address == channel.address) {
+            iface.reports_state = true;
+            iface.mtu = Some(T::mtu());
+        }
 
         let inner = Arc::new(Mutex::new(inner));
 
+        let (state_tx, _) = watch::channel(None);
+        let reporter = InterfaceReporter {
+            address: channel.address,
+            events_tx: self.events_tx.clone(),
+            state_tx: Arc::new(state_tx),
+        };
+
         InterfaceContext::<T> {
             inner: inner.clone(),
             channel,
-            cancel: self.cancel.clone(),
+            // Stops with the manager, or alone through its handle
+            cancel: self.cancel.child_token(),
+            reporter,
         }
     }
 
-    pub fn spawn<T: Interface, F, R>(&mut self, inner: T, worker: F) -> AddressHash
+    /// Spawns `worker` to drive the interface `inner`. The returned handle
+    /// tells when it is ready, e.g. once a TCP server is listening.
+    pub fn spawn<T: Interface, F, R>(&mut self, inner: T, worker: F) -> InterfaceHandle
+    where
+        F: FnOnce(InterfaceContext<T>) -> R,
+        R: std::future::Future<Output = ()> + Send + 'static,
+        R::Output: Send + 'static,
+    {
+        self.spawn_with_mode(inner, InterfaceMode::default(), worker)
+    }
+
+    pub fn spawn_with_mode<T: Interface, F, R>(
+        &mut self,
+        inner: T,
+        mode: InterfaceMode,
+        worker: F,
+    ) -> InterfaceHandle
+    where
+        F: FnOnce(InterfaceContext<T>) -> R,
+        R: std::future::Future<Output = ()> + Send + 'static,
+        R::Output: Send + 'static,
+    {
+        self.spawn_inner(None, inner, mode, worker)
+    }
+
+    /// Like [`InterfaceManager::spawn_with_mode`], and names the interface
+    /// for logs and summaries, see [`InterfaceManager::set_name`].
+    pub fn spawn_named<T: Interface, F, R>(
+        &mut self,
+        name: impl Into<String>,
+        inner: T,
+        mode: InterfaceMode,
+        worker: F,
+    ) -> InterfaceHandle
     where
         F: FnOnce(InterfaceContext<T>) -> R,
         R: std::future::Future<Output = ()> + Send + 'static,
         R::Output: Send + 'static,
     {
-        let context = self.new_context(inner);
-        let address = *context.channel.address();
+        self.spawn_inner(Some(name.into()), inner, mode, worker)
+    }
+
+    fn spawn_inner<T: Interface, F, R>(
+        &mut self,
+        name: Option<String>,
+        inner: T,
+        mode: InterfaceMode,
+        worker: F,
+    ) -> InterfaceHandle
+    where
+        F: FnOnce(InterfaceContext<T>) -> R,
+        R: std::future::Future<Output = ()> + Send + 'static,
+        R::Output: Send + 'static,
+    {
+        let context = self.new_context_with_mode(inner, mode);
+        if let Some(name) = name {
+            self.set_name(&context.channel.address, name);
+        }
+
+        let handle = InterfaceHandle {
+            address: context.channel.address,
+            state: context.reporter.state_tx.subscribe(),
+            cancel: context.cancel.clone(),
+            stop: context.channel.stop.clone(),
+        };
 
-        task::spawn(worker(context));
+        runtime::spawn(worker(context));
 
-        address
+        handle
     }
 
     pub fn receiver(&self) -> Arc<tokio::sync::Mutex<InterfaceRxReceiver>> {
         self.rx_recv.clone()
     }
 
-    pub fn cleanup(&mut self) {
-        self.ifaces.retain(|iface| !iface.stop.is_cancelled());
+    pub fn events(&self) -> broadcast::Receiver<InterfaceEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Returns the mode of a registered interface.
+    pub fn mode(&self, address: &AddressHash) -> Option<InterfaceMode> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .map(|iface| iface.mode)
+    }
+
+    /// Whether a registered interface reports its state as
+    /// [`InterfaceEvent::State`]. Those created with a context do, bare
+    /// channels are ready as soon as they are registered.
+    pub fn reports_state(&self, address: &AddressHash) -> bool {
+        self.ifaces
+            .iter()
+            .any(|iface| iface.address == *address && iface.reports_state)
+    }
+
+    /// Largest packet a registered interface sends. Interfaces created with
+    /// a context have the MTU of their type, bare channels none unless it is
+    /// set with [`InterfaceManager::set_mtu`].
+    pub fn mtu(&self, address: &AddressHash) -> Option<usize> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .and_then(|iface| iface.mtu)
+    }
+
+    pub fn set_mtu(&mut self, address: &AddressHash, mtu: usize) {
+        if let Some(iface) = self.ifaces.iter_mut().find(|iface| iface.address == *address) {
+            iface.mtu = Some(mtu);
+        }
+    }
+
+    /// Smallest MTU of the running interfaces a message of `tx_type` goes
+    /// to, `None` if none of them has one.
+    pub fn tx_mtu(&self, tx_type: &TxMessageType) -> Option<usize> {
+        self.ifaces
+            .iter()
+            .filter(|iface| !iface.stop.is_cancelled())
+            .filter(|iface| match tx_type {
+                TxMessageType::Broadcast(exclude) => *exclude != Some(iface.address),
+                TxMessageType::Direct(address) => iface.address == *address,
+            })
+            .filter_map(|iface| iface.mtu)
+            .min()
+    }
+
+    /// Names an interface. The name shows up in logs and in
+    /// [`InterfaceManager::interfaces`], e.g. the one from the daemon config.
+    pub fn set_name(&mut self, address: &AddressHash, name: impl Into<String>) {
+        if let Some(iface) = self.ifaces.iter_mut().find(|iface| iface.address == *address) {
+            let name = name.into();
+            log::debug!("iface: {} is '{}'", address, name);
+            iface.name = Some(name);
+        }
+    }
+
+    pub fn name(&self, address: &AddressHash) -> Option<String> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .and_then(|iface| iface.name.clone())
+    }
+
+    /// Name of an interface for log messages, its address if it has none.
+    pub fn display_name(&self, address: &AddressHash) -> String {
+        self.name(address).unwrap_or_else(|| address.to_string())
+    }
+
+    /// Time after which interfaces registered from now on are flagged as
+    /// stalled if their tx queue stays full, [`DEFAULT_TX_STALL_TIMEOUT`] by
+    /// default.
+    pub fn set_tx_stall_timeout(&mut self, timeout: Duration) {
+        self.tx_stall_timeout = timeout;
+    }
+
+    /// Sets the bitrate of an interface in bits per second. It is taken
+    /// into account when choosing between paths.
+    pub fn set_bitrate(&mut self, address: &AddressHash, bitrate: u64) {
+        if let Some(iface) = self.ifaces.iter_mut().find(|iface| iface.address == *address) {
+            iface.bitrate = Some(bitrate);
+        }
+    }
+
+    /// Tags an interface with groups which [`PropagationRule`]s refer to.
+    pub fn set_groups(&mut self, address: &AddressHash, groups: Vec<String>) {
+        if let Some(iface) = self.ifaces.iter_mut().find(|iface| iface.address == *address) {
+            iface.groups = groups;
+        }
+    }
+
+    pub fn groups(&self, address: &AddressHash) -> Vec<String> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .map(|iface| iface.groups.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn add_propagation_rule(&mut self, rule: PropagationRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn bitrate(&self, address: &AddressHash) -> Option<u64> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .and_then(|iface| iface.bitrate)
+    }
+
+    /// Returns whether `packet` received on the interface `address` is one
+    /// we sent on it ourselves a moment ago. Such echoes are counted, see
+    /// [`InterfaceManager::echoes`].
+    pub fn is_echo(&self, address: &AddressHash, packet: &Packet) -> bool {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .is_some_and(|iface| iface.is_echo(packet))
+    }
+
+    /// Notes that the interface `address` delivered `packet`, it won't get
+    /// the packet back with broadcasts for a while.
+    pub fn remember_received(&self, address: &AddressHash, packet: &Packet) {
+        if let Some(iface) = self.ifaces.iter().find(|iface| iface.address == *address) {
+            iface.remember_received(packet);
+        }
+    }
+
+    /// Number of our own packets the interface `address` delivered back.
+    pub fn echoes(&self, address: &AddressHash) -> Option<u64> {
+        self.ifaces
+            .iter()
+            .find(|iface| iface.address == *address)
+            .map(|iface| iface.tx_history.lock().unwrap().echoes)
+    }
+
+    /// Returns a summary of every interface which is still running.
+    pub fn interfaces(&self) -> Vec<InterfaceSummary> {
+        self.ifaces
+            .iter()
+            .filter(|iface| !iface.stop.is_cancelled())
+            .map(LocalInterface::summary)
+            .collect()
+    }
+
+    /// Removes the interfaces which stopped and returns them.
+    pub fn cleanup(&mut self) -> Vec<InterfaceSummary> {
+        let (stopped, running): (Vec<_>, Vec<_>) =
+            self.ifaces.drain(..).partition(|iface| iface.stop.is_cancelled());
+        self.ifaces = running;
+
+        stopped.iter().map(LocalInterface::summary).collect()
     }
 
     pub async fn send(&self, message: TxMessage) {
+        self.delivery(message).send().await;
+    }
+
+    /// Picks the interfaces `message` goes to. The returned [`Delivery`]
+    /// doesn't borrow the manager, so it can wait for room in the
+    /// interface queues after the manager lock is released.
+    pub(crate) fn delivery(&self, message: TxMessage) -> Delivery {
+        let is_announce = message.packet.header.packet_type == PacketType::Announce;
+
+        // Announces from interfaces which are already gone are treated as
+        // received on a full mode interface
+        let announce_from = match message.tx_type {
+            TxMessageType::Broadcast(Some(address)) if is_announce => {
+                Some(self.mode(&address).unwrap_or_default())
+            }
+            _ => None,
+        };
+
+        let hash = match message.tx_type {
+            TxMessageType::Broadcast(_) => Some(message.packet.hash()),
+            TxMessageType::Direct(_) => None,
+        };
+
+        let origin_groups = match message.tx_type {
+            TxMessageType::Broadcast(Some(address)) if !self.rules.is_empty() => {
+                self.groups(&address)
+            }
+            _ => Vec::new(),
+        };
+
+        let mut targets = Vec::new();
         for iface in &self.ifaces {
             let should_send = match message.tx_type {
                 TxMessageType::Broadcast(address) => {
@@ -186,13 +848,51 @@ impl InterfaceManager {
                         should_send = address != iface.address;
                     }
 
+                    if should_send && hash.is_some_and(|hash| iface.has_delivered(&hash)) {
+                        should_send = false;
+                    }
+
+                    if should_send && is_announce {
+                        should_send = iface.mode.rebroadcasts_announce_from(announce_from);
+                    }
+
+                    if should_send {
+                        should_send = !self
+                            .rules
+                            .iter()
+                            .any(|rule| rule.blocks(&origin_groups, &iface.groups, is_announce));
+                    }
+
                     should_send
                 },
                 TxMessageType::Direct(address) => address == iface.address,
             };
 
             if should_send && !iface.stop.is_cancelled() {
-                let _ = iface.tx_send.send(message).await;
+                iface.remember_sent(&message.packet);
+                targets.push((iface.tx_send.clone(), iface.stalled.clone()));
+            }
+        }
+
+        Delivery { message, targets }
+    }
+}
+
+/// A message and the interfaces it goes to, see
+/// [`InterfaceManager::delivery`].
+pub(crate) struct Delivery {
+    message: TxMessage,
+    targets: Vec<(InterfaceTxSender, Arc<AtomicBool>)>,
+}
+
+impl Delivery {
+    pub(crate) async fn send(self) {
+        for (tx_send, stalled) in self.targets {
+            // Waiting for a stalled interface would hold up all others
+            if stalled.load(Ordering::Relaxed) {
+                let _ = tx_send.try_send(self.message);
+            } else {
+                let _ = tx_send.send(self.message).await;
             }
         }
     }
@@ -203,3 +903,252 @@ impl Drop for InterfaceManager {
         self.cancel.cancel();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use crate::destination::{DestinationName, SingleInputDestination};
+    use crate::identity::PrivateIdentity;
+    use crate::packet::PacketDataBuffer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn announces_respect_interface_modes() {
+        let mut manager = InterfaceManager::new(1);
+
+        let mut full = manager.new_channel_with_mode(4, InterfaceMode::Full);
+        let mut access_point = manager.new_channel_with_mode(4, InterfaceMode::AccessPoint);
+        let mut roaming = manager.new_channel_with_mode(4, InterfaceMode::Roaming);
+        let mut boundary = manager.new_channel_with_mode(4, InterfaceMode::Boundary);
+
+        let announce = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "modes"),
+        )
+        .announce(OsRng, None)
+        .unwrap();
+
+        // Local announces skip access points only
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Broadcast(None), packet: announce })
+            .await;
+
+        assert!(full.tx_channel.try_recv().is_ok());
+        assert!(access_point.tx_channel.try_recv().is_err());
+        assert!(roaming.tx_channel.try_recv().is_ok());
+        assert!(boundary.tx_channel.try_recv().is_ok());
+
+        // Announces from roaming segments stay there
+        manager
+            .send(TxMessage {
+                tx_type: TxMessageType::Broadcast(Some(roaming.address)),
+                packet: announce,
+            })
+            .await;
+
+        assert!(full.tx_channel.try_recv().is_ok());
+        assert!(access_point.tx_channel.try_recv().is_err());
+        assert!(roaming.tx_channel.try_recv().is_err());
+        assert!(boundary.tx_channel.try_recv().is_err());
+
+        // Path responses are sent directly regardless of the mode
+        manager
+            .send(TxMessage {
+                tx_type: TxMessageType::Direct(access_point.address),
+                packet: announce,
+            })
+            .await;
+
+        assert!(access_point.tx_channel.try_recv().is_ok());
+    }
+
+    #[test]
+    fn parse_python_mode_names() {
+        assert_eq!("gw".parse::<InterfaceMode>(), Ok(InterfaceMode::Gateway));
+        assert_eq!("access_point".parse::<InterfaceMode>(), Ok(InterfaceMode::AccessPoint));
+        assert_eq!("Boundary".parse::<InterfaceMode>(), Ok(InterfaceMode::Boundary));
+        assert!("mesh".parse::<InterfaceMode>().is_err());
+    }
+
+    #[test]
+    fn discover_paths_like_python() {
+        for mode in [InterfaceMode::AccessPoint, InterfaceMode::Gateway, InterfaceMode::Roaming] {
+            assert!(mode.discovers_paths(), "{:?}", mode);
+        }
+        for mode in [InterfaceMode::Full, InterfaceMode::PointToPoint, InterfaceMode::Boundary] {
+            assert!(!mode.discovers_paths(), "{:?}", mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn detect_echoed_packets() {
+        let mut manager = InterfaceManager::new(1);
+        let mut iface = manager.new_channel(4);
+        let other = manager.new_channel(4);
+
+        let packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"echo"),
+            ..Default::default()
+        };
+
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Direct(iface.address), packet })
+            .await;
+        assert!(iface.tx_channel.try_recv().is_ok());
+
+        // Relayed back by a neighbour or received elsewhere
+        let mut relayed = packet;
+        relayed.header.hops += 1;
+        assert!(!manager.is_echo(&iface.address, &relayed));
+        assert!(!manager.is_echo(&other.address, &packet));
+
+        assert!(manager.is_echo(&iface.address, &packet));
+        assert_eq!(manager.echoes(&iface.address), Some(1));
+        assert_eq!(manager.echoes(&other.address), Some(0));
+    }
+
+    #[tokio::test]
+    async fn broadcasts_skip_origin_interfaces() {
+        let mut manager = InterfaceManager::new(1);
+        let mut first = manager.new_channel(4);
+        let mut second = manager.new_channel(4);
+        let mut third = manager.new_channel(4);
+
+        let packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"mesh"),
+            ..Default::default()
+        };
+
+        // The packet arrived on the first interface and shortly after on
+        // the second one with more hops
+        let mut relayed = packet;
+        relayed.header.hops += 1;
+        manager.remember_received(&first.address, &packet);
+        manager.remember_received(&second.address, &relayed);
+
+        let mut forwarded = packet;
+        forwarded.header.hops += 1;
+        manager
+            .send(TxMessage {
+                tx_type: TxMessageType::Broadcast(Some(first.address)),
+                packet: forwarded,
+            })
+            .await;
+
+        assert!(first.tx_channel.try_recv().is_err());
+        assert!(second.tx_channel.try_recv().is_err());
+        assert!(third.tx_channel.try_recv().is_ok());
+
+        // Direct sends are up to the caller
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Direct(second.address), packet })
+            .await;
+        assert!(second.tx_channel.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn propagation_rules_between_groups() {
+        use propagation::PropagationScope;
+
+        let mut manager = InterfaceManager::new(1);
+        let lora = manager.new_channel(4);
+        let mut other_lora = manager.new_channel(4);
+        let mut backbone = manager.new_channel(4);
+        let mut untagged = manager.new_channel(4);
+
+        manager.set_groups(&lora.address, vec!["lora".into()]);
+        manager.set_groups(&other_lora.address, vec!["lora".into()]);
+        manager.set_groups(&backbone.address, vec!["backbone".into()]);
+        manager.add_propagation_rule(PropagationRule::new("lora", "lora", PropagationScope::Announces));
+        manager.add_propagation_rule(PropagationRule::new("lora", "backbone", PropagationScope::All));
+
+        let announce = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "groups"),
+        )
+        .announce(OsRng, None)
+        .unwrap();
+
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Broadcast(Some(lora.address)), packet: announce })
+            .await;
+
+        assert!(other_lora.tx_channel.try_recv().is_err());
+        assert!(backbone.tx_channel.try_recv().is_err());
+        assert!(untagged.tx_channel.try_recv().is_ok());
+
+        // Only announces are kept within the group
+        let packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"data"),
+            ..Default::default()
+        };
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Broadcast(Some(lora.address)), packet })
+            .await;
+
+        assert!(other_lora.tx_channel.try_recv().is_ok());
+        assert!(backbone.tx_channel.try_recv().is_err());
+        assert!(untagged.tx_channel.try_recv().is_ok());
+
+        // Our own broadcasts have no origin group
+        manager
+            .send(TxMessage { tx_type: TxMessageType::Broadcast(None), packet: announce })
+            .await;
+
+        assert!(other_lora.tx_channel.try_recv().is_ok());
+        assert!(backbone.tx_channel.try_recv().is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_flags_stalled_interfaces() {
+        let mut manager = InterfaceManager::new(1);
+        manager.set_tx_stall_timeout(Duration::from_secs(5));
+        let mut events = manager.events();
+
+        let mut stuck = manager.new_channel(1);
+        let mut other = manager.new_channel(1);
+        let packet = |data: &[u8]| TxMessage {
+            tx_type: TxMessageType::Broadcast(None),
+            packet: Packet { data: PacketDataBuffer::new_from_slice(data), ..Default::default() },
+        };
+
+        manager.send(packet(b"first")).await;
+        assert!(other.tx_channel.try_recv().is_ok());
+
+        // The full queue of the stuck interface doesn't drain
+        loop {
+            match events.recv().await.unwrap() {
+                InterfaceEvent::Stalled(address) => {
+                    assert_eq!(address, stuck.address);
+                    break;
+                }
+                _ => continue,
+            }
+        }
+        assert!(manager.interfaces().iter().any(|iface| iface.stalled));
+
+        // Packets for it are dropped instead of holding up the others
+        manager.send(packet(b"second")).await;
+        assert!(other.tx_channel.try_recv().is_ok());
+
+        assert!(stuck.tx_channel.try_recv().is_ok());
+        assert!(stuck.tx_channel.try_recv().is_err());
+        tokio::time::sleep(WATCHDOG_INTERVAL * 2).await;
+        assert!(manager.interfaces().iter().all(|iface| !iface.stalled));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_stops_interfaces_whose_task_exited() {
+        let mut manager = InterfaceManager::new(1);
+        let mut events = manager.events();
+
+        let channel = manager.new_channel(1);
+        let address = channel.address;
+        drop(channel);
+
+        while events.recv().await.unwrap() != InterfaceEvent::Down(address) {}
+        assert!(manager.interfaces().is_empty());
+    }
+}
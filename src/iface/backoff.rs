@@ -0,0 +1,174 @@
+//! Delays between the attempts of an interface to connect or bind.
+//!
+//! Every failed attempt doubles the delay up to a maximum, a random part of
+//! it is taken off so that interfaces which lost the same peer don't retry
+//! in lockstep. After a configured number of attempts the interface gives up
+//! and stops.
+
+use core::time::Duration;
+
+use rand_core::{OsRng, RngCore};
+
+use super::{InterfaceReporter, InterfaceState};
+
+/// Reconnect policy of an interface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffConfig {
+    /// Delay after the first failed attempt.
+    pub initial: Duration,
+    /// Upper bound of the delay.
+    pub max: Duration,
+    /// Factor the delay grows by with every failed attempt.
+    pub multiplier: f64,
+    /// Share of the delay, between 0 and 1, which is randomly taken off.
+    pub jitter: f64,
+    /// Failed attempts in a row after which the interface gives up, `None`
+    /// retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(5),
+            max: Duration::from_secs(300),
+            multiplier: 2.0,
+            jitter: 0.25,
+            max_attempts: None,
+        }
+    }
+}
+
+impl BackoffConfig {
+    /// Retries after the same delay every time, forever.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            initial: delay,
+            max: delay,
+            multiplier: 1.0,
+            jitter: 0.0,
+            max_attempts: None,
+        }
+    }
+
+    pub fn set_max_attempts(mut self, max_attempts: Option<u32>) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+}
+
+/// Counts the failed attempts of an interface.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(config: BackoffConfig) -> Self {
+        Self { config, attempts: 0 }
+    }
+
+    /// Failed attempts since the last success.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Starts over after a successful attempt.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// Counts a failed attempt and returns the delay before the next one,
+    /// `None` once all attempts are used up.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        self.attempts = self.attempts.saturating_add(1);
+
+        if self
+            .config
+            .max_attempts
+            .is_some_and(|max_attempts| self.attempts >= max_attempts)
+        {
+            return None;
+        }
+
+        let exponent = i32::try_from(self.attempts - 1).unwrap_or(i32::MAX);
+        let delay = (self.config.initial.as_secs_f64() * self.config.multiplier.powi(exponent))
+            .min(self.config.max.as_secs_f64());
+
+        let jitter = self.config.jitter.clamp(0.0, 1.0);
+        let random = OsRng.next_u32() as f64 / u32::MAX as f64;
+
+        Some(Duration::from_secs_f64((delay * (1.0 - jitter * random)).max(0.0)))
+    }
+
+    /// Reports a successful attempt.
+    pub fn connected(&mut self, reporter: &InterfaceReporter) {
+        self.reset();
+        reporter.report(InterfaceState::Connected);
+    }
+
+    /// Reports a failed attempt together with the delay before the next one,
+    /// or that the interface gave up.
+    pub fn failed(&mut self, reporter: &InterfaceReporter) -> Option<Duration> {
+        let delay = self.next_delay();
+
+        match delay {
+            Some(delay) => reporter.report(InterfaceState::Reconnecting {
+                attempt: self.attempts,
+                delay,
+            }),
+            None => reporter.report(InterfaceState::Failed),
+        }
+
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{Backoff, BackoffConfig};
+
+    #[test]
+    fn delay_doubles_up_to_max() {
+        let mut backoff = Backoff::new(BackoffConfig {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(5),
+            jitter: 0.0,
+            ..Default::default()
+        });
+
+        let delays: Vec<_> = (0..5).map(|_| backoff.next_delay().unwrap().as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5, 5]);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn jitter_shortens_delay() {
+        let mut backoff = Backoff::new(BackoffConfig {
+            initial: Duration::from_secs(8),
+            max: Duration::from_secs(8),
+            jitter: 0.5,
+            ..Default::default()
+        });
+
+        for _ in 0..32 {
+            let delay = backoff.next_delay().unwrap();
+            assert!(delay >= Duration::from_secs(4) && delay <= Duration::from_secs(8));
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut backoff = Backoff::new(BackoffConfig::fixed(Duration::from_secs(1)).set_max_attempts(Some(3)));
+
+        assert!(backoff.next_delay().is_some());
+        assert!(backoff.next_delay().is_some());
+        assert_eq!(backoff.next_delay(), None);
+        assert_eq!(backoff.attempts(), 3);
+    }
+}
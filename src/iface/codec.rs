@@ -0,0 +1,127 @@
+//! Framing codecs shared by the stream based interface drivers.
+//!
+//! A codec only knows how to delimit packets in a byte stream. Drivers for
+//! sockets, serial ports or pipes pick a codec and leave the frame parsing
+//! to it.
+//!
+//! Codecs decode incrementally: bytes are handed to [`Framing::feed`] as they
+//! arrive, in chunks of any size, and complete frames come out as soon as
+//! their closing delimiter has been seen.
+
+use alloc::vec::Vec;
+
+use crate::{buffer::OutputBuffer, error::RnsError};
+
+pub mod hdlc;
+pub mod kiss;
+
+/// Payload of one decoded frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Counters kept by a codec instance.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Frames successfully decoded.
+    pub decoded: u64,
+    /// Frames successfully encoded.
+    pub encoded: u64,
+    /// Frames dropped because they exceeded the MTU.
+    pub oversized: u64,
+    /// Frames dropped because they were malformed.
+    pub invalid: u64,
+}
+
+pub trait Framing: Send + 'static {
+    /// Creates a codec which accepts frame payloads of up to `mtu` bytes.
+    fn new(mtu: usize) -> Self;
+
+    /// Writes `data` as one complete frame into `buffer`.
+    ///
+    /// Fails with [`RnsError::InvalidArgument`] if `data` exceeds the MTU.
+    fn encode(&mut self, data: &[u8], buffer: &mut OutputBuffer) -> Result<usize, RnsError>;
+
+    /// Consumes received bytes and returns all frames completed by them.
+    ///
+    /// Partial frames are kept until the next call.
+    fn feed(&mut self, data: &[u8]) -> impl Iterator<Item = Frame> + Send;
+
+    /// Drops any partially received frame.
+    fn reset(&mut self);
+
+    fn mtu(&self) -> usize;
+
+    fn stats(&self) -> &FrameStats;
+}
+
+/// Accumulates the payload of the frame currently being received.
+struct FrameAssembler {
+    buffer: Vec<u8>,
+    mtu: usize,
+    oversized: bool,
+}
+
+impl FrameAssembler {
+    fn new(mtu: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            mtu,
+            oversized: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.buffer.len() < self.mtu {
+            self.buffer.push(byte);
+        } else {
+            self.oversized = true;
+        }
+    }
+
+    /// Completes the current frame and starts a new one.
+    fn finish(&mut self, stats: &mut FrameStats) -> Option<Frame> {
+        let oversized = self.oversized;
+        self.oversized = false;
+
+        if oversized {
+            self.buffer.clear();
+            stats.oversized += 1;
+            return None;
+        }
+
+        if self.buffer.is_empty() {
+            return None;
+        }
+
+        stats.decoded += 1;
+
+        Some(Frame {
+            data: core::mem::take(&mut self.buffer),
+        })
+    }
+
+    fn discard(&mut self) {
+        self.buffer.clear();
+        self.oversized = false;
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+
+use crate::{buffer::OutputBuffer, error::RnsError};
+
+use super::{Frame, FrameAssembler, FrameStats, Framing};
+
+const HDLC_FRAME_FLAG: u8 = 0x7e;
+const HDLC_ESCAPE_BYTE: u8 = 0x7d;
+const HDLC_ESCAPE_MASK: u8 = 0b00100000;
+
+pub struct Hdlc {}
+
+impl Hdlc {
+    pub fn encode(data: &[u8], buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
+        buffer.write_byte(HDLC_FRAME_FLAG)?;
+
+        for &byte in data {
+            match byte {
+                HDLC_FRAME_FLAG | HDLC_ESCAPE_BYTE => {
+                    buffer.write(&[HDLC_ESCAPE_BYTE, byte ^ HDLC_ESCAPE_MASK])?;
+                }
+                _ => {
+                    buffer.write_byte(byte)?;
+                }
+            }
+        }
+
+        buffer.write_byte(HDLC_FRAME_FLAG)?;
+
+        Ok(buffer.offset())
+    }
+}
+
+/// Incremental HDLC encoder and decoder, as used by [`TcpClient`].
+///
+/// Frames are delimited by `0x7e` flags, one flag may close a frame and open
+/// the next one at the same time. Bytes outside of a frame are ignored.
+///
+/// [`TcpClient`]: crate::iface::tcp_client::TcpClient
+pub struct HdlcCodec {
+    frame: FrameAssembler,
+    in_frame: bool,
+    escape: bool,
+    stats: FrameStats,
+}
+
+impl HdlcCodec {
+    pub fn new(mtu: usize) -> Self {
+        Self {
+            frame: FrameAssembler::new(mtu),
+            in_frame: false,
+            escape: false,
+            stats: FrameStats::default(),
+        }
+    }
+
+    fn feed_byte(&mut self, byte: u8) -> Option<Frame> {
+        if byte == HDLC_FRAME_FLAG {
+            let frame = if self.in_frame && self.escape {
+                // Flag right after escape byte aborts the frame
+                self.frame.discard();
+                self.stats.invalid += 1;
+                None
+            } else if self.in_frame {
+                self.frame.finish(&mut self.stats)
+            } else {
+                None
+            };
+
+            self.in_frame = true;
+            self.escape = false;
+
+            return frame;
+        }
+
+        if !self.in_frame {
+            return None;
+        }
+
+        if self.escape {
+            self.escape = false;
+            self.frame.push(byte ^ HDLC_ESCAPE_MASK);
+        } else if byte == HDLC_ESCAPE_BYTE {
+            self.escape = true;
+        } else {
+            self.frame.push(byte);
+        }
+
+        None
+    }
+}
+
+impl Framing for HdlcCodec {
+    fn new(mtu: usize) -> Self {
+        HdlcCodec::new(mtu)
+    }
+
+    fn encode(&mut self, data: &[u8], buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
+        if data.len() > self.frame.mtu {
+            self.stats.oversized += 1;
+            return Err(RnsError::InvalidArgument);
+        }
+
+        let len = Hdlc::encode(data, buffer)?;
+        self.stats.encoded += 1;
+
+        Ok(len)
+    }
+
+    fn feed(&mut self, data: &[u8]) -> impl Iterator<Item = Frame> + Send {
+        let frames: Vec<Frame> = data.iter().filter_map(|&byte| self.feed_byte(byte)).collect();
+        frames.into_iter()
+    }
+
+    fn reset(&mut self) {
+        self.frame.discard();
+        self.in_frame = false;
+        self.escape = false;
+    }
+
+    fn mtu(&self) -> usize {
+        self.frame.mtu
+    }
+
+    fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(codec: &mut HdlcCodec, data: &[u8]) -> Vec<u8> {
+        let mut buffer = [0u8; 64];
+        let mut output = OutputBuffer::new(&mut buffer);
+        codec.encode(data, &mut output).unwrap();
+        output.as_slice().to_vec()
+    }
+
+    #[test]
+    fn feed_split_and_joined_frames() {
+        let mut codec = HdlcCodec::new(16);
+
+        let mut stream = vec![0x01, 0x02]; // noise before the first flag
+        stream.extend(encode(&mut codec, &[0x10, HDLC_FRAME_FLAG, 0x11]));
+        stream.extend(encode(&mut codec, &[HDLC_ESCAPE_BYTE]));
+
+        let mut frames = vec![];
+        for chunk in stream.chunks(3) {
+            frames.extend(codec.feed(chunk));
+        }
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].as_slice(), [0x10, HDLC_FRAME_FLAG, 0x11]);
+        assert_eq!(frames[1].as_slice(), [HDLC_ESCAPE_BYTE]);
+
+        // Frames sharing one flag
+        let frames: Vec<Frame> = codec.feed(&[0x20, HDLC_FRAME_FLAG, 0x21, HDLC_FRAME_FLAG]).collect();
+        assert_eq!(frames.len(), 2);
+
+        assert_eq!(codec.stats().decoded, 4);
+        assert_eq!(codec.stats().encoded, 2);
+    }
+
+    #[test]
+    fn decode_escapes_and_drop_aborted_frames() {
+        let mut codec = HdlcCodec::new(16);
+
+        // An unfinished frame waits for its closing flag
+        let stream = [HDLC_FRAME_FLAG, 0x01, HDLC_ESCAPE_BYTE, HDLC_ESCAPE_BYTE ^ HDLC_ESCAPE_MASK];
+        assert_eq!(codec.feed(&stream).count(), 0);
+        let frames: Vec<Frame> = codec.feed(&[0x02, HDLC_FRAME_FLAG]).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_slice(), [0x01, HDLC_ESCAPE_BYTE, 0x02]);
+
+        // A flag right after an escape byte aborts the frame
+        let frames: Vec<Frame> = codec.feed(&[0x03, HDLC_ESCAPE_BYTE, HDLC_FRAME_FLAG, 0x04, HDLC_FRAME_FLAG]).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_slice(), [0x04]);
+        assert_eq!(codec.stats().invalid, 1);
+    }
+
+    #[test]
+    fn enforce_mtu() {
+        let mut codec = HdlcCodec::new(4);
+
+        let mut buffer = [0u8; 64];
+        let mut output = OutputBuffer::new(&mut buffer);
+        assert_eq!(
+            codec.encode(&[0u8; 5], &mut output),
+            Err(RnsError::InvalidArgument)
+        );
+
+        let mut stream = vec![HDLC_FRAME_FLAG, 1, 2, 3, 4, 5, HDLC_FRAME_FLAG];
+        stream.extend([1, 2, 3, 4, HDLC_FRAME_FLAG]);
+
+        let frames: Vec<Frame> = codec.feed(&stream).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_slice(), [1, 2, 3, 4]);
+
+        assert_eq!(codec.stats().oversized, 2);
+        assert_eq!(codec.stats().decoded, 1);
+    }
+}
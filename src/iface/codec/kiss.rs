@@ -0,0 +1,198 @@
+use alloc::vec::Vec;
+
+use crate::{buffer::OutputBuffer, error::RnsError};
+
+use super::{Frame, FrameAssembler, FrameStats, Framing};
+
+const KISS_FEND: u8 = 0xc0;
+const KISS_FESC: u8 = 0xdb;
+const KISS_TFEND: u8 = 0xdc;
+const KISS_TFESC: u8 = 0xdd;
+const KISS_CMD_DATA: u8 = 0x00;
+
+/// KISS framing as used by TNCs and Python's `kiss_framing` TCP option.
+///
+/// Only data frames on port 0 are produced and accepted.
+pub struct Kiss {}
+
+impl Kiss {
+    pub fn encode(data: &[u8], buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
+        buffer.write(&[KISS_FEND, KISS_CMD_DATA])?;
+
+        for &byte in data {
+            match byte {
+                KISS_FEND => {
+                    buffer.write(&[KISS_FESC, KISS_TFEND])?;
+                }
+                KISS_FESC => {
+                    buffer.write(&[KISS_FESC, KISS_TFESC])?;
+                }
+                _ => {
+                    buffer.write_byte(byte)?;
+                }
+            }
+        }
+
+        buffer.write_byte(KISS_FEND)?;
+
+        Ok(buffer.offset())
+    }
+}
+
+/// Incremental KISS encoder and decoder.
+///
+/// Frames carrying any other command than data are counted as invalid and
+/// dropped.
+pub struct KissCodec {
+    frame: FrameAssembler,
+    in_frame: bool,
+    escape: bool,
+    command: Option<u8>,
+    stats: FrameStats,
+}
+
+impl KissCodec {
+    pub fn new(mtu: usize) -> Self {
+        Self {
+            frame: FrameAssembler::new(mtu),
+            in_frame: false,
+            escape: false,
+            command: None,
+            stats: FrameStats::default(),
+        }
+    }
+
+    fn feed_byte(&mut self, byte: u8) -> Option<Frame> {
+        if byte == KISS_FEND {
+            let command = self.command.take();
+            self.in_frame = true;
+            self.escape = false;
+
+            return match command {
+                // Consecutive FENDs only delimit empty frames
+                None => None,
+                Some(KISS_CMD_DATA) => self.frame.finish(&mut self.stats),
+                Some(_) => {
+                    self.frame.discard();
+                    self.stats.invalid += 1;
+                    None
+                }
+            };
+        }
+
+        if !self.in_frame {
+            return None;
+        }
+
+        let byte = if self.escape {
+            self.escape = false;
+            match byte {
+                KISS_TFEND => KISS_FEND,
+                KISS_TFESC => KISS_FESC,
+                _ => byte,
+            }
+        } else if byte == KISS_FESC {
+            self.escape = true;
+            return None;
+        } else {
+            byte
+        };
+
+        if self.command.is_none() {
+            self.command = Some(byte);
+        } else if self.command == Some(KISS_CMD_DATA) {
+            self.frame.push(byte);
+        }
+
+        None
+    }
+}
+
+impl Framing for KissCodec {
+    fn new(mtu: usize) -> Self {
+        KissCodec::new(mtu)
+    }
+
+    fn encode(&mut self, data: &[u8], buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
+        if data.len() > self.frame.mtu {
+            self.stats.oversized += 1;
+            return Err(RnsError::InvalidArgument);
+        }
+
+        let len = Kiss::encode(data, buffer)?;
+        self.stats.encoded += 1;
+
+        Ok(len)
+    }
+
+    fn feed(&mut self, data: &[u8]) -> impl Iterator<Item = Frame> + Send {
+        let frames: Vec<Frame> = data.iter().filter_map(|&byte| self.feed_byte(byte)).collect();
+        frames.into_iter()
+    }
+
+    fn reset(&mut self) {
+        self.frame.discard();
+        self.in_frame = false;
+        self.escape = false;
+        self.command = None;
+    }
+
+    fn mtu(&self) -> usize {
+        self.frame.mtu
+    }
+
+    fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kiss_roundtrip() {
+        let data = [0x01, KISS_FEND, 0x02, KISS_FESC, 0x03];
+
+        let mut encoded = [0u8; 32];
+        let mut output = OutputBuffer::new(&mut encoded);
+        Kiss::encode(&data, &mut output).unwrap();
+
+        assert_eq!(
+            output.as_slice(),
+            [KISS_FEND, KISS_CMD_DATA, 0x01, KISS_FESC, KISS_TFEND, 0x02, KISS_FESC, KISS_TFESC, 0x03, KISS_FEND]
+        );
+
+        // Leading FEND runs must be skipped
+        let mut stream = vec![KISS_FEND, KISS_FEND];
+        stream.extend_from_slice(output.as_slice());
+
+        let mut codec = KissCodec::new(16);
+        let frames: Vec<Frame> = codec.feed(&stream).collect();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_slice(), data);
+        assert_eq!(codec.stats().invalid, 0);
+    }
+
+    #[test]
+    fn kiss_codec_feed() {
+        let mut codec = KissCodec::new(16);
+
+        let mut encoded = [0u8; 32];
+        let mut output = OutputBuffer::new(&mut encoded);
+        codec.encode(&[0x01, KISS_FEND, 0x02], &mut output).unwrap();
+
+        let mut stream = vec![KISS_FEND, 0x06, 0x01, KISS_FEND]; // non-data command
+        stream.extend_from_slice(output.as_slice());
+
+        let mut frames = vec![];
+        for chunk in stream.chunks(2) {
+            frames.extend(codec.feed(chunk));
+        }
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_slice(), [0x01, KISS_FEND, 0x02]);
+        assert_eq!(codec.stats().invalid, 1);
+        assert_eq!(codec.stats().decoded, 1);
+    }
+}
@@ -0,0 +1,251 @@
+//! Interface for radios and other devices which transfer whole frames.
+//!
+//! Embedded targets implement [`FramedDevice`] on top of their HAL driver,
+//! e.g. an SX126x transceiver attached over SPI or I2C, and hand it to
+//! [`FramedDeviceInterface`]. No OS networking is involved: every frame read
+//! from the device is one packet, and every packet is written as one frame.
+//!
+//! [`FramedDevice`] depends on `core` only, so it can move into a `no_std`
+//! core crate together with the packet types.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use alloc::vec::Vec;
+
+use crate::buffer::{InputBuffer, OutputBuffer};
+use crate::diagnostics;
+use crate::packet::{Packet, PACKET_MAX_SIZE};
+use crate::serde::Serialize;
+
+use super::{Interface, InterfaceContext, InterfaceState, RxMessage};
+
+/// Raw frame access to a radio or bus device.
+pub trait FramedDevice: Send + 'static {
+    type Error: core::fmt::Debug + Send + 'static;
+
+    /// Largest frame the device can transfer.
+    fn mtu(&self) -> usize;
+
+    /// Copies a pending frame into `buffer` and returns its length, or
+    /// `None` if nothing was received. Should return promptly.
+    fn read_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+
+    /// Transmits `frame` as one frame.
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), Self::Error>;
+}
+
+pub struct FramedDeviceInterface<D: FramedDevice> {
+    device: Option<D>,
+    poll_interval: Duration,
+}
+
+impl<D: FramedDevice> FramedDeviceInterface<D> {
+    pub fn new(device: D) -> Self {
+        Self {
+            device: Some(device),
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// How long to wait before polling the device again when no frame was
+    /// pending.
+    pub fn set_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub async fn spawn(context: InterfaceContext<Self>) {
+        let iface_stop = context.channel.stop.clone();
+        let iface_address = context.channel.address;
+
+        let (device, poll_interval) = {
+            let mut inner = context.inner.lock().unwrap();
+            (inner.device.take(), inner.poll_interval)
+        };
+
+        let Some(device) = device else {
+            log::warn!("framed_device: device is already in use");
+            iface_stop.cancel();
+            return;
+        };
+
+        let mtu = device.mtu().min(PACKET_MAX_SIZE);
+        let device = Arc::new(Mutex::new(device));
+        context.reporter.report(InterfaceState::Connected);
+
+        let (rx_channel, mut tx_channel) = context.channel.split();
+
+        // Device calls may block on the bus, keep them off the async executor
+        let rx_task = {
+            let cancel = context.cancel.clone();
+            let device = device.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    let read = {
+                        let device = device.clone();
+                        tokio::task::spawn_blocking(move || {
+                            let mut buffer = vec![0u8; mtu];
+                            let result = device.lock().unwrap().read_frame(&mut buffer);
+                            result.map(|len| len.map(|len| Vec::from(&buffer[..len.min(mtu)])))
+                        })
+                    };
+
+                    let wait = match read.await {
+                        Ok(Ok(Some(frame))) => {
+                            match Packet::deserialize(&mut InputBuffer::new(&frame)) {
+                                Ok(packet) => {
+                                    let _ = rx_channel
+                                        .send(RxMessage { address: iface_address, packet })
+                                        .await;
+                                }
+                                Err(_) => diagnostics::packet_undecodable("framed_device", &frame),
+                            }
+                            false
+                        }
+                        Ok(Ok(None)) => true,
+                        Ok(Err(err)) => {
+                            log::warn!("framed_device: read error {:?}", err);
+                            true
+                        }
+                        Err(_) => break,
+                    };
+
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+
+                    if wait {
+                        tokio::select! {
+                            _ = cancel.cancelled() => break,
+                            _ = tokio::time::sleep(poll_interval) => {}
+                        }
+                    }
+                }
+            })
+        };
+
+        loop {
+            tokio::select! {
+                _ = context.cancel.cancelled() => {
+                    break;
+                }
+                Some(message) = tx_channel.recv() => {
+                    let mut tx_buffer = [0u8; PACKET_MAX_SIZE];
+                    let mut output = OutputBuffer::new(&mut tx_buffer);
+                    if message.packet.serialize(&mut output).is_err() {
+                        continue;
+                    }
+
+                    if output.offset() > mtu {
+                        log::warn!(
+                            "framed_device: dropping packet of {} bytes, device mtu is {}",
+                            output.offset(),
+                            mtu
+                        );
+                        continue;
+                    }
+
+                    let frame = Vec::from(output.as_slice());
+                    let device = device.clone();
+                    let result = tokio::task::spawn_blocking(move || {
+                        device.lock().unwrap().write_frame(&frame)
+                    })
+                    .await;
+
+                    if let Ok(Err(err)) = result {
+                        log::warn!("framed_device: write error {:?}", err);
+                    }
+                }
+            }
+        }
+
+        let _ = rx_task.await;
+
+        iface_stop.cancel();
+    }
+}
+
+impl<D: FramedDevice> Interface for FramedDeviceInterface<D> {
+    fn mtu() -> usize {
+        2048
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::transport::{Transport, TransportConfig};
+
+    use super::*;
+
+    type Air = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+    /// Half-duplex link: frames written by one device are read by the other.
+    struct MockRadio {
+        tx: Air,
+        rx: Air,
+    }
+
+    impl FramedDevice for MockRadio {
+        type Error = ();
+
+        fn mtu(&self) -> usize {
+            255
+        }
+
+        fn read_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>, ()> {
+            Ok(self.rx.lock().unwrap().pop_front().map(|frame| {
+                buffer[..frame.len()].copy_from_slice(&frame);
+                frame.len()
+            }))
+        }
+
+        fn write_frame(&mut self, frame: &[u8]) -> Result<(), ()> {
+            self.tx.lock().unwrap().push_back(frame.to_vec());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn exchange_packets_over_device() {
+        let a_to_b = Air::default();
+        let b_to_a = Air::default();
+
+        let transport_a = Transport::new(TransportConfig::default());
+        transport_a.iface_manager().lock().await.spawn(
+            FramedDeviceInterface::new(MockRadio { tx: a_to_b.clone(), rx: b_to_a.clone() }),
+            FramedDeviceInterface::spawn,
+        );
+
+        let transport_b = Transport::new(TransportConfig::default());
+        transport_b.iface_manager().lock().await.spawn(
+            FramedDeviceInterface::new(MockRadio { tx: b_to_a, rx: a_to_b.clone() }),
+            FramedDeviceInterface::spawn,
+        );
+
+        let mut iface_rx = transport_b.iface_rx();
+
+        let mut packet = Packet::default();
+        packet.data.write(b"radio").unwrap();
+        transport_a.send_packet(packet).await.unwrap();
+
+        // Too large for the device mtu
+        let mut packet = Packet::default();
+        packet.data.resize(1024);
+        transport_a.send_packet(packet).await.unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(2), iface_rx.recv())
+            .await
+            .expect("packet was not received over the device")
+            .unwrap();
+
+        assert_eq!(message.packet.data.as_slice(), b"radio");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(iface_rx.try_recv().is_err());
+        assert!(a_to_b.lock().unwrap().is_empty());
+    }
+}
@@ -15,8 +15,10 @@ use tokio_util::sync::CancellationToken;
 use tonic::transport::Channel;
 
 use crate::buffer::{InputBuffer, OutputBuffer};
+use crate::diagnostics;
 use crate::error::RnsError;
-use crate::iface::{Interface, InterfaceContext, RxMessage};
+use crate::iface::backoff::{Backoff, BackoffConfig};
+use crate::iface::{Interface, InterfaceContext, InterfaceState, RxMessage};
 use crate::packet::Packet;
 use crate::serde::Serialize;
 
@@ -30,6 +32,7 @@ pub struct KaonicGrpc {
     addr: String,
     config: Arc<Mutex<RadioConfig>>,
     config_channel: Arc<Mutex<Option<Receiver<RadioConfig>>>>,
+    backoff: BackoffConfig,
 }
 
 impl KaonicGrpc {
@@ -42,14 +45,22 @@ impl KaonicGrpc {
             addr: addr.into(),
             config: Arc::new(Mutex::new(config)),
             config_channel: Arc::new(Mutex::new(config_channel)),
+            backoff: BackoffConfig::default(),
         }
     }
 
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
     pub async fn spawn(context: InterfaceContext<Self>) {
         let addr = { context.inner.lock().unwrap().addr.clone() };
         let current_config = { context.inner.lock().unwrap().config.clone() };
 
         let iface_address = context.channel.address;
+        let iface_stop = context.channel.stop.clone();
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
 
         let (rx_channel, tx_channel) = context.channel.split();
 
@@ -70,7 +81,16 @@ impl KaonicGrpc {
 
             if let Err(err) = grpc_channel {
                 log::warn!("kaonic_grpc: couldn't connect to <{}> = '{}'", addr, err);
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                let Some(delay) = backoff.failed(&context.reporter) else {
+                    log::warn!(
+                        "kaonic_grpc: giving up on <{}> after {} attempts",
+                        addr,
+                        backoff.attempts()
+                    );
+                    iface_stop.cancel();
+                    break;
+                };
+                tokio::time::sleep(delay).await;
                 continue;
             }
 
@@ -89,6 +109,7 @@ impl KaonicGrpc {
                 .into_inner();
 
             log::info!("kaonic_grpc: connected to <{}>", addr);
+            backoff.connected(&context.reporter);
 
             const BUFFER_SIZE: usize = std::mem::size_of::<Packet>() * 2;
 
@@ -123,7 +144,7 @@ impl KaonicGrpc {
                                                 if let Ok(packet) = Packet::deserialize(&mut InputBuffer::new(buf)) {
                                                         let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
                                                 } else {
-                                                    log::warn!("kaonic_grpc: couldn't decode packet");
+                                                    diagnostics::packet_undecodable("kaonic_grpc", buf);
                                                 }
                                             }
                                         }
@@ -224,6 +245,7 @@ impl KaonicGrpc {
             rx_task.await.unwrap();
 
             log::info!("kaonic_grpc: disconnected from <{}>", addr);
+            context.reporter.report(InterfaceState::Disconnected);
         }
     }
 }
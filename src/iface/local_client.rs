@@ -0,0 +1,308 @@
+//! Client side of the local shared instance interface.
+//!
+//! A [`LocalClientInterface`] attaches to a Reticulum instance (Rust or
+//! Python `rnsd`) which is already running on the same machine and shares
+//! its interfaces over the shared instance socket, see
+//! [`LocalServerInterface`](super::local_server::LocalServerInterface) for
+//! the other side. Packets are exchanged with HDLC framing, the same as on
+//! TCP interfaces.
+//!
+//! The shared instance socket is a loopback TCP port on every platform. On
+//! Linux an abstract unix domain socket can be used instead, on Windows a
+//! named pipe takes that role.
+
+use std::sync::Arc;
+
+use alloc::string::String;
+
+use tokio::net::TcpStream;
+
+use super::backoff::{Backoff, BackoffConfig};
+use super::codec::hdlc::HdlcCodec;
+use super::stream::{handle_stream, Liveness};
+use super::{Interface, InterfaceContext, InterfaceState};
+
+/// Default TCP port of a shared instance (`shared_instance_port`).
+pub const DEFAULT_SHARED_INSTANCE_PORT: u16 = 37428;
+
+/// Default name of a shared instance (`instance_name`).
+pub const DEFAULT_INSTANCE_NAME: &str = "default";
+
+/// How to reach the shared instance socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalSocket {
+    /// TCP socket on the loopback interface with the given port.
+    Tcp(u16),
+    /// Abstract unix domain socket `rns/<instance_name>`, as used by
+    /// Python `rnsd` on Linux.
+    #[cfg(target_os = "linux")]
+    Unix(String),
+    /// Named pipe `\\.\pipe\rns\<instance_name>`, the Windows counterpart
+    /// of the abstract unix socket.
+    #[cfg(windows)]
+    Pipe(String),
+}
+
+impl LocalSocket {
+    pub(super) fn describe(&self) -> String {
+        match self {
+            LocalSocket::Tcp(port) => format!("127.0.0.1:{}", port),
+            #[cfg(target_os = "linux")]
+            LocalSocket::Unix(instance_name) => format!("@rns/{}", instance_name),
+            #[cfg(windows)]
+            LocalSocket::Pipe(instance_name) => pipe_name(instance_name),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub(super) fn pipe_name(instance_name: &str) -> String {
+    format!(r"\\.\pipe\rns\{}", instance_name)
+}
+
+impl Default for LocalSocket {
+    fn default() -> Self {
+        LocalSocket::Tcp(DEFAULT_SHARED_INSTANCE_PORT)
+    }
+}
+
+pub(super) enum LocalStream {
+    Tcp(TcpStream),
+    #[cfg(target_os = "linux")]
+    Unix(tokio::net::UnixStream),
+    #[cfg(windows)]
+    Pipe(tokio::net::windows::named_pipe::NamedPipeClient),
+    /// A client connected to our end of the pipe.
+    #[cfg(windows)]
+    PipeServer(tokio::net::windows::named_pipe::NamedPipeServer),
+}
+
+pub struct LocalClientInterface {
+    socket: LocalSocket,
+    /// Connection a shared instance accepted, the interface stops once it
+    /// closes.
+    stream: Option<LocalStream>,
+    backoff: BackoffConfig,
+}
+
+impl LocalClientInterface {
+    pub fn new(socket: LocalSocket) -> Self {
+        Self {
+            socket,
+            stream: None,
+            backoff: BackoffConfig::default(),
+        }
+    }
+
+    /// Interface of a client which connected to `socket` of our shared
+    /// instance.
+    pub(super) fn new_from_stream(socket: LocalSocket, stream: LocalStream) -> Self {
+        Self {
+            socket,
+            stream: Some(stream),
+            backoff: BackoffConfig::default(),
+        }
+    }
+
+    pub fn new_tcp(port: u16) -> Self {
+        Self::new(LocalSocket::Tcp(port))
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_unix<T: Into<String>>(instance_name: T) -> Self {
+        Self::new(LocalSocket::Unix(instance_name.into()))
+    }
+
+    #[cfg(windows)]
+    pub fn new_pipe<T: Into<String>>(instance_name: T) -> Self {
+        Self::new(LocalSocket::Pipe(instance_name.into()))
+    }
+
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    async fn connect(socket: &LocalSocket) -> std::io::Result<LocalStream> {
+        match socket {
+            LocalSocket::Tcp(port) => Ok(LocalStream::Tcp(
+                TcpStream::connect(("127.0.0.1", *port)).await?,
+            )),
+            #[cfg(target_os = "linux")]
+            LocalSocket::Unix(instance_name) => {
+                use std::os::linux::net::SocketAddrExt;
+
+                let name = format!("rns/{}", instance_name);
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+                let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+                stream.set_nonblocking(true)?;
+
+                Ok(LocalStream::Unix(tokio::net::UnixStream::from_std(stream)?))
+            }
+            #[cfg(windows)]
+            LocalSocket::Pipe(instance_name) => {
+                use tokio::net::windows::named_pipe::ClientOptions;
+
+                Ok(LocalStream::Pipe(
+                    ClientOptions::new().open(pipe_name(instance_name))?,
+                ))
+            }
+        }
+    }
+
+    pub async fn spawn(context: InterfaceContext<Self>) {
+        let iface_stop = context.channel.stop.clone();
+        let socket = { context.inner.lock().unwrap().socket.clone() };
+        let mut stream = { context.inner.lock().unwrap().stream.take() };
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
+        let iface_address = context.channel.address;
+
+        let (rx_channel, tx_channel) = context.channel.split();
+        let tx_channel = Arc::new(tokio::sync::Mutex::new(tx_channel));
+
+        let mut running = true;
+        'outer: loop {
+            if !running || context.cancel.is_cancelled() {
+                break;
+            }
+
+            let stream = match stream.take() {
+                Some(stream) => {
+                    running = false;
+                    Ok(stream)
+                }
+                None => {
+                    let mut tx_channel = tx_channel.lock().await;
+
+                    tokio::select! {
+                        biased;
+                        _ = context.cancel.cancelled() => {
+                            break;
+                        }
+                        Some(_) = tx_channel.recv() => {
+                            continue;
+                        }
+                        result = Self::connect(&socket) => result
+                    }
+                }
+            };
+
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::info!(
+                        "local_client: couldn't connect to shared instance <{}>: {}",
+                        socket.describe(),
+                        err
+                    );
+
+                    let Some(delay) = backoff.failed(&context.reporter) else {
+                        log::warn!(
+                            "local_client: giving up on shared instance <{}> after {} attempts",
+                            socket.describe(),
+                            backoff.attempts()
+                        );
+                        break;
+                    };
+                    let retry_at = tokio::time::Instant::now() + delay;
+
+                    loop {
+                        let mut tx_channel = tx_channel.lock().await;
+
+                        tokio::select! {
+                            biased;
+                            _ = context.cancel.cancelled() => {
+                                break 'outer;
+                            }
+                            Some(_) = tx_channel.recv() => {}
+                            _ = tokio::time::sleep_until(retry_at) => {
+                                break;
+                            }
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            log::info!(
+                "local_client: connected to shared instance <{}>",
+                socket.describe()
+            );
+            backoff.connected(&context.reporter);
+
+            match stream {
+                LocalStream::Tcp(stream) => {
+                    handle_stream::<HdlcCodec, _>(
+                        "local_client",
+                        stream,
+                        iface_address,
+                        rx_channel.clone(),
+                        tx_channel.clone(),
+                        context.cancel.clone(),
+                        Liveness::default(),
+                    )
+                    .await
+                }
+                #[cfg(target_os = "linux")]
+                LocalStream::Unix(stream) => {
+                    handle_stream::<HdlcCodec, _>(
+                        "local_client",
+                        stream,
+                        iface_address,
+                        rx_channel.clone(),
+                        tx_channel.clone(),
+                        context.cancel.clone(),
+                        Liveness::default(),
+                    )
+                    .await
+                }
+                #[cfg(windows)]
+                LocalStream::Pipe(stream) => {
+                    handle_stream::<HdlcCodec, _>(
+                        "local_client",
+                        stream,
+                        iface_address,
+                        rx_channel.clone(),
+                        tx_channel.clone(),
+                        context.cancel.clone(),
+                        Liveness::default(),
+                    )
+                    .await
+                }
+                #[cfg(windows)]
+                LocalStream::PipeServer(stream) => {
+                    handle_stream::<HdlcCodec, _>(
+                        "local_client",
+                        stream,
+                        iface_address,
+                        rx_channel.clone(),
+                        tx_channel.clone(),
+                        context.cancel.clone(),
+                        Liveness::default(),
+                    )
+                    .await
+                }
+            }
+
+            log::info!(
+                "local_client: disconnected from shared instance <{}>",
+                socket.describe()
+            );
+            context.reporter.report(InterfaceState::Disconnected);
+        }
+
+        iface_stop.cancel();
+    }
+}
+
+impl Default for LocalClientInterface {
+    fn default() -> Self {
+        Self::new(LocalSocket::default())
+    }
+}
+
+impl Interface for LocalClientInterface {
+    fn mtu() -> usize {
+        2048
+    }
+}
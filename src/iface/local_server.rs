@@ -0,0 +1,183 @@
+//! Server side of the local shared instance interface.
+//!
+//! A [`LocalServerInterface`] makes this instance the shared instance of the
+//! machine: programs which run a [`LocalClientInterface`], or Python
+//! programs started next to it, attach to it and use its interfaces. Every
+//! client gets an interface of its own, like the clients of a
+//! [`TcpServer`](super::tcp_server::TcpServer).
+//!
+//! It listens on the same sockets the client connects to: a loopback TCP
+//! port, the abstract unix socket `rns/<instance_name>` on Linux or the
+//! named pipe `\\.\pipe\rns\<instance_name>` on Windows.
+
+use std::io;
+use std::sync::Arc;
+
+use tokio::net::TcpListener;
+
+use super::backoff::{Backoff, BackoffConfig};
+use super::local_client::{LocalClientInterface, LocalSocket, LocalStream};
+use super::{Interface, InterfaceContext, InterfaceManager};
+
+/// A bound shared instance socket.
+enum LocalListener {
+    Tcp(TcpListener),
+    #[cfg(target_os = "linux")]
+    Unix(tokio::net::UnixListener),
+    /// The pipe instance the next client connects to. Named pipes take one
+    /// client per instance, so a new one is created for each client.
+    #[cfg(windows)]
+    Pipe(String, tokio::net::windows::named_pipe::NamedPipeServer),
+}
+
+impl LocalListener {
+    async fn bind(socket: &LocalSocket) -> io::Result<Self> {
+        match socket {
+            LocalSocket::Tcp(port) => Ok(LocalListener::Tcp(TcpListener::bind(("127.0.0.1", *port)).await?)),
+            #[cfg(target_os = "linux")]
+            LocalSocket::Unix(instance_name) => {
+                use std::os::linux::net::SocketAddrExt;
+
+                let name = format!("rns/{}", instance_name);
+                let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+                let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+                listener.set_nonblocking(true)?;
+
+                Ok(LocalListener::Unix(tokio::net::UnixListener::from_std(listener)?))
+            }
+            #[cfg(windows)]
+            LocalSocket::Pipe(instance_name) => {
+                use tokio::net::windows::named_pipe::ServerOptions;
+
+                let name = super::local_client::pipe_name(instance_name);
+                // Fails if another instance already serves the pipe
+                let server = ServerOptions::new().first_pipe_instance(true).create(&name)?;
+
+                Ok(LocalListener::Pipe(name, server))
+            }
+        }
+    }
+
+    async fn accept(&mut self) -> io::Result<LocalStream> {
+        match self {
+            LocalListener::Tcp(listener) => Ok(LocalStream::Tcp(listener.accept().await?.0)),
+            #[cfg(target_os = "linux")]
+            LocalListener::Unix(listener) => Ok(LocalStream::Unix(listener.accept().await?.0)),
+            #[cfg(windows)]
+            LocalListener::Pipe(name, server) => {
+                use tokio::net::windows::named_pipe::ServerOptions;
+
+                server.connect().await?;
+                let next = ServerOptions::new().create(name.as_str())?;
+
+                Ok(LocalStream::PipeServer(core::mem::replace(server, next)))
+            }
+        }
+    }
+}
+
+pub struct LocalServerInterface {
+    socket: LocalSocket,
+    iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>,
+    backoff: BackoffConfig,
+}
+
+impl LocalServerInterface {
+    pub fn new(socket: LocalSocket, iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>) -> Self {
+        Self {
+            socket,
+            iface_manager,
+            backoff: BackoffConfig::default(),
+        }
+    }
+
+    /// Delays between attempts to bind the shared instance socket.
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub async fn spawn(context: InterfaceContext<Self>) {
+        let socket = { context.inner.lock().unwrap().socket.clone() };
+        let iface_manager = { context.inner.lock().unwrap().iface_manager.clone() };
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
+        let iface_stop = context.channel.stop.clone();
+
+        // Clients inherit the mode and groups of the server interface
+        let mode = context.channel.mode;
+        let server_address = context.channel.address;
+
+        // Packets go out over the interfaces of the clients
+        let (_, mut tx_channel) = context.channel.split();
+
+        let mut listener = loop {
+            match LocalListener::bind(&socket).await {
+                Ok(listener) => break listener,
+                Err(err) => {
+                    log::warn!("local_server: couldn't bind <{}>: {}", socket.describe(), err);
+
+                    let Some(delay) = backoff.failed(&context.reporter) else {
+                        log::warn!(
+                            "local_server: giving up on <{}> after {} attempts",
+                            socket.describe(),
+                            backoff.attempts()
+                        );
+                        iface_stop.cancel();
+                        return;
+                    };
+
+                    tokio::select! {
+                        _ = context.cancel.cancelled() => return,
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+            }
+        };
+
+        log::info!("local_server: listen on <{}>", socket.describe());
+        backoff.connected(&context.reporter);
+
+        let mut clients = 0usize;
+        loop {
+            tokio::select! {
+                _ = context.cancel.cancelled() => break,
+                // Skip all tx messages
+                _ = tx_channel.recv() => {}
+                client = listener.accept() => match client {
+                    Ok(stream) => {
+                        clients += 1;
+                        log::info!("local_server: client {} connected to <{}>", clients, socket.describe());
+
+                        let mut iface_manager = iface_manager.lock().await;
+
+                        // Named like the clients of a TCP server
+                        let name = format!(
+                            "Local client {} on {}",
+                            clients,
+                            iface_manager.display_name(&server_address)
+                        );
+                        let address = iface_manager
+                            .spawn_named(
+                                name,
+                                LocalClientInterface::new_from_stream(socket.clone(), stream),
+                                mode,
+                                LocalClientInterface::spawn,
+                            )
+                            .address();
+                        let groups = iface_manager.groups(&server_address);
+                        iface_manager.set_groups(&address, groups);
+                    }
+                    Err(err) => log::warn!("local_server: couldn't accept client: {}", err),
+                },
+            }
+        }
+
+        iface_stop.cancel();
+    }
+}
+
+impl Interface for LocalServerInterface {
+    fn mtu() -> usize {
+        2048
+    }
+}
@@ -0,0 +1,46 @@
+//! Interface groups and the rules which limit broadcasts between them.
+//!
+//! Interfaces can be tagged with any number of groups, e.g. "backbone" or
+//! "lora". A [`PropagationRule`] stops broadcasts received on an interface
+//! of one group from going out on interfaces of another (or the same)
+//! group. Direct sends along known paths are not affected, but destinations
+//! whose announces are held back can't be reached through us anyway.
+
+use alloc::string::String;
+
+/// Traffic a [`PropagationRule`] applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PropagationScope {
+    /// Only announces are held back.
+    #[default]
+    Announces,
+    /// All broadcast packets are held back.
+    All,
+}
+
+/// Keeps broadcasts received on interfaces of group `from` off the
+/// interfaces of group `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropagationRule {
+    pub from: String,
+    pub to: String,
+    pub scope: PropagationScope,
+}
+
+impl PropagationRule {
+    pub fn new<T: Into<String>>(from: T, to: T, scope: PropagationScope) -> Self {
+        Self {
+            from: from.into(),
+            to: to.into(),
+            scope,
+        }
+    }
+
+    /// Returns whether a packet received on an interface with the groups
+    /// `from` may not be sent on an interface with the groups `to`.
+    pub fn blocks(&self, from: &[String], to: &[String], is_announce: bool) -> bool {
+        (is_announce || self.scope == PropagationScope::All)
+            && from.contains(&self.from)
+            && to.contains(&self.to)
+    }
+}
@@ -0,0 +1,139 @@
+//! Binding the sockets of servers and UDP interfaces.
+
+use std::io;
+use std::net::{Ipv6Addr, SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// Options for the sockets an interface binds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Bind addresses still in use by closed connections or other sockets
+    /// (`SO_REUSEADDR`). TCP listeners always do on Unix.
+    pub reuse_address: bool,
+    /// Let several sockets bind the same address and port (`SO_REUSEPORT`),
+    /// Unix only.
+    pub reuse_port: bool,
+    /// Whether sockets bound to IPv6 addresses accept IPv6 only, or IPv4
+    /// mapped addresses as well. `None` keeps the system default, which is
+    /// dual-stack on most systems.
+    pub only_v6: Option<bool>,
+}
+
+impl SocketOptions {
+    fn socket(&self, addr: &SocketAddr, ty: Type, protocol: Protocol) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::for_address(*addr), ty, Some(protocol))?;
+
+        // Like tokio, so a restarted server can bind its port right away
+        let reuse_address = self.reuse_address || (cfg!(unix) && ty == Type::STREAM);
+        socket.set_reuse_address(reuse_address)?;
+
+        if self.reuse_port {
+            #[cfg(unix)]
+            socket.set_reuse_port(true)?;
+            #[cfg(not(unix))]
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "SO_REUSEPORT is Unix only"));
+        }
+
+        if let (true, Some(only_v6)) = (addr.is_ipv6(), self.only_v6) {
+            socket.set_only_v6(only_v6)?;
+        }
+
+        socket.set_nonblocking(true)?;
+        socket.bind(&(*addr).into())?;
+
+        Ok(socket)
+    }
+
+    /// Binds a TCP listener to the first address `addr` resolves to which
+    /// can be bound.
+    pub async fn bind_tcp(&self, addr: &str) -> io::Result<TcpListener> {
+        self.bind_any(addr, |addr| {
+            let socket = self.socket(addr, Type::STREAM, Protocol::TCP)?;
+            socket.listen(1024)?;
+            TcpListener::from_std(socket.into())
+        })
+        .await
+    }
+
+    /// Binds a UDP socket to the first address `addr` resolves to which can
+    /// be bound.
+    pub async fn bind_udp(&self, addr: &str) -> io::Result<UdpSocket> {
+        self.bind_any(addr, |addr| {
+            let socket = self.socket(addr, Type::DGRAM, Protocol::UDP)?;
+            UdpSocket::from_std(socket.into())
+        })
+        .await
+    }
+
+    async fn bind_any<S>(
+        &self,
+        addr: &str,
+        bind: impl Fn(&SocketAddr) -> io::Result<S>,
+    ) -> io::Result<S> {
+        let mut last_err = None;
+
+        for addr in tokio::net::lookup_host(addr).await? {
+            match bind(&addr) {
+                Ok(socket) => return Ok(socket),
+                Err(err) => last_err = Some(err),
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "address resolved to nothing")
+        }))
+    }
+}
+
+/// Joins `host` and `port` to an address which can be bound or connected
+/// to. IPv6 addresses are put in brackets, e.g. `[2001:db8::1]:4242`.
+pub fn join_host_port(host: &str, port: u16) -> String {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        return format!("[{}]:{}", host, port);
+    }
+
+    // Python configs sometimes end hosts with a colon
+    format!("{}:{}", host.trim_end_matches(':'), port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_ipv6_hosts_in_brackets() {
+        assert_eq!(join_host_port("127.0.0.1", 4242), "127.0.0.1:4242");
+        assert_eq!(join_host_port("0.0.0.0:", 4242), "0.0.0.0:4242");
+        assert_eq!(join_host_port("localhost", 4242), "localhost:4242");
+        assert_eq!(join_host_port("::", 4242), "[::]:4242");
+        assert_eq!(join_host_port("2001:db8::1", 4242), "[2001:db8::1]:4242");
+        assert_eq!(join_host_port("[::1]", 4242), "[::1]:4242");
+    }
+
+    #[tokio::test]
+    async fn sockets_share_port_with_reuse_port() {
+        let options = SocketOptions { reuse_port: true, ..Default::default() };
+
+        let first = options.bind_udp("127.0.0.1:0").await.unwrap();
+        let addr = first.local_addr().unwrap().to_string();
+
+        assert!(SocketOptions::default().bind_udp(&addr).await.is_err());
+        #[cfg(unix)]
+        assert!(options.bind_udp(&addr).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn ipv6_only_sockets_leave_ipv4_free() {
+        let options = SocketOptions { only_v6: Some(true), ..Default::default() };
+
+        // Hosts without IPv6 can't run this test
+        let Ok(v6) = options.bind_tcp("[::]:0").await else {
+            return;
+        };
+        let port = v6.local_addr().unwrap().port();
+
+        assert!(SocketOptions::default().bind_tcp(&format!("0.0.0.0:{}", port)).await.is_ok());
+    }
+}
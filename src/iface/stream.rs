@@ -0,0 +1,194 @@
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::{sleep_until, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::buffer::{InputBuffer, OutputBuffer};
+use crate::diagnostics;
+use crate::hash::AddressHash;
+use crate::packet::{Packet, PACKET_MAX_SIZE};
+use crate::serde::Serialize;
+use crate::trace::{trace_packet, TraceCategory};
+
+use super::codec::Framing;
+use super::{InterfaceRxSender, InterfaceTxReceiver, RxMessage};
+
+const FRAME_MTU: usize = PACKET_MAX_SIZE;
+
+const STREAM_BUFFER_SIZE: usize = FRAME_MTU * 16;
+
+/// Keeps an idle connection open and notices when it died silently.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Liveness {
+    /// An empty frame is sent after this long without sending anything.
+    /// Peers drop empty frames.
+    pub ping_interval: Option<Duration>,
+    /// The connection is closed after this long without receiving anything.
+    pub rx_timeout: Option<Duration>,
+}
+
+async fn write_frame<S: AsyncWrite + Unpin>(stream: &mut S, frame: &[u8]) -> io::Result<()> {
+    stream.write_all(frame).await?;
+    stream.flush().await
+}
+
+/// Runs framed packet exchange over a connected byte stream until the
+/// connection is closed or `cancel` is triggered.
+pub(crate) async fn handle_stream<F, S>(
+    name: &'static str,
+    stream: S,
+    iface_address: AddressHash,
+    rx_channel: InterfaceRxSender,
+    tx_channel: Arc<tokio::sync::Mutex<InterfaceTxReceiver>>,
+    cancel: CancellationToken,
+    liveness: Liveness,
+) where
+    F: Framing,
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let stop = CancellationToken::new();
+
+    let (read_stream, write_stream) = tokio::io::split(stream);
+
+    // Start receive task
+    let rx_task = {
+        let cancel = cancel.clone();
+        let stop = stop.clone();
+        let mut stream = read_stream;
+        let rx_channel = rx_channel.clone();
+
+        tokio::spawn(async move {
+            let mut codec = F::new(FRAME_MTU);
+            let mut stream_buffer = [0u8; STREAM_BUFFER_SIZE];
+            let rx_timeout = liveness.rx_timeout;
+            let mut last_rx = Instant::now();
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                            break;
+                    }
+                    _ = stop.cancelled() => {
+                            break;
+                    }
+                    _ = sleep_until(last_rx + rx_timeout.unwrap_or_default()), if rx_timeout.is_some() => {
+                            log::warn!(
+                                "{}: nothing received for {} seconds, closing connection",
+                                name,
+                                rx_timeout.unwrap_or_default().as_secs()
+                            );
+                            stop.cancel();
+                            break;
+                    }
+                    result = stream.read(&mut stream_buffer[..]) => {
+                            match result {
+                                Ok(0) => {
+                                    log::warn!("{}: connection closed", name);
+                                    stop.cancel();
+                                    break;
+                                }
+                                Ok(n) => {
+                                    last_rx = Instant::now();
+
+                                    // Stream may contain several or partial frames
+                                    let oversized = codec.stats().oversized;
+                                    let invalid = codec.stats().invalid;
+
+                                    for frame in codec.feed(&stream_buffer[..n]) {
+                                        if let Ok(packet) = Packet::deserialize(&mut InputBuffer::new(frame.as_slice())) {
+                                            trace_packet!(TraceCategory::InterfaceRx, "{}: rx << ({}) {}", name, iface_address, packet);
+                                            let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
+                                        } else {
+                                            diagnostics::packet_undecodable(name, frame.as_slice());
+                                        }
+                                    }
+
+                                    if codec.stats().oversized != oversized {
+                                        diagnostics::frame_undecodable(name, "oversized", &stream_buffer[..n]);
+                                    }
+                                    if codec.stats().invalid != invalid {
+                                        diagnostics::frame_undecodable(name, "invalid", &stream_buffer[..n]);
+                                    }
+                                }
+                                Err(e) => {
+                                    log::warn!("{}: connection error {}", name, e);
+                                    stop.cancel();
+                                    break;
+                                }
+                            }
+                        },
+                };
+            }
+        })
+    };
+
+    // Start transmit task
+    let tx_task = {
+        let cancel = cancel.clone();
+        let tx_channel = tx_channel.clone();
+        let mut stream = write_stream;
+
+        tokio::spawn(async move {
+            let mut codec = F::new(FRAME_MTU);
+            let ping_interval = liveness.ping_interval;
+            let mut last_tx = Instant::now();
+
+            loop {
+                if stop.is_cancelled() {
+                    break;
+                }
+
+                // Worst case every byte is escaped
+                let mut frame_tx_buffer = [0u8; FRAME_MTU * 2 + 4];
+                let mut tx_buffer = [0u8; FRAME_MTU];
+
+                let mut tx_channel = tx_channel.lock().await;
+
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                            break;
+                    }
+                    _ = stop.cancelled() => {
+                            break;
+                    }
+                    Some(message) = tx_channel.recv() => {
+                        let packet = message.packet;
+                        trace_packet!(TraceCategory::InterfaceTx, "{}: tx >> ({}) {}", name, iface_address, packet);
+                        let mut output = OutputBuffer::new(&mut tx_buffer);
+                        if packet.serialize(&mut output).is_ok() {
+
+                            let mut frame_output = OutputBuffer::new(&mut frame_tx_buffer[..]);
+
+                            if codec.encode(output.as_slice(), &mut frame_output).is_ok() {
+                                if let Err(e) = write_frame(&mut stream, frame_output.as_slice()).await {
+                                    log::warn!("{}: connection error {}", name, e);
+                                    stop.cancel();
+                                    break;
+                                }
+                                last_tx = Instant::now();
+                            }
+                        }
+                    }
+                    _ = sleep_until(last_tx + ping_interval.unwrap_or_default()), if ping_interval.is_some() => {
+                        let mut frame_output = OutputBuffer::new(&mut frame_tx_buffer[..]);
+
+                        if codec.encode(&[], &mut frame_output).is_ok() {
+                            if let Err(e) = write_frame(&mut stream, frame_output.as_slice()).await {
+                                log::warn!("{}: connection error {}", name, e);
+                                stop.cancel();
+                                break;
+                            }
+                        }
+                        last_tx = Instant::now();
+                    }
+                };
+            }
+        })
+    };
+
+    tx_task.await.unwrap();
+    rx_task.await.unwrap();
+}
@@ -1,28 +1,150 @@
+use std::io;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
-use tokio_util::sync::CancellationToken;
 
-use crate::buffer::{InputBuffer, OutputBuffer};
 use crate::error::RnsError;
-use crate::iface::RxMessage;
-use crate::packet::Packet;
-use crate::serde::Serialize;
-
-use tokio::io::AsyncReadExt;
 
 use alloc::string::String;
 
-use super::hdlc::Hdlc;
-use super::{Interface, InterfaceContext};
+use super::backoff::{Backoff, BackoffConfig};
+use super::codec::hdlc::HdlcCodec;
+use super::codec::kiss::KissCodec;
+use super::stream::{handle_stream, Liveness};
+use super::{Interface, InterfaceContext, InterfaceState};
+
+/// How a [`TcpClient`] keeps its connection open and notices when it died
+/// silently, e.g. because a NAT on the way dropped it.
+#[derive(Debug, Clone, Copy)]
+pub struct TcpKeepalive {
+    /// Idle time before the OS starts probing the connection, `None` turns
+    /// TCP keepalive off.
+    pub idle: Option<Duration>,
+    /// Time between two probes.
+    pub interval: Duration,
+    /// Unanswered probes after which the OS drops the connection.
+    pub retries: u32,
+    /// An empty frame is sent after this long without sending anything, for
+    /// networks which filter keepalive probes.
+    pub ping_interval: Option<Duration>,
+    /// The connection is reopened after this long without receiving
+    /// anything. Only useful if the peer pings as well.
+    pub rx_timeout: Option<Duration>,
+}
+
+impl Default for TcpKeepalive {
+    /// Probes as Python Reticulum does, a dead connection is dropped after
+    /// about 30 seconds.
+    fn default() -> Self {
+        Self {
+            idle: Some(Duration::from_secs(5)),
+            interval: Duration::from_secs(2),
+            retries: 12,
+            ping_interval: Some(Duration::from_secs(30)),
+            rx_timeout: None,
+        }
+    }
+}
+
+impl TcpKeepalive {
+    fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = socket2::SockRef::from(stream);
+
+        let Some(idle) = self.idle else {
+            return socket.set_keepalive(false);
+        };
+
+        let params = socket2::TcpKeepalive::new().with_time(idle);
+        #[cfg(any(
+            target_os = "android",
+            target_os = "freebsd",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "windows",
+        ))]
+        let params = params.with_interval(self.interval).with_retries(self.retries);
+        socket.set_tcp_keepalive(&params)?;
+
+        // Unacknowledged data fails the connection within the same time
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        socket.set_tcp_user_timeout(Some(idle + self.interval * self.retries))?;
+
+        Ok(())
+    }
+
+    fn liveness(&self) -> Liveness {
+        Liveness {
+            ping_interval: self.ping_interval,
+            rx_timeout: self.rx_timeout,
+        }
+    }
+}
+
+/// Time each address of the target gets to accept the connection, before
+/// the next one is tried, see [`TcpClient::set_connect_timeout`].
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Orders the addresses a host resolved to by alternating between IPv6 and
+/// IPv4, starting with the family of the first one, so a broken network of
+/// one family doesn't hold up the other for long.
+fn connect_order(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+
+    let first_v6 = first.is_ipv6();
+    let (mut first_family, mut other_family): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|addr| addr.is_ipv6() == first_v6);
+    first_family.reverse();
+    other_family.reverse();
+
+    let mut ordered = Vec::with_capacity(first_family.len() + other_family.len());
+    while !first_family.is_empty() || !other_family.is_empty() {
+        ordered.extend(first_family.pop());
+        ordered.extend(other_family.pop());
+    }
+
+    ordered
+}
+
+/// Connects to the first of `addrs` which accepts the connection in time.
+async fn connect_any(addrs: &[SocketAddr], timeout: Duration) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "host resolved to no address");
+
+    for addr in addrs {
+        match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => {
+                log::debug!("tcp_client: couldn't connect to {}: {}", addr, err);
+                last_err = err;
+            }
+            Err(_) => {
+                log::debug!("tcp_client: {} didn't answer in time", addr);
+                last_err = io::Error::new(io::ErrorKind::TimedOut, "connection timed out");
+            }
+        }
+    }
 
-// TODO: Configure via features
-const PACKET_TRACE: bool = false;
+    Err(last_err)
+}
+
+/// Resolves `addr` anew and tries all of its addresses, so targets behind
+/// round-robin DNS or with changing addresses are found again.
+async fn connect(addr: &str, timeout: Duration) -> io::Result<TcpStream> {
+    let addrs = tokio::net::lookup_host(addr).await?.collect();
+    connect_any(&connect_order(addrs), timeout).await
+}
 
 pub struct TcpClient {
     addr: String,
     stream: Option<TcpStream>,
+    kiss_framing: bool,
+    keepalive: TcpKeepalive,
+    backoff: BackoffConfig,
+    connect_timeout: Duration,
 }
 
 impl TcpClient {
@@ -30,6 +152,10 @@ impl TcpClient {
         Self {
             addr: addr.into(),
             stream: None,
+            kiss_framing: false,
+            keepalive: TcpKeepalive::default(),
+            backoff: BackoffConfig::default(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
         }
     }
 
@@ -37,14 +163,46 @@ impl TcpClient {
         Self {
             addr: addr.into(),
             stream: Some(stream),
+            kiss_framing: false,
+            keepalive: TcpKeepalive::default(),
+            backoff: BackoffConfig::default(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
         }
     }
 
+    /// Use KISS instead of HDLC framing, for TNC software attached over TCP.
+    pub fn set_kiss_framing(mut self, kiss_framing: bool) -> Self {
+        self.kiss_framing = kiss_framing;
+        self
+    }
+
+    pub fn set_keepalive(mut self, keepalive: TcpKeepalive) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Time each address of the target gets to accept the connection. A
+    /// host which doesn't answer counts as a failed attempt once all its
+    /// addresses timed out.
+    pub fn set_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
     pub async fn spawn(context: InterfaceContext<TcpClient>) {
         let iface_stop = context.channel.stop.clone();
         let addr = { context.inner.lock().unwrap().addr.clone() };
         let iface_address = context.channel.address;
         let mut stream = { context.inner.lock().unwrap().stream.take() };
+        let kiss_framing = { context.inner.lock().unwrap().kiss_framing };
+        let keepalive = { context.inner.lock().unwrap().keepalive };
+        let connect_timeout = { context.inner.lock().unwrap().connect_timeout };
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
 
         let (rx_channel, tx_channel) = context.channel.split();
         let tx_channel = Arc::new(tokio::sync::Mutex::new(tx_channel));
@@ -71,8 +229,11 @@ impl TcpClient {
                         Some(_) = tx_channel.recv() => {
                             continue;
                         }
-                        result = TcpStream::connect(addr.clone()) => {
-                            result.map_err(|_| RnsError::ConnectionError)
+                        result = connect(&addr, connect_timeout) => {
+                            result.map_err(|err| {
+                                log::debug!("tcp_client: connecting to <{}> failed: {}", addr, err);
+                                RnsError::ConnectionError
+                            })
                         }
                     }
                 }
@@ -80,7 +241,15 @@ impl TcpClient {
 
             if stream.is_err() {
                 log::info!("tcp_client: couldn't connect to <{}>", addr);
-                let retry_at = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+                let Some(delay) = backoff.failed(&context.reporter) else {
+                    log::warn!(
+                        "tcp_client: giving up on <{}> after {} attempts",
+                        addr,
+                        backoff.attempts()
+                    );
+                    break;
+                };
+                let retry_at = tokio::time::Instant::now() + delay;
 
                 loop {
                     let mut tx_channel = tx_channel.lock().await;
@@ -99,136 +268,44 @@ impl TcpClient {
                 continue;
             }
 
-            let cancel = context.cancel.clone();
-            let stop = CancellationToken::new();
-
             let stream = stream.unwrap();
-            let (read_stream, write_stream) = stream.into_split();
-
-            log::info!("tcp_client connected to <{}>", addr);
-
-            const BUFFER_SIZE: usize = core::mem::size_of::<Packet>() * 2;
-
-            // Start receive task
-            let rx_task = {
-                let cancel = cancel.clone();
-                let stop = stop.clone();
-                let mut stream = read_stream;
-                let rx_channel = rx_channel.clone();
-
-                tokio::spawn(async move {
-                    let mut hdlc_rx_buffer = [0u8; BUFFER_SIZE];
-                    let mut rx_buffer = [0u8; BUFFER_SIZE + (BUFFER_SIZE / 2)];
-                    let mut tcp_buffer = [0u8; (BUFFER_SIZE * 16)];
-
-                    loop {
-                        tokio::select! {
-                            _ = cancel.cancelled() => {
-                                    break;
-                            }
-                            _ = stop.cancelled() => {
-                                    break;
-                            }
-                            result = stream.read(&mut tcp_buffer[..]) => {
-                                    match result {
-                                        Ok(0) => {
-                                            log::warn!("tcp_client: connection closed");
-                                            stop.cancel();
-                                            break;
-                                        }
-                                        Ok(n) => {
-                                            // TCP stream may contain several or partial HDLC frames
-                                            for byte in &tcp_buffer[..n] {
-                                                // Push new byte from the end of buffer
-                                                rx_buffer[BUFFER_SIZE-1] = *byte;
-
-                                                // Check if it is contains a HDLC frame
-                                                let frame = Hdlc::find(&rx_buffer[..]);
-                                                if let Some(frame) = frame {
-                                                    // Decode HDLC frame and deserialize packet
-                                                    let frame_buffer = &mut rx_buffer[frame.0..frame.1+1];
-                                                    let mut output = OutputBuffer::new(&mut hdlc_rx_buffer[..]);
-                                                    if Hdlc::decode(frame_buffer, &mut output).is_ok() {
-                                                        if let Ok(packet) = Packet::deserialize(&mut InputBuffer::new(output.as_slice())) {
-                                                            if PACKET_TRACE {
-                                                                log::trace!("tcp_client: rx << ({}) {}", iface_address, packet);
-                                                            }
-                                                            let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
-                                                        } else {
-                                                            log::warn!("tcp_client: couldn't decode packet");
-                                                        }
-                                                    } else {
-                                                        log::warn!("tcp_client: couldn't decode hdlc frame");
-                                                    }
-
-                                                    // Remove current HDLC frame data
-                                                    frame_buffer.fill(0);
-                                                } else {
-                                                    // Move data left
-                                                    rx_buffer.copy_within(1.., 0);
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            log::warn!("tcp_client: connection error {}", e);
-                                            break;
-                                        }
-                                    }
-                                },
-                        };
-                    }
-                })
-            };
 
-            // Start transmit task
-            let tx_task = {
-                let cancel = cancel.clone();
-                let tx_channel = tx_channel.clone();
-                let mut stream = write_stream;
-
-                tokio::spawn(async move {
-                    loop {
-                        if stop.is_cancelled() {
-                            break;
-                        }
+            match stream.peer_addr() {
+                Ok(peer) => log::info!("tcp_client connected to <{}> at {}", addr, peer),
+                Err(_) => log::info!("tcp_client connected to <{}>", addr),
+            }
+            backoff.connected(&context.reporter);
 
-                        let mut hdlc_tx_buffer = [0u8; BUFFER_SIZE];
-                        let mut tx_buffer = [0u8; BUFFER_SIZE];
-
-                        let mut tx_channel = tx_channel.lock().await;
-
-                        tokio::select! {
-                            _ = cancel.cancelled() => {
-                                    break;
-                            }
-                            _ = stop.cancelled() => {
-                                    break;
-                            }
-                            Some(message) = tx_channel.recv() => {
-                                let packet = message.packet;
-                                if PACKET_TRACE {
-                                    log::trace!("tcp_client: tx >> ({}) {}", iface_address, packet);
-                                }
-                                let mut output = OutputBuffer::new(&mut tx_buffer);
-                                if packet.serialize(&mut output).is_ok() {
-
-                                    let mut hdlc_output = OutputBuffer::new(&mut hdlc_tx_buffer[..]);
-
-                                    if Hdlc::encode(output.as_slice(), &mut hdlc_output).is_ok() {
-                                        let _ = stream.write_all(hdlc_output.as_slice()).await;
-                                        let _ = stream.flush().await;
-                                    }
-                                }
-                            }
-                        };
-                    }
-                })
-            };
+            if let Err(err) = keepalive.apply(&stream) {
+                log::warn!("tcp_client: couldn't enable keepalive for <{}>: {}", addr, err);
+            }
 
-            tx_task.await.unwrap();
-            rx_task.await.unwrap();
+            if kiss_framing {
+                handle_stream::<KissCodec, _>(
+                    "tcp_client",
+                    stream,
+                    iface_address,
+                    rx_channel.clone(),
+                    tx_channel.clone(),
+                    context.cancel.clone(),
+                    keepalive.liveness(),
+                )
+                .await;
+            } else {
+                handle_stream::<HdlcCodec, _>(
+                    "tcp_client",
+                    stream,
+                    iface_address,
+                    rx_channel.clone(),
+                    tx_channel.clone(),
+                    context.cancel.clone(),
+                    keepalive.liveness(),
+                )
+                .await;
+            }
 
             log::info!("tcp_client: disconnected from <{}>", addr);
+            context.reporter.report(InterfaceState::Disconnected);
         }
 
         iface_stop.cancel();
@@ -240,3 +317,38 @@ impl Interface for TcpClient {
         2048
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn alternates_address_families() {
+        let v4 = |port| SocketAddr::from(([127, 0, 0, 1], port));
+        let v6 = |port| SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], port));
+
+        assert_eq!(
+            connect_order(vec![v6(1), v6(2), v6(3), v4(4), v4(5)]),
+            vec![v6(1), v4(4), v6(2), v4(5), v6(3)]
+        );
+        assert_eq!(connect_order(vec![v4(1), v4(2), v6(3)]), vec![v4(1), v6(3), v4(2)]);
+        assert!(connect_order(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn falls_over_to_next_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reachable = listener.local_addr().unwrap();
+
+        // A port nobody listens on refuses the connection
+        let refused = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let stream = connect_any(&[refused, reachable], Duration::from_secs(1)).await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), reachable);
+
+        assert!(connect_any(&[refused], Duration::from_secs(1)).await.is_err());
+        assert!(connect_any(&[], Duration::from_secs(1)).await.is_err());
+    }
+}
@@ -1,16 +1,21 @@
 use alloc::string::String;
 use std::sync::Arc;
 
-use tokio::net::TcpListener;
-
-use crate::error::RnsError;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
+use super::backoff::{Backoff, BackoffConfig};
+use super::socket::SocketOptions;
 use super::tcp_client::TcpClient;
 use super::{Interface, InterfaceContext, InterfaceManager};
 
 pub struct TcpServer {
-    addr: String,
+    addrs: Vec<String>,
     iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>,
+    kiss_framing: bool,
+    backoff: BackoffConfig,
+    socket_options: SocketOptions,
 }
 
 impl TcpServer {
@@ -19,15 +24,68 @@ impl TcpServer {
         iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>,
     ) -> Self {
         Self {
-            addr: addr.into(),
+            addrs: vec![addr.into()],
             iface_manager,
+            kiss_framing: false,
+            backoff: BackoffConfig::default(),
+            socket_options: SocketOptions::default(),
+        }
+    }
+
+    /// Listens on `addr` as well, e.g. on `[::]:4242` next to
+    /// `0.0.0.0:4242` with IPv6 only sockets.
+    pub fn add_bind_addr<T: Into<String>>(mut self, addr: T) -> Self {
+        self.addrs.push(addr.into());
+        self
+    }
+
+    pub fn set_socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Use KISS instead of HDLC framing on all accepted connections.
+    pub fn set_kiss_framing(mut self, kiss_framing: bool) -> Self {
+        self.kiss_framing = kiss_framing;
+        self
+    }
+
+    /// Delays between attempts to bind the listening socket.
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Binds all addresses, or none if one fails.
+    async fn bind(addrs: &[String], options: &SocketOptions) -> Option<Vec<TcpListener>> {
+        let mut listeners = Vec::new();
+
+        for addr in addrs {
+            match options.bind_tcp(addr).await {
+                Ok(listener) => listeners.push(listener),
+                Err(err) => {
+                    log::warn!("tcp_server: couldn't bind to <{}>: {}", addr, err);
+                    return None;
+                }
+            }
         }
+
+        Some(listeners)
     }
 
     pub async fn spawn(context: InterfaceContext<Self>) {
-        let addr = { context.inner.lock().unwrap().addr.clone() };
+        let addrs = { context.inner.lock().unwrap().addrs.clone() };
+        let addr = addrs.join(", ");
+        let socket_options = { context.inner.lock().unwrap().socket_options };
 
         let iface_manager = { context.inner.lock().unwrap().iface_manager.clone() };
+        let kiss_framing = { context.inner.lock().unwrap().kiss_framing };
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
+        let iface_stop = context.channel.stop.clone();
+
+        // Clients inherit the mode and groups of the server interface
+        let mode = context.channel.mode;
+        let server_address = context.channel.address;
 
         let (_, tx_channel) = context.channel.split();
         let tx_channel = Arc::new(tokio::sync::Mutex::new(tx_channel));
@@ -37,19 +95,25 @@ impl TcpServer {
                 break;
             }
 
-            let listener = TcpListener::bind(addr.clone())
-                .await
-                .map_err(|_| RnsError::ConnectionError);
-
-            if listener.is_err() {
-                log::warn!("tcp_server: couldn't bind to <{}>", addr);
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            let Some(listeners) = Self::bind(&addrs, &socket_options).await else {
+                let Some(delay) = backoff.failed(&context.reporter) else {
+                    log::warn!(
+                        "tcp_server: giving up on <{}> after {} attempts",
+                        addr,
+                        backoff.attempts()
+                    );
+                    iface_stop.cancel();
+                    break;
+                };
+                tokio::time::sleep(delay).await;
                 continue;
-            }
+            };
 
             log::info!("tcp_server: listen on <{}>", addr);
+            backoff.connected(&context.reporter);
 
-            let listener = listener.unwrap();
+            let accept_stop = CancellationToken::new();
+            let mut clients = Self::accept_all(listeners, accept_stop.clone());
 
             let tx_task = {
                 let cancel = context.cancel.clone();
@@ -86,8 +150,8 @@ impl TcpServer {
                         break;
                     }
 
-                    client = listener.accept() => {
-                        if let Ok(client) = client {
+                    client = clients.recv() => {
+                        if let Some(client) = client {
                             log::info!(
                                 "tcp_server: new client <{}> connected to <{}>",
                                 client.1,
@@ -96,18 +160,62 @@ impl TcpServer {
 
                             let mut iface_manager = iface_manager.lock().await;
 
-                            iface_manager.spawn(
-                                TcpClient::new_from_stream(client.1.to_string(), client.0),
-                                TcpClient::spawn,
+                            // Named after the peer and the server, like "Client on <server>" in
+                            // Python Reticulum
+                            let name = format!(
+                                "{} on {}",
+                                client.1,
+                                iface_manager.display_name(&server_address)
                             );
+                            let address = iface_manager.spawn_named(
+                                name,
+                                TcpClient::new_from_stream(client.1.to_string(), client.0)
+                                    .set_kiss_framing(kiss_framing),
+                                mode,
+                                TcpClient::spawn,
+                            ).address();
+                            let groups = iface_manager.groups(&server_address);
+                            iface_manager.set_groups(&address, groups);
                         }
                     }
                 }
             }
 
+            accept_stop.cancel();
             let _ = tokio::join!(tx_task);
         }
     }
+
+    /// Accepts clients on all `listeners` until `stop` is cancelled.
+    fn accept_all(
+        listeners: Vec<TcpListener>,
+        stop: CancellationToken,
+    ) -> mpsc::Receiver<(TcpStream, std::net::SocketAddr)> {
+        let (clients_tx, clients_rx) = mpsc::channel(1);
+
+        for listener in listeners {
+            let clients_tx = clients_tx.clone();
+            let stop = stop.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = stop.cancelled() => break,
+                        client = listener.accept() => match client {
+                            Ok(client) => {
+                                if clients_tx.send(client).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(err) => log::warn!("tcp_server: couldn't accept client: {}", err),
+                        },
+                    }
+                }
+            });
+        }
+
+        clients_rx
+    }
 }
 
 impl Interface for TcpServer {
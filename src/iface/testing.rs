@@ -0,0 +1,321 @@
+//! Interfaces for testing how the stack copes with bad links.
+//!
+//! [`Degraded`] wraps any interface and delays, drops and reorders the
+//! packets it sends and receives, so retries, windows and timeouts can be
+//! exercised against realistic conditions:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use reticulum::iface::tcp_client::TcpClient;
+//! # use reticulum::iface::testing::Degraded;
+//! # use reticulum::iface::InterfaceManager;
+//! # async fn example(manager: &mut InterfaceManager) {
+//! let client = Degraded::new(TcpClient::new("127.0.0.1:4242"))
+//!     .set_delay(Duration::from_millis(300))
+//!     .set_jitter(Duration::from_millis(100))
+//!     .set_loss(0.05);
+//! manager.spawn(client, |context| Degraded::run(context, TcpClient::spawn));
+//! # }
+//! ```
+//!
+//! Only the traffic of the wrapped interface itself is degraded, clients
+//! accepted by a wrapped server are interfaces of their own.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand_core::{OsRng, RngCore};
+use tokio::sync::mpsc;
+
+use crate::runtime::{self, Instant};
+
+use super::{InterfaceChannel, Interface, InterfaceContext};
+
+/// Conditions of a degraded interface, the same in both directions.
+#[derive(Debug, Clone, Copy, Default)]
+struct Conditions {
+    delay: Duration,
+    jitter: Duration,
+    loss: f64,
+    reorder: f64,
+    reorder_hold: Duration,
+}
+
+impl Conditions {
+    /// When a packet passed at `now` comes out, `None` if it is lost.
+    fn schedule(&self, now: Instant, rng: &mut Rng) -> Option<Instant> {
+        if rng.chance(self.loss) {
+            return None;
+        }
+
+        let mut at = now + self.delay + self.jitter.mul_f64(rng.fraction());
+        if rng.chance(self.reorder) {
+            at += self.reorder_hold;
+        }
+
+        Some(at)
+    }
+}
+
+/// Predictable source of the conditions, xorshift64.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Zero would stay zero
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn fraction(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && self.fraction() < probability
+    }
+}
+
+/// Wraps interface `T` and degrades its traffic, see the [module](self).
+pub struct Degraded<T> {
+    inner: Option<T>,
+    conditions: Conditions,
+    seed: u64,
+}
+
+impl<T: Interface> Interface for Degraded<T> {
+    fn mtu() -> usize {
+        T::mtu()
+    }
+}
+
+impl<T: Interface + Send + 'static> Degraded<T> {
+    /// Wraps `inner`, which works as usual until conditions are set.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: Some(inner),
+            conditions: Conditions::default(),
+            seed: OsRng.next_u64(),
+        }
+    }
+
+    /// Delays every packet by `delay`.
+    pub fn set_delay(mut self, delay: Duration) -> Self {
+        self.conditions.delay = delay;
+        self
+    }
+
+    /// Delays every packet by up to `jitter` more, packets close to each
+    /// other may overtake one another.
+    pub fn set_jitter(mut self, jitter: Duration) -> Self {
+        self.conditions.jitter = jitter;
+        self
+    }
+
+    /// Drops packets with `probability` from 0 to 1.
+    pub fn set_loss(mut self, probability: f64) -> Self {
+        self.conditions.loss = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Holds back packets with `probability` from 0 to 1 for `hold` more,
+    /// so later ones overtake them.
+    pub fn set_reordering(mut self, probability: f64, hold: Duration) -> Self {
+        self.conditions.reorder = probability.clamp(0.0, 1.0);
+        self.conditions.reorder_hold = hold;
+        self
+    }
+
+    /// Seeds the losses, delays and reorderings, which are random by
+    /// default. The same seed and traffic degrade the same packets.
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Drives the wrapped interface with `worker`, its spawn function, and
+    /// passes its traffic through the conditions. Returns when the worker
+    /// does.
+    pub async fn run<F, R>(context: InterfaceContext<Self>, worker: F)
+    where
+        F: FnOnce(InterfaceContext<T>) -> R,
+        R: Future<Output = ()>,
+    {
+        let (inner, conditions, seed) = {
+            let mut degraded = context.inner.lock().unwrap();
+            (degraded.inner.take(), degraded.conditions, degraded.seed)
+        };
+        let Some(inner) = inner else {
+            log::error!("degraded({}): interface already runs", context.channel.address);
+            return;
+        };
+
+        let address = context.channel.address;
+        let mode = context.channel.mode;
+        let stop = context.channel.stop.clone();
+        let (rx_channel, tx_channel) = context.channel.split();
+
+        let (inner_rx_send, inner_rx_recv) = InterfaceChannel::make_rx_channel(1);
+        let (inner_tx_send, inner_tx_recv) = InterfaceChannel::make_tx_channel(1);
+
+        let mut channel = InterfaceChannel::new(inner_rx_send, inner_tx_recv, address, stop);
+        channel.mode = mode;
+
+        let inner_context = InterfaceContext {
+            inner: Arc::new(Mutex::new(inner)),
+            channel,
+            cancel: context.cancel,
+            reporter: context.reporter,
+        };
+
+        log::debug!("degraded({}): {:?}", address, conditions);
+
+        tokio::select! {
+            _ = worker(inner_context) => {}
+            _ = relay(tx_channel, inner_tx_send, conditions, Rng::new(seed)) => {}
+            _ = relay(inner_rx_recv, rx_channel, conditions, Rng::new(!seed)) => {}
+        }
+    }
+}
+
+/// Passes messages from `input` to `output` under `conditions`. Returns
+/// once either side closed.
+async fn relay<M>(
+    mut input: mpsc::Receiver<M>,
+    output: mpsc::Sender<M>,
+    conditions: Conditions,
+    mut rng: Rng,
+) {
+    let mut queue = BTreeMap::new();
+    let mut sequence = 0u64;
+
+    loop {
+        let next = queue.first_key_value().map(|((at, _), _)| *at);
+
+        tokio::select! {
+            message = input.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                if let Some(at) = conditions.schedule(Instant::now(), &mut rng) {
+                    queue.insert((at, sequence), message);
+                    sequence += 1;
+                }
+            }
+            _ = runtime::sleep_until(next.unwrap_or_else(Instant::now)), if next.is_some() => {
+                let Some((_, message)) = queue.pop_first() else {
+                    continue;
+                };
+                if output.send(message).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::iface::{InterfaceManager, RxMessage, TxMessage, TxMessageType};
+    use crate::packet::{Packet, PacketDataBuffer};
+
+    use super::*;
+
+    /// Receives whatever it sends.
+    struct Loopback;
+
+    impl Interface for Loopback {
+        fn mtu() -> usize {
+            500
+        }
+    }
+
+    impl Loopback {
+        async fn spawn(context: InterfaceContext<Self>) {
+            let address = context.channel.address;
+            let (rx_channel, mut tx_channel) = context.channel.split();
+
+            while let Some(message) = tx_channel.recv().await {
+                let received = RxMessage { address, packet: message.packet };
+                if rx_channel.send(received).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn packet(number: u8) -> Packet {
+        Packet {
+            data: PacketDataBuffer::new_from_slice(&[number]),
+            ..Default::default()
+        }
+    }
+
+    async fn send_all(manager: &InterfaceManager, address: crate::hash::AddressHash, count: u8) {
+        for number in 0..count {
+            let tx_type = TxMessageType::Direct(address);
+            manager.send(TxMessage { tx_type, packet: packet(number) }).await;
+            runtime::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn delays_and_reorders_packets() {
+        let mut manager = InterfaceManager::new(16);
+        let iface = Degraded::new(Loopback)
+            .set_delay(Duration::from_millis(100))
+            .set_reordering(0.5, Duration::from_millis(200))
+            .set_seed(7);
+        let handle = manager.spawn(iface, |context| Degraded::run(context, Loopback::spawn));
+
+        let receiver = manager.receiver();
+        let start = Instant::now();
+        send_all(&manager, handle.address(), 10).await;
+
+        let mut received = Vec::new();
+        while received.len() < 10 {
+            let message = receiver.lock().await.recv().await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(200));
+            received.push(message.packet.data.as_slice()[0]);
+        }
+
+        assert_ne!(received, (0..10).collect::<Vec<_>>());
+        received.sort();
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drops_lost_packets() {
+        let mut manager = InterfaceManager::new(16);
+        let iface = Degraded::new(Loopback).set_loss(0.5).set_seed(7);
+        let handle = manager.spawn(iface, |context| Degraded::run(context, Loopback::spawn));
+
+        let receiver = manager.receiver();
+        send_all(&manager, handle.address(), 40).await;
+        runtime::sleep(Duration::from_secs(1)).await;
+
+        let mut received = 0;
+        while receiver.lock().await.try_recv().is_ok() {
+            received += 1;
+        }
+        // Each direction loses half
+        assert!((2..=20).contains(&received), "{} of 40 packets came through", received);
+
+        let lossless = Degraded::new(Loopback).set_loss(0.0);
+        let handle = manager.spawn(lossless, |context| Degraded::run(context, Loopback::spawn));
+        send_all(&manager, handle.address(), 10).await;
+        runtime::sleep(Duration::from_secs(1)).await;
+        for _ in 0..10 {
+            assert!(receiver.lock().await.try_recv().is_ok());
+        }
+    }
+}
@@ -4,20 +4,22 @@ use tokio::net::UdpSocket;
 use tokio_util::sync::CancellationToken;
 
 use crate::buffer::{InputBuffer, OutputBuffer};
-use crate::error::RnsError;
+use crate::diagnostics;
 use crate::iface::RxMessage;
 use crate::packet::Packet;
 use crate::serde::Serialize;
+use crate::trace::{trace_packet, TraceCategory};
 
-use super::{Interface, InterfaceContext};
-
-// TODO: Configure via features
-const PACKET_TRACE: bool = true;
+use super::backoff::{Backoff, BackoffConfig};
+use super::socket::SocketOptions;
+use super::{Interface, InterfaceContext, InterfaceState};
 
 pub struct UdpInterface {
-    bind_addr: String,
+    bind_addrs: Vec<String>,
     forward_addr: Option<String>,
-    broadcast: bool
+    broadcast: bool,
+    backoff: BackoffConfig,
+    socket_options: SocketOptions,
 }
 
 impl UdpInterface {
@@ -27,16 +29,56 @@ impl UdpInterface {
         broadcast: bool
     ) -> Self {
         Self {
-            bind_addr: bind_addr.into(),
+            bind_addrs: vec![bind_addr.into()],
             forward_addr: forward_addr.map(Into::into),
-            broadcast
+            broadcast,
+            backoff: BackoffConfig::default(),
+            socket_options: SocketOptions::default(),
+        }
+    }
+
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Receives on `addr` as well. Packets are forwarded from the first
+    /// socket of the same address family as the forward address.
+    pub fn add_bind_addr<T: Into<String>>(mut self, addr: T) -> Self {
+        self.bind_addrs.push(addr.into());
+        self
+    }
+
+    pub fn set_socket_options(mut self, options: SocketOptions) -> Self {
+        self.socket_options = options;
+        self
+    }
+
+    /// Binds all addresses, or none if one fails.
+    async fn bind(addrs: &[String], options: &SocketOptions) -> Option<Vec<Arc<UdpSocket>>> {
+        let mut sockets = Vec::new();
+
+        for addr in addrs {
+            match options.bind_udp(addr).await {
+                Ok(socket) => sockets.push(Arc::new(socket)),
+                Err(err) => {
+                    log::info!("udp_interface: couldn't bind to <{}>: {}", addr, err);
+                    return None;
+                }
+            }
         }
+
+        Some(sockets)
     }
 
     pub async fn spawn(context: InterfaceContext<Self>) {
-        let bind_addr = { context.inner.lock().unwrap().bind_addr.clone() };
+        let bind_addrs = { context.inner.lock().unwrap().bind_addrs.clone() };
+        let bind_addr = bind_addrs.join(", ");
         let forward_addr = { context.inner.lock().unwrap().forward_addr.clone() };
+        let socket_options = { context.inner.lock().unwrap().socket_options };
         let iface_address = context.channel.address;
+        let iface_stop = context.channel.stop.clone();
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
 
         let (rx_channel, tx_channel) = context.channel.split();
         let tx_channel = Arc::new(tokio::sync::Mutex::new(tx_channel));
@@ -46,36 +88,40 @@ impl UdpInterface {
                 break;
             }
 
-            let socket = UdpSocket::bind(bind_addr.clone())
-                .await
-                .map_err(|_| RnsError::ConnectionError);
-
-            if socket.is_err() {
-                log::info!("udp_interface: couldn't bind to <{}>", bind_addr);
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            let Some(sockets) = Self::bind(&bind_addrs, &socket_options).await else {
+                let Some(delay) = backoff.failed(&context.reporter) else {
+                    log::warn!(
+                        "udp_interface: giving up on <{}> after {} attempts",
+                        bind_addr,
+                        backoff.attempts()
+                    );
+                    iface_stop.cancel();
+                    break;
+                };
+                tokio::time::sleep(delay).await;
                 continue;
-            }
+            };
 
             let cancel = context.cancel.clone();
             let stop = CancellationToken::new();
 
-            let socket = socket.unwrap();
-            let read_socket = Arc::new(socket);
-            let write_socket = read_socket.clone();
             if context.inner.lock().unwrap().broadcast {
-                let _ = write_socket.set_broadcast(true)
-                    .map_err(|err| log::error!("error setting broadcast: {err}"));
+                for socket in &sockets {
+                    let _ = socket.set_broadcast(true)
+                        .map_err(|err| log::error!("error setting broadcast: {err}"));
+                }
             }
 
             log::info!("udp_interface bound to <{}>", bind_addr);
+            backoff.connected(&context.reporter);
 
             const BUFFER_SIZE: usize = core::mem::size_of::<Packet>() * 3;
 
-            // Start receive task
-            let rx_task = {
+            // Start receive tasks
+            let rx_tasks: Vec<_> = sockets.iter().map(|socket| {
                 let cancel = cancel.clone();
                 let stop = stop.clone();
-                let socket = read_socket;
+                let socket = socket.clone();
                 let rx_channel = rx_channel.clone();
 
                 tokio::spawn(async move {
@@ -98,16 +144,15 @@ impl UdpInterface {
                                     }
                                     Ok((n, _in_addr)) => {
                                         if let Ok(packet) = Packet::deserialize(&mut InputBuffer::new(&rx_buffer[..n])) {
-                                            if PACKET_TRACE {
-                                                log::trace!("udp_interface: rx << ({}) {}", iface_address, packet);
-                                            }
+                                            trace_packet!(TraceCategory::InterfaceRx, "udp_interface: rx << ({}) {}", iface_address, packet);
                                             let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
                                         } else {
-                                            log::warn!("udp_interface: couldn't decode packet");
+                                            diagnostics::packet_undecodable("udp_interface", &rx_buffer[..n]);
                                         }
                                     }
                                     Err(e) => {
                                         log::warn!("udp_interface: connection error {}", e);
+                                        stop.cancel();
                                         break;
                                     }
                                 }
@@ -115,14 +160,14 @@ impl UdpInterface {
                         };
                     }
                 })
-            };
+            }).collect();
 
             if let Some(forward_addr) = forward_addr.clone() {
                 // Start transmit task
                 let tx_task = {
                     let cancel = cancel.clone();
+                    let stop = stop.clone();
                     let tx_channel = tx_channel.clone();
-                    let socket = write_socket;
 
                     tokio::spawn(async move {
                         loop {
@@ -143,12 +188,10 @@ impl UdpInterface {
                                 }
                                 Some(message) = tx_channel.recv() => {
                                     let packet = message.packet;
-                                    if PACKET_TRACE {
-                                        log::trace!("udp_interface: tx >> ({}) {}", iface_address, packet);
-                                    }
+                                    trace_packet!(TraceCategory::InterfaceTx, "udp_interface: tx >> ({}) {}", iface_address, packet);
                                     let mut output = OutputBuffer::new(&mut tx_buffer);
                                     if packet.serialize(&mut output).is_ok() {
-                                        let _ = socket.send_to(output.as_slice(), &forward_addr).await;
+                                        Self::forward(&sockets, &forward_addr, output.as_slice()).await;
                                     }
                                 }
                             };
@@ -158,11 +201,34 @@ impl UdpInterface {
                 tx_task.await.unwrap();
             }
 
-            rx_task.await.unwrap();
+            for rx_task in rx_tasks {
+                rx_task.await.unwrap();
+            }
 
             log::info!("udp_interface <{}>: closed", bind_addr);
+            context.reporter.report(InterfaceState::Disconnected);
         }
     }
+
+    /// Sends `data` to `forward_addr` from the first socket of its address
+    /// family.
+    async fn forward(sockets: &[Arc<UdpSocket>], forward_addr: &str, data: &[u8]) {
+        let Some(target) = tokio::net::lookup_host(forward_addr)
+            .await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+        else {
+            log::debug!("udp_interface: couldn't resolve <{}>", forward_addr);
+            return;
+        };
+
+        let socket = sockets
+            .iter()
+            .find(|socket| socket.local_addr().is_ok_and(|addr| addr.is_ipv6() == target.is_ipv6()))
+            .unwrap_or(&sockets[0]);
+
+        let _ = socket.send_to(data, target).await;
+    }
 }
 
 impl Interface for UdpInterface {
@@ -0,0 +1,30 @@
+//! Packets over WebSocket, one packet per binary message.
+//!
+//! Browsers can't open raw sockets, so web apps join a network through a
+//! node serving [`WebSocketServer`], and connect to it with
+//! [`WebSocketClient`] when built for `wasm32`. WebSocket messages are framed
+//! already, so no HDLC is used.
+
+#[cfg(target_arch = "wasm32")]
+mod client;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+
+#[cfg(target_arch = "wasm32")]
+pub use client::WebSocketClient;
+#[cfg(not(target_arch = "wasm32"))]
+pub use server::WebSocketServer;
+
+use alloc::vec::Vec;
+
+use crate::packet::Packet;
+
+pub const WEBSOCKET_MTU: usize = 2048;
+
+fn encode(packet: &Packet) -> Option<Vec<u8>> {
+    packet.to_wire_vec().ok()
+}
+
+fn decode(message: &[u8]) -> Option<Packet> {
+    Packet::from_wire(message).ok()
+}
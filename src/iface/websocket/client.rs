@@ -0,0 +1,211 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use js_sys::{ArrayBuffer, Uint8Array};
+use tokio::sync::mpsc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+use crate::diagnostics;
+use crate::iface::backoff::{Backoff, BackoffConfig};
+use crate::iface::{Interface, InterfaceContext, InterfaceState, RxMessage};
+use crate::runtime;
+use crate::trace::{trace_packet, TraceCategory};
+
+use super::{decode, encode, WEBSOCKET_MTU};
+
+/// Connects to a [`WebSocketServer`](super::WebSocketServer) from the browser
+/// and reconnects whenever the connection is lost.
+pub struct WebSocketClient {
+    url: String,
+    backoff: BackoffConfig,
+}
+
+impl WebSocketClient {
+    /// `url` is the `ws://` or `wss://` address of the server.
+    pub fn new<T: Into<String>>(url: T) -> Self {
+        Self {
+            url: url.into(),
+            backoff: BackoffConfig::default(),
+        }
+    }
+
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub async fn spawn(context: InterfaceContext<WebSocketClient>) {
+        // The browser socket can't leave the JavaScript thread, the
+        // connection runs as a task of its own on the event loop.
+        runtime::spawn(run(context));
+    }
+}
+
+impl Interface for WebSocketClient {
+    fn mtu() -> usize {
+        WEBSOCKET_MTU
+    }
+}
+
+enum SocketEvent {
+    Open,
+    Message(Vec<u8>),
+    Closed,
+}
+
+/// An open browser socket with the callbacks which feed its events into a
+/// channel. Closes the socket when dropped.
+struct Connection {
+    socket: WebSocket,
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl Connection {
+    fn open(url: &str, events: mpsc::UnboundedSender<SocketEvent>) -> Option<Self> {
+        let socket = WebSocket::new(url).ok()?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let on_open = {
+            let events = events.clone();
+            Closure::<dyn FnMut()>::new(move || {
+                let _ = events.send(SocketEvent::Open);
+            })
+        };
+
+        let on_message = {
+            let events = events.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                if let Ok(buffer) = event.data().dyn_into::<ArrayBuffer>() {
+                    let _ = events.send(SocketEvent::Message(Uint8Array::new(&buffer).to_vec()));
+                }
+            })
+        };
+
+        // Errors are always followed by a close event
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| {
+            let _ = events.send(SocketEvent::Closed);
+        });
+
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        Some(Self {
+            socket,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    fn send(&self, data: &[u8]) -> bool {
+        self.socket.send_with_u8_array(data).is_ok()
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+async fn run(context: InterfaceContext<WebSocketClient>) {
+    let url = { context.inner.lock().unwrap().url.clone() };
+    let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
+    let iface_address = context.channel.address;
+    let iface_stop = context.channel.stop.clone();
+    let cancel = context.cancel;
+    let reporter = context.reporter;
+
+    let (rx_channel, mut tx_channel) = context.channel.split();
+
+    'outer: loop {
+        if cancel.is_cancelled() {
+            break;
+        }
+
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+
+        match Connection::open(&url, events_tx) {
+            Some(connection) => {
+                let mut open = false;
+
+                loop {
+                    tokio::select! {
+                        _ = cancel.cancelled() => {
+                            break 'outer;
+                        }
+                        event = events.recv() => match event {
+                            Some(SocketEvent::Open) => {
+                                log::info!("websocket_client: connected to <{}>", url);
+                                backoff.connected(&reporter);
+                                open = true;
+                            }
+                            Some(SocketEvent::Message(data)) => {
+                                if let Some(packet) = decode(&data) {
+                                    trace_packet!(TraceCategory::InterfaceRx, "websocket_client: rx << ({}) {}", iface_address, packet);
+                                    let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
+                                } else {
+                                    diagnostics::packet_undecodable("websocket_client", &data);
+                                }
+                            }
+                            Some(SocketEvent::Closed) | None => break,
+                        },
+                        Some(message) = tx_channel.recv() => {
+                            // Packets sent before the socket is open are dropped
+                            if !open {
+                                continue;
+                            }
+
+                            let packet = message.packet;
+                            trace_packet!(TraceCategory::InterfaceTx, "websocket_client: tx >> ({}) {}", iface_address, packet);
+                            if let Some(data) = encode(&packet) {
+                                connection.send(&data);
+                            }
+                        }
+                    }
+                }
+
+                if open {
+                    log::info!("websocket_client: disconnected from <{}>", url);
+                    reporter.report(InterfaceState::Disconnected);
+                } else {
+                    log::info!("websocket_client: couldn't connect to <{}>", url);
+                }
+            }
+            None => log::info!("websocket_client: couldn't connect to <{}>", url),
+        }
+
+        let Some(delay) = backoff.failed(&reporter) else {
+            log::warn!(
+                "websocket_client: giving up on <{}> after {} attempts",
+                url,
+                backoff.attempts()
+            );
+            break;
+        };
+
+        // Keep draining packets while waiting to reconnect
+        let retry_at = runtime::Instant::now() + delay;
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    break 'outer;
+                }
+                Some(_) = tx_channel.recv() => {}
+                _ = runtime::sleep_until(retry_at) => {
+                    break;
+                }
+            }
+        }
+    }
+
+    iface_stop.cancel();
+}
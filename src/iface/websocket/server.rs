@@ -0,0 +1,225 @@
+use alloc::string::{String, ToString};
+use std::sync::Arc;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::diagnostics;
+use crate::error::RnsError;
+use crate::iface::backoff::{Backoff, BackoffConfig};
+use crate::iface::{Interface, InterfaceContext, InterfaceManager, RxMessage};
+use crate::trace::{trace_packet, TraceCategory};
+
+use super::{decode, encode, WEBSOCKET_MTU};
+
+/// Accepts WebSocket connections, every client becomes an interface of its own.
+pub struct WebSocketServer {
+    addr: String,
+    iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>,
+    backoff: BackoffConfig,
+}
+
+impl WebSocketServer {
+    pub fn new<T: Into<String>>(
+        addr: T,
+        iface_manager: Arc<tokio::sync::Mutex<InterfaceManager>>,
+    ) -> Self {
+        Self {
+            addr: addr.into(),
+            iface_manager,
+            backoff: BackoffConfig::default(),
+        }
+    }
+
+    /// Delays between attempts to bind the listening socket.
+    pub fn set_backoff(mut self, backoff: BackoffConfig) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub async fn spawn(context: InterfaceContext<Self>) {
+        let addr = { context.inner.lock().unwrap().addr.clone() };
+        let iface_manager = { context.inner.lock().unwrap().iface_manager.clone() };
+        let mut backoff = Backoff::new(context.inner.lock().unwrap().backoff);
+        let iface_stop = context.channel.stop.clone();
+
+        // Clients inherit the mode and groups of the server interface
+        let mode = context.channel.mode;
+        let server_address = context.channel.address;
+
+        let (_, tx_channel) = context.channel.split();
+        let tx_channel = Arc::new(tokio::sync::Mutex::new(tx_channel));
+
+        loop {
+            if context.cancel.is_cancelled() {
+                break;
+            }
+
+            let listener = TcpListener::bind(addr.clone())
+                .await
+                .map_err(|_| RnsError::ConnectionError);
+
+            let Ok(listener) = listener else {
+                log::warn!("websocket_server: couldn't bind to <{}>", addr);
+                let Some(delay) = backoff.failed(&context.reporter) else {
+                    log::warn!(
+                        "websocket_server: giving up on <{}> after {} attempts",
+                        addr,
+                        backoff.attempts()
+                    );
+                    iface_stop.cancel();
+                    break;
+                };
+                tokio::time::sleep(delay).await;
+                continue;
+            };
+
+            log::info!("websocket_server: listen on <{}>", addr);
+            backoff.connected(&context.reporter);
+
+            let tx_task = {
+                let cancel = context.cancel.clone();
+                let tx_channel = tx_channel.clone();
+
+                tokio::spawn(async move {
+                    loop {
+                        let mut tx_channel = tx_channel.lock().await;
+
+                        tokio::select! {
+                            _ = cancel.cancelled() => {
+                                break;
+                            }
+                            // Skip all tx messages
+                            _ = tx_channel.recv() => {}
+                        }
+                    }
+                })
+            };
+
+            let cancel = context.cancel.clone();
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => {
+                        break;
+                    }
+
+                    client = listener.accept() => {
+                        if let Ok((stream, peer)) = client {
+                            log::info!(
+                                "websocket_server: new client <{}> connected to <{}>",
+                                peer,
+                                addr
+                            );
+
+                            let mut iface_manager = iface_manager.lock().await;
+                            let name = format!(
+                                "{} on {}",
+                                peer,
+                                iface_manager.display_name(&server_address)
+                            );
+                            let address = iface_manager.spawn_named(
+                                name,
+                                WebSocketConnection {
+                                    peer: peer.to_string(),
+                                    stream: Some(stream),
+                                },
+                                mode,
+                                WebSocketConnection::spawn,
+                            ).address();
+                            let groups = iface_manager.groups(&server_address);
+                            iface_manager.set_groups(&address, groups);
+                        }
+                    }
+                }
+            }
+
+            let _ = tokio::join!(tx_task);
+        }
+    }
+}
+
+impl Interface for WebSocketServer {
+    fn mtu() -> usize {
+        WEBSOCKET_MTU
+    }
+}
+
+/// A client accepted by [`WebSocketServer`].
+struct WebSocketConnection {
+    peer: String,
+    stream: Option<TcpStream>,
+}
+
+impl WebSocketConnection {
+    async fn spawn(context: InterfaceContext<Self>) {
+        let iface_stop = context.channel.stop.clone();
+        let iface_address = context.channel.address;
+        let peer = { context.inner.lock().unwrap().peer.clone() };
+        let stream = { context.inner.lock().unwrap().stream.take() };
+
+        let (rx_channel, mut tx_channel) = context.channel.split();
+
+        let socket = match stream {
+            Some(stream) => tokio_tungstenite::accept_async(stream).await,
+            None => return,
+        };
+
+        let mut socket = match socket {
+            Ok(socket) => socket,
+            Err(err) => {
+                log::info!("websocket_server: handshake with <{}> failed: {}", peer, err);
+                iface_stop.cancel();
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = context.cancel.cancelled() => {
+                    let _ = socket.close(None).await;
+                    break;
+                }
+                message = socket.next() => {
+                    match message {
+                        Some(Ok(Message::Binary(data))) => {
+                            if let Some(packet) = decode(&data) {
+                                trace_packet!(TraceCategory::InterfaceRx, "websocket_server: rx << ({}) {}", iface_address, packet);
+                                let _ = rx_channel.send(RxMessage { address: iface_address, packet }).await;
+                            } else {
+                                diagnostics::packet_undecodable(format_args!("websocket_server <{}>", peer), &data);
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None => break,
+                        // Pings are answered by the socket, text isn't used
+                        Some(Ok(_)) => {}
+                        Some(Err(err)) => {
+                            log::warn!("websocket_server: connection error with <{}>: {}", peer, err);
+                            break;
+                        }
+                    }
+                }
+                Some(message) = tx_channel.recv() => {
+                    let packet = message.packet;
+                    trace_packet!(TraceCategory::InterfaceTx, "websocket_server: tx >> ({}) {}", iface_address, packet);
+                    if let Some(data) = encode(&packet) {
+                        if socket.send(Message::binary(data)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        log::info!("websocket_server: client <{}> disconnected", peer);
+
+        iface_stop.cancel();
+    }
+}
+
+impl Interface for WebSocketConnection {
+    fn mtu() -> usize {
+        WEBSOCKET_MTU
+    }
+}
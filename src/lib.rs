@@ -24,6 +24,10 @@
 //! * [`iface::tcp_client::TcpClient`]
 //! * [`iface::tcp_server::TcpServer`]
 //! * [`iface::udp::UdpInterface`]
+//! * [`iface::local_client::LocalClientInterface`] to attach to a running shared instance
+//! * [`iface::local_server::LocalServerInterface`] to be the shared instance others attach to
+//! * [`iface::framed_device::FramedDeviceInterface`] for radios driven directly over SPI/I2C
+//! * WebSocket with the `websocket` feature, the interface of browser builds (`wasm32`)
 //! * Kaonic
 //!
 //! The main instance can be used to send messages to [`destination::Destination`]s directly
@@ -32,7 +36,9 @@
 //! [`hash::AddressHash`] is used for adressing destinations and [`destination::link::LinkId`] for
 //! links.
 //!
-//! `Resources` can be used to send arbitrary amounts of data using a simple interface.
+//! Resources, which Python uses to send arbitrary amounts of data over a link, aren't implemented
+//! yet. Data which doesn't fit into a packet is split over a [`channel::Channel`] instead, and
+//! [`fileshare`] serves and fetches whole files that way.
 //!
 //! ## Creating a Transport instance
 //!
@@ -52,7 +58,7 @@
 //! # #[tokio::main]
 //! # async fn main() {
 //!     # let transport = Transport::new(TransportConfig::default());
-//!     let client_addr = transport.iface_manager().lock().await
+//!     let client = transport.iface_manager().lock().await
 //!         .spawn(TcpClient::new("127.0.0.1:4242"), TcpClient::spawn);
 //! # }
 //! ```
@@ -71,12 +77,12 @@
 //! # use reticulum::hash::AddressHash;
 //! # #[tokio::main]
 //! # async fn main() {
-//!     # let mut transport = Transport::new(TransportConfig::default());
+//!     # let transport = Transport::new(TransportConfig::default());
 //!     let id = PrivateIdentity::new_from_rand(OsRng);
 //!     let destination = transport
 //!         .add_destination(id, DestinationName::new("example", "app"))
 //!         .await;
-//!     transport.send_announce(&destination, None).await;
+//!     transport.send_announce(&destination, None).await.unwrap();
 //! # }
 //! ```
 //!
@@ -121,7 +127,7 @@
 //!                     // Now this link can be used to send data
 //!                     let link = transport.find_in_link(&link_id).await.unwrap();
 //!                     let packet = link.lock().await.data_packet(b"hello world").unwrap();
-//!                     transport.send_packet(packet).await;
+//!                     transport.send_packet(packet).await.unwrap();
 //!                 }
 //!                 LinkEvent::Data(_payload) => {
 //!                     // Handle incoming messages
@@ -141,12 +147,31 @@ extern crate alloc;
 
 pub mod buffer;
 pub mod channel;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod control;
 pub mod crypt;
 pub mod destination;
+pub mod diagnostics;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod fileshare;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
 pub mod hash;
 pub mod identity;
 pub mod iface;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod management;
+pub mod msgpack;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod nomadnet;
 pub mod packet;
+pub mod runtime;
 pub mod transport;
 pub mod serde;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sim;
+pub mod trace;
+pub mod transfer;
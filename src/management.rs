@@ -0,0 +1,554 @@
+//! Remote management of a node over Reticulum.
+//!
+//! A [`Server`] serves the `rnstransport.remote.management` destination of the
+//! node's identity, like `rnsd` does with `enable_remote_management`. Peers
+//! open a link to it, identify and send requests to two paths:
+//!
+//! * `/status` with `[include_link_count]` is answered with `[status]` or
+//!   `[status, link_count]`. The status is a map of the transport id, the
+//!   interfaces and the announce counts of the node.
+//! * `/path` with `["table", destination, max_hops]`, the last two may be nil,
+//!   is answered with the path table in the layout of Python's
+//!   `Transport.get_path_table()`.
+//!
+//! Only identities on the allowed list get an answer. Links of other
+//! identities are closed once they identify, requests of peers which didn't
+//! identify are ignored. A [`Client`] queries a node from anywhere on the
+//! network.
+//!
+//! Responses have to fit into one packet, long path tables are cut short.
+//! Ask for a single destination to see its path on a large node.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::destination::link::{Link, LinkError, LinkEvent, LinkStatus};
+use crate::destination::request::path_hash;
+use crate::destination::{DestinationDesc, DestinationName, LinkAccess};
+use crate::error::RnsError;
+use crate::hash::{AddressHash, ADDRESS_HASH_SIZE};
+use crate::identity::PrivateIdentity;
+use crate::msgpack::{Reader, Writer};
+use crate::runtime::{self, Instant, SystemTime, UNIX_EPOCH};
+use crate::transport::{AnnounceCounts, ReceivedRequest, SendError, Transport};
+
+pub const APP_NAME: &str = "rnstransport";
+pub const ASPECTS: &str = "remote.management";
+
+pub const STATUS_PATH: &str = "/status";
+pub const PATH_PATH: &str = "/path";
+
+/// Interval at which the management destination is announced, the same as
+/// Python's.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// Largest response, what one link packet can carry with room to spare.
+const MAX_RESPONSE_SIZE: usize = 1536;
+
+/// Name of the management destination.
+pub fn destination_name() -> DestinationName {
+    DestinationName::new(APP_NAME, ASPECTS)
+}
+
+#[derive(Debug)]
+pub enum ManagementError {
+    /// The link to the node couldn't be used.
+    Link(LinkError),
+    /// No response came in time.
+    Timeout,
+    /// The response couldn't be decoded.
+    Protocol,
+    /// A packet couldn't be sent.
+    Send(SendError),
+}
+
+impl fmt::Display for ManagementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManagementError::Link(err) => write!(f, "management link failed: {}", err),
+            ManagementError::Timeout => write!(f, "management request timed out"),
+            ManagementError::Protocol => write!(f, "malformed management response"),
+            ManagementError::Send(err) => write!(f, "management request not sent: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ManagementError {}
+
+impl From<LinkError> for ManagementError {
+    fn from(err: LinkError) -> Self {
+        ManagementError::Link(err)
+    }
+}
+
+impl From<SendError> for ManagementError {
+    fn from(err: SendError) -> Self {
+        ManagementError::Send(err)
+    }
+}
+
+impl From<RnsError> for ManagementError {
+    fn from(_: RnsError) -> Self {
+        ManagementError::Protocol
+    }
+}
+
+/// An interface of a remote node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteInterface {
+    pub address: AddressHash,
+    pub name: Option<String>,
+    pub mode: String,
+    pub bitrate: Option<u64>,
+    pub echoes: u64,
+}
+
+/// Answer to a `/status` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteStatus {
+    pub transport_id: AddressHash,
+    pub interfaces: Vec<RemoteInterface>,
+    pub announces: AnnounceCounts,
+    /// Links of the node, if they were asked for.
+    pub link_count: Option<u64>,
+}
+
+/// A path of a remote node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemotePath {
+    pub destination: AddressHash,
+    /// Unix time in seconds of the announce establishing the path.
+    pub timestamp: f64,
+    pub via: AddressHash,
+    pub hops: u8,
+    /// Unix time in seconds at which the path expires.
+    pub expires: f64,
+    pub interface: String,
+}
+
+fn unix_time(instant: Instant) -> f64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    now - instant.elapsed().as_secs_f64()
+}
+
+fn read_address(reader: &mut Reader) -> Result<AddressHash, ManagementError> {
+    let bytes: [u8; ADDRESS_HASH_SIZE] =
+        reader.bin()?.try_into().map_err(|_| ManagementError::Protocol)?;
+    Ok(AddressHash::new(bytes))
+}
+
+/// Serves the management destination of a transport.
+pub struct Server {
+    transport: Arc<Transport>,
+    identity: PrivateIdentity,
+    allowed: Vec<AddressHash>,
+}
+
+impl Server {
+    /// `identity` should be the one of the transport, `allowed` holds the
+    /// identity hashes of the operators.
+    pub fn new(transport: Arc<Transport>, identity: PrivateIdentity, allowed: Vec<AddressHash>) -> Self {
+        Self { transport, identity, allowed }
+    }
+
+    /// Adds and announces the destination and answers requests until
+    /// `cancel` is triggered.
+    pub async fn run(self, cancel: CancellationToken) {
+        let mut requests = self.transport.link_requests();
+
+        let destination = self
+            .transport
+            .add_destination(self.identity.clone(), destination_name())
+            .await;
+        let address = {
+            let mut destination = destination.lock().await;
+            destination.set_link_access(LinkAccess::Allowed(self.allowed.clone()));
+            destination.desc.address_hash
+        };
+
+        log::info!("management: serving {} to {} identities", address, self.allowed.len());
+
+        let mut next_announce = Instant::now();
+
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = runtime::sleep_until(next_announce) => {
+                    if let Err(err) = self.transport.send_announce(&destination, None).await {
+                        log::warn!("management: couldn't announce: {}", err);
+                    }
+                    next_announce = Instant::now() + ANNOUNCE_INTERVAL;
+                }
+                request = requests.recv() => match request {
+                    Ok(request) if request.destination == address => self.handle_request(request).await,
+                    Ok(_) => {}
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(count)) => {
+                        log::warn!("management: dropped {} requests", count);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+
+    async fn handle_request(&self, received: ReceivedRequest) {
+        let request = &received.request;
+        let response = if request.path_hash == path_hash(STATUS_PATH) {
+            self.status(&request.data).await
+        } else if request.path_hash == path_hash(PATH_PATH) {
+            self.path_table(&request.data).await
+        } else {
+            log::debug!("management: unknown request path {}", request.path_hash);
+            None
+        };
+
+        let Some(response) = response else {
+            return;
+        };
+
+        if let Some(link) = self.transport.find_in_link(&received.link_id).await {
+            let packet = link.lock().await.response_packet(&request.request_id, &response);
+            match packet {
+                Ok(packet) => {
+                    if let Err(err) = self.transport.send_packet(packet).await {
+                        log::warn!("management: couldn't respond: {}", err);
+                    }
+                }
+                Err(err) => log::warn!("management: couldn't respond: {}", err),
+            }
+        }
+    }
+
+    async fn status(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let mut reader = Reader::new(data);
+        if reader.array().ok()? == 0 {
+            return None;
+        }
+        let include_link_count = reader.bool().unwrap_or(false);
+
+        let interfaces = self.transport.iface_manager().lock().await.interfaces();
+        let announces = self.transport.announce_counts().await;
+
+        let mut response = Writer::new();
+        response.array(if include_link_count { 2 } else { 1 });
+
+        response.map(3).str("transport_id").bin(self.identity.address_hash().as_slice());
+
+        response.str("interfaces").array(interfaces.len() as u32);
+        for iface in interfaces {
+            response
+                .map(5)
+                .str("hash")
+                .bin(iface.address.as_slice())
+                .str("name");
+            match &iface.name {
+                Some(name) => response.str(name),
+                None => response.nil(),
+            };
+            response
+                .str("mode")
+                .str(&format!("{:?}", iface.mode))
+                .str("bitrate")
+                .opt_uint(iface.bitrate)
+                .str("echoes")
+                .uint(iface.echoes);
+        }
+
+        response
+            .str("announces")
+            .map(4)
+            .str("received")
+            .uint(announces.received)
+            .str("dropped")
+            .uint(announces.dropped)
+            .str("sent")
+            .uint(announces.sent)
+            .str("retransmitted")
+            .uint(announces.retransmitted);
+
+        if include_link_count {
+            response.uint(self.transport.active_links().await.len() as u64);
+        }
+
+        Some(response.finish())
+    }
+
+    async fn path_table(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let mut reader = Reader::new(data);
+        let len = reader.array().ok()?;
+        if len == 0 || reader.str().ok()? != "table" {
+            return None;
+        }
+
+        let destination = if len > 1 && !reader.nil() {
+            Some(read_address(&mut reader).ok()?)
+        } else {
+            None
+        };
+        let max_hops = if len > 2 && !reader.nil() {
+            Some(reader.uint::<u8>().ok()?)
+        } else {
+            None
+        };
+
+        let timer_config = self.transport.timer_config().await;
+        let mut entries = Vec::new();
+        let mut size = 0;
+
+        for (address, path) in self.transport.all_paths().await {
+            if destination.is_some_and(|destination| destination != address)
+                || max_hops.is_some_and(|max_hops| path.hops > max_hops)
+            {
+                continue;
+            }
+
+            let announced = unix_time(path.announced);
+            let entry = Writer::new()
+                .map(6)
+                .str("hash")
+                .bin(address.as_slice())
+                .str("timestamp")
+                .f64(announced)
+                .str("via")
+                .bin(path.received_from.as_slice())
+                .str("hops")
+                .uint(path.hops as u64)
+                .str("expires")
+                .f64(announced + timer_config.path_lifetime(path.hops).as_secs_f64())
+                .str("interface")
+                .str(&path.iface.to_hex_string())
+                .finish();
+
+            size += entry.len();
+            if size > MAX_RESPONSE_SIZE {
+                log::debug!("management: path table cut short at {} entries", entries.len());
+                break;
+            }
+
+            entries.push(entry);
+        }
+
+        let mut response = Writer::new();
+        response.array(entries.len() as u32);
+        for entry in entries {
+            response.raw(&entry);
+        }
+
+        Some(response.finish())
+    }
+}
+
+/// Link to the management destination of a remote node.
+pub struct Client {
+    transport: Arc<Transport>,
+    link: Arc<Mutex<Link>>,
+    timeout: Duration,
+}
+
+impl Client {
+    /// Opens a link to the management destination `destination`, which has
+    /// to be announced, and identifies with `identity`. Requests fail after
+    /// `timeout`.
+    pub async fn connect(
+        transport: Arc<Transport>,
+        destination: DestinationDesc,
+        identity: &PrivateIdentity,
+        timeout: Duration,
+    ) -> Result<Self, ManagementError> {
+        let mut events = transport.out_link_events();
+        let link = transport.link(destination).await;
+        let link_id = *link.lock().await.id();
+
+        let activated = async {
+            while link.lock().await.status() != LinkStatus::Active {
+                match events.recv().await {
+                    Ok(event) if event.id == link_id && matches!(event.event, LinkEvent::Closed) => {
+                        return false;
+                    }
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(_) => return false,
+                }
+            }
+            true
+        };
+
+        if !runtime::timeout(timeout, activated).await.unwrap_or(false) {
+            return Err(ManagementError::Timeout);
+        }
+
+        let packet = link.lock().await.identify_packet(identity)?;
+        transport.send_packet(packet).await?;
+
+        Ok(Self { transport, link, timeout })
+    }
+
+    /// Sends the msgpack encoded `data` to `path` and returns the msgpack
+    /// encoded response.
+    pub async fn request(&self, path: &str, data: &[u8]) -> Result<Vec<u8>, ManagementError> {
+        let mut responses = self.transport.link_responses();
+
+        let (packet, request_id) = self.link.lock().await.request_packet(path, data)?;
+        self.transport.send_packet(packet).await?;
+
+        let response = async {
+            loop {
+                match responses.recv().await {
+                    Ok(received) if received.response.request_id == request_id => {
+                        return Some(received.response.data);
+                    }
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(_) => return None,
+                }
+            }
+        };
+
+        runtime::timeout(self.timeout, response)
+            .await
+            .ok()
+            .flatten()
+            .ok_or(ManagementError::Timeout)
+    }
+
+    pub async fn status(&self, include_link_count: bool) -> Result<RemoteStatus, ManagementError> {
+        let request = Writer::new().array(1).bool(include_link_count).finish();
+        let response = self.request(STATUS_PATH, &request).await?;
+
+        decode_status(&response)
+    }
+
+    /// Paths of the node, to `destination` only if it is set.
+    pub async fn path_table(
+        &self,
+        destination: Option<&AddressHash>,
+        max_hops: Option<u8>,
+    ) -> Result<Vec<RemotePath>, ManagementError> {
+        let request = Writer::new()
+            .array(3)
+            .str("table")
+            .opt_bin(destination.map(AddressHash::as_slice))
+            .opt_uint(max_hops.map(u64::from))
+            .finish();
+        let response = self.request(PATH_PATH, &request).await?;
+
+        decode_path_table(&response)
+    }
+
+    /// Closes the link to the node.
+    pub async fn close(self) -> Result<(), ManagementError> {
+        let link_id = *self.link.lock().await.id();
+        self.transport.link_close(link_id).await.map_err(|_| ManagementError::Protocol)
+    }
+
+    pub fn link(&self) -> &Arc<Mutex<Link>> {
+        &self.link
+    }
+}
+
+fn decode_status(data: &[u8]) -> Result<RemoteStatus, ManagementError> {
+    let mut reader = Reader::new(data);
+    let len = reader.array()?;
+
+    let mut status = RemoteStatus {
+        transport_id: AddressHash::new_empty(),
+        interfaces: Vec::new(),
+        announces: AnnounceCounts::default(),
+        link_count: None,
+    };
+
+    for _ in 0..reader.map()? {
+        match reader.str()? {
+            "transport_id" => status.transport_id = read_address(&mut reader)?,
+            "interfaces" => {
+                for _ in 0..reader.array()? {
+                    status.interfaces.push(decode_interface(&mut reader)?);
+                }
+            }
+            "announces" => {
+                for _ in 0..reader.map()? {
+                    let key = reader.str()?;
+                    let value = reader.uint()?;
+                    match key {
+                        "received" => status.announces.received = value,
+                        "dropped" => status.announces.dropped = value,
+                        "sent" => status.announces.sent = value,
+                        "retransmitted" => status.announces.retransmitted = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => return Err(ManagementError::Protocol),
+        }
+    }
+
+    if len > 1 {
+        status.link_count = Some(reader.uint()?);
+    }
+
+    Ok(status)
+}
+
+fn decode_interface(reader: &mut Reader) -> Result<RemoteInterface, ManagementError> {
+    let mut iface = RemoteInterface {
+        address: AddressHash::new_empty(),
+        name: None,
+        mode: String::new(),
+        bitrate: None,
+        echoes: 0,
+    };
+
+    for _ in 0..reader.map()? {
+        match reader.str()? {
+            "hash" => iface.address = read_address(reader)?,
+            "name" => iface.name = if reader.nil() { None } else { Some(reader.str()?.into()) },
+            "mode" => iface.mode = reader.str()?.into(),
+            "bitrate" => iface.bitrate = if reader.nil() { None } else { Some(reader.uint()?) },
+            "echoes" => iface.echoes = reader.uint()?,
+            _ => return Err(ManagementError::Protocol),
+        }
+    }
+
+    Ok(iface)
+}
+
+fn decode_path_table(data: &[u8]) -> Result<Vec<RemotePath>, ManagementError> {
+    let mut reader = Reader::new(data);
+    let mut paths = Vec::new();
+
+    for _ in 0..reader.array()? {
+        let mut path = RemotePath {
+            destination: AddressHash::new_empty(),
+            timestamp: 0.0,
+            via: AddressHash::new_empty(),
+            hops: 0,
+            expires: 0.0,
+            interface: String::new(),
+        };
+
+        for _ in 0..reader.map()? {
+            match reader.str()? {
+                "hash" => path.destination = read_address(&mut reader)?,
+                "timestamp" => path.timestamp = reader.f64()?,
+                "via" => path.via = read_address(&mut reader)?,
+                "hops" => path.hops = reader.uint()?,
+                "expires" => path.expires = reader.f64()?,
+                "interface" => path.interface = reader.bin_or_str()?.escape_ascii().to_string(),
+                _ => return Err(ManagementError::Protocol),
+            }
+        }
+
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
@@ -0,0 +1,273 @@
+//! Minimal msgpack encoding and decoding for RNS structures.
+//!
+//! Python Reticulum and LXMF pack announce app data, link RTT, requests and
+//! resource advertisements with msgpack. [`Writer`] and [`Reader`] cover the
+//! types these structures use and work on plain byte slices, so they don't
+//! depend on `std::io`.
+
+use alloc::vec::Vec;
+
+use rmp::decode::Bytes;
+
+use crate::error::RnsError;
+
+const MARKER_NIL: u8 = 0xc0;
+
+/// Appends msgpack values to a byte vector.
+#[derive(Default)]
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    // Writes into a `Vec` only fail if allocation fails, which aborts anyway
+
+    pub fn array(&mut self, len: u32) -> &mut Self {
+        rmp::encode::write_array_len(&mut self.data, len).unwrap();
+        self
+    }
+
+    pub fn map(&mut self, len: u32) -> &mut Self {
+        rmp::encode::write_map_len(&mut self.data, len).unwrap();
+        self
+    }
+
+    pub fn nil(&mut self) -> &mut Self {
+        rmp::encode::write_nil(&mut self.data).unwrap();
+        self
+    }
+
+    pub fn bool(&mut self, value: bool) -> &mut Self {
+        rmp::encode::write_bool(&mut self.data, value).unwrap();
+        self
+    }
+
+    /// Writes `value` in the smallest integer format, like Python does.
+    pub fn uint(&mut self, value: u64) -> &mut Self {
+        rmp::encode::write_uint(&mut self.data, value).unwrap();
+        self
+    }
+
+    pub fn int(&mut self, value: i64) -> &mut Self {
+        rmp::encode::write_sint(&mut self.data, value).unwrap();
+        self
+    }
+
+    pub fn f64(&mut self, value: f64) -> &mut Self {
+        rmp::encode::write_f64(&mut self.data, value).unwrap();
+        self
+    }
+
+    pub fn bin(&mut self, value: &[u8]) -> &mut Self {
+        rmp::encode::write_bin(&mut self.data, value).unwrap();
+        self
+    }
+
+    pub fn str(&mut self, value: &str) -> &mut Self {
+        rmp::encode::write_str(&mut self.data, value).unwrap();
+        self
+    }
+
+    /// Writes `value` as bin, or nil if it is `None`.
+    pub fn opt_bin(&mut self, value: Option<&[u8]>) -> &mut Self {
+        match value {
+            Some(value) => self.bin(value),
+            None => self.nil(),
+        }
+    }
+
+    /// Writes `value` as integer, or nil if it is `None`.
+    pub fn opt_uint(&mut self, value: Option<u64>) -> &mut Self {
+        match value {
+            Some(value) => self.uint(value),
+            None => self.nil(),
+        }
+    }
+
+    /// Appends a value which is encoded already.
+    pub fn raw(&mut self, value: &[u8]) -> &mut Self {
+        self.data.extend_from_slice(value);
+        self
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    pub fn finish(&mut self) -> Vec<u8> {
+        core::mem::take(&mut self.data)
+    }
+}
+
+/// Reads msgpack values from a byte slice.
+///
+/// All methods fail with [`RnsError::PacketError`] on malformed data or a
+/// value of a different type. A failed read doesn't consume any input.
+pub struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Bytes which haven't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the first byte of the next value.
+    pub fn peek_marker(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Consumes a nil value and returns `true` if the next value is nil.
+    pub fn nil(&mut self) -> bool {
+        if self.peek_marker() == Some(MARKER_NIL) {
+            self.data = &self.data[1..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read<T, E>(&mut self, f: impl FnOnce(&mut Bytes<'a>) -> Result<T, E>) -> Result<T, RnsError> {
+        let mut bytes = Bytes::new(self.data);
+        let value = f(&mut bytes).map_err(|_| RnsError::PacketError)?;
+        self.data = bytes.remaining_slice();
+        Ok(value)
+    }
+
+    pub fn array(&mut self) -> Result<u32, RnsError> {
+        self.read(rmp::decode::read_array_len)
+    }
+
+    pub fn map(&mut self) -> Result<u32, RnsError> {
+        self.read(rmp::decode::read_map_len)
+    }
+
+    pub fn bool(&mut self) -> Result<bool, RnsError> {
+        self.read(rmp::decode::read_bool)
+    }
+
+    /// Reads a non-negative integer of any msgpack format which fits into `T`.
+    pub fn uint<T: TryFrom<u64>>(&mut self) -> Result<T, RnsError> {
+        let mut bytes = Bytes::new(self.data);
+        let value = rmp::decode::read_int::<u64, _>(&mut bytes).map_err(|_| RnsError::PacketError)?;
+        let value = T::try_from(value).map_err(|_| RnsError::PacketError)?;
+        self.data = bytes.remaining_slice();
+        Ok(value)
+    }
+
+    /// Reads an integer of any msgpack format which fits into `T`.
+    pub fn int<T: TryFrom<i64>>(&mut self) -> Result<T, RnsError> {
+        let mut bytes = Bytes::new(self.data);
+        let value = rmp::decode::read_int::<i64, _>(&mut bytes).map_err(|_| RnsError::PacketError)?;
+        let value = T::try_from(value).map_err(|_| RnsError::PacketError)?;
+        self.data = bytes.remaining_slice();
+        Ok(value)
+    }
+
+    /// Reads a float, integers are converted like Python does.
+    pub fn f64(&mut self) -> Result<f64, RnsError> {
+        match self.peek_marker() {
+            Some(0xca) => self.read(rmp::decode::read_f32).map(f64::from),
+            Some(0xcb) => self.read(rmp::decode::read_f64),
+            _ => self.int::<i64>().map(|value| value as f64),
+        }
+    }
+
+    pub fn bin(&mut self) -> Result<&'a [u8], RnsError> {
+        let mut data = self.data;
+        let len = {
+            let mut bytes = Bytes::new(data);
+            let len = rmp::decode::read_bin_len(&mut bytes).map_err(|_| RnsError::PacketError)?;
+            data = bytes.remaining_slice();
+            len as usize
+        };
+
+        self.take(data, len)
+    }
+
+    pub fn str(&mut self) -> Result<&'a str, RnsError> {
+        let mut data = self.data;
+        let len = {
+            let mut bytes = Bytes::new(data);
+            let len = rmp::decode::read_str_len(&mut bytes).map_err(|_| RnsError::PacketError)?;
+            data = bytes.remaining_slice();
+            len as usize
+        };
+
+        let value = core::str::from_utf8(data.get(..len).ok_or(RnsError::PacketError)?)
+            .map_err(|_| RnsError::PacketError)?;
+
+        self.data = &data[len..];
+        Ok(value)
+    }
+
+    /// Reads bin or str as raw bytes, Python implementations use both for
+    /// text depending on their version.
+    pub fn bin_or_str(&mut self) -> Result<&'a [u8], RnsError> {
+        match self.bin() {
+            Ok(value) => Ok(value),
+            Err(_) => self.str().map(str::as_bytes),
+        }
+    }
+
+    fn take(&mut self, data: &'a [u8], len: usize) -> Result<&'a [u8], RnsError> {
+        if data.len() < len {
+            return Err(RnsError::PacketError);
+        }
+
+        let (value, rest) = data.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let data = Writer::new()
+            .array(6)
+            .bin(b"bin")
+            .str("str")
+            .uint(300)
+            .int(-2)
+            .f64(0.5)
+            .nil()
+            .finish();
+
+        let mut reader = Reader::new(&data);
+        assert_eq!(reader.array(), Ok(6));
+        assert_eq!(reader.str(), Err(RnsError::PacketError));
+        assert_eq!(reader.bin_or_str(), Ok(&b"bin"[..]));
+        assert_eq!(reader.bin_or_str(), Ok(&b"str"[..]));
+        assert_eq!(reader.uint::<u8>(), Err(RnsError::PacketError));
+        assert_eq!(reader.uint::<u16>(), Ok(300));
+        assert_eq!(reader.f64(), Ok(-2.0));
+        assert_eq!(reader.f64(), Ok(0.5));
+        assert!(reader.nil());
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn truncated_data() {
+        // msgpack.packb(b"abcd")[:4]
+        let mut reader = Reader::new(&[0xc4, 0x04, b'a', b'b']);
+        assert_eq!(reader.bin(), Err(RnsError::PacketError));
+        assert_eq!(reader.remaining().len(), 4);
+    }
+}
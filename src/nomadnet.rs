@@ -0,0 +1,278 @@
+//! Hosting a [NomadNet](https://github.com/markqvist/NomadNet) node.
+//!
+//! A [`Node`] serves the `nomadnetwork.node` destination of an identity and
+//! announces it with the node name, so NomadNet and Sideband clients list it
+//! and can browse it. Every page and file is a request handler of the
+//! destination, clients request two kinds of paths over a link:
+//!
+//! * `/page/<path>` is answered with the micron markup of the page. Clients
+//!   open `/page/index.mu` first, a placeholder is served if there is no
+//!   such page.
+//! * `/file/<path>` is answered with `[name, data]`, what older NomadNet
+//!   versions send for downloads.
+//!
+//! This is a proof of concept. There are no resources yet, so responses have
+//! to fit into one packet: larger pages are answered with a short page
+//! saying so, larger files not at all. Pages are served as they are, without
+//! running executable ones or checking `.allowed` lists.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::destination::request::{IncomingRequest, RequestPolicy};
+use crate::destination::DestinationName;
+use crate::identity::PrivateIdentity;
+use crate::msgpack::Writer;
+use crate::runtime;
+use crate::transport::Transport;
+
+pub const APP_NAME: &str = "nomadnetwork";
+pub const ASPECTS: &str = "node";
+
+pub const PAGE_PREFIX: &str = "/page/";
+pub const FILE_PREFIX: &str = "/file/";
+/// Page clients open when they connect to a node.
+pub const INDEX_PATH: &str = "/page/index.mu";
+
+/// Interval at which the node is announced, NomadNet's default.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Largest page or file, so the response still fits into a link packet.
+const MAX_CONTENT_SIZE: usize = 1400;
+
+const DEFAULT_INDEX: &[u8] = b">Default Home Page\n\n\
+This node is serving pages, but the home page file (index.mu) was not found. \
+This is an auto-generated placeholder.\n";
+
+const TOO_LARGE_PAGE: &[u8] = b">Page Too Large\n\n\
+This page is too large to be served by this node.\n";
+
+/// Name of the node destination.
+pub fn destination_name() -> DestinationName {
+    DestinationName::new(APP_NAME, ASPECTS)
+}
+
+/// Where the content of a page or file comes from.
+enum Content {
+    Static(Vec<u8>),
+    /// Read for every request, so edits show up right away.
+    File(PathBuf),
+}
+
+impl Content {
+    fn load(&self) -> io::Result<Vec<u8>> {
+        match self {
+            Content::Static(data) => Ok(data.clone()),
+            Content::File(path) => std::fs::read(path),
+        }
+    }
+}
+
+/// Serves the pages and files of a NomadNet node over a transport.
+pub struct Node {
+    transport: Arc<Transport>,
+    identity: PrivateIdentity,
+    name: String,
+    /// Pages and files by their request path.
+    entries: HashMap<String, Content>,
+}
+
+impl Node {
+    /// Node of `identity` which is listed as `name`.
+    pub fn new(transport: Arc<Transport>, identity: PrivateIdentity, name: &str) -> Self {
+        Self {
+            transport,
+            identity,
+            name: name.into(),
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Serves `content` at `/page/<path>`, e.g. `index.mu`.
+    pub fn add_page(&mut self, path: &str, content: impl Into<Vec<u8>>) {
+        self.add(PAGE_PREFIX, path, Content::Static(content.into()));
+    }
+
+    /// Serves `content` at `/file/<path>`.
+    pub fn add_file(&mut self, path: &str, content: impl Into<Vec<u8>>) {
+        self.add(FILE_PREFIX, path, Content::Static(content.into()));
+    }
+
+    /// Serves the files below `dir` as pages, the same way NomadNet serves
+    /// its `pages` directory. Returns how many were added.
+    pub fn add_pages_dir(&mut self, dir: &Path) -> io::Result<usize> {
+        self.add_dir(PAGE_PREFIX, dir)
+    }
+
+    /// Serves the files below `dir` as downloads. Returns how many were
+    /// added.
+    pub fn add_files_dir(&mut self, dir: &Path) -> io::Result<usize> {
+        self.add_dir(FILE_PREFIX, dir)
+    }
+
+    fn add(&mut self, prefix: &str, path: &str, content: Content) {
+        let path = format!("{}{}", prefix, path.trim_start_matches('/'));
+        self.entries.insert(path, content);
+    }
+
+    fn add_dir(&mut self, prefix: &str, dir: &Path) -> io::Result<usize> {
+        let mut files = Vec::new();
+        collect_files(dir, dir, &mut files)?;
+
+        let count = files.len();
+        for (path, file) in files {
+            self.add(prefix, &path, Content::File(file));
+        }
+
+        Ok(count)
+    }
+
+    /// Adds the destination with a request handler for every page and file
+    /// and announces it until `cancel` is triggered.
+    pub async fn run(mut self, cancel: CancellationToken) {
+        if !self.entries.contains_key(INDEX_PATH) {
+            self.add_page("index.mu", DEFAULT_INDEX);
+        }
+
+        let destination = self
+            .transport
+            .add_destination(self.identity.clone(), destination_name())
+            .await;
+        let address = {
+            let mut destination = destination.lock().await;
+            // Announces on interfaces which come up carry the name as well
+            if let Err(err) = destination.set_default_app_data(Some(self.name.as_bytes())) {
+                log::warn!("nomadnet: node name '{}' can't be announced: {:?}", self.name, err);
+            }
+
+            for (path, content) in self.entries.drain() {
+                let handler = move |request: &IncomingRequest| respond(request.path, &content);
+                destination.register_request_handler(&path, handler, RequestPolicy::All);
+            }
+
+            destination.desc.address_hash
+        };
+
+        log::info!(
+            "nomadnet: serving {} as '{}' with {} pages and files",
+            address,
+            self.name,
+            destination.lock().await.request_paths().count()
+        );
+
+        loop {
+            if let Err(err) = self.transport.send_announce(&destination, None).await {
+                log::warn!("nomadnet: couldn't announce: {}", err);
+            }
+
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = runtime::sleep(ANNOUNCE_INTERVAL) => {}
+            }
+        }
+    }
+}
+
+/// The msgpack encoded response to a request for `path`.
+fn respond(path: &str, content: &Content) -> Option<Vec<u8>> {
+    let data = match content.load() {
+        Ok(data) => data,
+        Err(err) => {
+            log::warn!("nomadnet: couldn't read {}: {}", path, err);
+            return None;
+        }
+    };
+
+    if let Some(name) = path.strip_prefix(FILE_PREFIX) {
+        if data.len() > MAX_CONTENT_SIZE {
+            log::warn!("nomadnet: file {} is too large to be served", path);
+            return None;
+        }
+
+        let name = name.rsplit('/').next().unwrap_or(name);
+        return Some(Writer::new().array(2).str(name).bin(&data).finish());
+    }
+
+    if data.len() > MAX_CONTENT_SIZE {
+        log::warn!("nomadnet: page {} is too large to be served", path);
+        return Some(Writer::new().bin(TOO_LARGE_PAGE).finish());
+    }
+
+    Some(Writer::new().bin(&data).finish())
+}
+
+/// Adds the files below `dir` with their path relative to `root`, leaving
+/// out hidden ones and NomadNet's `.allowed` lists.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') || name.ends_with(".allowed") {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let relative: Vec<_> = relative.iter().filter_map(|part| part.to_str()).collect();
+            files.push((relative.join("/"), path));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msgpack::Reader;
+
+    fn respond_static(path: &str, data: &[u8]) -> Option<Vec<u8>> {
+        respond(path, &Content::Static(data.to_vec()))
+    }
+
+    #[test]
+    fn pages_and_files_are_encoded_like_nomadnet() {
+        let page = respond_static("/page/index.mu", b">Hello").unwrap();
+        assert_eq!(Reader::new(&page).bin().unwrap(), b">Hello");
+
+        let file = respond_static("/file/docs/notes.txt", b"notes").unwrap();
+        let mut reader = Reader::new(&file);
+        assert_eq!(reader.array().unwrap(), 2);
+        assert_eq!(reader.str().unwrap(), "notes.txt");
+        assert_eq!(reader.bin().unwrap(), b"notes");
+
+        let large = vec![b'x'; MAX_CONTENT_SIZE + 1];
+        let page = respond_static("/page/large.mu", &large).unwrap();
+        assert_eq!(Reader::new(&page).bin().unwrap(), TOO_LARGE_PAGE);
+        assert!(respond_static("/file/large.bin", &large).is_none());
+    }
+
+    #[tokio::test]
+    async fn directories_map_to_request_paths() {
+        let dir = std::env::temp_dir().join(format!("nomadnet-pages-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("index.mu"), ">Index").unwrap();
+        std::fs::write(dir.join("sub").join("about.mu"), ">About").unwrap();
+        std::fs::write(dir.join("index.mu.allowed"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let transport = Arc::new(Transport::new(Default::default()));
+        let mut node = Node::new(transport, PrivateIdentity::new_from_name("node"), "test");
+        assert_eq!(node.add_pages_dir(&dir).unwrap(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(node.entries.contains_key(INDEX_PATH));
+        assert!(node.entries.contains_key("/page/sub/about.mu"));
+    }
+}
@@ -1,12 +1,18 @@
+use alloc::vec::Vec;
 use core::fmt;
 
 use sha2::Digest;
 
-use crate::buffer::StaticBuffer;
+use crate::buffer::{InputBuffer, OutputBuffer, StaticBuffer};
+use crate::error::RnsError;
 use crate::hash::AddressHash;
 use crate::hash::Hash;
+use crate::hash::ADDRESS_HASH_SIZE;
+use crate::serde::Serialize;
 
 pub const PACKET_MDU: usize = 2048usize;
+/// Largest serialized packet: header, transport id, destination, context and data.
+pub const PACKET_MAX_SIZE: usize = 2 + 2 * ADDRESS_HASH_SIZE + 1 + PACKET_MDU;
 pub const PACKET_IFAC_MAX_LENGTH: usize = 64usize;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -41,6 +47,22 @@ impl From<u8> for HeaderType {
     }
 }
 
+/// Set on announces which carry a ratchet key.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ContextFlag {
+    Unset = 0b0,
+    Set = 0b1,
+}
+
+impl From<u8> for ContextFlag {
+    fn from(value: u8) -> Self {
+        match value & 0b1 {
+            0 => ContextFlag::Unset,
+            _ => ContextFlag::Set,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum PropagationType {
     Broadcast = 0b00,
@@ -103,32 +125,39 @@ impl From<u8> for PacketType {
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum PacketContext {
-    None = 0x00,                    // Generic data packet
-    Resource = 0x01,                // Packet is part of a resource
-    ResourceAdvertisement = 0x02,    // Packet is a resource advertisement
-    ResourceRequest = 0x03,         // Packet is a resource part request
-    ResourceHashUpdate = 0x04,      // Packet is a resource hashmap update
-    ResourceProof = 0x05,           // Packet is a resource proof
-    ResourceInitiatorCancel = 0x06, // Packet is a resource initiator cancel message
-    ResourceReceiverCancel = 0x07,  // Packet is a resource receiver cancel message
-    CacheRequest = 0x08,            // Packet is a cache request
-    Request = 0x09,                 // Packet is a request
-    Response = 0x0A,                // Packet is a response to a request
-    PathResponse = 0x0B,            // Packet is a response to a path request
-    Command = 0x0C,                 // Packet is a command
-    CommandStatus = 0x0D,           // Packet is a status of an executed command
-    Channel = 0x0E,                 // Packet contains link channel data
-    KeepAlive = 0xFA,               // Packet is a keepalive packet
-    LinkIdentify = 0xFB,            // Packet is a link peer identification proof
-    LinkClose = 0xFC,               // Packet is a link close message
-    LinkProof = 0xFD,               // Packet is a link packet proof
-    LinkRTT = 0xFE,                 // Packet is a link request round-trip time measurement
-    LinkRequestProof = 0xFF,        // Packet is a link request proof
+    None,                   // 0x00: Generic data packet
+    Resource,               // 0x01: Packet is part of a resource
+    ResourceAdvertisement,  // 0x02: Packet is a resource advertisement
+    ResourceRequest,        // 0x03: Packet is a resource part request
+    ResourceHashUpdate,     // 0x04: Packet is a resource hashmap update
+    ResourceProof,          // 0x05: Packet is a resource proof
+    ResourceInitiatorCancel, // 0x06: Packet is a resource initiator cancel message
+    ResourceReceiverCancel, // 0x07: Packet is a resource receiver cancel message
+    CacheRequest,           // 0x08: Packet is a cache request
+    Request,                // 0x09: Packet is a request
+    Response,               // 0x0A: Packet is a response to a request
+    PathResponse,           // 0x0B: Packet is a response to a path request
+    Command,                // 0x0C: Packet is a command
+    CommandStatus,          // 0x0D: Packet is a status of an executed command
+    Channel,                // 0x0E: Packet contains link channel data
+    LinkCompression,        // 0xF0: Packet offers or accepts link compression, not in Python
+    Compressed,             // 0xF1: Packet contains compressed link data, not in Python
+    Sequenced,              // 0xF2: Packet contains numbered link data, not in Python
+    KeepAlive,              // 0xFA: Packet is a keepalive packet
+    LinkIdentify,           // 0xFB: Packet is a link peer identification proof
+    LinkClose,              // 0xFC: Packet is a link close message
+    LinkProof,              // 0xFD: Packet is a link packet proof
+    LinkRTT,                // 0xFE: Packet is a link request round-trip time measurement
+    LinkRequestProof,       // 0xFF: Packet is a link request proof
+    /// A context this implementation doesn't know. The value is kept, so
+    /// such packets can still be forwarded unchanged.
+    Unknown(u8),
 }
 
 impl From<u8> for PacketContext {
     fn from(value: u8) -> Self {
         match value {
+            0x00 => PacketContext::None,
             0x01 => PacketContext::Resource,
             0x02 => PacketContext::ResourceAdvertisement,
             0x03 => PacketContext::ResourceRequest,
@@ -143,13 +172,74 @@ impl From<u8> for PacketContext {
             0x0C => PacketContext::Command,
             0x0D => PacketContext::CommandStatus,
             0x0E => PacketContext::Channel,
+            0xF0 => PacketContext::LinkCompression,
+            0xF1 => PacketContext::Compressed,
+            0xF2 => PacketContext::Sequenced,
             0xFA => PacketContext::KeepAlive,
             0xFB => PacketContext::LinkIdentify,
             0xFC => PacketContext::LinkClose,
             0xFD => PacketContext::LinkProof,
             0xFE => PacketContext::LinkRTT,
             0xFF => PacketContext::LinkRequestProof,
-            _ => PacketContext::None,
+            _ => PacketContext::Unknown(value),
+        }
+    }
+}
+
+impl PacketContext {
+    /// Whether the context is only used in packets of a link.
+    pub fn is_link_only(&self) -> bool {
+        matches!(
+            self,
+            PacketContext::Resource
+                | PacketContext::ResourceAdvertisement
+                | PacketContext::ResourceRequest
+                | PacketContext::ResourceHashUpdate
+                | PacketContext::ResourceProof
+                | PacketContext::ResourceInitiatorCancel
+                | PacketContext::ResourceReceiverCancel
+                | PacketContext::Request
+                | PacketContext::Response
+                | PacketContext::Channel
+                | PacketContext::LinkCompression
+                | PacketContext::Compressed
+                | PacketContext::Sequenced
+                | PacketContext::KeepAlive
+                | PacketContext::LinkIdentify
+                | PacketContext::LinkClose
+                | PacketContext::LinkRTT
+        )
+    }
+}
+
+impl From<PacketContext> for u8 {
+    fn from(context: PacketContext) -> Self {
+        match context {
+            PacketContext::None => 0x00,
+            PacketContext::Resource => 0x01,
+            PacketContext::ResourceAdvertisement => 0x02,
+            PacketContext::ResourceRequest => 0x03,
+            PacketContext::ResourceHashUpdate => 0x04,
+            PacketContext::ResourceProof => 0x05,
+            PacketContext::ResourceInitiatorCancel => 0x06,
+            PacketContext::ResourceReceiverCancel => 0x07,
+            PacketContext::CacheRequest => 0x08,
+            PacketContext::Request => 0x09,
+            PacketContext::Response => 0x0A,
+            PacketContext::PathResponse => 0x0B,
+            PacketContext::Command => 0x0C,
+            PacketContext::CommandStatus => 0x0D,
+            PacketContext::Channel => 0x0E,
+            PacketContext::LinkCompression => 0xF0,
+            PacketContext::Compressed => 0xF1,
+            PacketContext::Sequenced => 0xF2,
+            PacketContext::KeepAlive => 0xFA,
+            PacketContext::LinkIdentify => 0xFB,
+            PacketContext::LinkClose => 0xFC,
+            PacketContext::LinkProof => 0xFD,
+            PacketContext::LinkRTT => 0xFE,
+            PacketContext::LinkRequestProof => 0xFF,
+            PacketContext::Unknown(value) => value,
         }
     }
 }
@@ -158,6 +248,7 @@ impl From<u8> for PacketContext {
 pub struct Header {
     pub ifac_flag: IfacFlag,
     pub header_type: HeaderType,
+    pub context_flag: ContextFlag,
     pub propagation_type: PropagationType,
     pub destination_type: DestinationType,
     pub packet_type: PacketType,
@@ -169,6 +260,7 @@ impl Default for Header {
         Self {
             ifac_flag: IfacFlag::Open,
             header_type: HeaderType::Type1,
+            context_flag: ContextFlag::Unset,
             propagation_type: PropagationType::Broadcast,
             destination_type: DestinationType::Single,
             packet_type: PacketType::Data,
@@ -181,6 +273,7 @@ impl Header {
     pub fn to_meta(&self) -> u8 {
         (self.ifac_flag as u8) << 7
             | (self.header_type as u8) << 6
+            | (self.context_flag as u8) << 5
             | (self.propagation_type as u8) << 4
             | (self.destination_type as u8) << 2
             | (self.packet_type as u8) //<< 0
@@ -190,7 +283,9 @@ impl Header {
         Self {
             ifac_flag: IfacFlag::from(meta >> 7),
             header_type: HeaderType::from(meta >> 6),
-            propagation_type: PropagationType::from(meta >> 4),
+            context_flag: ContextFlag::from(meta >> 5),
+            // The propagation type is a single bit next to the context flag
+            propagation_type: PropagationType::from((meta >> 4) & 0b1),
             destination_type: DestinationType::from(meta >> 2),
             packet_type: PacketType::from(meta /*>> 0*/),
             hops: 0,
@@ -202,9 +297,10 @@ impl fmt::Display for Header {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{:b}{:b}{:0>2b}{:0>2b}{:0>2b}.{}",
+            "{:b}{:b}{:b}{:b}{:0>2b}{:0>2b}.{}",
             self.ifac_flag as u8,
             self.header_type as u8,
+            self.context_flag as u8,
             self.propagation_type as u8,
             self.destination_type as u8,
             self.packet_type as u8,
@@ -247,13 +343,67 @@ pub struct Packet {
 }
 
 impl Packet {
+    /// SHA-256 over the hashable part of the packet, the same value as
+    /// Python's `Packet.get_hash()`. The hashable part leaves out the hops,
+    /// the transport id, the header type and IFAC, so the hash stays the
+    /// same while a packet travels through the network.
+    pub fn packet_hash(&self) -> Hash {
+        self.hash_with_data(self.data.as_slice())
+    }
+
+    /// The first 16 bytes of [`Packet::packet_hash`], the same value as
+    /// Python's `Packet.getTruncatedHash()`.
+    pub fn truncated_hash(&self) -> AddressHash {
+        AddressHash::new_from_hash(&self.packet_hash())
+    }
+
+    /// Same as [`Packet::packet_hash`].
     pub fn hash(&self) -> Hash {
+        self.packet_hash()
+    }
+
+    /// Length of the packet on the wire, without IFAC.
+    pub fn wire_len(&self) -> usize {
+        let transport_len = match self.header.header_type {
+            HeaderType::Type1 => 0,
+            HeaderType::Type2 => ADDRESS_HASH_SIZE,
+        };
+
+        2 + transport_len + ADDRESS_HASH_SIZE + 1 + self.data.len()
+    }
+
+    /// Writes the packet as sent on the wire, without IFAC, to the start of
+    /// `buffer` and returns its length. Fails if `buffer` is shorter than
+    /// [`Packet::wire_len`] or a Type2 packet has no transport id.
+    pub fn to_wire(&self, buffer: &mut [u8]) -> Result<usize, RnsError> {
+        let mut output = OutputBuffer::new(buffer);
+        self.serialize(&mut output)?;
+
+        Ok(output.offset())
+    }
+
+    /// Like [`Packet::to_wire`], into a new vector.
+    pub fn to_wire_vec(&self) -> Result<Vec<u8>, RnsError> {
+        let mut buffer = [0u8; PACKET_MAX_SIZE];
+        let len = self.to_wire(&mut buffer)?;
+
+        Ok(buffer[..len].to_vec())
+    }
+
+    /// Reads a packet from its wire bytes, the inverse of
+    /// [`Packet::to_wire`].
+    pub fn from_wire(bytes: &[u8]) -> Result<Self, RnsError> {
+        Self::deserialize(&mut InputBuffer::new(bytes))
+    }
+
+    /// Hashes the hashable part with `data` in place of the packet data.
+    pub(crate) fn hash_with_data(&self, data: &[u8]) -> Hash {
         Hash::new(
             Hash::generator()
                 .chain_update([self.header.to_meta() & 0b00001111])
                 .chain_update(self.destination.as_slice())
-                .chain_update([self.context as u8])
-                .chain_update(self.data.as_slice())
+                .chain_update([u8::from(self.context)])
+                .chain_update(data)
                 .finalize()
                 .into(),
         )
@@ -288,3 +438,363 @@ impl fmt::Display for Packet {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketBuildError {
+    /// No destination was given.
+    NoDestination,
+    /// The data is longer than [`PACKET_MDU`].
+    DataTooLong(usize),
+    /// Packets of the type can't be sent to destinations of the type.
+    DestinationType(PacketType, DestinationType),
+    /// The context can't be used in packets of the type to destinations of
+    /// the type.
+    Context(PacketContext, PacketType, DestinationType),
+    /// Only announces carry the context flag.
+    ContextFlag(PacketType),
+}
+
+impl fmt::Display for PacketBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PacketBuildError::NoDestination => write!(f, "packet has no destination"),
+            PacketBuildError::DataTooLong(len) => {
+                write!(f, "packet data of {} bytes exceeds {} bytes", len, PACKET_MDU)
+            }
+            PacketBuildError::DestinationType(packet_type, destination_type) => write!(
+                f,
+                "{:?} packets can't be sent to {:?} destinations",
+                packet_type, destination_type
+            ),
+            PacketBuildError::Context(context, packet_type, destination_type) => write!(
+                f,
+                "context {:?} can't be used in {:?} packets to {:?} destinations",
+                context, packet_type, destination_type
+            ),
+            PacketBuildError::ContextFlag(packet_type) => {
+                write!(f, "{:?} packets can't carry the context flag", packet_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PacketBuildError {}
+
+/// Builds packets with header fields which fit together.
+///
+/// The packet type and destination are set by one of [`announce`],
+/// [`data_to`], [`link_request`] or [`proof_to`], [`via_transport`] sets the
+/// header type and propagation type for a packet sent through a transport
+/// node. [`build`] checks that the destination type and context are valid for
+/// the packet type.
+///
+/// ```
+/// # use reticulum::hash::AddressHash;
+/// # use reticulum::packet::{PacketBuilder, PacketContext, DestinationType};
+/// let link_id = AddressHash::new([0x11; 16]);
+/// let packet = PacketBuilder::new()
+///     .data_to(link_id)
+///     .destination_type(DestinationType::Link)
+///     .context(PacketContext::Channel)
+///     .data(b"hello")
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// [`announce`]: PacketBuilder::announce
+/// [`data_to`]: PacketBuilder::data_to
+/// [`link_request`]: PacketBuilder::link_request
+/// [`proof_to`]: PacketBuilder::proof_to
+/// [`via_transport`]: PacketBuilder::via_transport
+/// [`build`]: PacketBuilder::build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PacketBuilder {
+    header: Header,
+    destination: Option<AddressHash>,
+    transport: Option<AddressHash>,
+    context: Option<PacketContext>,
+    data: PacketDataBuffer,
+    data_len: usize,
+}
+
+impl PacketBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn to(mut self, packet_type: PacketType, destination: AddressHash) -> Self {
+        self.header.packet_type = packet_type;
+        self.destination = Some(destination);
+        self
+    }
+
+    /// Announce of the single destination `destination`.
+    pub fn announce(self, destination: AddressHash) -> Self {
+        self.to(PacketType::Announce, destination)
+    }
+
+    /// Data for `destination`, a single destination unless changed with
+    /// [`PacketBuilder::destination_type`].
+    pub fn data_to(self, destination: AddressHash) -> Self {
+        self.to(PacketType::Data, destination)
+    }
+
+    /// Request for a link to the single destination `destination`.
+    pub fn link_request(self, destination: AddressHash) -> Self {
+        self.to(PacketType::LinkRequest, destination)
+    }
+
+    /// Proof of a packet received from `destination`, or of a link if
+    /// `destination` is a link id.
+    pub fn proof_to(self, destination: AddressHash) -> Self {
+        self.to(PacketType::Proof, destination)
+    }
+
+    pub fn destination_type(mut self, destination_type: DestinationType) -> Self {
+        self.header.destination_type = destination_type;
+        self
+    }
+
+    /// Sends the packet through the transport node `transport_id` instead of
+    /// broadcasting it.
+    pub fn via_transport(mut self, transport_id: AddressHash) -> Self {
+        self.header.header_type = HeaderType::Type2;
+        self.header.propagation_type = PropagationType::Transport;
+        self.transport = Some(transport_id);
+        self
+    }
+
+    pub fn context(mut self, context: PacketContext) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Sets the context flag, which marks announces carrying a ratchet key.
+    pub fn with_ratchet(mut self) -> Self {
+        self.header.context_flag = ContextFlag::Set;
+        self
+    }
+
+    pub fn hops(mut self, hops: u8) -> Self {
+        self.header.hops = hops;
+        self
+    }
+
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.data = PacketDataBuffer::new_from_slice(data);
+        self.data_len = data.len();
+        self
+    }
+
+    pub fn build(self) -> Result<Packet, PacketBuildError> {
+        let destination = self.destination.ok_or(PacketBuildError::NoDestination)?;
+
+        if self.data_len > PACKET_MDU {
+            return Err(PacketBuildError::DataTooLong(self.data_len));
+        }
+
+        let packet_type = self.header.packet_type;
+        let destination_type = self.header.destination_type;
+
+        let single_only = matches!(packet_type, PacketType::Announce | PacketType::LinkRequest);
+        if single_only && destination_type != DestinationType::Single {
+            return Err(PacketBuildError::DestinationType(packet_type, destination_type));
+        }
+
+        let context = self.context.unwrap_or(PacketContext::None);
+        let valid_context = match context {
+            PacketContext::PathResponse => packet_type == PacketType::Announce,
+            PacketContext::LinkRequestProof => packet_type == PacketType::Proof,
+            context if context.is_link_only() => destination_type == DestinationType::Link,
+            _ => true,
+        };
+        if !valid_context {
+            return Err(PacketBuildError::Context(context, packet_type, destination_type));
+        }
+
+        if self.header.context_flag == ContextFlag::Set && packet_type != PacketType::Announce {
+            return Err(PacketBuildError::ContextFlag(packet_type));
+        }
+
+        Ok(Packet {
+            header: self.header,
+            ifac: None,
+            destination,
+            transport: self.transport,
+            context,
+            data: self.data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values computed with Python's Packet.get_hash() from the raw packets
+    #[test]
+    fn packet_hash_matches_python() {
+        // 00 03 | 11 * 16 | 00 | "hello"
+        let packet = Packet {
+            header: Header {
+                hops: 3,
+                ..Default::default()
+            },
+            destination: AddressHash::new([0x11; 16]),
+            data: PacketDataBuffer::new_from_slice(b"hello"),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            packet.packet_hash().to_string(),
+            "cb7fbc6a98327b5bf0567f60804e1d777e7445d3bc522d48ad423c4cc48dfd37"
+        );
+        assert_eq!(packet.truncated_hash().to_hex_string(), "cb7fbc6a98327b5bf0567f60804e1d77");
+
+        // 5c 05 | 22 * 16 | 33 * 16 | 0e | 01 02 03
+        let packet = Packet {
+            header: Header {
+                header_type: HeaderType::Type2,
+                propagation_type: PropagationType::Transport,
+                destination_type: DestinationType::Link,
+                hops: 5,
+                ..Default::default()
+            },
+            transport: Some(AddressHash::new([0x22; 16])),
+            destination: AddressHash::new([0x33; 16]),
+            context: PacketContext::Channel,
+            data: PacketDataBuffer::new_from_slice(&[1, 2, 3]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            packet.packet_hash().to_string(),
+            "9cdec2fab2c10ba49cd558aa890b763ecfa0d8f38440ac42b322d43a65fb9fb8"
+        );
+        assert_eq!(packet.truncated_hash().to_hex_string(), "9cdec2fab2c10ba49cd558aa890b763e");
+
+        // Neither hops nor the transport id change the hash
+        let forwarded = Packet {
+            header: Header {
+                header_type: HeaderType::Type1,
+                propagation_type: PropagationType::Broadcast,
+                hops: 6,
+                ..packet.header
+            },
+            transport: None,
+            ..packet
+        };
+        assert_eq!(forwarded.packet_hash(), packet.packet_hash());
+    }
+
+    #[test]
+    fn context_byte_round_trip() {
+        for value in 0..=u8::MAX {
+            assert_eq!(u8::from(PacketContext::from(value)), value);
+        }
+
+        assert_eq!(PacketContext::from(0x0E), PacketContext::Channel);
+        assert_eq!(PacketContext::from(0x10), PacketContext::Unknown(0x10));
+
+        // Unknown contexts are part of the hash like any other
+        let packet = Packet {
+            context: PacketContext::Unknown(0x10),
+            ..Default::default()
+        };
+        assert_ne!(packet.packet_hash(), Packet::default().packet_hash());
+    }
+
+    #[test]
+    fn builder_sets_and_checks_fields() {
+        let destination = AddressHash::new([0x11; 16]);
+        let transport = AddressHash::new([0x22; 16]);
+
+        let packet = PacketBuilder::new()
+            .data_to(destination)
+            .via_transport(transport)
+            .data(b"hello")
+            .build()
+            .unwrap();
+        assert_eq!(packet.header.packet_type, PacketType::Data);
+        assert_eq!(packet.header.header_type, HeaderType::Type2);
+        assert_eq!(packet.header.propagation_type, PropagationType::Transport);
+        assert_eq!(packet.transport, Some(transport));
+        assert_eq!(packet.destination, destination);
+        assert_eq!(packet.context, PacketContext::None);
+        assert_eq!(packet.data.as_slice(), b"hello");
+
+        let announce = PacketBuilder::new()
+            .announce(destination)
+            .context(PacketContext::PathResponse)
+            .with_ratchet()
+            .build()
+            .unwrap();
+        assert_eq!(announce.header.header_type, HeaderType::Type1);
+        assert_eq!(announce.header.propagation_type, PropagationType::Broadcast);
+        assert_eq!(announce.header.context_flag, ContextFlag::Set);
+
+        assert_eq!(PacketBuilder::new().build(), Err(PacketBuildError::NoDestination));
+        assert_eq!(
+            PacketBuilder::new().data_to(destination).data(&[0u8; PACKET_MDU + 1]).build(),
+            Err(PacketBuildError::DataTooLong(PACKET_MDU + 1))
+        );
+        assert_eq!(
+            PacketBuilder::new()
+                .announce(destination)
+                .destination_type(DestinationType::Plain)
+                .build(),
+            Err(PacketBuildError::DestinationType(PacketType::Announce, DestinationType::Plain))
+        );
+        assert_eq!(
+            PacketBuilder::new().data_to(destination).context(PacketContext::Channel).build(),
+            Err(PacketBuildError::Context(
+                PacketContext::Channel,
+                PacketType::Data,
+                DestinationType::Single
+            ))
+        );
+        assert_eq!(
+            PacketBuilder::new().data_to(destination).context(PacketContext::PathResponse).build(),
+            Err(PacketBuildError::Context(
+                PacketContext::PathResponse,
+                PacketType::Data,
+                DestinationType::Single
+            ))
+        );
+        assert_eq!(
+            PacketBuilder::new().link_request(destination).with_ratchet().build(),
+            Err(PacketBuildError::ContextFlag(PacketType::LinkRequest))
+        );
+    }
+
+    #[test]
+    fn wire_bytes() {
+        let packet = PacketBuilder::new()
+            .data_to(AddressHash::new([0x33; 16]))
+            .via_transport(AddressHash::new([0x22; 16]))
+            .context(PacketContext::Unknown(0x10))
+            .hops(5)
+            .data(&[1, 2, 3])
+            .build()
+            .unwrap();
+
+        let wire = packet.to_wire_vec().unwrap();
+        assert_eq!(wire.len(), packet.wire_len());
+        assert_eq!(wire.len(), 2 + 16 + 16 + 1 + 3);
+        assert_eq!(&wire[..2], &[0x50, 5]);
+        assert_eq!(wire[34], 0x10);
+
+        let mut short = [0u8; 37];
+        assert_eq!(packet.to_wire(&mut short), Err(RnsError::OutOfMemory));
+        let mut buffer = [0u8; 64];
+        assert_eq!(packet.to_wire(&mut buffer), Ok(wire.len()));
+        assert_eq!(&buffer[..wire.len()], wire.as_slice());
+
+        let decoded = Packet::from_wire(&wire).unwrap();
+        assert_eq!(decoded, packet);
+        assert_eq!(decoded.packet_hash(), packet.packet_hash());
+
+        let announce = Packet { header: Header::default(), transport: None, ..packet };
+        assert_eq!(announce.wire_len(), 2 + 16 + 1 + 3);
+    }
+}
@@ -0,0 +1,127 @@
+//! Clock, timers and task spawning of the async runtime.
+//!
+//! Native builds run on tokio and use its clock, so tests can pause time. In
+//! the browser (`wasm32`) there is neither a tokio runtime nor a usable
+//! `std::time`: tasks are spawned on the JavaScript event loop, timers are
+//! backed by `setTimeout` and the clock is `performance.now()`.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tokio::time::{error::Elapsed, sleep, sleep_until, timeout, Instant};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use tokio::task::yield_now;
+
+#[cfg(target_arch = "wasm32")]
+pub use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Future returned by [`Clock::sleep`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Future returned by [`Clock::sleep`].
+#[cfg(target_arch = "wasm32")]
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Source of time for a transport, see
+/// [`TransportConfig::set_clock`](crate::transport::TransportConfig::set_clock).
+///
+/// The default [`RuntimeClock`] follows the runtime. Tests can substitute a
+/// clock which they move forward by hand to reach link timeouts and path
+/// expiry without waiting, targets without tokio one backed by a tick counter.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Completes once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> SleepFuture;
+}
+
+/// The clock of the async runtime.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RuntimeClock;
+
+impl Clock for RuntimeClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        Box::pin(sleep(duration))
+    }
+}
+
+/// Spawns a task which runs in the background until it completes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(future);
+}
+
+/// Spawns a task which runs in the background until it completes.
+#[cfg(target_arch = "wasm32")]
+pub fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + 'static,
+{
+    wasm_bindgen_futures::spawn_local(future);
+}
+
+/// Runs CPU bound work off the async executor where there is a thread pool
+/// for it, the browser runs it in place. Returns `None` if the work panicked.
+pub async fn run_blocking<F, R>(work: F) -> Option<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    return tokio::task::spawn_blocking(work).await.ok();
+
+    #[cfg(target_arch = "wasm32")]
+    return Some(work());
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await
+}
+
+/// Error returned by [`timeout`] when the deadline passed first.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Elapsed;
+
+#[cfg(target_arch = "wasm32")]
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    tokio::select! {
+        output = future => Ok(output),
+        _ = sleep(duration) => Err(Elapsed),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn sleep_until(deadline: Instant) {
+    sleep(deadline.saturating_duration_since(Instant::now())).await
+}
+
+/// Lets the other tasks on the event loop run before this one continues.
+#[cfg(target_arch = "wasm32")]
+pub async fn yield_now() {
+    let mut yielded = false;
+    core::future::poll_fn(|cx| {
+        if yielded {
+            return core::task::Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        core::task::Poll::Pending
+    })
+    .await
+}
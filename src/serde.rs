@@ -22,7 +22,7 @@ impl Serialize for Header {
 }
 impl Serialize for PacketContext {
     fn serialize(&self, buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
-        buffer.write(&[*self as u8])
+        buffer.write(&[u8::from(*self)])
     }
 }
 
@@ -30,10 +30,13 @@ impl Serialize for Packet {
     fn serialize(&self, buffer: &mut OutputBuffer) -> Result<usize, RnsError> {
         self.header.serialize(buffer)?;
 
+        // Type2 headers carry the transport id in front of the destination,
+        // the header type alone tells the receiver whether it is there
         if self.header.header_type == HeaderType::Type2 {
-            if let Some(transport) = &self.transport {
-                transport.serialize(buffer)?;
-            }
+            match &self.transport {
+                Some(transport) => transport.serialize(buffer)?,
+                None => return Err(RnsError::PacketError),
+            };
         }
 
         self.destination.serialize(buffer)?;
@@ -91,7 +94,7 @@ impl Packet {
             data: StaticBuffer::new(),
         };
 
-        buffer.read(packet.data.accuire_buf(buffer.bytes_left()))?;
+        buffer.read(packet.data.try_acquire_buf(buffer.bytes_left())?)?;
 
         Ok(packet)
     }
@@ -105,8 +108,8 @@ mod tests {
         buffer::{InputBuffer, OutputBuffer, StaticBuffer},
         hash::AddressHash,
         packet::{
-            DestinationType, Header, HeaderType, IfacFlag, Packet, PacketContext, PacketType,
-            PropagationType,
+            ContextFlag, DestinationType, Header, HeaderType, IfacFlag, Packet, PacketContext, PacketType,
+            PropagationType, PACKET_MDU,
         },
     };
 
@@ -122,6 +125,7 @@ mod tests {
             header: Header {
                 ifac_flag: IfacFlag::Open,
                 header_type: HeaderType::Type1,
+                context_flag: ContextFlag::Unset,
                 propagation_type: PropagationType::Broadcast,
                 destination_type: DestinationType::Single,
                 packet_type: PacketType::Announce,
@@ -149,6 +153,7 @@ mod tests {
             header: Header {
                 ifac_flag: IfacFlag::Open,
                 header_type: HeaderType::Type1,
+                context_flag: ContextFlag::Unset,
                 propagation_type: PropagationType::Broadcast,
                 destination_type: DestinationType::Single,
                 packet_type: PacketType::Announce,
@@ -175,4 +180,90 @@ mod tests {
         assert_eq!(packet.context, new_packet.context);
         assert_eq!(packet.data.as_slice(), new_packet.data.as_slice());
     }
+
+    #[test]
+    fn type2_round_trip() {
+        let transport = AddressHash::new([0x11; 16]);
+        let destination = AddressHash::new([0x22; 16]);
+
+        let packet_types = [
+            (PacketType::Data, DestinationType::Single, PacketContext::None),
+            (PacketType::Data, DestinationType::Link, PacketContext::Channel),
+            (PacketType::Announce, DestinationType::Single, PacketContext::PathResponse),
+            (PacketType::LinkRequest, DestinationType::Single, PacketContext::None),
+            (PacketType::Proof, DestinationType::Link, PacketContext::LinkRequestProof),
+            (PacketType::Proof, DestinationType::Single, PacketContext::None),
+        ];
+
+        for (packet_type, destination_type, context) in packet_types {
+            let mut packet = Packet {
+                header: Header {
+                    ifac_flag: IfacFlag::Open,
+                    header_type: HeaderType::Type2,
+                    context_flag: ContextFlag::Unset,
+                    propagation_type: PropagationType::Transport,
+                    destination_type,
+                    packet_type,
+                    hops: 3,
+                },
+                ifac: None,
+                destination,
+                transport: Some(transport),
+                context,
+                data: StaticBuffer::new(),
+            };
+            packet.data.safe_write(b"payload");
+
+            let mut output_data = [0u8; 4096];
+            let mut buffer = OutputBuffer::new(&mut output_data);
+            packet.serialize(&mut buffer).expect("serialized packet");
+
+            let raw = buffer.as_slice();
+            assert_eq!(raw.len(), 2 + 16 + 16 + 1 + 7);
+            assert_eq!(raw[0] >> 6, HeaderType::Type2 as u8);
+            assert_eq!(raw[1], 3);
+            assert_eq!(&raw[2..18], transport.as_slice());
+            assert_eq!(&raw[18..34], destination.as_slice());
+            assert_eq!(raw[34], u8::from(context));
+
+            let new_packet =
+                Packet::deserialize(&mut InputBuffer::new(raw)).expect("deserialized packet");
+
+            assert_eq!(packet.header, new_packet.header);
+            assert_eq!(new_packet.transport, Some(transport));
+            assert_eq!(new_packet.destination, destination);
+            assert_eq!(new_packet.context, context);
+            assert_eq!(new_packet.data.as_slice(), b"payload");
+            assert_eq!(new_packet.hash(), packet.hash());
+        }
+    }
+
+    #[test]
+    fn type2_requires_transport() {
+        let packet = Packet {
+            header: Header {
+                header_type: HeaderType::Type2,
+                ..Default::default()
+            },
+            transport: None,
+            ..Default::default()
+        };
+
+        let mut output_data = [0u8; 4096];
+        let mut buffer = OutputBuffer::new(&mut output_data);
+        assert!(packet.serialize(&mut buffer).is_err());
+
+        // Too short for both addresses
+        let raw = [0x40, 0x00, 0x11, 0x22];
+        assert!(Packet::deserialize(&mut InputBuffer::new(&raw)).is_err());
+    }
+
+    #[test]
+    fn oversized_data_is_rejected() {
+        // Header, destination and context of a Type1 packet, then one byte
+        // more data than a packet holds
+        let raw = [0u8; 2 + 16 + 1 + PACKET_MDU + 1];
+        assert!(Packet::deserialize(&mut InputBuffer::new(&raw[..raw.len() - 1])).is_ok());
+        assert!(Packet::deserialize(&mut InputBuffer::new(&raw)).is_err());
+    }
 }
@@ -0,0 +1,323 @@
+//! [serde](https://serde.rs) support of the core types, with the `serde`
+//! feature.
+//!
+//! Hashes, identities and packets are byte strings: lowercase hex in
+//! human-readable formats such as JSON, plain bytes in binary ones. Packets
+//! take their wire form. Paths store how long ago they were announced, since
+//! an [`Instant`] means nothing to another process.
+
+use core::fmt;
+use core::fmt::Write;
+use std::time::Duration;
+
+use ::serde::de::{self, Deserializer, SeqAccess, Visitor};
+use ::serde::ser::Serializer;
+use ::serde::{Deserialize, Serialize};
+
+use crate::destination::{DestinationDesc, DestinationName};
+use crate::hash::{AddressHash, Hash, ADDRESS_HASH_SIZE, HASH_SIZE};
+use crate::identity::{Identity, PUBLIC_KEY_LENGTH};
+use crate::iface::InterfaceMode;
+use crate::packet::Packet;
+use crate::runtime::Instant;
+use crate::transport::PathEntry;
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            write!(&mut hex, "{:02x}", byte).unwrap();
+        }
+        serializer.serialize_str(&hex)
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+/// Takes hex strings, bytes and sequences of bytes, whichever the format has.
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("bytes or a hex string")
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<Self::Value, E> {
+        if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+            return Err(E::invalid_value(de::Unexpected::Str(hex), &self));
+        }
+
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(hex), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(BytesVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+/// Bytes of exactly `N` bytes.
+fn deserialize_array<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    let bytes = deserialize_bytes(deserializer)?;
+    let len = bytes.len();
+
+    bytes
+        .try_into()
+        .map_err(|_| de::Error::invalid_length(len, &format!("{} bytes", N).as_str()))
+}
+
+impl Serialize for AddressHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.as_slice(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AddressHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array::<D, ADDRESS_HASH_SIZE>(deserializer).map(AddressHash::new)
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(self.as_slice(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_array::<D, HASH_SIZE>(deserializer).map(Hash::new)
+    }
+}
+
+/// The encryption key followed by the signing key, as in announces.
+impl Serialize for Identity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut keys = [0u8; PUBLIC_KEY_LENGTH * 2];
+        keys[..PUBLIC_KEY_LENGTH].copy_from_slice(self.public_key_bytes());
+        keys[PUBLIC_KEY_LENGTH..].copy_from_slice(self.verifying_key_bytes());
+
+        serialize_bytes(&keys, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Identity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let keys = deserialize_array::<D, { PUBLIC_KEY_LENGTH * 2 }>(deserializer)?;
+
+        Ok(Identity::new_from_slices(&keys[..PUBLIC_KEY_LENGTH], &keys[PUBLIC_KEY_LENGTH..]))
+    }
+}
+
+/// Names known by their hash only have no full name.
+#[derive(Serialize, Deserialize)]
+struct NameRepr {
+    hash: Hash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    full_name: Option<String>,
+}
+
+impl Serialize for DestinationName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NameRepr { hash: self.hash, full_name: self.full_name().map(String::from) }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DestinationName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = NameRepr::deserialize(deserializer)?;
+
+        match repr.full_name {
+            Some(full_name) => {
+                let name = DestinationName::new_from_full_name(&full_name);
+                if name.hash != repr.hash {
+                    return Err(de::Error::custom(format!("hash doesn't match name {}", full_name)));
+                }
+                Ok(name)
+            }
+            None => Ok(DestinationName::new_from_hash_slice(repr.hash.as_slice())),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct DestinationDescRepr {
+    identity: Identity,
+    address_hash: AddressHash,
+    name: DestinationName,
+}
+
+impl Serialize for DestinationDesc {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DestinationDescRepr {
+            identity: self.identity,
+            address_hash: self.address_hash,
+            name: self.name,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DestinationDesc {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DestinationDescRepr::deserialize(deserializer)?;
+
+        Ok(DestinationDesc {
+            identity: repr.identity,
+            address_hash: repr.address_hash,
+            name: repr.name,
+        })
+    }
+}
+
+/// The wire form without IFAC, see [`Packet::to_wire`].
+impl Serialize for Packet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let wire = self.to_wire_vec().map_err(|err| ::serde::ser::Error::custom(format!("{:?}", err)))?;
+
+        serialize_bytes(&wire, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Packet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let wire = deserialize_bytes(deserializer)?;
+
+        Packet::from_wire(&wire).map_err(|err| de::Error::custom(format!("invalid packet: {:?}", err)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PathEntryRepr {
+    received_from: AddressHash,
+    hops: u8,
+    iface: AddressHash,
+    mode: InterfaceMode,
+    #[serde(default)]
+    bitrate: Option<u64>,
+    /// Time since the path was announced.
+    age: Duration,
+}
+
+impl Serialize for PathEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PathEntryRepr {
+            received_from: self.received_from,
+            hops: self.hops,
+            iface: self.iface,
+            mode: self.mode,
+            bitrate: self.bitrate,
+            age: self.announced.elapsed(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = PathEntryRepr::deserialize(deserializer)?;
+        let now = Instant::now();
+
+        Ok(PathEntry {
+            received_from: repr.received_from,
+            hops: repr.hops,
+            iface: repr.iface,
+            mode: repr.mode,
+            bitrate: repr.bitrate,
+            announced: now.checked_sub(repr.age).unwrap_or(now),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use crate::destination::{DestinationName, SingleInputDestination};
+    use crate::identity::PrivateIdentity;
+    use crate::packet::PacketDataBuffer;
+
+    use super::*;
+
+    #[test]
+    fn hashes_are_hex_strings_in_json() {
+        let address = AddressHash::new_from_rand(OsRng);
+
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", address.to_hex_string()));
+        assert_eq!(serde_json::from_str::<AddressHash>(&json).unwrap(), address);
+
+        assert!(serde_json::from_str::<AddressHash>("\"abcd\"").is_err());
+        assert!(serde_json::from_str::<AddressHash>(&format!("\"{}\"", "zz".repeat(16))).is_err());
+    }
+
+    #[test]
+    fn destinations_and_packets_round_trip() {
+        let identity = PrivateIdentity::new_from_name("serde");
+        let destination = SingleInputDestination::new(identity, DestinationName::new("test", "serde"));
+
+        let json = serde_json::to_string(&destination.desc).unwrap();
+        let desc: DestinationDesc = serde_json::from_str(&json).unwrap();
+        assert_eq!(desc.address_hash, destination.desc.address_hash);
+        assert_eq!(desc.identity.address_hash, destination.desc.identity.address_hash);
+        assert_eq!(desc.name.full_name(), Some("test.serde"));
+
+        let packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"serde"),
+            destination: desc.address_hash,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&packet).unwrap();
+        let decoded: Packet = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.hash(), packet.hash());
+        assert_eq!(decoded.data.as_slice(), b"serde");
+    }
+
+    #[test]
+    fn path_entries_keep_their_age() {
+        let entry = PathEntry {
+            received_from: AddressHash::new_from_rand(OsRng),
+            hops: 3,
+            iface: AddressHash::new_from_rand(OsRng),
+            mode: InterfaceMode::AccessPoint,
+            bitrate: Some(9600),
+            announced: Instant::now() - Duration::from_secs(60),
+        };
+
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["mode"], "access_point");
+        assert_eq!(json["age"]["secs"], 60);
+
+        let decoded: PathEntry = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.hops, 3);
+        assert_eq!(decoded.iface, entry.iface);
+        assert!(decoded.announced.elapsed() >= Duration::from_secs(60));
+    }
+}
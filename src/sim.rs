@@ -0,0 +1,340 @@
+//! Simulated networks of transports for routing experiments.
+//!
+//! A [`SimNetwork`] connects transports over in-memory links and drives all
+//! of them from one [`SimClock`]. Time only passes when the harness calls
+//! [`SimNetwork::advance`], which fires the timers of every node in order of
+//! their deadlines and waits for the resulting traffic to die down. Announce
+//! convergence or path churn over minutes of network time can be checked in
+//! seconds, also in networks of thousands of nodes when built with
+//! optimizations.
+//!
+//! Simulations are best run on a current thread runtime, which is what
+//! `#[tokio::test]` uses.
+//!
+//! ```
+//! # use std::time::Duration;
+//! # use reticulum::destination::DestinationName;
+//! # use reticulum::identity::PrivateIdentity;
+//! # use reticulum::sim::SimNetwork;
+//! # use reticulum::transport::TransportConfig;
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let mut network = SimNetwork::new();
+//! let a = network.add_node(TransportConfig::new("a", &PrivateIdentity::new_from_name("a"), false));
+//! let b = network.add_node(TransportConfig::new("b", &PrivateIdentity::new_from_name("b"), false));
+//! network.connect(a, b).await;
+//!
+//! let destination = network
+//!     .node_mut(a)
+//!     .add_destination(PrivateIdentity::new_from_name("app"), DestinationName::new("example", "sim"))
+//!     .await;
+//! network.node(a).send_announce(&destination, None).await.unwrap();
+//! network.advance(Duration::from_secs(1)).await;
+//!
+//! let address = destination.lock().await.desc.address_hash;
+//! assert_eq!(network.node(b).paths(&address).await.len(), 1);
+//! # }
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use std::sync::Mutex;
+
+use tokio::sync::oneshot;
+
+use crate::iface::RxMessage;
+use crate::runtime::{self, Clock, Instant, SleepFuture};
+use crate::transport::{Transport, TransportConfig};
+
+/// Capacity of the queues of a simulated link.
+const LINK_QUEUE: usize = 64;
+
+/// Rounds without traffic after which the network is considered idle.
+const IDLE_ROUNDS: usize = 3;
+
+/// Task switches per round, enough for a packet to cross a node.
+const YIELDS_PER_ROUND: usize = 32;
+
+struct SimClockState {
+    now: Instant,
+    next_sleeper: u64,
+    sleepers: BTreeMap<(Instant, u64), oneshot::Sender<()>>,
+}
+
+/// A clock which only moves when it is told to, see [`SimNetwork::advance`].
+#[derive(Clone)]
+pub struct SimClock(Arc<Mutex<SimClockState>>);
+
+impl SimClock {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(SimClockState {
+            now: Instant::now(),
+            next_sleeper: 0,
+            sleepers: BTreeMap::new(),
+        })))
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimClockState> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Deadline of the sleeper which is due first.
+    fn next_deadline(&self) -> Option<Instant> {
+        self.state().sleepers.keys().next().map(|(deadline, _)| *deadline)
+    }
+
+    /// Moves the time forward to `instant` and wakes everybody who slept
+    /// until then.
+    fn move_to(&self, instant: Instant) {
+        let mut state = self.state();
+        state.now = state.now.max(instant);
+
+        let now = state.now;
+        let pending = state.sleepers.split_off(&(now, u64::MAX));
+        for (_, sleeper) in core::mem::replace(&mut state.sleepers, pending) {
+            let _ = sleeper.send(());
+        }
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SimClock {
+    fn now(&self) -> Instant {
+        self.state().now
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        if duration.is_zero() {
+            return Box::pin(core::future::ready(()));
+        }
+
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.state();
+            let key = (state.now + duration, state.next_sleeper);
+            state.next_sleeper += 1;
+            state.sleepers.insert(key, tx);
+        }
+
+        Box::pin(async move {
+            let _ = rx.await;
+        })
+    }
+}
+
+/// Transports connected by in-memory links, all running on one
+/// [`SimClock`].
+pub struct SimNetwork {
+    clock: SimClock,
+    nodes: Vec<Transport>,
+    /// Packets carried over any link so far.
+    carried: Arc<AtomicU64>,
+}
+
+impl SimNetwork {
+    pub fn new() -> Self {
+        Self {
+            clock: SimClock::new(),
+            nodes: Vec::new(),
+            carried: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    /// Builds a transport from `config` on the clock of the network and
+    /// returns its index.
+    pub fn add_node(&mut self, config: TransportConfig) -> usize {
+        self.nodes.push(config.set_clock(self.clock.clone()).build());
+        self.nodes.len() - 1
+    }
+
+    pub fn node(&self, index: usize) -> &Transport {
+        &self.nodes[index]
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> &mut Transport {
+        &mut self.nodes[index]
+    }
+
+    pub fn nodes(&self) -> &[Transport] {
+        &self.nodes
+    }
+
+    /// Packets carried over all links so far.
+    pub fn carried_packets(&self) -> u64 {
+        self.carried.load(Ordering::Relaxed)
+    }
+
+    /// Connects nodes `a` and `b` with a lossless link. Each of them gets a
+    /// new interface for it.
+    pub async fn connect(&self, a: usize, b: usize) {
+        let channel_a = self.nodes[a].iface_manager().lock().await.new_channel(LINK_QUEUE);
+        let channel_b = self.nodes[b].iface_manager().lock().await.new_channel(LINK_QUEUE);
+
+        let address_a = channel_a.address;
+        let address_b = channel_b.address;
+        let (rx_a, tx_a) = channel_a.split();
+        let (rx_b, tx_b) = channel_b.split();
+
+        for (mut from, to, to_address) in [(tx_a, rx_b, address_b), (tx_b, rx_a, address_a)] {
+            let carried = self.carried.clone();
+            runtime::spawn(async move {
+                while let Some(message) = from.recv().await {
+                    carried.fetch_add(1, Ordering::Relaxed);
+                    let message = RxMessage { address: to_address, packet: message.packet };
+                    if to.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    }
+
+    /// Lets `duration` pass. Timers fire in order of their deadlines and the
+    /// traffic they cause settles before the next one fires.
+    pub async fn advance(&self, duration: Duration) {
+        // Tasks of new nodes start their timers once they run
+        self.settle().await;
+
+        let target = self.clock.now() + duration;
+
+        while let Some(deadline) = self.clock.next_deadline().filter(|deadline| *deadline <= target) {
+            self.clock.move_to(deadline);
+            self.settle().await;
+        }
+
+        self.clock.move_to(target);
+        self.settle().await;
+    }
+
+    /// Waits until no packets have been carried for a few rounds, without
+    /// moving the clock.
+    pub async fn settle(&self) {
+        let mut idle = 0;
+
+        while idle < IDLE_ROUNDS {
+            let carried = self.carried_packets();
+
+            for _ in 0..YIELDS_PER_ROUND {
+                tokio::task::yield_now().await;
+            }
+
+            // Announce signatures are checked on the blocking pool, which
+            // runs in real time
+            tokio::time::sleep(Duration::from_millis(1)).await;
+
+            if self.carried_packets() == carried {
+                idle += 1;
+            } else {
+                idle = 0;
+            }
+        }
+    }
+}
+
+impl Default for SimNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::destination::DestinationName;
+    use crate::identity::PrivateIdentity;
+
+    fn node_config(name: &str) -> TransportConfig {
+        TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false).set_transport_enabled(true)
+    }
+
+    #[tokio::test]
+    async fn sleepers_wake_in_order_of_deadlines() {
+        let clock = SimClock::new();
+        let start = clock.now();
+
+        let late = clock.sleep(Duration::from_secs(5));
+        let early = clock.sleep(Duration::from_secs(2));
+        assert_eq!(clock.next_deadline(), Some(start + Duration::from_secs(2)));
+
+        clock.move_to(start + Duration::from_secs(3));
+        runtime::timeout(Duration::from_secs(1), early).await.unwrap();
+        assert_eq!(clock.next_deadline(), Some(start + Duration::from_secs(5)));
+
+        clock.move_to(start + Duration::from_secs(5));
+        runtime::timeout(Duration::from_secs(1), late).await.unwrap();
+        assert_eq!(clock.now(), start + Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn announces_converge_along_a_chain() {
+        const NODES: usize = 30;
+
+        let mut network = SimNetwork::new();
+        for index in 0..NODES {
+            network.add_node(node_config(&format!("node{index}")));
+        }
+        for index in 1..NODES {
+            network.connect(index - 1, index).await;
+        }
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("origin"), DestinationName::new("test", "sim"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        let start = network.clock().now();
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(60)).await;
+        assert_eq!(network.clock().now(), start + Duration::from_secs(60));
+
+        for index in 1..NODES {
+            let paths = network.node(index).paths(&address).await;
+            assert_eq!(paths.len(), 1, "node {index} has no path");
+            assert_eq!(paths[0].hops as usize, index);
+        }
+    }
+
+    #[tokio::test]
+    async fn hop_paths_name_the_nearest_relay() {
+        let mut network = SimNetwork::new();
+        for index in 0..4 {
+            network.add_node(node_config(&format!("node{index}")).set_record_hop_paths(true));
+        }
+        for index in 1..4 {
+            network.connect(index - 1, index).await;
+        }
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("origin"), DestinationName::new("test", "sim"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(60)).await;
+
+        let direct = network.node(1).hop_path(&address).await.unwrap();
+        assert!(direct.relays.is_empty());
+        assert_eq!(direct.to_string(), "direct");
+
+        let relay = *PrivateIdentity::new_from_name("node2").address_hash();
+        let hop_path = network.node(3).hop_path(&address).await.unwrap();
+        assert_eq!(hop_path.hops, 3);
+        assert_eq!(hop_path.relays, vec![Some(relay), None]);
+        assert!(!hop_path.is_complete());
+        assert_eq!(hop_path.to_string(), format!("{relay} > ?"));
+    }
+}
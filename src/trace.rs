@@ -0,0 +1,156 @@
+//! Rate limited trace output for per-packet log points.
+//!
+//! Logging every received and transmitted packet floods the log of a busy
+//! node and slows down the tasks which do the logging. Per-packet trace
+//! points go through `trace_packet!` instead: each [`TraceCategory`] allows a
+//! limited number of lines per second, and the number of suppressed lines is
+//! reported with a summary line when the category logs again in a later
+//! second.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use crate::runtime::Instant;
+
+/// Default number of trace lines per category and second.
+pub const DEFAULT_RATE_LIMIT: u32 = 32;
+
+/// Default number of [`TraceCategory::DecodeDump`] dumps per second, each
+/// takes several lines.
+pub const DEFAULT_DUMP_RATE_LIMIT: u32 = 4;
+
+const WINDOW_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceCategory {
+    /// Packets received by interfaces.
+    InterfaceRx,
+    /// Packets transmitted by interfaces.
+    InterfaceTx,
+    /// Packets handled by the transport.
+    TransportRx,
+    /// Packets sent by the transport.
+    TransportTx,
+    /// Dumps of received bytes which couldn't be decoded, see
+    /// [`crate::diagnostics`].
+    DecodeDump,
+}
+
+impl TraceCategory {
+    fn name(&self) -> &'static str {
+        match self {
+            TraceCategory::InterfaceRx => "iface rx",
+            TraceCategory::InterfaceTx => "iface tx",
+            TraceCategory::TransportRx => "transport rx",
+            TraceCategory::TransportTx => "transport tx",
+            TraceCategory::DecodeDump => "decode dump",
+        }
+    }
+}
+
+struct Limiter {
+    limit: AtomicU32,
+    window: AtomicU64,
+    count: AtomicU32,
+    suppressed: AtomicU32,
+}
+
+impl Limiter {
+    const fn new(limit: u32) -> Self {
+        Self {
+            limit: AtomicU32::new(limit),
+            window: AtomicU64::new(0),
+            count: AtomicU32::new(0),
+            suppressed: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns whether a line may be logged in `window` and the number of
+    /// lines suppressed in earlier windows which weren't reported yet.
+    fn allow(&self, window: u64) -> (bool, u32) {
+        let mut suppressed = 0;
+
+        let current = self.window.load(Ordering::Relaxed);
+        if window != current
+            && self
+                .window
+                .compare_exchange(current, window, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.count.store(0, Ordering::Relaxed);
+            suppressed = self.suppressed.swap(0, Ordering::Relaxed);
+        }
+
+        if self.count.fetch_add(1, Ordering::Relaxed) < self.limit.load(Ordering::Relaxed) {
+            (true, suppressed)
+        } else {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            (false, suppressed)
+        }
+    }
+}
+
+static LIMITERS: [Limiter; 5] = [
+    Limiter::new(DEFAULT_RATE_LIMIT),
+    Limiter::new(DEFAULT_RATE_LIMIT),
+    Limiter::new(DEFAULT_RATE_LIMIT),
+    Limiter::new(DEFAULT_RATE_LIMIT),
+    Limiter::new(DEFAULT_DUMP_RATE_LIMIT),
+];
+
+fn limiter(category: TraceCategory) -> &'static Limiter {
+    &LIMITERS[category as usize]
+}
+
+/// Sets how many lines of `category` are logged per second, `None` logs
+/// every line.
+pub fn set_rate_limit(category: TraceCategory, lines_per_second: Option<u32>) {
+    limiter(category)
+        .limit
+        .store(lines_per_second.unwrap_or(u32::MAX), Ordering::Relaxed);
+}
+
+/// Returns whether a trace line of `category` may be logged now.
+pub fn allow(category: TraceCategory) -> bool {
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    let window = START.get_or_init(Instant::now).elapsed().as_millis() as u64 / WINDOW_MS;
+
+    let (allowed, suppressed) = limiter(category).allow(window);
+    if suppressed > 0 {
+        log::trace!("trace: suppressed {} {} lines", suppressed, category.name());
+    }
+
+    allowed
+}
+
+/// Logs at trace level if the rate limit of the category allows it.
+macro_rules! trace_packet {
+    ($category:expr, $($arg:tt)+) => {
+        if log::log_enabled!(log::Level::Trace) && $crate::trace::allow($category) {
+            log::trace!($($arg)+);
+        }
+    };
+}
+
+pub(crate) use trace_packet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_lines_per_window() {
+        let limiter = Limiter::new(2);
+
+        assert_eq!(limiter.allow(1), (true, 0));
+        assert_eq!(limiter.allow(1), (true, 0));
+        assert_eq!(limiter.allow(1), (false, 0));
+        assert_eq!(limiter.allow(1), (false, 0));
+
+        // Suppressed lines are reported once in the next window
+        assert_eq!(limiter.allow(2), (true, 2));
+        assert_eq!(limiter.allow(2), (true, 0));
+        assert_eq!(limiter.allow(3), (true, 0));
+    }
+}
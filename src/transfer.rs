@@ -0,0 +1,244 @@
+//! Progress and control of long running transfers.
+//!
+//! APIs which send data in many parts return a [`TransferHandle`]. It reports
+//! the progress of the transfer as the peer confirms parts, and lets the
+//! application pause, resume or cancel the transfer, e.g. from a progress
+//! bar. The sending side drives the transfer through a [`Transfer`] sharing
+//! its state with the handle.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use tokio::sync::{watch, Notify};
+
+use crate::runtime::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferState {
+    /// Parts are being sent.
+    Active,
+    /// The transfer waits to be resumed.
+    Paused,
+    /// The peer confirmed all parts.
+    Complete,
+    /// The transfer was cancelled through its handle.
+    Cancelled,
+    /// The transfer can't continue, e.g. because its link was closed.
+    Failed,
+}
+
+impl TransferState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Complete | Self::Cancelled | Self::Failed)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub state: TransferState,
+    pub total_bytes: usize,
+    /// Bytes of the parts the peer confirmed.
+    pub done_bytes: usize,
+    pub total_parts: usize,
+    pub done_parts: usize,
+}
+
+impl TransferProgress {
+    /// Share of the bytes confirmed so far, from 0 to 1.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+
+        self.done_bytes as f32 / self.total_bytes as f32
+    }
+}
+
+/// What the application asked the transfer to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TransferCommand {
+    Run = 0,
+    Pause = 1,
+    Cancel = 2,
+}
+
+struct Shared {
+    progress: watch::Sender<TransferProgress>,
+    command: AtomicU8,
+    /// Notified when the command changes, so the sender applies it.
+    wake: Arc<Notify>,
+    started: Instant,
+}
+
+/// Sending side of a transfer.
+#[derive(Clone)]
+pub(crate) struct Transfer {
+    shared: Arc<Shared>,
+}
+
+impl Transfer {
+    /// Creates a transfer of `total_parts` parts with `total_bytes` bytes.
+    /// `wake` is notified when the application pauses, resumes or cancels it.
+    pub(crate) fn new(
+        total_bytes: usize,
+        total_parts: usize,
+        wake: Arc<Notify>,
+    ) -> (Self, TransferHandle) {
+        let (progress, _) = watch::channel(TransferProgress {
+            state: TransferState::Active,
+            total_bytes,
+            done_bytes: 0,
+            total_parts,
+            done_parts: 0,
+        });
+
+        let shared = Arc::new(Shared {
+            progress,
+            command: AtomicU8::new(TransferCommand::Run as u8),
+            wake,
+            started: Instant::now(),
+        });
+
+        (Self { shared: shared.clone() }, TransferHandle { shared })
+    }
+
+    pub(crate) fn command(&self) -> TransferCommand {
+        match self.shared.command.load(Ordering::Acquire) {
+            1 => TransferCommand::Pause,
+            2 => TransferCommand::Cancel,
+            _ => TransferCommand::Run,
+        }
+    }
+
+    pub(crate) fn state(&self) -> TransferState {
+        self.shared.progress.borrow().state
+    }
+
+    /// Moves the transfer to `state` unless it is finished already.
+    pub(crate) fn set_state(&self, state: TransferState) {
+        self.shared.progress.send_if_modified(|progress| {
+            if progress.state.is_finished() || progress.state == state {
+                return false;
+            }
+
+            progress.state = state;
+            true
+        });
+    }
+
+    /// Records that the peer confirmed a part of `bytes` bytes. The transfer
+    /// is complete with its last part.
+    pub(crate) fn part_done(&self, bytes: usize) {
+        self.shared.progress.send_modify(|progress| {
+            progress.done_parts += 1;
+            progress.done_bytes += bytes;
+
+            if progress.done_parts >= progress.total_parts && !progress.state.is_finished() {
+                progress.state = TransferState::Complete;
+            }
+        });
+    }
+}
+
+/// Application side of a transfer.
+///
+/// Dropping the handle doesn't stop the transfer.
+#[derive(Clone)]
+pub struct TransferHandle {
+    shared: Arc<Shared>,
+}
+
+impl TransferHandle {
+    pub fn progress(&self) -> TransferProgress {
+        self.shared.progress.borrow().clone()
+    }
+
+    /// Receiver which is notified whenever the progress changes.
+    pub fn subscribe(&self) -> watch::Receiver<TransferProgress> {
+        self.shared.progress.subscribe()
+    }
+
+    /// Confirmed bytes per second since the transfer started.
+    pub fn throughput(&self) -> f64 {
+        let elapsed = self.shared.started.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+
+        self.shared.progress.borrow().done_bytes as f64 / elapsed
+    }
+
+    /// Stops sending further parts. Parts on the way are still confirmed.
+    pub fn pause(&self) {
+        self.command(TransferCommand::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.command(TransferCommand::Run);
+    }
+
+    /// Stops the transfer for good. The peer drops the parts it received.
+    pub fn cancel(&self) {
+        self.command(TransferCommand::Cancel);
+    }
+
+    /// Waits until the transfer completes, is cancelled or fails.
+    pub async fn finished(&self) -> TransferState {
+        let mut progress = self.shared.progress.subscribe();
+
+        let state = match progress.wait_for(|progress| progress.state.is_finished()).await {
+            Ok(progress) => progress.state,
+            // The sender lives as long as the handle does
+            Err(_) => TransferState::Failed,
+        };
+
+        state
+    }
+
+    fn command(&self, command: TransferCommand) {
+        let current = self.shared.command.load(Ordering::Acquire);
+        if current == TransferCommand::Cancel as u8 || self.progress().state.is_finished() {
+            return;
+        }
+
+        self.shared.command.store(command as u8, Ordering::Release);
+        self.shared.wake.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn progress_and_commands() {
+        let wake = Arc::new(Notify::new());
+        let (transfer, handle) = Transfer::new(300, 3, wake.clone());
+        let mut progress = handle.subscribe();
+
+        handle.pause();
+        wake.notified().await;
+        assert_eq!(transfer.command(), TransferCommand::Pause);
+        transfer.set_state(TransferState::Paused);
+        assert_eq!(handle.progress().state, TransferState::Paused);
+
+        handle.resume();
+        assert_eq!(transfer.command(), TransferCommand::Run);
+        transfer.set_state(TransferState::Active);
+
+        transfer.part_done(100);
+        progress.changed().await.unwrap();
+        assert_eq!(progress.borrow_and_update().done_bytes, 100);
+        assert!((handle.progress().fraction() - 1.0 / 3.0).abs() < 1e-6);
+
+        transfer.part_done(100);
+        transfer.part_done(100);
+        assert_eq!(handle.finished().await, TransferState::Complete);
+
+        // Finished transfers ignore commands
+        handle.cancel();
+        assert_eq!(transfer.command(), TransferCommand::Run);
+        transfer.set_state(TransferState::Failed);
+        assert_eq!(handle.progress().state, TransferState::Complete);
+    }
+}
@@ -1,5 +1,6 @@
 use alloc::sync::Arc;
 use announce_limits::AnnounceLimits;
+use link_limits::LinkRequestLimits;
 use announce_table::AnnounceTable;
 use link_table::LinkTable;
 use packet_cache::PacketCache;
@@ -7,35 +8,67 @@ use path_requests::create_path_request_destination;
 use path_requests::PathRequests;
 use path_requests::TagBytes;
 use path_table::PathTable;
+use rand_core::CryptoRngCore;
 use rand_core::OsRng;
+use events::AnnounceReplay;
+use outbox::Outbox;
+use processing::SLOW_PACKET;
+use traffic::TrafficTable;
+use verified_announces::{VerifiedAnnounces, MAX_VERIFIED_ANNOUNCES};
 use std::collections::HashMap;
+use std::fmt;
+use std::mem::size_of;
+use std::io;
+use std::path::PathBuf;
 use std::time::Duration;
-use tokio::time;
 use tokio_util::sync::CancellationToken;
 
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
 use tokio::sync::MutexGuard;
 
+use crate::crypt::rng::SharedRng;
 use crate::destination::link::Link;
+use crate::destination::link::LinkError;
+use crate::destination::link::LinkEvent;
 use crate::destination::link::LinkEventData;
 use crate::destination::link::LinkHandleResult;
 use crate::destination::link::LinkId;
 use crate::destination::link::LinkStatus;
+use crate::destination::link::ProofError;
+use crate::destination::link_compression::LinkCompression;
+use crate::destination::link_sequence::LinkSequence;
+use crate::destination::link_window::proof_timeout;
+use crate::destination::request::IncomingRequest;
+use crate::destination::request::LinkRequest;
+use crate::destination::request::LinkResponse;
+use crate::destination::request::RegisteredHandler;
+use crate::destination::DecryptionKey;
 use crate::destination::DestinationAnnounce;
 use crate::destination::DestinationDesc;
 use crate::destination::DestinationHandleStatus;
 use crate::destination::DestinationName;
+use crate::destination::LinkAccess;
+use crate::destination::LinkLimits;
+use crate::destination::LinkOverflow;
+use crate::destination::LinkRequestInfo;
 use crate::destination::SingleInputDestination;
 use crate::destination::SingleOutputDestination;
+use crate::destination::max_announce_app_data;
+use crate::destination::RATCHET_LENGTH;
 
 use crate::error::RnsError;
 
 use crate::hash::AddressHash;
 use crate::hash::Hash;
+use crate::identity::Identity;
 use crate::identity::PrivateIdentity;
 
+use crate::iface::InterfaceEvent;
 use crate::iface::InterfaceManager;
+use crate::iface::InterfaceMode;
+use crate::iface::InterfaceState;
 use crate::iface::InterfaceRxReceiver;
 use crate::iface::RxMessage;
 use crate::iface::TxMessage;
@@ -46,26 +79,312 @@ use crate::packet::Packet;
 use crate::packet::PacketContext;
 use crate::packet::PacketDataBuffer;
 use crate::packet::PacketType;
+use crate::packet::PACKET_MDU;
+use crate::runtime;
+use crate::runtime::Clock;
+use crate::runtime::Instant;
+use crate::runtime::RuntimeClock;
+use crate::trace::trace_packet;
+use crate::trace::TraceCategory;
 
 mod announce_limits;
 mod announce_table;
+mod events;
+mod link_limits;
+mod link_policy;
 mod link_table;
+mod outbox;
 mod packet_cache;
 mod path_requests;
 mod path_table;
+mod processing;
+mod traffic;
+mod verified_announces;
+
+pub use announce_table::HopPath;
+pub use events::AnnounceSubscription;
+pub use events::EventSubscription;
+pub use events::TransportEvent;
+pub use link_policy::LinkPolicy;
+pub use path_table::DefaultPathPolicy;
+pub use path_table::PathEntry;
+pub use path_table::PathPolicy;
+pub use processing::LatencyStats;
+pub use processing::ProcessingStats;
+pub use outbox::DEFAULT_OUTBOX_CAPACITY;
+pub use processing::DEFAULT_RX_BUDGET;
+pub use traffic::TrafficStats;
 
-// TODO: Configure via features
-const PACKET_TRACE: bool = false;
 pub const PATHFINDER_M: usize = 128; // Max hops
 
 // Other constants
 const KEEP_ALIVE_REQUEST: u8 = 0xFF;
 const KEEP_ALIVE_RESPONSE: u8 = 0xFE;
 
+/// Link events buffered for slow subscribers.
+const LINK_EVENTS_CAPACITY: usize = 256;
+
 #[derive(Clone)]
 pub struct ReceivedData {
     pub destination: AddressHash,
     pub data: PacketDataBuffer,
+    /// Hash of the received packet, proofs refer to it.
+    pub packet_hash: Hash,
+    /// Interface the packet was received on.
+    pub iface: AddressHash,
+    /// Identity of the sender if it is known. Packets sent to a single
+    /// destination are anonymous, so this is `None` unless the sender was
+    /// identified by other means.
+    pub source_identity: Option<Identity>,
+    /// Whether the sender expects a proof of delivery. Python senders track
+    /// a receipt for plain data packets to single destinations.
+    pub proof_requested: bool,
+    /// Inbound link the data came over, `None` for packets sent to the
+    /// destination itself. Answer over the same link with
+    /// [`Transport::reply_on_link`].
+    pub link_id: Option<LinkId>,
+    /// Number of the data among what the peer of the link sent, if it
+    /// sequences it.
+    pub sequence: Option<LinkSequence>,
+    /// Key the data was decrypted with, `None` for data received over a
+    /// link. Packets to the destination itself which none of its keys
+    /// decrypt aren't delivered.
+    pub decrypted_with: Option<DecryptionKey>,
+}
+
+/// A request received over an inbound link, see [`Transport::link_requests`].
+#[derive(Clone)]
+pub struct ReceivedRequest {
+    pub link_id: LinkId,
+    pub destination: AddressHash,
+    /// Identity the peer identified with on the link, `None` if it didn't.
+    pub remote_identity: Option<Identity>,
+    pub request: LinkRequest,
+}
+
+/// A response received over an outbound link, see
+/// [`Transport::link_responses`].
+#[derive(Clone)]
+pub struct ReceivedResponse {
+    pub link_id: LinkId,
+    pub destination: AddressHash,
+    pub response: LinkResponse,
+}
+
+/// Why a packet wasn't sent, see [`Transport::send_packet`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The packet is larger than the MTU of an interface it would go out on.
+    PacketTooLarge { size: usize, mtu: usize },
+    /// The announce doesn't fit into the MTU of an interface it would go out
+    /// on with `app_data` bytes of app data, at most `max` bytes fit.
+    AnnounceTooLarge { app_data: usize, max: usize },
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::PacketTooLarge { size, mtu } => {
+                write!(f, "packet of {} bytes is larger than the MTU of {} bytes", size, mtu)
+            }
+            SendError::AnnounceTooLarge { app_data, max } => write!(
+                f,
+                "announce app data of {} bytes exceeds the {} bytes which fit the MTU",
+                app_data, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+impl From<SendError> for RnsError {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::PacketTooLarge { .. } | SendError::AnnounceTooLarge { .. } => {
+                RnsError::PacketTooLarge
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkDirection {
+    /// Opened by this transport.
+    Outbound,
+    /// Opened by a peer to one of the local destinations.
+    Inbound,
+}
+
+/// Snapshot of a link, see [`Transport::active_links`].
+#[derive(Debug, Clone)]
+pub struct LinkSummary {
+    pub id: LinkId,
+    pub destination: AddressHash,
+    pub direction: LinkDirection,
+    pub status: LinkStatus,
+    pub rtt: Duration,
+    pub age: Duration,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Replayed packets the link dropped.
+    pub replays: u64,
+}
+
+impl LinkSummary {
+    fn new(link: &Link, direction: LinkDirection) -> Self {
+        Self {
+            id: *link.id(),
+            destination: link.destination().address_hash,
+            direction,
+            status: link.status(),
+            rtt: *link.rtt(),
+            age: link.age(),
+            rx_bytes: link.rx_bytes(),
+            tx_bytes: link.tx_bytes(),
+            replays: link.replays(),
+        }
+    }
+}
+
+/// Announces handled by a transport, see [`Transport::announce_counts`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AnnounceCounts {
+    /// Valid announces of remote destinations.
+    pub received: u64,
+    /// Announces dropped for an invalid signature or the rate limit.
+    pub dropped: u64,
+    /// Announces of local destinations.
+    pub sent: u64,
+    /// Announces of remote destinations passed on to other interfaces.
+    pub retransmitted: u64,
+}
+
+/// Packets to local destinations by the key which decrypted them, see
+/// [`Transport::decryption_counts`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DecryptionCounts {
+    pub current_ratchet: u64,
+    /// Packets encrypted to an older ratchet by senders with a cached
+    /// announce.
+    pub previous_ratchet: u64,
+    pub identity: u64,
+    /// Packets none of the keys decrypted, e.g. ones sent unencrypted. They
+    /// are dropped.
+    pub undecrypted: u64,
+}
+
+impl DecryptionCounts {
+    fn count(&mut self, key: Option<DecryptionKey>) {
+        match key {
+            Some(DecryptionKey::CurrentRatchet) => self.current_ratchet += 1,
+            Some(DecryptionKey::PreviousRatchet(_)) => self.previous_ratchet += 1,
+            Some(DecryptionKey::Identity) => self.identity += 1,
+            None => self.undecrypted += 1,
+        }
+    }
+}
+
+/// Link request proofs rejected by a transport, see
+/// [`Transport::rejected_proofs`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RejectedProofs {
+    /// Proofs without the length of a signature and a key.
+    pub malformed: u64,
+    /// Proofs not signed by the destination of the link.
+    pub invalid_signature: u64,
+    /// Proofs of links this transport neither opened nor routes.
+    pub unknown_link: u64,
+    /// Relayed proofs received on another interface than the one the link
+    /// request was sent on.
+    pub wrong_interface: u64,
+}
+
+impl RejectedProofs {
+    fn count(&mut self, error: &ProofError) {
+        match error {
+            ProofError::InvalidLength(_) => self.malformed += 1,
+            ProofError::InvalidSignature => self.invalid_signature += 1,
+            ProofError::WrongLink { .. } | ProofError::UnknownLink(_) => self.unknown_link += 1,
+            ProofError::WrongInterface => self.wrong_interface += 1,
+        }
+    }
+}
+
+/// Entries of a table and the memory they take, roughly. Bytes count the
+/// entries with their keys and the heap data they own, but not the spare
+/// capacity of the table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TableStats {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+impl TableStats {
+    /// Stats of `entries` entries of a map from `K` to `V`.
+    pub(crate) fn of_map<K, V>(entries: usize) -> Self {
+        Self {
+            entries,
+            bytes: entries * (size_of::<K>() + size_of::<V>()),
+        }
+    }
+
+    /// Adds heap data owned by the entries.
+    pub(crate) fn with_heap(mut self, bytes: usize) -> Self {
+        self.bytes += bytes;
+        self
+    }
+}
+
+impl core::ops::Add for TableStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            entries: self.entries + other.entries,
+            bytes: self.bytes + other.bytes,
+        }
+    }
+}
+
+/// Sizes of the tables of a transport, see [`Transport::memory_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Paths to remote destinations, all candidates included.
+    pub paths: TableStats,
+    /// Announces waiting for retransmission or kept to answer path requests.
+    pub announces: TableStats,
+    /// Links of this transport, inbound and outbound.
+    pub links: TableStats,
+    /// Links of other nodes this transport relays.
+    pub relayed_links: TableStats,
+    /// Hashes of recent packets, to drop duplicates.
+    pub packet_cache: TableStats,
+    /// Local and known remote destinations with their announced keys and
+    /// app data.
+    pub destinations: TableStats,
+    /// Announces whose signature was verified already.
+    pub verified_announces: TableStats,
+    /// Traffic counters by destination.
+    pub traffic: TableStats,
+}
+
+impl MemoryStats {
+    pub fn total_bytes(&self) -> usize {
+        [
+            self.paths,
+            self.announces,
+            self.links,
+            self.relayed_links,
+            self.packet_cache,
+            self.destinations,
+            self.verified_announces,
+            self.traffic,
+        ]
+        .iter()
+        .map(|table| table.bytes)
+        .sum()
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -81,8 +400,31 @@ pub struct TimerConfig {
     pub iface_cleanup: Duration,
     pub announces_retransmit: Duration,
     pub old_announces_retransmit: Duration,
+    /// Packets with the same hash are dropped as duplicates within this window.
     pub keep_packet_cached: Duration,
     pub packet_cache_cleanup: Duration,
+    /// Interval at which known announces are written to the announce cache
+    /// file, if one is configured.
+    pub announce_cache_persist: Duration,
+    /// Lifetime of paths to neighbours, which announce themselves directly.
+    pub direct_path_expiry: Duration,
+    /// Lifetime of paths to destinations more than one hop away.
+    pub path_expiry: Duration,
+    /// Local destinations are announced on an interface coming up at most
+    /// once within this time, see
+    /// [`TransportConfig::set_announce_on_iface_up`].
+    pub iface_announce_holdoff: Duration,
+}
+
+impl TimerConfig {
+    /// How long a path over `hops` hops is kept without new announces.
+    pub fn path_lifetime(&self, hops: u8) -> Duration {
+        if hops <= 1 {
+            self.direct_path_expiry
+        } else {
+            self.path_expiry
+        }
+    }
 }
 
 impl Default for TimerConfig {
@@ -101,6 +443,33 @@ impl Default for TimerConfig {
             old_announces_retransmit: Duration::from_secs(60),
             keep_packet_cached: Duration::from_secs(180),
             packet_cache_cleanup: Duration::from_secs(90),
+            announce_cache_persist: Duration::from_secs(15 * 60),
+            direct_path_expiry: Duration::from_secs(24 * 60 * 60),
+            path_expiry: Duration::from_secs(7 * 24 * 60 * 60),
+            iface_announce_holdoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Which received packets a transport passes on to other interfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ForwardingPolicy {
+    /// Only packets routed through this node, like the reference
+    /// implementation: packets addressed to its transport id, traffic of
+    /// links it relays and their proofs. Needs transport to be enabled.
+    #[default]
+    Routed,
+    /// Also rebroadcast every other packet apart from announces on all other
+    /// interfaces, whether transport is enabled or not.
+    Flood,
+}
+
+impl ForwardingPolicy {
+    fn from_broadcast(broadcast: bool) -> Self {
+        if broadcast {
+            Self::Flood
+        } else {
+            Self::Routed
         }
     }
 }
@@ -108,8 +477,15 @@ impl Default for TimerConfig {
 pub struct TransportConfig {
     name: String,
     identity: PrivateIdentity,
-    broadcast: bool,
-    retransmit: bool,
+    forwarding: ForwardingPolicy,
+
+    /// Never pass on packets of others, whatever else is set, see
+    /// [`TransportConfig::new_client`].
+    client_mode: bool,
+
+    /// Act as a transport node: retransmit announces, answer path requests
+    /// for remote destinations and forward packets routed through this node.
+    transport_enabled: bool,
 
     /// If `false`, `Transport` will replace known routes to distant destinations
     /// only if they are shorter (fewer hops) than the new one.
@@ -124,6 +500,41 @@ pub struct TransportConfig {
     /// the initial round of announces is over.
     announce_forever: bool,
 
+    /// File the known announces are kept in across restarts.
+    announce_cache_path: Option<PathBuf>,
+
+    /// Announces of this many destinations are replayed to late
+    /// subscribers, see [`Transport::recv_announces_with_replay`].
+    announce_replay: usize,
+
+    /// Chooses between several paths to a destination, the default policy
+    /// is used if `None`.
+    path_policy: Option<Arc<dyn PathPolicy>>,
+
+    /// Source of announce nonces, link keys and path request tags.
+    rng: SharedRng,
+
+    /// Drives the timers of the transport and its links.
+    clock: Arc<dyn Clock>,
+
+    /// Keep the [`HopPath`] of received announces.
+    record_hop_paths: bool,
+
+    /// Compress data over links whose peers agree to.
+    link_compression: Option<LinkCompression>,
+
+    /// Number the data sent over links.
+    link_sequencing: bool,
+
+    /// Packets processed in a row before other tasks get to run.
+    rx_budget: usize,
+
+    /// Packets waiting for the interfaces before further ones are dropped.
+    outbox_capacity: usize,
+
+    /// Announce the local destinations on interfaces which come up.
+    announce_on_iface_up: bool,
+
     timer_config: TimerConfig,
 }
 
@@ -133,28 +544,64 @@ pub struct AnnounceEvent {
     pub app_data: PacketDataBuffer,
 }
 
+/// What the latest valid announces of a destination told about it.
+#[derive(Clone)]
+pub struct DestinationInfo {
+    pub identity: Identity,
+    /// Latest announced ratchet key, kept when later announces carry none.
+    pub ratchet: Option<[u8; RATCHET_LENGTH]>,
+    /// App data of the latest announce.
+    pub app_data: Vec<u8>,
+    pub hops: u8,
+    pub last_seen: Instant,
+}
+
 pub(crate) struct TransportHandler {
     config: TransportConfig,
     iface_manager: Arc<Mutex<InterfaceManager>>,
     announce_tx: broadcast::Sender<AnnounceEvent>,
+    announce_replay: AnnounceReplay,
 
     path_table: PathTable,
     announce_table: AnnounceTable,
     link_table: LinkTable,
     single_in_destinations: HashMap<AddressHash, Arc<Mutex<SingleInputDestination>>>,
     single_out_destinations: HashMap<AddressHash, Arc<Mutex<SingleOutputDestination>>>,
-
+    destination_info: HashMap<AddressHash, DestinationInfo>,
+
+    announce_counts: AnnounceCounts,
+    decryption_counts: DecryptionCounts,
+    rejected_proofs: RejectedProofs,
+    link_replays: u64,
+    /// When local destinations were last announced on an interface coming up.
+    iface_announces: HashMap<AddressHash, Instant>,
     announce_limits: AnnounceLimits,
+    link_request_limits: LinkRequestLimits,
+    /// Shared with the packet task, which verifies announces before it
+    /// locks the handler.
+    verified_announces: Arc<std::sync::Mutex<VerifiedAnnounces>>,
 
     out_links: HashMap<AddressHash, Arc<Mutex<Link>>>,
     in_links: HashMap<AddressHash, Arc<Mutex<Link>>>,
+    /// Policies of outbound links to destinations which don't follow the
+    /// timers.
+    link_policies: HashMap<AddressHash, LinkPolicy>,
+    /// Links replacing dead ones, by the id of the new one, until they are
+    /// active.
+    reestablished: HashMap<LinkId, LinkId>,
 
     packet_cache: Mutex<PacketCache>,
+    traffic: Mutex<TrafficTable>,
+    outbox: Arc<Outbox>,
 
     path_requests: PathRequests,
 
     link_in_event_tx: broadcast::Sender<LinkEventData>,
+    link_out_event_tx: broadcast::Sender<LinkEventData>,
     received_data_tx: broadcast::Sender<ReceivedData>,
+    link_requests_tx: broadcast::Sender<ReceivedRequest>,
+    link_responses_tx: broadcast::Sender<ReceivedResponse>,
+    events_tx: broadcast::Sender<TransportEvent>,
 
     fixed_dest_path_requests: AddressHash,
 
@@ -166,36 +613,85 @@ pub struct Transport {
     link_in_event_tx: broadcast::Sender<LinkEventData>,
     link_out_event_tx: broadcast::Sender<LinkEventData>,
     received_data_tx: broadcast::Sender<ReceivedData>,
+    link_requests_tx: broadcast::Sender<ReceivedRequest>,
+    link_responses_tx: broadcast::Sender<ReceivedResponse>,
     iface_messages_tx: broadcast::Sender<RxMessage>,
+    events_tx: broadcast::Sender<TransportEvent>,
     handler: Arc<Mutex<TransportHandler>>,
     iface_manager: Arc<Mutex<InterfaceManager>>,
+    outbox: Arc<Outbox>,
+    rng: SharedRng,
+    clock: Arc<dyn Clock>,
+    processing_stats: Arc<std::sync::Mutex<ProcessingStats>>,
     cancel: CancellationToken,
 }
 
 impl TransportConfig {
+    /// Creates the config of a transport which floods all packets if
+    /// `broadcast` is set, see [`ForwardingPolicy::Flood`].
     pub fn new<T: Into<String>>(name: T, identity: &PrivateIdentity, broadcast: bool) -> Self {
         Self {
             name: name.into(),
             identity: identity.clone(),
-            broadcast,
-            retransmit: false,
+            forwarding: ForwardingPolicy::from_broadcast(broadcast),
+            client_mode: false,
+            transport_enabled: false,
             reroute_eager: false,
             restart_outlinks: false,
             announce_forever: false,
+            announce_cache_path: None,
+            announce_replay: 0,
+            path_policy: None,
+            rng: SharedRng::default(),
+            clock: Arc::new(RuntimeClock),
+            record_hop_paths: false,
+            link_compression: None,
+            link_sequencing: false,
+            rx_budget: DEFAULT_RX_BUDGET,
+            outbox_capacity: DEFAULT_OUTBOX_CAPACITY,
+            announce_on_iface_up: true,
             timer_config: TimerConfig::default(),
         }
     }
 
-    pub fn set_retransmit(mut self, retransmit: bool) -> Self {
-        self.retransmit = retransmit;
+    /// Creates the config of an end-user node, like a Python instance with
+    /// transport disabled: it sends and receives for its own destinations
+    /// and hands received announces to its subscribers, but never
+    /// retransmits announces, answers path requests for other destinations
+    /// or forwards packets. See [`TransportConfig::set_client_mode`].
+    pub fn new_client<T: Into<String>>(name: T, identity: &PrivateIdentity) -> Self {
+        Self::new(name, identity, false).set_client_mode(true)
+    }
+
+    /// Keep the transport from passing on packets of others. Transport and
+    /// flooding stay off even if they are enabled, so a node embedded in an
+    /// application can't turn into a relay by mistake.
+    pub fn set_client_mode(mut self, client_mode: bool) -> Self {
+        self.client_mode = client_mode;
+        self
+    }
+
+    pub fn set_transport_enabled(mut self, transport_enabled: bool) -> Self {
+        self.transport_enabled = transport_enabled;
         self
     }
 
-    pub fn set_broadcast(mut self, broadcast: bool) -> Self {
-        self.broadcast = broadcast;
+    #[deprecated(note = "use `set_transport_enabled`")]
+    pub fn set_retransmit(self, retransmit: bool) -> Self {
+        self.set_transport_enabled(retransmit)
+    }
+
+    pub fn set_forwarding_policy(mut self, forwarding: ForwardingPolicy) -> Self {
+        self.forwarding = forwarding;
         self
     }
 
+    /// Same as [`ForwardingPolicy::Flood`] if `broadcast` is set,
+    /// [`ForwardingPolicy::Routed`] otherwise.
+    pub fn set_broadcast(self, broadcast: bool) -> Self {
+        self.set_forwarding_policy(ForwardingPolicy::from_broadcast(broadcast))
+    }
+
     pub fn set_reroute_eager(mut self, reroute_eager: bool) -> Self {
         self.reroute_eager = reroute_eager;
         self
@@ -211,6 +707,92 @@ impl TransportConfig {
         self
     }
 
+    /// Keep the known announces in the file at `path`. They are loaded when
+    /// the transport is created, so a restarted transport node can answer
+    /// path requests and retransmits them once, and are saved periodically
+    /// and when the transport is dropped.
+    pub fn set_announce_cache_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.announce_cache_path = Some(path.into());
+        self
+    }
+
+    /// Remember the latest announces of up to `count` destinations for
+    /// components which subscribe later. Off by default.
+    pub fn set_announce_replay(mut self, count: usize) -> Self {
+        self.announce_replay = count;
+        self
+    }
+
+    /// Replace the [`DefaultPathPolicy`] used to choose between paths.
+    pub fn set_path_policy<P: PathPolicy + 'static>(mut self, policy: P) -> Self {
+        self.path_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Replace the operating system's random number generator, e.g. with a
+    /// seeded one in tests or a hardware generator on embedded targets.
+    pub fn set_rng<R: CryptoRngCore + Send + 'static>(mut self, rng: R) -> Self {
+        self.rng = SharedRng::new(rng);
+        self
+    }
+
+    /// Replace the runtime's clock, see [`Clock`].
+    pub fn set_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Record which transport nodes announces passed through, see
+    /// [`Transport::hop_path`]. Off by default.
+    pub fn set_record_hop_paths(mut self, record_hop_paths: bool) -> Self {
+        self.record_hop_paths = record_hop_paths;
+        self
+    }
+
+    /// Compress data sent over links whose peers compress as well, see
+    /// [`link_compression`](crate::destination::link_compression). Off by
+    /// default.
+    pub fn set_link_compression(mut self, compression: LinkCompression) -> Self {
+        self.link_compression = Some(compression);
+        self
+    }
+
+    /// Number the data sent over links, so receivers notice losses, see
+    /// [`link_sequence`](crate::destination::link_sequence). Off by default,
+    /// Python peers drop sequenced data.
+    pub fn set_link_sequencing(mut self, sequencing: bool) -> Self {
+        self.link_sequencing = sequencing;
+        self
+    }
+
+    /// Process at most `packets` received packets in a row, then yield so
+    /// timers, keepalives and the application get the handler on a busy
+    /// node. Defaults to [`DEFAULT_RX_BUDGET`], zero is taken as one.
+    pub fn set_rx_budget(mut self, packets: usize) -> Self {
+        self.rx_budget = packets.max(1);
+        self
+    }
+
+    /// Hold at most `packets` packets the transport sends on its own while
+    /// the interfaces don't take them, further packets are dropped, see
+    /// [`Transport::outbox_dropped`]. Packets sent through the API are never
+    /// dropped, the calls wait for the interfaces instead. Defaults to
+    /// [`DEFAULT_OUTBOX_CAPACITY`], zero is taken as one.
+    pub fn set_outbox_capacity(mut self, packets: usize) -> Self {
+        self.outbox_capacity = packets.max(1);
+        self
+    }
+
+    /// Announce all local destinations on an interface once it connects,
+    /// or is registered if it doesn't report its state, so new peers learn
+    /// about them without waiting for the next announce of the application.
+    /// Repeats within [`TimerConfig::iface_announce_holdoff`] are skipped.
+    /// On by default.
+    pub fn set_announce_on_iface_up(mut self, announce_on_iface_up: bool) -> Self {
+        self.announce_on_iface_up = announce_on_iface_up;
+        self
+    }
+
     pub fn set_timer_config(mut self, timer_config: TimerConfig) -> Self {
         self.timer_config = timer_config;
         self
@@ -226,68 +808,146 @@ impl Default for TransportConfig {
         Self {
             name: "tp".into(),
             identity: PrivateIdentity::new_from_rand(OsRng),
-            broadcast: false,
-            retransmit: false,
+            forwarding: ForwardingPolicy::Routed,
+            client_mode: false,
+            transport_enabled: false,
             reroute_eager: false,
             restart_outlinks: false,
             announce_forever: false,
+            announce_cache_path: None,
+            announce_replay: 0,
+            path_policy: None,
+            rng: SharedRng::default(),
+            clock: Arc::new(RuntimeClock),
+            record_hop_paths: false,
+            link_compression: None,
+            link_sequencing: false,
+            rx_budget: DEFAULT_RX_BUDGET,
+            outbox_capacity: DEFAULT_OUTBOX_CAPACITY,
+            announce_on_iface_up: true,
             timer_config: Default::default(),
         }
     }
 }
 
 impl Transport {
-    pub fn new(config: TransportConfig) -> Self {
+    pub fn new(mut config: TransportConfig) -> Self {
+        if config.client_mode {
+            if config.transport_enabled || config.forwarding == ForwardingPolicy::Flood {
+                log::warn!("tp({}): client mode, transport and flooding stay off", config.name);
+            }
+            config.transport_enabled = false;
+            config.forwarding = ForwardingPolicy::Routed;
+        }
+
         let (announce_tx, _) = tokio::sync::broadcast::channel(16);
-        let (link_in_event_tx, _) = tokio::sync::broadcast::channel(16);
-        let (link_out_event_tx, _) = tokio::sync::broadcast::channel(16);
+        // Every channel watches the events of all links for its proofs, so
+        // these have to keep up with many busy links
+        let (link_in_event_tx, _) = tokio::sync::broadcast::channel(LINK_EVENTS_CAPACITY);
+        let (link_out_event_tx, _) = tokio::sync::broadcast::channel(LINK_EVENTS_CAPACITY);
         let (received_data_tx, _) = tokio::sync::broadcast::channel(16);
+        let (link_requests_tx, _) = tokio::sync::broadcast::channel(16);
+        let (link_responses_tx, _) = tokio::sync::broadcast::channel(16);
         let (iface_messages_tx, _) = tokio::sync::broadcast::channel(16);
+        let (events_tx, _) = tokio::sync::broadcast::channel(64);
 
         let iface_manager = InterfaceManager::new(16);
 
         let rx_receiver = iface_manager.receiver();
+        let iface_events = iface_manager.events();
+        // Subscribed here, interfaces may come up before the task runs
+        let handler_iface_events = iface_manager.events();
 
         let iface_manager = Arc::new(Mutex::new(iface_manager));
+        let outbox = Arc::new(Outbox::new(config.outbox_capacity));
 
-        let transport_id = if config.retransmit {
+        let transport_id = if config.transport_enabled {
             Some(*config.identity.address_hash())
         } else {
             None
         };
-        let path_requests = PathRequests::new(config.name.as_str(), transport_id);
+        let path_requests = PathRequests::new(config.name.as_str(), transport_id, config.rng.clone());
 
         let path_request_dest = create_path_request_destination().desc.address_hash;
 
         let cancel = CancellationToken::new();
         let name = config.name.clone();
+        let rng = config.rng.clone();
+        let clock = config.clock.clone();
         let reroute_eager = config.reroute_eager;
+        let timer_config = config.timer_config;
+
+        let mut announce_table = AnnounceTable::new();
+        announce_table.set_record_hop_paths(config.record_hop_paths);
+        let mut path_table = match &config.path_policy {
+            Some(policy) => PathTable::with_policy(policy.clone()),
+            None => PathTable::new(reroute_eager),
+        };
+        if let Some(path) = &config.announce_cache_path {
+            restore_announces(&name, path, &mut announce_table, &mut path_table);
+        }
+
+        let announce_replay = AnnounceReplay::new(config.announce_replay);
+
         let handler = Arc::new(Mutex::new(TransportHandler {
             config,
             iface_manager: iface_manager.clone(),
-            announce_table: AnnounceTable::new(),
+            announce_table,
             link_table: LinkTable::new(),
-            path_table: PathTable::new(reroute_eager),
+            path_table,
             single_in_destinations: HashMap::new(),
             single_out_destinations: HashMap::new(),
+            destination_info: HashMap::new(),
+            announce_counts: AnnounceCounts::default(),
+            decryption_counts: DecryptionCounts::default(),
+            rejected_proofs: RejectedProofs::default(),
+            link_replays: 0,
+            iface_announces: HashMap::new(),
             announce_limits: AnnounceLimits::new(),
+            link_request_limits: LinkRequestLimits::new(),
+            verified_announces: Arc::new(std::sync::Mutex::new(VerifiedAnnounces::new(
+                MAX_VERIFIED_ANNOUNCES,
+            ))),
             out_links: HashMap::new(),
             in_links: HashMap::new(),
-            packet_cache: Mutex::new(PacketCache::new()),
+            link_policies: HashMap::new(),
+            reestablished: HashMap::new(),
+            packet_cache: Mutex::new(PacketCache::new(timer_config.keep_packet_cached)),
+            traffic: Mutex::new(TrafficTable::default()),
+            outbox: outbox.clone(),
             path_requests,
             announce_tx,
+            announce_replay,
             link_in_event_tx: link_in_event_tx.clone(),
+            link_out_event_tx: link_out_event_tx.clone(),
             received_data_tx: received_data_tx.clone(),
+            link_requests_tx: link_requests_tx.clone(),
+            link_responses_tx: link_responses_tx.clone(),
+            events_tx: events_tx.clone(),
             fixed_dest_path_requests: path_request_dest,
             cancel: cancel.clone(),
         }));
 
+        runtime::spawn(outbox.clone().run(iface_manager.clone(), cancel.clone()));
+
+        runtime::spawn(events::forward_events(
+            link_in_event_tx.subscribe(),
+            link_out_event_tx.subscribe(),
+            iface_events,
+            events_tx.clone(),
+            cancel.clone(),
+        ));
+
+        let processing_stats = Arc::new(std::sync::Mutex::new(ProcessingStats::default()));
+
         {
             let handler = handler.clone();
-            tokio::spawn(manage_transport(
+            runtime::spawn(manage_transport(
                 handler,
                 rx_receiver,
+                handler_iface_events,
                 iface_messages_tx.clone(),
+                processing_stats.clone(),
             ))
         };
 
@@ -297,8 +957,15 @@ impl Transport {
             link_in_event_tx,
             link_out_event_tx,
             received_data_tx,
+            link_requests_tx,
+            link_responses_tx,
             iface_messages_tx,
+            events_tx,
             handler,
+            outbox,
+            rng,
+            clock,
+            processing_stats,
             cancel,
         }
     }
@@ -307,8 +974,10 @@ impl Transport {
         let (packet, maybe_iface) = self.handler.lock().await.path_table.handle_packet(packet);
 
         if let Some(iface) = maybe_iface {
-            self.send_direct(iface, packet).await;
-            log::trace!("Sent outbound packet to {}", iface);
+            match self.send_direct(iface, packet).await {
+                Ok(()) => trace_packet!(TraceCategory::TransportTx, "Sent outbound packet to {}", iface),
+                Err(err) => log::warn!("tp({}): outbound packet not sent to {}: {}", self.name, iface, err),
+            }
         }
 
         // TODO handle other cases
@@ -326,48 +995,118 @@ impl Transport {
         self.handler.lock().await.announce_tx.subscribe()
     }
 
-    pub async fn send_packet(&self, packet: Packet) {
-        self.handler.lock().await.send_packet(packet).await;
+    /// Like [`Transport::recv_announces`], but first yields the latest
+    /// announces of the destinations already known, see
+    /// [`TransportConfig::set_announce_replay`].
+    pub async fn recv_announces_with_replay(&self) -> AnnounceSubscription {
+        let handler = self.handler.lock().await;
+        AnnounceSubscription::new(&handler.announce_replay, handler.announce_tx.subscribe())
+    }
+
+    /// Sends `packet` on all interfaces. Fails without sending anything if
+    /// it is larger than the smallest MTU among them.
+    pub async fn send_packet(&self, packet: Packet) -> Result<(), SendError> {
+        self.check_size(&packet, &TxMessageType::Broadcast(None)).await?;
+
+        self.handler.lock().await.send_packet_flushed(packet).await;
+        self.flush().await;
+
+        Ok(())
+    }
+
+    /// Checks that `packet` fits into the interfaces a message of `tx_type`
+    /// goes to.
+    async fn check_size(&self, packet: &Packet, tx_type: &TxMessageType) -> Result<(), SendError> {
+        let size = packet.wire_len();
+
+        match self.iface_manager.lock().await.tx_mtu(tx_type) {
+            Some(mtu) if size > mtu => Err(SendError::PacketTooLarge { size, mtu }),
+            _ => Ok(()),
+        }
     }
 
+    /// Announces `destination` with `app_data`, or with its default app
+    /// data if `app_data` is `None`. Fails without sending anything if the
+    /// app data is longer than [`Transport::max_announce_app_data`], less
+    /// the ratchet if the destination has ratchets enabled.
+    ///
+    /// Rotates the ratchets of the destination when they are due.
     pub async fn send_announce(
         &self,
         destination: &Arc<Mutex<SingleInputDestination>>,
         app_data: Option<&[u8]>,
-    ) {
-        self.handler
-            .lock()
-            .await
-            .send_packet(
-                destination
-                    .lock()
-                    .await
-                    .announce(OsRng, app_data)
-                    .expect("valid announce packet"),
-            )
-            .await;
+    ) -> Result<(), SendError> {
+        let max = self.max_announce_app_data().await;
+
+        let announce = {
+            let mut destination = destination.lock().await;
+
+            let max = match destination.ratchets() {
+                Some(_) => max.saturating_sub(RATCHET_LENGTH),
+                None => max,
+            };
+
+            let len = app_data.or(destination.default_app_data()).map_or(0, <[u8]>::len);
+            if len > max {
+                return Err(SendError::AnnounceTooLarge { app_data: len, max });
+            }
+
+            if let Err(err) = destination.rotate_ratchets(&self.rng) {
+                log::warn!(
+                    "tp({}): couldn't save the ratchets of {}, keeping the current one: {}",
+                    self.name,
+                    destination.desc.address_hash,
+                    err
+                );
+            }
+
+            destination.announce(&self.rng, app_data).expect("valid announce packet")
+        };
+
+        let mut handler = self.handler.lock().await;
+        handler.announce_counts.sent += 1;
+        handler.send_packet_flushed(announce).await;
+        drop(handler);
+
+        self.flush().await;
+
+        Ok(())
     }
 
-    pub async fn send_broadcast(&self, packet: Packet, from_iface: Option<AddressHash>) {
-        self.handler
-            .lock()
-            .await
-            .send(TxMessage {
-                tx_type: TxMessageType::Broadcast(from_iface),
-                packet,
-            })
-            .await;
+    /// Most app data an announce carries to all interfaces, limited by the
+    /// smallest MTU among them.
+    pub async fn max_announce_app_data(&self) -> usize {
+        let mtu = self.iface_manager.lock().await.tx_mtu(&TxMessageType::Broadcast(None));
+
+        max_announce_app_data(mtu.unwrap_or(usize::MAX))
     }
 
-    pub async fn send_direct(&self, addr: AddressHash, packet: Packet) {
-        self.handler
-            .lock()
-            .await
-            .send(TxMessage {
-                tx_type: TxMessageType::Direct(addr),
-                packet,
-            })
-            .await;
+    /// Sends `packet` on all interfaces but `from_iface`, see
+    /// [`Transport::send_packet`].
+    pub async fn send_broadcast(
+        &self,
+        packet: Packet,
+        from_iface: Option<AddressHash>,
+    ) -> Result<(), SendError> {
+        let tx_type = TxMessageType::Broadcast(from_iface);
+        self.check_size(&packet, &tx_type).await?;
+
+        self.handler.lock().await.send_flushed(TxMessage { tx_type, packet }).await;
+        self.flush().await;
+
+        Ok(())
+    }
+
+    /// Sends `packet` on the interface `addr`. Fails without sending if it
+    /// is larger than the MTU of the interface.
+    pub async fn send_direct(&self, addr: AddressHash, packet: Packet) -> Result<(), SendError> {
+        let tx_type = TxMessageType::Direct(addr);
+        self.check_size(&packet, &tx_type).await?;
+
+        self.handler.lock().await.send_flushed(TxMessage { tx_type, packet }).await;
+        self.flush().await;
+
+        Ok(())
     }
 
     pub async fn send_to_all_out_links(&self, payload: &[u8]) {
@@ -377,10 +1116,13 @@ impl Transport {
             if link.status() == LinkStatus::Active {
                 let packet = link.data_packet(payload);
                 if let Ok(packet) = packet {
-                    handler.send_packet(packet).await;
+                    handler.send_packet_flushed(packet).await;
                 }
             }
         }
+        drop(handler);
+
+        self.flush().await;
     }
 
     pub async fn send_to_out_links(&self, destination: &AddressHash, payload: &[u8]) -> Vec<Hash> {
@@ -393,12 +1135,15 @@ impl Transport {
             {
                 let packet = link.data_packet(payload);
                 if let Ok(packet) = packet {
-                    handler.send_packet(packet).await;
+                    handler.send_packet_flushed(packet).await;
                     link.touch();
                     sent_packets.push(packet.hash());
                 }
             }
         }
+        drop(handler);
+
+        self.flush().await;
 
         if sent_packets.is_empty() {
             log::trace!(
@@ -411,6 +1156,54 @@ impl Transport {
         sent_packets
     }
 
+    /// Sends `payload` over `link` as soon as its window has room and returns
+    /// the hash of the sent packet.
+    ///
+    /// The window only grows when the peer proves the packets, see
+    /// [`LinkWindow`](crate::destination::link_window::LinkWindow). Packets
+    /// which aren't proved time out after a multiple of the link RTT, so
+    /// sending to peers which don't prove link packets is throttled but
+    /// makes progress.
+    pub async fn send_to_link(
+        &self,
+        link: &Arc<Mutex<Link>>,
+        payload: &[u8],
+    ) -> Result<Hash, RnsError> {
+        loop {
+            let (notify, timeout) = {
+                let mut link = link.lock().await;
+                if link.status() == LinkStatus::Closed {
+                    return Err(RnsError::LinkClosed);
+                }
+
+                let timeout = proof_timeout(*link.rtt());
+                link.window_mut().expire(self.clock.now(), timeout);
+
+                if link.window().is_open() {
+                    let packet = link.data_packet(payload)?;
+                    let hash = packet.hash();
+
+                    // Nothing is sent, so it mustn't take room in the window
+                    self.check_size(&packet, &TxMessageType::Broadcast(None)).await?;
+
+                    link.window_mut().sent(hash, self.clock.now());
+                    link.touch();
+                    drop(link);
+
+                    self.send_packet(packet).await?;
+
+                    return Ok(hash);
+                }
+
+                (link.window_notify(), timeout)
+            };
+
+            // A proof wakes the sender early, otherwise the oldest packet
+            // times out
+            let _ = runtime::timeout(timeout, notify.notified()).await;
+        }
+    }
+
     pub async fn send_to_in_links(&self, destination: &AddressHash, payload: &[u8]) {
         let handler = self.handler.lock().await;
         let mut count = 0usize;
@@ -422,12 +1215,15 @@ impl Transport {
             {
                 let packet = link.data_packet(payload);
                 if let Ok(packet) = packet {
-                    handler.send_packet(packet).await;
+                    handler.send_packet_flushed(packet).await;
                     link.touch();
                     count += 1;
                 }
             }
         }
+        drop(handler);
+
+        self.flush().await;
 
         if count == 0 {
             log::trace!(
@@ -438,34 +1234,363 @@ impl Transport {
         }
     }
 
+    async fn out_link_by_id(&self, link_id: &LinkId) -> Option<Arc<Mutex<Link>>> {
+        for link in self.handler.lock().await.out_links.values() {
+            if link.lock().await.id() == link_id {
+                return Some(link.clone());
+            }
+        }
+        None
+    }
+
     pub async fn find_out_link(&self, link_id: &AddressHash) -> Option<Arc<Mutex<Link>>> {
         self.handler.lock().await.out_links.get(link_id).cloned()
     }
 
-    pub async fn find_in_link(&self, link_id: &AddressHash) -> Option<Arc<Mutex<Link>>> {
-        self.handler.lock().await.in_links.get(link_id).cloned()
-    }
+    /// Sends `payload` over the inbound link `link_id`, e.g. to answer the
+    /// client which sent [`ReceivedData`] with that link id. Returns the hash
+    /// of the sent packet.
+    pub async fn reply_on_link(&self, link_id: &LinkId, payload: &[u8]) -> Result<Hash, LinkError> {
+        let handler = self.handler.lock().await;
 
-    pub async fn link(&self, destination: DestinationDesc) -> Arc<Mutex<Link>> {
-        let link = self
-            .handler
-            .lock()
-            .await
-            .out_links
-            .get(&destination.address_hash)
-            .cloned();
+        let link = handler
+            .in_links
+            .get(link_id)
+            .cloned()
+            .ok_or(LinkError::Unknown { link_id: *link_id })?;
+        let mut link = link.lock().await;
 
-        if let Some(link) = link {
-            if link.lock().await.status() != LinkStatus::Closed {
-                return link;
-            } else {
-                log::warn!("tp({}): link was closed", self.name);
-            }
-        }
+        let packet = link.data_packet(payload)?;
+        link.touch();
+        drop(link);
 
-        let mut link = Link::new(destination, self.link_out_event_tx.clone());
+        handler.send_packet_flushed(packet).await;
+        drop(handler);
 
-        let packet = link.request();
+        self.flush().await;
+
+        Ok(packet.hash())
+    }
+
+    pub async fn find_in_link(&self, link_id: &AddressHash) -> Option<Arc<Mutex<Link>>> {
+        self.handler.lock().await.in_links.get(link_id).cloned()
+    }
+
+    /// Returns a summary of every link the transport keeps, outbound links
+    /// first. Links which are closed but not yet cleaned up are included.
+    pub async fn active_links(&self) -> Vec<LinkSummary> {
+        let handler = self.handler.lock().await;
+
+        let mut links = Vec::with_capacity(handler.out_links.len() + handler.in_links.len());
+
+        for link in handler.out_links.values() {
+            links.push(LinkSummary::new(&*link.lock().await, LinkDirection::Outbound));
+        }
+
+        for link in handler.in_links.values() {
+            links.push(LinkSummary::new(&*link.lock().await, LinkDirection::Inbound));
+        }
+
+        links
+    }
+
+    /// Returns all known paths to `destination`, see [`PathPolicy`] for
+    /// how one of them is chosen.
+    pub async fn paths(&self, destination: &AddressHash) -> Vec<PathEntry> {
+        self.handler.lock().await.path_table.paths(destination).to_vec()
+    }
+
+    /// Returns the transport nodes the latest announce of `destination`
+    /// passed through, as far as they are known. `None` if hop paths aren't
+    /// recorded, see [`TransportConfig::set_record_hop_paths`], or the
+    /// destination didn't announce.
+    pub async fn hop_path(&self, destination: &AddressHash) -> Option<HopPath> {
+        let handler = self.handler.lock().await;
+
+        // Announces are kept by the identity of their destination
+        let identity = handler.destination_info.get(destination)?.identity.address_hash;
+        handler.announce_table.hop_path(&identity).cloned()
+    }
+
+    /// Routes packets to `destination` over `via_interface` regardless of
+    /// announces until [`Transport::unpin_path`]. `next_hop` is the transport
+    /// id of the relay to hand packets to, or `destination` itself if it is
+    /// a direct neighbour.
+    pub async fn pin_path(
+        &self,
+        destination: AddressHash,
+        via_interface: AddressHash,
+        next_hop: AddressHash,
+    ) {
+        let mut handler = self.handler.lock().await;
+
+        let (mode, bitrate) = {
+            let iface_manager = handler.iface_manager.lock().await;
+            (
+                iface_manager.mode(&via_interface).unwrap_or_default(),
+                iface_manager.bitrate(&via_interface),
+            )
+        };
+
+        // Relayed packets need the transport header, the hop count learned
+        // through the relay is kept if there is one
+        let hops = if next_hop == destination {
+            1
+        } else {
+            handler
+                .path_table
+                .paths(&destination)
+                .iter()
+                .find(|path| path.received_from == next_hop)
+                .map_or(2, |path| path.hops.max(2))
+        };
+
+        handler.path_table.pin(
+            destination,
+            PathEntry {
+                received_from: next_hop,
+                hops,
+                iface: via_interface,
+                mode,
+                bitrate,
+                announced: self.clock.now(),
+            },
+        );
+
+        let _ = handler.events_tx.send(TransportEvent::PathDiscovered {
+            destination,
+            hops,
+            iface: via_interface,
+        });
+    }
+
+    /// Removes a path set with [`Transport::pin_path`] and returns whether
+    /// there was one. Paths learned from announces are used again.
+    pub async fn unpin_path(&self, destination: &AddressHash) -> bool {
+        let mut handler = self.handler.lock().await;
+
+        if handler.path_table.unpin(destination).is_none() {
+            return false;
+        }
+
+        match handler.path_table.get(destination).map(|path| (path.hops, path.iface)) {
+            Some((hops, iface)) => {
+                let _ = handler.events_tx.send(TransportEvent::PathDiscovered {
+                    destination: *destination,
+                    hops,
+                    iface,
+                });
+            }
+            None => handle_lost_paths(&mut handler, vec![*destination]).await,
+        }
+
+        true
+    }
+
+    /// Routes packets to `destination` over `via_interface` until a path to
+    /// it is learned from announces, and again once learned paths are lost,
+    /// so fixed deployments work before announces got around. `hops` is the
+    /// distance to the destination, packets are handed to the interface as
+    /// if it were a neighbour.
+    pub async fn add_static_path(&self, destination: AddressHash, via_interface: AddressHash, hops: u8) {
+        let mut handler = self.handler.lock().await;
+
+        let (mode, bitrate) = {
+            let iface_manager = handler.iface_manager.lock().await;
+            (
+                iface_manager.mode(&via_interface).unwrap_or_default(),
+                iface_manager.bitrate(&via_interface),
+            )
+        };
+
+        let hops = hops.max(1);
+        let unknown = handler.path_table.get(&destination).is_none();
+
+        handler.path_table.add_static(
+            destination,
+            PathEntry {
+                received_from: destination,
+                hops,
+                iface: via_interface,
+                mode,
+                bitrate,
+                announced: self.clock.now(),
+            },
+        );
+
+        if unknown {
+            let _ = handler.events_tx.send(TransportEvent::PathDiscovered {
+                destination,
+                hops,
+                iface: via_interface,
+            });
+        }
+    }
+
+    /// Removes a path set with [`Transport::add_static_path`] and returns
+    /// whether there was one.
+    pub async fn remove_static_path(&self, destination: &AddressHash) -> bool {
+        let mut handler = self.handler.lock().await;
+
+        if handler.path_table.remove_static(destination).is_none() {
+            return false;
+        }
+
+        if handler.path_table.get(destination).is_none() {
+            handle_lost_paths(&mut handler, vec![*destination]).await;
+        }
+
+        true
+    }
+
+    /// Returns the data packets exchanged with a local or remote
+    /// destination, `None` if there were none.
+    pub async fn traffic_stats(&self, destination: &AddressHash) -> Option<TrafficStats> {
+        self.handler.lock().await.traffic.lock().await.get(destination)
+    }
+
+    /// Returns the traffic of every destination packets were exchanged with.
+    pub async fn all_traffic_stats(&self) -> Vec<(AddressHash, TrafficStats)> {
+        self.handler
+            .lock()
+            .await
+            .traffic
+            .lock()
+            .await
+            .all()
+            .map(|(destination, stats)| (*destination, *stats))
+            .collect()
+    }
+
+    /// Returns the selected path of every known destination.
+    pub async fn all_paths(&self) -> Vec<(AddressHash, PathEntry)> {
+        self.handler
+            .lock()
+            .await
+            .path_table
+            .selected()
+            .map(|(destination, path)| (*destination, path.clone()))
+            .collect()
+    }
+
+    pub async fn timer_config(&self) -> TimerConfig {
+        self.handler.lock().await.config.timer_config
+    }
+
+    pub async fn announce_counts(&self) -> AnnounceCounts {
+        self.handler.lock().await.announce_counts
+    }
+
+    pub async fn decryption_counts(&self) -> DecryptionCounts {
+        self.handler.lock().await.decryption_counts
+    }
+
+    /// Sent packets dropped because the interfaces didn't take them and the
+    /// outbox was full, see [`TransportConfig::set_outbox_capacity`].
+    pub fn outbox_dropped(&self) -> u64 {
+        self.outbox.dropped()
+    }
+
+    pub async fn rejected_proofs(&self) -> RejectedProofs {
+        self.handler.lock().await.rejected_proofs
+    }
+
+    /// Returns the number of entries and approximate memory of each table,
+    /// e.g. to tune the table limits of embedded nodes.
+    pub async fn memory_stats(&self) -> MemoryStats {
+        let handler = self.handler.lock().await;
+
+        let links = handler.in_links.len() + handler.out_links.len();
+        let in_destinations = handler.single_in_destinations.len();
+        let out_destinations = handler.single_out_destinations.len();
+        let app_data = handler.destination_info.values().map(|info| info.app_data.capacity()).sum();
+
+        let destinations =
+            TableStats::of_map::<AddressHash, Arc<Mutex<SingleInputDestination>>>(in_destinations)
+                .with_heap(in_destinations * size_of::<Mutex<SingleInputDestination>>())
+                + TableStats::of_map::<AddressHash, Arc<Mutex<SingleOutputDestination>>>(out_destinations)
+                    .with_heap(out_destinations * size_of::<Mutex<SingleOutputDestination>>())
+                + TableStats::of_map::<AddressHash, DestinationInfo>(handler.destination_info.len())
+                    .with_heap(app_data);
+
+        let packet_cache = handler.packet_cache.lock().await.stats();
+        let traffic = handler.traffic.lock().await.stats();
+        let verified_announces = handler.verified_announces.lock().unwrap().stats();
+
+        MemoryStats {
+            paths: handler.path_table.stats(),
+            announces: handler.announce_table.stats(),
+            links: TableStats::of_map::<AddressHash, Arc<Mutex<Link>>>(links)
+                .with_heap(links * size_of::<Mutex<Link>>()),
+            relayed_links: handler.link_table.stats(),
+            packet_cache,
+            destinations,
+            verified_announces,
+            traffic,
+        }
+    }
+
+    /// Returns how long received packets waited for and took to process,
+    /// to spot a node which can't keep up with its interfaces. Doesn't wait
+    /// for the handler.
+    pub fn processing_stats(&self) -> ProcessingStats {
+        *self.processing_stats.lock().unwrap()
+    }
+
+    /// Replayed packets dropped by local links, including links which are
+    /// closed by now.
+    pub async fn link_replays(&self) -> u64 {
+        self.handler.lock().await.link_replays
+    }
+
+    /// Returns the keys, ratchet and app data last announced by `destination`.
+    pub async fn destination_info(&self, destination: &AddressHash) -> Option<DestinationInfo> {
+        self.handler.lock().await.destination_info.get(destination).cloned()
+    }
+
+    /// Writes the known announces to the configured announce cache file.
+    /// Does nothing if no file is configured.
+    pub async fn persist_announces(&self) -> io::Result<()> {
+        persist_announces(&*self.handler.lock().await)
+    }
+
+    /// Treats outbound links to `destination` according to `policy` rather
+    /// than the timers, see [`LinkPolicy`]. Applies to existing links as
+    /// well.
+    pub async fn set_link_policy(&self, destination: AddressHash, policy: LinkPolicy) {
+        self.handler.lock().await.link_policies.insert(destination, policy);
+    }
+
+    /// Returns outbound links to `destination` to the timers.
+    pub async fn remove_link_policy(&self, destination: &AddressHash) {
+        self.handler.lock().await.link_policies.remove(destination);
+    }
+
+    /// The policy outbound links to `destination` follow.
+    pub async fn link_policy(&self, destination: &AddressHash) -> LinkPolicy {
+        self.handler.lock().await.link_policy(destination)
+    }
+
+    pub async fn link(&self, destination: DestinationDesc) -> Arc<Mutex<Link>> {
+        let link = self
+            .handler
+            .lock()
+            .await
+            .out_links
+            .get(&destination.address_hash)
+            .cloned();
+
+        if let Some(link) = link {
+            if link.lock().await.status() != LinkStatus::Closed {
+                return link;
+            } else {
+                log::warn!("tp({}): link was closed", self.name);
+            }
+        }
+
+        let mut link = self.handler.lock().await.new_out_link(destination);
+
+        let packet = link.request();
 
         log::debug!(
             "tp({}): create new link {} for destination {}",
@@ -476,13 +1601,16 @@ impl Transport {
 
         let link = Arc::new(Mutex::new(link));
 
-        self.send_packet(packet).await;
+        // The proof may arrive before `send_packet` returns
+        {
+            let mut handler = self.handler.lock().await;
+            handler.traffic.lock().await.add_link(*link.lock().await.id(), destination.address_hash);
+            handler.out_links.insert(destination.address_hash, link.clone());
+        }
 
-        self.handler
-            .lock()
-            .await
-            .out_links
-            .insert(destination.address_hash, link.clone());
+        if let Err(err) = self.send_packet(packet).await {
+            log::warn!("tp({}): couldn't send link request: {}", self.name, err);
+        }
 
         link
     }
@@ -491,13 +1619,13 @@ impl Transport {
         let link = if let Some(link) = self.find_in_link(&link_id).await {
             Some(link)
         } else {
-            self.find_out_link(&link_id).await
+            self.out_link_by_id(&link_id).await
         };
         if let Some(link) = link {
             let mut link = link.lock().await;
             if let Some(packet) = link.teardown()? {
                 drop(link);
-                self.send_packet(packet).await
+                self.send_packet(packet).await?;
             }
         } else {
             log::warn!("tp({}): close link {link_id} not found", self.name)
@@ -515,7 +1643,8 @@ impl Transport {
             .lock()
             .await
             .request_path(destination, on_iface, tag)
-            .await
+            .await;
+        self.flush().await;
     }
 
     pub fn out_link_events(&self) -> broadcast::Receiver<LinkEventData> {
@@ -539,8 +1668,41 @@ impl Transport {
         self.received_data_tx.subscribe()
     }
 
+    /// Subscribes to the requests peers send over inbound links, answer them
+    /// with [`Link::response_packet`].
+    pub fn link_requests(&self) -> broadcast::Receiver<ReceivedRequest> {
+        self.link_requests_tx.subscribe()
+    }
+
+    /// Subscribes to the responses to requests sent over outbound links with
+    /// [`Link::request_packet`].
+    pub fn link_responses(&self) -> broadcast::Receiver<ReceivedResponse> {
+        self.link_responses_tx.subscribe()
+    }
+
+    /// Subscribes to all events of the transport: announces, path changes,
+    /// link activation and closing, interfaces coming and going and received
+    /// data.
+    pub fn events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events_tx.subscribe()
+    }
+
+    /// Subscribes to the events for which `filter` returns `true`, e.g.
+    /// `transport.events_filtered(TransportEvent::is_link)`.
+    pub fn events_filtered<F>(&self, filter: F) -> EventSubscription
+    where
+        F: Fn(&TransportEvent) -> bool + Send + Sync + 'static,
+    {
+        EventSubscription::new(self.events_tx.subscribe(), Box::new(filter))
+    }
+
+    /// Subscribes to the events about `destination`.
+    pub fn events_for_destination(&self, destination: AddressHash) -> EventSubscription {
+        self.events_filtered(move |event| event.destination() == Some(&destination))
+    }
+
     pub async fn add_destination(
-        &mut self,
+        &self,
         identity: PrivateIdentity,
         name: DestinationName,
     ) -> Arc<Mutex<SingleInputDestination>> {
@@ -592,6 +1754,12 @@ impl Transport {
         self.handler.lock().await.knows_destination(address)
     }
 
+    /// Passes the packets queued by the handler to the interfaces. Called
+    /// after releasing the handler lock.
+    async fn flush(&self) {
+        self.outbox.flush(&self.iface_manager).await;
+    }
+
     #[allow(unused)]
     // For testing purposes only. Since it is only used in unit tests, it
     // would generate a warning when running cargo build.
@@ -607,6 +1775,43 @@ impl Drop for Transport {
 }
 
 impl TransportHandler {
+    fn link_policy(&self, destination: &AddressHash) -> LinkPolicy {
+        self.link_policies.get(destination).copied().unwrap_or_else(|| {
+            LinkPolicy::from_timers(&self.config.timer_config, self.config.restart_outlinks)
+        })
+    }
+
+    /// Outbound link to `destination` with the link settings of the config,
+    /// not requested yet.
+    fn new_out_link(&self, destination: DestinationDesc) -> Link {
+        let mut link = Link::new_with_rng(destination, self.link_out_event_tx.clone(), self.config.rng.clone())
+            .with_clock(self.config.clock.clone());
+        link.set_compression(self.config.link_compression);
+        link.set_sequencing(self.config.link_sequencing);
+        link
+    }
+
+    /// Replaces the dead outbound link `old` to `destination` with a new
+    /// one and requests it.
+    async fn reestablish_link(&mut self, destination: DestinationDesc, old: LinkId) {
+        let mut link = self.new_out_link(destination);
+        let packet = link.request();
+        let id = *link.id();
+
+        log::info!(
+            "tp({}): reestablishing link {} to {} as {}",
+            self.config.name,
+            old,
+            destination.address_hash,
+            id
+        );
+
+        self.reestablished.insert(id, old);
+        self.traffic.lock().await.add_link(id, destination.address_hash);
+        self.out_links.insert(destination.address_hash, Arc::new(Mutex::new(link)));
+        self.send_packet(packet).await;
+    }
+
     async fn send_packet(&self, packet: Packet) {
         let message = TxMessage {
             tx_type: TxMessageType::Broadcast(None),
@@ -616,42 +1821,188 @@ impl TransportHandler {
         self.send(message).await;
     }
 
+    /// Like [`TransportHandler::send_packet`], see
+    /// [`TransportHandler::send_flushed`].
+    async fn send_packet_flushed(&self, packet: Packet) {
+        let message = TxMessage {
+            tx_type: TxMessageType::Broadcast(None),
+            packet,
+        };
+
+        self.send_flushed(message).await;
+    }
+
+    /// Queues `message` for the interfaces, which get it once the handler
+    /// lock is released, see [`Outbox`]. It is dropped if the outbox is full.
     async fn send(&self, message: TxMessage) {
-        self.packet_cache.lock().await.update(&message.packet);
-        self.iface_manager.lock().await.send(message).await;
+        let packet = message.packet;
+        if self.outbox.push(message) {
+            self.record_sent(&packet).await;
+        }
+    }
+
+    /// Queues `message` like [`TransportHandler::send`], but even if the
+    /// outbox is full. For API calls, which flush the outbox right after
+    /// and so wait for the interfaces to take their packets.
+    async fn send_flushed(&self, message: TxMessage) {
+        let packet = message.packet;
+        self.outbox.push_flushed(message);
+        self.record_sent(&packet).await;
+    }
+
+    async fn record_sent(&self, packet: &Packet) {
+        self.packet_cache.lock().await.update(packet);
+        self.traffic.lock().await.sent(packet);
     }
 
     fn has_destination(&self, address: &AddressHash) -> bool {
         self.single_in_destinations.contains_key(address)
     }
 
+    async fn link_access(&self, address: &AddressHash) -> LinkAccess {
+        match self.single_in_destinations.get(address) {
+            Some(destination) => destination.lock().await.link_access().clone(),
+            None => LinkAccess::All,
+        }
+    }
+
+    /// Answers `request` over `link` with the handler registered for its
+    /// path, unless the policy of the path doesn't allow the peer.
+    async fn answer_request(&self, link: &Link, request: &LinkRequest, registered: &RegisteredHandler) {
+        let remote_identity = link.remote_identity();
+        if !registered.policy.allows(remote_identity) {
+            log::debug!(
+                "tp({}): request {} to {} over link {} isn't allowed",
+                self.config.name,
+                request.request_id,
+                registered.path,
+                link.id()
+            );
+            return;
+        }
+
+        let incoming = IncomingRequest {
+            path: &registered.path,
+            data: &request.data,
+            request_id: request.request_id,
+            link_id: *link.id(),
+            remote_identity,
+            requested_at: request.sent_at,
+        };
+        let Some(response) = (registered.handler)(&incoming) else {
+            return;
+        };
+
+        match link.response_packet(&request.request_id, &response) {
+            Ok(packet) => self.send_packet(packet).await,
+            Err(err) => log::warn!(
+                "tp({}): couldn't respond to request {} to {}: {}",
+                self.config.name,
+                request.request_id,
+                registered.path,
+                err
+            ),
+        }
+    }
+
+    /// Inbound links to `destination` which aren't closed.
+    async fn active_in_links(&self, destination: &AddressHash) -> usize {
+        let mut links = 0;
+        for link in self.in_links.values() {
+            let link = link.lock().await;
+            if link.destination().address_hash == *destination && link.status() != LinkStatus::Closed
+            {
+                links += 1;
+            }
+        }
+
+        links
+    }
+
+    /// Returns whether a local destination with `limits` may accept another
+    /// inbound link.
+    async fn accepts_link(&mut self, destination: &AddressHash, limits: &LinkLimits) -> bool {
+        if let Some(max_links) = limits.max_links {
+            let links = self.active_in_links(destination).await;
+
+            if links >= max_links {
+                log::debug!(
+                    "tp({}): {} has {} links already",
+                    self.config.name,
+                    destination,
+                    links
+                );
+                return false;
+            }
+        }
+
+        if let Some(rate) = limits.request_rate {
+            let now = self.config.clock.now();
+            if !self.link_request_limits.accept(destination, rate, now) {
+                log::debug!(
+                    "tp({}): too many link requests for {}",
+                    self.config.name,
+                    destination
+                );
+                return false;
+            }
+        }
+
+        true
+    }
+
     fn knows_destination(&self, address: &AddressHash) -> bool {
         self.single_out_destinations.contains_key(address)
     }
 
-    async fn filter_duplicate_packets(&self, packet: &Packet) -> bool {
-        let mut allow_duplicate = false;
+    /// Whether `packet`, which isn't for this node, may be passed on. Only
+    /// transport nodes forward, and packets addressed to another transport
+    /// are left to it.
+    fn forwards(&self, packet: &Packet) -> bool {
+        let forwards = self.config.transport_enabled
+            && packet
+                .transport
+                .is_none_or(|transport| transport == *self.config.identity.address_hash());
+        self.assert_relaying_allowed(forwards);
+
+        forwards
+    }
+
+    /// Checks that nodes in client mode don't get to relay whatever a code
+    /// path decided, the config keeps them from it.
+    fn assert_relaying_allowed(&self, relays: bool) {
+        debug_assert!(
+            !(relays && self.config.client_mode),
+            "tp({}): client mode must not relay packets",
+            self.config.name
+        );
+    }
 
-        match packet.header.packet_type {
+    async fn filter_duplicate_packets(&self, packet: &Packet) -> bool {
+        let mut allow_duplicate = match packet.header.packet_type {
             PacketType::Announce => {
                 return true;
             }
-            PacketType::LinkRequest => {
-                allow_duplicate = true;
-            }
-            PacketType::Data => {
-                allow_duplicate = packet.context == PacketContext::KeepAlive;
-            }
-            PacketType::Proof => {
-                if packet.context == PacketContext::LinkRequestProof {
-                    if let Some(link) = self.in_links.get(&packet.destination) {
-                        if link.lock().await.status().not_yet_active() {
-                            allow_duplicate = true;
-                        }
-                    }
-                }
-            },
-        }
+            PacketType::LinkRequest => true,
+            PacketType::Data => false,
+            // Python never remembers link request proofs
+            PacketType::Proof => packet.context == PacketContext::LinkRequestProof,
+        };
+
+        // Same exemptions as Python's packet filter, these contexts repeat
+        // identical payloads on purpose
+        allow_duplicate |= matches!(
+            packet.context,
+            PacketContext::KeepAlive
+                | PacketContext::Resource
+                | PacketContext::ResourceRequest
+                | PacketContext::ResourceProof
+                | PacketContext::CacheRequest
+                | PacketContext::Channel
+        );
+
+        // Traffic of links routed through this node
+        allow_duplicate |= self.link_table.contains(&packet.destination);
 
         let is_new = self.packet_cache.lock().await.update(packet);
 
@@ -674,42 +2025,112 @@ impl TransportHandler {
     }
 }
 
-async fn handle_proof<'a>(packet: &Packet, mut handler: MutexGuard<'a, TransportHandler>) {
+async fn handle_proof<'a>(
+    packet: &Packet,
+    mut handler: MutexGuard<'a, TransportHandler>,
+    iface: AddressHash,
+) {
     log::trace!(
         "tp({}): handle proof for {}",
         handler.config.name,
         packet.destination
     );
 
-    for link in handler.out_links.values() {
+    let mut own_link = false;
+    let mut rejected = None;
+    let mut activated = None;
+
+    for (destination, link) in &handler.out_links {
         let mut link = link.lock().await;
-        if let LinkHandleResult::Activated = link.handle_packet(packet, true) {
-            let rtt_packet = link.create_rtt();
-            handler.send_packet(rtt_packet).await;
+        own_link |= *link.id() == packet.destination;
+        match link.handle_packet(packet, true) {
+            LinkHandleResult::Activated => {
+                activated = Some((*link.id(), *destination));
+                let rtt_packet = link.create_rtt();
+                handler.send_packet(rtt_packet).await;
+                if let Some(offer) = link.compression_offer() {
+                    handler.send_packet(offer).await;
+                }
+            }
+            LinkHandleResult::ProofRejected(err) => rejected = Some(err),
+            _ => {}
         }
     }
 
     for link in handler.in_links.values() {
-        link.lock().await.handle_packet(packet, false);
+        let mut link = link.lock().await;
+        own_link |= *link.id() == packet.destination;
+        link.handle_packet(packet, false);
     }
 
-    let maybe_packet = handler.link_table.handle_proof(packet);
+    if let Some((new, destination)) = activated {
+        if let Some(old) = handler.reestablished.remove(&new) {
+            log::info!("tp({}): link {} reestablished as {}", handler.config.name, old, new);
+            let _ = handler.link_out_event_tx.send(LinkEventData {
+                id: new,
+                address_hash: destination,
+                event: LinkEvent::Reestablished { old, new },
+            });
+        }
+    }
 
-    if let Some((packet, iface)) = maybe_packet {
-        handler
-            .send(TxMessage {
-                tx_type: TxMessageType::Direct(iface),
-                packet,
-            })
-            .await;
+    if packet.context != PacketContext::LinkRequestProof {
+        if packet.header.destination_type == DestinationType::Link {
+            forward_link_packet(packet, &handler, iface).await;
+        }
+        return;
     }
-}
 
-async fn send_to_next_hop<'a>(
-    packet: &Packet,
+    if let Some(err) = rejected {
+        handler.rejected_proofs.count(&err);
+        return;
+    }
+
+    let identity = handler
+        .link_table
+        .destination(&packet.destination)
+        .and_then(|destination| handler.destination_info.get(&destination))
+        .map(|info| info.identity);
+
+    match handler.link_table.handle_proof(packet, iface, identity.as_ref()) {
+        Ok(Some((packet, iface))) => {
+            handler
+                .send(TxMessage {
+                    tx_type: TxMessageType::Direct(iface),
+                    packet,
+                })
+                .await;
+        }
+        Ok(None) if own_link => {}
+        Ok(None) => {
+            log::debug!(
+                "tp({}): proof for unknown link {}",
+                handler.config.name,
+                packet.destination
+            );
+            handler.rejected_proofs.count(&ProofError::UnknownLink(packet.destination));
+        }
+        Err(err) => {
+            log::warn!(
+                "tp({}): dropped relayed proof of link {}: {}",
+                handler.config.name,
+                packet.destination,
+                err
+            );
+            handler.rejected_proofs.count(&err);
+        }
+    }
+}
+
+async fn send_to_next_hop<'a>(
+    packet: &Packet,
     handler: &MutexGuard<'a, TransportHandler>,
     lookup: Option<AddressHash>,
 ) -> bool {
+    if !handler.forwards(packet) {
+        return false;
+    }
+
     let (packet, maybe_iface) = handler.path_table.handle_inbound_packet(packet, lookup);
 
     if let Some(iface) = maybe_iface {
@@ -724,37 +2145,50 @@ async fn send_to_next_hop<'a>(
     maybe_iface.is_some()
 }
 
-async fn handle_keepalive_response<'a>(
+async fn forward_link_packet<'a>(
     packet: &Packet,
     handler: &MutexGuard<'a, TransportHandler>,
+    iface: AddressHash,
 ) -> bool {
-    if packet.context == PacketContext::KeepAlive
-        && packet.data.as_slice()[0] == KEEP_ALIVE_RESPONSE
-    {
-        let lookup = handler.link_table.handle_keepalive(packet);
+    if !handler.forwards(packet) {
+        return false;
+    }
 
-        if let Some((propagated, iface)) = lookup {
-            handler.send(TxMessage {
-                tx_type: TxMessageType::Direct(iface),
-                packet: propagated,
-            })
-            .await;
-        }
+    let Some((forwarded, out_iface)) = handler.link_table.forward(packet, iface) else {
+        return false;
+    };
 
-        return true;
-    }
+    log::trace!(
+        "tp({}): forward packet to remote link {} ctx={:?}",
+        handler.config.name,
+        packet.destination,
+        packet.context,
+    );
+
+    handler
+        .send(TxMessage {
+            tx_type: TxMessageType::Direct(out_iface),
+            packet: forwarded,
+        })
+        .await;
 
-    false
+    true
 }
 
-async fn handle_data<'a>(packet: &Packet, handler: MutexGuard<'a, TransportHandler>) {
+async fn handle_data<'a>(
+    packet: &Packet,
+    mut handler: MutexGuard<'a, TransportHandler>,
+    iface: AddressHash,
+    decrypted: Option<(PacketDataBuffer, DecryptionKey)>,
+) {
     let mut data_handled = false;
 
     if packet.header.destination_type == DestinationType::Link {
-        let mut local_out_link_handled = false;
+        let mut local_link_handled = false;
 
         if let Some(link) = handler.in_links.get(&packet.destination).cloned() {
             let mut link = link.lock().await;
+            handler.traffic.lock().await.received(packet);
             let result = link.handle_packet(packet, false);
             match result {
                 LinkHandleResult::KeepAlive => {
@@ -764,59 +2198,181 @@ async fn handle_data<'a>(packet: &Packet, handler: MutexGuard<'a, TransportHandl
                 LinkHandleResult::MessageReceived(Some(proof)) => {
                     handler.send_packet(proof).await;
                 }
+                LinkHandleResult::CompressionAgreed(Some(answer)) => {
+                    handler.send_packet(answer).await;
+                }
+                LinkHandleResult::DataReceived(proof, payload) => {
+                    if let Some(proof) = proof {
+                        handler.send_packet(proof).await;
+                    }
+
+                    let received = ReceivedData {
+                        destination: link.destination().address_hash,
+                        data: PacketDataBuffer::new_from_slice(payload.as_slice()),
+                        packet_hash: packet.hash(),
+                        iface,
+                        source_identity: link.remote_identity().copied(),
+                        proof_requested: false,
+                        link_id: Some(*link.id()),
+                        sequence: payload.sequence(),
+                        decrypted_with: None,
+                    };
+
+                    let _ = handler
+                        .events_tx
+                        .send(TransportEvent::DataReceived(Box::new(received.clone())));
+                    handler.received_data_tx.send(received).ok();
+                }
+                LinkHandleResult::Replayed(proof) => {
+                    handler.link_replays += 1;
+                    if let Some(proof) = proof {
+                        handler.send_packet(proof).await;
+                    }
+                }
+                LinkHandleResult::Identified(identity) => {
+                    let access = handler.link_access(&link.destination().address_hash).await;
+                    if !access.allows(&identity) {
+                        log::info!(
+                            "tp({}): closing link {} of identity {} which isn't allowed",
+                            handler.config.name,
+                            link.id(),
+                            identity.address_hash
+                        );
+                        let link_id = *link.id();
+                        if let Some(packet) = link.teardown().unwrap_or_else(|err| {
+                            log::error!("tp({}): teardown link error: {err:?}", handler.config.name);
+                            None
+                        }) {
+                            handler.send_packet(packet).await;
+                        }
+                        handler.in_links.remove(&link_id);
+                        handler.traffic.lock().await.remove_link(&link_id);
+                    } else {
+                        link.post_event(LinkEvent::IdentityReceived(Box::new(identity)));
+                    }
+                }
+                LinkHandleResult::Request(request)
+                    if link.remote_identity().is_none()
+                        && handler
+                            .link_access(&link.destination().address_hash)
+                            .await
+                            .is_restricted() =>
+                {
+                    log::debug!(
+                        "tp({}): dropping request {} over unidentified link {}",
+                        handler.config.name,
+                        request.request_id,
+                        link.id()
+                    );
+                }
+                LinkHandleResult::Request(request) => {
+                    link.post_event(LinkEvent::RequestReceived(request.clone()));
+
+                    let address = link.destination().address_hash;
+                    let registered = match handler.single_in_destinations.get(&address) {
+                        Some(destination) => destination.lock().await.request_handler(&request.path_hash).cloned(),
+                        None => None,
+                    };
+
+                    match registered {
+                        Some(registered) => handler.answer_request(&link, &request, &registered).await,
+                        None => {
+                            let _ = handler.link_requests_tx.send(ReceivedRequest {
+                                link_id: *link.id(),
+                                destination: address,
+                                remote_identity: link.remote_identity().copied(),
+                                request: *request,
+                            });
+                        }
+                    }
+                }
                 _ => {}
             }
+
+            local_link_handled = true;
         }
 
+        let mut out_link = None;
         for link in handler.out_links.values() {
+            if link.lock().await.id() == &packet.destination {
+                out_link = Some(link.clone());
+                break;
+            }
+        }
+
+        if let Some(link) = out_link {
             let mut link = link.lock().await;
-            if link.id() == &packet.destination {
-                let result = link.handle_packet(packet, true);
+            handler.traffic.lock().await.received(packet);
+            let result = link.handle_packet(packet, true);
 
-                if let LinkHandleResult::MessageReceived(Some(proof)) = result {
+            match result {
+                LinkHandleResult::MessageReceived(Some(proof))
+                | LinkHandleResult::DataReceived(Some(proof), _) => {
                     handler.send_packet(proof).await;
                 }
-
-                local_out_link_handled = true;
-                data_handled = true;
+                LinkHandleResult::Replayed(proof) => {
+                    handler.link_replays += 1;
+                    if let Some(proof) = proof {
+                        handler.send_packet(proof).await;
+                    }
+                }
+                LinkHandleResult::Response(response) => {
+                    let _ = handler.link_responses_tx.send(ReceivedResponse {
+                        link_id: *link.id(),
+                        destination: link.destination().address_hash,
+                        response: *response,
+                    });
+                }
+                _ => {}
             }
-        }
 
-        if !local_out_link_handled && handle_keepalive_response(packet, &handler).await {
-            return;
+            local_link_handled = true;
+            data_handled = true;
         }
 
-        if !local_out_link_handled {
-            let lookup = handler.link_table.original_destination(&packet.destination);
-            if lookup.is_some() {
-                let sent = send_to_next_hop(packet, &handler, lookup).await;
-
-                log::trace!(
-                    "tp({}): {} packet to remote link {}",
-                    handler.config.name,
-                    if sent {
-                        "forwarded"
-                    } else {
-                        "could not forward"
-                    },
-                    packet.destination
-                );
-            }
+        if !local_link_handled {
+            forward_link_packet(packet, &handler, iface).await;
         }
     }
 
     if packet.header.destination_type == DestinationType::Single {
-        if let Some(_destination) = handler
-            .single_in_destinations
-            .get(&packet.destination)
-            .cloned()
-        {
+        if handler.single_in_destinations.contains_key(&packet.destination) {
             data_handled = true;
+            handler.traffic.lock().await.received(packet);
+
+            // Like Python Reticulum, packets none of the keys decrypt are
+            // dropped rather than passed on as garbage
+            let (data, key) = match decrypted {
+                Some(decrypted) => decrypted,
+                None => {
+                    log::debug!(
+                        "tp({}): dropping packet {} to {} which none of its keys decrypt",
+                        handler.config.name,
+                        packet.hash(),
+                        packet.destination
+                    );
+                    handler.decryption_counts.count(None);
+                    return;
+                }
+            };
+            handler.decryption_counts.count(Some(key));
 
-            handler.received_data_tx.send(ReceivedData {
+            let received = ReceivedData {
                 destination: packet.destination,
-                data: packet.data,
-            }).ok();
+                data,
+                packet_hash: packet.hash(),
+                iface,
+                source_identity: None,
+                proof_requested: packet.context == PacketContext::None,
+                link_id: None,
+                sequence: None,
+                decrypted_with: Some(key),
+            };
+
+            let _ = handler
+                .events_tx
+                .send(TransportEvent::DataReceived(Box::new(received.clone())));
+            handler.received_data_tx.send(received).ok();
         } else {
             data_handled = send_to_next_hop(packet, &handler, None).await;
         }
@@ -828,15 +2384,91 @@ async fn handle_data<'a>(packet: &Packet, handler: MutexGuard<'a, TransportHandl
             handler.config.name,
             packet.destination,
             packet.header.destination_type as u8,
-            packet.context as u8,
+            u8::from(packet.context),
         );
     }
 }
 
+/// What the packet task works out about a packet before it locks the
+/// handler, as that work is CPU bound.
+#[derive(Default)]
+struct PacketChecks {
+    /// Whether an announce carries a valid signature.
+    valid_announce: bool,
+    /// Data to a local single destination, decrypted, and the key which
+    /// decrypted it. `None` if none of the keys do.
+    decrypted: Option<(PacketDataBuffer, DecryptionKey)>,
+}
+
+async fn check_packet(
+    handler: &Mutex<TransportHandler>,
+    verified: &std::sync::Mutex<VerifiedAnnounces>,
+    packet: &Packet,
+) -> PacketChecks {
+    let valid_announce = packet.header.packet_type == PacketType::Announce
+        && verify_announce(verified, packet).await;
+
+    PacketChecks {
+        valid_announce,
+        decrypted: decrypt_data(handler, packet).await,
+    }
+}
+
+/// Decrypts a data packet to a local single destination. Trying its keys
+/// takes a key exchange for every ratchet, so it runs off the async
+/// executor, with neither the handler nor the destination locked.
+async fn decrypt_data(
+    handler: &Mutex<TransportHandler>,
+    packet: &Packet,
+) -> Option<(PacketDataBuffer, DecryptionKey)> {
+    if packet.header.packet_type != PacketType::Data
+        || packet.header.destination_type != DestinationType::Single
+    {
+        return None;
+    }
+
+    let (destination, rng) = {
+        let handler = handler.lock().await;
+        let destination = handler.single_in_destinations.get(&packet.destination)?.clone();
+        (destination, handler.config.rng.clone())
+    };
+    let keys = destination.lock().await.decryption_keys();
+
+    let data = packet.data;
+    runtime::run_blocking(move || {
+        let mut buffer = [0u8; PACKET_MDU];
+        keys.decrypt(&rng, data.as_slice(), &mut buffer)
+            .ok()
+            .map(|(plain_text, key)| (PacketDataBuffer::new_from_slice(plain_text), key))
+    })
+    .await
+    .flatten()
+}
+
+/// Verifies the signature of an announce unless the outcome is already known.
+///
+/// Signature verification is CPU bound, so it runs off the async executor
+/// and without the handler lock held.
+async fn verify_announce(verified: &std::sync::Mutex<VerifiedAnnounces>, announce: &Packet) -> bool {
+    if let Some(is_valid) = verified.lock().unwrap().get(announce) {
+        return is_valid;
+    }
+
+    let packet = *announce;
+    let is_valid = runtime::run_blocking(move || DestinationAnnounce::validate(&packet).is_ok())
+        .await
+        .unwrap_or(false);
+
+    verified.lock().unwrap().insert(announce, is_valid);
+
+    is_valid
+}
+
 async fn handle_announce<'a>(
     packet: &Packet,
     mut handler: MutexGuard<'a, TransportHandler>,
     iface: AddressHash,
+    is_valid: bool,
 ) {
     if handler.has_destination(&packet.destination) {
         // destination is local
@@ -850,13 +2482,44 @@ async fn handle_announce<'a>(
             packet.destination,
             blocked_until.as_secs(),
         );
+        handler.announce_counts.dropped += 1;
+        return;
+    }
+
+    // The packet task has verified the signature before locking the handler
+    if !is_valid {
+        log::debug!(
+            "tp({}): dropping announce with invalid signature for {}",
+            handler.config.name,
+            packet.destination
+        );
+        handler.announce_counts.dropped += 1;
         return;
     }
 
-    if let Ok(result) = DestinationAnnounce::validate(packet) {
+    if let Ok(result) = DestinationAnnounce::decode_verified(packet) {
         let destination = result.0;
         let app_data = result.1;
         let dest_hash = destination.identity.address_hash;
+        handler.announce_counts.received += 1;
+
+        let ratchet = DestinationAnnounce::ratchet(packet);
+        let now = handler.config.clock.now();
+        let previous_ratchet = handler
+            .destination_info
+            .get(&packet.destination)
+            .and_then(|info| info.ratchet);
+        handler.destination_info.insert(
+            packet.destination,
+            DestinationInfo {
+                identity: destination.identity,
+                ratchet: ratchet.or(previous_ratchet),
+                app_data: app_data.to_vec(),
+                hops: packet.header.hops + 1,
+                last_seen: now,
+            },
+        );
+
         let destination = Arc::new(Mutex::new(destination));
 
         if !handler
@@ -876,22 +2539,43 @@ async fn handle_announce<'a>(
 
         handler.announce_table.add(packet, dest_hash, iface);
 
-        handler
+        let (mode, bitrate) = {
+            let iface_manager = handler.iface_manager.lock().await;
+            (iface_manager.mode(&iface).unwrap_or_default(), iface_manager.bitrate(&iface))
+        };
+
+        if handler
             .path_table
-            .handle_announce(packet, packet.transport, iface);
+            .handle_announce(packet, packet.transport, iface, mode, bitrate)
+        {
+            if let Some(path) = handler.path_table.get(&packet.destination) {
+                let _ = handler.events_tx.send(TransportEvent::PathDiscovered {
+                    destination: packet.destination,
+                    hops: path.hops,
+                    iface: path.iface,
+                });
+            }
+        }
 
-        let retransmit = handler.config.retransmit;
-        if retransmit {
+        if handler.config.transport_enabled {
+            handler.assert_relaying_allowed(true);
             let transport_id = *handler.config.identity.address_hash();
             if let Some(message) = handler.announce_table.new_packet(&dest_hash, &transport_id) {
+                handler.announce_counts.retransmitted += 1;
                 handler.send(message).await;
             }
         }
 
-        let _ = handler.announce_tx.send(AnnounceEvent {
+        let event = AnnounceEvent {
             destination,
             app_data: PacketDataBuffer::new_from_slice(app_data),
-        });
+        };
+
+        let _ = handler
+            .events_tx
+            .send(TransportEvent::AnnounceReceived(Box::new(event.clone())));
+        handler.announce_replay.push(packet.destination, event.clone());
+        let _ = handler.announce_tx.send(event);
     }
 }
 
@@ -905,9 +2589,20 @@ async fn handle_path_request<'a>(
             let response = dest
                 .lock()
                 .await
-                .path_response(OsRng, None)
+                .path_response(&handler.config.rng, None)
                 .expect("valid path response");
 
+            let mtu = handler.iface_manager.lock().await.mtu(&iface);
+            if mtu.is_some_and(|mtu| response.wire_len() > mtu) {
+                log::warn!(
+                    "tp({}): path response for {} doesn't fit the MTU of {}",
+                    handler.config.name,
+                    request.destination,
+                    iface
+                );
+                return;
+            }
+
             handler
                 .send(TxMessage {
                     tx_type: TxMessageType::Direct(iface),
@@ -924,8 +2619,24 @@ async fn handle_path_request<'a>(
             return;
         }
 
-        if handler.config.retransmit {
+        let iface_mode = handler
+            .iface_manager
+            .lock()
+            .await
+            .mode(&iface)
+            .unwrap_or_default();
+
+        if handler.config.transport_enabled {
             if let Some(entry) = handler.path_table.get(&request.destination) {
+                if iface_mode == InterfaceMode::Roaming && entry.iface == iface {
+                    log::trace!(
+                        "tp({}): not answering path request for {}, next hop is on the same roaming interface",
+                        handler.config.name,
+                        request.destination
+                    );
+                    return;
+                }
+
                 if let Some(requestor_id) = request.requesting_transport {
                     if requestor_id == entry.received_from {
                         log::trace!(
@@ -939,6 +2650,7 @@ async fn handle_path_request<'a>(
 
                 let hops = entry.hops;
 
+                handler.assert_relaying_allowed(true);
                 handler
                     .announce_table
                     .add_response(request.destination, iface, hops);
@@ -955,6 +2667,35 @@ async fn handle_path_request<'a>(
             }
         }
 
+        if handler.config.client_mode {
+            log::trace!(
+                "tp({}): not discovering path to {} in client mode",
+                handler.config.name,
+                request.destination
+            );
+            return;
+        }
+
+        if !handler.config.transport_enabled {
+            log::trace!(
+                "tp({}): not discovering path to {} without transport",
+                handler.config.name,
+                request.destination
+            );
+            return;
+        }
+
+        if !iface_mode.discovers_paths() {
+            log::trace!(
+                "tp({}): not discovering path to {} for {:?} interface {}",
+                handler.config.name,
+                request.destination,
+                iface_mode,
+                iface
+            );
+            return;
+        }
+
         if let Some(packet) =
             handler
                 .path_requests
@@ -986,40 +2727,99 @@ async fn handle_fixed_destinations<'a>(
 async fn handle_link_request_as_destination<'a>(
     destination: Arc<Mutex<SingleInputDestination>>,
     packet: &Packet,
+    iface: AddressHash,
     mut handler: MutexGuard<'a, TransportHandler>,
 ) {
     let mut destination = destination.lock().await;
     match destination.handle_packet(packet) {
         DestinationHandleStatus::LinkProof => {
             let link_id = LinkId::from(packet);
-            if !handler.in_links.contains_key(&link_id) {
-                log::trace!(
-                    "tp({}): send proof to {}",
+            if handler.in_links.contains_key(&link_id) {
+                return;
+            }
+
+            let limits = *destination.link_limits();
+            let address = destination.desc.address_hash;
+            let mut accepted = handler.accepts_link(&address, &limits).await;
+            let mut reason = "over its limits";
+
+            if let Some(acceptor) = destination.link_acceptor().filter(|_| accepted).cloned() {
+                let request = LinkRequestInfo {
+                    link_id,
+                    destination: address,
+                    iface,
+                    hops: packet.header.hops,
+                    active_links: handler.active_in_links(&address).await,
+                };
+                accepted = acceptor(request).await;
+                reason = "rejected by its acceptor";
+            }
+
+            if !accepted && limits.overflow == LinkOverflow::Ignore {
+                log::info!(
+                    "tp({}): ignoring link request {} for {} {}",
                     handler.config.name,
-                    packet.destination
+                    link_id,
+                    address,
+                    reason
                 );
+                return;
+            }
 
-                let link = Link::new_from_request(
-                    packet,
-                    destination.sign_key().clone(),
-                    destination.desc,
-                    handler.link_in_event_tx.clone(),
-                );
+            log::trace!(
+                "tp({}): send proof to {}",
+                handler.config.name,
+                packet.destination
+            );
+
+            let link = Link::new_from_request_with_rng(
+                packet,
+                destination.sign_key().clone(),
+                destination.desc,
+                handler.link_in_event_tx.clone(),
+                handler.config.rng.clone(),
+            )
+            .map(|link| link.with_clock(handler.config.clock.clone()));
 
-                if let Ok(mut link) = link {
-                    handler.send_packet(link.prove()).await;
+            if let Ok(mut link) = link {
+                link.set_compression(handler.config.link_compression);
+                link.set_sequencing(handler.config.link_sequencing);
+                handler.send_packet(link.prove()).await;
 
-                    log::debug!(
-                        "tp({}): save input link {} for destination {}",
+                if !accepted {
+                    log::info!(
+                        "tp({}): closing link {} for {} {}",
                         handler.config.name,
-                        link.id(),
-                        link.destination().address_hash
+                        link_id,
+                        address,
+                        reason
                     );
-
-                    handler
-                        .in_links
-                        .insert(*link.id(), Arc::new(Mutex::new(link)));
+                    match link.teardown() {
+                        Ok(Some(packet)) => handler.send_packet(packet).await,
+                        Ok(None) => {}
+                        Err(err) => log::error!(
+                            "tp({}): teardown link error: {err:?}",
+                            handler.config.name
+                        ),
+                    }
+                    return;
                 }
+
+                log::debug!(
+                    "tp({}): save input link {} for destination {}",
+                    handler.config.name,
+                    link.id(),
+                    link.destination().address_hash
+                );
+
+                handler
+                    .traffic
+                    .lock()
+                    .await
+                    .add_link(*link.id(), link.destination().address_hash);
+                handler
+                    .in_links
+                    .insert(*link.id(), Arc::new(Mutex::new(link)));
             }
         }
         DestinationHandleStatus::None => {}
@@ -1028,15 +2828,14 @@ async fn handle_link_request_as_destination<'a>(
 
 async fn handle_link_request_as_intermediate<'a>(
     received_from: AddressHash,
-    next_hop: AddressHash,
+    next_hop_iface: AddressHash,
     packet: &Packet,
     mut handler: MutexGuard<'a, TransportHandler>,
 ) {
     handler.link_table.add(
         packet,
-        packet.destination,
         received_from,
-        next_hop,
+        next_hop_iface,
     );
 
     send_to_next_hop(packet, &handler, None).await;
@@ -1058,7 +2857,13 @@ async fn handle_link_request<'a>(
             packet.destination
         );
 
-        handle_link_request_as_destination(destination, packet, handler).await;
+        handle_link_request_as_destination(destination, packet, iface, handler).await;
+    } else if !handler.forwards(packet) {
+        log::trace!(
+            "tp({}): not forwarding link request for remote destination {}",
+            handler.config.name,
+            packet.destination
+        );
     } else if let Some(entry) = handler.path_table.next_hop_full(&packet.destination) {
         log::trace!(
             "tp({}): handle link request for remote destination {}",
@@ -1066,8 +2871,8 @@ async fn handle_link_request<'a>(
             packet.destination
         );
 
-        let (next_hop, _) = entry;
-        handle_link_request_as_intermediate(iface, next_hop, packet, handler).await;
+        let (_, next_hop_iface) = entry;
+        handle_link_request_as_intermediate(iface, next_hop_iface, packet, handler).await;
     } else {
         log::trace!(
             "tp({}): dropping link request to unknown destination {}",
@@ -1103,23 +2908,30 @@ async fn handle_check_links<'a>(mut handler: MutexGuard<'a, TransportHandler>) {
 
     for addr in &links_to_remove {
         handler.in_links.remove(addr);
+        handler.traffic.lock().await.remove_link(addr);
     }
 
     links_to_remove.clear();
 
-    for link_entry in &handler.out_links {
-        let mut link = link_entry.1.lock().await;
+    let mut dead_links = Vec::new();
+
+    for (destination, link) in &handler.out_links {
+        let policy = handler.link_policy(destination);
+        let mut link = link.lock().await;
 
         match link.status() {
-            LinkStatus::Active if link.elapsed() > timer_config.out_link_stale => {
+            LinkStatus::Active if link.elapsed() > policy.stale => {
                 link.stale();
             }
+            LinkStatus::Active if policy.keep_alive.is_some_and(|interval| link.keep_alive_due(interval)) => {
+                handler.send_packet(link.keep_alive_packet(KEEP_ALIVE_REQUEST)).await;
+            }
             LinkStatus::Stale => {
-                if handler.config.restart_outlinks {
-                    if link.elapsed() > timer_config.out_link_restart {
+                if let Some(restart) = policy.restart {
+                    if link.elapsed() > restart {
                         link.restart();
                     }
-                } else if link.elapsed() > timer_config.out_link_stale + timer_config.out_link_close {
+                } else if link.elapsed() > policy.stale + policy.close {
                     if let Some(packet) = link.teardown().unwrap_or_else(|err| {
                         log::error!(
                             "tp({}): teardown stale out-link error: {err:?}",
@@ -1129,10 +2941,14 @@ async fn handle_check_links<'a>(mut handler: MutexGuard<'a, TransportHandler>) {
                     }) {
                         handler.send_packet(packet).await
                     }
-                    links_to_remove.push(*link_entry.0);
+                    handler.traffic.lock().await.remove_link(link.id());
+                    links_to_remove.push(*destination);
+                    if policy.reestablish {
+                        dead_links.push((*link.destination(), *link.id()));
+                    }
                 }
             }
-            LinkStatus::Pending if link.elapsed() > timer_config.out_link_repeat => {
+            LinkStatus::Pending if link.elapsed() > policy.repeat => {
                 log::warn!(
                     "tp({}): repeat link request {}",
                     handler.config.name,
@@ -1142,7 +2958,11 @@ async fn handle_check_links<'a>(mut handler: MutexGuard<'a, TransportHandler>) {
             }
             LinkStatus::Closed => {
                 link.close();
-                links_to_remove.push(*link_entry.0);
+                handler.traffic.lock().await.remove_link(link.id());
+                links_to_remove.push(*destination);
+                if policy.reestablish && link.closed_by_peer() {
+                    dead_links.push((*link.destination(), *link.id()));
+                }
             }
             _ => {}
         }
@@ -1151,60 +2971,330 @@ async fn handle_check_links<'a>(mut handler: MutexGuard<'a, TransportHandler>) {
     for addr in &links_to_remove {
         handler.out_links.remove(addr);
     }
-}
-
-async fn handle_keep_links<'a>(handler: MutexGuard<'a, TransportHandler>) {
-    for link in handler.out_links.values() {
-        let link = link.lock().await;
 
-        if link.status() == LinkStatus::Active {
-            handler
-                .send_packet(link.keep_alive_packet(KEEP_ALIVE_REQUEST))
-                .await;
-        }
+    for (destination, old) in dead_links {
+        handler.reestablish_link(destination, old).await;
     }
 }
 
-async fn handle_cleanup<'a>(handler: MutexGuard<'a, TransportHandler>) {
-    handler.iface_manager.lock().await.cleanup();
-}
+async fn handle_cleanup<'a>(mut handler: MutexGuard<'a, TransportHandler>) {
+    let removed = handler.iface_manager.lock().await.cleanup();
+    for iface in removed {
+        let name = iface.name.unwrap_or_else(|| iface.address.to_string());
+        purge_iface(&mut handler, &iface.address, &name).await;
+    }
 
-async fn retransmit_announces<'a>(
-    mut handler: MutexGuard<'a, TransportHandler>,
-    retransmit_old: bool,
-) {
-    let transport_id = *handler.config.identity.address_hash();
-    let messages = handler.announce_table.tx_to_retransmit(&transport_id);
+    let timer_config = handler.config.timer_config;
+    let now = handler.config.clock.now();
+    let lost = handler.path_table.expire(
+        now,
+        timer_config.direct_path_expiry,
+        timer_config.path_expiry,
+    );
+    handle_lost_paths(&mut handler, lost).await;
+}
 
-    for message in messages {
-        handler.send(message).await;
-    }
+/// Forgets the paths and announces learned on `iface`, which is gone.
+/// Does nothing if they were already forgotten.
+async fn purge_iface(handler: &mut TransportHandler, iface: &AddressHash, name: &str) {
+    handler.iface_announces.remove(iface);
 
-    if retransmit_old {
-        let messages = handler.announce_table.tx_to_retransmit_old(&transport_id);
+    let lost = handler.path_table.remove_iface(iface);
+    let announces = handler.announce_table.remove_iface(iface);
 
-        for message in messages {
-            handler.send(message).await;
-        }
+    if !lost.is_empty() || announces > 0 {
+        log::debug!(
+            "tp({}): interface {} down, {} paths and {} announces dropped",
+            handler.config.name,
+            name,
+            lost.len(),
+            announces
+        );
     }
+
+    handle_lost_paths(handler, lost).await;
 }
 
-async fn manage_transport(
-    handler: Arc<Mutex<TransportHandler>>,
-    rx_receiver: Arc<Mutex<InterfaceRxReceiver>>,
-    iface_messages_tx: broadcast::Sender<RxMessage>,
-) {
-    let cancel = handler.lock().await.cancel.clone();
-    let retransmit = handler.lock().await.config.retransmit;
-    let timer_config = handler.lock().await.config.timer_config;
+/// Announces the local destinations on `iface`, which just came up, unless
+/// that happened within the holdoff or the mode keeps announces off it.
+async fn announce_on_iface(handler: &mut TransportHandler, iface: AddressHash) {
+    if !handler.config.announce_on_iface_up || handler.single_in_destinations.is_empty() {
+        return;
+    }
 
-    let mut last_retransmit_old = if handler.lock().await.config.announce_forever {
-        Some(time::Instant::now() - timer_config.old_announces_retransmit)
-    } else {
-        None
+    let (mode, name) = {
+        let iface_manager = handler.iface_manager.lock().await;
+        (iface_manager.mode(&iface), iface_manager.display_name(&iface))
     };
-
-    let _packet_task = {
+    if !mode.is_some_and(|mode| mode.rebroadcasts_announce_from(None)) {
+        return;
+    }
+
+    let now = handler.config.clock.now();
+    let holdoff = handler.config.timer_config.iface_announce_holdoff;
+    if let Some(last) = handler.iface_announces.get(&iface) {
+        if now.duration_since(*last) < holdoff {
+            return;
+        }
+    }
+    handler.iface_announces.insert(iface, now);
+
+    log::debug!(
+        "tp({}): announcing {} destinations on {}",
+        handler.config.name,
+        handler.single_in_destinations.len(),
+        name
+    );
+
+    let mtu = handler.iface_manager.lock().await.mtu(&iface);
+
+    let rng = handler.config.rng.clone();
+    for destination in handler.single_in_destinations.values() {
+        let Ok(packet) = destination.lock().await.announce(&rng, None) else {
+            continue;
+        };
+        if mtu.is_some_and(|mtu| packet.wire_len() > mtu) {
+            log::warn!(
+                "tp({}): announce of {} doesn't fit the MTU of {}",
+                handler.config.name,
+                packet.destination,
+                name
+            );
+            continue;
+        }
+        handler.announce_counts.sent += 1;
+        handler.send(TxMessage { tx_type: TxMessageType::Direct(iface), packet }).await;
+    }
+}
+
+/// Reports lost paths and requests new ones for destinations which
+/// out links are kept to.
+async fn handle_lost_paths(handler: &mut TransportHandler, lost: Vec<AddressHash>) {
+    for destination in lost {
+        let _ = handler.events_tx.send(TransportEvent::PathLost { destination });
+
+        if handler.out_links.contains_key(&destination) {
+            log::debug!(
+                "tp({}): requesting new path to linked destination {}",
+                handler.config.name,
+                destination
+            );
+            handler.request_path(&destination, None, None).await;
+        }
+    }
+}
+
+async fn retransmit_announces<'a>(
+    mut handler: MutexGuard<'a, TransportHandler>,
+    retransmit_old: bool,
+) {
+    let transport_id = *handler.config.identity.address_hash();
+    let messages = handler.announce_table.tx_to_retransmit(&transport_id);
+    handler.assert_relaying_allowed(!messages.is_empty());
+
+    for message in messages {
+        handler.announce_counts.retransmitted += 1;
+        handler.send(message).await;
+    }
+
+    if retransmit_old {
+        let messages = handler.announce_table.tx_to_retransmit_old(&transport_id);
+
+        for message in messages {
+            handler.announce_counts.retransmitted += 1;
+            handler.send(message).await;
+        }
+    }
+}
+
+/// Reason why a received packet was not dispatched.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum DropReason {
+    MaxHops,
+    Echo,
+    Duplicate,
+}
+
+/// What became of a received packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum PacketVerdict {
+    /// The packet was passed to the handler for its type.
+    Dispatched,
+    /// The packet was addressed to a fixed destination of the transport.
+    Handled,
+    Dropped(DropReason),
+}
+
+/// Checks whether a received packet may be processed at all.
+async fn screen_packet(handler: &TransportHandler, message: &RxMessage) -> Result<(), DropReason> {
+    if message.packet.header.hops as usize >= PATHFINDER_M {
+        return Err(DropReason::MaxHops);
+    }
+
+    let iface_manager = handler.iface_manager.lock().await;
+    if iface_manager.is_echo(&message.address, &message.packet) {
+        return Err(DropReason::Echo);
+    }
+
+    // Duplicates count as well, their origin doesn't need the packet again
+    iface_manager.remember_received(&message.address, &message.packet);
+
+    Ok(())
+}
+
+/// Passes a received packet through the pipeline: screening, fixed
+/// destinations, duplicate filter and dispatch by packet type. Every stage
+/// only decides about the current packet, so the packet task carries on
+/// with the next one whatever the verdict.
+async fn process_packet<'a>(
+    mut handler: MutexGuard<'a, TransportHandler>,
+    message: RxMessage,
+    checks: PacketChecks,
+) -> PacketVerdict {
+    let packet = message.packet;
+    let iface = message.address;
+
+    trace_packet!(TraceCategory::TransportRx, "tp: << rx({}) = {} {}", iface, packet, packet.hash());
+
+    if let Err(reason) = screen_packet(&handler, &message).await {
+        log_dropped(&handler, reason, &message);
+        return PacketVerdict::Dropped(reason);
+    }
+
+    if handle_fixed_destinations(&packet, &mut handler, iface).await {
+        return PacketVerdict::Handled;
+    }
+
+    if !handler.filter_duplicate_packets(&packet).await {
+        log_dropped(&handler, DropReason::Duplicate, &message);
+        return PacketVerdict::Dropped(DropReason::Duplicate);
+    }
+
+    dispatch_packet(handler, &packet, iface, checks).await;
+
+    PacketVerdict::Dispatched
+}
+
+fn log_dropped(handler: &TransportHandler, reason: DropReason, message: &RxMessage) {
+    let packet = &message.packet;
+
+    match reason {
+        DropReason::MaxHops => log::debug!(
+            "tp({}): dropping packet which exceeded {} hops: dst={}",
+            handler.config.name,
+            PATHFINDER_M,
+            packet.destination
+        ),
+        DropReason::Echo => log::debug!(
+            "tp({}): dropping own packet echoed by iface {}: dst={}",
+            handler.config.name,
+            message.address,
+            packet.destination
+        ),
+        DropReason::Duplicate => log::debug!(
+            "tp({}): dropping duplicate packet: dst={}, ctx={:?}, type={:?}",
+            handler.config.name,
+            packet.destination,
+            packet.context,
+            packet.header.packet_type
+        ),
+    }
+}
+
+async fn dispatch_packet<'a>(
+    handler: MutexGuard<'a, TransportHandler>,
+    packet: &Packet,
+    iface: AddressHash,
+    checks: PacketChecks,
+) {
+    if handler.config.forwarding == ForwardingPolicy::Flood
+        && packet.header.packet_type != PacketType::Announce
+    {
+        handler.assert_relaying_allowed(true);
+
+        // TODO: remove seperate handling for announces in handle_announce.
+        // Send broadcast message expect current iface address
+        let mut forwarded = *packet;
+        forwarded.header.hops += 1;
+        handler.send(TxMessage { tx_type: TxMessageType::Broadcast(Some(iface)), packet: forwarded }).await;
+    }
+
+    match packet.header.packet_type {
+        PacketType::Announce => handle_announce(packet, handler, iface, checks.valid_announce).await,
+        PacketType::LinkRequest => handle_link_request(packet, iface, handler).await,
+        PacketType::Proof => handle_proof(packet, handler, iface).await,
+        PacketType::Data => handle_data(packet, handler, iface, checks.decrypted).await,
+    }
+}
+
+fn restore_announces(
+    name: &str,
+    path: &std::path::Path,
+    announce_table: &mut AnnounceTable,
+    path_table: &mut PathTable,
+) {
+    let announces = match announce_table::read_announces(path) {
+        Ok(announces) => announces,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+        Err(err) => {
+            log::warn!("tp({}): couldn't load announces from {}: {}", name, path.display(), err);
+            return;
+        }
+    };
+
+    log::info!("tp({}): restored {} announces from {}", name, announces.len(), path.display());
+
+    for announce in announces {
+        let packet = announce.packet;
+        // The interfaces don't exist yet, they are assumed to be in full mode
+        path_table.handle_announce(
+            &packet,
+            packet.transport,
+            announce.received_from,
+            InterfaceMode::default(),
+            None,
+        );
+        announce_table.restore(announce);
+    }
+}
+
+fn persist_announces(handler: &TransportHandler) -> io::Result<()> {
+    let Some(path) = &handler.config.announce_cache_path else {
+        return Ok(());
+    };
+
+    let announces = handler.announce_table.stored();
+    announce_table::write_announces(path, &announces)?;
+
+    log::debug!(
+        "tp({}): saved {} announces to {}",
+        handler.config.name,
+        announces.len(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+async fn manage_transport(
+    handler: Arc<Mutex<TransportHandler>>,
+    rx_receiver: Arc<Mutex<InterfaceRxReceiver>>,
+    mut iface_events: broadcast::Receiver<InterfaceEvent>,
+    iface_messages_tx: broadcast::Sender<RxMessage>,
+    processing_stats: Arc<std::sync::Mutex<ProcessingStats>>,
+) {
+    let cancel = handler.lock().await.cancel.clone();
+    let retransmit = handler.lock().await.config.transport_enabled;
+    let timer_config = handler.lock().await.config.timer_config;
+    let clock = handler.lock().await.config.clock.clone();
+
+    let mut last_retransmit_old = if handler.lock().await.config.announce_forever {
+        Some(clock.now() - timer_config.old_announces_retransmit)
+    } else {
+        None
+    };
+
+    {
         let handler = handler.clone();
         let cancel = cancel.clone();
 
@@ -1213,7 +3303,16 @@ async fn manage_transport(
             handler.lock().await.config.name
         );
 
-        tokio::spawn(async move {
+        let (name, budget, verified_announces) = {
+            let handler = handler.lock().await;
+            (
+                handler.config.name.clone(),
+                handler.config.rx_budget,
+                handler.verified_announces.clone(),
+            )
+        };
+
+        runtime::spawn(async move {
             loop {
                 let mut rx_receiver = rx_receiver.lock().await;
 
@@ -1221,71 +3320,54 @@ async fn manage_transport(
                     break;
                 }
 
-                tokio::select! {
+                let mut message = tokio::select! {
                     _ = cancel.cancelled() => {
                         break;
                     },
-                    Some(message) = rx_receiver.recv() => {
-                        let _ = iface_messages_tx.send(message);
+                    Some(message) = rx_receiver.recv() => Some(message),
+                };
 
-                        let packet = message.packet;
+                // Take what is queued up to the budget, every packet locks
+                // the handler on its own so timers can get in between
+                let mut processed = 0;
+                while let Some(next) = message.take() {
+                    let _ = iface_messages_tx.send(next);
 
-                        let mut handler = handler.lock().await;
+                    let checks = check_packet(&handler, &verified_announces, &next.packet).await;
 
-                        if PACKET_TRACE {
-                            log::debug!("tp: << rx({}) = {} {}", message.address, packet, packet.hash());
-                        }
+                    let start = Instant::now();
+                    let locked_handler = handler.lock().await;
+                    let locked = Instant::now();
+                    process_packet(locked_handler, next, checks).await;
+                    let processing = locked.elapsed();
 
-                        if handle_fixed_destinations(
-                            &packet,
-                            &mut handler,
-                            message.address
-                        ).await {
-                            continue;
-                        }
+                    if processing > SLOW_PACKET {
+                        log::debug!("tp({}): packet took {:?} to process", name, processing);
+                    }
+                    processing_stats.lock().unwrap().record(locked - start, processing);
 
-                        if !handler.filter_duplicate_packets(&packet).await {
-                            log::debug!(
-                                "tp({}): dropping duplicate packet: dst={}, ctx={:?}, type={:?}",
-                                handler.config.name,
-                                packet.destination,
-                                packet.context,
-                                packet.header.packet_type
-                            );
-                            continue;
-                        }
+                    processed += 1;
+                    if processed < budget {
+                        message = rx_receiver.try_recv().ok();
+                    }
+                }
 
-                        if handler.config.broadcast && packet.header.packet_type != PacketType::Announce {
-                            // TODO: remove seperate handling for announces in handle_announce.
-                            // Send broadcast message expect current iface address
-                            handler.send(TxMessage { tx_type: TxMessageType::Broadcast(Some(message.address)), packet }).await;
-                        }
+                drop(rx_receiver);
 
-                        match packet.header.packet_type {
-                            PacketType::Announce => handle_announce(
-                                &packet,
-                                handler,
-                                message.address
-                            ).await,
-                            PacketType::LinkRequest => handle_link_request(
-                                &packet,
-                                message.address,
-                                handler
-                            ).await,
-                            PacketType::Proof => handle_proof(&packet, handler).await,
-                            PacketType::Data => handle_data(&packet, handler).await,
-                        }
-                    }
-                };
+                if processed == budget {
+                    processing_stats.lock().unwrap().budget_yields += 1;
+                    runtime::yield_now().await;
+                }
             }
-        })
-    };
+        });
+    }
 
     {
         let handler = handler.clone();
         let cancel = cancel.clone();
+        let clock = clock.clone();
 
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             loop {
                 if cancel.is_cancelled() {
                     break;
@@ -1295,7 +3377,7 @@ async fn manage_transport(
                     _ = cancel.cancelled() => {
                         break;
                     },
-                    _ = time::sleep(timer_config.link_check) => {
+                    _ = clock.sleep(timer_config.link_check) => {
                         handle_check_links(handler.lock().await).await;
                     }
                 }
@@ -1307,19 +3389,30 @@ async fn manage_transport(
         let handler = handler.clone();
         let cancel = cancel.clone();
 
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             loop {
-                if cancel.is_cancelled() {
-                    break;
-                }
-
                 tokio::select! {
-                    _ = cancel.cancelled() => {
-                        break;
+                    _ = cancel.cancelled() => break,
+                    event = iface_events.recv() => match event {
+                        Ok(InterfaceEvent::Down(iface)) => {
+                            let mut handler = handler.lock().await;
+                            let name = handler.iface_manager.lock().await.display_name(&iface);
+                            purge_iface(&mut handler, &iface, &name).await;
+                        }
+                        Ok(InterfaceEvent::Up(iface)) => {
+                            let mut handler = handler.lock().await;
+                            let reports_state = handler.iface_manager.lock().await.reports_state(&iface);
+                            if !reports_state {
+                                announce_on_iface(&mut handler, iface).await;
+                            }
+                        }
+                        Ok(InterfaceEvent::State(iface, InterfaceState::Connected)) => {
+                            announce_on_iface(&mut *handler.lock().await, iface).await;
+                        }
+                        Ok(InterfaceEvent::State(..) | InterfaceEvent::Stalled(_))
+                        | Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
                     },
-                    _ = time::sleep(timer_config.out_link_keep) => {
-                        handle_keep_links(handler.lock().await).await;
-                    }
                 }
             }
         });
@@ -1328,8 +3421,9 @@ async fn manage_transport(
     {
         let handler = handler.clone();
         let cancel = cancel.clone();
+        let clock = clock.clone();
 
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             loop {
                 if cancel.is_cancelled() {
                     break;
@@ -1339,7 +3433,7 @@ async fn manage_transport(
                     _ = cancel.cancelled() => {
                         break;
                     },
-                    _ = time::sleep(timer_config.iface_cleanup) => {
+                    _ = clock.sleep(timer_config.iface_cleanup) => {
                         handle_cleanup(handler.lock().await).await;
                     }
                 }
@@ -1350,8 +3444,9 @@ async fn manage_transport(
     {
         let handler = handler.clone();
         let cancel = cancel.clone();
+        let clock = clock.clone();
 
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             loop {
                 if cancel.is_cancelled() {
                     break;
@@ -1361,7 +3456,7 @@ async fn manage_transport(
                     _ = cancel.cancelled() => {
                         break;
                     },
-                    _ = time::sleep(timer_config.packet_cache_cleanup) => {
+                    _ = clock.sleep(timer_config.packet_cache_cleanup) => {
                         let mut handler = handler.lock().await;
 
                         handler
@@ -1370,6 +3465,12 @@ async fn manage_transport(
                             .await
                             .release(timer_config.keep_packet_cached);
 
+                        handler
+                            .verified_announces
+                            .lock()
+                            .unwrap()
+                            .release(timer_config.keep_packet_cached);
+
                         handler.link_table.remove_stale();
                     },
                 }
@@ -1377,11 +3478,36 @@ async fn manage_transport(
         });
     }
 
+    if handler.lock().await.config.announce_cache_path.is_some() {
+        let handler = handler.clone();
+        let cancel = cancel.clone();
+        let clock = clock.clone();
+
+        runtime::spawn(async move {
+            loop {
+                let stop = tokio::select! {
+                    _ = cancel.cancelled() => true,
+                    _ = clock.sleep(timer_config.announce_cache_persist) => false,
+                };
+
+                let handler = handler.lock().await;
+                if let Err(err) = persist_announces(&handler) {
+                    log::warn!("tp({}): couldn't save announces: {}", handler.config.name, err);
+                }
+
+                if stop {
+                    break;
+                }
+            }
+        });
+    }
+
     if retransmit {
         let handler = handler.clone();
         let cancel = cancel.clone();
+        let clock = clock.clone();
 
-        tokio::spawn(async move {
+        runtime::spawn(async move {
             loop {
                 if cancel.is_cancelled() {
                     break;
@@ -1391,11 +3517,11 @@ async fn manage_transport(
                     _ = cancel.cancelled() => {
                         break;
                     },
-                    _ = time::sleep(timer_config.announces_retransmit) => {
+                    _ = clock.sleep(timer_config.announces_retransmit) => {
                         let mut retransmit_old = false;
 
                         if let Some(instant) = last_retransmit_old {
-                            let now = time::Instant::now();
+                            let now = clock.now();
                             if now - instant > timer_config.old_announces_retransmit {
                                 retransmit_old = true;
                                 last_retransmit_old = Some(now);
@@ -1414,12 +3540,44 @@ async fn manage_transport(
 mod tests {
     use super::*;
 
+    use crate::destination::ratchets::RatchetRetention;
     use crate::packet::HeaderType;
 
+    /// `text` encrypted to the identity key of `destination`, as a sender
+    /// without a ratchet sends it.
+    fn encrypted_to(destination: &SingleInputDestination, text: &[u8]) -> PacketDataBuffer {
+        let output = SingleOutputDestination::new(destination.desc.identity, destination.desc.name);
+
+        let mut buffer = [0u8; PACKET_MDU];
+        PacketDataBuffer::new_from_slice(output.encrypt(OsRng, text, None, &mut buffer).unwrap())
+    }
+
+    /// Processes `message` like the packet task, checked before the handler
+    /// is locked.
+    async fn receive(handler: &Mutex<TransportHandler>, message: RxMessage) -> PacketVerdict {
+        let checks = checked(handler, &message.packet).await;
+        process_packet(handler.lock().await, message, checks).await
+    }
+
+    async fn receive_announce(handler: &Mutex<TransportHandler>, announce: &Packet, iface: AddressHash) {
+        let checks = checked(handler, announce).await;
+        handle_announce(announce, handler.lock().await, iface, checks.valid_announce).await;
+    }
+
+    async fn receive_data(handler: &Mutex<TransportHandler>, packet: &Packet, iface: AddressHash) {
+        let checks = checked(handler, packet).await;
+        handle_data(packet, handler.lock().await, iface, checks.decrypted).await;
+    }
+
+    async fn checked(handler: &Mutex<TransportHandler>, packet: &Packet) -> PacketChecks {
+        let verified = handler.lock().await.verified_announces.clone();
+        check_packet(handler, &verified, packet).await
+    }
+
     #[tokio::test]
     async fn drop_duplicates() {
         let transport = TransportConfig::default()
-            .set_retransmit(true)
+            .set_transport_enabled(true)
             .build();
 
         let handler = transport.get_handler();
@@ -1443,7 +3601,7 @@ mod tests {
                 .await
         );
 
-        handle_announce(&announce, handler.lock().await, next_hop_iface).await;
+        receive_announce(&handler, &announce, next_hop_iface).await;
 
         let data_packet: Packet = Packet {
             data: PacketDataBuffer::new_from_slice(b"foo"),
@@ -1479,7 +3637,7 @@ mod tests {
                 .await
         );
 
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        runtime::sleep(Duration::from_secs(2)).await;
         handler
             .lock()
             .await
@@ -1497,4 +3655,1810 @@ mod tests {
                 .await
         );
     }
+
+    #[tokio::test]
+    async fn duplicate_window_and_exemptions() {
+        let transport = TransportConfig::default()
+            .set_timer_config(TimerConfig {
+                keep_packet_cached: Duration::from_millis(100),
+                ..Default::default()
+            })
+            .build();
+
+        let handler = transport.get_handler();
+
+        let data_packet = Packet {
+            data: PacketDataBuffer::new_from_slice(b"foo"),
+            destination: AddressHash::new_from_slice(&[4u8; 32]),
+            ..Default::default()
+        };
+
+        let mut hopped = data_packet;
+        hopped.header.hops = 2;
+
+        let handler_guard = handler.lock().await;
+        assert!(handler_guard.filter_duplicate_packets(&data_packet).await);
+        assert!(!handler_guard.filter_duplicate_packets(&hopped).await);
+
+        let channel_packet = Packet {
+            context: PacketContext::Channel,
+            ..data_packet
+        };
+        assert!(handler_guard.filter_duplicate_packets(&channel_packet).await);
+        assert!(handler_guard.filter_duplicate_packets(&channel_packet).await);
+        drop(handler_guard);
+
+        // Window elapsed, no cleanup run needed
+        runtime::sleep(Duration::from_millis(150)).await;
+        assert!(handler.lock().await.filter_duplicate_packets(&data_packet).await);
+    }
+
+    #[tokio::test]
+    async fn received_data_context() {
+        let transport = TransportConfig::default().build();
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("rx"), DestinationName::new("test", "rx"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        let mut events = transport.received_data_events();
+
+        let iface = AddressHash::new_from_slice(&[5u8; 32]);
+        let packet = Packet {
+            data: encrypted_to(&*destination.lock().await, b"foo"),
+            destination: address,
+            ..Default::default()
+        };
+
+        receive_data(&transport.get_handler(), &packet, iface).await;
+
+        let received = events.try_recv().unwrap();
+        assert_eq!(received.destination, address);
+        assert_eq!(received.data.as_slice(), b"foo");
+        assert_eq!(received.decrypted_with, Some(DecryptionKey::Identity));
+        assert_eq!(received.packet_hash, packet.hash());
+        assert_eq!(received.iface, iface);
+        assert!(received.source_identity.is_none());
+        assert!(received.proof_requested);
+
+        let proof = destination.lock().await.proof(&received.packet_hash);
+        assert_eq!(proof.header.packet_type, PacketType::Proof);
+        assert_eq!(proof.destination, AddressHash::new_from_hash(&packet.hash()));
+        assert_eq!(&proof.data.as_slice()[..32], packet.hash().as_slice());
+    }
+
+    #[tokio::test]
+    async fn event_bus() {
+        let transport = TransportConfig::default().build();
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("rx"), DestinationName::new("test", "rx"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        let mut iface_events = transport.events_filtered(TransportEvent::is_interface);
+        let mut destination_events = transport.events_for_destination(address);
+
+        let channel = transport.iface_manager().lock().await.new_channel(1);
+        let iface = *channel.address();
+
+        let packet = Packet {
+            data: encrypted_to(&*destination.lock().await, b"foo"),
+            destination: address,
+            ..Default::default()
+        };
+        receive_data(&transport.get_handler(), &packet, iface).await;
+
+        match destination_events.recv().await.unwrap() {
+            TransportEvent::DataReceived(data) => assert_eq!(data.packet_hash, packet.hash()),
+            _ => panic!("expected received data"),
+        }
+
+        channel.stop.cancel();
+
+        let timeout = Duration::from_secs(1);
+        assert!(matches!(
+            runtime::timeout(timeout, iface_events.recv()).await,
+            Ok(Ok(TransportEvent::InterfaceUp(address))) if address == iface
+        ));
+        assert!(matches!(
+            runtime::timeout(timeout, iface_events.recv()).await,
+            Ok(Ok(TransportEvent::InterfaceDown(address))) if address == iface
+        ));
+    }
+
+    #[tokio::test]
+    async fn packet_task_yields_after_its_budget() {
+        let transport = TransportConfig::default().set_rx_budget(4).build();
+
+        let channel = transport.iface_manager().lock().await.new_channel(1);
+        for number in 0..12u8 {
+            let packet = Packet {
+                data: PacketDataBuffer::new_from_slice(&[number]),
+                ..Default::default()
+            };
+            let message = RxMessage { address: *channel.address(), packet };
+            channel.rx_channel.send(message).await.unwrap();
+        }
+
+        runtime::sleep(Duration::from_millis(100)).await;
+
+        let stats = transport.processing_stats();
+        assert_eq!(stats.packets, 12);
+        assert!(stats.budget_yields >= 2, "{:?}", stats);
+        assert!(stats.processing.max >= stats.mean_processing());
+    }
+
+    #[tokio::test]
+    async fn packet_pipeline_continues_after_duplicates() {
+        let transport = TransportConfig::default().build();
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("rx"), DestinationName::new("test", "rx"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        let iface = AddressHash::new_from_slice(&[5u8; 32]);
+        let message = |data: &PacketDataBuffer, hops: u8| {
+            let mut packet = Packet {
+                data: *data,
+                destination: address,
+                ..Default::default()
+            };
+            packet.header.hops = hops;
+            RxMessage { address: iface, packet }
+        };
+
+        let handler = transport.get_handler();
+        let process = |message| {
+            let handler = handler.clone();
+            async move { receive(&handler, message).await }
+        };
+
+        let [foo, bar, baz, qux] = {
+            let destination = destination.lock().await;
+            [b"foo", b"bar", b"baz", b"qux"].map(|text| encrypted_to(&destination, text))
+        };
+
+        assert_eq!(process(message(&foo, 0)).await, PacketVerdict::Dispatched);
+        assert_eq!(
+            process(message(&foo, 0)).await,
+            PacketVerdict::Dropped(DropReason::Duplicate)
+        );
+        assert_eq!(
+            process(message(&bar, PATHFINDER_M as u8)).await,
+            PacketVerdict::Dropped(DropReason::MaxHops)
+        );
+
+        // The packet task keeps receiving after a duplicate
+        let mut events = transport.received_data_events();
+        let channel = transport.iface_manager().lock().await.new_channel(1);
+        for data in [&baz, &baz, &qux] {
+            channel
+                .rx_channel
+                .send(RxMessage { address: *channel.address(), ..message(data, 0) })
+                .await
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(1);
+        let first = runtime::timeout(timeout, events.recv()).await.unwrap().unwrap();
+        let second = runtime::timeout(timeout, events.recv()).await.unwrap().unwrap();
+        assert_eq!(first.data.as_slice(), b"baz");
+        assert_eq!(second.data.as_slice(), b"qux");
+    }
+
+    #[tokio::test]
+    async fn warm_start_from_announce_cache() {
+        let path = std::env::temp_dir().join("reticulum-warm-start-test");
+        let _ = std::fs::remove_file(&path);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("cached"),
+            DestinationName::new("test", "cache"),
+        );
+        let announce = destination.announce(OsRng, None).unwrap();
+        let address = destination.desc.address_hash;
+        let iface = AddressHash::new_from_slice(&[5u8; 32]);
+
+        let transport = TransportConfig::default()
+            .set_transport_enabled(true)
+            .set_announce_cache_path(&path)
+            .build();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+        drop(transport);
+        runtime::sleep(Duration::from_millis(100)).await;
+
+        let transport = TransportConfig::default()
+            .set_announce_cache_path(&path)
+            .build();
+        let handler = transport.get_handler();
+        let handler = handler.lock().await;
+        std::fs::remove_file(&path).unwrap();
+
+        let entry = handler.path_table.get(&address).expect("restored path");
+        assert_eq!(entry.iface, iface);
+        assert_eq!(entry.hops, 1);
+        assert_eq!(handler.announce_table.stored().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn path_lost_with_interface() {
+        let transport = TransportConfig::default().build();
+        let mut path_events = transport.events_filtered(TransportEvent::is_path);
+
+        let lost_iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut other_iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "paths"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, *lost_iface.address()).await;
+
+        let timeout = Duration::from_secs(1);
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathDiscovered { destination, .. })) if destination == address
+        ));
+
+        transport.link(destination.desc).await;
+        while other_iface.tx_channel.try_recv().is_ok() {}
+
+        lost_iface.stop.cancel();
+
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathLost { destination })) if destination == address
+        ));
+
+        // A new path is requested for the linked destination
+        let request = runtime::timeout(timeout, other_iface.tx_channel.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .packet;
+        let path_request_destination = create_path_request_destination().desc.address_hash;
+        assert_eq!(request.destination, path_request_destination);
+        assert_eq!(&request.data.as_slice()[..16], address.as_slice());
+    }
+
+    #[tokio::test]
+    async fn announces_local_destinations_on_new_interfaces() {
+        let transport = TransportConfig::default().build();
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("up"), DestinationName::new("test", "up"))
+            .await;
+        let address = destination.lock().await.desc.address_hash;
+
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut access_point = transport
+            .iface_manager()
+            .lock()
+            .await
+            .new_channel_with_mode(4, InterfaceMode::AccessPoint);
+
+        let timeout = Duration::from_secs(1);
+        let message = runtime::timeout(timeout, iface.tx_channel.recv()).await.unwrap().unwrap();
+        assert_eq!(message.tx_type, TxMessageType::Direct(*iface.address()));
+        assert_eq!(message.packet.header.packet_type, PacketType::Announce);
+        assert_eq!(message.packet.destination, address);
+        assert_eq!(transport.announce_counts().await.sent, 1);
+
+        // Not again within the holdoff, and never on access points
+        announce_on_iface(&mut *transport.get_handler().lock().await, *iface.address()).await;
+        runtime::sleep(Duration::from_millis(100)).await;
+        assert!(iface.tx_channel.try_recv().is_err());
+        assert!(access_point.tx_channel.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn cleanup_purges_removed_interfaces() {
+        let transport = TransportConfig::default().build();
+        let mut path_events = transport.events_filtered(TransportEvent::is_path);
+
+        let lost_iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut other_iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "cleanup"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, *lost_iface.address()).await;
+
+        let timeout = Duration::from_secs(1);
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathDiscovered { destination, .. })) if destination == address
+        ));
+
+        transport.link(destination.desc).await;
+        while other_iface.tx_channel.try_recv().is_ok() {}
+
+        // Cleanup gets to the stopped interface before its down event does
+        let handler = transport.get_handler();
+        let guard = handler.lock().await;
+        lost_iface.stop.cancel();
+        handle_cleanup(guard).await;
+
+        {
+            let handler = handler.lock().await;
+            assert!(handler.path_table.get(&address).is_none());
+            assert!(handler.announce_table.stored().is_empty());
+            assert_eq!(handler.iface_manager.lock().await.interfaces().len(), 1);
+        }
+
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathLost { destination })) if destination == address
+        ));
+
+        let request = runtime::timeout(timeout, other_iface.tx_channel.recv())
+            .await
+            .unwrap()
+            .unwrap()
+            .packet;
+        let path_request_destination = create_path_request_destination().desc.address_hash;
+        assert_eq!(request.destination, path_request_destination);
+
+        // The down event which follows finds nothing left to purge
+        let later = Duration::from_millis(100);
+        assert!(runtime::timeout(later, path_events.recv()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn static_path_routes_packets_before_announces() {
+        let transport = TransportConfig::default().build();
+        let mut path_events = transport.events_filtered(TransportEvent::is_path);
+
+        let mut static_iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut other_iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let address = AddressHash::new([0x42; 16]);
+        transport.add_static_path(address, *static_iface.address(), 2).await;
+
+        let timeout = Duration::from_secs(1);
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathDiscovered { destination, hops: 2, iface }))
+                if destination == address && iface == *static_iface.address()
+        ));
+
+        let packet = Packet {
+            destination: address,
+            data: PacketDataBuffer::new_from_slice(b"static"),
+            ..Default::default()
+        };
+        transport.outbound(&packet).await;
+
+        let sent = static_iface.tx_channel.try_recv().unwrap().packet;
+        assert_eq!(sent.destination, address);
+        assert!(other_iface.tx_channel.try_recv().is_err());
+
+        assert!(transport.remove_static_path(&address).await);
+        assert!(!transport.remove_static_path(&address).await);
+        assert!(matches!(
+            runtime::timeout(timeout, path_events.recv()).await,
+            Ok(Ok(TransportEvent::PathLost { destination })) if destination == address
+        ));
+    }
+
+    #[tokio::test]
+    async fn pinned_path_routes_packets() {
+        let transport = TransportConfig::default().build();
+        let mut path_events = transport.events_filtered(TransportEvent::is_path);
+
+        let mut learned_iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut pinned_iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "pinning"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, *learned_iface.address()).await;
+        transport.flush().await;
+        while learned_iface.tx_channel.try_recv().is_ok() {}
+        while pinned_iface.tx_channel.try_recv().is_ok() {}
+
+        let relay = AddressHash::new_from_slice(&[9u8; 16]);
+        transport.pin_path(address, *pinned_iface.address(), relay).await;
+
+        let packet = Packet {
+            destination: address,
+            data: PacketDataBuffer::new_from_slice(b"pinned"),
+            ..Default::default()
+        };
+        transport.outbound(&packet).await;
+
+        let sent = pinned_iface.tx_channel.try_recv().unwrap().packet;
+        assert_eq!(sent.transport, Some(relay));
+        assert!(learned_iface.tx_channel.try_recv().is_err());
+
+        // Unpinning falls back to the announced path
+        assert!(transport.unpin_path(&address).await);
+        assert!(!transport.unpin_path(&address).await);
+        transport.outbound(&packet).await;
+        assert!(learned_iface.tx_channel.try_recv().is_ok());
+
+        let timeout = Duration::from_secs(1);
+        let mut ifaces = Vec::new();
+        while let Ok(Ok(TransportEvent::PathDiscovered { iface, .. })) =
+            runtime::timeout(timeout, path_events.recv()).await
+        {
+            ifaces.push(iface);
+            if ifaces.len() == 3 {
+                break;
+            }
+        }
+        assert_eq!(
+            ifaces,
+            [*learned_iface.address(), *pinned_iface.address(), *learned_iface.address()]
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_only_as_transport_node() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "forwarding"),
+        );
+        let announce = destination.announce(OsRng, None).unwrap();
+        let packet = Packet {
+            destination: destination.desc.address_hash,
+            data: PacketDataBuffer::new_from_slice(b"forward me"),
+            ..Default::default()
+        };
+
+        for transport_enabled in [false, true] {
+            let transport = TransportConfig::default()
+                .set_transport_enabled(transport_enabled)
+                .build();
+            let mut near_iface = transport.iface_manager().lock().await.new_channel(4);
+            let far_iface = transport.iface_manager().lock().await.new_channel(4);
+
+            receive_announce(&transport.get_handler(), &announce, *near_iface.address()).await;
+            transport.flush().await;
+            while near_iface.tx_channel.try_recv().is_ok() {}
+
+            receive_data(&transport.get_handler(), &packet, far_iface.address).await;
+            transport.flush().await;
+            assert_eq!(near_iface.tx_channel.try_recv().is_ok(), transport_enabled);
+
+            // Packets routed through another transport node are left to it
+            let mut routed = packet;
+            routed.header.header_type = HeaderType::Type2;
+            routed.transport = Some(AddressHash::new_from_slice(&[3u8; 16]));
+            receive_data(&transport.get_handler(), &routed, far_iface.address).await;
+            transport.flush().await;
+            assert!(near_iface.tx_channel.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn client_mode_never_relays() {
+        let transport = TransportConfig::new_client("client", &PrivateIdentity::new_from_name("client"))
+            .set_transport_enabled(true)
+            .set_broadcast(true)
+            .build();
+        let mut announces = transport.recv_announces().await;
+        let mut near_iface = transport.iface_manager().lock().await.new_channel(4);
+        let mut far_iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "client"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        let handler = transport.get_handler();
+        receive(&handler, RxMessage { address: *near_iface.address(), packet: announce }).await;
+
+        // Subscribers still learn about the destination
+        let event = runtime::timeout(Duration::from_secs(1), announces.recv()).await.unwrap().unwrap();
+        assert_eq!(event.destination.lock().await.desc.address_hash, address);
+
+        let data = Packet {
+            destination: address,
+            data: PacketDataBuffer::new_from_slice(b"relay me"),
+            ..Default::default()
+        };
+        let path_request = PathRequests::new("peer", None, SharedRng::default()).generate(&address, None);
+        for packet in [data, path_request] {
+            receive(&handler, RxMessage { address: *far_iface.address(), packet }).await;
+        }
+
+        transport.flush().await;
+        runtime::sleep(Duration::from_millis(1500)).await;
+        assert!(near_iface.tx_channel.try_recv().is_err());
+        assert!(far_iface.tx_channel.try_recv().is_err());
+        assert_eq!(transport.announce_counts().await.retransmitted, 0);
+    }
+
+    #[tokio::test]
+    async fn discovers_paths_only_on_gateway_modes() {
+        for (mode, discovers) in [(InterfaceMode::Full, false), (InterfaceMode::Gateway, true)] {
+            let transport = TransportConfig::default().set_transport_enabled(true).build();
+            let requesting_iface = transport
+                .iface_manager()
+                .lock()
+                .await
+                .new_channel_with_mode(4, mode);
+            let mut other_iface = transport.iface_manager().lock().await.new_channel(4);
+
+            let unknown = AddressHash::new_from_slice(&[7u8; 16]);
+            let path_request = PathRequests::new("peer", None, SharedRng::default()).generate(&unknown, None);
+            receive(
+                &transport.get_handler(),
+                RxMessage { address: requesting_iface.address, packet: path_request },
+            )
+            .await;
+
+            transport.flush().await;
+            assert_eq!(other_iface.tx_channel.try_recv().is_ok(), discovers, "{:?}", mode);
+        }
+    }
+
+    #[tokio::test]
+    async fn discovers_paths_only_as_transport_node() {
+        for transport_enabled in [false, true] {
+            let transport = TransportConfig::default()
+                .set_transport_enabled(transport_enabled)
+                .build();
+            let requesting_iface = transport
+                .iface_manager()
+                .lock()
+                .await
+                .new_channel_with_mode(4, InterfaceMode::Gateway);
+            let mut other_iface = transport.iface_manager().lock().await.new_channel(4);
+
+            let unknown = AddressHash::new_from_slice(&[7u8; 16]);
+            let path_request = PathRequests::new("peer", None, SharedRng::default()).generate(&unknown, None);
+            receive(
+                &transport.get_handler(),
+                RxMessage { address: requesting_iface.address, packet: path_request },
+            )
+            .await;
+
+            transport.flush().await;
+            assert_eq!(other_iface.tx_channel.try_recv().is_ok(), transport_enabled);
+        }
+    }
+
+    #[tokio::test]
+    async fn decrypts_before_locking_the_handler() {
+        let transport = TransportConfig::default().build();
+        let iface = AddressHash::new_from_slice(&[1u8; 16]);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_rand(OsRng), DestinationName::new("test", "decrypt"))
+            .await;
+        let packet = {
+            let destination = destination.lock().await;
+            Packet {
+                destination: destination.desc.address_hash,
+                data: encrypted_to(&destination, b"text"),
+                ..Default::default()
+            }
+        };
+
+        let handler = transport.get_handler();
+        let checks = checked(&handler, &packet).await;
+        let (data, key) = checks.decrypted.unwrap();
+        assert_eq!(data.as_slice(), b"text");
+        assert_eq!(key, DecryptionKey::Identity);
+
+        // The handler takes what was decrypted, it doesn't try the keys itself
+        let mut received = transport.received_data_events();
+        handle_data(&packet, handler.lock().await, iface, None).await;
+        assert!(received.try_recv().is_err());
+
+        handle_data(&packet, handler.lock().await, iface, checks.decrypted).await;
+        assert_eq!(received.try_recv().unwrap().data.as_slice(), b"text");
+    }
+
+    #[tokio::test]
+    async fn decrypts_with_ratchets_and_identity() {
+        let transport = TransportConfig::default().build();
+        let iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_rand(OsRng), DestinationName::new("test", "ratchets"))
+            .await;
+        let hash = destination.lock().await.desc.address_hash;
+        let path = std::env::temp_dir().join(format!("reticulum-ratchets-{}", hash.to_hex()));
+        let _ = std::fs::remove_file(&path);
+
+        let (address, output, previous, current) = {
+            let mut destination = destination.lock().await;
+            let retention = RatchetRetention { interval: Duration::ZERO, ..Default::default() };
+            destination.enable_ratchets(&path, retention).unwrap();
+            destination.rotate_ratchets(OsRng).unwrap();
+            destination.rotate_ratchets(OsRng).unwrap();
+
+            let mut ratchets = destination
+                .ratchets()
+                .unwrap()
+                .iter()
+                .map(|ratchet| ratchet.public_key().to_bytes());
+            let current = ratchets.next().unwrap();
+            let previous = ratchets.next().unwrap();
+            let output = SingleOutputDestination::new(destination.desc.identity, destination.desc.name);
+
+            (destination.desc.address_hash, output, previous, current)
+        };
+
+        let encrypt = |ratchet: Option<&[u8; RATCHET_LENGTH]>, text: &[u8]| {
+            let mut buffer = [0u8; 256];
+            PacketDataBuffer::new_from_slice(output.encrypt(OsRng, text, ratchet, &mut buffer).unwrap())
+        };
+        let send = |data: PacketDataBuffer| {
+            let message = RxMessage {
+                address: iface.address,
+                packet: Packet { destination: address, data, ..Default::default() },
+            };
+            iface.rx_channel.send(message)
+        };
+
+        let mut received = transport.received_data_events();
+        let expected = [
+            (encrypt(Some(&current), b"current"), DecryptionKey::CurrentRatchet),
+            (encrypt(Some(&previous), b"previous"), DecryptionKey::PreviousRatchet(1)),
+            (encrypt(None, b"identity"), DecryptionKey::Identity),
+        ];
+
+        for (data, key) in expected {
+            send(data).await.unwrap();
+
+            let data = runtime::timeout(Duration::from_secs(1), received.recv()).await.unwrap().unwrap();
+            assert_eq!(data.decrypted_with, Some(key));
+        }
+
+        // Neither data encrypted to a foreign key nor plain data is delivered
+        let foreign = PrivateIdentity::new_from_rand(OsRng);
+        send(encrypt(Some(foreign.as_identity().public_key_bytes()), b"foreign")).await.unwrap();
+        send(PacketDataBuffer::new_from_slice(b"plain")).await.unwrap();
+        runtime::sleep(Duration::from_millis(100)).await;
+        assert!(received.try_recv().is_err());
+
+        assert_eq!(
+            transport.decryption_counts().await,
+            DecryptionCounts { current_ratchet: 1, previous_ratchet: 1, identity: 1, undecrypted: 2 }
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn traffic_stats_per_destination() {
+        let transport = TransportConfig::default().build();
+        let iface = transport.iface_manager().lock().await.new_channel(4);
+
+        let local = transport
+            .add_destination(PrivateIdentity::new_from_name("local"), DestinationName::new("test", "traffic"))
+            .await;
+        let inbound = encrypted_to(&*local.lock().await, b"inbound");
+        let local = local.lock().await.desc.address_hash;
+        let remote = AddressHash::new_from_slice(&[7u8; 16]);
+
+        let mut events = transport.events_filtered(|event| matches!(event, TransportEvent::DataReceived(_)));
+        iface
+            .rx_channel
+            .send(RxMessage {
+                address: iface.address,
+                packet: Packet {
+                    destination: local,
+                    data: inbound,
+                    ..Default::default()
+                },
+            })
+            .await
+            .unwrap();
+        runtime::timeout(Duration::from_secs(1), events.recv()).await.unwrap().unwrap();
+
+        transport
+            .send_packet(Packet {
+                destination: remote,
+                data: PacketDataBuffer::new_from_slice(b"out"),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let local_stats = transport.traffic_stats(&local).await.unwrap();
+        assert_eq!(
+            (local_stats.packets_received, local_stats.bytes_received),
+            (1, inbound.len() as u64)
+        );
+        assert_eq!(local_stats.packets_sent, 0);
+
+        let remote_stats = transport.traffic_stats(&remote).await.unwrap();
+        assert_eq!((remote_stats.packets_sent, remote_stats.bytes_sent), (1, 3));
+        assert_eq!(transport.all_traffic_stats().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn refuses_packets_larger_than_the_mtu() {
+        let transport = TransportConfig::default().build();
+        let iface_manager = transport.iface_manager();
+        let (small, mut large) = {
+            let mut manager = iface_manager.lock().await;
+            let small = manager.new_channel(4);
+            let large = manager.new_channel(4);
+            manager.set_mtu(&small.address, 100);
+            manager.set_mtu(&large.address, 500);
+            (small, large)
+        };
+
+        let packet = |len: usize| Packet {
+            destination: AddressHash::new_from_slice(&[7u8; 16]),
+            data: PacketDataBuffer::new_from_slice(&vec![0u8; len]),
+            ..Default::default()
+        };
+        let size = packet(200).wire_len();
+
+        // Broadcasts have to fit into every interface
+        assert_eq!(
+            transport.send_packet(packet(200)).await,
+            Err(SendError::PacketTooLarge { size, mtu: 100 })
+        );
+        assert!(large.tx_channel.try_recv().is_err());
+
+        transport.send_broadcast(packet(200), Some(small.address)).await.unwrap();
+        assert!(large.tx_channel.try_recv().is_ok());
+
+        assert!(transport.send_direct(small.address, packet(200)).await.is_err());
+        transport.send_direct(large.address, packet(200)).await.unwrap();
+        transport.send_packet(packet(50)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_announces_larger_than_the_mtu() {
+        let transport = TransportConfig::default().build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        transport.iface_manager().lock().await.set_mtu(&iface.address, 300);
+
+        let destination = transport
+            .add_destination(
+                PrivateIdentity::new_from_rand(OsRng),
+                DestinationName::new("test", "announce"),
+            )
+            .await;
+
+        let max = transport.max_announce_app_data().await;
+        assert_eq!(max, max_announce_app_data(300));
+
+        let app_data = vec![0u8; max + 1];
+        assert_eq!(
+            transport.send_announce(&destination, Some(&app_data)).await,
+            Err(SendError::AnnounceTooLarge { app_data: max + 1, max })
+        );
+        assert!(iface.tx_channel.try_recv().is_err());
+
+        transport.send_announce(&destination, Some(&app_data[..max])).await.unwrap();
+        let sent = iface.tx_channel.try_recv().unwrap();
+        assert_eq!(sent.packet.wire_len(), 300);
+    }
+
+    #[tokio::test]
+    async fn rejects_corrupted_link_proofs() {
+        let transport = TransportConfig::default().build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        let iface_address = *iface.address();
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "proofs"),
+        );
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface_address).await;
+        transport.flush().await;
+        while iface.tx_channel.try_recv().is_ok() {}
+
+        let link = transport.link(destination.desc).await;
+        let request = iface.tx_channel.try_recv().unwrap().packet;
+
+        let (event_tx, _) = tokio::sync::broadcast::channel(1);
+        let proof = Link::new_from_request(&request, destination.sign_key().clone(), destination.desc, event_tx)
+            .unwrap()
+            .prove();
+
+        let mut truncated = proof;
+        truncated.data = PacketDataBuffer::new_from_slice(&proof.data.as_slice()[..95]);
+        let mut extended = proof;
+        extended.data.safe_write(&[0u8; 5]);
+        let mut forged = proof;
+        forged.data.as_mut_slice()[0] ^= 0xff;
+        let mut unknown = proof;
+        unknown.destination = AddressHash::new_from_slice(&[5u8; 16]);
+
+        for packet in [truncated, extended, forged, unknown] {
+            handle_proof(&packet, transport.get_handler().lock().await, iface_address).await;
+        }
+
+        assert_eq!(link.lock().await.status(), LinkStatus::Pending);
+        assert_eq!(
+            transport.rejected_proofs().await,
+            RejectedProofs {
+                malformed: 2,
+                invalid_signature: 1,
+                unknown_link: 1,
+                wrong_interface: 0,
+            }
+        );
+
+        handle_proof(&proof, transport.get_handler().lock().await, iface_address).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn drops_replayed_link_data() {
+        let transport = TransportConfig::default().build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        let iface_address = *iface.address();
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "replay"),
+        );
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface_address).await;
+        transport.flush().await;
+        while iface.tx_channel.try_recv().is_ok() {}
+
+        let link = transport.link(destination.desc).await;
+        let request = iface.tx_channel.try_recv().unwrap().packet;
+
+        let (event_tx, _) = tokio::sync::broadcast::channel(1);
+        let mut peer = Link::new_from_request(&request, destination.sign_key().clone(), destination.desc, event_tx)
+            .unwrap();
+        let proof = peer.prove();
+        handle_proof(&proof, transport.get_handler().lock().await, iface_address).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Active);
+
+        let mut events = transport.out_link_events();
+        let packet = peer.data_packet(b"hello").unwrap();
+        for _ in 0..2 {
+            receive_data(&transport.get_handler(), &packet, iface_address).await;
+        }
+
+        let mut received = 0;
+        while let Ok(event) = events.try_recv() {
+            if let crate::destination::link::LinkEvent::Data(payload) = event.event {
+                assert_eq!(payload.as_slice(), b"hello");
+                received += 1;
+            }
+        }
+        assert_eq!(received, 1);
+        assert_eq!(link.lock().await.replays(), 1);
+        assert_eq!(transport.link_replays().await, 1);
+    }
+
+    #[tokio::test]
+    async fn replay_announces_to_late_subscribers() {
+        let transport = TransportConfig::default().set_announce_replay(2).build();
+        let iface = AddressHash::new_from_slice(&[1u8; 16]);
+
+        let destinations: Vec<_> = ["first", "second", "third"]
+            .iter()
+            .map(|name| {
+                SingleInputDestination::new(
+                    PrivateIdentity::new_from_name(name),
+                    DestinationName::new("test", "replay"),
+                )
+            })
+            .collect();
+
+        for destination in &destinations {
+            let announce = destination.announce(OsRng, None).unwrap();
+            receive_announce(&transport.get_handler(), &announce, iface).await;
+        }
+
+        // A newer announce of a buffered destination replaces the older one
+        let announce = destinations[1].announce(OsRng, Some(b"again")).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+
+        let mut announces = transport.recv_announces_with_replay().await;
+        let mut replayed = Vec::new();
+        for _ in 0..2 {
+            let event = announces.recv().await.unwrap();
+            let address = event.destination.lock().await.desc.address_hash;
+            replayed.push((address, event.app_data.as_slice().to_vec()));
+        }
+        assert_eq!(
+            replayed,
+            [
+                (destinations[2].desc.address_hash, Vec::new()),
+                (destinations[1].desc.address_hash, b"again".to_vec()),
+            ]
+        );
+
+        // Then live announces follow
+        let announce = destinations[0].announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+        let event = runtime::timeout(Duration::from_secs(1), announces.recv()).await.unwrap().unwrap();
+        assert_eq!(event.destination.lock().await.desc.address_hash, destinations[0].desc.address_hash);
+    }
+
+    #[tokio::test]
+    async fn announces_are_verified_without_the_handler_lock() {
+        let transport = TransportConfig::default().build();
+        let iface = AddressHash::new_from_slice(&[1u8; 16]);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "verify"),
+        );
+        let announce = destination.announce(OsRng, Some(b"valid")).unwrap();
+        let mut tampered = destination.announce(OsRng, Some(b"tampered")).unwrap();
+        tampered.data.safe_write(b"!");
+
+        let handler = transport.get_handler();
+        let verified = handler.lock().await.verified_announces.clone();
+        let results = {
+            let _locked = handler.lock().await;
+            let verify = |packet| runtime::timeout(Duration::from_secs(5), verify_announce(&verified, packet));
+            [verify(&announce).await.unwrap(), verify(&tampered).await.unwrap()]
+        };
+
+        assert_eq!(results, [true, false]);
+        assert_eq!(verified.lock().unwrap().get(&announce), Some(true));
+        assert_eq!(verified.lock().unwrap().get(&tampered), Some(false));
+
+        // The handler goes by the results it is handed, it doesn't look
+        // them up or verify again
+        handle_announce(&tampered, handler.lock().await, iface, true).await;
+        handle_announce(&announce, handler.lock().await, iface, false).await;
+
+        let counts = transport.announce_counts().await;
+        assert_eq!(counts.received, 1);
+        assert_eq!(counts.dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn destination_info_from_announces() {
+        let transport = TransportConfig::default().build();
+        let iface = AddressHash::new_from_slice(&[1u8; 16]);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "info"),
+        );
+        let address = destination.desc.address_hash;
+        assert!(transport.destination_info(&address).await.is_none());
+
+        let announce = destination.announce(OsRng, Some(b"first")).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+
+        let info = transport.destination_info(&address).await.unwrap();
+        assert_eq!(info.identity.address_hash, *destination.identity.address_hash());
+        assert_eq!(info.app_data, b"first");
+        assert_eq!(info.hops, 1);
+        assert_eq!(info.ratchet, None);
+
+        let seen = info.last_seen;
+        let announce = destination.announce(OsRng, Some(b"second")).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+
+        let info = transport.destination_info(&address).await.unwrap();
+        assert_eq!(info.app_data, b"second");
+        assert!(info.last_seen >= seen);
+
+        let counts = transport.announce_counts().await;
+        assert_eq!(counts.received, 2);
+        assert_eq!(counts.dropped, 0);
+
+        let paths = transport.all_paths().await;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].0, address);
+        assert_eq!(paths[0].1.iface, iface);
+    }
+
+    #[tokio::test]
+    async fn list_active_links() {
+        let transport = TransportConfig::default().build();
+        assert!(transport.active_links().await.is_empty());
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "links"),
+        );
+        let link = transport.link(destination.desc).await;
+
+        let links = transport.active_links().await;
+        assert_eq!(links.len(), 1);
+
+        let summary = &links[0];
+        assert_eq!(summary.id, *link.lock().await.id());
+        assert_eq!(summary.destination, destination.desc.address_hash);
+        assert_eq!(summary.direction, LinkDirection::Outbound);
+        assert_eq!(summary.status, LinkStatus::Pending);
+        assert_eq!(summary.tx_bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn memory_stats_count_entries() {
+        let transport = TransportConfig::default().build();
+        let iface = AddressHash::new_from_slice(&[1u8; 16]);
+
+        let empty = transport.memory_stats().await;
+        assert_eq!(empty.paths.entries, 0);
+        assert_eq!(empty.links.entries, 0);
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "memory"),
+        );
+        let announce = destination.announce(OsRng, Some(b"app data")).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface).await;
+        transport.link(destination.desc).await;
+
+        let stats = transport.memory_stats().await;
+        assert_eq!(stats.paths.entries, 1);
+        assert_eq!(stats.links.entries, 1);
+        assert!(stats.destinations.entries > empty.destinations.entries);
+        assert!(stats.paths.bytes > 0);
+        assert!(stats.links.bytes > 0);
+        assert!(stats.total_bytes() > empty.total_bytes());
+    }
+
+    #[tokio::test]
+    async fn injected_rng_makes_links_deterministic() {
+        struct SeededRng(u64);
+
+        impl rand_core::RngCore for SeededRng {
+            fn next_u32(&mut self) -> u32 {
+                self.next_u64() as u32
+            }
+
+            fn next_u64(&mut self) -> u64 {
+                // xorshift64
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0
+            }
+
+            fn fill_bytes(&mut self, dest: &mut [u8]) {
+                rand_core::impls::fill_bytes_via_next(self, dest)
+            }
+
+            fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+                self.fill_bytes(dest);
+                Ok(())
+            }
+        }
+
+        impl rand_core::CryptoRng for SeededRng {}
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "rng"),
+        );
+
+        let mut requests = Vec::new();
+        for _ in 0..2 {
+            let transport = TransportConfig::default().set_rng(SeededRng(42)).build();
+            let mut iface = transport.iface_manager().lock().await.new_channel(4);
+
+            transport.link(destination.desc).await;
+            requests.push(iface.tx_channel.try_recv().unwrap().packet);
+        }
+
+        assert_eq!(requests[0].data.as_slice(), requests[1].data.as_slice());
+        assert_eq!(LinkId::from(&requests[0]), LinkId::from(&requests[1]));
+    }
+
+    #[tokio::test]
+    async fn injected_clock_drives_link_and_path_timeouts() {
+        #[derive(Clone)]
+        struct TestClock {
+            start: Instant,
+            offset: Arc<std::sync::Mutex<Duration>>,
+        }
+
+        impl Clock for TestClock {
+            fn now(&self) -> Instant {
+                self.start + *self.offset.lock().unwrap()
+            }
+
+            fn sleep(&self, _duration: Duration) -> runtime::SleepFuture {
+                // Timers are run by hand
+                Box::pin(std::future::pending())
+            }
+        }
+
+        let clock = TestClock { start: Instant::now(), offset: Default::default() };
+        let advance = |duration| *clock.offset.lock().unwrap() += duration;
+
+        let transport = TransportConfig::default().set_clock(clock.clone()).build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        let iface_address = *iface.address();
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("peer"),
+            DestinationName::new("test", "clock"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface_address).await;
+        transport.flush().await;
+        while iface.tx_channel.try_recv().is_ok() {}
+
+        let link = transport.link(destination.desc).await;
+        let request = iface.tx_channel.try_recv().unwrap().packet;
+        let (event_tx, _) = tokio::sync::broadcast::channel(1);
+        let proof = Link::new_from_request(&request, destination.sign_key().clone(), destination.desc, event_tx)
+            .unwrap()
+            .prove();
+        handle_proof(&proof, transport.get_handler().lock().await, iface_address).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Active);
+
+        let timer_config = TimerConfig::default();
+        handle_check_links(transport.get_handler().lock().await).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Active);
+
+        advance(timer_config.out_link_stale + Duration::from_secs(1));
+        handle_check_links(transport.get_handler().lock().await).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Stale);
+
+        assert_eq!(transport.paths(&address).await.len(), 1);
+        advance(timer_config.direct_path_expiry);
+        handle_cleanup(transport.get_handler().lock().await).await;
+        assert!(transport.paths(&address).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn link_policies_per_destination() {
+        #[derive(Clone)]
+        struct TestClock {
+            start: Instant,
+            offset: Arc<std::sync::Mutex<Duration>>,
+        }
+
+        impl Clock for TestClock {
+            fn now(&self) -> Instant {
+                self.start + *self.offset.lock().unwrap()
+            }
+
+            fn sleep(&self, _duration: Duration) -> runtime::SleepFuture {
+                Box::pin(std::future::pending())
+            }
+        }
+
+        let clock = TestClock { start: Instant::now(), offset: Default::default() };
+        let advance = |duration| *clock.offset.lock().unwrap() += duration;
+
+        let transport = TransportConfig::default().set_clock(clock.clone()).build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(4);
+        let iface_address = *iface.address();
+
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("collector"),
+            DestinationName::new("test", "policy"),
+        );
+        let address = destination.desc.address_hash;
+        let announce = destination.announce(OsRng, None).unwrap();
+        receive_announce(&transport.get_handler(), &announce, iface_address).await;
+
+        let timers = TimerConfig::default();
+        let policy = LinkPolicy {
+            keep_alive: Some(Duration::from_secs(2)),
+            ..LinkPolicy::from_timers(&timers, false).close_when_idle(Duration::from_secs(30))
+        };
+        transport.set_link_policy(address, policy).await;
+        assert_eq!(transport.link_policy(&address).await, policy);
+
+        let link = transport.link(destination.desc).await;
+        let request = iface.tx_channel.recv().await.unwrap().packet;
+        let (event_tx, _) = tokio::sync::broadcast::channel(1);
+        let proof = Link::new_from_request(&request, destination.sign_key().clone(), destination.desc, event_tx)
+            .unwrap()
+            .prove();
+        handle_proof(&proof, transport.get_handler().lock().await, iface_address).await;
+        transport.flush().await;
+        while iface.tx_channel.try_recv().is_ok() {}
+
+        let keep_alives = |iface: &mut crate::iface::InterfaceChannel| {
+            std::iter::from_fn(|| iface.tx_channel.try_recv().ok())
+                .filter(|message| message.packet.context == PacketContext::KeepAlive)
+                .count()
+        };
+
+        // Keep-alives every 2s, and no longer stale after the default 10s
+        advance(Duration::from_secs(3));
+        handle_check_links(transport.get_handler().lock().await).await;
+        advance(Duration::from_secs(1));
+        handle_check_links(transport.get_handler().lock().await).await;
+        transport.flush().await;
+        assert_eq!(keep_alives(&mut iface), 1);
+
+        advance(timers.out_link_stale);
+        handle_check_links(transport.get_handler().lock().await).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Active);
+
+        advance(Duration::from_secs(30));
+        handle_check_links(transport.get_handler().lock().await).await;
+        assert_eq!(link.lock().await.status(), LinkStatus::Stale);
+        handle_check_links(transport.get_handler().lock().await).await;
+        assert!(transport.get_handler().lock().await.out_links.is_empty());
+
+        transport.remove_link_policy(&address).await;
+        assert_eq!(transport.link_policy(&address).await, LinkPolicy::from_timers(&timers, false));
+    }
+
+    #[tokio::test]
+    async fn handler_stays_available_while_interfaces_are_busy() {
+        let transport = TransportConfig::default().build();
+        // Room for a single packet, and nobody drains it yet
+        let mut iface = transport.iface_manager().lock().await.new_channel(1);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("busy"), DestinationName::new("test", "outbox"))
+            .await;
+        let desc = destination.lock().await.desc;
+
+        // Each link request is answered with a proof, so the interface fills
+        // up while the transport still handles requests. Handlers used to
+        // wait for the interface with the handler locked.
+        let mut requests = Vec::new();
+        for _ in 0..4 {
+            let (event_tx, _) = tokio::sync::broadcast::channel(1);
+            let request = Link::new(desc, event_tx).request();
+            requests.push(LinkId::from(&request));
+            iface
+                .rx_channel
+                .send(RxMessage { address: iface.address, packet: request })
+                .await
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(1);
+        runtime::timeout(timeout, async {
+            while transport.active_links().await.len() < requests.len() {
+                runtime::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("handler is available");
+
+        // The proofs leave in the order of the requests once there is room
+        for link_id in requests {
+            let proof = runtime::timeout(timeout, iface.tx_channel.recv()).await.unwrap().unwrap();
+            assert_eq!(proof.packet.header.packet_type, PacketType::Proof);
+            assert_eq!(proof.packet.destination, link_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn outbox_drops_packets_once_full() {
+        let transport = TransportConfig::default()
+            .set_outbox_capacity(2)
+            .set_announce_on_iface_up(false)
+            .build();
+        // Room for a single packet, and nobody drains it
+        let mut iface = transport.iface_manager().lock().await.new_channel(1);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("busy"), DestinationName::new("test", "outbox"))
+            .await;
+        let desc = destination.lock().await.desc;
+
+        let mut requests = Vec::new();
+        for _ in 0..8 {
+            let (event_tx, _) = tokio::sync::broadcast::channel(1);
+            let request = Link::new(desc, event_tx).request();
+            requests.push(LinkId::from(&request));
+            iface
+                .rx_channel
+                .send(RxMessage { address: iface.address, packet: request })
+                .await
+                .unwrap();
+        }
+
+        let timeout = Duration::from_secs(1);
+        runtime::timeout(timeout, async {
+            while transport.active_links().await.len() < requests.len() {
+                runtime::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("handler is available");
+
+        // At most one proof sits in the interface queue, one waits for room
+        // in it and two in the outbox, the others are dropped
+        let dropped = transport.outbox_dropped();
+        assert!(dropped >= 4);
+
+        let mut proofs = Vec::new();
+        while let Ok(Some(proof)) = runtime::timeout(Duration::from_millis(100), iface.tx_channel.recv()).await {
+            assert_eq!(proof.packet.header.packet_type, PacketType::Proof);
+            proofs.push(proof.packet.destination);
+        }
+        assert_eq!(proofs.len() as u64 + dropped, requests.len() as u64);
+
+        // The proofs which made it leave in the order of the requests
+        let mut remaining = requests.iter();
+        assert!(proofs.iter().all(|proof| remaining.any(|request| request == proof)));
+    }
+
+    #[tokio::test]
+    async fn api_sends_wait_instead_of_dropping() {
+        let transport = Arc::new(
+            TransportConfig::default()
+                .set_outbox_capacity(1)
+                .set_announce_on_iface_up(false)
+                .build(),
+        );
+        let mut iface = transport.iface_manager().lock().await.new_channel(1);
+
+        let sender = {
+            let transport = transport.clone();
+            tokio::spawn(async move {
+                for index in 0..8u8 {
+                    let packet = Packet {
+                        destination: AddressHash::new_from_slice(&[index; 16]),
+                        data: PacketDataBuffer::new_from_slice(b"api"),
+                        ..Default::default()
+                    };
+                    transport.send_packet(packet).await.unwrap();
+                }
+            })
+        };
+
+        for index in 0..8u8 {
+            let message = runtime::timeout(Duration::from_secs(1), iface.tx_channel.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(message.packet.destination, AddressHash::new_from_slice(&[index; 16]));
+        }
+
+        sender.await.unwrap();
+        assert_eq!(transport.outbox_dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn limits_inbound_links() {
+        let transport = TransportConfig::default().build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(16);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("busy"), DestinationName::new("test", "limits"))
+            .await;
+        destination.lock().await.set_link_limits(LinkLimits {
+            max_links: Some(2),
+            request_rate: Some((3, Duration::from_secs(60))),
+            overflow: LinkOverflow::Ignore,
+        });
+        let desc = destination.lock().await.desc;
+
+        async fn request_link(
+            transport: &Transport,
+            iface: &mut crate::iface::InterfaceChannel,
+            desc: DestinationDesc,
+        ) -> (LinkId, Vec<Packet>) {
+            let (event_tx, _) = tokio::sync::broadcast::channel(1);
+            let request = Link::new(desc, event_tx).request();
+            handle_link_request(&request, *iface.address(), transport.get_handler().lock().await).await;
+            transport.flush().await;
+
+            let mut sent = Vec::new();
+            while let Ok(message) = iface.tx_channel.try_recv() {
+                sent.push(message.packet);
+            }
+            (LinkId::from(&request), sent)
+        }
+
+        let (first, sent) = request_link(&transport, &mut iface, desc).await;
+        assert_eq!(sent.len(), 1);
+        let (second, _) = request_link(&transport, &mut iface, desc).await;
+
+        // Too many links
+        let (_, sent) = request_link(&transport, &mut iface, desc).await;
+        assert!(sent.is_empty());
+        assert_eq!(transport.get_handler().lock().await.in_links.len(), 2);
+
+        let proofs = |sent: &[Packet]| {
+            sent.iter().filter(|packet| packet.header.packet_type == PacketType::Proof).count()
+        };
+
+        transport.link_close(first).await.unwrap();
+        let (_, sent) = request_link(&transport, &mut iface, desc).await;
+        assert_eq!(proofs(&sent), 1);
+
+        // Too many requests
+        transport.link_close(second).await.unwrap();
+        let (_, sent) = request_link(&transport, &mut iface, desc).await;
+        assert_eq!(proofs(&sent), 0);
+
+        // Excess links are closed right after their proof
+        destination.lock().await.set_link_limits(LinkLimits {
+            max_links: Some(0),
+            request_rate: None,
+            overflow: LinkOverflow::Close,
+        });
+        let (link_id, sent) = request_link(&transport, &mut iface, desc).await;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].header.packet_type, PacketType::Proof);
+        assert_eq!(sent[1].context, PacketContext::LinkClose);
+        assert!(!transport.get_handler().lock().await.in_links.contains_key(&link_id));
+    }
+
+    #[tokio::test]
+    async fn link_acceptor_decides_before_the_proof() {
+        let transport = TransportConfig::default().build();
+        let mut iface = transport.iface_manager().lock().await.new_channel(16);
+
+        let destination = transport
+            .add_destination(PrivateIdentity::new_from_name("gated"), DestinationName::new("test", "acceptor"))
+            .await;
+        let desc = destination.lock().await.desc;
+
+        let (seen_tx, mut seen) = tokio::sync::mpsc::unbounded_channel();
+        destination.lock().await.set_link_acceptor(move |request: LinkRequestInfo| {
+            let seen_tx = seen_tx.clone();
+            async move {
+                let _ = seen_tx.send(request);
+                // Sheds load beyond one link
+                request.active_links < 1
+            }
+        });
+
+        let request_link = |iface: &mut crate::iface::InterfaceChannel| {
+            let (event_tx, _) = tokio::sync::broadcast::channel(1);
+            let request = Link::new(desc, event_tx).request();
+            let address = *iface.address();
+            let transport = &transport;
+            async move {
+                handle_link_request(&request, address, transport.get_handler().lock().await).await;
+                transport.flush().await;
+                LinkId::from(&request)
+            }
+        };
+
+        let accepted = request_link(&mut iface).await;
+        let proof = iface.tx_channel.try_recv().unwrap();
+        assert_eq!(proof.packet.header.packet_type, PacketType::Proof);
+        assert_eq!(proof.packet.destination, accepted);
+
+        let request = seen.try_recv().unwrap();
+        assert_eq!(request.link_id, accepted);
+        assert_eq!(request.destination, desc.address_hash);
+        assert_eq!(request.iface, *iface.address());
+        assert_eq!(request.active_links, 0);
+
+        let rejected = request_link(&mut iface).await;
+        assert!(iface.tx_channel.try_recv().is_err());
+        assert_eq!(seen.try_recv().unwrap().active_links, 1);
+        assert!(!transport.get_handler().lock().await.in_links.contains_key(&rejected));
+
+        // Requests over the limits don't reach the acceptor
+        destination.lock().await.set_link_limits(LinkLimits { max_links: Some(1), ..Default::default() });
+        request_link(&mut iface).await;
+        assert!(seen.try_recv().is_err());
+
+        destination.lock().await.remove_link_acceptor();
+        destination.lock().await.set_link_limits(LinkLimits::default());
+        request_link(&mut iface).await;
+        assert_eq!(iface.tx_channel.try_recv().unwrap().packet.header.packet_type, PacketType::Proof);
+    }
+
+    #[tokio::test]
+    async fn registered_request_handlers_answer() {
+        use crate::destination::request::{IncomingRequest, RequestPolicy};
+        use crate::sim::SimNetwork;
+
+        let mut network = SimNetwork::new();
+        for name in ["server", "client"] {
+            network.add_node(TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false));
+        }
+        network.connect(0, 1).await;
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("rpc"), DestinationName::new("test", "rpc"))
+            .await;
+        {
+            let mut destination = destination.lock().await;
+            destination.register_request_handler(
+                "/echo",
+                |request: &IncomingRequest| Some(request.data.to_vec()),
+                RequestPolicy::All,
+            );
+            let operators = RequestPolicy::Allowed(vec![AddressHash::new_from_rand(OsRng)]);
+            destination.register_request_handler("/private", |_: &IncomingRequest| Some(vec![0xc3]), operators);
+        }
+        let desc = destination.lock().await.desc;
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let mut requests = network.node(0).link_requests();
+        let mut responses = network.node(1).link_responses();
+        let link = network.node(1).link(desc).await;
+        network.advance(Duration::from_secs(5)).await;
+
+        let mut sent = Vec::new();
+        for path in ["/echo", "/private", "/unknown"] {
+            let (packet, request_id) = link.lock().await.request_packet(path, &[0xa2, b'h', b'i']).unwrap();
+            network.node(1).send_packet(packet).await.unwrap();
+            sent.push(request_id);
+        }
+        network.advance(Duration::from_secs(1)).await;
+
+        let response = responses.try_recv().unwrap().response;
+        assert_eq!(response.request_id, sent[0]);
+        assert_eq!(response.data, [0xa2, b'h', b'i']);
+        assert!(responses.try_recv().is_err());
+
+        // Only requests without a handler reach the application
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.request.request_id, sent[2]);
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn links_compress_when_both_peers_do() {
+        use crate::sim::SimNetwork;
+
+        let mut network = SimNetwork::new();
+        for name in ["server", "compressing", "plain"] {
+            let mut config = TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false);
+            if name != "plain" {
+                config = config.set_link_compression(LinkCompression::default());
+            }
+            network.add_node(config);
+        }
+        network.connect(0, 1).await;
+        network.connect(0, 2).await;
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("service"), DestinationName::new("test", "compress"))
+            .await;
+        let desc = destination.lock().await.desc;
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let mut received = network.node(0).received_data_events();
+        let compressing = network.node(1).link(desc).await;
+        let plain = network.node(2).link(desc).await;
+        network.advance(Duration::from_secs(5)).await;
+
+        assert!(compressing.lock().await.compresses());
+        assert!(!plain.lock().await.compresses());
+
+        let text = "status: all sensors nominal; ".repeat(8);
+        for link in [&compressing, &plain] {
+            let packet = link.lock().await.data_packet(text.as_bytes()).unwrap();
+            let expected = if Arc::ptr_eq(link, &compressing) {
+                PacketContext::Compressed
+            } else {
+                PacketContext::None
+            };
+            assert_eq!(packet.context, expected);
+        }
+
+        for node in [1, 2] {
+            network.node(node).send_to_out_links(&desc.address_hash, text.as_bytes()).await;
+        }
+        network.advance(Duration::from_secs(1)).await;
+
+        for _ in 0..2 {
+            assert_eq!(received.try_recv().unwrap().data.as_slice(), text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn accepts_compressed_data_before_the_answer() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("service"),
+            DestinationName::new("test", "compress"),
+        );
+
+        let (event_tx, _) = tokio::sync::broadcast::channel(4);
+        let mut initiator = Link::new(destination.desc, event_tx.clone());
+        initiator.set_compression(Some(LinkCompression::default()));
+        let request = initiator.request();
+
+        let mut responder =
+            Link::new_from_request(&request, destination.sign_key().clone(), destination.desc, event_tx).unwrap();
+        responder.set_compression(Some(LinkCompression::default()));
+
+        let proof = responder.prove();
+        assert!(matches!(initiator.handle_packet(&proof, true), LinkHandleResult::Activated));
+        responder.handle_packet(&initiator.create_rtt(), false);
+
+        let offer = initiator.compression_offer().unwrap();
+        let answer = match responder.handle_packet(&offer, false) {
+            LinkHandleResult::CompressionAgreed(Some(answer)) => answer,
+            _ => panic!("responder agrees to compress"),
+        };
+
+        // The responder compresses as soon as it answered, its data may
+        // overtake the answer
+        let text = "status: all sensors nominal; ".repeat(8);
+        let data = responder.data_packet(text.as_bytes()).unwrap();
+        assert_eq!(data.context, PacketContext::Compressed);
+        assert!(!initiator.compresses());
+
+        match initiator.handle_packet(&data, true) {
+            LinkHandleResult::DataReceived(_, payload) => assert_eq!(payload.as_slice(), text.as_bytes()),
+            _ => panic!("compressed data is received"),
+        }
+
+        initiator.handle_packet(&answer, true);
+        assert!(initiator.compresses());
+    }
+
+    #[tokio::test]
+    async fn sequenced_link_data_reveals_gaps() {
+        use crate::sim::SimNetwork;
+
+        let mut network = SimNetwork::new();
+        for name in ["server", "client"] {
+            let config = TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false)
+                .set_link_sequencing(name == "client");
+            network.add_node(config);
+        }
+        network.connect(0, 1).await;
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("service"), DestinationName::new("test", "sequence"))
+            .await;
+        let desc = destination.lock().await.desc;
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let mut received = network.node(0).received_data_events();
+        let link = network.node(1).link(desc).await;
+        network.advance(Duration::from_secs(5)).await;
+
+        let packets: Vec<_> = {
+            let link = link.lock().await;
+            (0..4u8).map(|i| link.data_packet(&[i]).unwrap()).collect()
+        };
+        assert!(packets.iter().all(|packet| packet.context == PacketContext::Sequenced));
+
+        // The second packet comes last
+        for i in [0, 2, 3] {
+            network.node(1).send_packet(packets[i]).await.unwrap();
+            network.advance(Duration::from_millis(100)).await;
+        }
+
+        let sequences: Vec<_> = (0..3).map(|_| received.try_recv().unwrap()).collect();
+        assert_eq!(sequences[0].data.as_slice(), &[0]);
+        assert_eq!(sequences[0].sequence, Some(LinkSequence { number: 0, missed: 0, late: false }));
+        assert_eq!(sequences[1].sequence, Some(LinkSequence { number: 2, missed: 1, late: false }));
+        assert_eq!(sequences[2].sequence, Some(LinkSequence { number: 3, missed: 0, late: false }));
+
+        network.node(1).send_packet(packets[1]).await.unwrap();
+        network.advance(Duration::from_millis(100)).await;
+        let late = received.try_recv().unwrap();
+        assert_eq!(late.data.as_slice(), &[1]);
+        assert!(late.sequence.unwrap().late);
+
+        // The server doesn't sequence what it sends
+        let inbound = network.node(0).find_in_link(link.lock().await.id()).await.unwrap();
+        assert!(!inbound.lock().await.sequencing());
+        assert_eq!(inbound.lock().await.data_packet(b"reply").unwrap().context, PacketContext::None);
+    }
+
+    #[tokio::test]
+    async fn dead_links_are_reestablished() {
+        use crate::sim::SimNetwork;
+
+        let mut network = SimNetwork::new();
+        for name in ["server", "client"] {
+            network.add_node(TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false));
+        }
+        network.connect(0, 1).await;
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("service"), DestinationName::new("test", "reestablish"))
+            .await;
+        let desc = destination.lock().await.desc;
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let client = network.node(1);
+        let policy = LinkPolicy { reestablish: true, ..client.link_policy(&desc.address_hash).await };
+        client.set_link_policy(desc.address_hash, policy).await;
+
+        let mut events = client.events_filtered(TransportEvent::is_link);
+        let old = *client.link(desc).await.lock().await.id();
+        network.advance(Duration::from_secs(5)).await;
+        assert!(matches!(events.recv().await.unwrap(), TransportEvent::LinkActivated { id, .. } if id == old));
+
+        network.node(0).link_close(old).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let new = loop {
+            match events.recv().await.unwrap() {
+                TransportEvent::LinkClosed { id, .. } => assert_eq!(id, old),
+                TransportEvent::LinkActivated { id, .. } => break id,
+                _ => panic!("link not reestablished"),
+            }
+        };
+        assert_ne!(new, old);
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            TransportEvent::LinkReestablished { old: o, new: n, destination }
+                if (o, n, destination) == (old, new, desc.address_hash)
+        ));
+
+        let link = client.link(desc).await;
+        let link = link.lock().await;
+        assert_eq!((*link.id(), link.status()), (new, LinkStatus::Active));
+        drop(link);
+
+        // Links closed by this side stay closed
+        client.link_close(new).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+        assert!(client.get_handler().lock().await.out_links.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reply_on_link_answers_the_sending_client() {
+        use crate::sim::SimNetwork;
+
+        let mut network = SimNetwork::new();
+        for name in ["server", "client1", "client2"] {
+            network.add_node(TransportConfig::new(name, &PrivateIdentity::new_from_name(name), false));
+        }
+        network.connect(0, 1).await;
+        network.connect(0, 2).await;
+
+        let destination = network
+            .node_mut(0)
+            .add_destination(PrivateIdentity::new_from_name("service"), DestinationName::new("test", "reply"))
+            .await;
+        let desc = destination.lock().await.desc;
+        network.node(0).send_announce(&destination, None).await.unwrap();
+        network.advance(Duration::from_secs(5)).await;
+
+        let mut received = network.node(0).received_data_events();
+        let mut client1_events = network.node(1).out_link_events();
+        let mut client2_events = network.node(2).out_link_events();
+        network.node(1).link(desc).await;
+        network.node(2).link(desc).await;
+        network.advance(Duration::from_secs(5)).await;
+
+        assert_eq!(network.node(1).send_to_out_links(&desc.address_hash, b"ping").await.len(), 1);
+        network.advance(Duration::from_secs(1)).await;
+
+        let data = received.try_recv().unwrap();
+        assert_eq!(data.destination, desc.address_hash);
+        assert_eq!(data.data.as_slice(), b"ping");
+        let link_id = data.link_id.unwrap();
+
+        network.node(0).reply_on_link(&link_id, b"pong").await.unwrap();
+        network.advance(Duration::from_secs(1)).await;
+
+        let replies = |events: &mut broadcast::Receiver<LinkEventData>| {
+            let mut replies = vec![];
+            while let Ok(event) = events.try_recv() {
+                if let LinkEvent::Data(payload) = event.event {
+                    replies.push((event.id, payload.as_slice().to_vec()));
+                }
+            }
+            replies
+        };
+        assert_eq!(replies(&mut client1_events), vec![(link_id, b"pong".to_vec())]);
+        assert!(replies(&mut client2_events).is_empty());
+
+        let unknown = LinkId::new_from_slice(&[7u8; 16]);
+        assert!(matches!(
+            network.node(0).reply_on_link(&unknown, b"pong").await,
+            Err(LinkError::Unknown { .. })
+        ));
+    }
 }
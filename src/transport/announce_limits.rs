@@ -1,9 +1,9 @@
 use alloc::collections::BTreeMap;
 
-use tokio::time::Duration;
-use tokio::time::Instant;
+use core::time::Duration;
 
 use crate::hash::AddressHash;
+use crate::runtime::Instant;
 
 pub struct AnnounceRateLimit {
   pub target: Duration,
@@ -1,13 +1,88 @@
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
-use tokio::time::{Duration, Instant};
-
-use crate::hash::AddressHash;
+use core::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use core::mem::size_of;
+
+use super::TableStats;
+use crate::buffer::{InputBuffer, OutputBuffer};
+use crate::error::RnsError;
+use crate::hash::{AddressHash, ADDRESS_HASH_SIZE};
 use crate::iface::{TxMessage, TxMessageType};
 use crate::packet::{
     DestinationType, Header, HeaderType, IfacFlag,
     Packet, PacketContext, PacketType, PropagationType
 };
+use crate::runtime::Instant;
+use crate::serde::Serialize;
+
+/// Version of the announce cache file format.
+const STORE_VERSION: u8 = 1;
+
+/// An announce as it is kept across restarts of the transport.
+pub struct StoredAnnounce {
+    pub destination: AddressHash,
+    pub received_from: AddressHash,
+    pub hops: u8,
+    pub packet: Packet,
+}
+
+/// Transport nodes an announce passed through on its way here, nearest
+/// first.
+///
+/// Each transport node replaces the transport id of an announce with its own
+/// when it retransmits it, so only the nearest relay can be derived. The
+/// farther ones are `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HopPath {
+    pub hops: u8,
+    pub relays: Vec<Option<AddressHash>>,
+}
+
+impl HopPath {
+    pub fn from_announce(announce: &Packet) -> Self {
+        let hops = announce.header.hops.saturating_add(1);
+
+        let mut relays = vec![None; usize::from(hops - 1)];
+        if let Some(nearest) = relays.first_mut() {
+            *nearest = announce.transport;
+        }
+
+        Self { hops, relays }
+    }
+
+    /// Whether every relay is known.
+    pub fn is_complete(&self) -> bool {
+        self.relays.iter().all(Option::is_some)
+    }
+}
+
+impl fmt::Display for HopPath {
+    /// Relays separated by `>`, unknown ones as `?`, or `direct` for a
+    /// neighbour.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.relays.is_empty() {
+            return f.write_str("direct");
+        }
+
+        for (index, relay) in self.relays.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" > ")?;
+            }
+
+            match relay {
+                Some(relay) => write!(f, "{}", relay)?,
+                None => f.write_str("?")?,
+            }
+        }
+
+        Ok(())
+    }
+}
 
 #[derive(Clone)]
 pub struct AnnounceEntry {
@@ -17,6 +92,8 @@ pub struct AnnounceEntry {
     pub retries: u8,
     pub hops: u8,
     pub response_to_iface: Option<AddressHash>,
+    /// Recorded if enabled, see [`AnnounceTable::set_record_hop_paths`].
+    pub hop_path: Option<HopPath>,
 }
 
 impl AnnounceEntry {
@@ -47,7 +124,8 @@ impl AnnounceEntry {
             header: Header {
                 ifac_flag: IfacFlag::Open,
                 header_type: HeaderType::Type2,
-                propagation_type: PropagationType::Broadcast,
+                context_flag: self.packet.header.context_flag,
+                propagation_type: PropagationType::Transport,
                 destination_type: DestinationType::Single,
                 packet_type: PacketType::Announce,
                 hops: self.hops,
@@ -93,23 +171,37 @@ impl AnnounceCache {
         self.newer.as_mut().unwrap().insert(destination, entry);
     }
 
-    fn get(&self, destination: &AddressHash) -> Option<AnnounceEntry> {
+    fn len(&self) -> usize {
+        self.newer.as_ref().map_or(0, BTreeMap::len) + self.older.as_ref().map_or(0, BTreeMap::len)
+    }
+
+    fn get(&self, destination: &AddressHash) -> Option<&AnnounceEntry> {
         if let Some(entry) = self.newer.as_ref().unwrap().get(destination) {
-            return Some(AnnounceEntry::clone(entry));
+            return Some(entry);
         }
 
         if let Some(ref older) = self.older {
-            return older.get(destination).cloned();
+            return older.get(destination);
         }
 
         None
     }
 }
 
+fn stored_announce(destination: &AddressHash, entry: &AnnounceEntry) -> StoredAnnounce {
+    StoredAnnounce {
+        destination: *destination,
+        received_from: entry.received_from,
+        hops: entry.hops,
+        packet: entry.packet,
+    }
+}
+
 pub struct AnnounceTable {
     map: BTreeMap<AddressHash, AnnounceEntry>,
     responses: BTreeMap<AddressHash, AnnounceEntry>,
     cache: AnnounceCache,
+    record_hop_paths: bool,
 }
 
 impl AnnounceTable {
@@ -118,9 +210,44 @@ impl AnnounceTable {
             map: BTreeMap::new(),
             responses: BTreeMap::new(),
             cache: AnnounceCache::new(100000), // TODO make capacity configurable
+            record_hop_paths: false,
         }
     }
 
+    /// Record the [`HopPath`] of announces added from now on.
+    /// Stats of the announces to retransmit, to answer and of the cache
+    /// for path requests together.
+    pub fn stats(&self) -> TableStats {
+        let hop_paths = self
+            .map
+            .values()
+            .chain(self.responses.values())
+            .filter_map(|entry| entry.hop_path.as_ref())
+            .map(|hop_path| hop_path.relays.capacity() * size_of::<Option<AddressHash>>())
+            .sum();
+
+        TableStats::of_map::<AddressHash, AnnounceEntry>(
+            self.map.len() + self.responses.len() + self.cache.len(),
+        )
+        .with_heap(hop_paths)
+    }
+
+    pub fn set_record_hop_paths(&mut self, record_hop_paths: bool) {
+        self.record_hop_paths = record_hop_paths;
+    }
+
+    fn hop_path_of(&self, announce: &Packet) -> Option<HopPath> {
+        self.record_hop_paths.then(|| HopPath::from_announce(announce))
+    }
+
+    /// Returns the recorded hop path of the latest announce of `destination`.
+    pub fn hop_path(&self, destination: &AddressHash) -> Option<&HopPath> {
+        self.map
+            .get(destination)
+            .or_else(|| self.cache.get(destination))
+            .and_then(|entry| entry.hop_path.as_ref())
+    }
+
     pub fn add(
         &mut self,
         announce: &Packet,
@@ -137,6 +264,7 @@ impl AnnounceTable {
             retries: 5, // TODO: make this configurable too?
             hops,
             response_to_iface: None,
+            hop_path: self.hop_path_of(announce),
         };
 
         self.map.insert(destination, entry);
@@ -176,6 +304,55 @@ impl AnnounceTable {
         false
     }
 
+    /// Returns every announce the table knows of.
+    pub fn stored(&self) -> Vec<StoredAnnounce> {
+        let mut announces = BTreeMap::new();
+
+        // Newer entries replace older ones of the same destination
+        let caches = [self.cache.older.as_ref(), self.cache.newer.as_ref(), Some(&self.map)];
+        for entries in caches.into_iter().flatten() {
+            for (destination, entry) in entries {
+                announces.insert(*destination, stored_announce(destination, entry));
+            }
+        }
+
+        announces.into_values().collect()
+    }
+
+    /// Adds an announce which was stored before a restart. It is
+    /// retransmitted once and then kept for answering path requests.
+    pub fn restore(&mut self, announce: StoredAnnounce) {
+        let entry = AnnounceEntry {
+            packet: announce.packet,
+            timeout: Instant::now() + Duration::from_secs(60),
+            received_from: announce.received_from,
+            retries: 1,
+            hops: announce.hops,
+            response_to_iface: None,
+            hop_path: self.hop_path_of(&announce.packet),
+        };
+
+        self.map.insert(announce.destination, entry);
+    }
+
+    /// Drops the announces received on or to be answered on `iface`, which
+    /// is gone. Returns how many were dropped.
+    pub fn remove_iface(&mut self, iface: &AddressHash) -> usize {
+        let keep = |entry: &AnnounceEntry| {
+            entry.received_from != *iface && entry.response_to_iface != Some(*iface)
+        };
+
+        let before = self.map.len() + self.responses.len() + self.cache.len();
+
+        self.map.retain(|_, entry| keep(entry));
+        self.responses.retain(|_, entry| keep(entry));
+        for cache in [self.cache.newer.as_mut(), self.cache.older.as_mut()].into_iter().flatten() {
+            cache.retain(|_, entry| keep(entry));
+        }
+
+        before - (self.map.len() + self.responses.len() + self.cache.len())
+    }
+
     pub fn new_packet(
         &mut self,
         dest_hash: &AddressHash,
@@ -263,3 +440,139 @@ impl AnnounceTable {
         messages
     }
 }
+
+/// Writes `announces` to the file at `path`, replacing it atomically.
+pub fn write_announces(path: &Path, announces: &[StoredAnnounce]) -> io::Result<()> {
+    let mut data = vec![STORE_VERSION];
+    let mut buffer = [0u8; 1024];
+
+    for announce in announces {
+        let mut output = OutputBuffer::new(&mut buffer);
+        if announce.packet.serialize(&mut output).is_err() {
+            log::warn!("announce cache: skipping unserializable announce for {}", announce.destination);
+            continue;
+        }
+
+        let raw = output.as_slice();
+
+        data.extend_from_slice(announce.destination.as_slice());
+        data.extend_from_slice(announce.received_from.as_slice());
+        data.push(announce.hops);
+        data.extend_from_slice(&(raw.len() as u16).to_be_bytes());
+        data.extend_from_slice(raw);
+    }
+
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, data)?;
+    fs::rename(&temp_path, path)
+}
+
+/// Reads the announces written by [`write_announces`].
+pub fn read_announces(path: &Path) -> io::Result<Vec<StoredAnnounce>> {
+    let data = fs::read(path)?;
+    decode_announces(&data).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, "malformed announce cache")
+    })
+}
+
+fn decode_announces(data: &[u8]) -> Result<Vec<StoredAnnounce>, RnsError> {
+    let mut input = InputBuffer::new(data);
+
+    if input.read_byte()? != STORE_VERSION {
+        return Err(RnsError::InvalidArgument);
+    }
+
+    let mut announces = Vec::new();
+
+    while input.bytes_left() > 0 {
+        let destination = read_address(&mut input)?;
+        let received_from = read_address(&mut input)?;
+        let hops = input.read_byte()?;
+
+        let len = u16::from_be_bytes([input.read_byte()?, input.read_byte()?]) as usize;
+        let packet = Packet::deserialize(&mut InputBuffer::new(input.read_slice(len)?))?;
+
+        announces.push(StoredAnnounce { destination, received_from, hops, packet });
+    }
+
+    Ok(announces)
+}
+
+fn read_address(input: &mut InputBuffer) -> Result<AddressHash, RnsError> {
+    let mut address = [0u8; ADDRESS_HASH_SIZE];
+    input.read(&mut address)?;
+
+    Ok(AddressHash::new(address))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::destination::{DestinationName, SingleInputDestination};
+    use crate::identity::PrivateIdentity;
+
+    #[test]
+    fn persist_announces() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("cached"),
+            DestinationName::new("test", "cache"),
+        );
+        let announce = destination.announce(OsRng, Some(b"app data")).unwrap();
+        let address = destination.desc.address_hash;
+        let iface = AddressHash::new([0x11; ADDRESS_HASH_SIZE]);
+
+        let mut table = AnnounceTable::new();
+        table.add(&announce, address, iface);
+
+        let path = std::env::temp_dir().join("reticulum-announce-cache-test");
+        write_announces(&path, &table.stored()).unwrap();
+
+        let mut restored = AnnounceTable::new();
+        for announce in read_announces(&path).unwrap() {
+            restored.restore(announce);
+        }
+        fs::remove_file(&path).unwrap();
+
+        let stored = restored.stored();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].destination, address);
+        assert_eq!(stored[0].received_from, iface);
+        assert_eq!(stored[0].hops, 1);
+        assert_eq!(stored[0].packet.hash(), announce.hash());
+
+        // Restored announces are retransmitted once and still answer path requests
+        let transport_id = AddressHash::new([0x22; ADDRESS_HASH_SIZE]);
+        assert_eq!(restored.tx_to_retransmit(&transport_id).len(), 1);
+        assert!(restored.tx_to_retransmit(&transport_id).is_empty());
+        assert!(restored.add_response(address, iface, 1));
+
+        assert!(decode_announces(&[STORE_VERSION + 1]).is_err());
+        assert!(decode_announces(&[STORE_VERSION, 0x00]).is_err());
+    }
+
+    #[test]
+    fn remove_iface_drops_its_announces() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_name("removed"),
+            DestinationName::new("test", "remove"),
+        );
+        let announce = destination.announce(OsRng, None).unwrap();
+        let address = destination.desc.address_hash;
+        let iface = AddressHash::new([0x11; ADDRESS_HASH_SIZE]);
+        let other = AddressHash::new([0x33; ADDRESS_HASH_SIZE]);
+
+        let mut table = AnnounceTable::new();
+        table.add(&announce, address, iface);
+        assert!(table.add_response(address, other, 1));
+
+        assert_eq!(table.remove_iface(&other), 1);
+        assert!(!table.stored().is_empty());
+
+        assert!(table.remove_iface(&iface) > 0);
+        assert!(table.stored().is_empty());
+        assert!(!table.add_response(address, other, 1));
+        assert_eq!(table.remove_iface(&iface), 0);
+    }
+}
@@ -0,0 +1,246 @@
+use alloc::boxed::Box;
+use std::collections::VecDeque;
+
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+
+use crate::destination::link::LinkEvent;
+use crate::destination::link::LinkEventData;
+use crate::destination::link::LinkId;
+use crate::hash::AddressHash;
+use crate::iface::InterfaceEvent;
+
+use super::AnnounceEvent;
+use super::ReceivedData;
+
+/// Everything a [`Transport`](super::Transport) reports, in one stream.
+#[derive(Clone)]
+pub enum TransportEvent {
+    /// A valid announce was received.
+    AnnounceReceived(Box<AnnounceEvent>),
+    /// A new or better path to `destination` was learned.
+    PathDiscovered {
+        destination: AddressHash,
+        hops: u8,
+        iface: AddressHash,
+    },
+    /// The path to `destination` was removed from the path table.
+    PathLost { destination: AddressHash },
+    LinkActivated {
+        id: LinkId,
+        destination: AddressHash,
+    },
+    LinkClosed {
+        id: LinkId,
+        destination: AddressHash,
+    },
+    /// The outbound link `old` died and `new` took its place, see
+    /// [`LinkEvent::Reestablished`].
+    LinkReestablished {
+        old: LinkId,
+        new: LinkId,
+        destination: AddressHash,
+    },
+    InterfaceUp(AddressHash),
+    InterfaceDown(AddressHash),
+    /// An interface stopped taking packets, see
+    /// [`InterfaceEvent::Stalled`].
+    InterfaceStalled(AddressHash),
+    /// A data packet for one of the local destinations was received.
+    DataReceived(Box<ReceivedData>),
+}
+
+impl TransportEvent {
+    /// Destination the event is about, if any.
+    pub fn destination(&self) -> Option<&AddressHash> {
+        match self {
+            TransportEvent::AnnounceReceived(_) => None,
+            TransportEvent::PathDiscovered { destination, .. } => Some(destination),
+            TransportEvent::PathLost { destination } => Some(destination),
+            TransportEvent::LinkActivated { destination, .. } => Some(destination),
+            TransportEvent::LinkClosed { destination, .. } => Some(destination),
+            TransportEvent::LinkReestablished { destination, .. } => Some(destination),
+            TransportEvent::InterfaceUp(_)
+            | TransportEvent::InterfaceDown(_)
+            | TransportEvent::InterfaceStalled(_) => None,
+            TransportEvent::DataReceived(data) => Some(&data.destination),
+        }
+    }
+
+    pub fn is_announce(&self) -> bool {
+        matches!(self, TransportEvent::AnnounceReceived(_))
+    }
+
+    pub fn is_path(&self) -> bool {
+        matches!(
+            self,
+            TransportEvent::PathDiscovered { .. } | TransportEvent::PathLost { .. }
+        )
+    }
+
+    pub fn is_link(&self) -> bool {
+        matches!(
+            self,
+            TransportEvent::LinkActivated { .. }
+                | TransportEvent::LinkClosed { .. }
+                | TransportEvent::LinkReestablished { .. }
+        )
+    }
+
+    pub fn is_interface(&self) -> bool {
+        matches!(
+            self,
+            TransportEvent::InterfaceUp(_)
+                | TransportEvent::InterfaceDown(_)
+                | TransportEvent::InterfaceStalled(_)
+        )
+    }
+
+    pub fn is_data(&self) -> bool {
+        matches!(self, TransportEvent::DataReceived(_))
+    }
+
+    fn from_link_event(event: LinkEventData) -> Option<Self> {
+        match event.event {
+            LinkEvent::Activated => Some(TransportEvent::LinkActivated {
+                id: event.id,
+                destination: event.address_hash,
+            }),
+            LinkEvent::Closed => Some(TransportEvent::LinkClosed {
+                id: event.id,
+                destination: event.address_hash,
+            }),
+            LinkEvent::Reestablished { old, new } => Some(TransportEvent::LinkReestablished {
+                old,
+                new,
+                destination: event.address_hash,
+            }),
+            _ => None,
+        }
+    }
+}
+
+type EventFilter = Box<dyn Fn(&TransportEvent) -> bool + Send + Sync>;
+
+/// Receiver of the transport events which pass a filter.
+pub struct EventSubscription {
+    rx: broadcast::Receiver<TransportEvent>,
+    filter: EventFilter,
+}
+
+impl EventSubscription {
+    pub(super) fn new(rx: broadcast::Receiver<TransportEvent>, filter: EventFilter) -> Self {
+        Self { rx, filter }
+    }
+
+    /// Waits for the next matching event. Like a broadcast receiver it
+    /// returns [`RecvError::Lagged`] if events were missed, after which the
+    /// subscription can be used further.
+    pub async fn recv(&mut self) -> Result<TransportEvent, RecvError> {
+        loop {
+            let event = self.rx.recv().await?;
+            if (self.filter)(&event) {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Latest announces of distinct destinations, replayed to subscribers of
+/// [`Transport::recv_announces_with_replay`](super::Transport::recv_announces_with_replay).
+pub(super) struct AnnounceReplay {
+    capacity: usize,
+    announces: VecDeque<(AddressHash, AnnounceEvent)>,
+}
+
+impl AnnounceReplay {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            announces: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Remembers an announce, an older one of the same destination is
+    /// replaced and the oldest one dropped if the buffer is full.
+    pub(super) fn push(&mut self, destination: AddressHash, event: AnnounceEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.announces.retain(|(known, _)| *known != destination);
+        if self.announces.len() == self.capacity {
+            self.announces.pop_front();
+        }
+        self.announces.push_back((destination, event));
+    }
+
+    fn events(&self) -> VecDeque<AnnounceEvent> {
+        self.announces.iter().map(|(_, event)| event.clone()).collect()
+    }
+}
+
+/// Receiver of announces which yields the buffered announces of an
+/// [`AnnounceReplay`] before the ones received after subscribing.
+pub struct AnnounceSubscription {
+    replay: VecDeque<AnnounceEvent>,
+    rx: broadcast::Receiver<AnnounceEvent>,
+}
+
+impl AnnounceSubscription {
+    pub(super) fn new(replay: &AnnounceReplay, rx: broadcast::Receiver<AnnounceEvent>) -> Self {
+        Self {
+            replay: replay.events(),
+            rx,
+        }
+    }
+
+    /// Waits for the next announce, oldest replayed ones first. Errors as
+    /// a broadcast receiver does.
+    pub async fn recv(&mut self) -> Result<AnnounceEvent, RecvError> {
+        match self.replay.pop_front() {
+            Some(event) => Ok(event),
+            None => self.rx.recv().await,
+        }
+    }
+}
+
+/// Feeds the link and interface events into the transport event stream.
+pub(super) async fn forward_events(
+    mut link_in_events: broadcast::Receiver<LinkEventData>,
+    mut link_out_events: broadcast::Receiver<LinkEventData>,
+    mut iface_events: broadcast::Receiver<InterfaceEvent>,
+    events_tx: broadcast::Sender<TransportEvent>,
+    cancel: CancellationToken,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = cancel.cancelled() => break,
+            event = link_in_events.recv() => match event {
+                Ok(event) => TransportEvent::from_link_event(event),
+                Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => break,
+            },
+            event = link_out_events.recv() => match event {
+                Ok(event) => TransportEvent::from_link_event(event),
+                Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => break,
+            },
+            event = iface_events.recv() => match event {
+                Ok(InterfaceEvent::Up(address)) => Some(TransportEvent::InterfaceUp(address)),
+                Ok(InterfaceEvent::Down(address)) => Some(TransportEvent::InterfaceDown(address)),
+                Ok(InterfaceEvent::Stalled(address)) => {
+                    Some(TransportEvent::InterfaceStalled(address))
+                }
+                Ok(InterfaceEvent::State(..)) => None,
+                Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if let Some(event) = event {
+            let _ = events_tx.send(event);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use alloc::collections::{BTreeMap, VecDeque};
+
+use core::time::Duration;
+
+use crate::hash::AddressHash;
+use crate::runtime::Instant;
+
+/// Times of the link requests local destinations accepted recently, to
+/// enforce the request rate of their [`LinkLimits`](crate::destination::LinkLimits).
+pub struct LinkRequestLimits {
+    accepted: BTreeMap<AddressHash, VecDeque<Instant>>,
+}
+
+impl LinkRequestLimits {
+    pub fn new() -> Self {
+        Self { accepted: BTreeMap::new() }
+    }
+
+    /// Returns whether `destination` may accept another link request at
+    /// `now` if it accepts at most `max` within `interval`, and records it if
+    /// so.
+    pub fn accept(
+        &mut self,
+        destination: &AddressHash,
+        (max, interval): (u32, Duration),
+        now: Instant,
+    ) -> bool {
+        let accepted = self.accepted.entry(*destination).or_default();
+
+        while accepted.front().is_some_and(|time| now.duration_since(*time) >= interval) {
+            accepted.pop_front();
+        }
+
+        if accepted.len() >= max as usize {
+            return false;
+        }
+
+        accepted.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_within_interval() {
+        let mut limits = LinkRequestLimits::new();
+        let destination = AddressHash::new_from_slice(&[1u8; 16]);
+        let other = AddressHash::new_from_slice(&[2u8; 16]);
+        let rate = (2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(limits.accept(&destination, rate, start));
+        assert!(limits.accept(&destination, rate, start + Duration::from_secs(1)));
+        assert!(!limits.accept(&destination, rate, start + Duration::from_secs(2)));
+        assert!(limits.accept(&other, rate, start + Duration::from_secs(2)));
+
+        // The first request leaves the interval
+        assert!(limits.accept(&destination, rate, start + Duration::from_secs(10)));
+        assert!(!limits.accept(&destination, rate, start + Duration::from_secs(10)));
+    }
+}
@@ -0,0 +1,82 @@
+//! Lifecycle of outbound links.
+//!
+//! By default every outbound link follows the [`TimerConfig`] of the
+//! transport. Destinations with needs of their own get a [`LinkPolicy`]
+//! with [`Transport::set_link_policy`]: a chat peer may keep its link for a
+//! long idle time with sparse keep-alives, while a telemetry collector drops
+//! links soon after the readings stop.
+//!
+//! [`Transport::set_link_policy`]: super::Transport::set_link_policy
+
+use core::time::Duration;
+
+use super::TimerConfig;
+
+/// How a transport treats its outbound links to a destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkPolicy {
+    /// Time without traffic after which an active link is stale.
+    pub stale: Duration,
+    /// Time a stale link is kept before it is closed.
+    pub close: Duration,
+    /// Restart stale links after this time without traffic instead of
+    /// closing them.
+    pub restart: Option<Duration>,
+    /// Interval at which an unanswered link request is repeated.
+    pub repeat: Duration,
+    /// Interval of the keep-alives sent over active links, `None` for none.
+    pub keep_alive: Option<Duration>,
+    /// Replace a link which died, closed by the peer or after going stale,
+    /// with a new one. Once that is active, it is reported as
+    /// [`LinkEvent::Reestablished`].
+    ///
+    /// [`LinkEvent::Reestablished`]: crate::destination::link::LinkEvent::Reestablished
+    pub reestablish: bool,
+}
+
+impl LinkPolicy {
+    /// The policy of links to destinations without one of their own, which
+    /// restarts stale links if `restart` is set.
+    pub fn from_timers(timers: &TimerConfig, restart: bool) -> Self {
+        Self {
+            stale: timers.out_link_stale,
+            close: timers.out_link_close,
+            restart: restart.then_some(timers.out_link_restart),
+            repeat: timers.out_link_repeat,
+            keep_alive: Some(timers.out_link_keep),
+            reestablish: false,
+        }
+    }
+
+    /// Closes links after `idle` without traffic and doesn't keep them
+    /// alive.
+    pub fn close_when_idle(self, idle: Duration) -> Self {
+        Self {
+            stale: idle,
+            close: Duration::ZERO,
+            restart: None,
+            keep_alive: None,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_follow_the_timers() {
+        let timers = TimerConfig::default();
+
+        let policy = LinkPolicy::from_timers(&timers, false);
+        assert_eq!(policy.stale, timers.out_link_stale);
+        assert_eq!(policy.keep_alive, Some(timers.out_link_keep));
+        assert_eq!(policy.restart, None);
+        assert_eq!(LinkPolicy::from_timers(&timers, true).restart, Some(timers.out_link_restart));
+
+        let idle = policy.close_when_idle(Duration::from_secs(30));
+        assert_eq!((idle.stale, idle.close, idle.keep_alive), (Duration::from_secs(30), Duration::ZERO, None));
+        assert_eq!(idle.repeat, timers.out_link_repeat);
+    }
+}
@@ -1,33 +1,26 @@
 use std::collections::HashMap;
-use tokio::time::{Duration, Instant};
+use std::time::Duration;
 
-use crate::destination::link::LinkId;
+use super::TableStats;
+use crate::destination::link::{check_link_proof_len, validate_link_proof, LinkId, ProofError};
 use crate::hash::AddressHash;
-use crate::packet::{Header, HeaderType, IfacFlag, Packet};
+use crate::identity::Identity;
+use crate::packet::Packet;
+use crate::runtime::Instant;
 
 pub struct LinkEntry {
+    /// Destination the link request was sent to.
+    pub destination: AddressHash,
     pub proof_timeout: Instant,
-    pub next_hop: AddressHash,
+    pub next_hop_iface: AddressHash,
     pub received_from: AddressHash,
-    pub original_destination: AddressHash,
     pub remaining_hops: u8,
     pub validated: bool,
 }
 
 fn send_backwards(packet: &Packet, entry: &LinkEntry) -> (Packet, AddressHash) {
-    let propagated = Packet {
-        header: Header {
-            ifac_flag: IfacFlag::Open,
-            header_type: HeaderType::Type2,
-            hops: packet.header.hops + 1,
-            .. packet.header
-        },
-        ifac: None,
-        destination: packet.destination,
-        transport: Some(entry.next_hop),
-        context: packet.context,
-        data: packet.data,
-    };
+    let mut propagated = *packet;
+    propagated.header.hops = propagated.header.hops.saturating_add(1);
 
     (propagated, entry.received_from)
 }
@@ -42,9 +35,8 @@ impl LinkTable {
     pub fn add(
         &mut self,
         link_request: &Packet,
-        destination: AddressHash,
         received_from: AddressHash,
-        next_hop: AddressHash,
+        next_hop_iface: AddressHash,
     ) {
         let link_id = LinkId::from(link_request);
 
@@ -55,10 +47,10 @@ impl LinkTable {
         let now = Instant::now();
 
         let entry = LinkEntry {
+            destination: link_request.destination,
             proof_timeout: now + Duration::from_secs(600), // TODO
-            next_hop,
+            next_hop_iface,
             received_from,
-            original_destination: destination,
             remaining_hops: 0,
             validated: false
         };
@@ -66,24 +58,67 @@ impl LinkTable {
         self.0.insert(link_id, entry);
     }
 
-    pub fn original_destination(&self, link_id: &LinkId) -> Option<AddressHash> {
-        self.0.get(link_id).filter(|e| e.validated).map(|e| e.original_destination)
+    pub fn stats(&self) -> TableStats {
+        TableStats::of_map::<LinkId, LinkEntry>(self.0.len())
     }
 
-    pub fn handle_keepalive(&self, packet: &Packet) -> Option<(Packet, AddressHash)> {
-        self.0.get(&packet.destination).map(|entry| send_backwards(packet, entry))
+    pub fn contains(&self, link_id: &LinkId) -> bool {
+        self.0.contains_key(link_id)
     }
 
-    pub fn handle_proof(&mut self, proof: &Packet) -> Option<(Packet, AddressHash)> {
-        match self.0.get_mut(&proof.destination) {
-            Some(entry) => {
-                entry.remaining_hops = proof.header.hops;
-                entry.validated = true;
+    /// Forwards traffic of a link routed through this node, e.g. link data,
+    /// keep-alives or proofs of link packets. Like Python, the packet is
+    /// passed on unchanged apart from the hop count, whatever its context,
+    /// and the direction follows from the interface it was received on.
+    pub fn forward(&self, packet: &Packet, iface: AddressHash) -> Option<(Packet, AddressHash)> {
+        let entry = self.0.get(&packet.destination)?;
+
+        let out_iface = if iface == entry.next_hop_iface {
+            entry.received_from
+        } else if iface == entry.received_from {
+            entry.next_hop_iface
+        } else {
+            return None;
+        };
+
+        let mut forwarded = *packet;
+        forwarded.header.hops = forwarded.header.hops.saturating_add(1);
+
+        Some((forwarded, out_iface))
+    }
 
-                Some(send_backwards(proof, entry))
-            },
-            None => None
+    /// Destination of a link routed through this node.
+    pub fn destination(&self, link_id: &LinkId) -> Option<AddressHash> {
+        self.0.get(link_id).map(|entry| entry.destination)
+    }
+
+    /// Passes the proof of a link routed through this node back towards the
+    /// initiator. The proof has to come from the direction the request was
+    /// sent to and, if the `identity` of the destination is known, carry
+    /// its signature. Returns `None` for links which aren't routed here.
+    pub fn handle_proof(
+        &mut self,
+        proof: &Packet,
+        iface: AddressHash,
+        identity: Option<&Identity>,
+    ) -> Result<Option<(Packet, AddressHash)>, ProofError> {
+        let Some(entry) = self.0.get_mut(&proof.destination) else {
+            return Ok(None);
+        };
+
+        if iface != entry.next_hop_iface {
+            return Err(ProofError::WrongInterface);
+        }
+
+        match identity {
+            Some(identity) => validate_link_proof(identity, &proof.destination, proof).map(|_| ())?,
+            None => check_link_proof_len(proof)?,
         }
+
+        entry.remaining_hops = proof.header.hops;
+        entry.validated = true;
+
+        Ok(Some(send_backwards(proof, entry)))
     }
 
     pub fn remove_stale(&mut self) {
@@ -0,0 +1,108 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::{Mutex, Notify};
+use tokio_util::sync::CancellationToken;
+
+use crate::iface::{InterfaceManager, TxMessage};
+
+/// Packets the outbox holds by default, see
+/// [`TransportConfig::set_outbox_capacity`](super::TransportConfig::set_outbox_capacity).
+pub const DEFAULT_OUTBOX_CAPACITY: usize = 256;
+
+/// Packets the transport handler sent, waiting to be passed to the
+/// interfaces.
+///
+/// Handlers only queue packets while they hold the handler lock. Interfaces
+/// may wait for room in their queues, and the handler must stay available
+/// while they do, so the packets are passed on after the lock is released:
+/// by [`Outbox::flush`] in API calls which return once their packets are
+/// on the way, by [`Outbox::run`] for everything else.
+///
+/// The outbox holds at most `capacity` packets the handler sends on its own.
+/// While interfaces don't take packets it fills up, further packets are
+/// dropped and counted like the interface queues drop packets for stalled
+/// interfaces. Packets of API calls are always queued, as the calls flush
+/// the outbox and wait for the interfaces before they return.
+pub(crate) struct Outbox {
+    queue: std::sync::Mutex<VecDeque<TxMessage>>,
+    capacity: usize,
+    dropped: AtomicU64,
+    /// Held while passing packets on, so they leave in the order they were
+    /// queued.
+    flushing: Mutex<()>,
+    notify: Notify,
+}
+
+impl Outbox {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            queue: std::sync::Mutex::new(VecDeque::new()),
+            capacity,
+            dropped: AtomicU64::new(0),
+            flushing: Mutex::new(()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Queues `message` unless the outbox is full, returns whether it was
+    /// queued.
+    pub(crate) fn push(&self, message: TxMessage) -> bool {
+        {
+            let mut queue = self.queue.lock().expect("outbox lock");
+            if queue.len() >= self.capacity {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                log::debug!("outbox: full, dropped packet {}", message.packet);
+                return false;
+            }
+            queue.push_back(message);
+        }
+        self.notify.notify_one();
+
+        true
+    }
+
+    /// Queues `message` even if the outbox is full, for callers which flush
+    /// it right after.
+    pub(crate) fn push_flushed(&self, message: TxMessage) {
+        self.queue.lock().expect("outbox lock").push_back(message);
+        self.notify.notify_one();
+    }
+
+    /// Packets dropped because the outbox was full.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Passes all queued packets to the interfaces. The interface manager is
+    /// only locked to pick the interfaces, not while waiting for them.
+    pub(crate) async fn flush(&self, iface_manager: &Mutex<InterfaceManager>) {
+        let _flushing = self.flushing.lock().await;
+
+        loop {
+            let message = self.queue.lock().expect("outbox lock").pop_front();
+            match message {
+                Some(message) => {
+                    let delivery = iface_manager.lock().await.delivery(message);
+                    delivery.send().await;
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Passes packets on as they are queued, until `cancel` is cancelled.
+    pub(crate) async fn run(
+        self: Arc<Self>,
+        iface_manager: Arc<Mutex<InterfaceManager>>,
+        cancel: CancellationToken,
+    ) {
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = self.notify.notified() => self.flush(&iface_manager).await,
+            }
+        }
+    }
+}
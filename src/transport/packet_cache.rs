@@ -1,29 +1,41 @@
 use std::{
     cmp::min,
     collections::HashMap,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
-use crate::{hash::Hash, packet::Packet};
+use super::TableStats;
+use crate::{hash::Hash, packet::Packet, runtime::Instant};
 
 pub struct PacketTrack {
     pub time: Instant,
     pub min_hops: u8,
 }
 
+/// Remembers packet hashes to detect duplicates.
+///
+/// Packets are keyed on [`Packet::hash`], which leaves out the hop count and
+/// transport id, so the same packet arriving over several paths is detected.
+/// A packet is a duplicate if its hash was first seen less than `window` ago.
 pub struct PacketCache {
     map: HashMap<Hash, PacketTrack>,
     remove_cache: Vec<Hash>,
+    window: Duration,
 }
 
 impl PacketCache {
-    pub fn new() -> Self {
+    pub fn new(window: Duration) -> Self {
         Self {
             map: HashMap::new(),
             remove_cache: Vec::new(),
+            window,
         }
     }
 
+    pub fn stats(&self) -> TableStats {
+        TableStats::of_map::<Hash, PacketTrack>(self.map.len())
+    }
+
     pub fn release(&mut self, duration: Duration) {
         for entry in &self.map {
             if entry.1.time.elapsed() > duration {
@@ -44,8 +56,7 @@ impl PacketCache {
         let mut is_new_packet = false;
 
         let track = self.map.get_mut(&hash);
-        if let Some(track) = track {
-            track.time = Instant::now();
+        if let Some(track) = track.filter(|track| track.time.elapsed() <= self.window) {
             track.min_hops = min(packet.header.hops, track.min_hops);
         } else {
             is_new_packet = true;
@@ -1,9 +1,6 @@
 use alloc::collections::{BTreeSet, BTreeMap};
 
-use rand_core::OsRng;
-
-use tokio::time::Instant;
-
+use crate::crypt::rng::SharedRng;
 use crate::destination::DestinationName;
 use crate::destination::PlainInputDestination;
 use crate::hash::AddressHash;
@@ -12,12 +9,14 @@ use crate::identity::EmptyIdentity;
 use crate::packet::DestinationType;
 use crate::packet::Header;
 use crate::packet::HeaderType;
+use crate::packet::ContextFlag;
 use crate::packet::IfacFlag;
 use crate::packet::Packet;
 use crate::packet::PacketContext;
 use crate::packet::PacketDataBuffer;
 use crate::packet::PacketType;
 use crate::packet::PropagationType;
+use crate::runtime::Instant;
 
 pub fn create_path_request_destination() -> PlainInputDestination {
     PlainInputDestination::new(
@@ -28,8 +27,8 @@ pub fn create_path_request_destination() -> PlainInputDestination {
 
 pub type TagBytes = Vec<u8>;
 
-pub fn create_random_tag() -> TagBytes {
-    AddressHash::new_from_rand(OsRng).as_slice().into()
+pub fn create_random_tag(rng: &SharedRng) -> TagBytes {
+    AddressHash::new_from_rand(rng).as_slice().into()
 }
 
 pub struct PathRequest {
@@ -82,16 +81,18 @@ pub struct PathRequests {
     transport_id: Option<AddressHash>,
     controlled_destination: PlainInputDestination,
     discovery: BTreeMap<AddressHash, Instant>,
+    rng: SharedRng,
 }
 
 impl PathRequests {
-    pub fn new(name: &str, transport_id: Option<AddressHash>) -> Self {
+    pub fn new(name: &str, transport_id: Option<AddressHash>, rng: SharedRng) -> Self {
         Self {
             cache: BTreeSet::new(),
             name: name.into(),
             transport_id,
             controlled_destination: create_path_request_destination(),
             discovery: BTreeMap::new(),
+            rng,
         }
     }
 
@@ -127,7 +128,7 @@ impl PathRequests {
             data.safe_write(transport_id.as_slice());
         }
 
-        data.safe_write(tag.unwrap_or_else(create_random_tag).as_slice());
+        data.safe_write(tag.unwrap_or_else(|| create_random_tag(&self.rng)).as_slice());
 
         let destination = self.controlled_destination.desc.address_hash;
 
@@ -135,6 +136,7 @@ impl PathRequests {
             header: Header {
                 ifac_flag: IfacFlag::Open,
                 header_type: HeaderType::Type1,
+                context_flag: ContextFlag::Unset,
                 propagation_type: PropagationType::Broadcast,
                 destination_type: DestinationType::Plain,
                 packet_type: PacketType::Data,
@@ -195,11 +197,13 @@ impl PathRequests {
 
 #[cfg(test)]
 mod tests {
+    use rand_core::OsRng;
+
     use super::*;
 
     #[test]
     fn path_request_roundtrip() {
-        let mut testee = PathRequests::new("", None);
+        let mut testee = PathRequests::new("", None, SharedRng::default());
 
         let dest = AddressHash::new_from_rand(OsRng);
 
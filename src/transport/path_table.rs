@@ -1,69 +1,305 @@
+use std::cmp::Ordering;
+use std::mem::size_of;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
+use super::TableStats;
 use crate::{
     hash::AddressHash,
-    packet::{DestinationType, Header, HeaderType, IfacFlag, Packet, PacketType},
+    iface::InterfaceMode,
+    packet::{DestinationType, Header, HeaderType, IfacFlag, Packet, PacketType, PropagationType},
+    runtime::Instant,
 };
 
+#[derive(Debug, Clone)]
 pub struct PathEntry {
     pub received_from: AddressHash,
     pub hops: u8,
     pub iface: AddressHash,
+    /// Mode of the interface the announce was received on.
+    pub mode: InterfaceMode,
+    /// Bitrate of that interface, if known.
+    pub bitrate: Option<u64>,
+    /// When the announce establishing the path was received.
+    pub announced: Instant,
+}
+
+/// Decides which of several known paths to a destination is used.
+pub trait PathPolicy: Send + Sync {
+    /// Orders two paths to the same destination, the better one first.
+    /// Of equal paths the one known longer is used.
+    fn compare(&self, a: &PathEntry, b: &PathEntry) -> Ordering;
+}
+
+/// Prefers the fewest hops, then interfaces in a stable mode, then higher
+/// bitrates. With `reroute_eager` a fresher announce wins among paths
+/// which are equal otherwise.
+pub struct DefaultPathPolicy {
+    pub reroute_eager: bool,
+}
+
+impl DefaultPathPolicy {
+    fn mode_rank(mode: InterfaceMode) -> u8 {
+        match mode {
+            InterfaceMode::Full | InterfaceMode::PointToPoint | InterfaceMode::Gateway => 0,
+            InterfaceMode::AccessPoint => 1,
+            InterfaceMode::Roaming | InterfaceMode::Boundary => 2,
+        }
+    }
+}
+
+impl PathPolicy for DefaultPathPolicy {
+    fn compare(&self, a: &PathEntry, b: &PathEntry) -> Ordering {
+        let order = a
+            .hops
+            .cmp(&b.hops)
+            .then(Self::mode_rank(a.mode).cmp(&Self::mode_rank(b.mode)))
+            .then(b.bitrate.unwrap_or(0).cmp(&a.bitrate.unwrap_or(0)));
+
+        if self.reroute_eager {
+            order.then(b.announced.cmp(&a.announced))
+        } else {
+            order
+        }
+    }
+}
+
+/// Paths to one destination, at most one per interface.
+struct Paths {
+    candidates: Vec<PathEntry>,
+    selected: usize,
+}
+
+impl Paths {
+    /// Selects the best candidate. The one at `current` stays selected
+    /// unless another one is better.
+    fn select(&mut self, policy: &dyn PathPolicy, current: usize) {
+        self.selected = (0..self.candidates.len()).fold(current, |best, index| {
+            if policy.compare(&self.candidates[index], &self.candidates[best]) == Ordering::Less {
+                index
+            } else {
+                best
+            }
+        });
+    }
 }
 
 pub struct PathTable {
-    map: HashMap<AddressHash, PathEntry>,
-    reroute_eager: bool,
+    map: HashMap<AddressHash, Paths>,
+    /// Paths set by hand, they take precedence over learned ones.
+    pinned: HashMap<AddressHash, PathEntry>,
+    /// Paths known in advance, used until one is learned.
+    statics: HashMap<AddressHash, PathEntry>,
+    policy: Arc<dyn PathPolicy>,
 }
 
 impl PathTable {
     pub fn new(reroute_eager: bool) -> Self {
+        Self::with_policy(Arc::new(DefaultPathPolicy { reroute_eager }))
+    }
+
+    pub fn with_policy(policy: Arc<dyn PathPolicy>) -> Self {
         Self {
             map: HashMap::new(),
-            reroute_eager,
+            pinned: HashMap::new(),
+            statics: HashMap::new(),
+            policy,
         }
     }
 
+    /// Returns the selected path to `destination`, a pinned one if any.
+    pub fn stats(&self) -> TableStats {
+        let candidates = self.map.values().map(|paths| paths.candidates.len()).sum::<usize>();
+
+        TableStats::of_map::<AddressHash, Paths>(self.map.len())
+            .with_heap(candidates * size_of::<PathEntry>())
+            + TableStats::of_map::<AddressHash, PathEntry>(self.pinned.len())
+            + TableStats::of_map::<AddressHash, PathEntry>(self.statics.len())
+    }
+
     pub fn get(&self, destination: &AddressHash) -> Option<&PathEntry> {
-        self.map.get(destination)
+        self.pinned
+            .get(destination)
+            .or_else(|| {
+                self.map
+                    .get(destination)
+                    .map(|paths| &paths.candidates[paths.selected])
+            })
+            .or_else(|| self.statics.get(destination))
+    }
+
+    /// Uses `entry` as the path to `destination` regardless of announces
+    /// until it is unpinned. Pinned paths neither expire nor go away with
+    /// their interface.
+    pub fn pin(&mut self, destination: AddressHash, entry: PathEntry) {
+        log::info!(
+            "path to {} is pinned to {} on iface {}",
+            destination,
+            entry.received_from,
+            entry.iface
+        );
+        self.pinned.insert(destination, entry);
+    }
+
+    /// Removes a pinned path, the paths learned from announces are used
+    /// again.
+    pub fn unpin(&mut self, destination: &AddressHash) -> Option<PathEntry> {
+        self.pinned.remove(destination)
+    }
+
+    /// Uses `entry` as the path to `destination` while no path to it is
+    /// learned from announces. Static paths neither expire nor go away with
+    /// their interface.
+    pub fn add_static(&mut self, destination: AddressHash, entry: PathEntry) {
+        log::info!(
+            "static path to {} over {} hops on iface {}",
+            destination,
+            entry.hops,
+            entry.iface
+        );
+        self.statics.insert(destination, entry);
+    }
+
+    /// Removes a static path.
+    pub fn remove_static(&mut self, destination: &AddressHash) -> Option<PathEntry> {
+        self.statics.remove(destination)
+    }
+
+    /// Returns all paths to `destination` learned from announces.
+    pub fn paths(&self, destination: &AddressHash) -> &[PathEntry] {
+        self.map
+            .get(destination)
+            .map(|paths| paths.candidates.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the selected path of every known destination.
+    pub fn selected(&self) -> impl Iterator<Item = (&AddressHash, &PathEntry)> {
+        let learned = self
+            .map
+            .iter()
+            .filter(|(destination, _)| !self.pinned.contains_key(destination))
+            .map(|(destination, paths)| (destination, &paths.candidates[paths.selected]));
+        let statics = self.statics.iter().filter(|(destination, _)| {
+            !self.pinned.contains_key(destination) && !self.map.contains_key(destination)
+        });
+
+        self.pinned.iter().chain(learned).chain(statics)
     }
 
     pub fn next_hop_full(&self, destination: &AddressHash) -> Option<(AddressHash, AddressHash)> {
-        self.map.get(destination).map(|entry| (entry.received_from, entry.iface))
+        self.get(destination).map(|entry| (entry.received_from, entry.iface))
     }
 
+    /// Records the path of an announce received on `iface` and returns
+    /// whether the selected path to the destination changed. It never
+    /// changes while the path is pinned.
     pub fn handle_announce(
         &mut self,
         announce: &Packet,
         transport_id: Option<AddressHash>,
         iface: AddressHash,
-    ) {
+        mode: InterfaceMode,
+        bitrate: Option<u64>,
+    ) -> bool {
         let hops = announce.header.hops + 1;
-
-        if let Some(existing_entry) = self.map.get(&announce.destination) {
-            if hops > existing_entry.hops {
-                return;
-            }
-            if !self.reroute_eager && hops == existing_entry.hops {
-                return;
-            }
-        }
-
         let received_from = transport_id.unwrap_or(announce.destination);
+
         let new_entry = PathEntry {
             received_from,
             hops,
             iface,
+            mode,
+            bitrate,
+            announced: Instant::now(),
         };
 
-        self.map.insert(announce.destination, new_entry);
+        let paths = self.map.entry(announce.destination).or_insert(Paths {
+            candidates: Vec::new(),
+            selected: 0,
+        });
+
+        let previous = paths
+            .candidates
+            .get(paths.selected)
+            .map(|entry| (entry.received_from, entry.hops, entry.iface));
+
+        match paths.candidates.iter_mut().find(|entry| entry.iface == iface) {
+            Some(entry) => *entry = new_entry,
+            None => paths.candidates.push(new_entry),
+        }
+
+        let current = previous.map(|_| paths.selected).unwrap_or(0);
+        paths.select(self.policy.as_ref(), current);
+
+        let selected = &paths.candidates[paths.selected];
+        if previous == Some((selected.received_from, selected.hops, selected.iface))
+            || self.pinned.contains_key(&announce.destination)
+        {
+            return false;
+        }
 
         log::info!(
             "{} is now reachable over {} hops through {}",
             announce.destination,
-            hops,
-            received_from,
+            selected.hops,
+            selected.received_from,
         );
+
+        true
+    }
+
+    /// Removes the paths learned from announces older than their lifetime,
+    /// `direct` for neighbours and `multi_hop` for all others. Returns the
+    /// destinations no path is left to.
+    pub fn expire(&mut self, now: Instant, direct: Duration, multi_hop: Duration) -> Vec<AddressHash> {
+        self.retain(|entry| {
+            let lifetime = if entry.hops <= 1 { direct } else { multi_hop };
+            now.duration_since(entry.announced) < lifetime
+        })
+    }
+
+    /// Removes the paths over `iface` and returns the destinations no path
+    /// is left to.
+    pub fn remove_iface(&mut self, iface: &AddressHash) -> Vec<AddressHash> {
+        self.retain(|entry| entry.iface != *iface)
+    }
+
+    fn retain<F: Fn(&PathEntry) -> bool>(&mut self, keep: F) -> Vec<AddressHash> {
+        let mut lost = Vec::new();
+
+        for (destination, paths) in self.map.iter_mut() {
+            let selected_iface = paths.candidates[paths.selected].iface;
+
+            paths.candidates.retain(&keep);
+
+            if paths.candidates.is_empty() {
+                lost.push(*destination);
+                continue;
+            }
+
+            let current = paths
+                .candidates
+                .iter()
+                .position(|entry| entry.iface == selected_iface)
+                .unwrap_or(0);
+            paths.select(self.policy.as_ref(), current);
+        }
+
+        for destination in &lost {
+            self.map.remove(destination);
+        }
+
+        // Pinned and static destinations stay reachable
+        lost.retain(|destination| {
+            !self.pinned.contains_key(destination) && !self.statics.contains_key(destination)
+        });
+        for destination in &lost {
+            log::info!("path to {} was lost", destination);
+        }
+
+        lost
     }
 
     pub fn handle_inbound_packet(
@@ -73,22 +309,32 @@ impl PathTable {
     ) -> (Packet, Option<AddressHash>) {
         let lookup = lookup.unwrap_or(original_packet.destination);
 
-        let entry = match self.map.get(&lookup) {
+        let entry = match self.get(&lookup) {
             Some(entry) => entry,
             None => return (*original_packet, None),
         };
 
+        // The last hop delivers the packet without transport id, Python
+        // destinations only accept link requests addressed to them that way.
+        // Static paths may know the distance but no relay.
+        let (header_type, propagation_type, transport) = if entry.hops > 1 && entry.received_from != lookup {
+            (HeaderType::Type2, PropagationType::Transport, Some(entry.received_from))
+        } else {
+            (HeaderType::Type1, PropagationType::Broadcast, None)
+        };
+
         (
             Packet {
                 header: Header {
                     ifac_flag: IfacFlag::Open,
-                    header_type: HeaderType::Type2,
+                    header_type,
+                    propagation_type,
                     hops: original_packet.header.hops + 1,
                     .. original_packet.header
                 },
                 ifac: None,
                 destination: original_packet.destination,
-                transport: Some(entry.received_from),
+                transport,
                 context: original_packet.context,
                 data: original_packet.data,
             },
@@ -111,7 +357,7 @@ impl PathTable {
             return (*original_packet, None);
         }
 
-        let entry = match self.map.get(&original_packet.destination) {
+        let entry = match self.get(&original_packet.destination) {
             Some(entry) => entry,
             None => return (*original_packet, None),
         };
@@ -120,6 +366,7 @@ impl PathTable {
             Packet {
                 header: Header {
                     header_type: HeaderType::Type2,
+                    propagation_type: PropagationType::Transport,
                     .. original_packet.header
                 },
                 ifac: original_packet.ifac,
@@ -132,3 +379,162 @@ impl PathTable {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announce(destination: AddressHash, hops: u8) -> Packet {
+        let mut packet = Packet {
+            destination,
+            ..Default::default()
+        };
+        packet.header.packet_type = PacketType::Announce;
+        packet.header.hops = hops;
+        packet
+    }
+
+    #[test]
+    fn select_best_path() {
+        let destination = AddressHash::new([1; 16]);
+        let (iface_a, iface_b, iface_c) = (
+            AddressHash::new([2; 16]),
+            AddressHash::new([3; 16]),
+            AddressHash::new([4; 16]),
+        );
+
+        let mut table = PathTable::new(false);
+        assert!(table.handle_announce(&announce(destination, 3), None, iface_a, InterfaceMode::Full, None));
+
+        // Fewer hops win, a longer path is kept as alternative
+        assert!(table.handle_announce(&announce(destination, 1), None, iface_b, InterfaceMode::Full, None));
+        assert!(!table.handle_announce(&announce(destination, 5), None, iface_c, InterfaceMode::Full, None));
+        assert_eq!(table.get(&destination).unwrap().iface, iface_b);
+        assert_eq!(table.paths(&destination).len(), 3);
+
+        // Equal hops: stable mode, then bitrate
+        assert!(!table.handle_announce(&announce(destination, 1), None, iface_c, InterfaceMode::Roaming, None));
+        assert!(table.handle_announce(&announce(destination, 1), None, iface_a, InterfaceMode::Full, Some(1_000_000)));
+        assert_eq!(table.get(&destination).unwrap().iface, iface_a);
+
+        // A fresher announce alone doesn't reroute
+        assert!(!table.handle_announce(&announce(destination, 1), None, iface_b, InterfaceMode::Full, Some(1_000_000)));
+        assert_eq!(table.get(&destination).unwrap().iface, iface_a);
+
+        // Unless the policy prefers it
+        let mut eager = PathTable::new(true);
+        eager.handle_announce(&announce(destination, 1), None, iface_a, InterfaceMode::Full, None);
+        assert!(eager.handle_announce(&announce(destination, 1), None, iface_b, InterfaceMode::Full, None));
+        assert_eq!(eager.get(&destination).unwrap().iface, iface_b);
+    }
+
+    #[test]
+    fn expire_paths() {
+        let (near, far) = (AddressHash::new([1; 16]), AddressHash::new([5; 16]));
+        let (iface_a, iface_b) = (AddressHash::new([2; 16]), AddressHash::new([3; 16]));
+
+        let mut table = PathTable::new(false);
+        table.handle_announce(&announce(near, 0), None, iface_a, InterfaceMode::Full, None);
+        table.handle_announce(&announce(far, 2), None, iface_a, InterfaceMode::Full, None);
+        table.handle_announce(&announce(far, 4), None, iface_b, InterfaceMode::Full, None);
+
+        let now = Instant::now();
+        let (direct, multi_hop) = (Duration::from_secs(10), Duration::from_secs(60));
+
+        assert!(table.expire(now, direct, multi_hop).is_empty());
+        assert_eq!(table.expire(now + Duration::from_secs(20), direct, multi_hop), vec![near]);
+        assert!(table.get(&near).is_none());
+
+        // Losing the interface of the selected path falls back to another one
+        assert!(table.remove_iface(&iface_a).is_empty());
+        assert_eq!(table.get(&far).unwrap().iface, iface_b);
+        assert_eq!(table.remove_iface(&iface_b), vec![far]);
+        assert!(table.get(&far).is_none());
+    }
+
+    #[test]
+    fn custom_policy() {
+        struct PreferRoaming;
+
+        impl PathPolicy for PreferRoaming {
+            fn compare(&self, a: &PathEntry, b: &PathEntry) -> Ordering {
+                (b.mode == InterfaceMode::Roaming).cmp(&(a.mode == InterfaceMode::Roaming))
+            }
+        }
+
+        let destination = AddressHash::new([1; 16]);
+        let roaming = AddressHash::new([2; 16]);
+
+        let mut table = PathTable::with_policy(Arc::new(PreferRoaming));
+        table.handle_announce(&announce(destination, 0), None, AddressHash::new([3; 16]), InterfaceMode::Full, None);
+        table.handle_announce(&announce(destination, 4), None, roaming, InterfaceMode::Roaming, None);
+
+        assert_eq!(table.get(&destination).unwrap().iface, roaming);
+    }
+
+    #[test]
+    fn pinned_path_overrides_announces() {
+        let destination = AddressHash::new([1; 16]);
+        let (learned, pinned) = (AddressHash::new([2; 16]), AddressHash::new([3; 16]));
+        let relay = AddressHash::new([4; 16]);
+
+        let mut table = PathTable::new(false);
+        table.handle_announce(&announce(destination, 0), None, learned, InterfaceMode::Full, None);
+
+        table.pin(destination, PathEntry {
+            received_from: relay,
+            hops: 2,
+            iface: pinned,
+            mode: InterfaceMode::Full,
+            bitrate: None,
+            announced: Instant::now(),
+        });
+        assert_eq!(table.next_hop_full(&destination), Some((relay, pinned)));
+
+        // Better announces and lost interfaces don't move a pinned path
+        assert!(!table.handle_announce(&announce(destination, 0), None, pinned, InterfaceMode::Full, None));
+        assert!(table.remove_iface(&learned).is_empty());
+        assert!(table.remove_iface(&pinned).is_empty());
+        assert_eq!(table.next_hop_full(&destination), Some((relay, pinned)));
+        assert_eq!(table.selected().count(), 1);
+
+        assert!(table.unpin(&destination).is_some());
+        assert!(table.get(&destination).is_none());
+    }
+
+    #[test]
+    fn static_path_is_used_until_one_is_learned() {
+        let destination = AddressHash::new([1; 16]);
+        let (learned, fixed) = (AddressHash::new([2; 16]), AddressHash::new([3; 16]));
+
+        let mut table = PathTable::new(false);
+        table.add_static(destination, PathEntry {
+            received_from: destination,
+            hops: 3,
+            iface: fixed,
+            mode: InterfaceMode::Full,
+            bitrate: None,
+            announced: Instant::now(),
+        });
+        assert_eq!(table.next_hop_full(&destination), Some((destination, fixed)));
+        assert_eq!(table.selected().count(), 1);
+
+        // Without a relay the packet goes out as to a neighbour
+        let (packet, iface) = table.handle_inbound_packet(&Packet { destination, ..Default::default() }, None);
+        assert_eq!(iface, Some(fixed));
+        assert_eq!(packet.header.header_type, HeaderType::Type1);
+        assert!(packet.transport.is_none());
+
+        assert!(table.handle_announce(&announce(destination, 4), None, learned, InterfaceMode::Full, None));
+        assert_eq!(table.get(&destination).unwrap().iface, learned);
+        assert_eq!(table.selected().count(), 1);
+
+        // Losing the learned path falls back to the static one
+        assert!(table.remove_iface(&learned).is_empty());
+        assert!(table.expire(Instant::now() + Duration::from_secs(3600), Duration::ZERO, Duration::ZERO).is_empty());
+        assert_eq!(table.get(&destination).unwrap().iface, fixed);
+
+        assert!(table.remove_static(&destination).is_some());
+        assert!(table.get(&destination).is_none());
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// Packets the packet task handles in a row before it lets other tasks run,
+/// see [`TransportConfig::set_rx_budget`](super::TransportConfig::set_rx_budget).
+pub const DEFAULT_RX_BUDGET: usize = 32;
+
+/// Packets which hold the handler longer than this are logged.
+pub(crate) const SLOW_PACKET: Duration = Duration::from_millis(50);
+
+/// Durations measured for each packet, the longest and in total.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub max: Duration,
+    pub total: Duration,
+}
+
+impl LatencyStats {
+    fn record(&mut self, duration: Duration) {
+        self.max = self.max.max(duration);
+        self.total += duration;
+    }
+}
+
+/// How the packet task of a transport keeps up with the interfaces, see
+/// [`Transport::processing_stats`](super::Transport::processing_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessingStats {
+    /// Packets received from the interfaces and processed.
+    pub packets: u64,
+    /// Times the task used up its budget and yielded to the timers and
+    /// other tasks.
+    pub budget_yields: u64,
+    /// Time packets waited for the handler, which the timers, links and
+    /// the application lock as well.
+    pub lock_wait: LatencyStats,
+    /// Time packets held the handler while they were processed.
+    pub processing: LatencyStats,
+}
+
+impl ProcessingStats {
+    pub(crate) fn record(&mut self, lock_wait: Duration, processing: Duration) {
+        self.packets += 1;
+        self.lock_wait.record(lock_wait);
+        self.processing.record(processing);
+    }
+
+    /// Average time a packet waited for the handler.
+    pub fn mean_lock_wait(&self) -> Duration {
+        mean(self.lock_wait.total, self.packets)
+    }
+
+    /// Average time a packet held the handler.
+    pub fn mean_processing(&self) -> Duration {
+        mean(self.processing.total, self.packets)
+    }
+}
+
+fn mean(total: Duration, count: u64) -> Duration {
+    if count == 0 {
+        return Duration::ZERO;
+    }
+
+    Duration::from_nanos((total.as_nanos() / count as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_longest_and_mean_durations() {
+        let mut stats = ProcessingStats::default();
+        assert_eq!(stats.mean_processing(), Duration::ZERO);
+
+        stats.record(Duration::from_millis(1), Duration::from_millis(2));
+        stats.record(Duration::from_millis(3), Duration::from_millis(6));
+
+        assert_eq!(stats.packets, 2);
+        assert_eq!(stats.lock_wait.max, Duration::from_millis(3));
+        assert_eq!(stats.processing.max, Duration::from_millis(6));
+        assert_eq!(stats.mean_lock_wait(), Duration::from_millis(2));
+        assert_eq!(stats.mean_processing(), Duration::from_millis(4));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use super::TableStats;
+
+use crate::hash::AddressHash;
+use crate::packet::{DestinationType, Packet, PacketType};
+use crate::runtime::Instant;
+
+/// Data packets exchanged with one destination, see
+/// [`Transport::traffic_stats`](super::Transport::traffic_stats).
+///
+/// For local destinations these are the packets they received and the
+/// packets sent on their links, for remote destinations the ones sent to
+/// them directly or over a link. Bytes count the packet payload.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficStats {
+    pub packets_sent: u64,
+    pub bytes_sent: u64,
+    pub packets_received: u64,
+    pub bytes_received: u64,
+    /// When a packet was last sent or received.
+    pub last_activity: Option<Instant>,
+}
+
+impl TrafficStats {
+    fn sent(&mut self, packet: &Packet) {
+        self.packets_sent += 1;
+        self.bytes_sent += packet.data.len() as u64;
+        self.last_activity = Some(Instant::now());
+    }
+
+    fn received(&mut self, packet: &Packet) {
+        self.packets_received += 1;
+        self.bytes_received += packet.data.len() as u64;
+        self.last_activity = Some(Instant::now());
+    }
+}
+
+/// Traffic counters by destination.
+#[derive(Default)]
+pub struct TrafficTable {
+    stats: HashMap<AddressHash, TrafficStats>,
+    /// Destination each link of the transport belongs to. Links can't be
+    /// asked while packets are sent, they are locked at that time.
+    links: HashMap<AddressHash, AddressHash>,
+}
+
+impl TrafficTable {
+    pub fn add_link(&mut self, link_id: AddressHash, destination: AddressHash) {
+        self.links.insert(link_id, destination);
+    }
+
+    pub fn remove_link(&mut self, link_id: &AddressHash) {
+        self.links.remove(link_id);
+    }
+
+    fn destination(&self, packet: &Packet) -> Option<AddressHash> {
+        match packet.header.destination_type {
+            DestinationType::Link => self.links.get(&packet.destination).copied(),
+            DestinationType::Single => Some(packet.destination),
+            DestinationType::Group | DestinationType::Plain => None,
+        }
+    }
+
+    /// Counts a packet sent by the transport. Packets relayed for others
+    /// have hops and are skipped.
+    pub fn sent(&mut self, packet: &Packet) {
+        if packet.header.packet_type != PacketType::Data || packet.header.hops > 0 {
+            return;
+        }
+
+        if let Some(destination) = self.destination(packet) {
+            self.stats.entry(destination).or_default().sent(packet);
+        }
+    }
+
+    /// Counts a data packet delivered to a local destination or link.
+    pub fn received(&mut self, packet: &Packet) {
+        if let Some(destination) = self.destination(packet) {
+            self.stats.entry(destination).or_default().received(packet);
+        }
+    }
+
+    /// Stats of the counters, with the links mapped to their destination.
+    pub fn stats(&self) -> TableStats {
+        TableStats::of_map::<AddressHash, TrafficStats>(self.stats.len())
+            .with_heap(self.links.len() * 2 * size_of::<AddressHash>())
+    }
+
+    pub fn get(&self, destination: &AddressHash) -> Option<TrafficStats> {
+        self.stats.get(destination).copied()
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = (&AddressHash, &TrafficStats)> {
+        self.stats.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{PacketContext, PacketDataBuffer};
+
+    use super::*;
+
+    fn data_packet(destination: AddressHash, destination_type: DestinationType, data: &[u8]) -> Packet {
+        let mut packet = Packet {
+            destination,
+            context: PacketContext::None,
+            data: PacketDataBuffer::new_from_slice(data),
+            ..Default::default()
+        };
+        packet.header.destination_type = destination_type;
+        packet
+    }
+
+    #[test]
+    fn count_by_destination() {
+        let destination = AddressHash::new([1; 16]);
+        let link_id = AddressHash::new([2; 16]);
+
+        let mut table = TrafficTable::default();
+        table.add_link(link_id, destination);
+
+        table.sent(&data_packet(destination, DestinationType::Single, b"hello"));
+        table.received(&data_packet(link_id, DestinationType::Link, b"over link"));
+
+        // Relayed packets aren't ours
+        let mut relayed = data_packet(destination, DestinationType::Single, b"relayed");
+        relayed.header.hops = 2;
+        table.sent(&relayed);
+
+        let stats = table.get(&destination).unwrap();
+        assert_eq!((stats.packets_sent, stats.bytes_sent), (1, 5));
+        assert_eq!((stats.packets_received, stats.bytes_received), (1, 9));
+        assert!(stats.last_activity.is_some());
+
+        // Packets of unknown links can't be attributed
+        table.remove_link(&link_id);
+        table.received(&data_packet(link_id, DestinationType::Link, b"late"));
+        assert_eq!(table.get(&destination).unwrap().packets_received, 1);
+        assert_eq!(table.all().count(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::size_of,
+    time::Duration,
+};
+
+use sha2::Digest;
+
+use super::TableStats;
+use crate::{hash::Hash, packet::Packet, runtime::Instant};
+
+/// How many verification results are kept at most.
+pub const MAX_VERIFIED_ANNOUNCES: usize = 4096;
+
+/// Remembers the outcome of announce signature verifications.
+///
+/// Entries are keyed by the destination together with the complete announce
+/// payload (which includes the signature), so only byte-identical
+/// retransmissions skip verification. An announce replaying a known
+/// signature with different keys or app data is verified again.
+///
+/// Once full, the oldest entry makes room for a new one.
+pub struct VerifiedAnnounces {
+    map: HashMap<Hash, (bool, Instant)>,
+    order: VecDeque<Hash>,
+    max_entries: usize,
+}
+
+impl VerifiedAnnounces {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            map: HashMap::new(),
+            order: VecDeque::new(),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    fn key(announce: &Packet) -> Hash {
+        Hash::new(
+            Hash::generator()
+                .chain_update(announce.destination.as_slice())
+                .chain_update(announce.data.as_slice())
+                .finalize()
+                .into(),
+        )
+    }
+
+    /// Whether the signature of `announce` was found valid, if it has been
+    /// verified before.
+    pub fn get(&mut self, announce: &Packet) -> Option<bool> {
+        self.map.get_mut(&Self::key(announce)).map(|(valid, time)| {
+            *time = Instant::now();
+            *valid
+        })
+    }
+
+    pub fn insert(&mut self, announce: &Packet, valid: bool) {
+        let key = Self::key(announce);
+        if self.map.insert(key, (valid, Instant::now())).is_some() {
+            return;
+        }
+
+        self.order.push_back(key);
+        while self.order.len() > self.max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.map.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn stats(&self) -> TableStats {
+        TableStats::of_map::<Hash, (bool, Instant)>(self.map.len())
+            .with_heap(self.order.capacity() * size_of::<Hash>())
+    }
+
+    pub fn release(&mut self, duration: Duration) {
+        self.map.retain(|_, (_, time)| time.elapsed() <= duration);
+
+        let map = &self.map;
+        self.order.retain(|key| map.contains_key(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_core::OsRng;
+
+    use crate::destination::{DestinationName, SingleInputDestination};
+    use crate::identity::PrivateIdentity;
+
+    use super::*;
+
+    #[test]
+    fn only_identical_announces_are_known() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "verified"),
+        );
+
+        let announce = destination.announce(OsRng, Some(b"data")).unwrap();
+
+        let mut verified = VerifiedAnnounces::new(MAX_VERIFIED_ANNOUNCES);
+        assert_eq!(verified.get(&announce), None);
+
+        verified.insert(&announce, true);
+        assert_eq!(verified.get(&announce), Some(true));
+
+        let mut retransmitted = announce;
+        retransmitted.header.hops += 1;
+        assert_eq!(verified.get(&retransmitted), Some(true));
+
+        let mut tampered = announce;
+        tampered.data.safe_write(b"!");
+        assert_eq!(verified.get(&tampered), None);
+
+        verified.insert(&tampered, false);
+        assert_eq!(verified.get(&tampered), Some(false));
+
+        std::thread::sleep(Duration::from_millis(2));
+        verified.release(Duration::from_millis(1));
+        assert_eq!(verified.get(&announce), None);
+        assert_eq!(verified.get(&tampered), None);
+    }
+
+    #[test]
+    fn oldest_entries_make_room() {
+        let destination = SingleInputDestination::new(
+            PrivateIdentity::new_from_rand(OsRng),
+            DestinationName::new("test", "verified"),
+        );
+
+        let announces: Vec<Packet> = (0..4u8)
+            .map(|i| destination.announce(OsRng, Some(&[i])).unwrap())
+            .collect();
+
+        let mut verified = VerifiedAnnounces::new(3);
+        for announce in &announces {
+            verified.insert(announce, true);
+        }
+
+        assert_eq!(verified.stats().entries, 3);
+        assert_eq!(verified.get(&announces[0]), None);
+        for announce in &announces[1..] {
+            assert_eq!(verified.get(announce), Some(true));
+        }
+    }
+}
@@ -1,15 +1,18 @@
+use std::collections::HashMap;
 use std::sync::{Arc, Once};
+use std::time::Duration;
 use rand_core::OsRng;
 use reticulum::{
     channel::{self, Channel},
     destination::DestinationName,
-    destination::link::LinkEvent,
+    destination::link::{LinkEvent, LinkId, LinkStatus},
     error::RnsError,
+    hash::AddressHash,
     identity::PrivateIdentity,
-    iface::udp::UdpInterface,
+    iface::{tcp_client::TcpClient, tcp_server::TcpServer, testing::Degraded, udp::UdpInterface},
     transport::{Transport, TransportConfig},
 };
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 
 static INIT: Once = Once::new();
 
@@ -48,10 +51,11 @@ async fn build_transport(name: &str, bind_addr: &str, forward_addr: &str)
         true,
     ));
 
-    transport.iface_manager().lock().await.spawn(
+    let iface = transport.iface_manager().lock().await.spawn(
         UdpInterface::new(bind_addr, Some(forward_addr), false),
         UdpInterface::spawn,
     );
+    iface.await_ready().await.expect("socket bound");
 
     log::info!("test: transport {} created", name);
 
@@ -62,7 +66,7 @@ async fn build_transport(name: &str, bind_addr: &str, forward_addr: &str)
 async fn channel_send() {
     setup();
 
-    let (mut transport_a, id_a) = build_transport("a", "127.0.0.1:8081", "127.0.0.1:8082").await;
+    let (transport_a, id_a) = build_transport("a", "127.0.0.1:8081", "127.0.0.1:8082").await;
     let (transport_b, _) = build_transport("b", "127.0.0.1:8082", "127.0.0.1:8081").await;
 
     let mut in_link_events = transport_a.in_link_events();
@@ -71,13 +75,13 @@ async fn channel_send() {
     let dest = transport_a.add_destination(
         id_a.clone(),
         DestinationName::new("test", "channels.send_multiple")).await;
-    transport_a.send_announce(&dest, None).await;
+    transport_a.send_announce(&dest, None).await.unwrap();
     let transport_a = Arc::new(Mutex::new(transport_a));
     let announce = recv_announces.recv().await.unwrap();
     // initiate the link from transport B and upgrade to channel
     let link = transport_b.link(announce.destination.lock().await.desc).await;
     let transport_b = Arc::new(Mutex::new(transport_b));
-    let (_channel_endpoint_b, _receiver_b) = Channel::<ChannelMessage>::new(link, &transport_b)
+    let (_channel_endpoint_b, mut receiver_b) = Channel::<ChannelMessage>::new(link, &transport_b)
         .await.unwrap();
     // wait for link activated event on transport A and upgrade to channel
     let event = in_link_events.recv().await.unwrap();
@@ -94,4 +98,258 @@ async fn channel_send() {
     let message = ChannelMessage(b"test1".to_vec());
     let hash = channel_endpoint_a.send(&message).await.unwrap();
     assert!(channel_endpoint_a.watch_message_delivery(hash).await.unwrap().recv().await.unwrap());
+    assert_eq!(receiver_b.recv().await.unwrap().0, b"test1");
+
+    // Larger messages are split into fragments which fit into link packets
+    let large = ChannelMessage((0..10 * channel::CHANNEL_MDU).map(|i| i as u8).collect());
+    let mut sent = channel_endpoint_a.send(&large).await;
+    while let Err(err) = sent {
+        assert!(err.is_transient(), "send failed: {err:?}");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sent = channel_endpoint_a.send(&large).await;
+    }
+    let received = tokio::time::timeout(Duration::from_secs(10), receiver_b.recv())
+        .await
+        .expect("message in time")
+        .unwrap();
+    assert_eq!(received.0, large.0);
+}
+
+async fn send(channel: &Channel<ChannelMessage>, text: String) {
+    let message = ChannelMessage(text.into_bytes());
+
+    let mut sent = channel.send(&message).await;
+    while let Err(err) = sent {
+        assert!(err.is_transient(), "send failed: {err:?}");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sent = channel.send(&message).await;
+    }
+}
+
+async fn recv_message(incoming: &mut broadcast::Receiver<ChannelMessage>) -> String {
+    let message = tokio::time::timeout(Duration::from_secs(10), incoming.recv())
+        .await
+        .expect("message in time")
+        .expect("open channel");
+
+    String::from_utf8(message.0).unwrap()
+}
+
+// Clients with their own identities link to the same destination at once.
+// Every link has to keep its own keys, sequence numbers and events.
+#[tokio::test]
+async fn channels_of_many_clients() {
+    const CLIENTS: usize = 4;
+    const MESSAGES: usize = 20;
+
+    setup();
+
+    let server_addr = "127.0.0.1:8091";
+    let server_id = PrivateIdentity::new_from_name("channel-server");
+    let server = Transport::new(TransportConfig::new("server", &server_id, true));
+    let server_iface = server.iface_manager().lock().await.spawn(
+        TcpServer::new(server_addr, server.iface_manager()),
+        TcpServer::spawn,
+    );
+    server_iface.await_ready().await.expect("server listening");
+    let dest = server
+        .add_destination(server_id, DestinationName::new("test", "channels.clients"))
+        .await;
+    let desc = dest.lock().await.desc;
+    let mut server_link_events = server.in_link_events();
+    let server = Arc::new(Mutex::new(server));
+
+    let mut clients = vec![];
+    for index in 0..CLIENTS {
+        let identity = PrivateIdentity::new_from_name(&format!("channel-client{index}"));
+        let client = Transport::new(TransportConfig::new(format!("client{index}"), &identity, false));
+        client.iface_manager().lock().await.spawn(TcpClient::new(server_addr), TcpClient::spawn);
+        clients.push((Arc::new(Mutex::new(client)), identity));
+    }
+
+    // Announce until every client knows a path
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            server.lock().await.send_announce(&dest, None).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            let mut known = 0;
+            for (client, _) in &clients {
+                if !client.lock().await.paths(&desc.address_hash).await.is_empty() {
+                    known += 1;
+                }
+            }
+            if known == CLIENTS {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("clients received announce");
+
+    let mut client_channels = vec![];
+    for (client, identity) in &clients {
+        let link = client.lock().await.link(desc).await;
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while link.lock().await.status() != LinkStatus::Active {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("link activated");
+
+        let identify = link.lock().await.identify_packet(identity).unwrap();
+        client.lock().await.send_packet(identify).await.unwrap();
+
+        let link_id = *link.lock().await.id();
+        let (channel, incoming) = Channel::<ChannelMessage>::new(link, client).await.unwrap();
+        client_channels.push((link_id, channel, incoming));
+    }
+
+    // Wrap every inbound link into a channel and note who identified on it
+    let mut server_channels = HashMap::<LinkId, (Channel<ChannelMessage>, broadcast::Receiver<ChannelMessage>)>::new();
+    let mut identities = HashMap::<LinkId, AddressHash>::new();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while server_channels.len() < CLIENTS || identities.len() < CLIENTS {
+            let event = match server_link_events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(err) => panic!("in link events closed: {err}"),
+            };
+
+            match event.event {
+                LinkEvent::Activated => {
+                    let link = server.lock().await.find_in_link(&event.id).await.unwrap();
+                    let channel = Channel::<ChannelMessage>::new(link, &server).await.unwrap();
+                    server_channels.insert(event.id, channel);
+                }
+                LinkEvent::IdentityReceived(identity) => {
+                    identities.insert(event.id, identity.address_hash);
+                }
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("all links activated and identified");
+
+    for (index, (link_id, _, _)) in client_channels.iter().enumerate() {
+        let identity = clients[index].1.address_hash();
+        assert_eq!(identities.get(link_id), Some(identity));
+    }
+
+    // Every server channel echoes the messages of its own client over the
+    // same link
+    let mut echoes = vec![];
+    for (link_id, (channel, mut incoming)) in server_channels {
+        let index = client_channels.iter().position(|(id, _, _)| *id == link_id).unwrap();
+        echoes.push(tokio::spawn(async move {
+            for round in 0..MESSAGES {
+                let message = recv_message(&mut incoming).await;
+                assert_eq!(message, format!("client{index}-{round}"));
+                send(&channel, format!("echo {message}")).await;
+            }
+        }));
+    }
+
+    // All clients send at the same time and read the echoes while sending
+    let mut clients_done = vec![];
+    for (index, (_, channel, mut incoming)) in client_channels.into_iter().enumerate() {
+        let replies = tokio::spawn(async move {
+            for round in 0..MESSAGES {
+                assert_eq!(recv_message(&mut incoming).await, format!("echo client{index}-{round}"));
+            }
+            assert!(incoming.try_recv().is_err());
+        });
+
+        clients_done.push(tokio::spawn(async move {
+            for round in 0..MESSAGES {
+                send(&channel, format!("client{index}-{round}")).await;
+            }
+            replies.await.unwrap();
+        }));
+    }
+
+    for task in echoes.into_iter().chain(clients_done) {
+        task.await.unwrap();
+    }
+}
+
+// Messages get through in order, once each, over a link which delays, loses
+// and reorders packets.
+#[tokio::test]
+async fn channel_over_degraded_link() {
+    const MESSAGES: usize = 10;
+
+    setup();
+
+    let server_addr = "127.0.0.1:8781";
+    let server_id = PrivateIdentity::new_from_name("degraded-server");
+    let server = Transport::new(TransportConfig::new("server", &server_id, true));
+    let server_iface = server.iface_manager().lock().await.spawn(
+        TcpServer::new(server_addr, server.iface_manager()),
+        TcpServer::spawn,
+    );
+    server_iface.await_ready().await.expect("server listening");
+    let dest = server
+        .add_destination(server_id, DestinationName::new("test", "channels.degraded"))
+        .await;
+    let desc = dest.lock().await.desc;
+    let mut server_link_events = server.in_link_events();
+    let server = Arc::new(Mutex::new(server));
+
+    let client = Transport::new(TransportConfig::new("client", &PrivateIdentity::new_from_rand(OsRng), false));
+    let degraded = Degraded::new(TcpClient::new(server_addr))
+        .set_delay(Duration::from_millis(20))
+        .set_jitter(Duration::from_millis(30))
+        .set_loss(0.1)
+        .set_reordering(0.1, Duration::from_millis(50))
+        .set_seed(3);
+    client
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(degraded, |context| Degraded::run(context, TcpClient::spawn));
+
+    tokio::time::timeout(Duration::from_secs(20), async {
+        while client.paths(&desc.address_hash).await.is_empty() {
+            server.lock().await.send_announce(&dest, None).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    })
+    .await
+    .expect("client received announce");
+
+    // Lost link requests are repeated
+    let link = client.link(desc).await;
+    tokio::time::timeout(Duration::from_secs(30), async {
+        while link.lock().await.status() != LinkStatus::Active {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("link activated");
+    let link_id = *link.lock().await.id();
+
+    let client = Arc::new(Mutex::new(client));
+    let (channel, _incoming) = Channel::<ChannelMessage>::new(link, &client).await.unwrap();
+
+    let mut incoming = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let event = server_link_events.recv().await.expect("in link events open");
+            if event.id == link_id && matches!(event.event, LinkEvent::Activated) {
+                let link = server.lock().await.find_in_link(&event.id).await.unwrap();
+                return Channel::<ChannelMessage>::new(link, &server).await.unwrap().1;
+            }
+        }
+    })
+    .await
+    .expect("server saw the link");
+
+    for round in 0..MESSAGES {
+        send(&channel, format!("degraded-{round}")).await;
+    }
+    for round in 0..MESSAGES {
+        assert_eq!(recv_message(&mut incoming).await, format!("degraded-{round}"));
+    }
 }
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand_core::OsRng;
+use reticulum::{
+    channel::Channel,
+    destination::link::{LinkEvent, LinkStatus},
+    destination::DestinationName,
+    fileshare::{self, FileMessage, FileServer, FileShareError, CHUNK_SIZE},
+    identity::PrivateIdentity,
+    iface::udp::UdpInterface,
+    transfer::TransferState,
+    transport::{Transport, TransportConfig},
+};
+use tokio::sync::Mutex;
+
+async fn build_transport(name: &str, bind_addr: &str, forward_addr: &str) -> (Transport, PrivateIdentity) {
+    let id = PrivateIdentity::new_from_rand(OsRng);
+    let transport = Transport::new(TransportConfig::new(name, &id, true));
+
+    let iface = transport.iface_manager().lock().await.spawn(
+        UdpInterface::new(bind_addr, Some(forward_addr), false),
+        UdpInterface::spawn,
+    );
+    iface.await_ready().await.expect("socket bound");
+
+    (transport, id)
+}
+
+#[tokio::test]
+async fn fetch_files_from_a_directory() {
+    let dir = std::env::temp_dir().join(format!("fileshare-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("docs")).unwrap();
+
+    // Text compresses, the pseudo random data doesn't
+    let text = b"all work and no play makes jack a dull boy. ".repeat(2000);
+    let random: Vec<u8> = (0..CHUNK_SIZE as u32 * 2 + 100)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+    std::fs::write(dir.join("docs").join("text.txt"), &text).unwrap();
+    std::fs::write(dir.join("random.bin"), &random).unwrap();
+    std::fs::write(dir.join("empty"), b"").unwrap();
+
+    let (server, server_id) = build_transport("server", "127.0.0.1:8881", "127.0.0.1:8882").await;
+    let (client, _) = build_transport("client", "127.0.0.1:8882", "127.0.0.1:8881").await;
+
+    let mut in_link_events = server.in_link_events();
+    let mut announces = client.recv_announces().await;
+    let dest = server
+        .add_destination(server_id, DestinationName::new("test", "fileshare"))
+        .await;
+    server.send_announce(&dest, None).await.unwrap();
+    let server = Arc::new(Mutex::new(server));
+
+    let announce = tokio::time::timeout(Duration::from_secs(10), announces.recv())
+        .await
+        .expect("announce in time")
+        .unwrap();
+    let link = client.link(announce.destination.lock().await.desc).await;
+    let client = Arc::new(Mutex::new(client));
+
+    let link_id = loop {
+        let event = in_link_events.recv().await.unwrap();
+        if let LinkEvent::Activated = event.event {
+            break event.id;
+        }
+    };
+    while link.lock().await.status() != LinkStatus::Active {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let file_server = Arc::new(FileServer::new(&dir));
+    let mut served = file_server.subscribe();
+    {
+        let server_link = server.lock().await.find_in_link(&link_id).await.unwrap();
+        let (channel, mut incoming) = Channel::<FileMessage>::new(server_link, &server).await.unwrap();
+        let file_server = file_server.clone();
+        tokio::spawn(async move { file_server.serve(&channel, &mut incoming).await });
+    }
+
+    let (channel, mut incoming) = Channel::<FileMessage>::new(link, &client).await.unwrap();
+
+    for (path, expected) in [("docs/text.txt", &text), ("/random.bin", &random)] {
+        let mut updates = vec![];
+        let data = tokio::time::timeout(
+            Duration::from_secs(60),
+            fileshare::fetch(&channel, &mut incoming, path, |progress| updates.push(progress.clone())),
+        )
+        .await
+        .expect("file in time")
+        .unwrap();
+        assert_eq!(&data, expected);
+
+        let last = updates.last().unwrap();
+        assert_eq!(last.state, TransferState::Complete);
+        assert_eq!(last.done_bytes, expected.len());
+        assert_eq!(last.done_parts, expected.len().div_ceil(CHUNK_SIZE));
+        assert_eq!(updates.len(), last.total_parts + 1);
+
+        let file = served.recv().await.unwrap();
+        assert_eq!(file.path, path);
+        assert_eq!(file.transfer.finished().await, TransferState::Complete);
+        assert_eq!(file.transfer.progress().done_bytes, expected.len());
+    }
+
+    let empty = fileshare::fetch(&channel, &mut incoming, "empty", |_| {}).await.unwrap();
+    assert!(empty.is_empty());
+
+    for path in ["missing", "../fileshare-escape", "docs"] {
+        let result = fileshare::fetch(&channel, &mut incoming, path, |_| {}).await;
+        assert!(matches!(result, Err(FileShareError::NotFound { .. })), "{path}: {result:?}");
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
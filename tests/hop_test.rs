@@ -6,10 +6,11 @@ use reticulum::{
     destination::DestinationName,
     destination::link::LinkEvent,
     identity::PrivateIdentity,
-    iface::{tcp_client::TcpClient, tcp_server::TcpServer},
-    transport::{Transport, TransportConfig},
+    iface::{tcp_client::TcpClient, tcp_server::TcpServer, RxMessage},
+    packet::Packet,
+    transport::{Transport, TransportConfig, PATHFINDER_M},
 };
-use tokio::time;
+use tokio::{sync::broadcast, time};
 
 static INIT: Once = Once::new();
 
@@ -28,7 +29,7 @@ async fn build_transport_full(
     retransmit: bool
 ) -> Transport {
     let transport = TransportConfig::new(name, &PrivateIdentity::new_from_rand(OsRng), true)
-        .set_retransmit(retransmit)
+        .set_transport_enabled(retransmit)
         .build();
 
     transport.iface_manager().lock().await.spawn(
@@ -57,7 +58,7 @@ async fn build_transport(name: &str, server_addr: &str, client_addr: &[&str]) ->
 async fn calculate_hop_distance() {
     setup();
 
-    let mut transport_a = build_transport("a", "127.0.0.1:8081", &[]).await;
+    let transport_a = build_transport("a", "127.0.0.1:8081", &[]).await;
     let transport_b = build_transport("b", "127.0.0.1:8082", &["127.0.0.1:8081"]).await;
     let transport_c =
         build_transport("c", "127.0.0.1:8083", &["127.0.0.1:8081", "127.0.0.1:8082"]).await;
@@ -71,7 +72,7 @@ async fn calculate_hop_distance() {
     time::sleep(Duration::from_secs(2)).await;
 
     println!("======");
-    transport_a.send_announce(&dest_a, None).await;
+    transport_a.send_announce(&dest_a, None).await.unwrap();
 
     transport_b.recv_announces().await;
     transport_c.recv_announces().await;
@@ -84,7 +85,7 @@ async fn direct_path_request_and_response() {
     setup();
 
     let transport_a = build_transport("a", "127.0.0.1:8181", &[]).await;
-    let mut transport_b = build_transport("b", "127.0.0.1:8182", &["127.0.0.1:8181"]).await;
+    let transport_b = build_transport("b", "127.0.0.1:8182", &["127.0.0.1:8181"]).await;
 
     let id_b = PrivateIdentity::new_from_name("b");
 
@@ -107,13 +108,13 @@ async fn remote_path_request_and_response() {
     setup();
 
     let transport_a = build_transport("a", "127.0.0.1:8281", &[]).await;
-    let mut transport_b = build_transport_full(
+    let transport_b = build_transport_full(
         "b",
         "127.0.0.1:8282",
         &["127.0.0.1:8281"],
         true
     ).await;
-    let mut transport_c = build_transport("c", "127.0.0.1:8283", &["127.0.0.1:8282"]).await;
+    let transport_c = build_transport("c", "127.0.0.1:8283", &["127.0.0.1:8282"]).await;
 
     let id_c = PrivateIdentity::new_from_name("c");
     let dest_c = transport_c
@@ -128,7 +129,7 @@ async fn remote_path_request_and_response() {
 
     time::sleep(Duration::from_secs(2)).await;
 
-    transport_c.send_announce(&dest_c, None).await;
+    transport_c.send_announce(&dest_c, None).await.unwrap();
     transport_b.recv_announces().await;
 
     time::sleep(Duration::from_secs(2)).await;
@@ -139,7 +140,7 @@ async fn remote_path_request_and_response() {
     time::pause();
     time::advance(time::Duration::from_secs(3600)).await;
 
-    transport_b.send_announce(&dest_b, None).await; 
+    transport_b.send_announce(&dest_b, None).await.unwrap(); 
     transport_a.recv_announces().await;
     transport_a.request_path(&dest_c_hash, None, None).await;
 
@@ -154,7 +155,7 @@ async fn message_proof_over_remote_link() {
     let _transport_b =
         build_transport_full("b", "127.0.0.1:8382", &["127.0.0.1:8381"], true)
         .await;
-    let mut transport_c =
+    let transport_c =
         build_transport("c", "127.0.0.1:8383", &["127.0.0.1:8382"])
         .await;
 
@@ -167,7 +168,7 @@ async fn message_proof_over_remote_link() {
     // connected, the outgoing packet will be dropped
     // TODO: can we do this without waiting?
     time::sleep(Duration::from_millis(100)).await;
-    transport_c.send_announce(&dest_c, None).await;
+    transport_c.send_announce(&dest_c, None).await.unwrap();
 
     transport_a.recv_announces().await.recv().await.unwrap();
     let link = transport_a.link(dest_c.lock().await.desc).await;
@@ -198,3 +199,80 @@ async fn message_proof_over_remote_link() {
         },
     }
 }
+
+async fn collect_hops(
+    mut iface_rx: broadcast::Receiver<RxMessage>,
+    data: &'static [u8],
+    wait: Duration,
+) -> Vec<u8> {
+    let mut hops = vec![];
+
+    let _ = time::timeout(wait, async {
+        while let Ok(message) = iface_rx.recv().await {
+            if message.packet.data.as_slice() == data {
+                hops.push(message.packet.header.hops);
+            }
+        }
+    })
+    .await;
+
+    hops
+}
+
+#[tokio::test]
+async fn broadcast_loop_terminates() {
+    setup();
+
+    // A -> B -> C -> A
+    let transport_a = build_transport_full("a", "127.0.0.1:8481", &["127.0.0.1:8482"], true).await;
+    let transport_b = build_transport_full("b", "127.0.0.1:8482", &["127.0.0.1:8483"], true).await;
+    let transport_c = build_transport_full("c", "127.0.0.1:8483", &["127.0.0.1:8481"], true).await;
+
+    // Clients retry connecting to servers which didn't exist yet after 5s
+    time::sleep(Duration::from_secs(6)).await;
+
+    let wait = Duration::from_secs(2);
+    let rx_a = collect_hops(transport_a.iface_rx(), b"loop", wait);
+    let rx_b = collect_hops(transport_b.iface_rx(), b"loop", wait);
+    let rx_c = collect_hops(transport_c.iface_rx(), b"loop", wait);
+
+    let mut packet = Packet::default();
+    packet.data.write(b"loop").unwrap();
+    transport_a.send_packet(packet).await.unwrap();
+
+    let (rx_a, rx_b, rx_c) = tokio::join!(rx_a, rx_b, rx_c);
+
+    // Every node forwards a packet once, each forward adds one hop
+    assert!(!rx_b.is_empty() && !rx_c.is_empty());
+    for hops in rx_a.iter().chain(&rx_b).chain(&rx_c) {
+        assert!(*hops <= 2, "packet looped with {} hops", hops);
+    }
+    assert!(rx_a.len() + rx_b.len() + rx_c.len() <= 6);
+}
+
+#[tokio::test]
+async fn forwarding_stops_at_max_hops() {
+    setup();
+
+    // A - B - C - D
+    let transport_a = build_transport_full("a", "127.0.0.1:8581", &[], true).await;
+    let _transport_b = build_transport_full("b", "127.0.0.1:8582", &["127.0.0.1:8581"], true).await;
+    let transport_c = build_transport_full("c", "127.0.0.1:8583", &["127.0.0.1:8582"], true).await;
+    let transport_d = build_transport_full("d", "127.0.0.1:8584", &["127.0.0.1:8583"], true).await;
+
+    time::sleep(Duration::from_secs(2)).await;
+
+    let wait = Duration::from_secs(2);
+    let rx_c = collect_hops(transport_c.iface_rx(), b"far", wait);
+    let rx_d = collect_hops(transport_d.iface_rx(), b"far", wait);
+
+    let mut packet = Packet::default();
+    packet.header.hops = (PATHFINDER_M - 1) as u8;
+    packet.data.write(b"far").unwrap();
+    transport_a.send_packet(packet).await.unwrap();
+
+    let (rx_c, rx_d) = tokio::join!(rx_c, rx_d);
+
+    assert_eq!(rx_c, vec![PATHFINDER_M as u8]);
+    assert!(rx_d.is_empty(), "packet was forwarded beyond {} hops", PATHFINDER_M);
+}
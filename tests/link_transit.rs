@@ -0,0 +1,175 @@
+//! A Rust transport node between two Python endpoints, connected through
+//! in-memory interfaces. Link traffic has to pass the node unchanged apart
+//! from the hop count, whatever its context.
+
+use std::time::Duration;
+
+use rand_core::OsRng;
+use tokio::time;
+
+use reticulum::buffer::OutputBuffer;
+use reticulum::destination::link::{Link, LinkId};
+use reticulum::destination::{DestinationName, SingleInputDestination};
+use reticulum::hash::AddressHash;
+use reticulum::iface::{InterfaceChannel, RxMessage};
+use reticulum::identity::PrivateIdentity;
+use reticulum::packet::{
+    DestinationType, Header, HeaderType, Packet, PacketContext, PacketDataBuffer, PacketType,
+    PropagationType,
+};
+use reticulum::serde::Serialize;
+use reticulum::transport::TransportConfig;
+
+fn raw(packet: &Packet) -> Vec<u8> {
+    let mut buffer = [0u8; 1024];
+    let mut output = OutputBuffer::new(&mut buffer);
+    packet.serialize(&mut output).expect("serialized packet");
+    output.as_slice().to_vec()
+}
+
+async fn receive(iface: &InterfaceChannel, packet: Packet) {
+    iface
+        .rx_channel
+        .send(RxMessage { address: iface.address, packet })
+        .await
+        .unwrap();
+}
+
+async fn transmitted(iface: &mut InterfaceChannel) -> Packet {
+    time::timeout(Duration::from_secs(1), iface.tx_channel.recv())
+        .await
+        .expect("forwarded packet")
+        .unwrap()
+        .packet
+}
+
+/// Checks that `forwarded` is `original` with one more hop.
+fn assert_forwarded(original: &Packet, forwarded: &Packet) {
+    let (original, forwarded) = (raw(original), raw(forwarded));
+
+    assert_eq!(forwarded[0], original[0]);
+    assert_eq!(forwarded[1], original[1] + 1);
+    assert_eq!(forwarded[2..], original[2..]);
+}
+
+fn link_packet(
+    packet_type: PacketType,
+    link_id: LinkId,
+    context: PacketContext,
+    data: &[u8],
+) -> Packet {
+    Packet {
+        header: Header {
+            destination_type: DestinationType::Link,
+            packet_type,
+            hops: 1,
+            ..Default::default()
+        },
+        destination: link_id,
+        context,
+        data: PacketDataBuffer::new_from_slice(data),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn forward_link_traffic_unchanged() {
+    let identity = PrivateIdentity::new_from_name("transit");
+    let transport = TransportConfig::new("transit", &identity, false)
+        .set_transport_enabled(true)
+        .build();
+
+    let mut initiator_iface = transport.iface_manager().lock().await.new_channel(8);
+    let mut destination_iface = transport.iface_manager().lock().await.new_channel(8);
+
+    // The destination is one hop away from the transport node
+    let destination = SingleInputDestination::new(
+        PrivateIdentity::new_from_name("python-destination"),
+        DestinationName::new("example_utilities", "linkexample"),
+    );
+    receive(&destination_iface, destination.announce(OsRng, None).unwrap()).await;
+
+    // and the transport node passes its announce on
+    let announce = time::timeout(Duration::from_secs(3), initiator_iface.tx_channel.recv())
+        .await
+        .expect("retransmitted announce")
+        .unwrap()
+        .packet;
+    assert_eq!(announce.header.packet_type, PacketType::Announce);
+    assert_eq!(announce.transport, Some(*identity.address_hash()));
+
+    // Python initiators address link requests to the transport node
+    let (event_tx, _) = tokio::sync::broadcast::channel(1);
+    let mut request = Link::new(destination.desc, event_tx).request();
+    request.header.header_type = HeaderType::Type2;
+    request.header.propagation_type = PropagationType::Transport;
+    request.transport = Some(*identity.address_hash());
+    let link_id = LinkId::from(&request);
+
+    receive(&initiator_iface, request).await;
+
+    let forwarded = transmitted(&mut destination_iface).await;
+    assert_eq!(forwarded.header.header_type, HeaderType::Type1);
+    assert_eq!(forwarded.transport, None);
+    assert_eq!(forwarded.destination, destination.desc.address_hash);
+    assert_eq!(LinkId::from(&forwarded), link_id);
+
+    // Proofs are only passed on if the destination signed them and they come
+    // from its direction
+    let (event_tx, _) = tokio::sync::broadcast::channel(1);
+    let signed = Link::new_from_request(&forwarded, destination.sign_key().clone(), destination.desc, event_tx)
+        .unwrap()
+        .prove();
+    let proof = link_packet(
+        PacketType::Proof,
+        link_id,
+        PacketContext::LinkRequestProof,
+        signed.data.as_slice(),
+    );
+
+    let mut corrupted = proof;
+    corrupted.data.as_mut_slice()[10] ^= 0x01;
+    receive(&destination_iface, corrupted).await;
+    receive(&initiator_iface, proof).await;
+    assert!(time::timeout(Duration::from_millis(200), initiator_iface.tx_channel.recv())
+        .await
+        .is_err());
+
+    let rejected = transport.rejected_proofs().await;
+    assert_eq!((rejected.invalid_signature, rejected.wrong_interface), (1, 1));
+
+    receive(&destination_iface, proof).await;
+    assert_forwarded(&proof, &transmitted(&mut initiator_iface).await);
+
+    // Resource transfers, requests and contexts unknown to this
+    // implementation travel towards the destination ...
+    for context in [
+        PacketContext::ResourceAdvertisement,
+        PacketContext::Resource,
+        PacketContext::Request,
+        PacketContext::Unknown(0x42),
+    ] {
+        let packet = link_packet(PacketType::Data, link_id, context, b"encrypted");
+        receive(&initiator_iface, packet).await;
+        assert_forwarded(&packet, &transmitted(&mut destination_iface).await);
+    }
+
+    // ... and back
+    for (packet_type, context) in [
+        (PacketType::Data, PacketContext::Response),
+        (PacketType::Data, PacketContext::ResourceRequest),
+        (PacketType::Proof, PacketContext::None),
+        (PacketType::Proof, PacketContext::ResourceProof),
+    ] {
+        let packet = link_packet(packet_type, link_id, context, b"encrypted");
+        receive(&destination_iface, packet).await;
+        assert_forwarded(&packet, &transmitted(&mut initiator_iface).await);
+    }
+
+    // Traffic of unknown links isn't forwarded
+    let unknown = AddressHash::new([0x55; 16]);
+    receive(&initiator_iface, link_packet(PacketType::Data, unknown, PacketContext::None, b"x")).await;
+    assert!(time::timeout(Duration::from_millis(200), destination_iface.tx_channel.recv())
+        .await
+        .is_err());
+}
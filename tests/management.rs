@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reticulum::destination::link::{LinkEvent, LinkStatus};
+use reticulum::destination::DestinationName;
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::udp::UdpInterface;
+use reticulum::management::{self, Client, Server};
+use reticulum::transport::{Transport, TransportConfig};
+use tokio_util::sync::CancellationToken;
+
+async fn build_transport(identity: &PrivateIdentity, bind_addr: &str, forward_addr: &str) -> Arc<Transport> {
+    let transport = Transport::new(TransportConfig::new("management", identity, true));
+
+    transport.iface_manager().lock().await.spawn(
+        UdpInterface::new(bind_addr, Some(forward_addr), false),
+        UdpInterface::spawn,
+    );
+
+    Arc::new(transport)
+}
+
+#[tokio::test]
+async fn query_node_over_management_link() {
+    let node_identity = PrivateIdentity::new_from_name("managed-node");
+    let operator = PrivateIdentity::new_from_name("operator");
+    let stranger = PrivateIdentity::new_from_name("stranger");
+
+    let node = build_transport(&node_identity, "127.0.0.1:8681", "127.0.0.1:8682").await;
+    let client = build_transport(&PrivateIdentity::new_from_name("client"), "127.0.0.1:8682", "127.0.0.1:8681").await;
+
+    let mut announces = client.recv_announces().await;
+
+    let cancel = CancellationToken::new();
+    let server = Server::new(node.clone(), node_identity.clone(), vec![*operator.address_hash()]);
+    tokio::spawn(server.run(cancel.clone()));
+
+    let management_address = management::destination_name();
+    let destination = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let announce = announces.recv().await.unwrap();
+            let desc = announce.destination.lock().await.desc;
+            if desc.name.as_name_hash_slice() == management_address.as_name_hash_slice() {
+                return desc;
+            }
+        }
+    })
+    .await
+    .expect("management destination announced");
+
+    // Give the node a path to report
+    let app = client
+        .add_destination(PrivateIdentity::new_from_name("app"), DestinationName::new("test", "management"))
+        .await;
+    client.send_announce(&app, None).await.unwrap();
+    let app_address = app.lock().await.desc.address_hash;
+
+    let mut link_events = node.in_link_events();
+    let session = Client::connect(client.clone(), destination, &operator, Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let status = session.status(true).await.unwrap();
+    assert_eq!(status.transport_id, *node_identity.address_hash());
+    assert_eq!(status.interfaces.len(), 1);
+    assert_eq!(status.link_count, Some(1));
+
+    let paths = session.path_table(Some(&app_address), None).await.unwrap();
+    assert_eq!(paths.len(), 1);
+    assert_eq!(paths[0].destination, app_address);
+    assert_eq!(paths[0].hops, 1);
+    assert!(paths[0].expires > paths[0].timestamp);
+
+    let mut identified = false;
+    let mut requests = 0;
+    while let Ok(event) = link_events.try_recv() {
+        match event.event {
+            LinkEvent::IdentityReceived(identity) => {
+                identified = true;
+                assert_eq!(identity.address_hash, *operator.address_hash());
+            }
+            LinkEvent::RequestReceived(_) => requests += 1,
+            _ => {}
+        }
+    }
+    assert!(identified);
+    assert_eq!(requests, 2);
+
+    session.close().await.unwrap();
+
+    // Identities which aren't allowed get no answer and lose their link
+    let session = Client::connect(client.clone(), destination, &stranger, Duration::from_secs(2))
+        .await
+        .unwrap();
+    assert!(session.status(false).await.is_err());
+    assert_eq!(session.link().lock().await.status(), LinkStatus::Closed);
+
+    cancel.cancel();
+}
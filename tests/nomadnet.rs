@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reticulum::destination::link::LinkStatus;
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::udp::UdpInterface;
+use reticulum::msgpack::Reader;
+use reticulum::nomadnet::{self, Node};
+use reticulum::transport::{Transport, TransportConfig};
+use tokio_util::sync::CancellationToken;
+
+async fn build_transport(identity: &PrivateIdentity, bind_addr: &str, forward_addr: &str) -> Arc<Transport> {
+    let transport = Transport::new(TransportConfig::new("nomadnet", identity, true));
+
+    transport.iface_manager().lock().await.spawn(
+        UdpInterface::new(bind_addr, Some(forward_addr), false),
+        UdpInterface::spawn,
+    );
+
+    Arc::new(transport)
+}
+
+#[tokio::test]
+async fn browse_node_pages_and_files() {
+    let node_identity = PrivateIdentity::new_from_name("nomadnet-node");
+    let node = build_transport(&node_identity, "127.0.0.1:8691", "127.0.0.1:8692").await;
+    let client = build_transport(&PrivateIdentity::new_from_name("browser"), "127.0.0.1:8692", "127.0.0.1:8691").await;
+
+    let mut announces = client.recv_announces().await;
+
+    let cancel = CancellationToken::new();
+    let mut server = Node::new(node.clone(), node_identity, "Rust Node");
+    server.add_page("about.mu", ">About\n\nServed by Rust.");
+    server.add_file("readme.txt", "hello");
+    tokio::spawn(server.run(cancel.clone()));
+
+    let node_name = nomadnet::destination_name();
+    let (destination, app_data) = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let announce = announces.recv().await.unwrap();
+            let desc = announce.destination.lock().await.desc;
+            if desc.name.as_name_hash_slice() == node_name.as_name_hash_slice() {
+                return (desc, announce.app_data);
+            }
+        }
+    })
+    .await
+    .expect("node announced");
+    assert_eq!(app_data.as_slice(), b"Rust Node");
+
+    let link = client.link(destination).await;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while link.lock().await.status() != LinkStatus::Active {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("link activated");
+
+    let request = |path: &'static str| {
+        let client = client.clone();
+        let link = link.clone();
+        async move {
+            let mut responses = client.link_responses();
+            let (packet, request_id) = link.lock().await.request_packet(path, &[0xc0]).unwrap();
+            client.send_packet(packet).await.unwrap();
+
+            tokio::time::timeout(Duration::from_secs(5), async {
+                loop {
+                    let received = responses.recv().await.unwrap();
+                    if received.response.request_id == request_id {
+                        return received.response.data;
+                    }
+                }
+            })
+            .await
+            .expect("response")
+        }
+    };
+
+    // Nodes without an index page get a placeholder
+    let index = request(nomadnet::INDEX_PATH).await;
+    assert!(Reader::new(&index).bin().unwrap().starts_with(b">Default Home Page"));
+
+    let about = request("/page/about.mu").await;
+    assert_eq!(Reader::new(&about).bin().unwrap(), b">About\n\nServed by Rust.");
+
+    let file = request("/file/readme.txt").await;
+    let mut reader = Reader::new(&file);
+    assert_eq!(reader.array().unwrap(), 2);
+    assert_eq!(reader.str().unwrap(), "readme.txt");
+    assert_eq!(reader.bin().unwrap(), b"hello");
+
+    cancel.cancel();
+}
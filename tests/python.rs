@@ -196,7 +196,7 @@ async fn python_link_client() {
                         Ok(packet) => packet,
                         Err(err) => panic!("error creating data packet: {err:?}")
                     };
-                    transport.send_packet(packet).await;
+                    transport.send_packet(packet).await.unwrap();
                 }
                 LinkEvent::Data(payload) => {
                     log::debug!("got payload: {:?}", str::from_utf8(payload.as_slice()));
@@ -205,8 +205,8 @@ async fn python_link_client() {
                     // succeeded: shut down
                     break
                 }
-                LinkEvent::Proof(_) => {}
-                LinkEvent::Closed => panic!("error: link closed unexpectedly")
+                LinkEvent::Closed => panic!("error: link closed unexpectedly"),
+                _ => {}
             }
             Ok(Err(err)) => panic!("error receiving out link events: {err}"),
             Err(err) => panic!("timed out recieving out link events: {err}")
@@ -234,7 +234,7 @@ async fn python_link_server() {
 
     let server_identity = PrivateIdentity::new_from_rand(rand_core::OsRng);
     //let server_identity = PrivateIdentity::new_from_name("test-python-link-server");
-    let mut transport = TransportConfig::default().build();
+    let transport = TransportConfig::default().build();
     let _ = transport.iface_manager().lock().await.spawn(
         UdpInterface::new("0.0.0.0:4242", Some("127.0.0.1:4243"), false),
         UdpInterface::spawn);
@@ -253,7 +253,7 @@ async fn python_link_server() {
         .arg(script_path)
         .arg("--config")
         .arg("tests/rns-py-configs/udp")
-        .arg(destination_hash.to_string().trim_matches('/'))
+        .arg(destination_hash.to_hex())
         .stdin(Stdio::piped())   // to be able to send to stdin
         .stdout(Stdio::piped())  // to be able to process stdout lines
         .spawn()
@@ -304,10 +304,10 @@ async fn python_link_server() {
                             Ok(packet) => packet,
                             Err(err) => panic!("error creating data packet: {err:?}")
                         };
-                        transport.send_packet(packet).await;
+                        transport.send_packet(packet).await.unwrap();
                     }
-                    LinkEvent::Proof(_) => {}
-                    LinkEvent::Closed => panic!("error: link closed unexpectedly")
+                    LinkEvent::Closed => panic!("error: link closed unexpectedly"),
+                    _ => {}
                 }
                 Err(broadcast::error::TryRecvError::Empty) => {}
                 Err(err) => panic!("error receiving in link events: {err}")
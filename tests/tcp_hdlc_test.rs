@@ -2,9 +2,18 @@ use std::sync::Once;
 use std::time::Duration;
 
 use rand_core::OsRng;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpListener;
 use reticulum::{
     identity::PrivateIdentity,
-    iface::{tcp_client::TcpClient, tcp_server::TcpServer},
+    iface::{
+        backoff::BackoffConfig,
+        local_client::{LocalClientInterface, LocalSocket},
+        local_server::LocalServerInterface,
+        tcp_client::{TcpClient, TcpKeepalive},
+        tcp_server::TcpServer,
+        InterfaceEvent, InterfaceMode, InterfaceState,
+    },
     packet::Packet,
     transport::{Transport, TransportConfig},
 };
@@ -33,10 +42,11 @@ async fn build_transport(name: &str, server_addr: &str, client_addr: &[&str]) ->
         true,
     ));
 
-    transport.iface_manager().lock().await.spawn(
+    let server = transport.iface_manager().lock().await.spawn(
         TcpServer::new(server_addr, transport.iface_manager()),
         TcpServer::spawn,
     );
+    server.await_ready().await.expect("server listening");
 
     for &addr in client_addr {
         transport
@@ -82,8 +92,10 @@ async fn packet_overload() {
                             payload_size = 0;
                         }
 
-                        transport_a.send_packet(packet).await;
-                        tx_counter += 1;
+                        // Packets above the MTU are refused
+                        if transport_a.send_packet(packet).await.is_ok() {
+                            tx_counter += 1;
+                        }
                     },
                 };
             }
@@ -139,7 +151,7 @@ async fn unavailable_tcp_client_does_not_block_server_traffic() {
         for counter in 0..3u8 {
             let mut packet = Packet::default();
             packet.data.write(&[counter]).unwrap();
-            transport_a.send_packet(packet).await;
+            transport_a.send_packet(packet).await.unwrap();
         }
     });
 
@@ -160,3 +172,328 @@ async fn unavailable_tcp_client_does_not_block_server_traffic() {
     .await
     .expect("TCP server traffic stopped after another TCP client failed to connect");
 }
+
+#[tokio::test]
+async fn local_client_attaches_to_shared_instance() {
+    setup();
+
+    let server_addr = free_local_addr();
+    let port: u16 = server_addr.rsplit(':').next().unwrap().parse().unwrap();
+
+    let instance = build_transport("instance", &server_addr, &[]).await;
+
+    let app = Transport::new(TransportConfig::default());
+    let client = app
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(LocalClientInterface::new_tcp(port), LocalClientInterface::spawn);
+    client.await_ready().await.expect("attached to shared instance");
+
+    let mut iface_rx = instance.iface_rx();
+
+    let mut packet = Packet::default();
+    packet.data.write(b"local").unwrap();
+    app.send_packet(packet).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(2), iface_rx.recv())
+        .await
+        .expect("shared instance did not receive packet from local client")
+        .unwrap();
+
+    assert_eq!(message.packet.data.as_slice(), b"local");
+}
+
+async fn local_server_serves(socket: LocalSocket) {
+    let instance = Transport::new(TransportConfig::default());
+    let server = instance.iface_manager().lock().await.spawn(
+        LocalServerInterface::new(socket.clone(), instance.iface_manager()),
+        LocalServerInterface::spawn,
+    );
+    server.await_ready().await.expect("shared instance listening");
+
+    let app = Transport::new(TransportConfig::default());
+    let client = app
+        .iface_manager()
+        .lock()
+        .await
+        .spawn(LocalClientInterface::new(socket), LocalClientInterface::spawn);
+    client.await_ready().await.expect("attached to shared instance");
+
+    let mut instance_rx = instance.iface_rx();
+    let mut app_rx = app.iface_rx();
+
+    let mut packet = Packet::default();
+    packet.data.write(b"from app").unwrap();
+    app.send_packet(packet).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(2), instance_rx.recv())
+        .await
+        .expect("shared instance did not receive packet from local client")
+        .unwrap();
+    assert_eq!(message.packet.data.as_slice(), b"from app");
+
+    // The client has an interface of its own on the shared instance
+    let mut packet = Packet::default();
+    packet.data.write(b"from instance").unwrap();
+    instance.send_packet(packet).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(2), app_rx.recv())
+        .await
+        .expect("local client did not receive packet from shared instance")
+        .unwrap();
+    assert_eq!(message.packet.data.as_slice(), b"from instance");
+}
+
+#[tokio::test]
+async fn local_server_over_tcp() {
+    setup();
+
+    let server_addr = free_local_addr();
+    let port: u16 = server_addr.rsplit(':').next().unwrap().parse().unwrap();
+
+    local_server_serves(LocalSocket::Tcp(port)).await;
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn local_server_over_unix_socket() {
+    setup();
+
+    local_server_serves(LocalSocket::Unix(format!("test-{}", std::process::id()))).await;
+}
+
+#[tokio::test]
+async fn kiss_framing_client_to_server() {
+    setup();
+
+    let server_addr = free_local_addr();
+
+    let server = Transport::new(TransportConfig::default());
+    let server_iface = server.iface_manager().lock().await.spawn(
+        TcpServer::new(&server_addr, server.iface_manager()).set_kiss_framing(true),
+        TcpServer::spawn,
+    );
+    server_iface.await_ready().await.expect("server listening");
+
+    let client = Transport::new(TransportConfig::default());
+    let client_iface = client.iface_manager().lock().await.spawn(
+        TcpClient::new(&server_addr).set_kiss_framing(true),
+        TcpClient::spawn,
+    );
+    client_iface.await_ready().await.expect("client connected");
+
+    let mut iface_rx = server.iface_rx();
+
+    // 0xc0 and 0xdb must be escaped by KISS framing
+    let mut packet = Packet::default();
+    packet.data.write(&[0xc0, 0xdb, 0x7e]).unwrap();
+    client.send_packet(packet).await.unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(2), iface_rx.recv())
+        .await
+        .expect("server did not receive KISS framed packet")
+        .unwrap();
+
+    assert_eq!(message.packet.data.as_slice(), [0xc0, 0xdb, 0x7e]);
+}
+
+#[tokio::test]
+async fn idle_client_sends_pings() {
+    setup();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let client = Transport::new(TransportConfig::default());
+    client.iface_manager().lock().await.spawn(
+        TcpClient::new(&addr).set_keepalive(TcpKeepalive {
+            ping_interval: Some(Duration::from_millis(200)),
+            ..Default::default()
+        }),
+        TcpClient::spawn,
+    );
+
+    let (mut stream, _) = listener.accept().await.unwrap();
+
+    // Pings are empty HDLC frames
+    let mut ping = [0u8; 2];
+    tokio::time::timeout(Duration::from_secs(2), stream.read_exact(&mut ping))
+        .await
+        .expect("client did not ping")
+        .unwrap();
+    assert_eq!(ping, [0x7e, 0x7e]);
+}
+
+#[tokio::test]
+async fn silent_connection_is_reopened() {
+    setup();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+
+    let client = Transport::new(TransportConfig::default());
+    client.iface_manager().lock().await.spawn(
+        TcpClient::new(&addr).set_keepalive(TcpKeepalive {
+            ping_interval: None,
+            rx_timeout: Some(Duration::from_millis(500)),
+            ..Default::default()
+        }),
+        TcpClient::spawn,
+    );
+
+    // The peer never sends anything, like one behind a NAT which dropped the
+    // connection
+    let (_silent, _) = listener.accept().await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(3), listener.accept())
+        .await
+        .expect("client did not reconnect")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn unreachable_client_gives_up() {
+    setup();
+
+    let addr = free_local_addr();
+
+    let client = Transport::new(TransportConfig::default());
+    let mut events = client.iface_manager().lock().await.events();
+    let handle = client.iface_manager().lock().await.spawn(
+        TcpClient::new(&addr).set_backoff(BackoffConfig {
+            initial: Duration::from_millis(50),
+            max_attempts: Some(3),
+            ..Default::default()
+        }),
+        TcpClient::spawn,
+    );
+
+    let address = handle.address();
+
+    let mut states = Vec::new();
+    tokio::time::timeout(Duration::from_secs(3), async {
+        loop {
+            match events.recv().await.unwrap() {
+                InterfaceEvent::State(iface, state) if iface == address => states.push(state),
+                InterfaceEvent::Down(iface) if iface == address => break,
+                _ => {}
+            }
+        }
+    })
+    .await
+    .expect("client did not give up");
+
+    assert!(matches!(
+        states[..],
+        [
+            InterfaceState::Reconnecting { attempt: 1, .. },
+            InterfaceState::Reconnecting { attempt: 2, .. },
+            InterfaceState::Failed,
+        ]
+    ));
+    assert_eq!(handle.status(), Some(InterfaceState::Failed));
+    assert!(handle.await_ready().await.is_err());
+}
+
+#[tokio::test]
+async fn shutdown_stops_interface() {
+    setup();
+
+    let server_addr = free_local_addr();
+
+    let transport = Transport::new(TransportConfig::default());
+    let mut events = transport.iface_manager().lock().await.events();
+    let server = transport.iface_manager().lock().await.spawn(
+        TcpServer::new(&server_addr, transport.iface_manager()),
+        TcpServer::spawn,
+    );
+    assert_eq!(server.status(), None);
+
+    server.await_ready().await.expect("server listening");
+    assert_eq!(server.status(), Some(InterfaceState::Connected));
+
+    server.shutdown();
+    assert!(server.is_stopped());
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while events.recv().await.unwrap() != InterfaceEvent::Down(server.address()) {}
+    })
+    .await
+    .expect("interface went down");
+
+    // The socket is closed once the server stopped
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while TcpListener::bind(&server_addr).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("server socket released");
+}
+
+#[tokio::test]
+async fn server_clients_are_named_after_server() {
+    setup();
+
+    let server_addr = free_local_addr();
+
+    let transport = Transport::new(TransportConfig::default());
+    let server = transport.iface_manager().lock().await.spawn_named(
+        "Public Server",
+        TcpServer::new(&server_addr, transport.iface_manager()),
+        InterfaceMode::Full,
+        TcpServer::spawn,
+    );
+    server.await_ready().await.expect("server listening");
+
+    let stream = tokio::net::TcpStream::connect(&server_addr).await.unwrap();
+    let peer = stream.local_addr().unwrap();
+
+    let client_name = format!("{} on Public Server", peer);
+    tokio::time::timeout(Duration::from_secs(1), async {
+        loop {
+            let names: Vec<_> = transport
+                .iface_manager()
+                .lock()
+                .await
+                .interfaces()
+                .into_iter()
+                .filter_map(|iface| iface.name)
+                .collect();
+            if names.contains(&client_name) {
+                assert!(names.iter().any(|name| name == "Public Server"));
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("client interface named");
+}
+
+#[tokio::test]
+async fn server_listens_on_all_bind_addrs() {
+    setup();
+
+    let first_addr = free_local_addr();
+    let second_addr = free_local_addr();
+
+    let transport = Transport::new(TransportConfig::default());
+    let server = transport.iface_manager().lock().await.spawn(
+        TcpServer::new(&first_addr, transport.iface_manager()).add_bind_addr(&second_addr),
+        TcpServer::spawn,
+    );
+    server.await_ready().await.expect("server listening");
+
+    let _first = tokio::net::TcpStream::connect(&first_addr).await.unwrap();
+    let _second = tokio::net::TcpStream::connect(&second_addr).await.unwrap();
+
+    // The server and one interface for each client
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while transport.iface_manager().lock().await.interfaces().len() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("both clients accepted");
+}
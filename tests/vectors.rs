@@ -0,0 +1,149 @@
+//! Table driven tests over the packets in `tests/vectors/`, see
+//! `tests/vectors/generate.py` for how they were created.
+//!
+//! Every packet is decoded, checked and serialized again, which has to give
+//! the original bytes.
+
+use ed25519_dalek::Signature;
+use rand_core::OsRng;
+use serde_json::Value;
+use x25519_dalek::PublicKey;
+
+use reticulum::buffer::{InputBuffer, OutputBuffer};
+use reticulum::destination::link::LinkId;
+use reticulum::destination::{DestinationAnnounce, DestinationName};
+use reticulum::hash::AddressHash;
+use reticulum::identity::{DecryptIdentity, Identity, PrivateIdentity, PUBLIC_KEY_LENGTH};
+use reticulum::packet::{Packet, PacketContext, PacketType};
+use reticulum::serde::Serialize;
+
+fn vectors(json: &str) -> Vec<Value> {
+    serde_json::from_str::<Value>(json).unwrap().as_array().unwrap().clone()
+}
+
+fn field<'a>(vector: &'a Value, key: &str) -> &'a str {
+    vector[key].as_str().unwrap_or_else(|| panic!("{}: missing {}", vector["name"], key))
+}
+
+fn bytes(vector: &Value, key: &str) -> Vec<u8> {
+    let hex = field(vector, key);
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// Decodes the packet in `key` and checks that it serializes to the same bytes.
+fn decode(vector: &Value, key: &str) -> Packet {
+    let raw = bytes(vector, key);
+    let packet = Packet::deserialize(&mut InputBuffer::new(&raw)).expect("valid packet");
+
+    let mut buffer = [0u8; 4096];
+    let mut output = OutputBuffer::new(&mut buffer);
+    packet.serialize(&mut output).expect("serialized packet");
+    assert_eq!(output.as_slice(), &raw[..], "{}: re-encoded packet differs", vector["name"]);
+
+    packet
+}
+
+fn identity(vector: &Value) -> Identity {
+    let keys = bytes(vector, "identity_public_key");
+    Identity::new_from_slices(&keys[..PUBLIC_KEY_LENGTH], &keys[PUBLIC_KEY_LENGTH..])
+}
+
+#[test]
+fn announces() {
+    for vector in vectors(include_str!("vectors/announces.json")) {
+        let packet = decode(&vector, "raw");
+        assert_eq!(packet.header.packet_type, PacketType::Announce);
+
+        let (destination, app_data) =
+            DestinationAnnounce::validate(&packet).expect("valid announce signature");
+
+        assert_eq!(destination.desc.address_hash.to_hex_string(), field(&vector, "destination_hash"));
+        assert_eq!(destination.identity.address_hash.to_hex_string(), field(&vector, "identity_hash"));
+        assert_eq!(app_data, &bytes(&vector, "app_data")[..]);
+
+        let hash = DestinationName::hash_from_name_and_identity(field(&vector, "full_name"), Some(&destination.identity));
+        assert_eq!(hash.to_hex_string(), field(&vector, "destination_hash"));
+    }
+}
+
+#[test]
+fn link_requests_and_proofs() {
+    for vector in vectors(include_str!("vectors/links.json")) {
+        let request = decode(&vector, "raw");
+        assert_eq!(request.header.packet_type, PacketType::LinkRequest);
+        assert_eq!(request.destination.to_hex_string(), field(&vector, "destination_hash"));
+        assert_eq!(LinkId::from(&request).to_hex_string(), field(&vector, "link_id"));
+
+        let proof = decode(&vector, "proof_raw");
+        assert_eq!(proof.header.packet_type, PacketType::Proof);
+        assert_eq!(proof.context, PacketContext::LinkRequestProof);
+        assert_eq!(proof.destination.to_hex_string(), field(&vector, "link_id"));
+
+        let signature = Signature::from_slice(&proof.data.as_slice()[..64]).unwrap();
+        identity(&vector)
+            .verify(&bytes(&vector, "proof_signed_data"), &signature)
+            .expect("valid link proof signature");
+    }
+}
+
+#[test]
+fn data_packets() {
+    for vector in vectors(include_str!("vectors/data.json")) {
+        let packet = decode(&vector, "raw");
+        assert_eq!(packet.header.packet_type, PacketType::Data);
+        assert_eq!(packet.packet_hash().to_string(), field(&vector, "packet_hash"));
+
+        let plaintext = bytes(&vector, "plaintext");
+
+        let Some(private_key) = vector["identity_private_key"].as_str() else {
+            assert_eq!(packet.data.as_slice(), &plaintext[..]);
+            continue;
+        };
+
+        let identity = PrivateIdentity::new_from_hex_string(private_key).unwrap();
+        let data = packet.data.as_slice();
+
+        let mut ephemeral_key = [0u8; PUBLIC_KEY_LENGTH];
+        ephemeral_key.copy_from_slice(&data[..PUBLIC_KEY_LENGTH]);
+        let derived_key = identity.derive_key(
+            &PublicKey::from(ephemeral_key),
+            Some(identity.address_hash().as_slice()),
+        );
+
+        let mut buffer = [0u8; 1024];
+        let decrypted = identity
+            .decrypt(OsRng, &data[PUBLIC_KEY_LENGTH..], &derived_key, &mut buffer)
+            .expect("decrypted data");
+
+        assert_eq!(decrypted, &plaintext[..]);
+    }
+}
+
+#[test]
+fn proofs() {
+    for vector in vectors(include_str!("vectors/proofs.json")) {
+        let proof = decode(&vector, "raw");
+        assert_eq!(proof.header.packet_type, PacketType::Proof);
+
+        let packet_hash = bytes(&vector, "packet_hash");
+        assert_eq!(proof.destination, AddressHash::new(packet_hash[..16].try_into().unwrap()));
+
+        // Explicit proofs start with the proved hash, implicit ones are just the signature
+        let data = proof.data.as_slice();
+        let signature = match data.len() {
+            96 => {
+                assert_eq!(&data[..32], &packet_hash[..]);
+                &data[32..]
+            }
+            64 => data,
+            len => panic!("{}: unexpected proof length {}", vector["name"], len),
+        };
+
+        identity(&vector)
+            .verify(&packet_hash, &Signature::from_slice(signature).unwrap())
+            .expect("valid proof signature");
+    }
+}
@@ -0,0 +1,229 @@
+#!/usr/bin/env python3
+"""Generates the packet test vectors in this directory.
+
+The packets are encoded by this script, independently of the Rust code,
+following the packing in the RNS sources (RNS/Packet.py, RNS/Identity.py,
+RNS/Destination.py and RNS/Link.py) with the `cryptography` primitives.
+RNS itself doesn't produce them, so they catch changes to the Rust encoding
+but not a misreading of the wire format this script shares with it. All
+keys and random values are derived from fixed labels, so running the
+script again gives the same files.
+
+    python3 tests/vectors/generate.py
+"""
+
+import hashlib
+import json
+import os
+
+from cryptography.hazmat.primitives import hashes, hmac, padding
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.asymmetric.x25519 import X25519PrivateKey, X25519PublicKey
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+from cryptography.hazmat.primitives.kdf.hkdf import HKDF
+
+OUT_DIR = os.path.dirname(os.path.abspath(__file__))
+
+HEADER_1, HEADER_2 = 0, 1
+BROADCAST, TRANSPORT = 0, 1
+SINGLE, GROUP, PLAIN, LINK = 0, 1, 2, 3
+DATA, ANNOUNCE, LINKREQUEST, PROOF = 0, 1, 2, 3
+
+CTX_NONE = 0x00
+CTX_CHANNEL = 0x0E
+CTX_LRPROOF = 0xFF
+
+
+def sha256(data):
+    return hashlib.sha256(data).digest()
+
+
+def fixed(label, length=32):
+    return sha256(label.encode())[:length]
+
+
+class Identity:
+    def __init__(self, label):
+        self.private_key = fixed(label + ".x25519")
+        self.sign_seed = fixed(label + ".ed25519")
+        self.prv = X25519PrivateKey.from_private_bytes(self.private_key)
+        self.sig_prv = Ed25519PrivateKey.from_private_bytes(self.sign_seed)
+        self.pub_bytes = self.prv.public_key().public_bytes_raw()
+        self.sig_pub_bytes = self.sig_prv.public_key().public_bytes_raw()
+        self.hash = sha256(self.pub_bytes + self.sig_pub_bytes)[:16]
+
+    def private_key_hex(self):
+        return (self.private_key + self.sign_seed).hex()
+
+    def sign(self, data):
+        return self.sig_prv.sign(data)
+
+    def encrypt(self, plaintext, ephemeral_label, iv):
+        ephemeral = X25519PrivateKey.from_private_bytes(fixed(ephemeral_label))
+        shared = ephemeral.exchange(X25519PublicKey.from_public_bytes(self.pub_bytes))
+        key = HKDF(algorithm=hashes.SHA256(), length=64, salt=self.hash, info=b"").derive(shared)
+        signing_key, encryption_key = key[:32], key[32:]
+
+        padder = padding.PKCS7(128).padder()
+        padded = padder.update(plaintext) + padder.finalize()
+        encryptor = Cipher(algorithms.AES(encryption_key), modes.CBC(iv)).encryptor()
+        signed_parts = iv + encryptor.update(padded) + encryptor.finalize()
+
+        mac = hmac.HMAC(signing_key, hashes.SHA256())
+        mac.update(signed_parts)
+
+        return ephemeral.public_key().public_bytes_raw() + signed_parts + mac.finalize()
+
+
+def name_hash(full_name):
+    return sha256(full_name.encode())[:10]
+
+
+def destination_hash(full_name, identity):
+    return sha256(name_hash(full_name) + identity.hash)[:16]
+
+
+def pack(packet_type, destination_type, destination, context, data, hops=0, transport_id=None):
+    header_type = HEADER_2 if transport_id else HEADER_1
+    transport_type = TRANSPORT if transport_id else BROADCAST
+    flags = (header_type << 6) | (transport_type << 4) | (destination_type << 2) | packet_type
+
+    raw = bytes([flags, hops])
+    if transport_id:
+        raw += transport_id
+    return raw + destination + bytes([context]) + data
+
+
+def hashable_part(raw):
+    if raw[0] & 0b01000000:
+        return bytes([raw[0] & 0x0F]) + raw[18:]
+    return bytes([raw[0] & 0x0F]) + raw[2:]
+
+
+def packet_hash(raw):
+    return sha256(hashable_part(raw))
+
+
+def announce(identity, full_name, random_hash, app_data=b"", hops=0, transport_id=None):
+    destination = destination_hash(full_name, identity)
+    keys = identity.pub_bytes + identity.sig_pub_bytes
+    signed_data = destination + keys + name_hash(full_name) + random_hash + app_data
+    signature = identity.sign(signed_data)
+    data = keys + name_hash(full_name) + random_hash + signature + app_data
+
+    return {
+        "raw": pack(ANNOUNCE, SINGLE, destination, CTX_NONE, data, hops, transport_id).hex(),
+        "destination_hash": destination.hex(),
+        "identity_hash": identity.hash.hex(),
+        "full_name": full_name,
+        "app_data": app_data.hex(),
+    }
+
+
+def signalling_bytes(mtu, mode=0x01):
+    return ((mtu & 0x1FFFFF) + (((mode << 5) & 0xE0) << 16)).to_bytes(3, "big")
+
+
+def link_request(destination, ephemeral, signalling=b""):
+    data = ephemeral.pub_bytes + ephemeral.sig_pub_bytes + signalling
+    raw = pack(LINKREQUEST, SINGLE, destination, CTX_NONE, data)
+
+    hashable = hashable_part(raw)
+    if len(signalling) > 0:
+        hashable = hashable[: -len(signalling)]
+    link_id = sha256(hashable)[:16]
+
+    return raw, link_id
+
+
+def generate():
+    alice = Identity("alice")
+    bob = Identity("bob")
+
+    announces = [
+        dict(name="announce", **announce(alice, "example_utilities.announcesample.fruits", fixed("rand1", 10))),
+        dict(
+            name="announce_app_data",
+            **announce(alice, "lxmf.delivery", fixed("rand2", 10), bytes.fromhex("92c405416c69636508")),
+        ),
+        dict(
+            name="announce_transport",
+            **announce(bob, "nomadnetwork.node", fixed("rand3", 10), b"Node", hops=3, transport_id=fixed("transport", 16)),
+        ),
+    ]
+
+    bob_destination = destination_hash("example_utilities.linkexample", bob)
+    ephemeral = Identity("link.ephemeral")
+
+    links = []
+    for name, signalling in [("link_request", b""), ("link_request_mtu", signalling_bytes(500))]:
+        raw, link_id = link_request(bob_destination, ephemeral, signalling)
+
+        # The destination proves the link with its identity key
+        receiver_ephemeral = Identity(name + ".receiver")
+        signed_data = link_id + receiver_ephemeral.pub_bytes + bob.sig_pub_bytes + signalling
+        proof_data = bob.sign(signed_data) + receiver_ephemeral.pub_bytes + signalling
+
+        links.append(
+            {
+                "name": name,
+                "raw": raw.hex(),
+                "destination_hash": bob_destination.hex(),
+                "link_id": link_id.hex(),
+                "proof_raw": pack(PROOF, LINK, link_id, CTX_LRPROOF, proof_data).hex(),
+                "proof_signed_data": signed_data.hex(),
+                "identity_public_key": (bob.pub_bytes + bob.sig_pub_bytes).hex(),
+            }
+        )
+
+    plaintext = b"Hello from Python"
+    encrypted = bob.encrypt(plaintext, "data.ephemeral", fixed("data.iv", 16))
+    data_raw = pack(DATA, SINGLE, bob_destination, CTX_NONE, encrypted)
+    data = [
+        {
+            "name": "encrypted_single",
+            "raw": data_raw.hex(),
+            "identity_private_key": bob.private_key_hex(),
+            "plaintext": plaintext.hex(),
+            "packet_hash": packet_hash(data_raw).hex(),
+        },
+        {
+            "name": "plain_transport",
+            "raw": (
+                raw := pack(DATA, PLAIN, fixed("plain", 16), CTX_CHANNEL, b"\x00\x01plain", 2, fixed("transport", 16))
+            ).hex(),
+            "identity_private_key": None,
+            "plaintext": b"\x00\x01plain".hex(),
+            "packet_hash": packet_hash(raw).hex(),
+        },
+    ]
+
+    proved_hash = packet_hash(data_raw)
+    proofs = [
+        {
+            "name": "explicit_proof",
+            "raw": pack(PROOF, SINGLE, proved_hash[:16], CTX_NONE, proved_hash + bob.sign(proved_hash)).hex(),
+            "packet_hash": proved_hash.hex(),
+            "identity_public_key": (bob.pub_bytes + bob.sig_pub_bytes).hex(),
+        },
+        {
+            "name": "implicit_proof",
+            "raw": pack(PROOF, SINGLE, proved_hash[:16], CTX_NONE, bob.sign(proved_hash)).hex(),
+            "packet_hash": proved_hash.hex(),
+            "identity_public_key": (bob.pub_bytes + bob.sig_pub_bytes).hex(),
+        },
+    ]
+
+    for file_name, vectors in [
+        ("announces.json", announces),
+        ("links.json", links),
+        ("data.json", data),
+        ("proofs.json", proofs),
+    ]:
+        with open(os.path.join(OUT_DIR, file_name), "w") as file:
+            json.dump(vectors, file, indent=2)
+            file.write("\n")
+
+
+if __name__ == "__main__":
+    generate()
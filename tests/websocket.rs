@@ -0,0 +1,92 @@
+//! A browser client talking to a transport through the WebSocket server,
+//! played by a tungstenite client sending one packet per binary message.
+
+#![cfg(feature = "websocket")]
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use rand_core::OsRng;
+use tokio::time;
+use tokio_tungstenite::tungstenite::Message;
+
+use reticulum::buffer::{InputBuffer, OutputBuffer};
+use reticulum::destination::{DestinationName, SingleInputDestination};
+use reticulum::identity::PrivateIdentity;
+use reticulum::iface::websocket::WebSocketServer;
+use reticulum::packet::{Packet, PacketType};
+use reticulum::serde::Serialize;
+use reticulum::transport::{Transport, TransportConfig};
+
+fn free_local_addr() -> String {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn exchange_packets_with_browser_client() {
+    let addr = free_local_addr();
+    let transport = Transport::new(TransportConfig::new(
+        "hub",
+        &PrivateIdentity::new_from_rand(OsRng),
+        true,
+    ));
+    transport.iface_manager().lock().await.spawn(
+        WebSocketServer::new(addr.clone(), transport.iface_manager()),
+        WebSocketServer::spawn,
+    );
+    time::sleep(Duration::from_millis(100)).await;
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+        .await
+        .expect("connected to websocket server");
+
+    // Announces from the browser reach the transport
+    let mut announces = transport.recv_announces().await;
+    let web_destination = SingleInputDestination::new(
+        PrivateIdentity::new_from_name("web"),
+        DestinationName::new("example", "web"),
+    );
+    let announce = web_destination.announce(OsRng, None).unwrap();
+
+    let mut buffer = [0u8; 1024];
+    let mut output = OutputBuffer::new(&mut buffer);
+    announce.serialize(&mut output).unwrap();
+    socket.send(Message::binary(output.as_slice().to_vec())).await.unwrap();
+
+    let received = time::timeout(Duration::from_secs(2), announces.recv())
+        .await
+        .expect("announce received")
+        .unwrap();
+    assert_eq!(
+        received.destination.lock().await.desc.address_hash,
+        web_destination.desc.address_hash
+    );
+
+    // Announces of the hub reach the browser
+    let destination = transport
+        .add_destination(
+            PrivateIdentity::new_from_name("hub-app"),
+            DestinationName::new("example", "hub"),
+        )
+        .await;
+    let address = destination.lock().await.desc.address_hash;
+    transport.send_announce(&destination, None).await.unwrap();
+
+    let packet = loop {
+        let message = time::timeout(Duration::from_secs(2), socket.next())
+            .await
+            .expect("packet from hub")
+            .unwrap()
+            .unwrap();
+
+        let packet = Packet::deserialize(&mut InputBuffer::new(&message.into_data())).unwrap();
+        if packet.destination == address {
+            break packet;
+        }
+    };
+    assert_eq!(packet.header.packet_type, PacketType::Announce);
+}